@@ -0,0 +1,92 @@
+//! 内容寻址对象存储
+//!
+//! 文件内容和快照清单按 SHA-256 保存为 `objects/<前两位>/<其余>`，相同内容只保存一份。
+
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use sha2::{Digest, Sha256};
+
+use super::types::CheckpointError;
+
+/// 对象存储
+#[derive(Debug, Clone)]
+pub struct BlobStore {
+    root: PathBuf,
+}
+
+impl BlobStore {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// 计算内容哈希
+    pub fn hash(data: &[u8]) -> String {
+        let digest = Sha256::digest(data);
+        digest.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    /// 写入对象，返回哈希（已存在时不重复写入）
+    pub fn put(&self, data: &[u8]) -> Result<String, CheckpointError> {
+        let hash = Self::hash(data);
+        let path = self.object_path(&hash)?;
+        if path.exists() {
+            return Ok(hash);
+        }
+        let dir = path.parent().expect("对象路径总有父目录");
+        fs::create_dir_all(dir)?;
+        // 先写临时文件再重命名，避免中断时留下不完整的对象
+        let tmp = dir.join(format!(".{}.{}", &hash[2..], uuid::Uuid::new_v4()));
+        fs::write(&tmp, data)?;
+        fs::rename(&tmp, &path)?;
+        Ok(hash)
+    }
+
+    /// 读取对象
+    pub fn get(&self, hash: &str) -> Result<Vec<u8>, CheckpointError> {
+        match fs::read(self.object_path(hash)?) {
+            Ok(data) => Ok(data),
+            Err(e) if e.kind() == ErrorKind::NotFound => {
+                Err(CheckpointError::Corrupted(format!("对象不存在: {}", hash)))
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn object_path(&self, hash: &str) -> Result<PathBuf, CheckpointError> {
+        if hash.len() != 64 || !hash.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(CheckpointError::Corrupted(format!(
+                "无效的对象哈希: {}",
+                hash
+            )));
+        }
+        Ok(self.root.join(&hash[..2]).join(&hash[2..]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_put_and_get() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = BlobStore::new(dir.path());
+
+        let hash = store.put(b"hello").unwrap();
+        assert_eq!(hash, BlobStore::hash(b"hello"));
+        assert_eq!(store.put(b"hello").unwrap(), hash);
+        assert_eq!(store.get(&hash).unwrap(), b"hello");
+
+        assert!(matches!(
+            store.get(&BlobStore::hash(b"missing")),
+            Err(CheckpointError::Corrupted(_))
+        ));
+        assert!(store.get("../etc/passwd").is_err());
+    }
+}
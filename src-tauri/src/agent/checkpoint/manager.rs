@@ -0,0 +1,675 @@
+//! 检查点管理器
+//!
+//! 每次工具循环迭代执行前为涉及的文件创建快照；文件在某个检查点时的状态
+//! 取该检查点及之后第一个包含它的快照（之前没有被 Agent 修改过，所以内容相同）。
+//! 工具执行完成后通过 `record_created` 记录新建的文件，恢复时只删除这些文件。
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use parking_lot::Mutex;
+use similar::TextDiff;
+
+use super::blob_store::BlobStore;
+use super::snapshot::{scan_scope, targets_for_tool_calls, MAX_SNAPSHOT_FILE_BYTES};
+use super::types::{Checkpoint, CheckpointError, FileChange, FileChangeKind, Manifest};
+use crate::agent::types::ToolCall;
+use crate::database::dao::agent_checkpoints::AgentCheckpointDao;
+use crate::database::DbConnection;
+
+/// diff 上下文行数
+const DIFF_CONTEXT_LINES: usize = 3;
+
+/// 修改时间距今小于该值的文件不缓存（文件系统时间戳精度有限，同一时刻内的再次修改无法区分）
+const STAT_CACHE_MIN_AGE: Duration = Duration::from_secs(2);
+
+/// 文件元数据缓存项：大小、修改时间、内容哈希
+type StatEntry = (u64, SystemTime, String);
+
+/// 检查点管理器
+pub struct CheckpointManager {
+    db: DbConnection,
+    blobs: BlobStore,
+    /// 只快照此目录内的文件
+    base_dir: PathBuf,
+    /// 避免重复读取未变化的文件
+    stat_cache: Mutex<HashMap<PathBuf, StatEntry>>,
+}
+
+impl CheckpointManager {
+    /// `objects_dir` 为对象存储目录，`base_dir` 与工具注册表的基础目录一致
+    pub fn new(
+        db: DbConnection,
+        objects_dir: impl Into<PathBuf>,
+        base_dir: impl Into<PathBuf>,
+    ) -> Self {
+        Self {
+            db,
+            blobs: BlobStore::new(objects_dir),
+            base_dir: base_dir.into(),
+            stat_cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn with_conn<T>(
+        &self,
+        f: impl FnOnce(&rusqlite::Connection) -> Result<T, rusqlite::Error>,
+    ) -> Result<T, CheckpointError> {
+        let conn = self
+            .db
+            .lock()
+            .map_err(|e| CheckpointError::Database(format!("数据库锁错误: {}", e)))?;
+        Ok(f(&conn)?)
+    }
+
+    /// 为即将执行的工具调用创建检查点
+    ///
+    /// `message_index` 为当前会话消息数。没有可能修改文件的工具调用时返回 None。
+    pub fn create(
+        &self,
+        session_id: &str,
+        message_index: usize,
+        calls: &[ToolCall],
+    ) -> Result<Option<Checkpoint>, CheckpointError> {
+        let targets = targets_for_tool_calls(calls, &self.base_dir);
+        if targets.is_empty() {
+            return Ok(None);
+        }
+
+        let mut manifest = Manifest::default();
+        let mut untracked = targets.untracked;
+        for scope in &targets.scopes {
+            let Some(files) = scan_scope(scope) else {
+                untracked = true;
+                continue;
+            };
+            for file in files {
+                untracked |= !self.snapshot_into(&file, &mut manifest)?;
+            }
+            manifest.scopes.push(path_key(scope));
+        }
+        for file in &targets.files {
+            untracked |= !self.snapshot_into(file, &mut manifest)?;
+        }
+
+        let data =
+            serde_json::to_vec(&manifest).map_err(|e| CheckpointError::Corrupted(e.to_string()))?;
+        let checkpoint = Checkpoint {
+            id: uuid::Uuid::new_v4().to_string(),
+            session_id: session_id.to_string(),
+            message_index,
+            label: targets.labels.join("; "),
+            file_count: manifest.files.len(),
+            scopes: manifest.scopes.clone(),
+            untracked,
+            manifest: self.blobs.put(&data)?,
+            created_at: chrono::Utc::now().to_rfc3339(),
+        };
+        self.with_conn(|conn| AgentCheckpointDao::insert(conn, &checkpoint))?;
+        tracing::info!(
+            "[Checkpoint] 创建检查点: session={}, message_index={}, files={}, untracked={}",
+            session_id,
+            message_index,
+            checkpoint.file_count,
+            untracked
+        );
+        Ok(Some(checkpoint))
+    }
+
+    /// 工具执行完成后记录新建的文件
+    ///
+    /// 只有这里记录的文件会在恢复时删除，快照之外的文件（包括用户在会话期间新建的文件）
+    /// 保持不变。
+    pub fn record_created(&self, checkpoint: &Checkpoint) -> Result<usize, CheckpointError> {
+        let mut manifest = self.load_manifest(checkpoint)?;
+        let mut created: BTreeSet<String> = manifest
+            .files
+            .iter()
+            .filter(|(path, hash)| hash.is_none() && Path::new(path.as_str()).exists())
+            .map(|(path, _)| path.clone())
+            .collect();
+        for scope in &manifest.scopes {
+            for file in scan_scope(Path::new(scope)).unwrap_or_default() {
+                let key = path_key(&file);
+                if !manifest.files.contains_key(&key) && !manifest.oversized.contains(&key) {
+                    created.insert(key);
+                }
+            }
+        }
+        created.retain(|path| !manifest.created.contains(path));
+        if created.is_empty() {
+            return Ok(0);
+        }
+
+        let count = created.len();
+        manifest.created.extend(created);
+        let data =
+            serde_json::to_vec(&manifest).map_err(|e| CheckpointError::Corrupted(e.to_string()))?;
+        let hash = self.blobs.put(&data)?;
+        self.with_conn(|conn| AgentCheckpointDao::update_manifest(conn, &checkpoint.id, &hash))?;
+        Ok(count)
+    }
+
+    /// 列出会话的检查点
+    pub fn list(&self, session_id: &str) -> Result<Vec<Checkpoint>, CheckpointError> {
+        self.with_conn(|conn| AgentCheckpointDao::list(conn, session_id))
+    }
+
+    /// 检查点以来 Agent 涉及文件的全部变更
+    pub fn diff(
+        &self,
+        session_id: &str,
+        checkpoint_id: &str,
+    ) -> Result<Vec<FileChange>, CheckpointError> {
+        let baseline = self.baseline(session_id, checkpoint_id)?;
+        let mut changes = Vec::new();
+        for (path, hash) in &baseline {
+            if let Some(change) = self.file_change(Path::new(path), hash.as_deref())? {
+                changes.push(change);
+            }
+        }
+        Ok(changes)
+    }
+
+    /// 将文件恢复到检查点时的状态，`paths` 为空时恢复全部文件，返回实际改动的文件
+    pub fn restore(
+        &self,
+        session_id: &str,
+        checkpoint_id: &str,
+        paths: Option<&[String]>,
+    ) -> Result<Vec<String>, CheckpointError> {
+        let baseline = self.baseline(session_id, checkpoint_id)?;
+        let selected: Vec<(&String, &Option<String>)> = match paths {
+            None => baseline.iter().collect(),
+            Some(paths) => paths
+                .iter()
+                .map(|p| {
+                    baseline.get_key_value(p).ok_or_else(|| {
+                        CheckpointError::Invalid(format!("文件不在检查点范围内: {}", p))
+                    })
+                })
+                .collect::<Result<_, _>>()?,
+        };
+
+        let mut restored = Vec::new();
+        for (path, hash) in selected {
+            if self.restore_file(Path::new(path), hash.as_deref())? {
+                restored.push(path.clone());
+            }
+        }
+        tracing::info!(
+            "[Checkpoint] 恢复检查点 {}: {} 个文件",
+            checkpoint_id,
+            restored.len()
+        );
+        Ok(restored)
+    }
+
+    /// 将文件恢复到第 `message_index` 条消息时的状态，并删除之后的检查点
+    ///
+    /// 返回恢复的文件和删除的检查点数。
+    pub fn rewind(
+        &self,
+        session_id: &str,
+        message_index: usize,
+    ) -> Result<(Vec<String>, usize), CheckpointError> {
+        let first_after = self
+            .list(session_id)?
+            .into_iter()
+            .find(|c| c.message_index > message_index);
+        let restored = match first_after {
+            Some(checkpoint) => self.restore(session_id, &checkpoint.id, None)?,
+            None => Vec::new(),
+        };
+        let removed = self
+            .with_conn(|conn| AgentCheckpointDao::delete_after(conn, session_id, message_index))?;
+        Ok((restored, removed))
+    }
+
+    /// 删除会话的全部检查点（对象存储中的内容可能被其他检查点共享，不删除）
+    pub fn delete_session(&self, session_id: &str) -> Result<usize, CheckpointError> {
+        self.with_conn(|conn| AgentCheckpointDao::delete_session(conn, session_id))
+    }
+
+    /// 计算检查点时各文件的状态（路径 -> 内容哈希，None 表示当时不存在）
+    ///
+    /// 超出大小限制的文件无法恢复，不包含在结果中；当时不存在的文件只有由工具新建时
+    /// 才包含在结果中（恢复时删除）。
+    fn baseline(
+        &self,
+        session_id: &str,
+        checkpoint_id: &str,
+    ) -> Result<BTreeMap<String, Option<String>>, CheckpointError> {
+        let checkpoints = self.list(session_id)?;
+        let start = checkpoints
+            .iter()
+            .position(|c| c.id == checkpoint_id)
+            .ok_or_else(|| CheckpointError::NotFound(checkpoint_id.to_string()))?;
+
+        let mut baseline: BTreeMap<String, Option<String>> = BTreeMap::new();
+        let mut oversized: BTreeSet<String> = BTreeSet::new();
+        for checkpoint in &checkpoints[start..] {
+            let manifest = self.load_manifest(checkpoint)?;
+            for (path, hash) in &manifest.files {
+                if baseline.contains_key(path) || oversized.contains(path) {
+                    continue;
+                }
+                if hash.is_some() || manifest.created.contains(path) {
+                    baseline.insert(path.clone(), hash.clone());
+                }
+            }
+            // 整体扫描范围内由本次工具执行新建的文件
+            for path in &manifest.created {
+                if !baseline.contains_key(path) && !oversized.contains(path) {
+                    baseline.insert(path.clone(), None);
+                }
+            }
+            for path in manifest.oversized {
+                if !baseline.contains_key(&path) {
+                    oversized.insert(path);
+                }
+            }
+        }
+        Ok(baseline)
+    }
+
+    fn load_manifest(&self, checkpoint: &Checkpoint) -> Result<Manifest, CheckpointError> {
+        let data = self.blobs.get(&checkpoint.manifest)?;
+        serde_json::from_slice(&data)
+            .map_err(|e| CheckpointError::Corrupted(format!("快照清单解析失败: {}", e)))
+    }
+
+    /// 将文件快照写入清单，文件过大无法快照时返回 false
+    fn snapshot_into(&self, path: &Path, manifest: &mut Manifest) -> Result<bool, CheckpointError> {
+        let key = path_key(path);
+        if manifest.files.contains_key(&key) {
+            return Ok(true);
+        }
+        if manifest.oversized.contains(&key) {
+            return Ok(false);
+        }
+        let metadata = match fs::metadata(path) {
+            Ok(metadata) if metadata.is_file() => metadata,
+            Ok(_) => return Ok(true),
+            Err(e) if e.kind() == ErrorKind::NotFound => {
+                manifest.files.insert(key, None);
+                return Ok(true);
+            }
+            Err(e) => return Err(e.into()),
+        };
+        if metadata.len() > MAX_SNAPSHOT_FILE_BYTES {
+            tracing::warn!("[Checkpoint] 文件过大，跳过快照: {:?}", path);
+            manifest.oversized.insert(key);
+            return Ok(false);
+        }
+
+        let modified = metadata.modified()?;
+        let cached = self
+            .stat_cache
+            .lock()
+            .get(path)
+            .filter(|(len, mtime, _)| *len == metadata.len() && *mtime == modified)
+            .map(|(_, _, hash)| hash.clone());
+        let hash = match cached {
+            Some(hash) => hash,
+            None => {
+                let hash = self.blobs.put(&fs::read(path)?)?;
+                let settled = modified
+                    .elapsed()
+                    .is_ok_and(|age| age >= STAT_CACHE_MIN_AGE);
+                if settled {
+                    self.stat_cache
+                        .lock()
+                        .insert(path.to_path_buf(), (metadata.len(), modified, hash.clone()));
+                }
+                hash
+            }
+        };
+        manifest.files.insert(key, Some(hash));
+        Ok(true)
+    }
+
+    fn file_change(
+        &self,
+        path: &Path,
+        baseline: Option<&str>,
+    ) -> Result<Option<FileChange>, CheckpointError> {
+        let current = read_optional(path)?;
+        let kind = match (baseline, &current) {
+            (None, None) => return Ok(None),
+            (Some(hash), Some(data)) if BlobStore::hash(data) == hash => return Ok(None),
+            (None, Some(_)) => FileChangeKind::Added,
+            (Some(_), None) => FileChangeKind::Deleted,
+            (Some(_), Some(_)) => FileChangeKind::Modified,
+        };
+
+        let old = baseline.map(|h| self.blobs.get(h)).transpose()?;
+        let old_text = old.as_deref().map(text_content);
+        let new_text = current.as_deref().map(text_content);
+        let (binary, diff) = match (old_text, new_text) {
+            (Some(None), _) | (_, Some(None)) => (true, String::new()),
+            (old, new) => {
+                let old = old.flatten().unwrap_or_default();
+                let new = new.flatten().unwrap_or_default();
+                let name = path.to_string_lossy();
+                let diff = TextDiff::from_lines(old, new)
+                    .unified_diff()
+                    .context_radius(DIFF_CONTEXT_LINES)
+                    .header(&name, &name)
+                    .to_string();
+                (false, diff)
+            }
+        };
+
+        Ok(Some(FileChange {
+            path: path_key(path),
+            kind,
+            diff,
+            binary,
+        }))
+    }
+
+    /// 恢复单个文件，内容已一致时返回 false
+    fn restore_file(&self, path: &Path, baseline: Option<&str>) -> Result<bool, CheckpointError> {
+        let current = read_optional(path)?;
+        match (baseline, current) {
+            (None, None) => Ok(false),
+            (Some(hash), Some(data)) if BlobStore::hash(&data) == hash => Ok(false),
+            (None, Some(_)) => {
+                fs::remove_file(path)?;
+                Ok(true)
+            }
+            (Some(hash), _) => {
+                let data = self.blobs.get(hash)?;
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                fs::write(path, data)?;
+                Ok(true)
+            }
+        }
+    }
+}
+
+fn path_key(path: &Path) -> String {
+    path.to_string_lossy().into_owned()
+}
+
+fn read_optional(path: &Path) -> Result<Option<Vec<u8>>, CheckpointError> {
+    match fs::read(path) {
+        Ok(data) => Ok(Some(data)),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// 文本内容，二进制文件返回 None
+fn text_content(data: &[u8]) -> Option<&str> {
+    if data.contains(&0) {
+        return None;
+    }
+    std::str::from_utf8(data).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::types::FunctionCall;
+    use crate::database::schema::create_agent_session_tables;
+    use std::sync::{Arc, Mutex as StdMutex};
+
+    struct Fixture {
+        _dir: tempfile::TempDir,
+        base: PathBuf,
+        manager: CheckpointManager,
+    }
+
+    fn fixture() -> Fixture {
+        let dir = tempfile::tempdir().unwrap();
+        let base = dir.path().join("work");
+        fs::create_dir_all(&base).unwrap();
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        create_agent_session_tables(&conn).unwrap();
+        conn.execute(
+            "INSERT INTO agent_sessions (id, model, created_at, updated_at)
+             VALUES ('s1', 'gpt-4o', '2025-01-01T00:00:00+00:00', '2025-01-01T00:00:00+00:00')",
+            [],
+        )
+        .unwrap();
+        let manager = CheckpointManager::new(
+            Arc::new(StdMutex::new(conn)),
+            dir.path().join("objects"),
+            &base,
+        );
+        Fixture {
+            _dir: dir,
+            base,
+            manager,
+        }
+    }
+
+    fn call(name: &str, arguments: serde_json::Value) -> ToolCall {
+        ToolCall {
+            id: "call_1".to_string(),
+            call_type: "function".to_string(),
+            function: FunctionCall {
+                name: name.to_string(),
+                arguments: arguments.to_string(),
+            },
+        }
+    }
+
+    #[test]
+    fn test_diff_and_selective_restore() {
+        let f = fixture();
+        fs::write(f.base.join("a.txt"), "one\ntwo\n").unwrap();
+        fs::write(f.base.join("b.txt"), "keep\n").unwrap();
+
+        let checkpoint = f
+            .manager
+            .create(
+                "s1",
+                2,
+                &[
+                    call("write_file", serde_json::json!({"path": "a.txt"})),
+                    call("edit_file", serde_json::json!({"path": "b.txt"})),
+                    call("write_file", serde_json::json!({"path": "new.txt"})),
+                ],
+            )
+            .unwrap()
+            .unwrap();
+        assert_eq!(checkpoint.file_count, 3);
+        assert!(!checkpoint.untracked);
+
+        // 模拟工具执行
+        fs::write(f.base.join("a.txt"), "one\nTWO\n").unwrap();
+        fs::write(f.base.join("b.txt"), "changed\n").unwrap();
+        fs::write(f.base.join("new.txt"), "created\n").unwrap();
+        assert_eq!(f.manager.record_created(&checkpoint).unwrap(), 1);
+
+        let changes = f.manager.diff("s1", &checkpoint.id).unwrap();
+        assert_eq!(changes.len(), 3);
+        assert_eq!(changes[0].kind, FileChangeKind::Modified);
+        assert!(changes[0].diff.contains("-two"));
+        assert!(changes[0].diff.contains("+TWO"));
+        assert_eq!(changes[2].kind, FileChangeKind::Added);
+
+        // 只恢复 a.txt
+        let a = path_key(&f.base.join("a.txt"));
+        let restored = f
+            .manager
+            .restore("s1", &checkpoint.id, Some(std::slice::from_ref(&a)))
+            .unwrap();
+        assert_eq!(restored, vec![a]);
+        assert_eq!(
+            fs::read_to_string(f.base.join("a.txt")).unwrap(),
+            "one\ntwo\n"
+        );
+        assert_eq!(
+            fs::read_to_string(f.base.join("b.txt")).unwrap(),
+            "changed\n"
+        );
+
+        // 恢复全部：新建的文件被删除
+        f.manager.restore("s1", &checkpoint.id, None).unwrap();
+        assert_eq!(fs::read_to_string(f.base.join("b.txt")).unwrap(), "keep\n");
+        assert!(!f.base.join("new.txt").exists());
+        assert!(f.manager.diff("s1", &checkpoint.id).unwrap().is_empty());
+
+        assert!(f
+            .manager
+            .restore("s1", &checkpoint.id, Some(&["/elsewhere".to_string()]))
+            .is_err());
+    }
+
+    #[test]
+    fn test_rewind_uses_earliest_snapshot_per_file() {
+        let f = fixture();
+        fs::write(f.base.join("a.txt"), "v1").unwrap();
+
+        let first = f
+            .manager
+            .create(
+                "s1",
+                2,
+                &[call("write_file", serde_json::json!({"path": "a.txt"}))],
+            )
+            .unwrap()
+            .unwrap();
+        fs::write(f.base.join("a.txt"), "v2").unwrap();
+
+        // 第二次迭代才首次涉及 b.txt
+        fs::write(f.base.join("b.txt"), "b1").unwrap();
+        f.manager
+            .create(
+                "s1",
+                6,
+                &[
+                    call("write_file", serde_json::json!({"path": "a.txt"})),
+                    call("write_file", serde_json::json!({"path": "b.txt"})),
+                ],
+            )
+            .unwrap()
+            .unwrap();
+        fs::write(f.base.join("a.txt"), "v3").unwrap();
+        fs::write(f.base.join("b.txt"), "b2").unwrap();
+
+        // 回退到第 4 条消息：只撤销第二次迭代
+        let (restored, removed) = f.manager.rewind("s1", 4).unwrap();
+        assert_eq!(restored.len(), 2);
+        assert_eq!(removed, 1);
+        assert_eq!(fs::read_to_string(f.base.join("a.txt")).unwrap(), "v2");
+        assert_eq!(fs::read_to_string(f.base.join("b.txt")).unwrap(), "b1");
+
+        // 回退到开头：a.txt 恢复到第一次快照，b.txt 取第一次之后最早的快照
+        fs::write(f.base.join("b.txt"), "b3").unwrap();
+        f.manager
+            .create(
+                "s1",
+                6,
+                &[call("write_file", serde_json::json!({"path": "b.txt"}))],
+            )
+            .unwrap();
+        let changes = f.manager.diff("s1", &first.id).unwrap();
+        assert_eq!(changes.len(), 1);
+        let (_, removed) = f.manager.rewind("s1", 0).unwrap();
+        assert_eq!(removed, 2);
+        assert_eq!(fs::read_to_string(f.base.join("a.txt")).unwrap(), "v1");
+        assert_eq!(fs::read_to_string(f.base.join("b.txt")).unwrap(), "b3");
+        assert!(f.manager.list("s1").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_bash_scope_tracks_created_files() {
+        let f = fixture();
+        let project = f.base.join("project");
+        fs::create_dir_all(project.join(".git")).unwrap();
+        fs::write(project.join("main.rs"), "fn main() {}").unwrap();
+
+        let checkpoint = f
+            .manager
+            .create(
+                "s1",
+                2,
+                &[call(
+                    "bash",
+                    serde_json::json!({"command": "cd project && cargo init"}),
+                )],
+            )
+            .unwrap()
+            .unwrap();
+        assert_eq!(checkpoint.scopes, vec![path_key(&project)]);
+
+        fs::write(project.join("main.rs"), "fn main() { todo!() }").unwrap();
+        fs::write(project.join("Cargo.toml"), "[package]").unwrap();
+        f.manager.record_created(&checkpoint).unwrap();
+
+        let changes = f.manager.diff("s1", &checkpoint.id).unwrap();
+        let kinds: Vec<_> = changes.iter().map(|c| c.kind).collect();
+        assert_eq!(kinds, vec![FileChangeKind::Added, FileChangeKind::Modified]);
+
+        f.manager.restore("s1", &checkpoint.id, None).unwrap();
+        assert!(!project.join("Cargo.toml").exists());
+        assert_eq!(
+            fs::read_to_string(project.join("main.rs")).unwrap(),
+            "fn main() {}"
+        );
+
+        // 只读工具不创建检查点
+        assert!(f
+            .manager
+            .create(
+                "s1",
+                4,
+                &[call("read_file", serde_json::json!({"path": "x"}))]
+            )
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn test_rewind_keeps_oversized_and_user_files() {
+        let f = fixture();
+        let project = f.base.join("project");
+        fs::create_dir_all(project.join(".git")).unwrap();
+        let large = vec![b'x'; MAX_SNAPSHOT_FILE_BYTES as usize + 1];
+        fs::write(project.join("data.bin"), &large).unwrap();
+        fs::write(project.join("main.rs"), "fn main() {}").unwrap();
+
+        let checkpoint = f
+            .manager
+            .create(
+                "s1",
+                2,
+                &[call(
+                    "bash",
+                    serde_json::json!({"command": "cd project && cargo build"}),
+                )],
+            )
+            .unwrap()
+            .unwrap();
+        assert!(checkpoint.untracked);
+        fs::write(project.join("build.log"), "ok").unwrap();
+        f.manager.record_created(&checkpoint).unwrap();
+
+        // 工具执行之后用户新建的文件
+        fs::write(project.join("notes.md"), "mine").unwrap();
+        fs::write(project.join("main.rs"), "fn main() { todo!() }").unwrap();
+
+        let (restored, _) = f.manager.rewind("s1", 0).unwrap();
+        assert_eq!(restored.len(), 2);
+        assert_eq!(
+            fs::read(project.join("data.bin")).unwrap().len(),
+            large.len()
+        );
+        assert!(project.join("notes.md").exists());
+        assert!(!project.join("build.log").exists());
+        assert_eq!(
+            fs::read_to_string(project.join("main.rs")).unwrap(),
+            "fn main() {}"
+        );
+    }
+}
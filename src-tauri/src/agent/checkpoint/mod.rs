@@ -0,0 +1,20 @@
+//! 工作区检查点
+//!
+//! 每次工具循环迭代执行前，为工具调用将要涉及的文件创建快照，
+//! 支持查看检查点以来的全部改动、按文件恢复，以及将会话和文件一起回退到第 N 条消息。
+//!
+//! ## 模块结构
+//! - types - 检查点、快照清单、文件变更和错误类型
+//! - blob_store - 内容寻址对象存储（SHA-256）
+//! - snapshot - 根据工具调用确定快照范围，目录扫描
+//! - manager - 创建、diff、恢复和回退
+
+pub mod blob_store;
+pub mod manager;
+pub mod snapshot;
+pub mod types;
+
+pub use blob_store::BlobStore;
+pub use manager::CheckpointManager;
+pub use snapshot::{targets_for_tool_calls, SnapshotTargets};
+pub use types::{Checkpoint, CheckpointError, FileChange, FileChangeKind, Manifest, RewindResult};
@@ -0,0 +1,348 @@
+//! 快照范围
+//!
+//! 根据一次迭代中的工具调用确定需要快照的文件：
+//! - `write_file` / `edit_file`：参数 `path` 指向的文件
+//! - `apply_patch`：补丁涉及的全部文件（包括重命名目标）
+//! - `task`：子代理可使用写工具时，其改动无法预先确定，标记为无法追踪
+//! - `bash` / `process_start`：命令所在目录（支持开头的 `cd <目录> &&`）所属的 git 项目整体扫描，
+//!   遵循 `.gitignore`；不在 git 项目中时无法追踪，标记为 untracked
+
+use std::collections::BTreeSet;
+use std::path::{Component, Path, PathBuf};
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use crate::agent::subagent::{TaskRequest, DEFAULT_SUBAGENT_TOOLS};
+use crate::agent::tools::apply_patch::parse_patch;
+use crate::agent::types::ToolCall;
+
+/// 单个文件的最大快照大小
+pub const MAX_SNAPSHOT_FILE_BYTES: u64 = 2 * 1024 * 1024;
+
+/// 单个目录扫描的最大文件数，超出时放弃整体快照
+pub const MAX_SCOPE_FILES: usize = 10_000;
+
+/// 标签中单个命令的最大字符数
+const LABEL_COMMAND_CHARS: usize = 60;
+
+static CD_PREFIX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"^\s*cd\s+(?:"([^"]+)"|'([^']+)'|([^\s;&|]+))\s*(?:&&|;)"#).unwrap());
+
+/// 一次迭代需要快照的内容
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct SnapshotTargets {
+    /// 单独快照的文件
+    pub files: BTreeSet<PathBuf>,
+    /// 整体扫描的目录
+    pub scopes: BTreeSet<PathBuf>,
+    /// 是否存在无法追踪的工具调用
+    pub untracked: bool,
+    /// 工具调用描述
+    pub labels: Vec<String>,
+}
+
+impl SnapshotTargets {
+    /// 没有可能修改文件的工具调用
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty() && self.scopes.is_empty() && !self.untracked
+    }
+}
+
+/// 根据工具调用确定快照范围，`base_dir` 之外的路径不会被快照
+pub fn targets_for_tool_calls(calls: &[ToolCall], base_dir: &Path) -> SnapshotTargets {
+    let mut targets = SnapshotTargets::default();
+    for call in calls {
+        let args: serde_json::Value =
+            serde_json::from_str(&call.function.arguments).unwrap_or_default();
+        match call.function.name.as_str() {
+            "write_file" | "edit_file" => {
+                let Some(path) = args.get("path").and_then(|v| v.as_str()) else {
+                    continue;
+                };
+                targets
+                    .labels
+                    .push(format!("{} {}", call.function.name, path));
+                match resolve_path(path, base_dir) {
+                    Some(path) => {
+                        targets.files.insert(path);
+                    }
+                    None => targets.untracked = true,
+                }
+            }
+            "apply_patch" => {
+                // 无法解析的补丁不会被应用，无需快照
+                let Some(Ok(operations)) =
+                    args.get("patch").and_then(|v| v.as_str()).map(parse_patch)
+                else {
+                    continue;
+                };
+                let paths: Vec<&str> = operations.iter().flat_map(|op| op.paths()).collect();
+                targets.labels.push(format!(
+                    "apply_patch {}",
+                    truncate_chars(&paths.join(", "), LABEL_COMMAND_CHARS)
+                ));
+                for path in paths {
+                    match resolve_path(path, base_dir) {
+                        Some(path) => {
+                            targets.files.insert(path);
+                        }
+                        None => targets.untracked = true,
+                    }
+                }
+            }
+            "bash" | "process_start" => {
+                let Some(command) = args.get("command").and_then(|v| v.as_str()) else {
+                    continue;
+                };
+                targets.labels.push(format!(
+                    "{} {}",
+                    call.function.name,
+                    truncate_chars(command, LABEL_COMMAND_CHARS)
+                ));
+                match bash_working_dir(command, base_dir).and_then(|dir| find_git_root(&dir)) {
+                    Some(root) if root.starts_with(base_dir) => {
+                        targets.scopes.insert(root);
+                    }
+                    _ => targets.untracked = true,
+                }
+            }
+            "task" => {
+                let Ok(task) = TaskRequest::from_args(&args) else {
+                    continue;
+                };
+                // 只能使用默认只读工具的子代理不会修改文件
+                if task
+                    .tool_names()
+                    .iter()
+                    .all(|name| DEFAULT_SUBAGENT_TOOLS.contains(&name.as_str()))
+                {
+                    continue;
+                }
+                targets.labels.push(format!(
+                    "task {}",
+                    truncate_chars(&task.description, LABEL_COMMAND_CHARS)
+                ));
+                targets.untracked = true;
+            }
+            _ => {}
+        }
+    }
+
+    // 已在整体扫描范围内的文件不需要单独快照
+    let scopes = targets.scopes.clone();
+    targets
+        .files
+        .retain(|f| !scopes.iter().any(|s| f.starts_with(s)));
+    targets
+}
+
+/// 扫描目录下的文件（遵循 .gitignore，跳过 .git），超过文件数上限时返回 None
+pub fn scan_scope(scope: &Path) -> Option<Vec<PathBuf>> {
+    let walker = ignore::WalkBuilder::new(scope)
+        .hidden(false)
+        .filter_entry(|entry| entry.file_name() != ".git")
+        .build();
+
+    let mut files = Vec::new();
+    for entry in walker.flatten() {
+        if !entry.file_type().is_some_and(|t| t.is_file()) {
+            continue;
+        }
+        if files.len() >= MAX_SCOPE_FILES {
+            tracing::warn!(
+                "[Checkpoint] 目录文件数超过 {}，放弃整体快照: {:?}",
+                MAX_SCOPE_FILES,
+                scope
+            );
+            return None;
+        }
+        files.push(entry.into_path());
+    }
+    Some(files)
+}
+
+/// 解析工具参数中的路径，不在 `base_dir` 内时返回 None
+pub fn resolve_path(path: &str, base_dir: &Path) -> Option<PathBuf> {
+    let path = expand_home(path);
+    let full = if path.is_absolute() {
+        path
+    } else {
+        base_dir.join(path)
+    };
+    let normalized = normalize(&full);
+    normalized.starts_with(base_dir).then_some(normalized)
+}
+
+/// bash 命令的工作目录：默认为 `base_dir`，命令以 `cd <目录> &&` 开头时为该目录
+fn bash_working_dir(command: &str, base_dir: &Path) -> Option<PathBuf> {
+    let Some(captures) = CD_PREFIX.captures(command) else {
+        return Some(base_dir.to_path_buf());
+    };
+    let dir = captures
+        .get(1)
+        .or_else(|| captures.get(2))
+        .or_else(|| captures.get(3))?
+        .as_str();
+    resolve_path(dir, base_dir)
+}
+
+/// 向上查找包含 `.git` 的目录
+fn find_git_root(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors()
+        .find(|d| d.join(".git").exists())
+        .map(Path::to_path_buf)
+}
+
+fn expand_home(path: &str) -> PathBuf {
+    match path.strip_prefix("~/") {
+        Some(rest) => dirs::home_dir()
+            .map(|home| home.join(rest))
+            .unwrap_or_else(|| PathBuf::from(path)),
+        None if path == "~" => dirs::home_dir().unwrap_or_else(|| PathBuf::from(path)),
+        None => PathBuf::from(path),
+    }
+}
+
+/// 按字面消除 `.` 和 `..`（不访问文件系统，文件可能尚不存在）
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            other => normalized.push(other),
+        }
+    }
+    normalized
+}
+
+fn truncate_chars(text: &str, max_chars: usize) -> String {
+    let text = text.lines().next().unwrap_or_default();
+    match text.char_indices().nth(max_chars) {
+        Some((i, _)) => format!("{}…", &text[..i]),
+        None => text.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::types::FunctionCall;
+
+    fn call(name: &str, arguments: serde_json::Value) -> ToolCall {
+        ToolCall {
+            id: "call_1".to_string(),
+            call_type: "function".to_string(),
+            function: FunctionCall {
+                name: name.to_string(),
+                arguments: arguments.to_string(),
+            },
+        }
+    }
+
+    #[test]
+    fn test_targets_for_file_tools() {
+        let base = Path::new("/work");
+        let targets = targets_for_tool_calls(
+            &[
+                call("write_file", serde_json::json!({"path": "a/b.txt"})),
+                call("edit_file", serde_json::json!({"path": "/work/c/../d.rs"})),
+                call("write_file", serde_json::json!({"path": "../etc/passwd"})),
+                call("read_file", serde_json::json!({"path": "e.txt"})),
+                call(
+                    "apply_patch",
+                    serde_json::json!({
+                        "patch": "*** Begin Patch\n*** Update File: f.rs\n*** Move to: g.rs\n*** End Patch"
+                    }),
+                ),
+            ],
+            base,
+        );
+        assert_eq!(
+            targets.files.iter().collect::<Vec<_>>(),
+            vec![
+                Path::new("/work/a/b.txt"),
+                Path::new("/work/d.rs"),
+                Path::new("/work/f.rs"),
+                Path::new("/work/g.rs")
+            ]
+        );
+        assert!(targets.untracked);
+        assert_eq!(targets.labels.len(), 4);
+
+        let targets =
+            targets_for_tool_calls(&[call("read_file", serde_json::json!({"path": "x"}))], base);
+        assert!(targets.is_empty());
+    }
+
+    #[test]
+    fn test_task_targets() {
+        let base = Path::new("/work");
+        let read_only = call(
+            "task",
+            serde_json::json!({"description": "find", "prompt": "find foo"}),
+        );
+        assert!(targets_for_tool_calls(&[read_only], base).is_empty());
+
+        let writer = call(
+            "task",
+            serde_json::json!({"description": "fix", "prompt": "fix foo", "tools": ["edit_file"]}),
+        );
+        let targets = targets_for_tool_calls(&[writer], base);
+        assert!(targets.untracked);
+        assert_eq!(targets.labels, vec!["task fix"]);
+    }
+
+    #[test]
+    fn test_bash_scope_uses_git_root() {
+        let dir = tempfile::tempdir().unwrap();
+        let base = dir.path();
+        std::fs::create_dir_all(base.join("project/.git")).unwrap();
+        std::fs::create_dir_all(base.join("project/src")).unwrap();
+
+        let targets = targets_for_tool_calls(
+            &[
+                call(
+                    "bash",
+                    serde_json::json!({"command": "cd project/src && cargo fmt"}),
+                ),
+                call(
+                    "write_file",
+                    serde_json::json!({"path": "project/src/lib.rs"}),
+                ),
+            ],
+            base,
+        );
+        assert_eq!(
+            targets.scopes.iter().collect::<Vec<_>>(),
+            vec![&base.join("project")]
+        );
+        assert!(targets.files.is_empty());
+        assert!(!targets.untracked);
+
+        // 不在 git 项目中的命令无法追踪
+        let targets =
+            targets_for_tool_calls(&[call("bash", serde_json::json!({"command": "ls"}))], base);
+        assert!(targets.scopes.is_empty());
+        assert!(targets.untracked);
+    }
+
+    #[test]
+    fn test_scan_scope_respects_gitignore() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        std::fs::create_dir_all(root.join(".git")).unwrap();
+        std::fs::create_dir_all(root.join("target")).unwrap();
+        std::fs::write(root.join(".gitignore"), "target/\n").unwrap();
+        std::fs::write(root.join("main.rs"), "fn main() {}").unwrap();
+        std::fs::write(root.join("target/out"), "build").unwrap();
+        std::fs::write(root.join(".git/HEAD"), "ref").unwrap();
+
+        let mut files = scan_scope(root).unwrap();
+        files.sort();
+        assert_eq!(files, vec![root.join(".gitignore"), root.join("main.rs")]);
+    }
+}
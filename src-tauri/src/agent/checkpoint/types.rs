@@ -0,0 +1,107 @@
+//! 工作区检查点类型定义
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// 检查点错误
+#[derive(Debug, Error)]
+pub enum CheckpointError {
+    /// 检查点不存在
+    #[error("检查点不存在: {0}")]
+    NotFound(String),
+
+    /// 参数无效（消息序号越界、路径不在检查点范围内等）
+    #[error("参数无效: {0}")]
+    Invalid(String),
+
+    /// 快照对象缺失或损坏
+    #[error("快照对象损坏: {0}")]
+    Corrupted(String),
+
+    /// 数据库错误
+    #[error("数据库错误: {0}")]
+    Database(String),
+
+    /// IO 错误
+    #[error("IO 错误: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+impl From<rusqlite::Error> for CheckpointError {
+    fn from(e: rusqlite::Error) -> Self {
+        Self::Database(e.to_string())
+    }
+}
+
+/// 检查点：某次工具循环迭代执行前，被工具调用涉及的文件状态
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Checkpoint {
+    /// 检查点 ID
+    pub id: String,
+    /// 所属会话 ID
+    pub session_id: String,
+    /// 创建时会话的消息数，回退到该检查点之前的消息时恢复此时的文件状态
+    pub message_index: usize,
+    /// 触发检查点的工具调用（如 `write_file src/main.rs`）
+    pub label: String,
+    /// 快照的文件数
+    pub file_count: usize,
+    /// 整体快照的目录（bash 命令所在的 git 项目）
+    pub scopes: Vec<String>,
+    /// 是否有无法追踪的改动（不在 git 项目中执行的 bash 命令、超出大小限制的文件等）
+    pub untracked: bool,
+    /// 快照清单对象的哈希
+    #[serde(skip)]
+    pub manifest: String,
+    /// 创建时间
+    pub created_at: String,
+}
+
+/// 快照清单：文件路径到内容哈希，`None` 表示快照时文件不存在
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct Manifest {
+    pub files: BTreeMap<String, Option<String>>,
+    /// 完整扫描过的目录，目录内清单中没有的文件视为当时不存在
+    #[serde(default)]
+    pub scopes: Vec<String>,
+    /// 超出大小限制未快照的文件：快照时存在但无法恢复，回退时保持不变
+    #[serde(default)]
+    pub oversized: BTreeSet<String>,
+    /// 工具执行期间新建的文件，只有这些文件会在回退时删除
+    #[serde(default)]
+    pub created: BTreeSet<String>,
+}
+
+/// 文件变更类型
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FileChangeKind {
+    Added,
+    Modified,
+    Deleted,
+}
+
+/// 检查点以来的单个文件变更
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct FileChange {
+    /// 文件绝对路径
+    pub path: String,
+    pub kind: FileChangeKind,
+    /// 统一 diff（二进制文件为空）
+    pub diff: String,
+    /// 是否为二进制文件
+    pub binary: bool,
+}
+
+/// 回退会话的结果
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct RewindResult {
+    /// 恢复的文件
+    pub restored_files: Vec<String>,
+    /// 删除的消息数
+    pub removed_messages: usize,
+    /// 删除的检查点数
+    pub removed_checkpoints: usize,
+}
@@ -0,0 +1,480 @@
+//! 上下文压缩
+//!
+//! 按 token 预算管理原生 Agent 会话的上下文：
+//! - 发送给模型的上下文超过 `context_window_tokens * threshold` 时，
+//!   将较早的轮次交给（可配置的廉价）模型摘要，后续请求用摘要代替这些消息
+//! - 最近 `keep_recent_turns` 轮对话和固定（pinned）的消息始终保留原文
+//! - 较早轮次中过长的工具输出只保留首尾，中间省略
+//!
+//! 压缩不修改原始消息，只在会话中追加 `ContextCompaction` 记录，
+//! 用户可以查看哪些消息被摘要以及摘要内容。
+
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+
+use crate::agent::types::{AgentMessage, AgentSession, ContextCompaction, MessageContent};
+use crate::config::ContextCompactionSettings;
+use crate::telemetry::TokenEstimator;
+
+/// 每条消息的格式化开销（role、分隔符等）
+const TOKENS_PER_MESSAGE: u32 = 4;
+
+/// 摘要中固定消息的标题
+const PINNED_HEADER: &str = "以下消息已被用户固定，保留原文：";
+
+/// 摘要消息之后的确认回复，保持 user / assistant 交替
+const SUMMARY_ACK: &str = "好的，我已了解之前的对话内容，会在此基础上继续。";
+
+/// 生成摘要的系统提示词
+const SUMMARY_SYSTEM_PROMPT: &str = "你负责压缩 AI 编程助手的对话历史。\
+请用简洁的要点总结对话，保留：用户的目标和约束、已做出的决定、修改过的文件和关键代码位置、\
+工具调用得到的重要结果、尚未完成的工作。不要编造对话中没有的信息，直接输出摘要正文。";
+
+static ESTIMATOR: Lazy<Option<TokenEstimator>> = Lazy::new(|| match TokenEstimator::new() {
+    Ok(estimator) => Some(estimator),
+    Err(e) => {
+        tracing::warn!("[ContextCompactor] {}，改用字符数估算", e);
+        None
+    }
+});
+
+/// 估算文本的 token 数
+pub fn estimate_tokens(text: &str, model: &str) -> u32 {
+    match ESTIMATOR.as_ref() {
+        Some(estimator) => estimator.estimate(text, Some(model)),
+        None => (text.chars().count() as u32).div_ceil(4),
+    }
+}
+
+/// 估算单条消息的 token 数（包括工具调用参数）
+pub fn estimate_message_tokens(message: &AgentMessage, model: &str) -> u32 {
+    let mut tokens = TOKENS_PER_MESSAGE + estimate_tokens(&message.content.as_text(), model);
+    for call in message.tool_calls.iter().flatten() {
+        tokens += estimate_tokens(&call.function.name, model);
+        tokens += estimate_tokens(&call.function.arguments, model);
+    }
+    tokens
+}
+
+/// 一次待执行的压缩
+#[derive(Debug, Clone)]
+pub struct CompactionPlan {
+    /// 本次新摘要的第一条消息序号（即上一次压缩的终点）
+    pub compacted_from: usize,
+    /// 被摘要代替的消息终点（不含），总是某一轮用户消息的开始
+    pub compacted_until: usize,
+    /// 压缩前的上下文 token 数
+    pub tokens_before: u32,
+    /// 发送给摘要模型的系统提示词
+    pub system_prompt: String,
+    /// 发送给摘要模型的用户消息（上一次摘要 + 新增对话记录）
+    pub prompt: String,
+}
+
+/// 上下文压缩器
+pub struct ContextCompactor {
+    settings: RwLock<ContextCompactionSettings>,
+}
+
+impl Default for ContextCompactor {
+    fn default() -> Self {
+        Self::new(ContextCompactionSettings::default())
+    }
+}
+
+impl ContextCompactor {
+    pub fn new(settings: ContextCompactionSettings) -> Self {
+        Self {
+            settings: RwLock::new(settings),
+        }
+    }
+
+    pub fn settings(&self) -> ContextCompactionSettings {
+        self.settings.read().clone()
+    }
+
+    pub fn set_settings(&self, settings: ContextCompactionSettings) {
+        *self.settings.write() = settings;
+    }
+
+    /// 构建发送给模型的上下文
+    ///
+    /// 有压缩记录时，最后一次压缩范围内的消息替换为摘要（附带其中固定的消息原文）；
+    /// 最近几轮之前的过长工具输出省略中间部分。
+    pub fn build_context(&self, session: &AgentSession) -> Vec<AgentMessage> {
+        let settings = self.settings.read();
+        let until = compacted_until(session);
+        let recent_start = recent_turns_start(&session.messages, until, settings.keep_recent_turns);
+
+        let mut messages = Vec::with_capacity(session.messages.len() - until + 2);
+        if let Some(compaction) = session.compactions.last() {
+            let timestamp = compaction.created_at.clone();
+            messages.push(synthetic_message(
+                "user",
+                summary_text(&compaction.summary, &session.messages[..until]),
+                timestamp.clone(),
+            ));
+            messages.push(synthetic_message(
+                "assistant",
+                SUMMARY_ACK.to_string(),
+                timestamp,
+            ));
+        }
+
+        for (index, message) in session.messages.iter().enumerate().skip(until) {
+            let mut message = message.clone();
+            if index < recent_start && !message.pinned && message.role == "tool" {
+                let text = message.content.as_text();
+                if text.chars().count() > settings.tool_output_max_chars {
+                    message.content =
+                        MessageContent::Text(elide_middle(&text, settings.tool_output_max_chars));
+                }
+            }
+            messages.push(message);
+        }
+        messages
+    }
+
+    /// 估算上下文 token 数（包括系统提示词和尚未加入会话的用户消息）
+    pub fn estimate_context_tokens(&self, session: &AgentSession, pending: &str) -> u32 {
+        let model = session.model.as_str();
+        let messages: u32 = self
+            .build_context(session)
+            .iter()
+            .map(|m| estimate_message_tokens(m, model))
+            .sum();
+        let system = session
+            .system_prompt
+            .as_deref()
+            .map(|p| estimate_tokens(p, model))
+            .unwrap_or(0);
+        let pending = if pending.is_empty() {
+            0
+        } else {
+            TOKENS_PER_MESSAGE + estimate_tokens(pending, model)
+        };
+        messages + system + pending
+    }
+
+    /// 判断是否需要压缩，需要时返回压缩计划
+    ///
+    /// `force` 为 true 时忽略开关和阈值（手动压缩），但仍保留最近几轮对话。
+    pub fn plan(
+        &self,
+        session: &AgentSession,
+        pending: &str,
+        force: bool,
+    ) -> Option<CompactionPlan> {
+        let settings = self.settings();
+        let tokens_before = self.estimate_context_tokens(session, pending);
+        let budget = (settings.context_window_tokens as f64 * settings.threshold as f64) as u32;
+        if !force && (!settings.enabled || tokens_before <= budget) {
+            return None;
+        }
+
+        let compacted_from = compacted_until(session);
+        let compacted_until = recent_turns_start(
+            &session.messages,
+            compacted_from,
+            settings.keep_recent_turns,
+        );
+        if compacted_until <= compacted_from {
+            return None;
+        }
+
+        let mut prompt = String::new();
+        if let Some(previous) = session.compactions.last() {
+            prompt.push_str("## 之前的摘要\n\n");
+            prompt.push_str(&previous.summary);
+            prompt.push_str("\n\n");
+        }
+        prompt.push_str("## 需要压缩的对话\n\n");
+        prompt.push_str(&render_transcript(
+            &session.messages[compacted_from..compacted_until],
+            settings.tool_output_max_chars,
+        ));
+        prompt.push_str("\n请输出合并了之前摘要和以上对话的新摘要。");
+
+        Some(CompactionPlan {
+            compacted_from,
+            compacted_until,
+            tokens_before,
+            system_prompt: SUMMARY_SYSTEM_PROMPT.to_string(),
+            prompt,
+        })
+    }
+
+    /// 根据摘要结果生成压缩记录
+    pub fn finish(
+        &self,
+        session: &AgentSession,
+        plan: &CompactionPlan,
+        summary: String,
+        model: String,
+        pending: &str,
+    ) -> ContextCompaction {
+        let mut compaction = ContextCompaction {
+            id: uuid::Uuid::new_v4().to_string(),
+            compacted_from: plan.compacted_from,
+            compacted_until: plan.compacted_until,
+            summary,
+            model,
+            tokens_before: plan.tokens_before,
+            tokens_after: 0,
+            created_at: chrono::Utc::now().to_rfc3339(),
+        };
+
+        let mut compacted = session.clone();
+        compacted.compactions.push(compaction.clone());
+        compaction.tokens_after = self.estimate_context_tokens(&compacted, pending);
+        compaction
+    }
+}
+
+/// 最后一次压缩的终点（不超过当前消息数，会话被清空或截断后仍然安全）
+fn compacted_until(session: &AgentSession) -> usize {
+    session
+        .compactions
+        .last()
+        .map(|c| c.compacted_until.min(session.messages.len()))
+        .unwrap_or(0)
+}
+
+/// 最近 `keep_turns` 轮对话的起点（用户消息序号），轮数不足时返回 `from`
+fn recent_turns_start(messages: &[AgentMessage], from: usize, keep_turns: usize) -> usize {
+    let turn_starts: Vec<usize> = messages
+        .iter()
+        .enumerate()
+        .skip(from)
+        .filter(|(_, m)| m.role == "user")
+        .map(|(i, _)| i)
+        .collect();
+    if turn_starts.len() <= keep_turns {
+        return from;
+    }
+    turn_starts[turn_starts.len() - keep_turns.max(1)]
+}
+
+/// 摘要消息正文：摘要 + 被压缩范围内固定消息的原文
+fn summary_text(summary: &str, compacted: &[AgentMessage]) -> String {
+    let mut text = format!("以下是此前对话的摘要：\n\n{}", summary);
+    let pinned: Vec<&AgentMessage> = compacted.iter().filter(|m| m.pinned).collect();
+    if !pinned.is_empty() {
+        text.push_str("\n\n");
+        text.push_str(PINNED_HEADER);
+        text.push_str("\n\n");
+        text.push_str(&render_transcript_messages(&pinned, usize::MAX));
+    }
+    text
+}
+
+fn synthetic_message(role: &str, text: String, timestamp: String) -> AgentMessage {
+    AgentMessage {
+        role: role.to_string(),
+        content: MessageContent::Text(text),
+        timestamp,
+        tool_calls: None,
+        tool_call_id: None,
+        pinned: false,
+    }
+}
+
+/// 将消息渲染为纯文本对话记录
+fn render_transcript(messages: &[AgentMessage], tool_output_max_chars: usize) -> String {
+    let messages: Vec<&AgentMessage> = messages.iter().collect();
+    render_transcript_messages(&messages, tool_output_max_chars)
+}
+
+fn render_transcript_messages(messages: &[&AgentMessage], tool_output_max_chars: usize) -> String {
+    let mut transcript = String::new();
+    for message in messages {
+        let text = message.content.as_text();
+        match message.role.as_str() {
+            "tool" => {
+                transcript.push_str(&format!(
+                    "[工具结果 {}]\n{}\n\n",
+                    message.tool_call_id.as_deref().unwrap_or("-"),
+                    elide_middle(&text, tool_output_max_chars)
+                ));
+            }
+            role => {
+                let label = match role {
+                    "user" => "用户",
+                    "assistant" => "助手",
+                    other => other,
+                };
+                if !text.is_empty() {
+                    transcript.push_str(&format!("[{}]\n{}\n\n", label, text));
+                }
+                for call in message.tool_calls.iter().flatten() {
+                    transcript.push_str(&format!(
+                        "[{}调用工具 {}]\n{}\n\n",
+                        label, call.function.name, call.function.arguments
+                    ));
+                }
+            }
+        }
+    }
+    transcript
+}
+
+/// 保留文本首尾，省略中间部分，结果不超过 `max_chars` 个字符（加省略标记）
+pub fn elide_middle(text: &str, max_chars: usize) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    if chars.len() <= max_chars {
+        return text.to_string();
+    }
+    let head = max_chars / 2;
+    let tail = max_chars - head;
+    let mut result: String = chars[..head].iter().collect();
+    result.push_str(&format!(
+        "\n[... 已省略 {} 个字符 ...]\n",
+        chars.len() - head - tail
+    ));
+    result.extend(&chars[chars.len() - tail..]);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::types::{FunctionCall, ToolCall};
+
+    fn message(role: &str, text: &str) -> AgentMessage {
+        synthetic_message(
+            role,
+            text.to_string(),
+            "2025-01-01T00:00:00+00:00".to_string(),
+        )
+    }
+
+    /// 每轮：用户消息、带工具调用的助手消息、工具结果、助手回复
+    fn create_session(turns: usize, tool_output: &str) -> AgentSession {
+        let mut messages = Vec::new();
+        for turn in 0..turns {
+            messages.push(message("user", &format!("第 {} 轮问题", turn)));
+            let mut call = message("assistant", "");
+            call.tool_calls = Some(vec![ToolCall {
+                id: format!("call_{}", turn),
+                call_type: "function".to_string(),
+                function: FunctionCall {
+                    name: "bash".to_string(),
+                    arguments: r#"{"command":"cat log"}"#.to_string(),
+                },
+            }]);
+            messages.push(call);
+            let mut result = message("tool", tool_output);
+            result.tool_call_id = Some(format!("call_{}", turn));
+            messages.push(result);
+            messages.push(message("assistant", &format!("第 {} 轮回答", turn)));
+        }
+        AgentSession {
+            id: "s1".to_string(),
+            model: "gpt-4o".to_string(),
+            messages,
+            system_prompt: None,
+            created_at: "2025-01-01T00:00:00+00:00".to_string(),
+            updated_at: "2025-01-01T00:00:00+00:00".to_string(),
+            title: None,
+            archived: false,
+            parent_id: None,
+            compactions: Vec::new(),
+        }
+    }
+
+    fn compactor(context_window_tokens: u32) -> ContextCompactor {
+        ContextCompactor::new(ContextCompactionSettings {
+            context_window_tokens,
+            keep_recent_turns: 2,
+            tool_output_max_chars: 100,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_elide_middle() {
+        assert_eq!(elide_middle("short", 10), "short");
+        let elided = elide_middle(&"a".repeat(300), 100);
+        assert!(elided.starts_with(&"a".repeat(50)));
+        assert!(elided.ends_with(&"a".repeat(50)));
+        assert!(elided.contains("已省略 200 个字符"));
+    }
+
+    #[test]
+    fn test_build_context_elides_old_tool_outputs() {
+        let session = create_session(4, &"x".repeat(500));
+        let context = compactor(100_000).build_context(&session);
+        assert_eq!(context.len(), session.messages.len());
+
+        // 前两轮的工具输出被省略，最近两轮保留原文
+        assert!(context[2].content.as_text().contains("已省略"));
+        assert!(context[6].content.as_text().contains("已省略"));
+        assert_eq!(context[10].content.as_text(), "x".repeat(500));
+        assert_eq!(context[14].content.as_text(), "x".repeat(500));
+
+        // 固定的消息不省略
+        let mut session = session;
+        session.messages[2].pinned = true;
+        let context = compactor(100_000).build_context(&session);
+        assert_eq!(context[2].content.as_text(), "x".repeat(500));
+    }
+
+    #[test]
+    fn test_plan_respects_threshold_and_recent_turns() {
+        let session = create_session(4, "ok");
+        assert!(compactor(100_000)
+            .plan(&session, "下一个问题", false)
+            .is_none());
+
+        let plan = compactor(50).plan(&session, "下一个问题", false).unwrap();
+        assert_eq!(plan.compacted_from, 0);
+        assert_eq!(plan.compacted_until, 8);
+        assert!(plan.prompt.contains("第 1 轮回答"));
+        assert!(!plan.prompt.contains("第 2 轮问题"));
+        assert!(plan.prompt.contains("[助手调用工具 bash]"));
+
+        // 不足 keep_recent_turns 轮时无法压缩，即使强制
+        let short = create_session(2, "ok");
+        assert!(compactor(50).plan(&short, "", true).is_none());
+
+        let mut disabled = compactor(50);
+        disabled.settings.get_mut().enabled = false;
+        assert!(disabled.plan(&session, "", false).is_none());
+        assert!(disabled.plan(&session, "", true).is_some());
+    }
+
+    #[test]
+    fn test_compaction_replaces_history_with_summary() {
+        let compactor = compactor(50);
+        let mut session = create_session(4, &"x".repeat(500));
+        session.messages[1].pinned = true;
+
+        let plan = compactor.plan(&session, "", false).unwrap();
+        let compaction = compactor.finish(
+            &session,
+            &plan,
+            "用户在排查日志".to_string(),
+            "mini".into(),
+            "",
+        );
+        assert!(compaction.tokens_after < compaction.tokens_before);
+        session.compactions.push(compaction);
+
+        let context = compactor.build_context(&session);
+        assert_eq!(context.len(), 2 + 8);
+        let summary = context[0].content.as_text();
+        assert_eq!(context[0].role, "user");
+        assert!(summary.contains("用户在排查日志"));
+        assert!(summary.contains(PINNED_HEADER));
+        assert!(summary.contains("cat log"));
+        assert_eq!(context[1].role, "assistant");
+        assert_eq!(context[2].content.as_text(), "第 2 轮问题");
+
+        // 下一次压缩从上一次的终点开始，并带上之前的摘要
+        session.messages.extend(create_session(1, "ok").messages);
+        let plan = compactor.plan(&session, "", true).unwrap();
+        assert_eq!(plan.compacted_from, 8);
+        assert_eq!(plan.compacted_until, 12);
+        assert!(plan.prompt.contains("## 之前的摘要"));
+    }
+}
@@ -0,0 +1,152 @@
+//! MCP 客户端
+//!
+//! 完成 `initialize` 握手后提供 tools、resources、prompts 三类请求，
+//! 列表请求自动处理 `nextCursor` 分页。
+
+use std::time::Duration;
+
+use serde_json::{json, Value};
+
+use super::transport::{McpTransport, McpTransportConfig};
+use super::types::{McpCapabilities, McpError, McpToolInfo};
+
+/// 客户端使用的协议版本
+pub const MCP_PROTOCOL_VERSION: &str = "2025-03-26";
+
+/// 默认请求超时（毫秒），可通过服务器配置的 `timeout` 覆盖
+pub const DEFAULT_REQUEST_TIMEOUT_MS: u64 = 60_000;
+
+/// 列表分页上限，防止服务器返回循环游标
+const MAX_PAGES: usize = 100;
+
+/// 单个 MCP 服务器的客户端连接
+pub struct McpClient {
+    server_name: String,
+    transport: McpTransport,
+    capabilities: McpCapabilities,
+    instructions: Option<String>,
+    timeout: Duration,
+}
+
+impl McpClient {
+    /// 连接服务器并完成初始化握手
+    pub async fn connect(
+        server_name: &str,
+        config: &McpTransportConfig,
+        timeout: Duration,
+    ) -> Result<Self, McpError> {
+        let transport = McpTransport::connect(server_name, config).await?;
+
+        let params = json!({
+            "protocolVersion": MCP_PROTOCOL_VERSION,
+            "capabilities": {},
+            "clientInfo": {"name": "proxycast", "version": env!("CARGO_PKG_VERSION")},
+        });
+        let result = match transport.request("initialize", params, timeout).await {
+            Ok(result) => result,
+            Err(e) => {
+                transport.close().await;
+                return Err(e);
+            }
+        };
+        transport
+            .notify("notifications/initialized", json!({}))
+            .await?;
+
+        let client = Self {
+            server_name: server_name.to_string(),
+            capabilities: McpCapabilities::from_value(&result["capabilities"]),
+            instructions: result["instructions"].as_str().map(str::to_string),
+            transport,
+            timeout,
+        };
+        tracing::info!(
+            "[MCP {}] 已连接: server={}, capabilities={:?}",
+            server_name,
+            result["serverInfo"]["name"].as_str().unwrap_or("unknown"),
+            client.capabilities
+        );
+        Ok(client)
+    }
+
+    pub fn server_name(&self) -> &str {
+        &self.server_name
+    }
+
+    pub fn capabilities(&self) -> &McpCapabilities {
+        &self.capabilities
+    }
+
+    /// 服务器在初始化时提供的使用说明
+    pub fn instructions(&self) -> Option<&str> {
+        self.instructions.as_deref()
+    }
+
+    pub fn is_connected(&self) -> bool {
+        !self.transport.is_closed()
+    }
+
+    pub async fn request(&self, method: &str, params: Value) -> Result<Value, McpError> {
+        self.transport.request(method, params, self.timeout).await
+    }
+
+    /// 请求分页列表，合并所有页的 `key` 字段
+    async fn list_all(&self, method: &str, key: &str) -> Result<Vec<Value>, McpError> {
+        let mut items = Vec::new();
+        let mut cursor: Option<String> = None;
+        for _ in 0..MAX_PAGES {
+            let params = match &cursor {
+                Some(cursor) => json!({"cursor": cursor}),
+                None => json!({}),
+            };
+            let mut result = self.request(method, params).await?;
+            if let Some(Value::Array(page)) = result.get_mut(key).map(Value::take) {
+                items.extend(page);
+            }
+            cursor = result["nextCursor"].as_str().map(str::to_string);
+            if cursor.is_none() {
+                break;
+            }
+        }
+        Ok(items)
+    }
+
+    pub async fn list_tools(&self) -> Result<Vec<McpToolInfo>, McpError> {
+        self.list_all("tools/list", "tools")
+            .await?
+            .into_iter()
+            .map(|tool| {
+                serde_json::from_value(tool)
+                    .map_err(|e| McpError::Protocol(format!("无效的工具定义: {}", e)))
+            })
+            .collect()
+    }
+
+    /// 调用工具，返回 `CallToolResult`（`content`、`isError`）
+    pub async fn call_tool(&self, name: &str, arguments: Value) -> Result<Value, McpError> {
+        self.request("tools/call", json!({"name": name, "arguments": arguments}))
+            .await
+    }
+
+    pub async fn list_resources(&self) -> Result<Vec<Value>, McpError> {
+        self.list_all("resources/list", "resources").await
+    }
+
+    pub async fn read_resource(&self, uri: &str) -> Result<Value, McpError> {
+        self.request("resources/read", json!({"uri": uri})).await
+    }
+
+    pub async fn list_prompts(&self) -> Result<Vec<Value>, McpError> {
+        self.list_all("prompts/list", "prompts").await
+    }
+
+    pub async fn get_prompt(&self, name: &str, arguments: Value) -> Result<Value, McpError> {
+        self.request("prompts/get", json!({"name": name, "arguments": arguments}))
+            .await
+    }
+
+    /// 关闭连接
+    pub async fn close(&self) {
+        self.transport.close().await;
+    }
+}
@@ -0,0 +1,248 @@
+//! MCP 客户端管理器
+//!
+//! 根据 `mcp_servers` 表中启用了 ProxyCast 的服务器建立连接，
+//! 并将其工具注册到 Agent 的工具注册表。
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use parking_lot::RwLock;
+
+use super::client::{McpClient, DEFAULT_REQUEST_TIMEOUT_MS};
+use super::tool::{McpHelperTool, McpTool};
+use super::transport::McpTransportConfig;
+use super::types::{McpError, McpServerStatus};
+use crate::agent::tools::{Tool, ToolRegistry};
+use crate::database::DbConnection;
+use crate::models::McpServer;
+use crate::services::mcp_service::McpService;
+
+/// 单个服务器的连接状态
+struct McpConnection {
+    id: String,
+    name: String,
+    client: Option<Arc<McpClient>>,
+    tools: Vec<Arc<dyn Tool>>,
+    error: Option<String>,
+}
+
+impl McpConnection {
+    fn status(&self) -> McpServerStatus {
+        McpServerStatus {
+            id: self.id.clone(),
+            name: self.name.clone(),
+            connected: self.client.as_ref().is_some_and(|c| c.is_connected()),
+            capabilities: self
+                .client
+                .as_ref()
+                .map(|c| c.capabilities().clone())
+                .unwrap_or_default(),
+            tools: self.tools.iter().map(|t| t.definition().name).collect(),
+            error: self.error.clone(),
+        }
+    }
+}
+
+/// MCP 客户端管理器
+#[derive(Default)]
+pub struct McpClientManager {
+    connections: RwLock<Vec<McpConnection>>,
+}
+
+impl McpClientManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 从数据库读取启用的服务器并（重新）连接
+    ///
+    /// 单个服务器连接失败不影响其他服务器，错误记录在状态中。
+    pub async fn connect_all(&self, db: &DbConnection) -> Result<Vec<McpServerStatus>, String> {
+        let servers: Vec<McpServer> = McpService::get_all(db)?
+            .into_iter()
+            .filter(|s| s.enabled_proxycast)
+            .collect();
+
+        self.disconnect_all().await;
+
+        let connections = futures::future::join_all(servers.iter().map(connect_server)).await;
+        let statuses = connections.iter().map(McpConnection::status).collect();
+        *self.connections.write() = connections;
+        Ok(statuses)
+    }
+
+    /// 将所有已连接服务器的工具注册到注册表
+    ///
+    /// 与已有工具重名的 MCP 工具会被跳过。
+    pub fn register_tools(&self, registry: &ToolRegistry) {
+        for connection in self.connections.read().iter() {
+            if connection.client.is_none() {
+                continue;
+            }
+            for tool in &connection.tools {
+                if let Err(e) = registry.register_arc(tool.clone()) {
+                    tracing::warn!("[MCP {}] 注册工具失败: {}", connection.name, e);
+                }
+            }
+        }
+    }
+
+    /// 各服务器连接状态
+    pub fn status(&self) -> Vec<McpServerStatus> {
+        self.connections
+            .read()
+            .iter()
+            .map(McpConnection::status)
+            .collect()
+    }
+
+    /// 断开所有服务器
+    pub async fn disconnect_all(&self) {
+        let connections = std::mem::take(&mut *self.connections.write());
+        for connection in connections {
+            if let Some(client) = connection.client {
+                client.close().await;
+            }
+        }
+    }
+}
+
+async fn connect_server(server: &McpServer) -> McpConnection {
+    let mut connection = McpConnection {
+        id: server.id.clone(),
+        name: server.name.clone(),
+        client: None,
+        tools: Vec::new(),
+        error: None,
+    };
+
+    match connect_client(server).await {
+        Ok((client, tools)) => {
+            tracing::info!("[MCP {}] 已注册 {} 个工具", server.name, tools.len());
+            connection.client = Some(client);
+            connection.tools = tools;
+        }
+        Err(e) => {
+            tracing::warn!("[MCP {}] 连接失败: {}", server.name, e);
+            connection.error = Some(e.to_string());
+        }
+    }
+    connection
+}
+
+async fn connect_client(
+    server: &McpServer,
+) -> Result<(Arc<McpClient>, Vec<Arc<dyn Tool>>), McpError> {
+    let config = McpTransportConfig::from_server_config(&server.server_config)?;
+    let timeout = Duration::from_millis(
+        server.server_config["timeout"]
+            .as_u64()
+            .unwrap_or(DEFAULT_REQUEST_TIMEOUT_MS),
+    );
+    let client = Arc::new(McpClient::connect(&server.name, &config, timeout).await?);
+
+    match collect_tools(&client).await {
+        Ok(tools) => Ok((client, tools)),
+        Err(e) => {
+            client.close().await;
+            Err(e)
+        }
+    }
+}
+
+async fn collect_tools(client: &Arc<McpClient>) -> Result<Vec<Arc<dyn Tool>>, McpError> {
+    let capabilities = client.capabilities();
+    let mut tools: Vec<Arc<dyn Tool>> = Vec::new();
+
+    if capabilities.tools {
+        for info in client.list_tools().await? {
+            tools.push(Arc::new(McpTool::new(client.clone(), &info)));
+        }
+    }
+    for helper in McpHelperTool::for_client(client) {
+        tools.push(Arc::new(helper));
+    }
+    Ok(tools)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// 用 sh + sed 实现的最小 stdio MCP 服务器：按方法名返回固定结果
+    fn fake_server(name: &str) -> McpServer {
+        let script = r#"while IFS= read -r line; do
+  id=$(printf '%s' "$line" | sed -n 's/.*"id":\([0-9]*\).*/\1/p')
+  [ -z "$id" ] && continue
+  method=$(printf '%s' "$line" | sed -n 's/.*"method":"\([a-z_/]*\)".*/\1/p')
+  case "$method" in
+    initialize) result='{"protocolVersion":"2025-03-26","capabilities":{"tools":{},"resources":{}},"serverInfo":{"name":"fake"}}' ;;
+    tools/list) result='{"tools":[{"name":"echo","description":"回显","inputSchema":{"type":"object","properties":{"text":{"type":"string"}},"required":["text"]}}]}' ;;
+    tools/call) result='{"content":[{"type":"text","text":"pong"}]}' ;;
+    resources/list) result='{"resources":[{"uri":"memo://a","name":"a"}]}' ;;
+    resources/read) result='{"contents":[{"uri":"memo://a","text":"资源内容"}]}' ;;
+    *) printf '{"jsonrpc":"2.0","id":%s,"error":{"code":-32601,"message":"not found"}}\n' "$id"; continue ;;
+  esac
+  printf '{"jsonrpc":"2.0","id":%s,"result":%s}\n' "$id" "$result"
+done"#;
+        let mut server = McpServer::new(
+            format!("{}-id", name),
+            name.to_string(),
+            json!({"command": "sh", "args": ["-c", script], "timeout": 5000}),
+        );
+        server.enabled_proxycast = true;
+        server
+    }
+
+    #[tokio::test]
+    async fn test_connect_and_register_stdio_server() {
+        let connection = connect_server(&fake_server("fake")).await;
+        assert!(connection.error.is_none(), "{:?}", connection.error);
+
+        let status = connection.status();
+        assert!(status.connected);
+        assert!(status.capabilities.resources);
+        assert!(!status.capabilities.prompts);
+        assert_eq!(
+            status.tools,
+            vec![
+                "mcp__fake__echo",
+                "mcp__fake__list_resources",
+                "mcp__fake__read_resource"
+            ]
+        );
+
+        let manager = McpClientManager::new();
+        manager.connections.write().push(connection);
+        let registry = ToolRegistry::new();
+        manager.register_tools(&registry);
+
+        let result = registry
+            .execute("mcp__fake__echo", json!({"text": "ping"}))
+            .await
+            .unwrap();
+        assert_eq!(result.output, "pong");
+
+        let result = registry
+            .execute("mcp__fake__read_resource", json!({"uri": "memo://a"}))
+            .await
+            .unwrap();
+        assert_eq!(result.output, "资源内容");
+
+        manager.disconnect_all().await;
+        assert!(manager.status().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_connect_failure_is_reported() {
+        let mut server = fake_server("broken");
+        server.server_config = json!({"command": "/nonexistent/mcp-server"});
+        let connection = connect_server(&server).await;
+
+        let status = connection.status();
+        assert!(!status.connected);
+        assert!(status.tools.is_empty());
+        assert!(status.error.is_some());
+    }
+}
@@ -0,0 +1,22 @@
+//! MCP（Model Context Protocol）客户端
+//!
+//! 连接 `mcp_servers` 中启用 ProxyCast 的服务器（stdio / Streamable HTTP），
+//! 将其工具、资源和提示词注册为 Agent 工具。
+//!
+//! ## 模块结构
+//! - transport - stdio 与 HTTP 传输层（JSON-RPC）
+//! - client - 初始化握手与 tools / resources / prompts 请求
+//! - tool - MCP 工具到 Agent 工具的适配
+//! - manager - 多服务器连接管理
+
+pub mod client;
+pub mod manager;
+pub mod tool;
+pub mod transport;
+pub mod types;
+
+pub use client::{McpClient, MCP_PROTOCOL_VERSION};
+pub use manager::McpClientManager;
+pub use tool::{namespaced_tool_name, McpHelperTool, McpTool};
+pub use transport::{McpTransport, McpTransportConfig};
+pub use types::{McpCapabilities, McpError, McpServerStatus, McpToolInfo};
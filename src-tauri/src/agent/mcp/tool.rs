@@ -0,0 +1,417 @@
+//! MCP 工具适配
+//!
+//! 将 MCP 服务器的工具、资源和提示词包装为 Agent 工具：
+//! - 服务器工具注册为 `mcp__<server>__<tool>`
+//! - 声明 resources 能力的服务器额外提供 `list_resources` / `read_resource`
+//! - 声明 prompts 能力的服务器额外提供 `list_prompts` / `get_prompt`
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use serde_json::Value;
+
+use super::client::McpClient;
+use super::types::McpToolInfo;
+use crate::agent::tools::{
+    JsonSchema, PropertySchema, Tool, ToolDefinition, ToolError, ToolResult,
+};
+
+/// 工具名称长度上限（OpenAI 等 API 的限制）
+const MAX_TOOL_NAME_LEN: usize = 64;
+
+/// 生成带命名空间的工具名称：`mcp__<server>__<tool>`
+pub fn namespaced_tool_name(server: &str, tool: &str) -> String {
+    let sanitize = |s: &str| -> String {
+        s.chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '_' || c == '-' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect()
+    };
+    let mut name = format!("mcp__{}__{}", sanitize(server), sanitize(tool));
+    name.truncate(MAX_TOOL_NAME_LEN);
+    name
+}
+
+/// 将 MCP 工具的 inputSchema 转换为 JsonSchema
+///
+/// 属性中除 type / description / default / enum 以外的关键字保存在 `extra` 中，
+/// 联合类型（如 `["string", "null"]`）取第一个非 null 类型。
+pub fn schema_from_mcp(input_schema: &Value) -> JsonSchema {
+    let mut schema = JsonSchema::new();
+    if let Some(properties) = input_schema.get("properties").and_then(|p| p.as_object()) {
+        for (name, property) in properties {
+            schema
+                .properties
+                .insert(name.clone(), property_from_mcp(property));
+        }
+    }
+    if let Some(required) = input_schema.get("required").and_then(|r| r.as_array()) {
+        schema.required = required
+            .iter()
+            .filter_map(|r| r.as_str())
+            .filter(|r| schema.properties.contains_key(*r))
+            .map(str::to_string)
+            .collect();
+    }
+    schema
+}
+
+fn property_from_mcp(property: &Value) -> PropertySchema {
+    let mut extra = property.as_object().cloned().unwrap_or_default();
+    let prop_type = match extra.remove("type") {
+        Some(Value::String(t)) => t,
+        Some(Value::Array(types)) => types
+            .iter()
+            .filter_map(|t| t.as_str())
+            .find(|t| *t != "null")
+            .unwrap_or_default()
+            .to_string(),
+        _ => String::new(),
+    };
+    let description = match extra.remove("description") {
+        Some(Value::String(d)) => d,
+        _ => String::new(),
+    };
+    let default = extra.remove("default");
+    let enum_values = match extra.remove("enum") {
+        Some(Value::Array(values)) => Some(values),
+        _ => None,
+    };
+
+    PropertySchema {
+        prop_type,
+        description,
+        default,
+        enum_values,
+        extra,
+    }
+}
+
+/// 将 MCP 内容块列表格式化为文本
+fn format_content(content: &[Value]) -> String {
+    content
+        .iter()
+        .map(|item| match item["type"].as_str() {
+            Some("text") => item["text"].as_str().unwrap_or_default().to_string(),
+            Some("image") => format!("[图片: {}]", item["mimeType"].as_str().unwrap_or("image")),
+            Some("audio") => format!("[音频: {}]", item["mimeType"].as_str().unwrap_or("audio")),
+            Some("resource") => format_resource_contents(&item["resource"]),
+            Some("resource_link") => {
+                format!("[资源: {}]", item["uri"].as_str().unwrap_or_default())
+            }
+            _ => item.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn format_resource_contents(resource: &Value) -> String {
+    match resource["text"].as_str() {
+        Some(text) => text.to_string(),
+        None => format!(
+            "[二进制资源: {} ({})]",
+            resource["uri"].as_str().unwrap_or_default(),
+            resource["mimeType"]
+                .as_str()
+                .unwrap_or("application/octet-stream")
+        ),
+    }
+}
+
+/// 将 `tools/call` 结果转换为 ToolResult
+pub fn call_result_to_tool_result(result: &Value) -> ToolResult {
+    let mut output = result["content"]
+        .as_array()
+        .map(|content| format_content(content))
+        .unwrap_or_default();
+    if output.is_empty() {
+        if let Some(structured) = result.get("structuredContent") {
+            output = structured.to_string();
+        }
+    }
+
+    if result["isError"].as_bool().unwrap_or(false) {
+        ToolResult::failure(output)
+    } else {
+        ToolResult::success(output)
+    }
+}
+
+fn execution_error(e: impl std::fmt::Display) -> ToolError {
+    ToolError::ExecutionFailed(e.to_string())
+}
+
+/// MCP 服务器工具
+pub struct McpTool {
+    client: Arc<McpClient>,
+    name: String,
+    remote_name: String,
+    description: String,
+    parameters: JsonSchema,
+}
+
+impl McpTool {
+    pub fn new(client: Arc<McpClient>, info: &McpToolInfo) -> Self {
+        let description = info
+            .description
+            .clone()
+            .filter(|d| !d.trim().is_empty())
+            .unwrap_or_else(|| format!("MCP 服务器 {} 的工具 {}", client.server_name(), info.name));
+        Self {
+            name: namespaced_tool_name(client.server_name(), &info.name),
+            remote_name: info.name.clone(),
+            description,
+            parameters: schema_from_mcp(&info.input_schema),
+            client,
+        }
+    }
+}
+
+#[async_trait]
+impl Tool for McpTool {
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition::new(&self.name, &self.description).with_parameters(self.parameters.clone())
+    }
+
+    async fn execute(&self, args: Value) -> Result<ToolResult, ToolError> {
+        let result = self
+            .client
+            .call_tool(&self.remote_name, args)
+            .await
+            .map_err(execution_error)?;
+        Ok(call_result_to_tool_result(&result))
+    }
+}
+
+/// 资源与提示词工具类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum McpHelperKind {
+    ListResources,
+    ReadResource,
+    ListPrompts,
+    GetPrompt,
+}
+
+impl McpHelperKind {
+    fn tool_name(&self) -> &'static str {
+        match self {
+            Self::ListResources => "list_resources",
+            Self::ReadResource => "read_resource",
+            Self::ListPrompts => "list_prompts",
+            Self::GetPrompt => "get_prompt",
+        }
+    }
+}
+
+/// MCP 资源 / 提示词访问工具
+pub struct McpHelperTool {
+    client: Arc<McpClient>,
+    kind: McpHelperKind,
+}
+
+impl McpHelperTool {
+    pub fn new(client: Arc<McpClient>, kind: McpHelperKind) -> Self {
+        Self { client, kind }
+    }
+
+    /// 根据服务器能力创建对应的辅助工具
+    pub fn for_client(client: &Arc<McpClient>) -> Vec<Self> {
+        let capabilities = client.capabilities();
+        let mut kinds = Vec::new();
+        if capabilities.resources {
+            kinds.extend([McpHelperKind::ListResources, McpHelperKind::ReadResource]);
+        }
+        if capabilities.prompts {
+            kinds.extend([McpHelperKind::ListPrompts, McpHelperKind::GetPrompt]);
+        }
+        kinds
+            .into_iter()
+            .map(|kind| Self::new(client.clone(), kind))
+            .collect()
+    }
+}
+
+#[async_trait]
+impl Tool for McpHelperTool {
+    fn definition(&self) -> ToolDefinition {
+        let server = self.client.server_name();
+        let name = namespaced_tool_name(server, self.kind.tool_name());
+        match self.kind {
+            McpHelperKind::ListResources => ToolDefinition::new(
+                name,
+                format!("列出 MCP 服务器 {} 提供的资源（URI、名称、描述）", server),
+            ),
+            McpHelperKind::ReadResource => {
+                ToolDefinition::new(name, format!("读取 MCP 服务器 {} 的资源内容", server))
+                    .with_parameters(JsonSchema::new().add_property(
+                        "uri",
+                        PropertySchema::string("资源 URI"),
+                        true,
+                    ))
+            }
+            McpHelperKind::ListPrompts => ToolDefinition::new(
+                name,
+                format!("列出 MCP 服务器 {} 提供的提示词模板及其参数", server),
+            ),
+            McpHelperKind::GetPrompt => {
+                ToolDefinition::new(name, format!("获取 MCP 服务器 {} 的提示词模板内容", server))
+                    .with_parameters(
+                        JsonSchema::new()
+                            .add_property("name", PropertySchema::string("提示词名称"), true)
+                            .add_property(
+                                "arguments",
+                                PropertySchema::object("提示词参数（字符串键值对）"),
+                                false,
+                            ),
+                    )
+            }
+        }
+    }
+
+    async fn execute(&self, args: Value) -> Result<ToolResult, ToolError> {
+        let output = match self.kind {
+            McpHelperKind::ListResources => {
+                let resources = self
+                    .client
+                    .list_resources()
+                    .await
+                    .map_err(execution_error)?;
+                serde_json::to_string_pretty(&resources)?
+            }
+            McpHelperKind::ReadResource => {
+                let uri = args["uri"]
+                    .as_str()
+                    .ok_or_else(|| ToolError::InvalidArguments("uri 必须是字符串".to_string()))?;
+                let result = self
+                    .client
+                    .read_resource(uri)
+                    .await
+                    .map_err(execution_error)?;
+                result["contents"]
+                    .as_array()
+                    .map(|contents| {
+                        contents
+                            .iter()
+                            .map(format_resource_contents)
+                            .collect::<Vec<_>>()
+                            .join("\n")
+                    })
+                    .unwrap_or_default()
+            }
+            McpHelperKind::ListPrompts => {
+                let prompts = self.client.list_prompts().await.map_err(execution_error)?;
+                serde_json::to_string_pretty(&prompts)?
+            }
+            McpHelperKind::GetPrompt => {
+                let name = args["name"]
+                    .as_str()
+                    .ok_or_else(|| ToolError::InvalidArguments("name 必须是字符串".to_string()))?;
+                let arguments = match &args["arguments"] {
+                    Value::Null => Value::Object(Default::default()),
+                    arguments => arguments.clone(),
+                };
+                let result = self
+                    .client
+                    .get_prompt(name, arguments)
+                    .await
+                    .map_err(execution_error)?;
+                format_prompt(&result)
+            }
+        };
+        Ok(ToolResult::success(output))
+    }
+}
+
+/// 将 `prompts/get` 结果格式化为文本
+fn format_prompt(result: &Value) -> String {
+    let mut lines = Vec::new();
+    if let Some(description) = result["description"].as_str() {
+        lines.push(description.to_string());
+    }
+    for message in result["messages"].as_array().into_iter().flatten() {
+        let content = match &message["content"] {
+            Value::Array(content) => format_content(content),
+            content => format_content(std::slice::from_ref(content)),
+        };
+        lines.push(format!(
+            "[{}] {}",
+            message["role"].as_str().unwrap_or("user"),
+            content
+        ));
+    }
+    lines.join("\n\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_namespaced_tool_name() {
+        assert_eq!(
+            namespaced_tool_name("file system", "read.file"),
+            "mcp__file_system__read_file"
+        );
+        assert_eq!(namespaced_tool_name("s", &"x".repeat(100)).len(), 64);
+    }
+
+    #[test]
+    fn test_schema_from_mcp_preserves_nested_keywords() {
+        let schema = schema_from_mcp(&json!({
+            "type": "object",
+            "properties": {
+                "paths": {"type": "array", "items": {"type": "string"}, "description": "文件列表"},
+                "mode": {"type": ["string", "null"], "enum": ["a", "b"]},
+                "filter": {"anyOf": [{"type": "string"}, {"type": "number"}]}
+            },
+            "required": ["paths", "missing"]
+        }));
+
+        assert_eq!(schema.required, vec!["paths".to_string()]);
+        assert!(schema.validate().is_ok());
+
+        let api = serde_json::to_value(&schema).unwrap();
+        assert_eq!(
+            api["properties"]["paths"]["items"],
+            json!({"type": "string"})
+        );
+        assert_eq!(api["properties"]["mode"]["type"], "string");
+        assert!(api["properties"]["filter"].get("type").is_none());
+        assert!(api["properties"]["filter"]["anyOf"].is_array());
+    }
+
+    #[test]
+    fn test_call_result_to_tool_result() {
+        let result = call_result_to_tool_result(&json!({
+            "content": [
+                {"type": "text", "text": "hello"},
+                {"type": "image", "data": "...", "mimeType": "image/png"},
+                {"type": "resource", "resource": {"uri": "file:///a", "text": "body"}}
+            ]
+        }));
+        assert!(result.success);
+        assert_eq!(result.output, "hello\n[图片: image/png]\nbody");
+
+        let result = call_result_to_tool_result(&json!({
+            "content": [{"type": "text", "text": "bad input"}],
+            "isError": true
+        }));
+        assert!(!result.success);
+        assert_eq!(result.error.as_deref(), Some("bad input"));
+    }
+
+    #[test]
+    fn test_format_prompt() {
+        let text = format_prompt(&json!({
+            "description": "代码审查",
+            "messages": [{"role": "user", "content": {"type": "text", "text": "请审查"}}]
+        }));
+        assert_eq!(text, "代码审查\n\n[user] 请审查");
+    }
+}
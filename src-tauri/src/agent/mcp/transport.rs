@@ -0,0 +1,581 @@
+//! MCP 传输层
+//!
+//! - stdio：启动服务器子进程，按行交换 JSON-RPC 2.0 消息，stderr 转发到日志
+//! - Streamable HTTP：每条消息 POST 到服务器端点，响应为 JSON 或 SSE 流，
+//!   会话 ID 通过 `Mcp-Session-Id` 头维持
+//!
+//! 服务器发往客户端的请求中只应答 `ping` 和 `roots/list`，其余返回 method not found。
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures::StreamExt;
+use parking_lot::Mutex;
+use serde_json::{json, Value};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWriteExt, BufReader};
+use tokio::process::{Child, ChildStdin, Command};
+use tokio::sync::oneshot;
+
+use super::types::McpError;
+
+/// 服务器传输配置
+///
+/// 从 `mcp_servers.server_config` 解析：包含 `command` 为 stdio，包含 `url` 为 Streamable HTTP。
+#[derive(Debug, Clone, PartialEq)]
+pub enum McpTransportConfig {
+    Stdio {
+        command: String,
+        args: Vec<String>,
+        env: HashMap<String, String>,
+        cwd: Option<PathBuf>,
+    },
+    Http {
+        url: String,
+        headers: HashMap<String, String>,
+    },
+}
+
+impl McpTransportConfig {
+    pub fn from_server_config(config: &Value) -> Result<Self, McpError> {
+        let string_map = |key: &str| -> HashMap<String, String> {
+            config
+                .get(key)
+                .and_then(|v| v.as_object())
+                .map(|map| {
+                    map.iter()
+                        .filter_map(|(k, v)| v.as_str().map(|v| (k.clone(), v.to_string())))
+                        .collect()
+                })
+                .unwrap_or_default()
+        };
+
+        let transport_type = config.get("type").and_then(|v| v.as_str()).unwrap_or("");
+        if transport_type == "sse" {
+            return Err(McpError::Unsupported(
+                "不支持旧版 SSE 传输，请使用 Streamable HTTP".to_string(),
+            ));
+        }
+
+        if let Some(command) = config.get("command").and_then(|v| v.as_str()) {
+            let args = config
+                .get("args")
+                .and_then(|v| v.as_array())
+                .map(|args| {
+                    args.iter()
+                        .filter_map(|a| a.as_str().map(str::to_string))
+                        .collect()
+                })
+                .unwrap_or_default();
+            return Ok(Self::Stdio {
+                command: command.to_string(),
+                args,
+                env: string_map("env"),
+                cwd: config
+                    .get("cwd")
+                    .and_then(|v| v.as_str())
+                    .map(PathBuf::from),
+            });
+        }
+
+        let url = config
+            .get("url")
+            .or_else(|| config.get("httpUrl"))
+            .and_then(|v| v.as_str());
+        match url {
+            Some(url) => Ok(Self::Http {
+                url: url.to_string(),
+                headers: string_map("headers"),
+            }),
+            None => Err(McpError::Unsupported(
+                "服务器配置缺少 command 或 url".to_string(),
+            )),
+        }
+    }
+}
+
+type PendingRequests = Mutex<HashMap<u64, oneshot::Sender<Result<Value, McpError>>>>;
+
+/// 传输连接
+pub enum McpTransport {
+    Stdio(StdioTransport),
+    Http(HttpTransport),
+}
+
+impl McpTransport {
+    pub async fn connect(server_name: &str, config: &McpTransportConfig) -> Result<Self, McpError> {
+        match config {
+            McpTransportConfig::Stdio {
+                command,
+                args,
+                env,
+                cwd,
+            } => StdioTransport::spawn(server_name, command, args, env, cwd.as_ref())
+                .map(Self::Stdio),
+            McpTransportConfig::Http { url, headers } => {
+                HttpTransport::new(url, headers).map(Self::Http)
+            }
+        }
+    }
+
+    /// 发送请求并等待响应
+    pub async fn request(
+        &self,
+        method: &str,
+        params: Value,
+        timeout: Duration,
+    ) -> Result<Value, McpError> {
+        let call = async {
+            match self {
+                Self::Stdio(transport) => transport.request(method, params).await,
+                Self::Http(transport) => transport.request(method, params).await,
+            }
+        };
+        tokio::time::timeout(timeout, call)
+            .await
+            .map_err(|_| McpError::Timeout(method.to_string()))?
+    }
+
+    /// 发送通知（无响应）
+    pub async fn notify(&self, method: &str, params: Value) -> Result<(), McpError> {
+        let message = json!({"jsonrpc": "2.0", "method": method, "params": params});
+        match self {
+            Self::Stdio(transport) => transport.send(&message).await,
+            Self::Http(transport) => transport.post(&message).await.map(|_| ()),
+        }
+    }
+
+    pub fn is_closed(&self) -> bool {
+        match self {
+            Self::Stdio(transport) => transport.closed.load(Ordering::Relaxed),
+            Self::Http(_) => false,
+        }
+    }
+
+    /// 关闭连接：stdio 终止子进程，HTTP 结束服务器会话
+    pub async fn close(&self) {
+        match self {
+            Self::Stdio(transport) => {
+                let _ = transport.child.lock().await.kill().await;
+            }
+            Self::Http(transport) => transport.terminate_session().await,
+        }
+    }
+}
+
+/// stdio 传输
+pub struct StdioTransport {
+    stdin: Arc<tokio::sync::Mutex<ChildStdin>>,
+    pending: Arc<PendingRequests>,
+    next_id: AtomicU64,
+    closed: Arc<AtomicBool>,
+    child: tokio::sync::Mutex<Child>,
+}
+
+impl StdioTransport {
+    fn spawn(
+        server_name: &str,
+        command: &str,
+        args: &[String],
+        env: &HashMap<String, String>,
+        cwd: Option<&PathBuf>,
+    ) -> Result<Self, McpError> {
+        let mut cmd = Command::new(command);
+        cmd.args(args)
+            .envs(env)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+        if let Some(cwd) = cwd {
+            cmd.current_dir(cwd);
+        }
+        let mut child = cmd
+            .spawn()
+            .map_err(|e| McpError::Spawn(format!("{}: {}", command, e)))?;
+
+        let (Some(stdin), Some(stdout), Some(stderr)) =
+            (child.stdin.take(), child.stdout.take(), child.stderr.take())
+        else {
+            return Err(McpError::Spawn(
+                "无法连接服务器进程的标准输入输出".to_string(),
+            ));
+        };
+
+        let stdin = Arc::new(tokio::sync::Mutex::new(stdin));
+        let pending = Arc::new(PendingRequests::default());
+        let closed = Arc::new(AtomicBool::new(false));
+        tokio::spawn(read_stdout(
+            server_name.to_string(),
+            stdout,
+            stdin.clone(),
+            pending.clone(),
+            closed.clone(),
+        ));
+        tokio::spawn(read_stderr(server_name.to_string(), stderr));
+
+        Ok(Self {
+            stdin,
+            pending,
+            next_id: AtomicU64::new(1),
+            closed,
+            child: tokio::sync::Mutex::new(child),
+        })
+    }
+
+    async fn send(&self, message: &Value) -> Result<(), McpError> {
+        if self.closed.load(Ordering::Relaxed) {
+            return Err(McpError::Closed);
+        }
+        write_line(&self.stdin, message).await
+    }
+
+    async fn request(&self, method: &str, params: Value) -> Result<Value, McpError> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let (tx, rx) = oneshot::channel();
+        self.pending.lock().insert(id, tx);
+        // 超时取消时也要移除等待项
+        let _guard = PendingGuard {
+            pending: &self.pending,
+            id,
+        };
+
+        let message = json!({"jsonrpc": "2.0", "id": id, "method": method, "params": params});
+        self.send(&message).await?;
+        rx.await.unwrap_or(Err(McpError::Closed))
+    }
+}
+
+struct PendingGuard<'a> {
+    pending: &'a PendingRequests,
+    id: u64,
+}
+
+impl Drop for PendingGuard<'_> {
+    fn drop(&mut self) {
+        self.pending.lock().remove(&self.id);
+    }
+}
+
+async fn write_line(
+    stdin: &tokio::sync::Mutex<ChildStdin>,
+    message: &Value,
+) -> Result<(), McpError> {
+    let mut line = message.to_string();
+    line.push('\n');
+    let mut stdin = stdin.lock().await;
+    stdin.write_all(line.as_bytes()).await?;
+    stdin.flush().await?;
+    Ok(())
+}
+
+async fn read_stdout(
+    server_name: String,
+    stdout: impl AsyncRead + Unpin,
+    stdin: Arc<tokio::sync::Mutex<ChildStdin>>,
+    pending: Arc<PendingRequests>,
+    closed: Arc<AtomicBool>,
+) {
+    let mut lines = BufReader::new(stdout).lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        let Ok(message) = serde_json::from_str::<Value>(&line) else {
+            tracing::debug!("[MCP {}] 忽略非协议输出: {}", server_name, line);
+            continue;
+        };
+
+        match classify(&message) {
+            Incoming::Response(id, result) => {
+                if let Some(tx) = pending.lock().remove(&id) {
+                    let _ = tx.send(result);
+                }
+            }
+            Incoming::Request(reply) => {
+                if let Err(e) = write_line(&stdin, &reply).await {
+                    tracing::warn!("[MCP {}] 应答服务器请求失败: {}", server_name, e);
+                }
+            }
+            Incoming::Notification(method) => {
+                tracing::debug!("[MCP {}] 收到通知: {}", server_name, method);
+            }
+            Incoming::Invalid => {}
+        }
+    }
+
+    closed.store(true, Ordering::Relaxed);
+    for (_, tx) in pending.lock().drain() {
+        let _ = tx.send(Err(McpError::Closed));
+    }
+    tracing::info!("[MCP {}] 服务器进程已退出", server_name);
+}
+
+async fn read_stderr(server_name: String, stderr: impl AsyncRead + Unpin) {
+    let mut lines = BufReader::new(stderr).lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        tracing::debug!("[MCP {}] {}", server_name, line);
+    }
+}
+
+/// 服务器发来的消息
+enum Incoming {
+    /// 请求的响应
+    Response(u64, Result<Value, McpError>),
+    /// 服务器请求，附带待发送的应答
+    Request(Value),
+    Notification(String),
+    Invalid,
+}
+
+fn classify(message: &Value) -> Incoming {
+    let id = message.get("id");
+    match (message.get("method").and_then(|m| m.as_str()), id) {
+        (Some(method), Some(id)) => {
+            let reply = match method {
+                "ping" => json!({"jsonrpc": "2.0", "id": id, "result": {}}),
+                "roots/list" => json!({"jsonrpc": "2.0", "id": id, "result": {"roots": []}}),
+                _ => json!({
+                    "jsonrpc": "2.0",
+                    "id": id,
+                    "error": {"code": -32601, "message": format!("Method not found: {}", method)}
+                }),
+            };
+            Incoming::Request(reply)
+        }
+        (Some(method), None) => Incoming::Notification(method.to_string()),
+        (None, Some(id)) => {
+            let Some(id) = id.as_u64() else {
+                return Incoming::Invalid;
+            };
+            let result = match message.get("error") {
+                Some(error) => Err(McpError::Server {
+                    code: error.get("code").and_then(|c| c.as_i64()).unwrap_or(0),
+                    message: error
+                        .get("message")
+                        .and_then(|m| m.as_str())
+                        .unwrap_or_default()
+                        .to_string(),
+                }),
+                None => Ok(message.get("result").cloned().unwrap_or(Value::Null)),
+            };
+            Incoming::Response(id, result)
+        }
+        (None, None) => Incoming::Invalid,
+    }
+}
+
+/// Streamable HTTP 传输
+pub struct HttpTransport {
+    client: reqwest::Client,
+    url: String,
+    headers: HashMap<String, String>,
+    session_id: Mutex<Option<String>>,
+    next_id: AtomicU64,
+}
+
+const SESSION_HEADER: &str = "Mcp-Session-Id";
+
+impl HttpTransport {
+    fn new(url: &str, headers: &HashMap<String, String>) -> Result<Self, McpError> {
+        let client = reqwest::Client::builder()
+            .connect_timeout(Duration::from_secs(30))
+            .build()
+            .map_err(|e| McpError::Http(e.to_string()))?;
+        Ok(Self {
+            client,
+            url: url.to_string(),
+            headers: headers.clone(),
+            session_id: Mutex::new(None),
+            next_id: AtomicU64::new(1),
+        })
+    }
+
+    fn build(&self, method: reqwest::Method) -> reqwest::RequestBuilder {
+        let mut builder = self.client.request(method, &self.url);
+        for (name, value) in &self.headers {
+            builder = builder.header(name, value);
+        }
+        if let Some(session_id) = self.session_id.lock().clone() {
+            builder = builder.header(SESSION_HEADER, session_id);
+        }
+        builder
+    }
+
+    async fn post(&self, message: &Value) -> Result<reqwest::Response, McpError> {
+        let response = self
+            .build(reqwest::Method::POST)
+            .header("Accept", "application/json, text/event-stream")
+            .json(message)
+            .send()
+            .await
+            .map_err(|e| McpError::Http(e.to_string()))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(McpError::Http(format!("HTTP {}: {}", status, body)));
+        }
+        if let Some(session_id) = response
+            .headers()
+            .get(SESSION_HEADER)
+            .and_then(|v| v.to_str().ok())
+        {
+            *self.session_id.lock() = Some(session_id.to_string());
+        }
+        Ok(response)
+    }
+
+    async fn request(&self, method: &str, params: Value) -> Result<Value, McpError> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let message = json!({"jsonrpc": "2.0", "id": id, "method": method, "params": params});
+        let response = self.post(&message).await?;
+
+        let is_sse = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|v| v.starts_with("text/event-stream"));
+
+        if !is_sse {
+            let body: Value = response
+                .json()
+                .await
+                .map_err(|e| McpError::Protocol(format!("无效的 JSON 响应: {}", e)))?;
+            // 响应可能是单条消息或批量消息
+            let messages = match body {
+                Value::Array(messages) => messages,
+                message => vec![message],
+            };
+            return messages
+                .iter()
+                .find_map(|m| match classify(m) {
+                    Incoming::Response(rid, result) if rid == id => Some(result),
+                    _ => None,
+                })
+                .unwrap_or_else(|| Err(McpError::Protocol("响应中缺少请求结果".to_string())));
+        }
+
+        // SSE 流：逐个事件解析，收到本请求的响应后结束
+        let mut stream = response.bytes_stream();
+        let mut buffer = String::new();
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk.map_err(|e| McpError::Http(e.to_string()))?;
+            buffer.push_str(&String::from_utf8_lossy(&chunk));
+            while let Some((event, rest)) = split_event(&buffer) {
+                buffer = rest;
+                let Some(message) = parse_sse_data(&event) else {
+                    continue;
+                };
+                match classify(&message) {
+                    Incoming::Response(rid, result) if rid == id => return result,
+                    Incoming::Request(reply) => {
+                        let _ = self.post(&reply).await;
+                    }
+                    _ => {}
+                }
+            }
+        }
+        Err(McpError::Closed)
+    }
+
+    /// 结束服务器会话
+    async fn terminate_session(&self) {
+        if self.session_id.lock().is_none() {
+            return;
+        }
+        let _ = self.build(reqwest::Method::DELETE).send().await;
+        self.session_id.lock().take();
+    }
+}
+
+/// 从缓冲区切出一个完整的 SSE 事件
+fn split_event(buffer: &str) -> Option<(String, String)> {
+    let normalized;
+    let buffer = if buffer.contains('\r') {
+        normalized = buffer.replace("\r\n", "\n");
+        normalized.as_str()
+    } else {
+        buffer
+    };
+    let end = buffer.find("\n\n")?;
+    Some((buffer[..end].to_string(), buffer[end + 2..].to_string()))
+}
+
+/// 解析 SSE 事件中的 data 字段
+fn parse_sse_data(event: &str) -> Option<Value> {
+    let data: Vec<&str> = event
+        .lines()
+        .filter_map(|line| line.strip_prefix("data:"))
+        .map(|data| data.strip_prefix(' ').unwrap_or(data))
+        .collect();
+    if data.is_empty() {
+        return None;
+    }
+    serde_json::from_str(&data.join("\n")).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transport_config_from_server_config() {
+        let config = McpTransportConfig::from_server_config(&json!({
+            "command": "npx",
+            "args": ["-y", "@modelcontextprotocol/server-filesystem"],
+            "env": {"DEBUG": "1"}
+        }))
+        .unwrap();
+        match config {
+            McpTransportConfig::Stdio {
+                command, args, env, ..
+            } => {
+                assert_eq!(command, "npx");
+                assert_eq!(args.len(), 2);
+                assert_eq!(env.get("DEBUG").map(String::as_str), Some("1"));
+            }
+            _ => panic!("Expected stdio transport"),
+        }
+
+        let config = McpTransportConfig::from_server_config(&json!({
+            "type": "http",
+            "url": "https://example.com/mcp",
+            "headers": {"Authorization": "Bearer x"}
+        }))
+        .unwrap();
+        assert!(matches!(config, McpTransportConfig::Http { .. }));
+
+        assert!(McpTransportConfig::from_server_config(&json!({
+            "type": "sse",
+            "url": "https://example.com/sse"
+        }))
+        .is_err());
+        assert!(McpTransportConfig::from_server_config(&json!({})).is_err());
+    }
+
+    #[test]
+    fn test_sse_event_parsing() {
+        let buffer = "event: message\r\ndata: {\"id\":1}\r\n\r\ndata: partial";
+        let (event, rest) = split_event(buffer).unwrap();
+        assert_eq!(parse_sse_data(&event), Some(json!({"id": 1})));
+        assert_eq!(rest, "data: partial");
+        assert!(split_event(&rest).is_none());
+    }
+
+    #[test]
+    fn test_classify_server_messages() {
+        match classify(&json!({"jsonrpc": "2.0", "id": 7, "method": "ping"})) {
+            Incoming::Request(reply) => assert_eq!(reply["result"], json!({})),
+            _ => panic!("Expected request"),
+        }
+        match classify(
+            &json!({"jsonrpc": "2.0", "id": 3, "error": {"code": -1, "message": "boom"}}),
+        ) {
+            Incoming::Response(3, Err(McpError::Server { message, .. })) => {
+                assert_eq!(message, "boom")
+            }
+            _ => panic!("Expected error response"),
+        }
+    }
+}
@@ -0,0 +1,91 @@
+//! MCP 客户端类型定义
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// MCP 客户端错误
+#[derive(Debug, Error)]
+pub enum McpError {
+    /// 启动服务器进程失败
+    #[error("启动 MCP 服务器失败: {0}")]
+    Spawn(String),
+
+    /// HTTP 传输错误
+    #[error("HTTP 请求失败: {0}")]
+    Http(String),
+
+    /// 协议错误（响应格式不正确等）
+    #[error("协议错误: {0}")]
+    Protocol(String),
+
+    /// 服务器返回的 JSON-RPC 错误
+    #[error("服务器错误 ({code}): {message}")]
+    Server { code: i64, message: String },
+
+    /// 请求超时
+    #[error("请求超时: {0}")]
+    Timeout(String),
+
+    /// 连接已关闭
+    #[error("连接已关闭")]
+    Closed,
+
+    /// 不支持的配置或能力
+    #[error("不支持: {0}")]
+    Unsupported(String),
+
+    /// IO 错误
+    #[error("IO 错误: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+/// 服务器声明的能力
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct McpCapabilities {
+    #[serde(default)]
+    pub tools: bool,
+    #[serde(default)]
+    pub resources: bool,
+    #[serde(default)]
+    pub prompts: bool,
+}
+
+impl McpCapabilities {
+    /// 从 `initialize` 响应的 capabilities 对象解析
+    pub fn from_value(value: &serde_json::Value) -> Self {
+        Self {
+            tools: value.get("tools").is_some(),
+            resources: value.get("resources").is_some(),
+            prompts: value.get("prompts").is_some(),
+        }
+    }
+}
+
+/// 服务器提供的工具
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct McpToolInfo {
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub input_schema: serde_json::Value,
+}
+
+/// 服务器连接状态（供前端展示）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct McpServerStatus {
+    /// 服务器 ID（mcp_servers 表）
+    pub id: String,
+    /// 服务器名称
+    pub name: String,
+    /// 是否已连接
+    pub connected: bool,
+    /// 服务器能力
+    pub capabilities: McpCapabilities,
+    /// 注册到 Agent 的工具名称（带命名空间）
+    pub tools: Vec<String>,
+    /// 连接错误
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
@@ -6,19 +6,39 @@
 //! ## 架构设计
 //! - protocols/ - 协议策略实现（策略模式）
 //! - parsers/ - SSE 流解析器
+//! - mcp/ - MCP 客户端（外部工具、资源、提示词）
 //! - native_agent - 核心 Agent 逻辑
-//! - tool_loop - 工具调用循环
+//! - session_store - 会话持久化（SQLite）与导出
+//! - tool_loop - 工具调用循环（只读调用并行执行）
+//! - subagent - 子代理（`task` 工具，委派独立子任务）
+//! - approval - 工具调用审批（策略、用户确认、审计日志）
+//! - checkpoint/ - 工作区检查点（文件快照、diff、恢复、回退）
+//! - context - 上下文压缩（token 预算、摘要、工具输出省略）
 //! - tools/ - 工具实现
 
+pub mod approval;
+pub mod checkpoint;
+pub mod context;
+pub mod mcp;
 pub mod native_agent;
 pub mod parsers;
 pub mod protocols;
+pub mod session_store;
+pub mod subagent;
 pub mod tool_loop;
 pub mod tools;
 pub mod types;
 
+pub use approval::{
+    ApprovalDecision, PendingApproval, ToolApprovalAuditEntry, ToolApprovalManager,
+};
+pub use checkpoint::{Checkpoint, CheckpointManager, FileChange, RewindResult};
+pub use context::{CompactionPlan, ContextCompactor};
+pub use mcp::{McpClientManager, McpServerStatus};
 pub use native_agent::{NativeAgent, NativeAgentState};
 pub use parsers::{AnthropicSSEParser, OpenAISSEParser};
 pub use protocols::{create_protocol, AnthropicProtocol, OpenAIProtocol, Protocol};
+pub use session_store::{export_session, AgentSessionStore};
+pub use subagent::{TaskRequest, TaskTool};
 pub use tool_loop::{ToolCallResult, ToolLoopConfig, ToolLoopEngine, ToolLoopError, ToolLoopState};
 pub use types::*;
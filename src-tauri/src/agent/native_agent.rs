@@ -17,17 +17,25 @@
 
 #![allow(dead_code)]
 
+use crate::agent::approval::ToolApprovalManager;
+use crate::agent::checkpoint::{Checkpoint, CheckpointManager, FileChange, RewindResult};
+use crate::agent::context::{CompactionPlan, ContextCompactor};
+use crate::agent::mcp::{McpClientManager, McpServerStatus};
 use crate::agent::protocols::{create_protocol, Protocol};
+use crate::agent::session_store::{AgentSessionStore, DEFAULT_SEARCH_LIMIT};
+use crate::agent::subagent::TaskTool;
 use crate::agent::tool_loop::{ToolCallResult, ToolLoopEngine, ToolLoopState};
-use crate::agent::tools::{create_default_registry, ToolRegistry};
+use crate::agent::tools::{create_agent_registry, BashSandbox, ProcessManager, ToolRegistry};
 use crate::agent::types::*;
+use crate::database::DbConnection;
+use crate::flow_monitor::ExportFormat;
 use crate::models::openai::{
     ChatCompletionRequest, ChatCompletionResponse, ChatMessage, ContentPart as OpenAIContentPart,
     MessageContent as OpenAIMessageContent,
 };
 use parking_lot::RwLock;
 use reqwest::Client;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::mpsc;
@@ -44,6 +52,12 @@ pub struct NativeAgent {
     provider_type: ProviderType,
     /// 协议处理器
     protocol: Box<dyn Protocol>,
+    /// 会话持久化存储（未配置时会话仅保存在内存中）
+    store: Option<AgentSessionStore>,
+    /// 上下文压缩
+    compactor: Arc<ContextCompactor>,
+    /// 工作区检查点（未配置时不创建检查点）
+    checkpoints: Option<Arc<CheckpointManager>>,
 }
 
 impl NativeAgent {
@@ -76,9 +90,30 @@ impl NativeAgent {
             config: AgentConfig::default(),
             provider_type,
             protocol,
+            store: None,
+            compactor: Arc::new(ContextCompactor::default()),
+            checkpoints: None,
         })
     }
 
+    /// 启用会话持久化
+    pub fn with_store(mut self, store: AgentSessionStore) -> Self {
+        self.store = Some(store);
+        self
+    }
+
+    /// 使用共享的上下文压缩器
+    pub fn with_compactor(mut self, compactor: Arc<ContextCompactor>) -> Self {
+        self.compactor = compactor;
+        self
+    }
+
+    /// 工具执行前创建工作区检查点
+    pub fn with_checkpoints(mut self, checkpoints: Arc<CheckpointManager>) -> Self {
+        self.checkpoints = Some(checkpoints);
+        self
+    }
+
     pub fn with_model(mut self, model: String) -> Self {
         self.config.model = model;
         self
@@ -100,12 +135,11 @@ impl NativeAgent {
             model, session_id, has_images
         );
 
-        // 获取会话
-        let session = if let Some(sid) = &session_id {
-            self.sessions.read().get(sid).cloned()
-        } else {
-            None
-        };
+        // 获取会话（必要时先压缩上下文）
+        if let Some(sid) = session_id.as_deref() {
+            self.try_compact(sid, &request.message, &model, None).await;
+        }
+        let session = session_id.as_deref().and_then(|sid| self.load_session(sid));
 
         // 构建消息
         let messages = self.build_openai_messages(
@@ -217,17 +251,17 @@ impl NativeAgent {
             tools.map(|t| t.len()).unwrap_or(0)
         );
 
-        // 获取会话
-        let session = if let Some(sid) = &session_id {
-            self.sessions.read().get(sid).cloned()
-        } else {
-            None
-        };
+        // 获取会话（必要时先压缩上下文）
+        if let Some(sid) = session_id.as_deref() {
+            self.try_compact(sid, &request.message, &model, Some(&tx))
+                .await;
+        }
+        let session = session_id.as_deref().and_then(|sid| self.load_session(sid));
 
-        // 获取会话历史和配置
+        // 获取会话上下文和配置
         let history: Vec<AgentMessage> = session
             .as_ref()
-            .map(|s| s.messages.clone())
+            .map(|s| self.compactor.build_context(s))
             .unwrap_or_default();
 
         let config = if let Some(ref sess) = session {
@@ -314,11 +348,20 @@ impl NativeAgent {
                 tool_calls.len()
             );
 
+            let checkpoint = match &session_id {
+                Some(sid) => self.create_checkpoint(sid, tool_calls, &tx).await,
+                None => None,
+            };
+
             // 执行所有工具调用
             let tool_results = tool_loop_engine
                 .execute_all_tool_calls(tool_calls, Some(&tx))
                 .await;
 
+            if let Some(checkpoint) = checkpoint {
+                self.record_checkpoint_created(checkpoint).await;
+            }
+
             // 将工具结果添加到会话
             if let Some(sid) = &session_id {
                 for result in &tool_results {
@@ -390,13 +433,12 @@ impl NativeAgent {
             tools.map(|t| t.len()).unwrap_or(0)
         );
 
-        // 获取会话
+        // 获取会话（工具输出可能使上下文超出预算，必要时先压缩）
+        self.try_compact(session_id, "", &model, Some(&tx)).await;
         let session = self
-            .sessions
-            .read()
-            .get(session_id)
-            .cloned()
+            .load_session(session_id)
             .ok_or_else(|| format!("会话不存在: {}", session_id))?;
+        let history = self.compactor.build_context(&session);
 
         // 获取配置
         let config = {
@@ -414,7 +456,7 @@ impl NativeAgent {
                 &self.client,
                 &self.base_url,
                 &self.api_key,
-                &session.messages,
+                &history,
                 &model,
                 &config,
                 tools,
@@ -432,6 +474,238 @@ impl NativeAgent {
         Ok(result)
     }
 
+    // ==================== 工作区检查点 ====================
+
+    /// 工具执行前为涉及的文件创建检查点，失败时仅记录警告，不影响工具执行
+    async fn create_checkpoint(
+        &self,
+        session_id: &str,
+        tool_calls: &[ToolCall],
+        tx: &mpsc::Sender<StreamEvent>,
+    ) -> Option<Checkpoint> {
+        let manager = self.checkpoints.clone()?;
+        let message_index = self.load_session(session_id).map(|s| s.messages.len())?;
+        let sid = session_id.to_string();
+        let calls = tool_calls.to_vec();
+        // 扫描和哈希文件可能较慢，放到阻塞线程执行
+        let result =
+            tokio::task::spawn_blocking(move || manager.create(&sid, message_index, &calls)).await;
+        match result {
+            Ok(Ok(Some(checkpoint))) => {
+                let _ = tx
+                    .send(StreamEvent::CheckpointCreated {
+                        checkpoint_id: checkpoint.id.clone(),
+                        message_index,
+                        file_count: checkpoint.file_count,
+                        untracked: checkpoint.untracked,
+                    })
+                    .await;
+                Some(checkpoint)
+            }
+            Ok(Ok(None)) => None,
+            Ok(Err(e)) => {
+                warn!("[NativeAgent] 创建检查点失败: {} - {}", session_id, e);
+                None
+            }
+            Err(e) => {
+                warn!("[NativeAgent] 创建检查点任务异常: {} - {}", session_id, e);
+                None
+            }
+        }
+    }
+
+    /// 工具执行后记录新建的文件，恢复检查点时只删除这些文件
+    async fn record_checkpoint_created(&self, checkpoint: Checkpoint) {
+        let Some(manager) = self.checkpoints.clone() else {
+            return;
+        };
+        let result = tokio::task::spawn_blocking(move || manager.record_created(&checkpoint)).await;
+        match result {
+            Ok(Ok(_)) => {}
+            Ok(Err(e)) => warn!("[NativeAgent] 记录新建文件失败: {}", e),
+            Err(e) => warn!("[NativeAgent] 记录新建文件任务异常: {}", e),
+        }
+    }
+
+    /// 截断会话，只保留前 `len` 条消息，返回删除的消息数
+    pub fn truncate_session(&self, session_id: &str, len: usize) -> Result<usize, String> {
+        let mut session = self
+            .load_session(session_id)
+            .ok_or_else(|| format!("会话不存在: {}", session_id))?;
+        let removed = session.messages.len().saturating_sub(len);
+        session.messages.truncate(len);
+        session.compactions.retain(|c| c.compacted_until <= len);
+        session.updated_at = chrono::Utc::now().to_rfc3339();
+        if let Some(store) = &self.store {
+            store.truncate_messages(session_id, len)?;
+        }
+        self.sessions
+            .write()
+            .insert(session_id.to_string(), session);
+        Ok(removed)
+    }
+
+    // ==================== 上下文压缩 ====================
+
+    /// 自动压缩上下文，失败时仅记录警告，继续使用未压缩的上下文
+    async fn try_compact(
+        &self,
+        session_id: &str,
+        pending: &str,
+        model: &str,
+        tx: Option<&mpsc::Sender<StreamEvent>>,
+    ) {
+        match self
+            .compact_session(session_id, pending, model, false)
+            .await
+        {
+            Ok(Some(compaction)) => {
+                if let Some(tx) = tx {
+                    let _ = tx
+                        .send(StreamEvent::ContextCompacted {
+                            compaction_id: compaction.id.clone(),
+                            compacted_messages: compaction.compacted_until
+                                - compaction.compacted_from,
+                            tokens_before: compaction.tokens_before,
+                            tokens_after: compaction.tokens_after,
+                        })
+                        .await;
+                }
+            }
+            Ok(None) => {}
+            Err(e) => warn!("[NativeAgent] 上下文压缩失败: {} - {}", session_id, e),
+        }
+    }
+
+    /// 压缩会话上下文
+    ///
+    /// `force` 为 false 时仅在超过 token 阈值时压缩；不需要或无法压缩时返回 None。
+    pub async fn compact_session(
+        &self,
+        session_id: &str,
+        pending: &str,
+        model: &str,
+        force: bool,
+    ) -> Result<Option<ContextCompaction>, String> {
+        let session = self
+            .load_session(session_id)
+            .ok_or_else(|| format!("会话不存在: {}", session_id))?;
+        let Some(plan) = self.compactor.plan(&session, pending, force) else {
+            return Ok(None);
+        };
+
+        let settings = self.compactor.settings();
+        let summary_model = settings.summary_model.unwrap_or_else(|| model.to_string());
+        info!(
+            "[NativeAgent] 压缩上下文: session={}, messages={}..{}, tokens={}, model={}",
+            session_id,
+            plan.compacted_from,
+            plan.compacted_until,
+            plan.tokens_before,
+            summary_model
+        );
+        let summary = self
+            .summarize(&plan, &summary_model, settings.summary_max_tokens)
+            .await?;
+
+        let compaction = self
+            .compactor
+            .finish(&session, &plan, summary, summary_model, pending);
+        if let Some(store) = &self.store {
+            store.add_compaction(session_id, &compaction)?;
+        }
+        if let Some(session) = self.sessions.write().get_mut(session_id) {
+            session.compactions.push(compaction.clone());
+        }
+        info!(
+            "[NativeAgent] 上下文压缩完成: session={}, tokens {} -> {}",
+            session_id, compaction.tokens_before, compaction.tokens_after
+        );
+        Ok(Some(compaction))
+    }
+
+    /// 调用摘要模型（非流式）
+    async fn summarize(
+        &self,
+        plan: &CompactionPlan,
+        model: &str,
+        max_tokens: u32,
+    ) -> Result<String, String> {
+        let text_message = |role: &str, text: &str| ChatMessage {
+            role: role.to_string(),
+            content: Some(OpenAIMessageContent::Text(text.to_string())),
+            tool_calls: None,
+            tool_call_id: None,
+        };
+        let request = ChatCompletionRequest {
+            model: model.to_string(),
+            messages: vec![
+                text_message("system", &plan.system_prompt),
+                text_message("user", &plan.prompt),
+            ],
+            stream: false,
+            temperature: Some(0.2),
+            max_tokens: Some(max_tokens),
+            top_p: None,
+            tools: None,
+            tool_choice: None,
+            reasoning_effort: None,
+        };
+
+        let url = format!("{}/v1/chat/completions", self.base_url);
+        let response = self
+            .client
+            .post(&url)
+            .header("Authorization", format!("Bearer {}", self.api_key))
+            .header("Content-Type", "application/json")
+            .json(&request)
+            .send()
+            .await
+            .map_err(|e| format!("摘要请求失败: {}", e))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(format!("摘要 API 错误 ({}): {}", status, body));
+        }
+
+        let body: ChatCompletionResponse = response
+            .json()
+            .await
+            .map_err(|e| format!("解析摘要响应失败: {}", e))?;
+        body.choices
+            .first()
+            .and_then(|c| c.message.content.clone())
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+            .ok_or_else(|| "摘要模型返回了空内容".to_string())
+    }
+
+    /// 设置消息固定状态（固定的消息在压缩时保留原文）
+    pub fn set_message_pinned(
+        &self,
+        session_id: &str,
+        index: usize,
+        pinned: bool,
+    ) -> Result<(), String> {
+        let mut session = self
+            .load_session(session_id)
+            .ok_or_else(|| format!("会话不存在: {}", session_id))?;
+        let count = session.messages.len();
+        let message = session
+            .messages
+            .get_mut(index)
+            .ok_or_else(|| format!("消息序号越界: {} (会话共 {} 条消息)", index, count))?;
+        message.pinned = pinned;
+        if let Some(store) = &self.store {
+            store.set_pinned(session_id, index, pinned)?;
+        }
+        self.sessions
+            .write()
+            .insert(session_id.to_string(), session);
+        Ok(())
+    }
+
     // ==================== 会话管理方法 ====================
 
     /// 构建 OpenAI 格式消息（用于非流式请求）
@@ -456,9 +730,9 @@ impl NativeAgent {
             });
         }
 
-        // 历史消息
+        // 历史消息（压缩后的上下文）
         if let Some(sess) = session {
-            for msg in &sess.messages {
+            for msg in &self.compactor.build_context(sess) {
                 messages.push(self.convert_to_chat_message(msg));
             }
         }
@@ -546,34 +820,34 @@ impl NativeAgent {
         content: MessageContent,
         images: Option<&[ImageData]>,
     ) {
-        let mut sessions = self.sessions.write();
-        if let Some(session) = sessions.get_mut(session_id) {
-            let final_content = if let Some(imgs) = images {
-                let mut parts = vec![ContentPart::Text {
-                    text: content.as_text(),
-                }];
-                for img in imgs {
-                    parts.push(ContentPart::ImageUrl {
-                        image_url: ImageUrl {
-                            url: format!("data:{};base64,{}", img.media_type, img.data),
-                            detail: None,
-                        },
-                    });
-                }
-                MessageContent::Parts(parts)
-            } else {
-                content
-            };
+        let final_content = if let Some(imgs) = images {
+            let mut parts = vec![ContentPart::Text {
+                text: content.as_text(),
+            }];
+            for img in imgs {
+                parts.push(ContentPart::ImageUrl {
+                    image_url: ImageUrl {
+                        url: format!("data:{};base64,{}", img.media_type, img.data),
+                        detail: None,
+                    },
+                });
+            }
+            MessageContent::Parts(parts)
+        } else {
+            content
+        };
 
-            session.messages.push(AgentMessage {
+        self.push_message(
+            session_id,
+            AgentMessage {
                 role: role.to_string(),
                 content: final_content,
                 timestamp: chrono::Utc::now().to_rfc3339(),
                 tool_calls: None,
                 tool_call_id: None,
-            });
-            session.updated_at = chrono::Utc::now().to_rfc3339();
-        }
+                pinned: false,
+            },
+        );
     }
 
     /// 添加 assistant 消息到会话（支持工具调用）
@@ -583,25 +857,78 @@ impl NativeAgent {
         content: MessageContent,
         tool_calls: Option<Vec<ToolCall>>,
     ) {
-        let mut sessions = self.sessions.write();
-        if let Some(session) = sessions.get_mut(session_id) {
-            session.messages.push(AgentMessage {
+        self.push_message(
+            session_id,
+            AgentMessage {
                 role: "assistant".to_string(),
                 content,
                 timestamp: chrono::Utc::now().to_rfc3339(),
                 tool_calls,
                 tool_call_id: None,
-            });
-            session.updated_at = chrono::Utc::now().to_rfc3339();
-        }
+                pinned: false,
+            },
+        );
     }
 
     /// 添加工具结果消息到会话
     fn add_tool_result_to_session(&self, session_id: &str, tool_result: &ToolCallResult) {
-        let mut sessions = self.sessions.write();
-        if let Some(session) = sessions.get_mut(session_id) {
-            session.messages.push(tool_result.to_agent_message());
+        self.push_message(session_id, tool_result.to_agent_message());
+    }
+
+    /// 追加消息并写入持久化存储
+    ///
+    /// 会话不在内存缓存中时先从存储重新加载，避免丢失进行中轮次的消息；
+    /// 序号在持有锁时分配，SQLite 写入在释放锁之后进行。
+    fn push_message(&self, session_id: &str, message: AgentMessage) {
+        if self.load_session(session_id).is_none() {
+            warn!("[NativeAgent] 会话不存在，丢弃消息: {}", session_id);
+            return;
+        }
+
+        let seq = {
+            let mut sessions = self.sessions.write();
+            let Some(session) = sessions.get_mut(session_id) else {
+                warn!("[NativeAgent] 会话已删除，丢弃消息: {}", session_id);
+                return;
+            };
+            session.messages.push(message.clone());
             session.updated_at = chrono::Utc::now().to_rfc3339();
+            session.messages.len() - 1
+        };
+
+        if let Some(store) = &self.store {
+            if let Err(e) = store.append_message(session_id, seq, &message) {
+                error!("[NativeAgent] 保存会话消息失败: {} - {}", session_id, e);
+            }
+        }
+    }
+
+    /// 获取会话：优先使用内存缓存，未命中时从持久化存储加载
+    fn load_session(&self, session_id: &str) -> Option<AgentSession> {
+        if let Some(session) = self.sessions.read().get(session_id) {
+            return Some(session.clone());
+        }
+
+        let session = match self.store.as_ref()?.load(session_id) {
+            Ok(session) => session?,
+            Err(e) => {
+                error!("[NativeAgent] 加载会话失败: {} - {}", session_id, e);
+                return None;
+            }
+        };
+        let mut sessions = self.sessions.write();
+        Some(
+            sessions
+                .entry(session_id.to_string())
+                .or_insert(session)
+                .clone(),
+        )
+    }
+
+    /// 就地更新内存缓存中的会话元数据（标题、归档状态等），不影响进行中的轮次
+    pub fn update_cached_session(&self, session_id: &str, update: impl FnOnce(&mut AgentSession)) {
+        if let Some(session) = self.sessions.write().get_mut(session_id) {
+            update(session);
         }
     }
 
@@ -617,8 +944,17 @@ impl NativeAgent {
             system_prompt,
             created_at: now.clone(),
             updated_at: now,
+            title: None,
+            archived: false,
+            parent_id: None,
+            compactions: Vec::new(),
         };
 
+        if let Some(store) = &self.store {
+            if let Err(e) = store.save(&session) {
+                error!("[NativeAgent] 保存会话失败: {} - {}", session_id, e);
+            }
+        }
         self.sessions.write().insert(session_id.clone(), session);
         info!("[NativeAgent] 创建会话: {}", session_id);
 
@@ -626,11 +962,19 @@ impl NativeAgent {
     }
 
     pub fn get_session(&self, session_id: &str) -> Option<AgentSession> {
-        self.sessions.read().get(session_id).cloned()
+        self.load_session(session_id)
     }
 
     pub fn delete_session(&self, session_id: &str) -> bool {
-        self.sessions.write().remove(session_id).is_some()
+        let removed = self.sessions.write().remove(session_id).is_some();
+        let deleted = match &self.store {
+            Some(store) => store.delete(session_id).unwrap_or_else(|e| {
+                error!("[NativeAgent] 删除会话失败: {} - {}", session_id, e);
+                false
+            }),
+            None => false,
+        };
+        removed || deleted
     }
 
     pub fn list_sessions(&self) -> Vec<AgentSession> {
@@ -638,9 +982,18 @@ impl NativeAgent {
     }
 
     pub fn clear_session_messages(&self, session_id: &str) -> bool {
+        if self.load_session(session_id).is_none() {
+            return false;
+        }
+        if let Some(store) = &self.store {
+            if let Err(e) = store.clear_messages(session_id) {
+                error!("[NativeAgent] 清空会话消息失败: {} - {}", session_id, e);
+            }
+        }
         let mut sessions = self.sessions.write();
         if let Some(session) = sessions.get_mut(session_id) {
             session.messages.clear();
+            session.compactions.clear();
             session.updated_at = chrono::Utc::now().to_rfc3339();
             true
         } else {
@@ -649,10 +1002,7 @@ impl NativeAgent {
     }
 
     pub fn get_session_messages(&self, session_id: &str) -> Option<Vec<AgentMessage>> {
-        self.sessions
-            .read()
-            .get(session_id)
-            .map(|s| s.messages.clone())
+        self.load_session(session_id).map(|s| s.messages)
     }
 }
 
@@ -662,22 +1012,79 @@ impl NativeAgent {
 #[derive(Clone, Default)]
 pub struct NativeAgentState {
     agent: Arc<RwLock<Option<NativeAgent>>>,
+    /// 会话持久化存储，Agent 未初始化时也可管理历史会话
+    store: Option<AgentSessionStore>,
+    /// MCP 服务器连接，其工具会注册到每次对话的工具注册表
+    mcp: Arc<McpClientManager>,
+    /// 工具调用审批
+    approvals: Arc<ToolApprovalManager>,
+    /// 上下文压缩
+    compactor: Arc<ContextCompactor>,
+    /// 工作区检查点
+    checkpoints: Option<Arc<CheckpointManager>>,
+    /// bash 工具隔离执行
+    bash_sandbox: Arc<BashSandbox>,
+    /// 后台进程，按会话追踪
+    processes: Arc<ProcessManager>,
+    /// 通过 API Server 创建的会话（有数据库时同时持久化）
+    api_sessions: Arc<RwLock<HashSet<String>>>,
 }
 
 impl NativeAgentState {
     pub fn new() -> Self {
         Self {
             agent: Arc::new(RwLock::new(None)),
+            store: None,
+            mcp: Arc::new(McpClientManager::new()),
+            approvals: Arc::new(ToolApprovalManager::new(None)),
+            compactor: Arc::new(ContextCompactor::default()),
+            checkpoints: None,
+            bash_sandbox: Arc::new(BashSandbox::default()),
+            processes: Arc::new(ProcessManager::new()),
+            api_sessions: Arc::new(RwLock::new(HashSet::new())),
         }
     }
 
+    /// 创建使用数据库持久化会话的状态
+    pub fn with_database(db: DbConnection) -> Self {
+        Self {
+            agent: Arc::new(RwLock::new(None)),
+            store: Some(AgentSessionStore::new(db.clone())),
+            mcp: Arc::new(McpClientManager::new()),
+            approvals: Arc::new(ToolApprovalManager::new(Some(db))),
+            compactor: Arc::new(ContextCompactor::default()),
+            checkpoints: None,
+            bash_sandbox: Arc::new(BashSandbox::default()),
+            processes: Arc::new(ProcessManager::new()),
+            api_sessions: Arc::new(RwLock::new(HashSet::new())),
+        }
+    }
+
+    /// 启用工作区检查点，文件快照保存在 `objects_dir`，只追踪 `base_dir` 内的文件
+    pub fn with_checkpoints(
+        mut self,
+        db: DbConnection,
+        objects_dir: impl Into<std::path::PathBuf>,
+        base_dir: impl Into<std::path::PathBuf>,
+    ) -> Self {
+        self.checkpoints = Some(Arc::new(CheckpointManager::new(db, objects_dir, base_dir)));
+        self
+    }
+
     pub fn init(
         &self,
         base_url: String,
         api_key: String,
         provider_type: ProviderType,
     ) -> Result<(), String> {
-        let agent = NativeAgent::new(base_url, api_key, provider_type)?;
+        let mut agent = NativeAgent::new(base_url, api_key, provider_type)?
+            .with_compactor(self.compactor.clone());
+        if let Some(store) = &self.store {
+            agent = agent.with_store(store.clone());
+        }
+        if let Some(checkpoints) = &self.checkpoints {
+            agent = agent.with_checkpoints(checkpoints.clone());
+        }
         *self.agent.write() = Some(agent);
         Ok(())
     }
@@ -690,13 +1097,173 @@ impl NativeAgentState {
         *self.agent.write() = None;
     }
 
-    /// 获取工具注册表
+    /// 获取工具注册表（默认工具、MCP 工具和子代理工具）
     pub fn get_tool_registry(&self) -> Result<Arc<ToolRegistry>, String> {
-        let base_dir = dirs::home_dir().ok_or_else(|| "无法获取用户 home 目录".to_string())?;
-        let registry = create_default_registry(base_dir);
+        let registry = self.base_tool_registry()?;
+        if let Err(e) = registry.register(TaskTool::new(self.clone())) {
+            error!("[NativeAgent] 注册 TaskTool 失败: {}", e);
+        }
         Ok(Arc::new(registry))
     }
 
+    /// 创建包含默认工具和 MCP 工具的注册表（不含子代理工具）
+    pub fn base_tool_registry(&self) -> Result<ToolRegistry, String> {
+        let base_dir = dirs::home_dir().ok_or_else(|| "无法获取用户 home 目录".to_string())?;
+        let registry =
+            create_agent_registry(base_dir, self.bash_sandbox.clone(), self.processes.clone());
+        self.mcp.register_tools(&registry);
+        Ok(registry)
+    }
+
+    /// 连接数据库中启用的 MCP 服务器（已有连接会先断开）
+    pub async fn connect_mcp_servers(
+        &self,
+        db: &DbConnection,
+    ) -> Result<Vec<McpServerStatus>, String> {
+        self.mcp.connect_all(db).await
+    }
+
+    /// MCP 服务器连接状态
+    pub fn mcp_status(&self) -> Vec<McpServerStatus> {
+        self.mcp.status()
+    }
+
+    /// 工具调用审批管理器
+    pub fn approvals(&self) -> Arc<ToolApprovalManager> {
+        self.approvals.clone()
+    }
+
+    /// 上下文压缩器
+    pub fn compactor(&self) -> Arc<ContextCompactor> {
+        self.compactor.clone()
+    }
+
+    /// bash 工具隔离配置
+    pub fn bash_sandbox(&self) -> Arc<BashSandbox> {
+        self.bash_sandbox.clone()
+    }
+
+    /// 后台进程管理器
+    pub fn processes(&self) -> Arc<ProcessManager> {
+        self.processes.clone()
+    }
+
+    /// 手动压缩会话上下文（忽略阈值，仍保留最近几轮对话）
+    pub async fn compact_session(&self, session_id: &str) -> Result<ContextCompaction, String> {
+        let temp_agent = self.create_temp_agent()?;
+        let model = temp_agent
+            .get_session(session_id)
+            .map(|s| s.model)
+            .ok_or_else(|| format!("会话不存在: {}", session_id))?;
+        temp_agent
+            .compact_session(session_id, "", &model, true)
+            .await?
+            .ok_or_else(|| "没有可压缩的消息".to_string())
+    }
+
+    /// 固定 / 取消固定消息
+    pub fn set_message_pinned(
+        &self,
+        session_id: &str,
+        index: usize,
+        pinned: bool,
+    ) -> Result<(), String> {
+        let guard = self.agent.read();
+        match (guard.as_ref(), &self.store) {
+            (Some(agent), _) => agent.set_message_pinned(session_id, index, pinned),
+            (None, Some(store)) => store
+                .set_pinned(session_id, index, pinned)?
+                .then_some(())
+                .ok_or_else(|| format!("消息不存在: {}#{}", session_id, index)),
+            (None, None) => Err("Agent 未初始化".to_string()),
+        }
+    }
+
+    /// 列出会话的检查点
+    pub fn list_checkpoints(&self, session_id: &str) -> Result<Vec<Checkpoint>, String> {
+        self.require_checkpoints()?
+            .list(session_id)
+            .map_err(|e| e.to_string())
+    }
+
+    /// 检查点以来 Agent 涉及文件的全部变更
+    pub fn checkpoint_diff(
+        &self,
+        session_id: &str,
+        checkpoint_id: &str,
+    ) -> Result<Vec<FileChange>, String> {
+        self.require_checkpoints()?
+            .diff(session_id, checkpoint_id)
+            .map_err(|e| e.to_string())
+    }
+
+    /// 将文件恢复到检查点时的状态（`paths` 为空时恢复全部文件），不修改会话
+    pub fn restore_checkpoint_files(
+        &self,
+        session_id: &str,
+        checkpoint_id: &str,
+        paths: Option<&[String]>,
+    ) -> Result<Vec<String>, String> {
+        self.require_checkpoints()?
+            .restore(session_id, checkpoint_id, paths)
+            .map_err(|e| e.to_string())
+    }
+
+    /// 将会话和文件一起回退到第 `message_index` 条（user）消息之前
+    ///
+    /// 该消息及之后的消息被删除，之后创建的检查点中的文件恢复到执行前的状态。
+    pub fn rewind_session(
+        &self,
+        session_id: &str,
+        message_index: usize,
+    ) -> Result<RewindResult, String> {
+        let checkpoints = self.require_checkpoints()?;
+        let session = self
+            .get_session(session_id)?
+            .ok_or_else(|| format!("会话不存在: {}", session_id))?;
+        let message = session.messages.get(message_index).ok_or_else(|| {
+            format!(
+                "消息序号越界: {} (会话共 {} 条消息)",
+                message_index,
+                session.messages.len()
+            )
+        })?;
+        if message.role != "user" {
+            return Err(format!(
+                "只能回退到用户消息: #{} 为 {}",
+                message_index, message.role
+            ));
+        }
+
+        let (restored_files, removed_checkpoints) = checkpoints
+            .rewind(session_id, message_index)
+            .map_err(|e| e.to_string())?;
+        let guard = self.agent.read();
+        let removed_messages = match (guard.as_ref(), &self.store) {
+            (Some(agent), _) => agent.truncate_session(session_id, message_index)?,
+            (None, Some(store)) => store.truncate_messages(session_id, message_index)?,
+            (None, None) => return Err("Agent 未初始化".to_string()),
+        };
+        info!(
+            "[NativeAgent] 回退会话 {} 到消息 #{}: 删除 {} 条消息，恢复 {} 个文件",
+            session_id,
+            message_index,
+            removed_messages,
+            restored_files.len()
+        );
+        Ok(RewindResult {
+            restored_files,
+            removed_messages,
+            removed_checkpoints,
+        })
+    }
+
+    fn require_checkpoints(&self) -> Result<&CheckpointManager, String> {
+        self.checkpoints
+            .as_deref()
+            .ok_or_else(|| "工作区检查点未启用".to_string())
+    }
+
     /// 创建临时 Agent 用于异步操作
     fn create_temp_agent(&self) -> Result<NativeAgent, String> {
         let guard = self.agent.read();
@@ -719,9 +1286,21 @@ impl NativeAgentState {
             config: agent.config.clone(),
             provider_type: agent.provider_type,
             protocol,
+            store: agent.store.clone(),
+            compactor: agent.compactor.clone(),
+            checkpoints: agent.checkpoints.clone(),
         })
     }
 
+    /// 创建子代理：与当前 Agent 使用相同的 API 配置，会话仅保存在内存中，不创建检查点
+    pub fn create_subagent(&self) -> Result<NativeAgent, String> {
+        let mut agent = self.create_temp_agent()?;
+        agent.sessions = Arc::new(RwLock::new(HashMap::new()));
+        agent.store = None;
+        agent.checkpoints = None;
+        Ok(agent)
+    }
+
     pub async fn chat(&self, request: NativeChatRequest) -> Result<NativeChatResponse, String> {
         let temp_agent = self.create_temp_agent()?;
         temp_agent.chat(request).await
@@ -758,27 +1337,138 @@ impl NativeAgentState {
         Ok(agent.create_session(model, system_prompt))
     }
 
+    /// 创建 API 会话，Agent API 只能操作通过它创建的会话
+    pub fn create_api_session(
+        &self,
+        model: Option<String>,
+        system_prompt: Option<String>,
+    ) -> Result<String, String> {
+        let session_id = self.create_session(model, system_prompt)?;
+        if let Some(store) = &self.store {
+            store.mark_api_session(&session_id)?;
+        }
+        self.api_sessions.write().insert(session_id.clone());
+        Ok(session_id)
+    }
+
+    /// 会话是否通过 API Server 创建
+    pub fn is_api_session(&self, session_id: &str) -> bool {
+        if self.api_sessions.read().contains(session_id) {
+            return true;
+        }
+        match &self.store {
+            Some(store) => store.is_api_session(session_id).unwrap_or_else(|e| {
+                warn!("[NativeAgent] 查询 API 会话失败: {} - {}", session_id, e);
+                false
+            }),
+            None => false,
+        }
+    }
+
     pub fn get_session(&self, session_id: &str) -> Result<Option<AgentSession>, String> {
         let guard = self.agent.read();
-        let agent = guard.as_ref().ok_or_else(|| "Agent 未初始化".to_string())?;
-        Ok(agent.get_session(session_id))
+        match (guard.as_ref(), &self.store) {
+            (Some(agent), _) => Ok(agent.get_session(session_id)),
+            (None, Some(store)) => store.load(session_id),
+            (None, None) => Err("Agent 未初始化".to_string()),
+        }
     }
 
     pub fn delete_session(&self, session_id: &str) -> bool {
+        self.approvals.clear_session_grants(session_id);
+        self.api_sessions.write().remove(session_id);
+        self.processes.cleanup_session(session_id);
+        if let Some(checkpoints) = &self.checkpoints {
+            if let Err(e) = checkpoints.delete_session(session_id) {
+                warn!("[NativeAgent] 删除会话检查点失败: {} - {}", session_id, e);
+            }
+        }
         let guard = self.agent.read();
-        if let Some(agent) = guard.as_ref() {
-            agent.delete_session(session_id)
-        } else {
-            false
+        match (guard.as_ref(), &self.store) {
+            (Some(agent), _) => agent.delete_session(session_id),
+            (None, Some(store)) => store.delete(session_id).unwrap_or(false),
+            (None, None) => false,
         }
     }
 
-    pub fn list_sessions(&self) -> Vec<AgentSession> {
+    /// 列出会话摘要，按最后活动时间倒序
+    pub fn list_sessions(
+        &self,
+        include_archived: bool,
+    ) -> Result<Vec<AgentSessionSummary>, String> {
+        if let Some(store) = &self.store {
+            return store.list(include_archived);
+        }
         let guard = self.agent.read();
-        if let Some(agent) = guard.as_ref() {
-            agent.list_sessions()
-        } else {
-            Vec::new()
+        let mut sessions: Vec<AgentSessionSummary> = guard
+            .as_ref()
+            .map(|agent| agent.list_sessions())
+            .unwrap_or_default()
+            .iter()
+            .filter(|s| include_archived || !s.archived)
+            .map(AgentSessionSummary::from)
+            .collect();
+        sessions.sort_by(|a, b| b.updated_at.cmp(&a.updated_at));
+        Ok(sessions)
+    }
+
+    /// 跨会话搜索
+    pub fn search_sessions(
+        &self,
+        query: &str,
+        include_archived: bool,
+        limit: Option<usize>,
+    ) -> Result<Vec<AgentSessionSearchHit>, String> {
+        self.require_store()?.search(
+            query,
+            include_archived,
+            limit.unwrap_or(DEFAULT_SEARCH_LIMIT),
+        )
+    }
+
+    /// 从指定消息处分叉会话
+    pub fn fork_session(
+        &self,
+        session_id: &str,
+        message_index: usize,
+    ) -> Result<AgentSession, String> {
+        self.require_store()?.fork(session_id, message_index)
+    }
+
+    pub fn rename_session(&self, session_id: &str, title: Option<&str>) -> Result<bool, String> {
+        let renamed = self.require_store()?.rename(session_id, title)?;
+        if renamed {
+            let title = title.map(str::to_string);
+            self.update_cached_session(session_id, |session| session.title = title);
+        }
+        Ok(renamed)
+    }
+
+    pub fn archive_session(&self, session_id: &str, archived: bool) -> Result<bool, String> {
+        let updated = self.require_store()?.set_archived(session_id, archived)?;
+        if archived {
+            self.processes.cleanup_session(session_id);
+        }
+        if updated {
+            self.update_cached_session(session_id, |session| session.archived = archived);
+        }
+        Ok(updated)
+    }
+
+    /// 导出会话（JSON、JSONL 或 Markdown）
+    pub fn export_session(&self, session_id: &str, format: ExportFormat) -> Result<String, String> {
+        self.require_store()?.export(session_id, format)
+    }
+
+    fn require_store(&self) -> Result<&AgentSessionStore, String> {
+        self.store
+            .as_ref()
+            .ok_or_else(|| "会话持久化未启用".to_string())
+    }
+
+    fn update_cached_session(&self, session_id: &str, update: impl FnOnce(&mut AgentSession)) {
+        if let Some(agent) = self.agent.read().as_ref() {
+            agent.update_cached_session(session_id, update);
         }
     }
 
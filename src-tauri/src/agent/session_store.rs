@@ -0,0 +1,349 @@
+//! Agent 会话持久化
+//!
+//! 将原生 Agent 会话保存到 SQLite 数据库（见 `database::dao::agent_sessions`），
+//! 并提供与 Flow 导出一致的 JSON / JSONL / Markdown 导出格式。
+
+use crate::agent::types::*;
+use crate::database::dao::agent_sessions::AgentSessionDao;
+use crate::database::DbConnection;
+use crate::flow_monitor::ExportFormat;
+
+/// 搜索结果默认数量上限
+pub const DEFAULT_SEARCH_LIMIT: usize = 50;
+
+/// Agent 会话存储
+#[derive(Clone)]
+pub struct AgentSessionStore {
+    db: DbConnection,
+}
+
+impl AgentSessionStore {
+    pub fn new(db: DbConnection) -> Self {
+        Self { db }
+    }
+
+    fn with_conn<T>(
+        &self,
+        f: impl FnOnce(&rusqlite::Connection) -> Result<T, String>,
+    ) -> Result<T, String> {
+        let conn = self.db.lock().map_err(|e| format!("数据库锁错误: {}", e))?;
+        f(&conn)
+    }
+
+    /// 保存会话（包括全部消息）
+    pub fn save(&self, session: &AgentSession) -> Result<(), String> {
+        self.with_conn(|conn| AgentSessionDao::save(conn, session).map_err(|e| e.to_string()))
+    }
+
+    /// 追加消息
+    pub fn append_message(
+        &self,
+        session_id: &str,
+        seq: usize,
+        message: &AgentMessage,
+    ) -> Result<(), String> {
+        self.with_conn(|conn| {
+            AgentSessionDao::append_message(conn, session_id, seq, message)
+                .map_err(|e| e.to_string())
+        })
+    }
+
+    /// 设置消息固定状态
+    pub fn set_pinned(&self, session_id: &str, seq: usize, pinned: bool) -> Result<bool, String> {
+        self.with_conn(|conn| {
+            AgentSessionDao::set_pinned(conn, session_id, seq, pinned).map_err(|e| e.to_string())
+        })
+    }
+
+    /// 追加上下文压缩记录
+    pub fn add_compaction(
+        &self,
+        session_id: &str,
+        compaction: &ContextCompaction,
+    ) -> Result<(), String> {
+        self.with_conn(|conn| {
+            AgentSessionDao::add_compaction(conn, session_id, compaction).map_err(|e| e.to_string())
+        })
+    }
+
+    pub fn load(&self, session_id: &str) -> Result<Option<AgentSession>, String> {
+        self.with_conn(|conn| AgentSessionDao::get(conn, session_id))
+    }
+
+    pub fn list(&self, include_archived: bool) -> Result<Vec<AgentSessionSummary>, String> {
+        self.with_conn(|conn| {
+            AgentSessionDao::list(conn, include_archived).map_err(|e| e.to_string())
+        })
+    }
+
+    pub fn search(
+        &self,
+        query: &str,
+        include_archived: bool,
+        limit: usize,
+    ) -> Result<Vec<AgentSessionSearchHit>, String> {
+        self.with_conn(|conn| {
+            AgentSessionDao::search(conn, query, include_archived, limit).map_err(|e| e.to_string())
+        })
+    }
+
+    /// 从指定消息处分叉会话，返回新会话
+    pub fn fork(&self, session_id: &str, message_index: usize) -> Result<AgentSession, String> {
+        let new_id = uuid::Uuid::new_v4().to_string();
+        let now = chrono::Utc::now().to_rfc3339();
+        self.with_conn(|conn| {
+            AgentSessionDao::fork(conn, session_id, message_index, &new_id, &now)?
+                .ok_or_else(|| format!("会话不存在: {}", session_id))
+        })
+    }
+
+    pub fn rename(&self, session_id: &str, title: Option<&str>) -> Result<bool, String> {
+        self.with_conn(|conn| {
+            AgentSessionDao::rename(conn, session_id, title).map_err(|e| e.to_string())
+        })
+    }
+
+    pub fn set_archived(&self, session_id: &str, archived: bool) -> Result<bool, String> {
+        self.with_conn(|conn| {
+            AgentSessionDao::set_archived(conn, session_id, archived).map_err(|e| e.to_string())
+        })
+    }
+
+    pub fn clear_messages(&self, session_id: &str) -> Result<bool, String> {
+        let now = chrono::Utc::now().to_rfc3339();
+        self.with_conn(|conn| {
+            AgentSessionDao::clear_messages(conn, session_id, &now).map_err(|e| e.to_string())
+        })
+    }
+
+    /// 截断会话，只保留前 `len` 条消息，返回删除的消息数
+    pub fn truncate_messages(&self, session_id: &str, len: usize) -> Result<usize, String> {
+        let now = chrono::Utc::now().to_rfc3339();
+        self.with_conn(|conn| {
+            AgentSessionDao::truncate_messages(conn, session_id, len, &now)
+                .map_err(|e| e.to_string())
+        })
+    }
+
+    /// 记录通过 API Server 创建的会话
+    pub fn mark_api_session(&self, session_id: &str) -> Result<(), String> {
+        let now = chrono::Utc::now().to_rfc3339();
+        self.with_conn(|conn| {
+            AgentSessionDao::mark_api_session(conn, session_id, &now).map_err(|e| e.to_string())
+        })
+    }
+
+    pub fn is_api_session(&self, session_id: &str) -> Result<bool, String> {
+        self.with_conn(|conn| {
+            AgentSessionDao::is_api_session(conn, session_id).map_err(|e| e.to_string())
+        })
+    }
+
+    pub fn delete(&self, session_id: &str) -> Result<bool, String> {
+        self.with_conn(|conn| AgentSessionDao::delete(conn, session_id).map_err(|e| e.to_string()))
+    }
+
+    /// 导出会话
+    pub fn export(&self, session_id: &str, format: ExportFormat) -> Result<String, String> {
+        let session = self
+            .load(session_id)?
+            .ok_or_else(|| format!("会话不存在: {}", session_id))?;
+        export_session(&session, format)
+    }
+}
+
+/// 导出会话
+///
+/// 支持 JSON、JSONL（每行一条消息）和 Markdown，HAR 和 CSV 仅适用于 Flow。
+pub fn export_session(session: &AgentSession, format: ExportFormat) -> Result<String, String> {
+    match format {
+        ExportFormat::JSON => serde_json::to_string_pretty(session).map_err(|e| e.to_string()),
+        ExportFormat::JSONL => Ok(session
+            .messages
+            .iter()
+            .filter_map(|m| serde_json::to_string(m).ok())
+            .collect::<Vec<_>>()
+            .join("\n")),
+        ExportFormat::Markdown => Ok(session_to_markdown(session)),
+        ExportFormat::HAR | ExportFormat::CSV => {
+            Err(format!("Agent 会话不支持导出为 {:?} 格式", format))
+        }
+    }
+}
+
+/// 将会话转换为 Markdown
+fn session_to_markdown(session: &AgentSession) -> String {
+    let mut md = String::new();
+
+    // 标题
+    md.push_str(&format!(
+        "# Agent 会话: {}\n\n",
+        session.title.as_deref().unwrap_or(&session.id)
+    ));
+
+    // 元信息
+    md.push_str("## 基本信息\n\n");
+    md.push_str(&format!("- **会话 ID**: `{}`\n", session.id));
+    md.push_str(&format!("- **模型**: {}\n", session.model));
+    md.push_str(&format!("- **创建时间**: {}\n", session.created_at));
+    md.push_str(&format!("- **最后活动**: {}\n", session.updated_at));
+    md.push_str(&format!("- **消息数**: {}\n", session.messages.len()));
+    if let Some(ref parent) = session.parent_id {
+        md.push_str(&format!("- **分叉自**: `{}`\n", parent));
+    }
+    if session.archived {
+        md.push_str("- **已归档**\n");
+    }
+    md.push('\n');
+
+    // 系统提示词
+    if let Some(ref system) = session.system_prompt {
+        md.push_str("## 系统提示词\n\n");
+        md.push_str("```\n");
+        md.push_str(system);
+        md.push_str("\n```\n\n");
+    }
+
+    // 上下文压缩记录
+    if !session.compactions.is_empty() {
+        md.push_str("## 上下文压缩\n\n");
+        for compaction in &session.compactions {
+            md.push_str(&format!(
+                "### 消息 {}-{}（{}，{} → {} tokens）\n\n",
+                compaction.compacted_from + 1,
+                compaction.compacted_until,
+                compaction.model,
+                compaction.tokens_before,
+                compaction.tokens_after
+            ));
+            md.push_str(&format!("- **时间**: {}\n\n", compaction.created_at));
+            md.push_str(&compaction.summary);
+            md.push_str("\n\n");
+        }
+    }
+
+    // 消息
+    if !session.messages.is_empty() {
+        md.push_str("## 消息\n\n");
+        for (i, msg) in session.messages.iter().enumerate() {
+            md.push_str(&format!("#### {} {}\n\n", i + 1, msg.role.to_uppercase()));
+            if msg.pinned {
+                md.push_str("- **已固定**\n\n");
+            }
+            if let Some(ref tool_call_id) = msg.tool_call_id {
+                md.push_str(&format!("- **工具调用 ID**: `{}`\n\n", tool_call_id));
+            }
+            let content = msg.content.as_text();
+            if !content.is_empty() {
+                md.push_str("```\n");
+                md.push_str(&content);
+                md.push_str("\n```\n\n");
+            }
+
+            // 工具调用
+            for tc in msg.tool_calls.iter().flatten() {
+                md.push_str(&format!("- **ID**: `{}`\n", tc.id));
+                md.push_str(&format!("- **函数**: `{}`\n", tc.function.name));
+                md.push_str("- **参数**:\n");
+                md.push_str("```json\n");
+                // 尝试格式化 JSON
+                match serde_json::from_str::<serde_json::Value>(&tc.function.arguments) {
+                    Ok(parsed) => md.push_str(
+                        &serde_json::to_string_pretty(&parsed)
+                            .unwrap_or(tc.function.arguments.clone()),
+                    ),
+                    Err(_) => md.push_str(&tc.function.arguments),
+                }
+                md.push_str("\n```\n\n");
+            }
+        }
+    }
+
+    md
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::schema::create_agent_session_tables;
+    use std::sync::{Arc, Mutex};
+
+    fn create_test_store() -> AgentSessionStore {
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        create_agent_session_tables(&conn).unwrap();
+        AgentSessionStore::new(Arc::new(Mutex::new(conn)))
+    }
+
+    fn create_test_session() -> AgentSession {
+        AgentSession {
+            id: "s1".to_string(),
+            model: "gpt-4o".to_string(),
+            messages: vec![
+                AgentMessage {
+                    role: "user".to_string(),
+                    content: MessageContent::Text("列出文件".to_string()),
+                    timestamp: "2025-01-01T00:00:00+00:00".to_string(),
+                    tool_calls: None,
+                    tool_call_id: None,
+                    pinned: false,
+                },
+                AgentMessage {
+                    role: "assistant".to_string(),
+                    content: MessageContent::Text(String::new()),
+                    timestamp: "2025-01-01T00:00:01+00:00".to_string(),
+                    tool_calls: Some(vec![ToolCall {
+                        id: "call_1".to_string(),
+                        call_type: "function".to_string(),
+                        function: FunctionCall {
+                            name: "bash".to_string(),
+                            arguments: r#"{"command":"ls"}"#.to_string(),
+                        },
+                    }]),
+                    tool_call_id: None,
+                    pinned: false,
+                },
+            ],
+            system_prompt: Some("你是一个有帮助的助手".to_string()),
+            created_at: "2025-01-01T00:00:00+00:00".to_string(),
+            updated_at: "2025-01-01T00:00:01+00:00".to_string(),
+            title: Some("文件列表".to_string()),
+            archived: false,
+            parent_id: None,
+            compactions: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_export_formats() {
+        let store = create_test_store();
+        store.save(&create_test_session()).unwrap();
+
+        let md = store.export("s1", ExportFormat::Markdown).unwrap();
+        assert!(md.starts_with("# Agent 会话: 文件列表"));
+        assert!(md.contains("#### 1 USER"));
+        assert!(md.contains("- **函数**: `bash`"));
+        assert!(md.contains("\"command\": \"ls\""));
+
+        let json: serde_json::Value =
+            serde_json::from_str(&store.export("s1", ExportFormat::JSON).unwrap()).unwrap();
+        assert_eq!(json["messages"].as_array().unwrap().len(), 2);
+
+        let jsonl = store.export("s1", ExportFormat::JSONL).unwrap();
+        assert_eq!(jsonl.lines().count(), 2);
+
+        assert!(store.export("s1", ExportFormat::HAR).is_err());
+        assert!(store.export("missing", ExportFormat::JSON).is_err());
+    }
+
+    #[test]
+    fn test_fork_creates_new_session() {
+        let store = create_test_store();
+        store.save(&create_test_session()).unwrap();
+
+        let forked = store.fork("s1", 0).unwrap();
+        assert_ne!(forked.id, "s1");
+        assert_eq!(forked.messages.len(), 1);
+        assert_eq!(store.list(false).unwrap().len(), 2);
+        assert!(store.fork("missing", 0).is_err());
+    }
+}
@@ -0,0 +1,435 @@
+//! 子代理模块
+//!
+//! 提供 `task` 工具：为一项独立的子任务启动子代理会话，子代理使用自己的模型、
+//! 系统提示词、受限的工具子集和迭代预算完成任务，最终回复作为工具结果返回给父代理。
+//!
+//! ## 设计
+//! - 子代理会话只保存在内存中，不持久化、不创建检查点
+//! - 子代理不能再使用 `task` 工具（委派深度为 1）
+//! - 子代理的工具调用沿用父会话的审批策略（包括 HTTP 会话的 `require_rule_for_writes`）
+//! - `task` 调用的审批对象是子代理的工具子集，只读委派的会话批准不覆盖可写委派
+//! - 子代理的流式事件包装为 `StreamEvent::SubAgent` 转发给父代理的事件通道
+//! - 子代理启动的后台进程在任务结束时终止
+
+use crate::agent::native_agent::NativeAgentState;
+use crate::agent::tool_loop::{ToolLoopConfig, ToolLoopEngine};
+use crate::agent::tools::{
+    JsonSchema, PropertySchema, Tool, ToolContext, ToolDefinition, ToolError, ToolRegistry,
+    ToolResult,
+};
+use crate::agent::types::{NativeChatRequest, StreamEvent};
+use async_trait::async_trait;
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+
+/// 工具名称
+pub const TASK_TOOL_NAME: &str = "task";
+
+/// 未指定工具时子代理可用的工具（只读）
+pub const DEFAULT_SUBAGENT_TOOLS: &[&str] = &["read_file", "grep", "glob", "list_directory"];
+
+/// 默认迭代预算
+const DEFAULT_MAX_ITERATIONS: usize = 10;
+
+/// 迭代预算上限
+const MAX_ITERATIONS_LIMIT: usize = 25;
+
+/// 返回给父代理的结果最大字符数
+const MAX_RESULT_CHARS: usize = 20_000;
+
+/// 子代理默认系统提示词
+const DEFAULT_SUBAGENT_PROMPT: &str = "You are a sub-agent working on a single task delegated \
+by another agent. Use the available tools to complete the task autonomously; you cannot ask \
+the user questions. When you are done, reply with a concise report of what you found or did, \
+including the relevant file paths and any facts the delegating agent needs. That report is the \
+only thing the delegating agent will see.";
+
+/// 子代理任务参数
+#[derive(Debug, Clone, PartialEq)]
+pub struct TaskRequest {
+    /// 简短的任务描述（用于显示）
+    pub description: String,
+    /// 交给子代理的完整任务说明
+    pub prompt: String,
+    /// 模型（为空时使用父会话的模型）
+    pub model: Option<String>,
+    /// 系统提示词（为空时使用默认子代理提示词）
+    pub system_prompt: Option<String>,
+    /// 可用工具（为空时使用 `DEFAULT_SUBAGENT_TOOLS`）
+    pub tools: Option<Vec<String>>,
+    /// 最大工具循环迭代次数
+    pub max_iterations: usize,
+}
+
+impl TaskRequest {
+    /// 从工具参数解析
+    pub fn from_args(args: &serde_json::Value) -> Result<Self, ToolError> {
+        let required = |key: &str| {
+            args.get(key)
+                .and_then(|v| v.as_str())
+                .map(str::trim)
+                .filter(|s| !s.is_empty())
+                .map(str::to_string)
+                .ok_or_else(|| ToolError::InvalidArguments(format!("缺少 {} 参数", key)))
+        };
+        let optional = |key: &str| {
+            args.get(key)
+                .and_then(|v| v.as_str())
+                .map(str::trim)
+                .filter(|s| !s.is_empty())
+                .map(str::to_string)
+        };
+        let tools = match args.get("tools") {
+            None | Some(serde_json::Value::Null) => None,
+            Some(serde_json::Value::Array(items)) => Some(
+                items
+                    .iter()
+                    .map(|v| {
+                        v.as_str().map(str::to_string).ok_or_else(|| {
+                            ToolError::InvalidArguments("tools 必须是工具名称数组".to_string())
+                        })
+                    })
+                    .collect::<Result<Vec<_>, _>>()?,
+            ),
+            Some(_) => {
+                return Err(ToolError::InvalidArguments(
+                    "tools 必须是工具名称数组".to_string(),
+                ))
+            }
+        };
+
+        Ok(Self {
+            description: required("description")?,
+            prompt: required("prompt")?,
+            model: optional("model"),
+            system_prompt: optional("system_prompt"),
+            tools,
+            max_iterations: args
+                .get("max_iterations")
+                .and_then(|v| v.as_u64())
+                .map(|n| (n as usize).clamp(1, MAX_ITERATIONS_LIMIT))
+                .unwrap_or(DEFAULT_MAX_ITERATIONS),
+        })
+    }
+
+    /// 子代理可用的工具名称
+    pub fn tool_names(&self) -> Vec<String> {
+        match &self.tools {
+            Some(tools) => tools.clone(),
+            None => DEFAULT_SUBAGENT_TOOLS
+                .iter()
+                .map(|s| s.to_string())
+                .collect(),
+        }
+    }
+}
+
+/// 将注册表限制为指定的工具子集（始终移除 `task`，禁止嵌套委派）
+///
+/// 请求了不存在的工具时返回错误。
+pub fn restrict_registry(registry: &ToolRegistry, tools: &[String]) -> Result<(), ToolError> {
+    let unknown: Vec<&str> = tools
+        .iter()
+        .map(String::as_str)
+        .filter(|name| *name == TASK_TOOL_NAME || !registry.contains(name))
+        .collect();
+    if !unknown.is_empty() {
+        let mut available = registry.list_names();
+        available.retain(|name| name != TASK_TOOL_NAME);
+        available.sort();
+        return Err(ToolError::InvalidArguments(format!(
+            "子代理不能使用以下工具: {}（可用工具: {}）",
+            unknown.join(", "),
+            available.join(", ")
+        )));
+    }
+    registry.retain(|name| tools.iter().any(|t| t == name));
+    Ok(())
+}
+
+/// 子代理工具
+pub struct TaskTool {
+    state: NativeAgentState,
+}
+
+impl TaskTool {
+    /// 创建子代理工具
+    pub fn new(state: NativeAgentState) -> Self {
+        Self { state }
+    }
+
+    /// 运行子代理，返回其最终回复
+    pub async fn run(
+        &self,
+        task: TaskRequest,
+        context: &ToolContext,
+    ) -> Result<ToolResult, ToolError> {
+        let registry = self
+            .state
+            .base_tool_registry()
+            .map_err(ToolError::ExecutionFailed)?;
+        restrict_registry(&registry, &task.tool_names())?;
+
+        let agent = self
+            .state
+            .create_subagent()
+            .map_err(ToolError::ExecutionFailed)?;
+        let model = task.model.clone().or_else(|| {
+            let parent = context.session_id.as_deref()?;
+            self.state
+                .get_session(parent)
+                .ok()
+                .flatten()
+                .map(|s| s.model)
+        });
+        let system_prompt = task
+            .system_prompt
+            .clone()
+            .unwrap_or_else(|| DEFAULT_SUBAGENT_PROMPT.to_string());
+        let session_id = agent.create_session(model.clone(), Some(system_prompt));
+
+        info!(
+            "[TaskTool] 启动子代理: session={}, parent={:?}, model={:?}, tools={:?}, max_iterations={}, task={}",
+            session_id,
+            context.session_id,
+            model,
+            registry.list_names(),
+            task.max_iterations,
+            task.description
+        );
+
+        // 子代理沿用父会话的审批策略：同一审批管理器、同一会话的批准记录和规则要求
+        let mut engine = ToolLoopEngine::with_config(
+            Arc::new(registry),
+            ToolLoopConfig::new(task.max_iterations),
+        )
+        .with_approvals(self.state.approvals(), context.session_id.clone());
+        if context.require_rule_for_writes {
+            engine = engine.require_rule_for_writes();
+        }
+
+        // 将子代理事件包装后转发给父代理，同时统计工具调用次数
+        let (tx, mut rx) = mpsc::channel::<StreamEvent>(100);
+        let parent_tx = context.event_tx.clone();
+        let parent_tool_id = context.tool_call_id.clone();
+        let child_session_id = session_id.clone();
+        let forwarder = tokio::spawn(async move {
+            let mut tool_calls = 0usize;
+            while let Some(event) = rx.recv().await {
+                if matches!(event, StreamEvent::ToolStart { .. }) {
+                    tool_calls += 1;
+                }
+                if let Some(parent_tx) = &parent_tx {
+                    let _ = parent_tx
+                        .send(StreamEvent::SubAgent {
+                            parent_tool_id: parent_tool_id.clone(),
+                            session_id: child_session_id.clone(),
+                            event: Box::new(event),
+                        })
+                        .await;
+                }
+            }
+            tool_calls
+        });
+
+        let request = NativeChatRequest {
+            session_id: Some(session_id.clone()),
+            message: task.prompt.clone(),
+            model: model.clone(),
+            images: None,
+            stream: true,
+        };
+        let result = agent.chat_stream_with_tools(request, tx, &engine).await;
+        // 子代理会话随任务结束，其后台进程一并终止
+        self.state.processes().cleanup_session(&session_id);
+        let tool_calls = forwarder.await.unwrap_or_else(|e| {
+            warn!("[TaskTool] 子代理事件转发异常: {}", e);
+            0
+        });
+
+        let result =
+            result.map_err(|e| ToolError::ExecutionFailed(format!("子代理失败: {}", e)))?;
+        let content = result.content.trim();
+        info!(
+            "[TaskTool] 子代理完成: session={}, tool_calls={}, content_len={}",
+            session_id,
+            tool_calls,
+            content.len()
+        );
+        if content.is_empty() {
+            return Ok(ToolResult::failure(format!(
+                "子代理未返回结果（执行了 {} 次工具调用）",
+                tool_calls
+            )));
+        }
+        Ok(ToolResult::success(format_report(content, tool_calls)))
+    }
+}
+
+/// 格式化子代理报告（过长时截断）
+fn format_report(content: &str, tool_calls: usize) -> String {
+    let mut report = if content.chars().count() > MAX_RESULT_CHARS {
+        let truncated: String = content.chars().take(MAX_RESULT_CHARS).collect();
+        format!("{}\n\n[子代理报告过长，已截断]", truncated)
+    } else {
+        content.to_string()
+    };
+    report.push_str(&format!("\n\n[子代理完成，共 {} 次工具调用]", tool_calls));
+    report
+}
+
+#[async_trait]
+impl Tool for TaskTool {
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition::new(
+            TASK_TOOL_NAME,
+            "Delegate a self-contained task to a sub-agent. The sub-agent starts with a fresh \
+             context, works autonomously with its own tool subset and iteration budget, and \
+             returns a single report. Use it for broad searches or investigations whose \
+             intermediate steps you do not need. By default the sub-agent can only read files \
+             (read_file, grep, glob, list_directory); sub-agents limited to read-only tools run \
+             in parallel when you start several in one turn.",
+        )
+        .with_parameters(
+            JsonSchema::new()
+                .add_property(
+                    "description",
+                    PropertySchema::string("A short (3-8 words) description of the task."),
+                    true,
+                )
+                .add_property(
+                    "prompt",
+                    PropertySchema::string(
+                        "The full task for the sub-agent, including all context it needs and \
+                         what its report should contain.",
+                    ),
+                    true,
+                )
+                .add_property(
+                    "tools",
+                    PropertySchema::array(
+                        "Tool names the sub-agent may use. Defaults to read-only tools.",
+                    )
+                    .with_items(PropertySchema::string("Tool name")),
+                    false,
+                )
+                .add_property(
+                    "model",
+                    PropertySchema::string("Model for the sub-agent. Defaults to your model."),
+                    false,
+                )
+                .add_property(
+                    "system_prompt",
+                    PropertySchema::string("Override the sub-agent's system prompt."),
+                    false,
+                )
+                .add_property(
+                    "max_iterations",
+                    PropertySchema::integer("Maximum tool-call rounds for the sub-agent (1-25).")
+                        .with_default(serde_json::json!(DEFAULT_MAX_ITERATIONS)),
+                    false,
+                ),
+        )
+    }
+
+    async fn execute(&self, args: serde_json::Value) -> Result<ToolResult, ToolError> {
+        self.execute_with_context(args, &ToolContext::default())
+            .await
+    }
+
+    async fn execute_with_context(
+        &self,
+        args: serde_json::Value,
+        context: &ToolContext,
+    ) -> Result<ToolResult, ToolError> {
+        let task = TaskRequest::from_args(&args)?;
+        self.run(task, context).await
+    }
+
+    /// 子代理只能使用只读工具时视为只读，可与其他只读调用并行
+    fn is_read_only(&self, args: &serde_json::Value) -> bool {
+        let Ok(task) = TaskRequest::from_args(args) else {
+            return false;
+        };
+        let Ok(registry) = self.state.base_tool_registry() else {
+            return false;
+        };
+        task.tool_names().iter().all(|name| {
+            name != TASK_TOOL_NAME && registry.is_read_only(name, &serde_json::Value::Null)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::tools::create_default_registry;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_task_request_from_args() {
+        let task = TaskRequest::from_args(&serde_json::json!({
+            "description": " Find usages ",
+            "prompt": "Find all callers of foo",
+            "max_iterations": 100,
+        }))
+        .unwrap();
+        assert_eq!(task.description, "Find usages");
+        assert_eq!(task.max_iterations, MAX_ITERATIONS_LIMIT);
+        assert_eq!(task.model, None);
+        assert_eq!(task.tool_names(), DEFAULT_SUBAGENT_TOOLS);
+
+        let task = TaskRequest::from_args(&serde_json::json!({
+            "description": "Fix",
+            "prompt": "Fix the bug",
+            "tools": ["read_file", "edit_file"],
+            "model": "gpt-4o",
+        }))
+        .unwrap();
+        assert_eq!(task.tool_names(), vec!["read_file", "edit_file"]);
+        assert_eq!(task.model.as_deref(), Some("gpt-4o"));
+        assert_eq!(task.max_iterations, DEFAULT_MAX_ITERATIONS);
+
+        assert!(TaskRequest::from_args(&serde_json::json!({"description": "x"})).is_err());
+        assert!(TaskRequest::from_args(&serde_json::json!({
+            "description": "x",
+            "prompt": "y",
+            "tools": "read_file",
+        }))
+        .is_err());
+    }
+
+    #[test]
+    fn test_restrict_registry() {
+        let dir = TempDir::new().unwrap();
+        let registry = create_default_registry(dir.path());
+        let tools: Vec<String> = DEFAULT_SUBAGENT_TOOLS
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        restrict_registry(&registry, &tools).unwrap();
+        let mut names = registry.list_names();
+        names.sort();
+        assert_eq!(names, vec!["glob", "grep", "list_directory", "read_file"]);
+        assert!(names
+            .iter()
+            .all(|name| registry.is_read_only(name, &serde_json::Value::Null)));
+
+        let registry = create_default_registry(dir.path());
+        let err =
+            restrict_registry(&registry, &["bash".to_string(), "missing".to_string()]).unwrap_err();
+        assert!(err.to_string().contains("missing"));
+        assert!(restrict_registry(&registry, &[TASK_TOOL_NAME.to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_format_report() {
+        assert_eq!(
+            format_report("found it", 3),
+            "found it\n\n[子代理完成，共 3 次工具调用]"
+        );
+        let long = "a".repeat(MAX_RESULT_CHARS + 10);
+        assert!(format_report(&long, 0).contains("[子代理报告过长，已截断]"));
+    }
+}
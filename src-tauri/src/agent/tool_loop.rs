@@ -5,14 +5,16 @@
 //!
 //! ## 功能
 //! - 检测 Agent 响应中的工具调用
-//! - 执行工具并收集结果
+//! - 执行工具并收集结果（相邻的只读调用并行执行）
 //! - 将工具结果发送回 Agent 继续对话
 //! - 最大迭代限制防止无限循环
 
-use crate::agent::tools::{ToolError, ToolRegistry, ToolResult as ToolsResult};
+use crate::agent::approval::{ApprovalVerdict, ToolApprovalManager};
+use crate::agent::tools::{ToolContext, ToolError, ToolRegistry, ToolResult as ToolsResult};
 use crate::agent::types::{
     AgentMessage, MessageContent, StreamEvent, StreamResult, ToolCall, ToolExecutionResult,
 };
+use futures::stream::{self, StreamExt};
 use std::sync::Arc;
 use thiserror::Error;
 use tokio::sync::mpsc;
@@ -84,6 +86,7 @@ impl ToolCallResult {
             timestamp: chrono::Utc::now().to_rfc3339(),
             tool_calls: None,
             tool_call_id: Some(self.tool_call_id.clone()),
+            pinned: false,
         }
     }
 
@@ -103,12 +106,15 @@ pub struct ToolLoopConfig {
     /// 最大迭代次数
     /// Requirements: 7.5 - THE Tool_Loop SHALL enforce a maximum iteration limit
     pub max_iterations: usize,
+    /// 同一批只读工具调用的最大并发数（为 1 时全部顺序执行）
+    pub max_parallel_tools: usize,
 }
 
 impl Default for ToolLoopConfig {
     fn default() -> Self {
         Self {
             max_iterations: 25, // 默认最大 25 次迭代
+            max_parallel_tools: 4,
         }
     }
 }
@@ -116,7 +122,16 @@ impl Default for ToolLoopConfig {
 impl ToolLoopConfig {
     /// 创建新的配置
     pub fn new(max_iterations: usize) -> Self {
-        Self { max_iterations }
+        Self {
+            max_iterations,
+            ..Self::default()
+        }
+    }
+
+    /// 设置只读工具调用的最大并发数
+    pub fn with_max_parallel_tools(mut self, max_parallel_tools: usize) -> Self {
+        self.max_parallel_tools = max_parallel_tools.max(1);
+        self
     }
 }
 
@@ -129,20 +144,46 @@ pub struct ToolLoopEngine {
     registry: Arc<ToolRegistry>,
     /// 配置
     config: ToolLoopConfig,
+    /// 工具调用审批（为空时直接执行所有工具）
+    approvals: Option<Arc<ToolApprovalManager>>,
+    /// 审批所属的会话 ID
+    session_id: Option<String>,
+    /// 未命中审批规则的修改类调用是否必须询问（HTTP API 会话）
+    require_rule_for_writes: bool,
 }
 
 impl ToolLoopEngine {
     /// 创建新的工具循环引擎
     pub fn new(registry: Arc<ToolRegistry>) -> Self {
+        Self::with_config(registry, ToolLoopConfig::default())
+    }
+
+    /// 使用自定义配置创建
+    pub fn with_config(registry: Arc<ToolRegistry>, config: ToolLoopConfig) -> Self {
         Self {
             registry,
-            config: ToolLoopConfig::default(),
+            config,
+            approvals: None,
+            session_id: None,
+            require_rule_for_writes: false,
         }
     }
 
-    /// 使用自定义配置创建
-    pub fn with_config(registry: Arc<ToolRegistry>, config: ToolLoopConfig) -> Self {
-        Self { registry, config }
+    /// 启用工具调用审批
+    pub fn with_approvals(
+        mut self,
+        approvals: Arc<ToolApprovalManager>,
+        session_id: Option<String>,
+    ) -> Self {
+        self.approvals = Some(approvals);
+        self.session_id = session_id;
+        self
+    }
+
+    /// 未命中审批规则的修改类调用必须询问，忽略 `default_policy: allow`
+    pub fn require_rule_for_writes(mut self) -> Self {
+        self.require_rule_for_writes = true;
+        self
     }
 
     /// 获取最大迭代次数
@@ -167,6 +208,15 @@ impl ToolLoopEngine {
     /// Requirements: 7.1 - THE Tool_Loop SHALL execute each tool and collect results
     /// Requirements: 7.4 - IF a tool execution fails, THEN THE Tool_Loop SHALL include the error
     pub async fn execute_tool_call(&self, tool_call: &ToolCall) -> ToolCallResult {
+        self.execute_tool_call_with_events(tool_call, None).await
+    }
+
+    /// 执行单个工具调用，工具可通过执行上下文推送进度事件
+    async fn execute_tool_call_with_events(
+        &self,
+        tool_call: &ToolCall,
+        event_tx: Option<&mpsc::Sender<StreamEvent>>,
+    ) -> ToolCallResult {
         let tool_name = &tool_call.function.name;
         let tool_id = &tool_call.id;
 
@@ -186,7 +236,17 @@ impl ToolLoopEngine {
         };
 
         // 执行工具
-        match self.registry.execute(tool_name, args).await {
+        let context = ToolContext {
+            session_id: self.session_id.clone(),
+            tool_call_id: tool_id.clone(),
+            event_tx: event_tx.cloned(),
+            require_rule_for_writes: self.require_rule_for_writes,
+        };
+        match self
+            .registry
+            .execute_with_context(tool_name, args, &context)
+            .await
+        {
             Ok(result) => {
                 debug!(
                     "[ToolLoopEngine] 工具执行成功: {} success={}",
@@ -216,6 +276,9 @@ impl ToolLoopEngine {
 
     /// 执行所有工具调用
     ///
+    /// 相邻的只读调用组成一批并行执行（审批仍按顺序进行），其余调用按顺序执行；
+    /// 返回结果的顺序与 `tool_calls` 一致。
+    ///
     /// Requirements: 7.1 - THE Tool_Loop SHALL execute each tool and collect results
     /// Requirements: 7.6 - WHILE the Tool_Loop is executing, THE Frontend SHALL display the current tool
     pub async fn execute_all_tool_calls(
@@ -225,6 +288,35 @@ impl ToolLoopEngine {
     ) -> Vec<ToolCallResult> {
         let mut results = Vec::with_capacity(tool_calls.len());
 
+        let mut start = 0;
+        while start < tool_calls.len() {
+            let mut end = start + 1;
+            if self.is_read_only(&tool_calls[start]) {
+                while end < tool_calls.len() && self.is_read_only(&tool_calls[end]) {
+                    end += 1;
+                }
+            }
+            results.extend(self.execute_batch(&tool_calls[start..end], event_tx).await);
+            start = end;
+        }
+
+        results
+    }
+
+    /// 执行一批工具调用：依次发送开始事件并审批，然后并发执行
+    async fn execute_batch(
+        &self,
+        tool_calls: &[ToolCall],
+        event_tx: Option<&mpsc::Sender<StreamEvent>>,
+    ) -> Vec<ToolCallResult> {
+        if tool_calls.len() > 1 {
+            debug!(
+                "[ToolLoopEngine] 并行执行 {} 个只读工具调用",
+                tool_calls.len()
+            );
+        }
+
+        let mut authorized = Vec::with_capacity(tool_calls.len());
         for tool_call in tool_calls {
             // 发送工具开始事件
             if let Some(tx) = event_tx {
@@ -236,24 +328,72 @@ impl ToolLoopEngine {
                     })
                     .await;
             }
+            authorized.push((tool_call, self.authorize(tool_call, event_tx).await));
+        }
 
-            // 执行工具
-            let result = self.execute_tool_call(tool_call).await;
+        stream::iter(authorized)
+            .map(|(tool_call, authorized)| async move {
+                // 审批通过后执行工具
+                let result = match authorized {
+                    Ok(tool_call) => {
+                        self.execute_tool_call_with_events(&tool_call, event_tx)
+                            .await
+                    }
+                    Err(reason) => ToolCallResult::new(
+                        tool_call.id.clone(),
+                        tool_call.function.name.clone(),
+                        ToolsResult::failure(reason),
+                    ),
+                };
 
-            // 发送工具结束事件
-            if let Some(tx) = event_tx {
-                let _ = tx
-                    .send(StreamEvent::ToolEnd {
-                        tool_id: tool_call.id.clone(),
-                        result: result.to_execution_result(),
-                    })
-                    .await;
-            }
+                // 发送工具结束事件
+                if let Some(tx) = event_tx {
+                    let _ = tx
+                        .send(StreamEvent::ToolEnd {
+                            tool_id: tool_call.id.clone(),
+                            result: result.to_execution_result(),
+                        })
+                        .await;
+                }
+                result
+            })
+            .buffered(self.config.max_parallel_tools.max(1))
+            .collect()
+            .await
+    }
 
-            results.push(result);
-        }
+    /// 工具调用是否只读（参数无法解析时视为非只读）
+    fn is_read_only(&self, tool_call: &ToolCall) -> bool {
+        serde_json::from_str::<serde_json::Value>(&tool_call.function.arguments)
+            .is_ok_and(|args| self.registry.is_read_only(&tool_call.function.name, &args))
+    }
 
-        results
+    /// 审批工具调用，返回实际要执行的调用（参数可能已被用户修改）
+    async fn authorize(
+        &self,
+        tool_call: &ToolCall,
+        event_tx: Option<&mpsc::Sender<StreamEvent>>,
+    ) -> Result<ToolCall, String> {
+        let Some(approvals) = &self.approvals else {
+            return Ok(tool_call.clone());
+        };
+        let require_rule = self.require_rule_for_writes && !self.is_read_only(tool_call);
+        match approvals
+            .authorize(
+                self.session_id.as_deref(),
+                tool_call,
+                event_tx,
+                require_rule,
+            )
+            .await
+        {
+            ApprovalVerdict::Execute { arguments } => {
+                let mut tool_call = tool_call.clone();
+                tool_call.function.arguments = arguments;
+                Ok(tool_call)
+            }
+            ApprovalVerdict::Reject { reason } => Err(reason),
+        }
     }
 
     /// 将工具结果转换为 Agent 消息列表
@@ -301,6 +441,7 @@ impl ToolLoopEngine {
                     .collect()
             }),
             tool_call_id: None,
+            pinned: false,
         }
     }
 }
@@ -662,6 +803,136 @@ mod tests {
         let event2 = rx.recv().await.unwrap();
         assert!(matches!(event2, StreamEvent::ToolEnd { .. }));
     }
+
+    /// 测试用的只读工具，记录最大并发数
+    struct SlowReadTool {
+        running: Arc<std::sync::atomic::AtomicUsize>,
+        max_running: Arc<std::sync::atomic::AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl Tool for SlowReadTool {
+        fn definition(&self) -> ToolDefinition {
+            ToolDefinition::new("slow_read", "A slow read-only tool")
+        }
+
+        fn is_read_only(&self, _args: &serde_json::Value) -> bool {
+            true
+        }
+
+        async fn execute(&self, _args: serde_json::Value) -> Result<ToolsResult, ToolError> {
+            use std::sync::atomic::Ordering;
+            let running = self.running.fetch_add(1, Ordering::SeqCst) + 1;
+            self.max_running.fetch_max(running, Ordering::SeqCst);
+            tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+            self.running.fetch_sub(1, Ordering::SeqCst);
+            Ok(ToolsResult::success("read"))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_execute_all_tool_calls_parallel_read_only() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let max_running = Arc::new(AtomicUsize::new(0));
+        let registry = ToolRegistry::new();
+        registry.register(EchoTool).unwrap();
+        registry
+            .register(SlowReadTool {
+                running: Arc::new(AtomicUsize::new(0)),
+                max_running: max_running.clone(),
+            })
+            .unwrap();
+        let engine = ToolLoopEngine::with_config(
+            Arc::new(registry),
+            ToolLoopConfig::new(10).with_max_parallel_tools(2),
+        );
+
+        // 3 个只读调用为一批（并发上限 2），echo 为非只读调用，单独执行
+        let tool_calls = vec![
+            create_tool_call("r1", "slow_read", "{}"),
+            create_tool_call("r2", "slow_read", "{}"),
+            create_tool_call("r3", "slow_read", "{}"),
+            create_tool_call("e1", "echo", r#"{"message": "after"}"#),
+            create_tool_call("r4", "slow_read", "{}"),
+        ];
+        let (tx, mut rx) = mpsc::channel::<StreamEvent>(20);
+        let results = engine.execute_all_tool_calls(&tool_calls, Some(&tx)).await;
+        drop(tx);
+
+        assert_eq!(max_running.load(Ordering::SeqCst), 2);
+        let ids: Vec<&str> = results.iter().map(|r| r.tool_call_id.as_str()).collect();
+        assert_eq!(ids, vec!["r1", "r2", "r3", "e1", "r4"]);
+        assert!(results.iter().all(|r| r.result.success));
+
+        // 批内先发送全部开始事件；非只读调用在前一批全部结束后才开始
+        let mut events = Vec::new();
+        while let Some(event) = rx.recv().await {
+            events.push(match event {
+                StreamEvent::ToolStart { tool_id, .. } => format!("start:{}", tool_id),
+                StreamEvent::ToolEnd { tool_id, .. } => format!("end:{}", tool_id),
+                other => panic!("unexpected event: {:?}", other),
+            });
+        }
+        assert_eq!(&events[..3], &["start:r1", "start:r2", "start:r3"]);
+        let e1_start = events.iter().position(|e| e == "start:e1").unwrap();
+        assert_eq!(
+            events[..e1_start]
+                .iter()
+                .filter(|e| e.starts_with("end:"))
+                .count(),
+            3
+        );
+        assert_eq!(&events[e1_start + 1..], &["end:e1", "start:r4", "end:r4"]);
+    }
+
+    #[tokio::test]
+    async fn test_execute_all_tool_calls_with_approvals() {
+        use crate::agent::approval::{ApprovalDecision, ToolApprovalManager};
+        use crate::config::{ToolApprovalPolicy, ToolApprovalRule, ToolApprovalSettings};
+
+        let approvals = Arc::new(ToolApprovalManager::new(None));
+        approvals.set_settings(ToolApprovalSettings {
+            default_policy: ToolApprovalPolicy::Ask,
+            timeout_secs: 5,
+            rules: vec![ToolApprovalRule {
+                tool: "failing".to_string(),
+                pattern: None,
+                policy: ToolApprovalPolicy::Deny,
+            }],
+        });
+        let engine = ToolLoopEngine::new(create_test_registry())
+            .with_approvals(approvals.clone(), Some("s1".to_string()));
+
+        let (tx, mut rx) = mpsc::channel::<StreamEvent>(10);
+        let resolver = tokio::spawn(async move {
+            while let Some(event) = rx.recv().await {
+                if let StreamEvent::ApprovalRequest { request_id, .. } = event {
+                    let decision = ApprovalDecision::Edit {
+                        arguments: serde_json::json!({"message": "edited"}),
+                    };
+                    approvals.resolve(&request_id, decision).unwrap();
+                }
+            }
+        });
+
+        let tool_calls = vec![
+            create_tool_call("call_1", "echo", r#"{"message": "Test"}"#),
+            create_tool_call("call_2", "failing", "{}"),
+        ];
+        let results = engine.execute_all_tool_calls(&tool_calls, Some(&tx)).await;
+        drop(tx);
+        resolver.await.unwrap();
+
+        assert_eq!(results[0].result.output, "edited");
+        assert!(!results[1].result.success);
+        assert!(results[1]
+            .result
+            .error
+            .as_deref()
+            .unwrap()
+            .contains("审批策略拒绝"));
+    }
 }
 
 #[cfg(test)]
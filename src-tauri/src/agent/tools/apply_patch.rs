@@ -0,0 +1,964 @@
+//! 补丁应用工具模块
+//!
+//! 一次应用涉及多个文件的补丁，支持两种格式：
+//! - unified diff（`git diff` / `diff -u` 输出，支持新建、删除和重命名）
+//! - Codex 补丁格式（`*** Begin Patch` ... `*** End Patch`）
+//!
+//! 所有文件的新内容先在内存中计算，全部成功后才写入；
+//! 写入中途失败时回滚已写入的文件，保证补丁要么全部生效，要么完全不生效。
+
+use super::registry::Tool;
+use super::security::SecurityManager;
+use super::types::{JsonSchema, PropertySchema, ToolDefinition, ToolError, ToolResult};
+use async_trait::async_trait;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tracing::{info, warn};
+
+static HUNK_HEADER: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^@@ -(\d+)(?:,(\d+))? \+(\d+)(?:,(\d+))? @@").unwrap());
+
+const CODEX_BEGIN: &str = "*** Begin Patch";
+const CODEX_END: &str = "*** End Patch";
+const CODEX_ADD: &str = "*** Add File: ";
+const CODEX_DELETE: &str = "*** Delete File: ";
+const CODEX_UPDATE: &str = "*** Update File: ";
+const CODEX_MOVE: &str = "*** Move to: ";
+const CODEX_EOF: &str = "*** End of File";
+const DEV_NULL: &str = "/dev/null";
+
+/// 补丁中的单个文件操作
+#[derive(Debug, Clone, PartialEq)]
+pub enum PatchOperation {
+    /// 新建文件
+    Add { path: String, content: String },
+    /// 删除文件
+    Delete { path: String },
+    /// 修改文件（可同时重命名）
+    Update {
+        path: String,
+        move_to: Option<String>,
+        hunks: Vec<Hunk>,
+    },
+}
+
+impl PatchOperation {
+    /// 操作涉及的全部路径（包括重命名目标）
+    pub fn paths(&self) -> Vec<&str> {
+        match self {
+            Self::Add { path, .. } | Self::Delete { path } => vec![path.as_str()],
+            Self::Update { path, move_to, .. } => std::iter::once(path.as_str())
+                .chain(move_to.as_deref())
+                .collect(),
+        }
+    }
+}
+
+/// 修改块
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Hunk {
+    /// 原文件中的起始行（unified diff，从 1 开始）
+    pub old_start: Option<usize>,
+    /// 定位行（Codex 格式 `@@` 之后的内容），修改位于该行之后
+    pub anchor: Option<String>,
+    /// 修改位于文件末尾（Codex 格式 `*** End of File`）
+    pub end_of_file: bool,
+    pub lines: Vec<HunkLine>,
+}
+
+/// 修改块中的一行
+#[derive(Debug, Clone, PartialEq)]
+pub enum HunkLine {
+    Context(String),
+    Remove(String),
+    Add(String),
+}
+
+/// 文件变更类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PatchedFileKind {
+    Added,
+    Modified,
+    Deleted,
+    Moved,
+}
+
+/// 单个文件的变更结果
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct PatchedFile {
+    /// 补丁中的文件路径
+    pub path: String,
+    pub kind: PatchedFileKind,
+    /// 重命名目标
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub moved_to: Option<String>,
+    /// 新增行数
+    pub added_lines: usize,
+    /// 删除行数
+    pub removed_lines: usize,
+}
+
+/// 补丁应用结果
+#[derive(Debug, Clone, Serialize)]
+pub struct ApplyPatchResult {
+    pub files: Vec<PatchedFile>,
+}
+
+/// 解析补丁（自动识别 Codex 格式和 unified diff）
+pub fn parse_patch(patch: &str) -> Result<Vec<PatchOperation>, ToolError> {
+    let operations = if patch.trim_start().starts_with(CODEX_BEGIN) {
+        parse_codex_patch(patch)?
+    } else {
+        parse_unified_diff(patch)?
+    };
+    if operations.is_empty() {
+        return Err(ToolError::InvalidArguments(
+            "补丁中没有文件变更".to_string(),
+        ));
+    }
+    Ok(operations)
+}
+
+fn parse_error(line: usize, message: impl std::fmt::Display) -> ToolError {
+    ToolError::InvalidArguments(format!("补丁第 {} 行: {}", line + 1, message))
+}
+
+/// 解析 Codex 补丁格式
+fn parse_codex_patch(patch: &str) -> Result<Vec<PatchOperation>, ToolError> {
+    let lines: Vec<&str> = patch.trim().lines().collect();
+    let mut operations = Vec::new();
+    let mut i = 1;
+
+    while i < lines.len() {
+        let line = lines[i].trim_end();
+        if line == CODEX_END {
+            return Ok(operations);
+        }
+
+        if let Some(path) = line.strip_prefix(CODEX_ADD) {
+            i += 1;
+            let mut content = String::new();
+            while i < lines.len() && !lines[i].starts_with("*** ") {
+                let text = lines[i]
+                    .strip_prefix('+')
+                    .ok_or_else(|| parse_error(i, "新建文件的每一行必须以 + 开头"))?;
+                content.push_str(text);
+                content.push('\n');
+                i += 1;
+            }
+            operations.push(PatchOperation::Add {
+                path: path.trim().to_string(),
+                content,
+            });
+        } else if let Some(path) = line.strip_prefix(CODEX_DELETE) {
+            operations.push(PatchOperation::Delete {
+                path: path.trim().to_string(),
+            });
+            i += 1;
+        } else if let Some(path) = line.strip_prefix(CODEX_UPDATE) {
+            i += 1;
+            let move_to = match lines.get(i).and_then(|l| l.strip_prefix(CODEX_MOVE)) {
+                Some(target) => {
+                    i += 1;
+                    Some(target.trim().to_string())
+                }
+                None => None,
+            };
+
+            let mut hunks = Vec::new();
+            let mut hunk = Hunk::default();
+            while i < lines.len() {
+                let line = lines[i];
+                if line.trim_end() == CODEX_EOF {
+                    hunk.end_of_file = true;
+                } else if line.starts_with("*** ") {
+                    break;
+                } else if let Some(anchor) = line.strip_prefix("@@") {
+                    if !hunk.lines.is_empty() {
+                        hunks.push(std::mem::take(&mut hunk));
+                    }
+                    let anchor = anchor.trim();
+                    hunk.anchor = (!anchor.is_empty()).then(|| anchor.to_string());
+                } else {
+                    hunk.lines.push(
+                        parse_hunk_line(line)
+                            .ok_or_else(|| parse_error(i, "修改行必须以空格、+ 或 - 开头"))?,
+                    );
+                }
+                i += 1;
+            }
+            if !hunk.lines.is_empty() {
+                hunks.push(hunk);
+            }
+            if hunks.is_empty() && move_to.is_none() {
+                return Err(parse_error(i - 1, format!("{} 没有任何修改", path.trim())));
+            }
+            operations.push(PatchOperation::Update {
+                path: path.trim().to_string(),
+                move_to,
+                hunks,
+            });
+        } else if line.is_empty() {
+            i += 1;
+        } else {
+            return Err(parse_error(i, format!("无法识别的内容: {}", line)));
+        }
+    }
+
+    Err(ToolError::InvalidArguments(format!(
+        "补丁缺少结尾的 {}",
+        CODEX_END
+    )))
+}
+
+/// 解析修改行，空行视为空的上下文行
+fn parse_hunk_line(line: &str) -> Option<HunkLine> {
+    match line.chars().next() {
+        None => Some(HunkLine::Context(String::new())),
+        Some(' ') => Some(HunkLine::Context(line[1..].to_string())),
+        Some('-') => Some(HunkLine::Remove(line[1..].to_string())),
+        Some('+') => Some(HunkLine::Add(line[1..].to_string())),
+        _ => None,
+    }
+}
+
+/// 解析 unified diff（可包含多个文件）
+fn parse_unified_diff(patch: &str) -> Result<Vec<PatchOperation>, ToolError> {
+    let lines: Vec<&str> = patch.lines().collect();
+    let is_file_header = |i: usize| {
+        lines[i].starts_with("--- ") && lines.get(i + 1).is_some_and(|l| l.starts_with("+++ "))
+    };
+
+    let mut operations = Vec::new();
+    // git 扩展头中的纯重命名（没有内容修改时不会出现 ---/+++ 头）
+    let mut rename_from: Option<String> = None;
+    let mut rename_to: Option<String> = None;
+    let flush_rename = |from: &mut Option<String>, to: &mut Option<String>, ops: &mut Vec<_>| {
+        if let (Some(path), Some(target)) = (from.take(), to.take()) {
+            ops.push(PatchOperation::Update {
+                path,
+                move_to: Some(target),
+                hunks: Vec::new(),
+            });
+        }
+    };
+
+    let mut i = 0;
+    while i < lines.len() {
+        let line = lines[i];
+        if line.starts_with("diff --git ") {
+            flush_rename(&mut rename_from, &mut rename_to, &mut operations);
+            i += 1;
+            continue;
+        }
+        if let Some(path) = line.strip_prefix("rename from ") {
+            rename_from = Some(path.trim().to_string());
+            i += 1;
+            continue;
+        }
+        if let Some(path) = line.strip_prefix("rename to ") {
+            rename_to = Some(path.trim().to_string());
+            i += 1;
+            continue;
+        }
+        if !is_file_header(i) {
+            // 提交信息、index、mode 等与内容无关的行
+            i += 1;
+            continue;
+        }
+
+        rename_from = None;
+        rename_to = None;
+        let (old_path, new_path) = header_paths(&line[4..], &lines[i + 1][4..]);
+        i += 2;
+
+        let mut hunks = Vec::new();
+        while i < lines.len() && lines[i].starts_with("@@") {
+            let captures = HUNK_HEADER
+                .captures(lines[i])
+                .ok_or_else(|| parse_error(i, format!("无效的 hunk 头: {}", lines[i])))?;
+            let number = |n: usize| {
+                captures
+                    .get(n)
+                    .map_or(1, |m| m.as_str().parse::<usize>().unwrap_or(0))
+            };
+            let (old_start, old_count) = (number(1), number(2));
+            i += 1;
+
+            let mut hunk = Hunk {
+                old_start: Some(old_start),
+                ..Default::default()
+            };
+            // 补丁文本末尾的空行可能不属于 hunk，超出 hunk 头行数时丢弃
+            let mut trailing_blank = 0;
+            while i < lines.len()
+                && !lines[i].starts_with("@@")
+                && !lines[i].starts_with("diff --git ")
+                && !is_file_header(i)
+            {
+                if lines[i].starts_with('\\') {
+                    // "\ No newline at end of file"
+                    i += 1;
+                    continue;
+                }
+                let Some(hunk_line) = parse_hunk_line(lines[i]) else {
+                    break;
+                };
+                trailing_blank = if lines[i].is_empty() {
+                    trailing_blank + 1
+                } else {
+                    0
+                };
+                hunk.lines.push(hunk_line);
+                i += 1;
+            }
+            let old_lines = |hunk: &Hunk| {
+                hunk.lines
+                    .iter()
+                    .filter(|l| !matches!(l, HunkLine::Add(_)))
+                    .count()
+            };
+            while trailing_blank > 0 && old_lines(&hunk) > old_count {
+                hunk.lines.pop();
+                trailing_blank -= 1;
+            }
+            hunks.push(hunk);
+        }
+
+        let operation = if old_path == DEV_NULL {
+            let mut content = String::new();
+            for line in hunks.iter().flat_map(|h| &h.lines) {
+                if let HunkLine::Add(text) = line {
+                    content.push_str(text);
+                    content.push('\n');
+                }
+            }
+            PatchOperation::Add {
+                path: new_path,
+                content,
+            }
+        } else if new_path == DEV_NULL {
+            PatchOperation::Delete { path: old_path }
+        } else {
+            let move_to = (new_path != old_path).then_some(new_path);
+            PatchOperation::Update {
+                path: old_path,
+                move_to,
+                hunks,
+            }
+        };
+        operations.push(operation);
+    }
+    flush_rename(&mut rename_from, &mut rename_to, &mut operations);
+
+    Ok(operations)
+}
+
+/// 解析 ---/+++ 头中的路径（去掉时间戳和 git 的 a/、b/ 前缀）
+fn header_paths(old: &str, new: &str) -> (String, String) {
+    let clean = |p: &str| p.split('\t').next().unwrap_or(p).trim().to_string();
+    let (old, new) = (clean(old), clean(new));
+    let git_style = (old == DEV_NULL || old.starts_with("a/"))
+        && (new == DEV_NULL || new.starts_with("b/"))
+        && !(old == DEV_NULL && new == DEV_NULL);
+    if !git_style {
+        return (old, new);
+    }
+    let strip = |p: String, prefix: &str| match p.strip_prefix(prefix) {
+        Some(rest) => rest.to_string(),
+        None => p,
+    };
+    (strip(old, "a/"), strip(new, "b/"))
+}
+
+/// 将修改块应用到文件内容，返回新内容、新增行数和删除行数
+fn apply_hunks(
+    path: &str,
+    content: &str,
+    hunks: &[Hunk],
+) -> Result<(String, usize, usize), ToolError> {
+    let crlf = content.contains("\r\n");
+    let text = if crlf {
+        content.replace("\r\n", "\n")
+    } else {
+        content.to_string()
+    };
+    let trailing_newline = text.is_empty() || text.ends_with('\n');
+    let mut lines: Vec<String> = text.lines().map(str::to_string).collect();
+
+    let (mut added, mut removed) = (0, 0);
+    let mut cursor = 0;
+    for (n, hunk) in hunks.iter().enumerate() {
+        let old: Vec<&str> = hunk
+            .lines
+            .iter()
+            .filter_map(|l| match l {
+                HunkLine::Context(s) | HunkLine::Remove(s) => Some(s.as_str()),
+                HunkLine::Add(_) => None,
+            })
+            .collect();
+        let new: Vec<String> = hunk
+            .lines
+            .iter()
+            .filter_map(|l| match l {
+                HunkLine::Context(s) | HunkLine::Add(s) => Some(s.clone()),
+                HunkLine::Remove(_) => None,
+            })
+            .collect();
+
+        if let Some(anchor) = &hunk.anchor {
+            let index = (cursor..lines.len())
+                .find(|&i| lines[i].trim() == anchor.trim())
+                .ok_or_else(|| {
+                    ToolError::ExecutionFailed(format!(
+                        "无法在 {} 中找到第 {} 个修改块的定位行: {}",
+                        path,
+                        n + 1,
+                        anchor
+                    ))
+                })?;
+            cursor = index + 1;
+        }
+
+        let position = if old.is_empty() {
+            // 纯新增：unified diff 的 `-N,0` 表示插入到第 N 行之后
+            match hunk.old_start {
+                Some(start) if !hunk.end_of_file => start.clamp(cursor, lines.len()),
+                _ if hunk.anchor.is_some() && !hunk.end_of_file => cursor,
+                _ => lines.len(),
+            }
+        } else {
+            locate(&lines, &old, cursor, hunk).ok_or_else(|| {
+                ToolError::ExecutionFailed(format!(
+                    "无法在 {} 中找到第 {} 个修改块的原内容（文件可能已变化，请重新读取后再生成补丁）:\n{}",
+                    path,
+                    n + 1,
+                    old.join("\n")
+                ))
+            })?
+        };
+
+        added += hunk
+            .lines
+            .iter()
+            .filter(|l| matches!(l, HunkLine::Add(_)))
+            .count();
+        removed += hunk
+            .lines
+            .iter()
+            .filter(|l| matches!(l, HunkLine::Remove(_)))
+            .count();
+        let new_len = new.len();
+        lines.splice(position..position + old.len(), new);
+        cursor = position + new_len;
+    }
+
+    let mut result = lines.join("\n");
+    if trailing_newline && !lines.is_empty() {
+        result.push('\n');
+    }
+    if crlf {
+        result = result.replace('\n', "\r\n");
+    }
+    Ok((result, added, removed))
+}
+
+/// 定位修改块的原内容：先尝试 hunk 头给出的位置，再从 `cursor` 向后依次按
+/// 精确匹配、忽略行尾空白、忽略首尾空白查找
+fn locate(lines: &[String], old: &[&str], cursor: usize, hunk: &Hunk) -> Option<usize> {
+    if old.len() > lines.len() {
+        return None;
+    }
+    let last_start = lines.len() - old.len();
+    let matches_at = |start: usize, normalize: fn(&str) -> &str| {
+        old.iter()
+            .zip(&lines[start..])
+            .all(|(expected, actual)| normalize(expected) == normalize(actual))
+    };
+
+    let preferred = if hunk.end_of_file {
+        Some(last_start)
+    } else {
+        hunk.old_start.map(|start| start.saturating_sub(1))
+    };
+    if let Some(start) = preferred.filter(|s| *s >= cursor && *s <= last_start) {
+        if matches_at(start, identity) {
+            return Some(start);
+        }
+    }
+
+    let normalizers: [fn(&str) -> &str; 3] = [identity, str::trim_end, str::trim];
+    normalizers
+        .into_iter()
+        .find_map(|normalize| (cursor..=last_start).find(|&start| matches_at(start, normalize)))
+}
+
+fn identity(s: &str) -> &str {
+    s
+}
+
+/// 待写入的文件变更
+struct PendingWrite {
+    path: PathBuf,
+    /// 原内容（文件不存在时为 None），用于回滚
+    original: Option<Vec<u8>>,
+    /// 新内容（删除时为 None）
+    content: Option<String>,
+}
+
+/// 补丁应用工具
+pub struct ApplyPatchTool {
+    /// 安全管理器
+    security: Arc<SecurityManager>,
+}
+
+impl ApplyPatchTool {
+    /// 创建新的补丁应用工具
+    pub fn new(security: Arc<SecurityManager>) -> Self {
+        Self { security }
+    }
+
+    /// 应用补丁：全部文件验证并计算成功后才写入
+    pub fn apply_patch(&self, patch: &str) -> Result<ApplyPatchResult, ToolError> {
+        let operations = parse_patch(patch)?;
+
+        let mut seen = HashSet::new();
+        for path in operations.iter().flat_map(|op| op.paths()) {
+            if !seen.insert(path) {
+                return Err(ToolError::InvalidArguments(format!(
+                    "补丁中同一文件出现多次: {}",
+                    path
+                )));
+            }
+        }
+
+        let mut writes = Vec::new();
+        let mut files = Vec::new();
+        for operation in &operations {
+            match operation {
+                PatchOperation::Add { path, content } => {
+                    let target = self.validate_new_file(path)?;
+                    files.push(PatchedFile {
+                        path: path.clone(),
+                        kind: PatchedFileKind::Added,
+                        moved_to: None,
+                        added_lines: content.lines().count(),
+                        removed_lines: 0,
+                    });
+                    writes.push(PendingWrite {
+                        path: target,
+                        original: None,
+                        content: Some(content.clone()),
+                    });
+                }
+                PatchOperation::Delete { path } => {
+                    let target = self.validate_existing_file(path)?;
+                    let original = fs::read(&target)?;
+                    files.push(PatchedFile {
+                        path: path.clone(),
+                        kind: PatchedFileKind::Deleted,
+                        moved_to: None,
+                        added_lines: 0,
+                        removed_lines: String::from_utf8_lossy(&original).lines().count(),
+                    });
+                    writes.push(PendingWrite {
+                        path: target,
+                        original: Some(original),
+                        content: None,
+                    });
+                }
+                PatchOperation::Update {
+                    path,
+                    move_to,
+                    hunks,
+                } => {
+                    let source = self.validate_existing_file(path)?;
+                    let original = fs::read_to_string(&source).map_err(|e| {
+                        ToolError::ExecutionFailed(format!("无法读取文件 {}: {}", path, e))
+                    })?;
+                    let (content, added_lines, removed_lines) =
+                        apply_hunks(path, &original, hunks)?;
+                    files.push(PatchedFile {
+                        path: path.clone(),
+                        kind: if move_to.is_some() {
+                            PatchedFileKind::Moved
+                        } else {
+                            PatchedFileKind::Modified
+                        },
+                        moved_to: move_to.clone(),
+                        added_lines,
+                        removed_lines,
+                    });
+                    match move_to {
+                        Some(target) => {
+                            let destination = self.validate_new_file(target)?;
+                            writes.push(PendingWrite {
+                                path: destination,
+                                original: None,
+                                content: Some(content),
+                            });
+                            writes.push(PendingWrite {
+                                path: source,
+                                original: Some(original.into_bytes()),
+                                content: None,
+                            });
+                        }
+                        None => writes.push(PendingWrite {
+                            path: source,
+                            original: Some(original.into_bytes()),
+                            content: Some(content),
+                        }),
+                    }
+                }
+            }
+        }
+
+        commit(&writes)?;
+        info!("[ApplyPatchTool] 应用补丁: {} 个文件", files.len());
+        Ok(ApplyPatchResult { files })
+    }
+
+    /// 验证将要新建的文件：路径安全且文件不存在
+    fn validate_new_file(&self, path: &str) -> Result<PathBuf, ToolError> {
+        let validated = self
+            .security
+            .validate_path_no_symlink_check(Path::new(path))
+            .map_err(|e| ToolError::Security(e.to_string()))?;
+        if validated.symlink_metadata().is_ok() {
+            return Err(ToolError::ExecutionFailed(format!("文件已存在: {}", path)));
+        }
+        Ok(validated)
+    }
+
+    /// 验证已有文件：路径安全、不是符号链接且为普通文件
+    fn validate_existing_file(&self, path: &str) -> Result<PathBuf, ToolError> {
+        let validated = self
+            .security
+            .validate_path(Path::new(path))
+            .map_err(|e| ToolError::Security(e.to_string()))?;
+        if !validated.is_file() {
+            return Err(ToolError::ExecutionFailed(format!("文件不存在: {}", path)));
+        }
+        Ok(validated)
+    }
+}
+
+/// 依次写入变更，任一失败时回滚已完成的写入
+fn commit(writes: &[PendingWrite]) -> Result<(), ToolError> {
+    for (done, write) in writes.iter().enumerate() {
+        let result = match &write.content {
+            Some(content) => write_atomic(&write.path, content.as_bytes()),
+            None => fs::remove_file(&write.path),
+        };
+        if let Err(e) = result {
+            for previous in writes[..done].iter().rev() {
+                let restored = match &previous.original {
+                    Some(original) => write_atomic(&previous.path, original),
+                    None => fs::remove_file(&previous.path),
+                };
+                if let Err(e) = restored {
+                    warn!("[ApplyPatchTool] 回滚失败: {:?} - {}", previous.path, e);
+                }
+            }
+            return Err(ToolError::ExecutionFailed(format!(
+                "写入 {} 失败，已回滚全部修改: {}",
+                write.path.display(),
+                e
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// 先写临时文件再重命名，避免留下不完整的文件
+fn write_atomic(path: &Path, data: &[u8]) -> std::io::Result<()> {
+    let parent = path.parent().unwrap_or_else(|| Path::new("."));
+    fs::create_dir_all(parent)?;
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+    let tmp = parent.join(format!(".{}.{}.tmp", file_name, uuid::Uuid::new_v4()));
+    fs::write(&tmp, data)?;
+    fs::rename(&tmp, path).inspect_err(|_| {
+        let _ = fs::remove_file(&tmp);
+    })
+}
+
+/// 格式化应用结果
+fn format_result(result: &ApplyPatchResult) -> String {
+    let mut output = format!("成功应用补丁，修改 {} 个文件:\n", result.files.len());
+    for file in &result.files {
+        let (flag, name) = match file.kind {
+            PatchedFileKind::Added => ("A", file.path.clone()),
+            PatchedFileKind::Modified => ("M", file.path.clone()),
+            PatchedFileKind::Deleted => ("D", file.path.clone()),
+            PatchedFileKind::Moved => (
+                "R",
+                format!(
+                    "{} -> {}",
+                    file.path,
+                    file.moved_to.as_deref().unwrap_or_default()
+                ),
+            ),
+        };
+        output.push_str(&format!(
+            "{} {} (+{} -{})\n",
+            flag, name, file.added_lines, file.removed_lines
+        ));
+    }
+    output
+}
+
+#[async_trait]
+impl Tool for ApplyPatchTool {
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition::new(
+            "apply_patch",
+            "Apply a patch that may create, modify, delete or rename multiple files. \
+             Accepts a unified diff (as produced by `git diff`) or the Codex patch format:\n\
+             *** Begin Patch\n\
+             *** Update File: src/main.rs\n\
+             @@ fn main() {\n\
+             -    old line\n\
+             +    new line\n\
+             *** Add File: src/new.rs\n\
+             +file content\n\
+             *** Delete File: src/old.rs\n\
+             *** End Patch\n\
+             The patch is applied atomically: if any file fails, no file is changed. \
+             Include a few unchanged context lines around each change so it can be located.",
+        )
+        .with_parameters(JsonSchema::new().add_property(
+            "patch",
+            PropertySchema::string("The full patch text."),
+            true,
+        ))
+    }
+
+    async fn execute(&self, args: serde_json::Value) -> Result<ToolResult, ToolError> {
+        let patch = args
+            .get("patch")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| ToolError::InvalidArguments("缺少 patch 参数".to_string()))?;
+
+        info!("[ApplyPatchTool] 应用补丁: {} 字节", patch.len());
+
+        let result = self.apply_patch(patch)?;
+        Ok(ToolResult::success(format_result(&result)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn setup_test_tool() -> (ApplyPatchTool, TempDir) {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::write(
+            root.join("src/main.rs"),
+            "fn main() {\n    let x = 1;\n    println!(\"{}\", x);\n}\n",
+        )
+        .unwrap();
+        fs::write(root.join("old.txt"), "obsolete\n").unwrap();
+        let tool = ApplyPatchTool::new(Arc::new(SecurityManager::new(root)));
+        (tool, temp_dir)
+    }
+
+    fn read(dir: &TempDir, path: &str) -> String {
+        fs::read_to_string(dir.path().join(path)).unwrap()
+    }
+
+    #[test]
+    fn test_parse_codex_patch() {
+        let patch = "*** Begin Patch\n\
+                     *** Add File: a.txt\n\
+                     +hello\n\
+                     *** Update File: b.rs\n\
+                     *** Move to: c.rs\n\
+                     @@ fn main() {\n\
+                     -    old\n\
+                     +    new\n\
+                     *** End of File\n\
+                     *** Delete File: d.txt\n\
+                     *** End Patch\n";
+        let operations = parse_patch(patch).unwrap();
+        assert_eq!(operations.len(), 3);
+        assert_eq!(
+            operations[0],
+            PatchOperation::Add {
+                path: "a.txt".to_string(),
+                content: "hello\n".to_string()
+            }
+        );
+        let PatchOperation::Update { move_to, hunks, .. } = &operations[1] else {
+            panic!("应为 Update");
+        };
+        assert_eq!(move_to.as_deref(), Some("c.rs"));
+        assert_eq!(hunks[0].anchor.as_deref(), Some("fn main() {"));
+        assert!(hunks[0].end_of_file);
+        assert_eq!(operations[2].paths(), vec!["d.txt"]);
+
+        assert!(parse_patch("*** Begin Patch\n*** Add File: a\n+x\n").is_err());
+        assert!(parse_patch("*** Begin Patch\n*** Update File: a\n*** End Patch").is_err());
+    }
+
+    #[test]
+    fn test_parse_git_diff() {
+        let patch = "diff --git a/src/main.rs b/src/main.rs\n\
+                     index 1234567..89abcde 100644\n\
+                     --- a/src/main.rs\n\
+                     +++ b/src/main.rs\n\
+                     @@ -1,3 +1,3 @@\n \
+                     fn main() {\n\
+                     -    let x = 1;\n\
+                     +    let x = 2;\n \
+                        println!(\"{}\", x);\n\
+                     diff --git a/new.txt b/new.txt\n\
+                     new file mode 100644\n\
+                     --- /dev/null\n\
+                     +++ b/new.txt\n\
+                     @@ -0,0 +1,2 @@\n\
+                     +line 1\n\
+                     +line 2\n\
+                     diff --git a/x.rs b/y.rs\n\
+                     similarity index 100%\n\
+                     rename from x.rs\n\
+                     rename to y.rs\n";
+        let operations = parse_patch(patch).unwrap();
+        assert_eq!(operations.len(), 3);
+        let PatchOperation::Update { path, hunks, .. } = &operations[0] else {
+            panic!("应为 Update");
+        };
+        assert_eq!(path, "src/main.rs");
+        assert_eq!(hunks[0].old_start, Some(1));
+        assert_eq!(hunks[0].lines.len(), 4);
+        assert_eq!(
+            operations[1],
+            PatchOperation::Add {
+                path: "new.txt".to_string(),
+                content: "line 1\nline 2\n".to_string()
+            }
+        );
+        assert_eq!(operations[2].paths(), vec!["x.rs", "y.rs"]);
+
+        // 普通 diff -u 输出不去掉路径前缀
+        assert_eq!(
+            header_paths("a/file.txt\t2024-01-01", "a/file.txt"),
+            ("a/file.txt".to_string(), "a/file.txt".to_string())
+        );
+    }
+
+    #[test]
+    fn test_apply_hunks_fuzzy_and_crlf() {
+        let content = "a\r\nb  \r\nc\r\nd\r\n";
+        let hunks = vec![Hunk {
+            old_start: Some(10),
+            lines: vec![
+                HunkLine::Context("b".to_string()),
+                HunkLine::Remove("c".to_string()),
+                HunkLine::Add("C".to_string()),
+            ],
+            ..Default::default()
+        }];
+        let (result, added, removed) = apply_hunks("f", content, &hunks).unwrap();
+        assert_eq!(result, "a\r\nb\r\nC\r\nd\r\n");
+        assert_eq!((added, removed), (1, 1));
+
+        let missing = vec![Hunk {
+            lines: vec![HunkLine::Remove("zzz".to_string())],
+            ..Default::default()
+        }];
+        assert!(apply_hunks("f", content, &missing).is_err());
+    }
+
+    #[test]
+    fn test_apply_patch_multi_file() {
+        let (tool, dir) = setup_test_tool();
+        let patch = "*** Begin Patch\n\
+                     *** Update File: src/main.rs\n\
+                     @@ fn main() {\n\
+                     -    let x = 1;\n\
+                     +    let x = 2;\n\
+                     *** Add File: src/util/mod.rs\n\
+                     +pub fn util() {}\n\
+                     *** Delete File: old.txt\n\
+                     *** End Patch";
+        let result = tool.apply_patch(patch).unwrap();
+        assert_eq!(result.files.len(), 3);
+        assert!(read(&dir, "src/main.rs").contains("let x = 2;"));
+        assert_eq!(read(&dir, "src/util/mod.rs"), "pub fn util() {}\n");
+        assert!(!dir.path().join("old.txt").exists());
+
+        let output = format_result(&result);
+        assert!(output.contains("M src/main.rs (+1 -1)"));
+        assert!(output.contains("A src/util/mod.rs (+1 -0)"));
+        assert!(output.contains("D old.txt (+0 -1)"));
+    }
+
+    #[test]
+    fn test_apply_patch_is_atomic() {
+        let (tool, dir) = setup_test_tool();
+        let patch = "*** Begin Patch\n\
+                     *** Add File: created.txt\n\
+                     +x\n\
+                     *** Update File: src/main.rs\n\
+                     -this line does not exist\n\
+                     +y\n\
+                     *** End Patch";
+        assert!(tool.apply_patch(patch).is_err());
+        assert!(!dir.path().join("created.txt").exists());
+
+        // 同一文件出现多次
+        let patch =
+            "*** Begin Patch\n*** Delete File: old.txt\n*** Delete File: old.txt\n*** End Patch";
+        assert!(matches!(
+            tool.apply_patch(patch),
+            Err(ToolError::InvalidArguments(_))
+        ));
+        assert_eq!(read(&dir, "old.txt"), "obsolete\n");
+    }
+
+    #[test]
+    fn test_apply_patch_rejects_unsafe_paths() {
+        let (tool, _dir) = setup_test_tool();
+        let patch = "*** Begin Patch\n*** Add File: ../escape.txt\n+x\n*** End Patch";
+        assert!(matches!(
+            tool.apply_patch(patch),
+            Err(ToolError::Security(_))
+        ));
+
+        let patch = "*** Begin Patch\n*** Add File: old.txt\n+x\n*** End Patch";
+        assert!(matches!(
+            tool.apply_patch(patch),
+            Err(ToolError::ExecutionFailed(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_tool_execute_unified_diff_rename() {
+        let (tool, dir) = setup_test_tool();
+        let patch = "--- a/src/main.rs\n\
+                     +++ b/src/app.rs\n\
+                     @@ -3,2 +3,2 @@\n\
+                     -    println!(\"{}\", x);\n\
+                     +    println!(\"x = {}\", x);\n \
+                     }\n";
+        let result = tool
+            .execute(serde_json::json!({ "patch": patch }))
+            .await
+            .unwrap();
+        assert!(result.success);
+        assert!(result
+            .output
+            .contains("R src/main.rs -> src/app.rs (+1 -1)"));
+        assert!(!dir.path().join("src/main.rs").exists());
+        assert!(read(&dir, "src/app.rs").contains("x = {}"));
+    }
+}
@@ -8,21 +8,26 @@
 //! - 命令执行（捕获 stdout/stderr）
 //! - 超时控制
 //! - 防止交互的环境变量设置
+//! - 输出上限和实时推送（`tool_output` 流式事件）
+//! - 可选的隔离执行（见 `sandbox` 模块）
 
 #![allow(dead_code)]
 
-use super::registry::Tool;
+use super::registry::{Tool, ToolContext};
+use super::sandbox::{kill_process_group, BashSandbox, SandboxPlan};
 use super::security::SecurityManager;
 use super::types::{JsonSchema, PropertySchema, ToolDefinition, ToolError, ToolResult};
+use crate::agent::types::StreamEvent;
 use async_trait::async_trait;
 use std::collections::HashMap;
 use std::env;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::Stdio;
 use std::sync::Arc;
 use std::time::Duration;
-use tokio::io::AsyncReadExt;
+use tokio::io::{AsyncRead, AsyncReadExt};
 use tokio::process::Command;
+use tokio::sync::mpsc;
 use tokio::time::timeout;
 use tracing::{debug, info, warn};
 
@@ -32,6 +37,9 @@ const DEFAULT_TIMEOUT_SECS: u64 = 120;
 /// 最大输出大小（字节）
 const MAX_OUTPUT_SIZE: usize = 1024 * 1024; // 1MB
 
+/// 读取输出的缓冲区大小（字节）
+const READ_CHUNK_SIZE: usize = 8 * 1024;
+
 /// Shell 类型
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ShellType {
@@ -137,6 +145,8 @@ pub struct BashTool {
     timeout_secs: u64,
     /// Shell 类型
     shell_type: ShellType,
+    /// 隔离执行配置，未设置或未启用时直接在用户 shell 中执行
+    sandbox: Option<Arc<BashSandbox>>,
 }
 
 impl BashTool {
@@ -150,9 +160,16 @@ impl BashTool {
             working_dir,
             timeout_secs: DEFAULT_TIMEOUT_SECS,
             shell_type,
+            sandbox: None,
         }
     }
 
+    /// 设置隔离执行配置
+    pub fn with_sandbox(mut self, sandbox: Arc<BashSandbox>) -> Self {
+        self.sandbox = Some(sandbox);
+        self
+    }
+
     /// 设置超时时间
     pub fn with_timeout(mut self, timeout_secs: u64) -> Self {
         self.timeout_secs = timeout_secs;
@@ -268,33 +285,32 @@ impl BashTool {
         command: &str,
         working_dir: Option<&PathBuf>,
         timeout_secs: Option<u64>,
+    ) -> Result<BashExecutionResult, ToolError> {
+        self.execute_command_with_output(command, working_dir, timeout_secs, None)
+            .await
+    }
+
+    /// 执行命令，并将输出增量实时推送到 `output`
+    async fn execute_command_with_output(
+        &self,
+        command: &str,
+        working_dir: Option<&PathBuf>,
+        timeout_secs: Option<u64>,
+        output: Option<OutputSink>,
     ) -> Result<BashExecutionResult, ToolError> {
         let work_dir = working_dir.unwrap_or(&self.working_dir);
         let timeout_duration = Duration::from_secs(timeout_secs.unwrap_or(self.timeout_secs));
+        let sandbox = self.prepare_sandbox();
 
         info!(
-            "[BashTool] 执行命令: {} (工作目录: {:?}, 超时: {:?})",
-            command, work_dir, timeout_duration
+            "[BashTool] 执行命令: {} (工作目录: {:?}, 超时: {:?}, 隔离: {})",
+            command,
+            work_dir,
+            timeout_duration,
+            sandbox.is_some()
         );
 
-        // 构建命令
-        let mut cmd = Command::new(self.shell_type.executable());
-
-        // 添加命令参数
-        for arg in self.shell_type.command_args() {
-            cmd.arg(arg);
-        }
-        cmd.arg(command);
-
-        // 设置工作目录
-        // Requirements: 3.6 - THE Bash_Executor SHALL support a configurable working directory
-        cmd.current_dir(work_dir);
-
-        // 设置环境变量
-        // Requirements: 3.4 - prevent interactive prompts
-        for (key, value) in Self::get_non_interactive_env() {
-            cmd.env(key, value);
-        }
+        let mut cmd = self.build_command(command, work_dir, sandbox.as_ref());
 
         // 配置标准输入输出
         cmd.stdin(Stdio::null());
@@ -307,90 +323,132 @@ impl BashTool {
             .map_err(|e| ToolError::ExecutionFailed(format!("无法启动 shell 进程: {}", e)))?;
 
         // 获取输出流
-        let mut stdout = child
+        let stdout = child
             .stdout
             .take()
             .ok_or_else(|| ToolError::ExecutionFailed("无法获取 stdout".to_string()))?;
-        let mut stderr = child
+        let stderr = child
             .stderr
             .take()
             .ok_or_else(|| ToolError::ExecutionFailed("无法获取 stderr".to_string()))?;
 
-        // 异步读取输出
-        let stdout_handle = tokio::spawn(async move {
-            let mut buffer = Vec::new();
-            let _ = stdout.read_to_end(&mut buffer).await;
-            buffer
-        });
-
-        let stderr_handle = tokio::spawn(async move {
-            let mut buffer = Vec::new();
-            let _ = stderr.read_to_end(&mut buffer).await;
-            buffer
-        });
+        // 异步读取输出，每个流最多保留 max_output 字节
+        let max_output = sandbox
+            .as_ref()
+            .map_or(MAX_OUTPUT_SIZE, |plan| plan.max_output_bytes());
+        let stdout_handle = tokio::spawn(read_output(
+            stdout,
+            max_output,
+            output.as_ref().map(|o| o.for_stream("stdout")),
+        ));
+        let stderr_handle = tokio::spawn(read_output(
+            stderr,
+            max_output,
+            output.as_ref().map(|o| o.for_stream("stderr")),
+        ));
 
         // 等待进程完成（带超时）
         // Requirements: 3.3 - WHEN a command exceeds the timeout limit, THE Bash_Executor SHALL terminate it
         let result = timeout(timeout_duration, child.wait()).await;
 
-        match result {
-            Ok(Ok(status)) => {
-                // 进程正常完成
-                let stdout_bytes = stdout_handle.await.unwrap_or_default();
-                let stderr_bytes = stderr_handle.await.unwrap_or_default();
-
-                let stdout_str =
-                    Self::truncate_output(String::from_utf8_lossy(&stdout_bytes).to_string());
-                let stderr_str =
-                    Self::truncate_output(String::from_utf8_lossy(&stderr_bytes).to_string());
-
-                let exit_code = status.code().unwrap_or(-1);
-
-                debug!(
-                    "[BashTool] 命令完成: exit_code={}, stdout_len={}, stderr_len={}",
-                    exit_code,
-                    stdout_str.len(),
-                    stderr_str.len()
-                );
-
-                Ok(BashExecutionResult::success(
-                    stdout_str, stderr_str, exit_code,
-                ))
-            }
+        let timed_out = match &result {
+            Ok(Ok(_)) => false,
             Ok(Err(e)) => {
                 // 进程等待失败
-                Err(ToolError::ExecutionFailed(format!("等待进程失败: {}", e)))
+                return Err(ToolError::ExecutionFailed(format!("等待进程失败: {}", e)));
             }
             Err(_) => {
                 // 超时
                 warn!("[BashTool] 命令超时，正在终止进程");
 
-                // 尝试终止进程
+                // 隔离模式下终止整个进程组，避免后台子进程占用输出管道
+                if let (Some(_), Some(pid)) = (&sandbox, child.id()) {
+                    kill_process_group(pid);
+                }
                 let _ = child.kill().await;
+                true
+            }
+        };
 
-                // 获取已有的输出
-                let stdout_bytes = stdout_handle.await.unwrap_or_default();
-                let stderr_bytes = stderr_handle.await.unwrap_or_default();
-
-                let stdout_str =
-                    Self::truncate_output(String::from_utf8_lossy(&stdout_bytes).to_string());
-                let stderr_str =
-                    Self::truncate_output(String::from_utf8_lossy(&stderr_bytes).to_string());
+        // 获取已有的输出
+        let (stdout_bytes, stdout_total) = stdout_handle.await.unwrap_or_default();
+        let (stderr_bytes, stderr_total) = stderr_handle.await.unwrap_or_default();
 
-                Ok(BashExecutionResult::timeout(stdout_str, stderr_str))
+        let stdout_str = Self::format_captured_output(stdout_bytes, stdout_total);
+        let mut stderr_str = Self::format_captured_output(stderr_bytes, stderr_total);
+        if let Some(plan) = &sandbox {
+            for note in plan.notes() {
+                if !stderr_str.is_empty() {
+                    stderr_str.push('\n');
+                }
+                stderr_str.push_str(note);
             }
         }
+
+        if timed_out {
+            return Ok(BashExecutionResult::timeout(stdout_str, stderr_str));
+        }
+
+        let exit_code = match result {
+            Ok(Ok(status)) => status.code().unwrap_or(-1),
+            _ => -1,
+        };
+
+        debug!(
+            "[BashTool] 命令完成: exit_code={}, stdout_len={}, stderr_len={}",
+            exit_code, stdout_total, stderr_total
+        );
+
+        Ok(BashExecutionResult::success(
+            stdout_str, stderr_str, exit_code,
+        ))
     }
 
-    /// 截断过长的输出
-    fn truncate_output(output: String) -> String {
-        if output.len() > MAX_OUTPUT_SIZE {
-            let truncated = &output[..MAX_OUTPUT_SIZE];
-            format!(
-                "{}\n\n[输出已截断，原始大小: {} 字节]",
-                truncated,
-                output.len()
-            )
+    /// 按当前隔离配置生成本次执行的隔离方案，未启用时返回 None
+    pub(crate) fn prepare_sandbox(&self) -> Option<SandboxPlan> {
+        self.sandbox
+            .as_ref()
+            .and_then(|sandbox| sandbox.prepare(&self.working_dir))
+    }
+
+    /// 构建 shell 命令（工作目录、隔离方案、非交互环境变量），不设置标准输入输出
+    pub(crate) fn build_command(
+        &self,
+        command: &str,
+        work_dir: &Path,
+        sandbox: Option<&SandboxPlan>,
+    ) -> Command {
+        let mut cmd = Command::new(self.shell_type.executable());
+
+        // 添加命令参数
+        for arg in self.shell_type.command_args() {
+            cmd.arg(arg);
+        }
+        cmd.arg(command);
+
+        // 设置工作目录
+        // Requirements: 3.6 - THE Bash_Executor SHALL support a configurable working directory
+        cmd.current_dir(work_dir);
+
+        // 隔离模式：清理环境变量、独立进程组、资源限制和命名空间
+        if let Some(plan) = sandbox {
+            plan.apply(&mut cmd);
+        }
+
+        // 设置环境变量
+        // Requirements: 3.4 - prevent interactive prompts
+        for (key, value) in Self::get_non_interactive_env() {
+            cmd.env(key, value);
+        }
+
+        cmd
+    }
+
+    /// 将捕获的输出转为文本，超出上限时附加截断说明
+    fn format_captured_output(captured: Vec<u8>, total: usize) -> String {
+        let output = String::from_utf8_lossy(&captured).to_string();
+        if total > captured.len() {
+            format!("{}\n\n[输出已截断，原始大小: {} 字节]", output, total)
         } else {
             output
         }
@@ -412,6 +470,92 @@ impl BashTool {
     }
 }
 
+/// 输出增量推送目标
+#[derive(Clone)]
+struct OutputSink {
+    tx: mpsc::Sender<StreamEvent>,
+    tool_id: String,
+    stream: &'static str,
+}
+
+impl OutputSink {
+    fn for_stream(&self, stream: &'static str) -> Self {
+        Self {
+            stream,
+            ..self.clone()
+        }
+    }
+
+    async fn send(&self, text: String) {
+        let _ = self
+            .tx
+            .send(StreamEvent::ToolOutput {
+                tool_id: self.tool_id.clone(),
+                stream: self.stream.to_string(),
+                text,
+            })
+            .await;
+    }
+}
+
+/// 读取输出流直到结束，返回保留的内容和总字节数
+///
+/// 超过 `limit` 的部分继续读取但丢弃（避免子进程阻塞在写管道上），保留的部分实时推送到 `sink`
+async fn read_output<R: AsyncRead + Unpin>(
+    mut reader: R,
+    limit: usize,
+    sink: Option<OutputSink>,
+) -> (Vec<u8>, usize) {
+    let mut captured = Vec::new();
+    let mut total = 0;
+    // 尚未推送的字节（可能以不完整的 UTF-8 字符结尾）
+    let mut pending = Vec::new();
+    let mut buffer = vec![0u8; READ_CHUNK_SIZE];
+
+    loop {
+        let n = match reader.read(&mut buffer).await {
+            Ok(0) | Err(_) => break,
+            Ok(n) => n,
+        };
+        total += n;
+        let keep = n.min(limit.saturating_sub(captured.len()));
+        if keep == 0 {
+            continue;
+        }
+        captured.extend_from_slice(&buffer[..keep]);
+        if let Some(sink) = &sink {
+            pending.extend_from_slice(&buffer[..keep]);
+            let text = take_utf8_prefix(&mut pending);
+            if !text.is_empty() {
+                sink.send(text).await;
+            }
+        }
+    }
+
+    if let Some(sink) = &sink {
+        if !pending.is_empty() {
+            sink.send(String::from_utf8_lossy(&pending).to_string())
+                .await;
+        }
+    }
+    (captured, total)
+}
+
+/// 取出缓冲区开头的完整 UTF-8 文本，末尾不完整的字符留在缓冲区中
+fn take_utf8_prefix(pending: &mut Vec<u8>) -> String {
+    let valid = match std::str::from_utf8(pending) {
+        Ok(_) => pending.len(),
+        // 末尾字符不完整，等待后续字节
+        Err(e) if e.error_len().is_none() => e.valid_up_to(),
+        // 无效字节，按有损方式输出全部内容
+        Err(_) => pending.len(),
+    };
+    let rest = pending.split_off(valid);
+    let text = String::from_utf8_lossy(pending).to_string();
+    *pending = rest;
+    text
+}
+
 #[async_trait]
 impl Tool for BashTool {
     fn definition(&self) -> ToolDefinition {
@@ -442,6 +586,15 @@ impl Tool for BashTool {
     }
 
     async fn execute(&self, args: serde_json::Value) -> Result<ToolResult, ToolError> {
+        self.execute_with_context(args, &ToolContext::default())
+            .await
+    }
+
+    async fn execute_with_context(
+        &self,
+        args: serde_json::Value,
+        context: &ToolContext,
+    ) -> Result<ToolResult, ToolError> {
         // 解析参数
         let command = args
             .get("command")
@@ -453,9 +606,14 @@ impl Tool for BashTool {
             .and_then(|v| v.as_u64())
             .unwrap_or(self.timeout_secs);
 
-        // 执行命令
+        // 执行命令，输出实时推送给前端
+        let output = context.event_tx.clone().map(|tx| OutputSink {
+            tx,
+            tool_id: context.tool_call_id.clone(),
+            stream: "stdout",
+        });
         let result = self
-            .execute_command(command, None, Some(timeout_secs))
+            .execute_command_with_output(command, None, Some(timeout_secs), output)
             .await?;
 
         // 构建输出
@@ -637,13 +795,168 @@ mod tests {
     fn test_truncate_output() {
         // 短输出不截断
         let short = "Hello".to_string();
-        assert_eq!(BashTool::truncate_output(short.clone()), short);
+        assert_eq!(
+            BashTool::format_captured_output(short.clone().into_bytes(), short.len()),
+            short
+        );
 
         // 长输出截断
-        let long = "x".repeat(MAX_OUTPUT_SIZE + 100);
-        let truncated = BashTool::truncate_output(long.clone());
-        assert!(truncated.len() < long.len());
+        let long = "x".repeat(MAX_OUTPUT_SIZE);
+        let truncated = BashTool::format_captured_output(long.into_bytes(), MAX_OUTPUT_SIZE + 100);
         assert!(truncated.contains("[输出已截断"));
+        assert!(truncated.contains(&(MAX_OUTPUT_SIZE + 100).to_string()));
+    }
+
+    #[test]
+    fn test_take_utf8_prefix() {
+        // "你" = E4 BD A0，拆成两段
+        let mut pending = vec![b'a', 0xE4, 0xBD];
+        assert_eq!(take_utf8_prefix(&mut pending), "a");
+        assert_eq!(pending, vec![0xE4, 0xBD]);
+
+        pending.push(0xA0);
+        assert_eq!(take_utf8_prefix(&mut pending), "你");
+        assert!(pending.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_output_streamed_and_capped() {
+        let (tool, _temp_dir) = setup_test_tool();
+        let (tx, mut rx) = mpsc::channel(64);
+        let context = ToolContext {
+            session_id: None,
+            tool_call_id: "call_1".to_string(),
+            event_tx: Some(tx),
+            require_rule_for_writes: false,
+        };
+
+        let result = tool
+            .execute_with_context(
+                serde_json::json!({"command": "echo out; echo err >&2"}),
+                &context,
+            )
+            .await
+            .unwrap();
+        assert!(result.success);
+        drop(context);
+
+        let mut streamed = Vec::new();
+        while let Some(event) = rx.recv().await {
+            if let StreamEvent::ToolOutput {
+                tool_id,
+                stream,
+                text,
+            } = event
+            {
+                assert_eq!(tool_id, "call_1");
+                streamed.push((stream, text));
+            }
+        }
+        assert!(streamed.contains(&("stdout".to_string(), "out\n".to_string())));
+        assert!(streamed.contains(&("stderr".to_string(), "err\n".to_string())));
+
+        // 超出上限的输出被丢弃但仍计入总大小
+        let (captured, total) = read_output(&[b'x'; 5000][..], 1000, None).await;
+        assert_eq!(captured.len(), 1000);
+        assert_eq!(total, 5000);
+    }
+
+    #[cfg(target_os = "linux")]
+    fn sandboxed_tool(
+        workspace: &std::path::Path,
+        settings: crate::config::BashSandboxSettings,
+    ) -> BashTool {
+        let security = Arc::new(SecurityManager::new(workspace));
+        BashTool::new(security).with_sandbox(Arc::new(BashSandbox::new(settings)))
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_sandbox_resource_limits() {
+        let temp_dir = TempDir::new().unwrap();
+        let tool = sandboxed_tool(
+            temp_dir.path(),
+            crate::config::BashSandboxSettings {
+                enabled: true,
+                cpu_time_secs: 7,
+                file_size_mb: 1,
+                deny_network: false,
+                read_only_outside_workspace: false,
+                ..Default::default()
+            },
+        );
+
+        let result = tool.execute_command("ulimit -t", None, None).await.unwrap();
+        assert_eq!(result.stdout.trim(), "7");
+
+        // 超过文件大小限制的写入被截断
+        let result = tool
+            .execute_command("head -c 2097152 /dev/zero > big.bin", None, None)
+            .await
+            .unwrap();
+        assert!(!result.is_success());
+        let size = std::fs::metadata(temp_dir.path().join("big.bin"))
+            .unwrap()
+            .len();
+        assert!(size <= 1024 * 1024);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_sandbox_network_and_read_only() {
+        if !super::super::sandbox::namespaces_available() {
+            return;
+        }
+        let workspace = TempDir::new().unwrap();
+        let outside = TempDir::new().unwrap();
+        let tool = sandboxed_tool(
+            workspace.path(),
+            crate::config::BashSandboxSettings {
+                enabled: true,
+                writable_paths: Vec::new(),
+                ..Default::default()
+            },
+        );
+
+        // 独立网络命名空间中只有 lo
+        let result = tool
+            .execute_command("grep -c : /proc/net/dev", None, None)
+            .await
+            .unwrap();
+        assert_eq!(result.stdout.trim(), "1");
+
+        let command = format!(
+            "touch inside.txt && touch {}/outside.txt",
+            outside.path().display()
+        );
+        let result = tool.execute_command(&command, None, None).await.unwrap();
+        assert!(!result.is_success());
+        assert!(workspace.path().join("inside.txt").exists());
+        assert!(!outside.path().join("outside.txt").exists());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_sandbox_timeout_kills_process_group() {
+        let temp_dir = TempDir::new().unwrap();
+        let tool = sandboxed_tool(
+            temp_dir.path(),
+            crate::config::BashSandboxSettings {
+                enabled: true,
+                deny_network: false,
+                read_only_outside_workspace: false,
+                ..Default::default()
+            },
+        );
+
+        // 后台子进程持有输出管道，只终止 shell 会一直等待输出结束
+        let started = std::time::Instant::now();
+        let result = tool
+            .execute_command("sleep 30 & sleep 30", None, Some(1))
+            .await
+            .unwrap();
+        assert!(result.timed_out);
+        assert!(started.elapsed() < Duration::from_secs(10));
     }
 
     #[test]
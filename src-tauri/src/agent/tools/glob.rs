@@ -0,0 +1,244 @@
+//! 文件匹配工具模块
+//!
+//! 按 glob 模式查找文件（如 `**/*.rs`、`src/**/test_*.{ts,tsx}`），
+//! 默认遵循 .gitignore 并跳过隐藏文件
+
+use super::registry::Tool;
+use super::security::SecurityManager;
+use super::types::{JsonSchema, PropertySchema, ToolDefinition, ToolError, ToolResult};
+use super::walk::{self, WalkOptions, WalkRoot};
+use async_trait::async_trait;
+use globset::GlobBuilder;
+use serde::Serialize;
+use std::sync::Arc;
+use tracing::info;
+
+/// 默认最大结果数
+const DEFAULT_MAX_RESULTS: usize = 200;
+
+/// 最大结果数上限
+const MAX_RESULTS_LIMIT: usize = 2000;
+
+/// 文件匹配结果
+#[derive(Debug, Clone, Serialize)]
+pub struct GlobResult {
+    /// 匹配的文件路径（按路径排序）
+    pub paths: Vec<String>,
+    /// 是否因达到结果上限而截断
+    pub truncated: bool,
+}
+
+/// 文件匹配工具
+pub struct GlobTool {
+    /// 安全管理器
+    security: Arc<SecurityManager>,
+}
+
+impl GlobTool {
+    /// 创建新的文件匹配工具
+    pub fn new(security: Arc<SecurityManager>) -> Self {
+        Self { security }
+    }
+
+    /// 在 `path`（为空时为基础目录）下查找相对路径匹配 `pattern` 的文件
+    ///
+    /// `*` 不匹配路径分隔符，`**` 匹配任意层目录；不含 `/` 的模式匹配任意层级的文件名。
+    pub fn glob(
+        &self,
+        pattern: &str,
+        path: Option<&str>,
+        max_results: usize,
+        include_ignored: bool,
+    ) -> Result<GlobResult, ToolError> {
+        let pattern = pattern.trim().trim_start_matches("./");
+        if pattern.is_empty() {
+            return Err(ToolError::InvalidArguments("pattern 不能为空".to_string()));
+        }
+        let pattern = if pattern.contains('/') {
+            pattern.to_string()
+        } else {
+            format!("**/{}", pattern)
+        };
+        let matcher = GlobBuilder::new(&pattern)
+            .literal_separator(true)
+            .build()
+            .map_err(|e| ToolError::InvalidArguments(format!("无效的 glob: {}", e)))?
+            .compile_matcher();
+
+        let root = WalkRoot::resolve(&self.security, path)?;
+        let options = WalkOptions {
+            include_ignored,
+            max_depth: None,
+        };
+
+        let mut result = GlobResult {
+            paths: Vec::new(),
+            truncated: false,
+        };
+        for entry in walk::files(&root.path, options) {
+            if !matcher.is_match(root.relative(entry.path())) {
+                continue;
+            }
+            if result.paths.len() >= max_results {
+                result.truncated = true;
+                break;
+            }
+            result.paths.push(root.display_path(entry.path()));
+        }
+        Ok(result)
+    }
+}
+
+#[async_trait]
+impl Tool for GlobTool {
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition::new(
+            "glob",
+            "Find files by glob pattern, e.g. \"**/*.rs\" or \"src/**/*.{ts,tsx}\". \
+             Patterns without a slash match file names at any depth. \
+             Respects .gitignore and skips hidden files by default. Returns matching paths.",
+        )
+        .with_parameters(
+            JsonSchema::new()
+                .add_property(
+                    "pattern",
+                    PropertySchema::string("The glob pattern, relative to path."),
+                    true,
+                )
+                .add_property(
+                    "path",
+                    PropertySchema::string(
+                        "Directory to search in. Defaults to the base directory.",
+                    ),
+                    false,
+                )
+                .add_property(
+                    "max_results",
+                    PropertySchema::integer("Maximum number of paths to return.")
+                        .with_default(serde_json::json!(DEFAULT_MAX_RESULTS)),
+                    false,
+                )
+                .add_property(
+                    "include_ignored",
+                    PropertySchema::boolean("Also match hidden and gitignored files.")
+                        .with_default(serde_json::json!(false)),
+                    false,
+                ),
+        )
+    }
+
+    fn is_read_only(&self, _args: &serde_json::Value) -> bool {
+        true
+    }
+
+    async fn execute(&self, args: serde_json::Value) -> Result<ToolResult, ToolError> {
+        let pattern = args
+            .get("pattern")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| ToolError::InvalidArguments("缺少 pattern 参数".to_string()))?
+            .to_string();
+        let path = args
+            .get("path")
+            .and_then(|v| v.as_str())
+            .map(str::to_string);
+        let max_results = args
+            .get("max_results")
+            .and_then(|v| v.as_u64())
+            .map(|n| (n as usize).clamp(1, MAX_RESULTS_LIMIT))
+            .unwrap_or(DEFAULT_MAX_RESULTS);
+        let include_ignored = args
+            .get("include_ignored")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+
+        info!("[GlobTool] 查找文件: pattern={}, path={:?}", pattern, path);
+
+        let tool = GlobTool::new(Arc::clone(&self.security));
+        let result = tokio::task::spawn_blocking(move || {
+            tool.glob(&pattern, path.as_deref(), max_results, include_ignored)
+        })
+        .await
+        .map_err(|e| ToolError::ExecutionFailed(format!("查找任务异常: {}", e)))??;
+
+        if result.paths.is_empty() {
+            return Ok(ToolResult::success("未找到匹配的文件"));
+        }
+        let mut output = result.paths.join("\n");
+        output.push('\n');
+        if result.truncated {
+            output.push_str(&format!(
+                "\n[结果已截断，仅显示前 {} 个文件]\n",
+                result.paths.len()
+            ));
+        }
+        Ok(ToolResult::success(output))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn setup_test_tool() -> (GlobTool, TempDir) {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        fs::create_dir_all(root.join("src/components")).unwrap();
+        fs::create_dir_all(root.join("node_modules/pkg")).unwrap();
+        fs::write(root.join(".gitignore"), "node_modules/\n").unwrap();
+        fs::write(root.join("src/main.ts"), "").unwrap();
+        fs::write(root.join("src/components/App.tsx"), "").unwrap();
+        fs::write(root.join("src/components/App.css"), "").unwrap();
+        fs::write(root.join("node_modules/pkg/index.ts"), "").unwrap();
+        let tool = GlobTool::new(Arc::new(SecurityManager::new(root)));
+        (tool, temp_dir)
+    }
+
+    #[test]
+    fn test_glob_recursive_and_braces() {
+        let (tool, _dir) = setup_test_tool();
+
+        let result = tool.glob("**/*.{ts,tsx}", None, 100, false).unwrap();
+        assert_eq!(result.paths, vec!["src/components/App.tsx", "src/main.ts"]);
+
+        // 不含 / 的模式匹配任意层级的文件名
+        let result = tool.glob("App.*", Some("src"), 100, false).unwrap();
+        assert_eq!(
+            result.paths,
+            vec!["src/components/App.css", "src/components/App.tsx"]
+        );
+
+        // * 不跨越目录
+        let result = tool.glob("src/*.ts*", None, 100, false).unwrap();
+        assert_eq!(result.paths, vec!["src/main.ts"]);
+    }
+
+    #[test]
+    fn test_glob_ignored_files_and_limit() {
+        let (tool, _dir) = setup_test_tool();
+
+        let result = tool.glob("**/index.ts", None, 100, false).unwrap();
+        assert!(result.paths.is_empty());
+        let result = tool.glob("**/index.ts", None, 100, true).unwrap();
+        assert_eq!(result.paths, vec!["node_modules/pkg/index.ts"]);
+
+        let result = tool.glob("*", None, 2, false).unwrap();
+        assert_eq!(result.paths.len(), 2);
+        assert!(result.truncated);
+
+        assert!(tool.glob("[", None, 10, false).is_err());
+        assert!(tool.glob("*", Some("../"), 10, false).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_tool_execute() {
+        let (tool, _dir) = setup_test_tool();
+        let result = tool
+            .execute(serde_json::json!({"pattern": "*.css"}))
+            .await
+            .unwrap();
+        assert!(result.success);
+        assert_eq!(result.output, "src/components/App.css\n");
+    }
+}
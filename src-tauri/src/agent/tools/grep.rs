@@ -0,0 +1,603 @@
+//! 内容搜索工具模块
+//!
+//! 提供类似 ripgrep 的正则搜索功能
+//!
+//! ## 功能
+//! - 正则搜索（可忽略大小写）
+//! - glob 文件过滤（如 `*.rs`、`src/**/*.{ts,tsx}`）
+//! - 上下文行
+//! - 三种输出模式：匹配内容、匹配文件、每个文件的匹配数
+//! - 结果数量上限，跳过二进制文件和超大文件
+
+use super::registry::Tool;
+use super::security::SecurityManager;
+use super::types::{JsonSchema, PropertySchema, ToolDefinition, ToolError, ToolResult};
+use super::walk::{self, WalkOptions, WalkRoot};
+use async_trait::async_trait;
+use ignore::overrides::OverrideBuilder;
+use regex::{Regex, RegexBuilder};
+use serde::Serialize;
+use std::fs;
+use std::sync::Arc;
+use tracing::{debug, info};
+
+/// 默认最大结果数
+const DEFAULT_MAX_RESULTS: usize = 100;
+
+/// 最大结果数上限
+const MAX_RESULTS_LIMIT: usize = 1000;
+
+/// 最大上下文行数
+const MAX_CONTEXT_LINES: usize = 10;
+
+/// 单行最大显示字符数
+const MAX_LINE_CHARS: usize = 500;
+
+/// 跳过超过此大小的文件
+const MAX_FILE_BYTES: u64 = 10 * 1024 * 1024;
+
+/// 正则表达式编译大小上限
+const REGEX_SIZE_LIMIT: usize = 10 * 1024 * 1024;
+
+/// 输出模式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GrepOutputMode {
+    /// 匹配行（含上下文）
+    Content,
+    /// 只列出匹配的文件
+    FilesWithMatches,
+    /// 每个文件的匹配行数
+    Count,
+}
+
+impl GrepOutputMode {
+    fn parse(value: &str) -> Result<Self, ToolError> {
+        match value {
+            "content" => Ok(Self::Content),
+            "files_with_matches" => Ok(Self::FilesWithMatches),
+            "count" => Ok(Self::Count),
+            other => Err(ToolError::InvalidArguments(format!(
+                "无效的 output_mode: {}（可选 content、files_with_matches、count）",
+                other
+            ))),
+        }
+    }
+}
+
+/// 搜索选项
+#[derive(Debug, Clone)]
+pub struct GrepOptions {
+    /// 文件过滤 glob，为空时搜索全部文件
+    pub globs: Vec<String>,
+    /// 忽略大小写
+    pub case_insensitive: bool,
+    /// 匹配行前后的上下文行数
+    pub context: usize,
+    /// 输出模式
+    pub output_mode: GrepOutputMode,
+    /// 最大结果数（content 模式为匹配行数，其他模式为文件数）
+    pub max_results: usize,
+    /// 包含隐藏文件和被忽略规则排除的文件
+    pub include_ignored: bool,
+}
+
+impl Default for GrepOptions {
+    fn default() -> Self {
+        Self {
+            globs: Vec::new(),
+            case_insensitive: false,
+            context: 0,
+            output_mode: GrepOutputMode::Content,
+            max_results: DEFAULT_MAX_RESULTS,
+            include_ignored: false,
+        }
+    }
+}
+
+/// 单个匹配行
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct GrepMatch {
+    /// 文件路径
+    pub path: String,
+    /// 行号（从 1 开始）
+    pub line_number: usize,
+    /// 匹配行内容
+    pub line: String,
+    /// 匹配行之前的上下文（行号, 内容）
+    pub before: Vec<(usize, String)>,
+    /// 匹配行之后的上下文（行号, 内容）
+    pub after: Vec<(usize, String)>,
+}
+
+/// 单个文件的匹配统计
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct GrepFileMatches {
+    /// 文件路径
+    pub path: String,
+    /// 匹配行数
+    pub count: usize,
+}
+
+/// 搜索结果
+#[derive(Debug, Clone, Serialize)]
+pub struct GrepResult {
+    /// 输出模式
+    pub output_mode: GrepOutputMode,
+    /// 匹配行（仅 content 模式）
+    pub matches: Vec<GrepMatch>,
+    /// 匹配的文件
+    pub files: Vec<GrepFileMatches>,
+    /// 搜索过的文件数
+    pub files_searched: usize,
+    /// 是否因达到结果上限而截断
+    pub truncated: bool,
+}
+
+/// 内容搜索工具
+pub struct GrepTool {
+    /// 安全管理器
+    security: Arc<SecurityManager>,
+}
+
+impl GrepTool {
+    /// 创建新的内容搜索工具
+    pub fn new(security: Arc<SecurityManager>) -> Self {
+        Self { security }
+    }
+
+    /// 在 `path`（文件或目录，为空时为基础目录）中搜索正则表达式
+    pub fn grep(
+        &self,
+        pattern: &str,
+        path: Option<&str>,
+        options: &GrepOptions,
+    ) -> Result<GrepResult, ToolError> {
+        let regex = RegexBuilder::new(pattern)
+            .case_insensitive(options.case_insensitive)
+            .size_limit(REGEX_SIZE_LIMIT)
+            .build()
+            .map_err(|e| ToolError::InvalidArguments(format!("无效的正则表达式: {}", e)))?;
+        let root = WalkRoot::resolve(&self.security, path)?;
+
+        let mut result = GrepResult {
+            output_mode: options.output_mode,
+            matches: Vec::new(),
+            files: Vec::new(),
+            files_searched: 0,
+            truncated: false,
+        };
+
+        if root.path.is_file() {
+            search_file(&root, &root.path, &regex, options, &mut result);
+            return Ok(result);
+        }
+
+        let mut overrides = OverrideBuilder::new(&root.path);
+        for glob in &options.globs {
+            overrides
+                .add(glob)
+                .map_err(|e| ToolError::InvalidArguments(format!("无效的 glob: {}", e)))?;
+        }
+        let overrides = overrides
+            .build()
+            .map_err(|e| ToolError::InvalidArguments(format!("无效的 glob: {}", e)))?;
+
+        let walk_options = WalkOptions {
+            include_ignored: options.include_ignored,
+            max_depth: None,
+        };
+        for entry in walk::files(&root.path, walk_options) {
+            if !overrides.is_empty() && !overrides.matched(entry.path(), false).is_whitelist() {
+                continue;
+            }
+            if !search_file(&root, entry.path(), &regex, options, &mut result) {
+                break;
+            }
+        }
+
+        Ok(result)
+    }
+}
+
+/// 搜索单个文件，达到结果上限时返回 false
+fn search_file(
+    root: &WalkRoot,
+    path: &std::path::Path,
+    regex: &Regex,
+    options: &GrepOptions,
+    result: &mut GrepResult,
+) -> bool {
+    if fs::metadata(path).map(|m| m.len()).unwrap_or(0) > MAX_FILE_BYTES {
+        debug!("[GrepTool] 跳过超大文件: {:?}", path);
+        return true;
+    }
+    let Ok(bytes) = fs::read(path) else {
+        return true;
+    };
+    // 与 ripgrep 一致：前 8KB 含 NUL 字节视为二进制文件
+    if bytes.iter().take(8192).any(|b| *b == 0) {
+        return true;
+    }
+    result.files_searched += 1;
+
+    let content = String::from_utf8_lossy(&bytes);
+    let lines: Vec<&str> = content.lines().collect();
+    let display = root.display_path(path);
+    let mut count = 0;
+
+    for (index, line) in lines.iter().enumerate() {
+        if !regex.is_match(line) {
+            continue;
+        }
+        count += 1;
+        if options.output_mode != GrepOutputMode::Content {
+            continue;
+        }
+        if result.matches.len() >= options.max_results {
+            result.truncated = true;
+            break;
+        }
+        let context = |range: std::ops::Range<usize>| {
+            range
+                .map(|i| (i + 1, truncate_line(lines[i])))
+                .collect::<Vec<_>>()
+        };
+        result.matches.push(GrepMatch {
+            path: display.clone(),
+            line_number: index + 1,
+            line: truncate_line(line),
+            before: context(index.saturating_sub(options.context)..index),
+            after: context(index + 1..(index + 1 + options.context).min(lines.len())),
+        });
+    }
+
+    if count > 0 {
+        if options.output_mode != GrepOutputMode::Content
+            && result.files.len() >= options.max_results
+        {
+            result.truncated = true;
+            return false;
+        }
+        result.files.push(GrepFileMatches {
+            path: display,
+            count,
+        });
+    }
+    !result.truncated
+}
+
+fn truncate_line(line: &str) -> String {
+    match line.char_indices().nth(MAX_LINE_CHARS) {
+        Some((i, _)) => format!("{}…", &line[..i]),
+        None => line.to_string(),
+    }
+}
+
+/// 格式化搜索结果（与 ripgrep 输出格式一致）
+fn format_result(result: &GrepResult) -> String {
+    let mut output = String::new();
+    match result.output_mode {
+        GrepOutputMode::Content => {
+            let mut last: Option<(&str, usize)> = None;
+            for m in &result.matches {
+                let first_line = m.before.first().map(|(n, _)| *n).unwrap_or(m.line_number);
+                // 与上一个匹配不连续时用 -- 分隔
+                if let Some((path, end)) = last {
+                    if path != m.path || first_line > end + 1 {
+                        output.push_str("--\n");
+                    }
+                }
+                for (n, line) in &m.before {
+                    if last.is_some_and(|(path, end)| path == m.path && *n <= end) {
+                        continue;
+                    }
+                    output.push_str(&format!("{}-{}-{}\n", m.path, n, line));
+                }
+                output.push_str(&format!("{}:{}:{}\n", m.path, m.line_number, m.line));
+                for (n, line) in &m.after {
+                    output.push_str(&format!("{}-{}-{}\n", m.path, n, line));
+                }
+                let end = m.after.last().map(|(n, _)| *n).unwrap_or(m.line_number);
+                last = Some((m.path.as_str(), end));
+            }
+        }
+        GrepOutputMode::FilesWithMatches => {
+            for file in &result.files {
+                output.push_str(&file.path);
+                output.push('\n');
+            }
+        }
+        GrepOutputMode::Count => {
+            for file in &result.files {
+                output.push_str(&format!("{}:{}\n", file.path, file.count));
+            }
+        }
+    }
+
+    if output.is_empty() {
+        output = format!("未找到匹配（搜索了 {} 个文件）\n", result.files_searched);
+    } else if result.truncated {
+        output.push_str("\n[结果已截断，请缩小搜索范围或增大 max_results]\n");
+    }
+    output
+}
+
+/// 解析 glob 参数（字符串或字符串数组）
+fn parse_globs(value: Option<&serde_json::Value>) -> Result<Vec<String>, ToolError> {
+    match value {
+        None | Some(serde_json::Value::Null) => Ok(Vec::new()),
+        Some(serde_json::Value::String(s)) => Ok(vec![s.clone()]),
+        Some(serde_json::Value::Array(items)) => items
+            .iter()
+            .map(|v| {
+                v.as_str()
+                    .map(str::to_string)
+                    .ok_or_else(|| ToolError::InvalidArguments("glob 必须是字符串".to_string()))
+            })
+            .collect(),
+        Some(_) => Err(ToolError::InvalidArguments(
+            "glob 必须是字符串或字符串数组".to_string(),
+        )),
+    }
+}
+
+#[async_trait]
+impl Tool for GrepTool {
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition::new(
+            "grep",
+            "Search file contents with a regular expression (ripgrep syntax). \
+             Respects .gitignore and skips hidden and binary files by default. \
+             Use output_mode to get matching lines, only matching file paths, or match counts per file.",
+        )
+        .with_parameters(
+            JsonSchema::new()
+                .add_property(
+                    "pattern",
+                    PropertySchema::string("The regular expression to search for."),
+                    true,
+                )
+                .add_property(
+                    "path",
+                    PropertySchema::string(
+                        "File or directory to search in. Defaults to the base directory.",
+                    ),
+                    false,
+                )
+                .add_property(
+                    "glob",
+                    PropertySchema::string(
+                        "Only search files matching this glob, e.g. \"*.rs\" or \"src/**/*.{ts,tsx}\". \
+                         Prefix with ! to exclude.",
+                    ),
+                    false,
+                )
+                .add_property(
+                    "case_insensitive",
+                    PropertySchema::boolean("Case insensitive search.")
+                        .with_default(serde_json::json!(false)),
+                    false,
+                )
+                .add_property(
+                    "context",
+                    PropertySchema::integer(
+                        "Number of lines to show before and after each match (content mode only).",
+                    )
+                    .with_default(serde_json::json!(0)),
+                    false,
+                )
+                .add_property(
+                    "output_mode",
+                    PropertySchema::string("What to return for matches.")
+                        .with_enum(vec![
+                            serde_json::json!("content"),
+                            serde_json::json!("files_with_matches"),
+                            serde_json::json!("count"),
+                        ])
+                        .with_default(serde_json::json!("content")),
+                    false,
+                )
+                .add_property(
+                    "max_results",
+                    PropertySchema::integer(
+                        "Maximum number of matching lines (content mode) or files (other modes).",
+                    )
+                    .with_default(serde_json::json!(DEFAULT_MAX_RESULTS)),
+                    false,
+                )
+                .add_property(
+                    "include_ignored",
+                    PropertySchema::boolean("Also search hidden and gitignored files.")
+                        .with_default(serde_json::json!(false)),
+                    false,
+                ),
+        )
+    }
+
+    fn is_read_only(&self, _args: &serde_json::Value) -> bool {
+        true
+    }
+
+    async fn execute(&self, args: serde_json::Value) -> Result<ToolResult, ToolError> {
+        let pattern = args
+            .get("pattern")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| ToolError::InvalidArguments("缺少 pattern 参数".to_string()))?
+            .to_string();
+        let path = args
+            .get("path")
+            .and_then(|v| v.as_str())
+            .map(str::to_string);
+
+        let options = GrepOptions {
+            globs: parse_globs(args.get("glob"))?,
+            case_insensitive: args
+                .get("case_insensitive")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false),
+            context: args
+                .get("context")
+                .and_then(|v| v.as_u64())
+                .map(|n| (n as usize).min(MAX_CONTEXT_LINES))
+                .unwrap_or(0),
+            output_mode: match args.get("output_mode").and_then(|v| v.as_str()) {
+                Some(mode) => GrepOutputMode::parse(mode)?,
+                None => GrepOutputMode::Content,
+            },
+            max_results: args
+                .get("max_results")
+                .and_then(|v| v.as_u64())
+                .map(|n| (n as usize).clamp(1, MAX_RESULTS_LIMIT))
+                .unwrap_or(DEFAULT_MAX_RESULTS),
+            include_ignored: args
+                .get("include_ignored")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false),
+        };
+
+        info!(
+            "[GrepTool] 搜索: pattern={}, path={:?}, globs={:?}",
+            pattern, path, options.globs
+        );
+
+        // 遍历和读取文件可能较慢，放到阻塞线程执行
+        let tool = GrepTool::new(Arc::clone(&self.security));
+        let result =
+            tokio::task::spawn_blocking(move || tool.grep(&pattern, path.as_deref(), &options))
+                .await
+                .map_err(|e| ToolError::ExecutionFailed(format!("搜索任务异常: {}", e)))??;
+
+        debug!(
+            "[GrepTool] 搜索完成: {} 个文件, {} 个匹配文件",
+            result.files_searched,
+            result.files.len()
+        );
+
+        Ok(ToolResult::success(format_result(&result)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn setup_test_tool() -> (GrepTool, TempDir) {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::create_dir_all(root.join("target")).unwrap();
+        fs::write(root.join(".gitignore"), "target/\n").unwrap();
+        fs::write(
+            root.join("src/main.rs"),
+            "fn main() {\n    let x = 1;\n    println!(\"{}\", x);\n}\n",
+        )
+        .unwrap();
+        fs::write(root.join("src/lib.rs"), "pub fn Main() {}\n").unwrap();
+        fs::write(root.join("notes.txt"), "main idea\n").unwrap();
+        fs::write(root.join("target/main.o"), "fn main").unwrap();
+        fs::write(root.join("data.bin"), b"fn main\0\x01").unwrap();
+        let tool = GrepTool::new(Arc::new(SecurityManager::new(root)));
+        (tool, temp_dir)
+    }
+
+    #[test]
+    fn test_tool_definition() {
+        let (tool, _dir) = setup_test_tool();
+        let def = tool.definition();
+        assert_eq!(def.name, "grep");
+        assert!(def.parameters.required.contains(&"pattern".to_string()));
+    }
+
+    #[test]
+    fn test_grep_content_with_glob_and_context() {
+        let (tool, _dir) = setup_test_tool();
+        let options = GrepOptions {
+            globs: vec!["*.rs".to_string()],
+            context: 1,
+            ..Default::default()
+        };
+        let result = tool.grep(r"fn \w+", None, &options).unwrap();
+
+        // target/ 被 .gitignore 排除，data.bin 为二进制文件，notes.txt 不匹配 glob
+        assert_eq!(
+            result
+                .files
+                .iter()
+                .map(|f| f.path.as_str())
+                .collect::<Vec<_>>(),
+            vec!["src/lib.rs", "src/main.rs"]
+        );
+        let main = &result.matches[1];
+        assert_eq!(main.line_number, 1);
+        assert!(main.before.is_empty());
+        assert_eq!(main.after, vec![(2, "    let x = 1;".to_string())]);
+
+        let output = format_result(&result);
+        assert!(output.contains("src/main.rs:1:fn main() {"));
+        assert!(output.contains("src/main.rs-2-    let x = 1;"));
+        assert!(output.contains("--\n"));
+    }
+
+    #[test]
+    fn test_grep_case_insensitive_and_count() {
+        let (tool, _dir) = setup_test_tool();
+        let options = GrepOptions {
+            case_insensitive: true,
+            output_mode: GrepOutputMode::Count,
+            ..Default::default()
+        };
+        let result = tool.grep("main", Some("src"), &options).unwrap();
+        assert_eq!(format_result(&result), "src/lib.rs:1\nsrc/main.rs:1\n");
+        assert!(result.matches.is_empty());
+    }
+
+    #[test]
+    fn test_grep_max_results_truncates() {
+        let (tool, _dir) = setup_test_tool();
+        let options = GrepOptions {
+            output_mode: GrepOutputMode::FilesWithMatches,
+            max_results: 1,
+            include_ignored: true,
+            ..Default::default()
+        };
+        let result = tool.grep("main", None, &options).unwrap();
+        assert_eq!(result.files.len(), 1);
+        assert!(result.truncated);
+    }
+
+    #[test]
+    fn test_grep_rejects_invalid_input() {
+        let (tool, _dir) = setup_test_tool();
+        let options = GrepOptions::default();
+        assert!(matches!(
+            tool.grep("(", None, &options),
+            Err(ToolError::InvalidArguments(_))
+        ));
+        assert!(matches!(
+            tool.grep("x", Some("../"), &options),
+            Err(ToolError::Security(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_tool_execute() {
+        let (tool, _dir) = setup_test_tool();
+        let result = tool
+            .execute(serde_json::json!({
+                "pattern": "println",
+                "output_mode": "files_with_matches"
+            }))
+            .await
+            .unwrap();
+        assert!(result.success);
+        assert_eq!(result.output, "src/main.rs\n");
+
+        let result = tool
+            .execute(serde_json::json!({"pattern": "nothing-matches-this"}))
+            .await
+            .unwrap();
+        assert!(result.output.starts_with("未找到匹配"));
+    }
+}
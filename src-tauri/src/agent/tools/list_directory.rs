@@ -0,0 +1,319 @@
+//! 目录树工具模块
+//!
+//! 以树形结构列出目录内容，默认遵循 .gitignore 并跳过隐藏文件
+//!
+//! ## 功能
+//! - 深度限制
+//! - 目录在前、按名称排序
+//! - 文件大小显示
+//! - 条目数量上限
+
+use super::registry::Tool;
+use super::security::SecurityManager;
+use super::types::{JsonSchema, PropertySchema, ToolDefinition, ToolError, ToolResult};
+use super::walk::{self, WalkOptions, WalkRoot};
+use async_trait::async_trait;
+use serde::Serialize;
+use std::sync::Arc;
+use tracing::info;
+
+/// 默认深度
+const DEFAULT_DEPTH: usize = 2;
+
+/// 最大深度
+const MAX_DEPTH: usize = 10;
+
+/// 最大条目数
+const MAX_ENTRIES: usize = 500;
+
+/// 目录树条目（深度优先顺序）
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct TreeEntry {
+    /// 相对于根目录的路径（`/` 分隔）
+    pub path: String,
+    /// 文件/目录名
+    pub name: String,
+    /// 深度（根目录的直接子项为 1）
+    pub depth: usize,
+    /// 是否为目录
+    pub is_dir: bool,
+    /// 文件大小（目录为 None）
+    pub size: Option<u64>,
+}
+
+/// 目录树结果
+#[derive(Debug, Clone, Serialize)]
+pub struct ListDirectoryResult {
+    /// 根目录（用户传入的路径）
+    pub root: String,
+    /// 条目（深度优先，目录在前）
+    pub entries: Vec<TreeEntry>,
+    /// 目录数
+    pub dir_count: usize,
+    /// 文件数
+    pub file_count: usize,
+    /// 是否因达到条目上限而截断
+    pub truncated: bool,
+}
+
+/// 目录树工具
+pub struct ListDirectoryTool {
+    /// 安全管理器
+    security: Arc<SecurityManager>,
+}
+
+impl ListDirectoryTool {
+    /// 创建新的目录树工具
+    pub fn new(security: Arc<SecurityManager>) -> Self {
+        Self { security }
+    }
+
+    /// 列出目录树
+    pub fn list_directory(
+        &self,
+        path: &str,
+        depth: usize,
+        include_ignored: bool,
+    ) -> Result<ListDirectoryResult, ToolError> {
+        let root = WalkRoot::resolve(&self.security, Some(path))?;
+        if !root.path.is_dir() {
+            return Err(ToolError::ExecutionFailed(format!("不是目录: {}", path)));
+        }
+
+        let options = WalkOptions {
+            include_ignored,
+            max_depth: Some(depth.clamp(1, MAX_DEPTH)),
+        };
+        let mut result = ListDirectoryResult {
+            root: root.display_path(&root.path),
+            entries: Vec::new(),
+            dir_count: 0,
+            file_count: 0,
+            truncated: false,
+        };
+        // 第一项为根目录本身
+        for entry in walk::walker(&root.path, options).flatten().skip(1) {
+            if result.entries.len() >= MAX_ENTRIES {
+                result.truncated = true;
+                break;
+            }
+            let is_dir = entry.file_type().is_some_and(|t| t.is_dir());
+            if is_dir {
+                result.dir_count += 1;
+            } else {
+                result.file_count += 1;
+            }
+            result.entries.push(TreeEntry {
+                path: root.relative(entry.path()),
+                name: entry.file_name().to_string_lossy().to_string(),
+                depth: entry.depth(),
+                is_dir,
+                size: if is_dir {
+                    None
+                } else {
+                    entry.metadata().ok().map(|m| m.len())
+                },
+            });
+        }
+        Ok(result)
+    }
+}
+
+/// 格式化为树形文本
+fn format_tree(result: &ListDirectoryResult) -> String {
+    let entries = &result.entries;
+
+    // 从后向前扫描，判断每个条目是否为同级最后一项
+    let mut is_last = vec![true; entries.len()];
+    let mut next_depth_seen: Vec<bool> = Vec::new();
+    for (i, entry) in entries.iter().enumerate().rev() {
+        if next_depth_seen.len() <= entry.depth {
+            next_depth_seen.resize(entry.depth + 1, false);
+        }
+        is_last[i] = !next_depth_seen[entry.depth];
+        next_depth_seen[entry.depth] = true;
+        // 进入上一级目录后，更深层级的同级状态重新计算
+        next_depth_seen.truncate(entry.depth + 1);
+    }
+
+    let mut output = format!("{}/\n", result.root.trim_end_matches('/'));
+    let mut ancestors_last: Vec<bool> = Vec::new();
+    for (entry, last) in entries.iter().zip(is_last) {
+        ancestors_last.truncate(entry.depth - 1);
+        for ancestor_last in &ancestors_last {
+            output.push_str(if *ancestor_last { "    " } else { "│   " });
+        }
+        output.push_str(if last { "└── " } else { "├── " });
+        output.push_str(&entry.name);
+        match entry.size {
+            _ if entry.is_dir => output.push('/'),
+            Some(size) => output.push_str(&format!(" ({})", format_size(size))),
+            None => {}
+        }
+        output.push('\n');
+        ancestors_last.push(last);
+    }
+
+    output.push_str(&format!(
+        "\n{} 个目录, {} 个文件",
+        result.dir_count, result.file_count
+    ));
+    if result.truncated {
+        output.push_str(&format!(
+            "（已截断，仅显示前 {} 项，请指定子目录或减小 depth）",
+            MAX_ENTRIES
+        ));
+    }
+    output.push('\n');
+    output
+}
+
+/// 格式化文件大小
+fn format_size(size: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+    let mut value = size as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", size)
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+#[async_trait]
+impl Tool for ListDirectoryTool {
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition::new(
+            "list_directory",
+            "List a directory as a tree, directories first. \
+             Respects .gitignore and skips hidden files by default. \
+             Use depth to control how many levels are shown.",
+        )
+        .with_parameters(
+            JsonSchema::new()
+                .add_property(
+                    "path",
+                    PropertySchema::string("The directory to list. Can be relative or absolute."),
+                    true,
+                )
+                .add_property(
+                    "depth",
+                    PropertySchema::integer("How many levels to show (1-10).")
+                        .with_default(serde_json::json!(DEFAULT_DEPTH)),
+                    false,
+                )
+                .add_property(
+                    "include_ignored",
+                    PropertySchema::boolean("Also show hidden and gitignored entries.")
+                        .with_default(serde_json::json!(false)),
+                    false,
+                ),
+        )
+    }
+
+    fn is_read_only(&self, _args: &serde_json::Value) -> bool {
+        true
+    }
+
+    async fn execute(&self, args: serde_json::Value) -> Result<ToolResult, ToolError> {
+        let path = args
+            .get("path")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| ToolError::InvalidArguments("缺少 path 参数".to_string()))?;
+        let depth = args
+            .get("depth")
+            .and_then(|v| v.as_u64())
+            .map(|n| n as usize)
+            .unwrap_or(DEFAULT_DEPTH);
+        let include_ignored = args
+            .get("include_ignored")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+
+        info!("[ListDirectoryTool] 列出目录: {} (depth={})", path, depth);
+
+        let result = self.list_directory(path, depth, include_ignored)?;
+        Ok(ToolResult::success(format_tree(&result)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn setup_test_tool() -> (ListDirectoryTool, TempDir) {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        fs::create_dir_all(root.join("src/utils")).unwrap();
+        fs::create_dir_all(root.join("dist")).unwrap();
+        fs::write(root.join(".gitignore"), "dist/\n").unwrap();
+        fs::write(root.join("Cargo.toml"), "[package]\n").unwrap();
+        fs::write(root.join("src/main.rs"), "fn main() {}\n").unwrap();
+        fs::write(root.join("src/utils/mod.rs"), "").unwrap();
+        fs::write(root.join("dist/bundle.js"), "").unwrap();
+        let tool = ListDirectoryTool::new(Arc::new(SecurityManager::new(root)));
+        (tool, temp_dir)
+    }
+
+    #[test]
+    fn test_tree_output() {
+        let (tool, _dir) = setup_test_tool();
+        let result = tool.list_directory(".", 3, false).unwrap();
+        assert_eq!(result.dir_count, 2);
+        assert_eq!(result.file_count, 3);
+
+        assert_eq!(
+            format_tree(&result),
+            "./\n\
+             ├── src/\n\
+             │   ├── utils/\n\
+             │   │   └── mod.rs (0 B)\n\
+             │   └── main.rs (13 B)\n\
+             └── Cargo.toml (10 B)\n\
+             \n2 个目录, 3 个文件\n"
+        );
+    }
+
+    #[test]
+    fn test_depth_and_ignored_entries() {
+        let (tool, _dir) = setup_test_tool();
+
+        let result = tool.list_directory("src", 1, false).unwrap();
+        assert_eq!(
+            result
+                .entries
+                .iter()
+                .map(|e| e.path.as_str())
+                .collect::<Vec<_>>(),
+            vec!["utils", "main.rs"]
+        );
+        assert!(format_tree(&result).starts_with("src/\n"));
+
+        let result = tool.list_directory(".", 1, true).unwrap();
+        assert!(result.entries.iter().any(|e| e.name == "dist"));
+        assert!(result.entries.iter().any(|e| e.name == ".gitignore"));
+    }
+
+    #[test]
+    fn test_rejects_files_and_outside_paths() {
+        let (tool, _dir) = setup_test_tool();
+        assert!(tool.list_directory("Cargo.toml", 2, false).is_err());
+        assert!(matches!(
+            tool.list_directory("../", 2, false),
+            Err(ToolError::Security(_))
+        ));
+    }
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!(format_size(512), "512 B");
+        assert_eq!(format_size(1536), "1.5 KB");
+        assert_eq!(format_size(3 * 1024 * 1024), "3.0 MB");
+    }
+}
@@ -8,25 +8,49 @@
 //! - `registry`: 工具注册表和 Tool trait
 //! - `security`: 安全管理器（路径验证、符号链接检查等）
 //! - `bash`: Bash 命令执行工具
+//! - `sandbox`: bash 命令隔离执行（资源限制、环境变量白名单、命名空间）
+//! - `process`: 后台进程管理和工具（启动、增量读取输出、写入输入、状态、终止）
 //! - `read_file`: 文件读取工具
 //! - `write_file`: 文件写入工具
 //! - `edit_file`: 文件编辑工具
+//! - `grep`: 内容搜索工具（正则、glob 过滤、上下文行）
+//! - `glob`: 文件匹配工具
+//! - `list_directory`: 目录树工具
+//! - `apply_patch`: 多文件补丁工具（unified diff / Codex 格式，原子应用）
+//! - `walk`: 目录遍历（遵循 .gitignore）
 //! - `prompt`: 工具 Prompt 生成器（System Prompt 工具注入）
 
+pub mod apply_patch;
 pub mod bash;
 pub mod edit_file;
+pub mod glob;
+pub mod grep;
+pub mod list_directory;
+pub mod process;
 pub mod prompt;
 pub mod read_file;
 pub mod registry;
+pub mod sandbox;
 pub mod security;
 pub mod types;
+pub mod walk;
 pub mod write_file;
 
+pub use apply_patch::{ApplyPatchResult, ApplyPatchTool, PatchedFile, PatchedFileKind};
+
 pub use bash::{BashExecutionResult, BashTool, ShellType};
 pub use edit_file::{EditFileResult, EditFileTool, UndoResult};
+pub use glob::{GlobResult, GlobTool};
+pub use grep::{GrepOptions, GrepOutputMode, GrepResult, GrepTool};
+pub use list_directory::{ListDirectoryResult, ListDirectoryTool};
+pub use process::{
+    ProcessInfo, ProcessInputTool, ProcessKillTool, ProcessManager, ProcessOutput,
+    ProcessOutputTool, ProcessStartTool, ProcessStatus, ProcessStatusTool,
+};
 pub use prompt::{generate_tools_prompt, PromptFormat, ToolPromptGenerator};
 pub use read_file::{ReadFileResult, ReadFileTool};
-pub use registry::{Tool, ToolRegistry};
+pub use registry::{Tool, ToolContext, ToolRegistry};
+pub use sandbox::BashSandbox;
 pub use security::{SecurityError, SecurityManager};
 pub use types::*;
 pub use write_file::{WriteFileResult, WriteFileTool};
@@ -41,13 +65,56 @@ use tracing::info;
 /// * `base_dir` - 基础目录，所有文件操作必须在此目录内
 ///
 /// # Returns
-/// 包含 bash, read_file, write_file, edit_file 工具的注册表
+/// 包含 bash, read_file, write_file, edit_file, grep, glob, list_directory, apply_patch 工具的注册表
 pub fn create_default_registry(base_dir: impl AsRef<Path>) -> ToolRegistry {
+    create_default_registry_with_sandbox(base_dir, Arc::new(BashSandbox::default()))
+}
+
+/// 创建 Agent 使用的工具注册表：默认工具加上后台进程工具
+///
+/// bash 和 process_start 按 `sandbox` 配置隔离执行，后台进程由 `processes` 跨对话追踪
+pub fn create_agent_registry(
+    base_dir: impl AsRef<Path>,
+    sandbox: Arc<BashSandbox>,
+    processes: Arc<ProcessManager>,
+) -> ToolRegistry {
+    let registry = create_default_registry_with_sandbox(base_dir.as_ref(), sandbox.clone());
+    let security = Arc::new(SecurityManager::new(base_dir.as_ref()));
+    let shell = Arc::new(BashTool::new(security).with_sandbox(sandbox));
+
+    if let Err(e) = registry.register(ProcessStartTool::new(shell, Arc::clone(&processes))) {
+        tracing::error!("注册 ProcessStartTool 失败: {}", e);
+    }
+
+    if let Err(e) = registry.register(ProcessOutputTool::new(Arc::clone(&processes))) {
+        tracing::error!("注册 ProcessOutputTool 失败: {}", e);
+    }
+
+    if let Err(e) = registry.register(ProcessInputTool::new(Arc::clone(&processes))) {
+        tracing::error!("注册 ProcessInputTool 失败: {}", e);
+    }
+
+    if let Err(e) = registry.register(ProcessStatusTool::new(Arc::clone(&processes))) {
+        tracing::error!("注册 ProcessStatusTool 失败: {}", e);
+    }
+
+    if let Err(e) = registry.register(ProcessKillTool::new(processes)) {
+        tracing::error!("注册 ProcessKillTool 失败: {}", e);
+    }
+
+    registry
+}
+
+/// 创建包含所有默认工具的注册表，bash 工具按 `sandbox` 配置隔离执行
+pub fn create_default_registry_with_sandbox(
+    base_dir: impl AsRef<Path>,
+    sandbox: Arc<BashSandbox>,
+) -> ToolRegistry {
     let security = Arc::new(SecurityManager::new(base_dir.as_ref()));
     let registry = ToolRegistry::new();
 
     // 注册核心工具
-    if let Err(e) = registry.register(BashTool::new(Arc::clone(&security))) {
+    if let Err(e) = registry.register(BashTool::new(Arc::clone(&security)).with_sandbox(sandbox)) {
         tracing::error!("注册 BashTool 失败: {}", e);
     }
 
@@ -63,6 +130,22 @@ pub fn create_default_registry(base_dir: impl AsRef<Path>) -> ToolRegistry {
         tracing::error!("注册 EditFileTool 失败: {}", e);
     }
 
+    if let Err(e) = registry.register(GrepTool::new(Arc::clone(&security))) {
+        tracing::error!("注册 GrepTool 失败: {}", e);
+    }
+
+    if let Err(e) = registry.register(GlobTool::new(Arc::clone(&security))) {
+        tracing::error!("注册 GlobTool 失败: {}", e);
+    }
+
+    if let Err(e) = registry.register(ListDirectoryTool::new(Arc::clone(&security))) {
+        tracing::error!("注册 ListDirectoryTool 失败: {}", e);
+    }
+
+    if let Err(e) = registry.register(ApplyPatchTool::new(Arc::clone(&security))) {
+        tracing::error!("注册 ApplyPatchTool 失败: {}", e);
+    }
+
     info!(
         "[Tools] 已创建默认工具注册表，共 {} 个工具: {:?}",
         registry.len(),
@@ -0,0 +1,1152 @@
+//! 后台进程模块
+//!
+//! 提供后台 shell 进程管理和对应的 Agent 工具：命令启动后立即返回，
+//! 之后按偏移量增量读取输出、写入标准输入、查询状态或终止进程。
+//! 适用于开发服务器、文件监听、耗时较长的测试等不应阻塞工具循环的命令。
+//!
+//! ## 设计
+//! - 进程按会话追踪，工具只能访问本会话启动的进程；会话删除或归档时终止
+//! - 每个会话只保留最近结束的 16 个进程，启动新进程时移除更早结束的进程及其输出
+//! - stdout 和 stderr 合并写入同一个输出缓冲区，偏移量按字节计算且单调递增；
+//!   每个进程只保留最近 1 MiB 输出，更早的部分被丢弃
+//! - 命令构建与 `bash` 工具一致（shell、工作目录、非交互环境变量、隔离配置）
+//! - 进程在独立进程组中运行，Linux 上终止时结束整个进程组
+
+use super::bash::BashTool;
+use super::registry::{Tool, ToolContext};
+use super::sandbox::kill_process_group;
+use super::types::{JsonSchema, PropertySchema, ToolDefinition, ToolError, ToolResult};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use parking_lot::{Mutex, RwLock};
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::process::Stdio;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+use tokio::process::{ChildStdin, Command};
+use tokio::sync::{oneshot, Notify};
+use tracing::{info, warn};
+
+/// 每个会话同时运行的后台进程上限
+pub const MAX_RUNNING_PROCESSES: usize = 8;
+
+/// 每个会话保留的已结束进程数
+pub const MAX_FINISHED_PROCESSES: usize = 16;
+
+/// 每个进程保留的输出字节数
+const OUTPUT_BUFFER_BYTES: usize = 1024 * 1024;
+
+/// 单次读取默认返回的最大字节数
+const DEFAULT_READ_BYTES: usize = 16 * 1024;
+
+/// 单次读取返回的最大字节数上限
+const MAX_READ_BYTES: usize = 64 * 1024;
+
+/// 读取输出时的最长等待时间（毫秒）
+const MAX_WAIT_MS: u64 = 30_000;
+
+/// 终止进程后等待其退出的时间
+const KILL_WAIT: Duration = Duration::from_secs(5);
+
+/// 进程退出后等待剩余输出读取完毕的时间（孙进程可能仍持有输出管道）
+const OUTPUT_DRAIN_WAIT: Duration = Duration::from_millis(500);
+
+/// 读取输出的缓冲区大小
+const READ_CHUNK_SIZE: usize = 8 * 1024;
+
+/// 后台进程状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProcessStatus {
+    /// 运行中
+    Running,
+    /// 已自行退出
+    Exited,
+    /// 已被终止
+    Killed,
+}
+
+impl ProcessStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ProcessStatus::Running => "running",
+            ProcessStatus::Exited => "exited",
+            ProcessStatus::Killed => "killed",
+        }
+    }
+}
+
+/// 后台进程信息
+#[derive(Debug, Clone, Serialize)]
+pub struct ProcessInfo {
+    /// 进程 ID（`proc_<序号>`，非系统 PID）
+    pub id: String,
+    /// 启动进程的会话 ID
+    pub session_id: Option<String>,
+    /// 执行的命令
+    pub command: String,
+    /// 系统 PID
+    pub pid: Option<u32>,
+    /// 状态
+    pub status: ProcessStatus,
+    /// 退出码（被信号终止时为空）
+    pub exit_code: Option<i32>,
+    /// 启动时间
+    pub started_at: DateTime<Utc>,
+    /// 结束时间
+    pub ended_at: Option<DateTime<Utc>>,
+    /// 累计输出字节数（即最新输出的结束偏移量）
+    pub output_bytes: u64,
+}
+
+impl ProcessInfo {
+    /// 单行状态描述
+    fn summary(&self) -> String {
+        let mut line = format!("{} {}", self.id, self.status.as_str());
+        if let Some(pid) = self.pid {
+            line.push_str(&format!(" pid={}", pid));
+        }
+        if let Some(code) = self.exit_code {
+            line.push_str(&format!(" exit_code={}", code));
+        }
+        let end = self.ended_at.unwrap_or_else(Utc::now);
+        line.push_str(&format!(
+            " runtime={}s output={}B: {}",
+            (end - self.started_at).num_seconds(),
+            self.output_bytes,
+            self.command
+        ));
+        line
+    }
+}
+
+/// 一次读取到的输出
+#[derive(Debug, Clone, Serialize)]
+pub struct ProcessOutput {
+    /// 输出文本
+    pub text: String,
+    /// 实际起始偏移量（请求的输出已被丢弃时大于请求值）
+    pub offset: u64,
+    /// 下一次读取使用的偏移量
+    pub next_offset: u64,
+    /// 因已被丢弃而跳过的字节数
+    pub skipped: u64,
+    /// 是否还有未返回的输出
+    pub has_more: bool,
+    /// 读取时的进程信息
+    pub info: ProcessInfo,
+}
+
+/// 保留最近输出的环形缓冲区，偏移量从进程启动起累计
+struct OutputBuffer {
+    data: VecDeque<u8>,
+    /// 缓冲区第一个字节的偏移量
+    start: u64,
+    capacity: usize,
+}
+
+impl OutputBuffer {
+    fn new(capacity: usize) -> Self {
+        Self {
+            data: VecDeque::new(),
+            start: 0,
+            capacity,
+        }
+    }
+
+    fn end(&self) -> u64 {
+        self.start + self.data.len() as u64
+    }
+
+    fn push(&mut self, bytes: &[u8]) {
+        self.data.extend(bytes);
+        let excess = self.data.len().saturating_sub(self.capacity);
+        if excess > 0 {
+            self.data.drain(..excess);
+            self.start += excess as u64;
+        }
+    }
+
+    /// 从 `offset` 开始读取最多 `max_bytes` 字节，返回实际起始偏移量和数据
+    ///
+    /// 末尾不完整的 UTF-8 字符留到下一次读取
+    fn read(&self, offset: u64, max_bytes: usize) -> (u64, Vec<u8>) {
+        let from = offset.clamp(self.start, self.end());
+        let skip = (from - self.start) as usize;
+        let mut bytes: Vec<u8> = self.data.range(skip..).take(max_bytes).copied().collect();
+        if let Err(e) = std::str::from_utf8(&bytes) {
+            if e.error_len().is_none() && e.valid_up_to() > 0 {
+                bytes.truncate(e.valid_up_to());
+            }
+        }
+        (from, bytes)
+    }
+}
+
+/// 后台进程
+struct BackgroundProcess {
+    info: Mutex<ProcessInfo>,
+    output: Mutex<OutputBuffer>,
+    stdin: tokio::sync::Mutex<Option<ChildStdin>>,
+    /// 有新输出或进程结束时通知
+    changed: Notify,
+    /// 终止信号
+    kill_tx: Mutex<Option<oneshot::Sender<()>>>,
+}
+
+impl BackgroundProcess {
+    fn snapshot(&self) -> ProcessInfo {
+        let mut info = self.info.lock().clone();
+        info.output_bytes = self.output.lock().end();
+        info
+    }
+
+    fn is_running(&self) -> bool {
+        self.info.lock().status == ProcessStatus::Running
+    }
+
+    fn request_kill(&self) {
+        if let Some(tx) = self.kill_tx.lock().take() {
+            let _ = tx.send(());
+        }
+    }
+
+    /// 等待新输出或状态变化，最多等待 `wait`
+    async fn wait_for_change(&self, offset: u64, wait: Duration) {
+        let deadline = tokio::time::Instant::now() + wait;
+        loop {
+            // 先创建通知再检查状态，避免错过检查之后的通知
+            let notified = self.changed.notified();
+            if self.output.lock().end() > offset || !self.is_running() {
+                return;
+            }
+            if tokio::time::timeout_at(deadline, notified).await.is_err() {
+                return;
+            }
+        }
+    }
+}
+
+/// 后台进程管理器
+///
+/// 由 `NativeAgentState` 持有，跨对话保留；进程按会话隔离
+#[derive(Default)]
+pub struct ProcessManager {
+    processes: RwLock<HashMap<String, Arc<BackgroundProcess>>>,
+    next_id: AtomicU64,
+}
+
+impl std::fmt::Debug for ProcessManager {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ProcessManager")
+            .field("processes", &self.processes.read().len())
+            .finish()
+    }
+}
+
+impl ProcessManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 启动后台进程
+    ///
+    /// `cmd` 的标准输入输出会被重新设置为管道
+    pub fn spawn(
+        &self,
+        session_id: Option<&str>,
+        command: &str,
+        mut cmd: Command,
+    ) -> Result<ProcessInfo, String> {
+        let running = self
+            .processes
+            .read()
+            .values()
+            .filter(|p| p.is_running() && p.info.lock().session_id.as_deref() == session_id)
+            .count();
+        if running >= MAX_RUNNING_PROCESSES {
+            return Err(format!(
+                "本会话已有 {} 个后台进程在运行，请先终止不再需要的进程",
+                running
+            ));
+        }
+
+        #[cfg(unix)]
+        cmd.process_group(0);
+        cmd.stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+        let mut child = cmd
+            .spawn()
+            .map_err(|e| format!("无法启动 shell 进程: {}", e))?;
+
+        let id = format!("proc_{}", self.next_id.fetch_add(1, Ordering::Relaxed) + 1);
+        let pid = child.id();
+        let (kill_tx, kill_rx) = oneshot::channel();
+        let process = Arc::new(BackgroundProcess {
+            info: Mutex::new(ProcessInfo {
+                id: id.clone(),
+                session_id: session_id.map(str::to_string),
+                command: command.to_string(),
+                pid,
+                status: ProcessStatus::Running,
+                exit_code: None,
+                started_at: Utc::now(),
+                ended_at: None,
+                output_bytes: 0,
+            }),
+            output: Mutex::new(OutputBuffer::new(OUTPUT_BUFFER_BYTES)),
+            stdin: tokio::sync::Mutex::new(child.stdin.take()),
+            changed: Notify::new(),
+            kill_tx: Mutex::new(Some(kill_tx)),
+        });
+
+        let readers: Vec<_> = [
+            child
+                .stdout
+                .take()
+                .map(|r| Box::new(r) as Box<dyn AsyncRead + Send + Unpin>),
+            child
+                .stderr
+                .take()
+                .map(|r| Box::new(r) as Box<dyn AsyncRead + Send + Unpin>),
+        ]
+        .into_iter()
+        .flatten()
+        .map(|reader| tokio::spawn(pump_output(reader, process.clone())))
+        .collect();
+
+        let watched = process.clone();
+        tokio::spawn(async move {
+            let exit = tokio::select! {
+                status = child.wait() => Some(status),
+                _ = kill_rx => None,
+            };
+            let killed = exit.is_none();
+            let status = match exit {
+                Some(status) => status,
+                None => {
+                    if let Some(pid) = pid {
+                        kill_process_group(pid);
+                    }
+                    let _ = child.start_kill();
+                    child.wait().await
+                }
+            };
+
+            let _ =
+                tokio::time::timeout(OUTPUT_DRAIN_WAIT, futures::future::join_all(readers)).await;
+            watched.stdin.lock().await.take();
+            {
+                let mut info = watched.info.lock();
+                info.status = if killed {
+                    ProcessStatus::Killed
+                } else {
+                    ProcessStatus::Exited
+                };
+                info.exit_code = status.ok().and_then(|s| s.code());
+                info.ended_at = Some(Utc::now());
+                info!(
+                    "[ProcessManager] 后台进程结束: {} status={} exit_code={:?}",
+                    info.id,
+                    info.status.as_str(),
+                    info.exit_code
+                );
+            }
+            watched.changed.notify_waiters();
+        });
+
+        info!(
+            "[ProcessManager] 启动后台进程: {} pid={:?} session={:?} command={}",
+            id, pid, session_id, command
+        );
+        let info = process.snapshot();
+        let mut processes = self.processes.write();
+        evict_finished(&mut processes, session_id);
+        processes.insert(id, process);
+        Ok(info)
+    }
+
+    /// 查找本会话的进程
+    fn get(&self, session_id: Option<&str>, id: &str) -> Result<Arc<BackgroundProcess>, String> {
+        self.processes
+            .read()
+            .get(id)
+            .filter(|p| p.info.lock().session_id.as_deref() == session_id)
+            .cloned()
+            .ok_or_else(|| format!("后台进程不存在: {}", id))
+    }
+
+    /// 列出会话的后台进程（包括已结束的），按启动顺序排列
+    pub fn list(&self, session_id: Option<&str>) -> Vec<ProcessInfo> {
+        let mut list: Vec<ProcessInfo> = self
+            .processes
+            .read()
+            .values()
+            .map(|p| p.snapshot())
+            .filter(|info| info.session_id.as_deref() == session_id)
+            .collect();
+        list.sort_by_key(|info| info.started_at);
+        list
+    }
+
+    /// 获取进程信息
+    pub fn info(&self, session_id: Option<&str>, id: &str) -> Result<ProcessInfo, String> {
+        Ok(self.get(session_id, id)?.snapshot())
+    }
+
+    /// 从 `offset` 开始读取输出
+    ///
+    /// 没有新输出且进程仍在运行时最多等待 `wait`，有新输出或进程结束时立即返回
+    pub async fn read_output(
+        &self,
+        session_id: Option<&str>,
+        id: &str,
+        offset: u64,
+        max_bytes: usize,
+        wait: Duration,
+    ) -> Result<ProcessOutput, String> {
+        let process = self.get(session_id, id)?;
+        if !wait.is_zero() {
+            process.wait_for_change(offset, wait).await;
+        }
+
+        let info = process.snapshot();
+        let output = process.output.lock();
+        let (from, bytes) = output.read(offset, max_bytes.max(1));
+        let next_offset = from + bytes.len() as u64;
+        Ok(ProcessOutput {
+            text: String::from_utf8_lossy(&bytes).to_string(),
+            offset: from,
+            next_offset,
+            skipped: from.saturating_sub(offset),
+            has_more: next_offset < output.end(),
+            info,
+        })
+    }
+
+    /// 写入标准输入，`close` 为真时写入后关闭标准输入（发送 EOF）
+    pub async fn write_input(
+        &self,
+        session_id: Option<&str>,
+        id: &str,
+        input: &str,
+        close: bool,
+    ) -> Result<(), String> {
+        let process = self.get(session_id, id)?;
+        let mut stdin = process.stdin.lock().await;
+        let pipe = stdin
+            .as_mut()
+            .ok_or_else(|| format!("进程 {} 的标准输入已关闭", id))?;
+        pipe.write_all(input.as_bytes())
+            .await
+            .map_err(|e| format!("写入标准输入失败: {}", e))?;
+        pipe.flush()
+            .await
+            .map_err(|e| format!("写入标准输入失败: {}", e))?;
+        if close {
+            stdin.take();
+        }
+        Ok(())
+    }
+
+    /// 终止进程并等待其退出
+    pub async fn kill(&self, session_id: Option<&str>, id: &str) -> Result<ProcessInfo, String> {
+        let process = self.get(session_id, id)?;
+        process.request_kill();
+        let deadline = tokio::time::Instant::now() + KILL_WAIT;
+        while process.is_running() && tokio::time::Instant::now() < deadline {
+            let remaining = deadline - tokio::time::Instant::now();
+            process.wait_for_change(u64::MAX, remaining).await;
+        }
+        Ok(process.snapshot())
+    }
+
+    /// 终止并移除会话的全部后台进程，返回终止的运行中进程数
+    pub fn cleanup_session(&self, session_id: &str) -> usize {
+        let removed: Vec<Arc<BackgroundProcess>> = {
+            let mut processes = self.processes.write();
+            let ids: Vec<String> = processes
+                .iter()
+                .filter(|(_, p)| p.info.lock().session_id.as_deref() == Some(session_id))
+                .map(|(id, _)| id.clone())
+                .collect();
+            ids.iter().filter_map(|id| processes.remove(id)).collect()
+        };
+
+        let mut killed = 0;
+        for process in removed {
+            if process.is_running() {
+                process.request_kill();
+                killed += 1;
+            }
+        }
+        if killed > 0 {
+            info!(
+                "[ProcessManager] 会话 {} 结束，已终止 {} 个后台进程",
+                session_id, killed
+            );
+        }
+        killed
+    }
+}
+
+/// 移除会话中超出保留数量的已结束进程，最早结束的先移除
+fn evict_finished(
+    processes: &mut HashMap<String, Arc<BackgroundProcess>>,
+    session_id: Option<&str>,
+) {
+    let mut finished: Vec<(DateTime<Utc>, String)> = processes
+        .iter()
+        .filter_map(|(id, p)| {
+            let info = p.info.lock();
+            if info.session_id.as_deref() != session_id {
+                return None;
+            }
+            info.ended_at.map(|ended_at| (ended_at, id.clone()))
+        })
+        .collect();
+    let excess = finished.len().saturating_sub(MAX_FINISHED_PROCESSES);
+    if excess == 0 {
+        return;
+    }
+    finished.sort();
+    for (_, id) in finished.into_iter().take(excess) {
+        processes.remove(&id);
+    }
+}
+
+/// 将进程输出写入共享缓冲区
+async fn pump_output<R: AsyncRead + Unpin>(mut reader: R, process: Arc<BackgroundProcess>) {
+    let mut buf = vec![0u8; READ_CHUNK_SIZE];
+    loop {
+        match reader.read(&mut buf).await {
+            Ok(0) => break,
+            Ok(n) => {
+                process.output.lock().push(&buf[..n]);
+                process.changed.notify_waiters();
+            }
+            Err(e) => {
+                warn!("[ProcessManager] 读取后台进程输出失败: {}", e);
+                break;
+            }
+        }
+    }
+}
+
+fn process_id_arg(args: &serde_json::Value) -> Result<&str, ToolError> {
+    args.get("process_id")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| ToolError::InvalidArguments("缺少 process_id 参数".to_string()))
+}
+
+fn process_id_property() -> PropertySchema {
+    PropertySchema::string("The process ID returned by process_start (e.g. proc_1).")
+}
+
+// ==================== 工具 ====================
+
+/// 启动后台进程工具
+pub struct ProcessStartTool {
+    shell: Arc<BashTool>,
+    manager: Arc<ProcessManager>,
+}
+
+impl ProcessStartTool {
+    /// `shell` 提供 shell 类型、工作目录和隔离配置
+    pub fn new(shell: Arc<BashTool>, manager: Arc<ProcessManager>) -> Self {
+        Self { shell, manager }
+    }
+}
+
+#[async_trait]
+impl Tool for ProcessStartTool {
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition::new(
+            "process_start",
+            "Start a shell command in the background and return immediately with a process ID. \
+             Use this instead of bash for long-running commands such as dev servers, file \
+             watchers or long test runs. Read its output with process_output, send input with \
+             process_input and stop it with process_kill. Background processes are stopped \
+             when the session ends.",
+        )
+        .with_parameters(JsonSchema::new().add_property(
+            "command",
+            PropertySchema::string("The shell command to run in the background."),
+            true,
+        ))
+    }
+
+    async fn execute(&self, args: serde_json::Value) -> Result<ToolResult, ToolError> {
+        self.execute_with_context(args, &ToolContext::default())
+            .await
+    }
+
+    async fn execute_with_context(
+        &self,
+        args: serde_json::Value,
+        context: &ToolContext,
+    ) -> Result<ToolResult, ToolError> {
+        let command = args
+            .get("command")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| ToolError::InvalidArguments("缺少 command 参数".to_string()))?;
+
+        let sandbox = self.shell.prepare_sandbox();
+        let cmd = self
+            .shell
+            .build_command(command, self.shell.working_dir(), sandbox.as_ref());
+        let info = self
+            .manager
+            .spawn(context.session_id.as_deref(), command, cmd)
+            .map_err(ToolError::ExecutionFailed)?;
+
+        let mut output = format!(
+            "已启动后台进程 {}（pid {}）\n使用 process_output 读取输出（offset 从 0 开始），process_kill 终止进程",
+            info.id,
+            info.pid.map_or_else(|| "未知".to_string(), |pid| pid.to_string())
+        );
+        if let Some(plan) = &sandbox {
+            for note in plan.notes() {
+                output.push('\n');
+                output.push_str(note);
+            }
+        }
+        Ok(ToolResult::success(output))
+    }
+}
+
+/// 读取后台进程输出工具
+pub struct ProcessOutputTool {
+    manager: Arc<ProcessManager>,
+}
+
+impl ProcessOutputTool {
+    pub fn new(manager: Arc<ProcessManager>) -> Self {
+        Self { manager }
+    }
+}
+
+#[async_trait]
+impl Tool for ProcessOutputTool {
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition::new(
+            "process_output",
+            "Read the combined stdout/stderr of a background process starting at a byte \
+             offset. Pass the returned next_offset on the following call to read only new \
+             output. Set wait_ms to wait for new output when none is available yet, e.g. \
+             while a server is starting. Only the most recent 1 MiB of output is kept.",
+        )
+        .with_parameters(
+            JsonSchema::new()
+                .add_property("process_id", process_id_property(), true)
+                .add_property(
+                    "offset",
+                    PropertySchema::integer("Byte offset to read from.")
+                        .with_default(serde_json::json!(0)),
+                    false,
+                )
+                .add_property(
+                    "max_bytes",
+                    PropertySchema::integer("Maximum number of bytes to return (up to 65536).")
+                        .with_default(serde_json::json!(DEFAULT_READ_BYTES)),
+                    false,
+                )
+                .add_property(
+                    "wait_ms",
+                    PropertySchema::integer(
+                        "Milliseconds to wait for new output if none is available (up to 30000).",
+                    )
+                    .with_default(serde_json::json!(0)),
+                    false,
+                ),
+        )
+    }
+
+    fn is_read_only(&self, _args: &serde_json::Value) -> bool {
+        true
+    }
+
+    async fn execute(&self, args: serde_json::Value) -> Result<ToolResult, ToolError> {
+        self.execute_with_context(args, &ToolContext::default())
+            .await
+    }
+
+    async fn execute_with_context(
+        &self,
+        args: serde_json::Value,
+        context: &ToolContext,
+    ) -> Result<ToolResult, ToolError> {
+        let id = process_id_arg(&args)?;
+        let offset = args.get("offset").and_then(|v| v.as_u64()).unwrap_or(0);
+        let max_bytes = args
+            .get("max_bytes")
+            .and_then(|v| v.as_u64())
+            .map_or(DEFAULT_READ_BYTES, |n| (n as usize).min(MAX_READ_BYTES));
+        let wait_ms = args
+            .get("wait_ms")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(0)
+            .min(MAX_WAIT_MS);
+
+        let output = self
+            .manager
+            .read_output(
+                context.session_id.as_deref(),
+                id,
+                offset,
+                max_bytes,
+                Duration::from_millis(wait_ms),
+            )
+            .await
+            .map_err(ToolError::ExecutionFailed)?;
+        Ok(ToolResult::success(format_process_output(&output)))
+    }
+}
+
+/// 格式化读取结果：状态、偏移量和输出文本
+fn format_process_output(output: &ProcessOutput) -> String {
+    let mut text = format!(
+        "{}\n输出范围: {}..{}，next_offset={}",
+        output.info.summary(),
+        output.offset,
+        output.next_offset,
+        output.next_offset
+    );
+    if output.skipped > 0 {
+        text.push_str(&format!(
+            "\n[已跳过 {} 字节被丢弃的较早输出]",
+            output.skipped
+        ));
+    }
+    if output.has_more {
+        text.push_str("\n[还有更多输出，使用 next_offset 继续读取]");
+    }
+    text.push_str("\n\n");
+    if output.text.is_empty() {
+        text.push_str("（没有新输出）");
+    } else {
+        text.push_str(&output.text);
+    }
+    text
+}
+
+/// 向后台进程写入标准输入工具
+pub struct ProcessInputTool {
+    manager: Arc<ProcessManager>,
+}
+
+impl ProcessInputTool {
+    pub fn new(manager: Arc<ProcessManager>) -> Self {
+        Self { manager }
+    }
+}
+
+#[async_trait]
+impl Tool for ProcessInputTool {
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition::new(
+            "process_input",
+            "Write text to the stdin of a background process. No newline is added; include \
+             \"\\n\" to submit a line. Set close_stdin to send EOF after writing.",
+        )
+        .with_parameters(
+            JsonSchema::new()
+                .add_property("process_id", process_id_property(), true)
+                .add_property(
+                    "input",
+                    PropertySchema::string("Text to write to stdin."),
+                    true,
+                )
+                .add_property(
+                    "close_stdin",
+                    PropertySchema::boolean("Close stdin after writing.")
+                        .with_default(serde_json::json!(false)),
+                    false,
+                ),
+        )
+    }
+
+    async fn execute(&self, args: serde_json::Value) -> Result<ToolResult, ToolError> {
+        self.execute_with_context(args, &ToolContext::default())
+            .await
+    }
+
+    async fn execute_with_context(
+        &self,
+        args: serde_json::Value,
+        context: &ToolContext,
+    ) -> Result<ToolResult, ToolError> {
+        let id = process_id_arg(&args)?;
+        let input = args
+            .get("input")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| ToolError::InvalidArguments("缺少 input 参数".to_string()))?;
+        let close = args
+            .get("close_stdin")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+
+        match self
+            .manager
+            .write_input(context.session_id.as_deref(), id, input, close)
+            .await
+        {
+            Ok(()) => Ok(ToolResult::success(format!(
+                "已向 {} 写入 {} 字节{}",
+                id,
+                input.len(),
+                if close {
+                    "，标准输入已关闭"
+                } else {
+                    ""
+                }
+            ))),
+            Err(e) => Ok(ToolResult::failure(e)),
+        }
+    }
+}
+
+/// 查询后台进程状态工具
+pub struct ProcessStatusTool {
+    manager: Arc<ProcessManager>,
+}
+
+impl ProcessStatusTool {
+    pub fn new(manager: Arc<ProcessManager>) -> Self {
+        Self { manager }
+    }
+}
+
+#[async_trait]
+impl Tool for ProcessStatusTool {
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition::new(
+            "process_status",
+            "Show the status, exit code, runtime and output size of a background process, or \
+             of all background processes in this session when process_id is omitted.",
+        )
+        .with_parameters(JsonSchema::new().add_property(
+            "process_id",
+            process_id_property(),
+            false,
+        ))
+    }
+
+    fn is_read_only(&self, _args: &serde_json::Value) -> bool {
+        true
+    }
+
+    async fn execute(&self, args: serde_json::Value) -> Result<ToolResult, ToolError> {
+        self.execute_with_context(args, &ToolContext::default())
+            .await
+    }
+
+    async fn execute_with_context(
+        &self,
+        args: serde_json::Value,
+        context: &ToolContext,
+    ) -> Result<ToolResult, ToolError> {
+        let session_id = context.session_id.as_deref();
+        if let Some(id) = args.get("process_id").and_then(|v| v.as_str()) {
+            let info = self
+                .manager
+                .info(session_id, id)
+                .map_err(ToolError::ExecutionFailed)?;
+            return Ok(ToolResult::success(info.summary()));
+        }
+
+        let list = self.manager.list(session_id);
+        if list.is_empty() {
+            return Ok(ToolResult::success("本会话没有后台进程"));
+        }
+        let lines: Vec<String> = list.iter().map(ProcessInfo::summary).collect();
+        Ok(ToolResult::success(lines.join("\n")))
+    }
+}
+
+/// 终止后台进程工具
+pub struct ProcessKillTool {
+    manager: Arc<ProcessManager>,
+}
+
+impl ProcessKillTool {
+    pub fn new(manager: Arc<ProcessManager>) -> Self {
+        Self { manager }
+    }
+}
+
+#[async_trait]
+impl Tool for ProcessKillTool {
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition::new(
+            "process_kill",
+            "Stop a background process and its child processes. Its output stays readable \
+             with process_output.",
+        )
+        .with_parameters(JsonSchema::new().add_property(
+            "process_id",
+            process_id_property(),
+            true,
+        ))
+    }
+
+    async fn execute(&self, args: serde_json::Value) -> Result<ToolResult, ToolError> {
+        self.execute_with_context(args, &ToolContext::default())
+            .await
+    }
+
+    async fn execute_with_context(
+        &self,
+        args: serde_json::Value,
+        context: &ToolContext,
+    ) -> Result<ToolResult, ToolError> {
+        let id = process_id_arg(&args)?;
+        let info = self
+            .manager
+            .kill(context.session_id.as_deref(), id)
+            .await
+            .map_err(ToolError::ExecutionFailed)?;
+        Ok(ToolResult::success(info.summary()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::tools::SecurityManager;
+    use tempfile::TempDir;
+
+    fn setup() -> (Arc<BashTool>, Arc<ProcessManager>, TempDir) {
+        let temp_dir = TempDir::new().unwrap();
+        let security = Arc::new(SecurityManager::new(temp_dir.path()));
+        (
+            Arc::new(BashTool::new(security)),
+            Arc::new(ProcessManager::new()),
+            temp_dir,
+        )
+    }
+
+    fn session(id: &str) -> ToolContext {
+        ToolContext {
+            session_id: Some(id.to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_output_buffer_offsets() {
+        let mut buffer = OutputBuffer::new(8);
+        buffer.push(b"hello ");
+        assert_eq!(buffer.read(0, 100), (0, b"hello ".to_vec()));
+        assert_eq!(buffer.read(2, 2), (2, b"ll".to_vec()));
+
+        // 超出容量时丢弃最早的输出，偏移量继续累计
+        buffer.push(b"world");
+        assert_eq!(buffer.end(), 11);
+        assert_eq!(buffer.read(0, 100), (3, b"lo world".to_vec()));
+        assert_eq!(buffer.read(11, 100), (11, Vec::new()));
+        assert_eq!(buffer.read(50, 100), (11, Vec::new()));
+    }
+
+    #[test]
+    fn test_output_buffer_keeps_incomplete_utf8() {
+        let mut buffer = OutputBuffer::new(64);
+        buffer.push("你好".as_bytes());
+        // 截断位置落在字符中间时，不完整的字符留到下一次读取
+        let (offset, bytes) = buffer.read(0, 4);
+        assert_eq!((offset, bytes), (0, "你".as_bytes().to_vec()));
+        let (offset, bytes) = buffer.read(3, 100);
+        assert_eq!((offset, bytes), (3, "好".as_bytes().to_vec()));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_start_poll_and_exit() {
+        let (shell, manager, _temp_dir) = setup();
+        let start = ProcessStartTool::new(shell, manager.clone());
+        let output_tool = ProcessOutputTool::new(manager.clone());
+        let ctx = session("s1");
+
+        let result = start
+            .execute_with_context(
+                serde_json::json!({"command": "echo first; sleep 0.2; echo second >&2"}),
+                &ctx,
+            )
+            .await
+            .unwrap();
+        assert!(result.success);
+        assert!(result.output.contains("proc_1"));
+
+        let first = manager
+            .read_output(Some("s1"), "proc_1", 0, 1024, Duration::from_secs(5))
+            .await
+            .unwrap();
+        assert!(first.text.starts_with("first\n"));
+
+        // 从 next_offset 继续读取，只返回新输出
+        let mut offset = first.next_offset;
+        let mut rest = String::new();
+        loop {
+            let output = manager
+                .read_output(Some("s1"), "proc_1", offset, 1024, Duration::from_secs(5))
+                .await
+                .unwrap();
+            rest.push_str(&output.text);
+            offset = output.next_offset;
+            if output.info.status != ProcessStatus::Running && !output.has_more {
+                assert_eq!(output.info.status, ProcessStatus::Exited);
+                assert_eq!(output.info.exit_code, Some(0));
+                break;
+            }
+        }
+        assert_eq!(format!("{}{}", first.text, rest), "first\nsecond\n");
+
+        let result = output_tool
+            .execute_with_context(
+                serde_json::json!({"process_id": "proc_1", "offset": offset}),
+                &ctx,
+            )
+            .await
+            .unwrap();
+        assert!(result.output.contains("exited"));
+        assert!(result.output.contains("（没有新输出）"));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_input_and_kill() {
+        let (shell, manager, _temp_dir) = setup();
+        let start = ProcessStartTool::new(shell, manager.clone());
+        let input = ProcessInputTool::new(manager.clone());
+        let kill = ProcessKillTool::new(manager.clone());
+        let ctx = session("s1");
+
+        start
+            .execute_with_context(
+                serde_json::json!({"command": "while read line; do echo \"got $line\"; done; sleep 60"}),
+                &ctx,
+            )
+            .await
+            .unwrap();
+        let result = input
+            .execute_with_context(
+                serde_json::json!({"process_id": "proc_1", "input": "ping\n", "close_stdin": true}),
+                &ctx,
+            )
+            .await
+            .unwrap();
+        assert!(result.success);
+
+        let output = manager
+            .read_output(Some("s1"), "proc_1", 0, 1024, Duration::from_secs(5))
+            .await
+            .unwrap();
+        assert_eq!(output.text, "got ping\n");
+
+        // 标准输入已关闭
+        let result = input
+            .execute_with_context(
+                serde_json::json!({"process_id": "proc_1", "input": "again\n"}),
+                &ctx,
+            )
+            .await
+            .unwrap();
+        assert!(!result.success);
+
+        let result = kill
+            .execute_with_context(serde_json::json!({"process_id": "proc_1"}), &ctx)
+            .await
+            .unwrap();
+        assert!(result.output.contains("killed"));
+        assert_eq!(
+            manager.info(Some("s1"), "proc_1").unwrap().status,
+            ProcessStatus::Killed
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_processes_scoped_to_session() {
+        let (shell, manager, _temp_dir) = setup();
+        let start = ProcessStartTool::new(shell, manager.clone());
+        let status = ProcessStatusTool::new(manager.clone());
+
+        start
+            .execute_with_context(serde_json::json!({"command": "sleep 60"}), &session("s1"))
+            .await
+            .unwrap();
+
+        // 其他会话无法访问
+        assert!(manager.info(Some("s2"), "proc_1").is_err());
+        let result = status
+            .execute_with_context(serde_json::json!({}), &session("s2"))
+            .await
+            .unwrap();
+        assert_eq!(result.output, "本会话没有后台进程");
+        let result = status
+            .execute_with_context(serde_json::json!({}), &session("s1"))
+            .await
+            .unwrap();
+        assert!(result.output.starts_with("proc_1 running"));
+
+        // 会话结束时终止并移除
+        let process = manager.get(Some("s1"), "proc_1").unwrap();
+        assert_eq!(manager.cleanup_session("s1"), 1);
+        assert!(manager.list(Some("s1")).is_empty());
+        process
+            .wait_for_change(u64::MAX, Duration::from_secs(5))
+            .await;
+        assert_eq!(process.snapshot().status, ProcessStatus::Killed);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_running_process_limit() {
+        let (shell, manager, _temp_dir) = setup();
+        for _ in 0..MAX_RUNNING_PROCESSES {
+            let cmd = shell.build_command("sleep 60", shell.working_dir(), None);
+            manager.spawn(Some("s1"), "sleep 60", cmd).unwrap();
+        }
+        let cmd = shell.build_command("sleep 60", shell.working_dir(), None);
+        assert!(manager.spawn(Some("s1"), "sleep 60", cmd).is_err());
+
+        // 其他会话不受影响
+        let cmd = shell.build_command("sleep 60", shell.working_dir(), None);
+        assert!(manager.spawn(Some("s2"), "sleep 60", cmd).is_ok());
+
+        manager.cleanup_session("s1");
+        manager.cleanup_session("s2");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_finished_processes_evicted() {
+        let (shell, manager, _temp_dir) = setup();
+        let total = MAX_FINISHED_PROCESSES + 2;
+        for i in 1..=total {
+            let cmd = shell.build_command("true", shell.working_dir(), None);
+            manager.spawn(Some("s1"), "true", cmd).unwrap();
+            let process = manager.get(Some("s1"), &format!("proc_{}", i)).unwrap();
+            process
+                .wait_for_change(u64::MAX, Duration::from_secs(5))
+                .await;
+            assert!(!process.is_running());
+        }
+
+        // 启动新进程时只保留最近结束的进程
+        let list = manager.list(Some("s1"));
+        assert_eq!(list.len(), MAX_FINISHED_PROCESSES + 1);
+        assert!(manager.info(Some("s1"), "proc_1").is_err());
+        assert!(manager.info(Some("s1"), &format!("proc_{}", total)).is_ok());
+
+        // 其他会话的记录不受影响
+        let cmd = shell.build_command("true", shell.working_dir(), None);
+        manager.spawn(Some("s2"), "true", cmd).unwrap();
+        assert_eq!(manager.list(Some("s1")).len(), MAX_FINISHED_PROCESSES + 1);
+    }
+}
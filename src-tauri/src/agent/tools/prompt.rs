@@ -198,7 +198,12 @@ const TOOL_USAGE_INSTRUCTIONS: &str = r#"你是一个友好的 AI 助手。
 - **read_file**：读取用户指定的文件或目录
 - **write_file**：创建/覆盖用户指定的文件
 - **edit_file**：修改用户指定的文件
+- **apply_patch**：一次修改多个文件（unified diff 或 Codex 补丁格式）
+- **list_directory**：以树形结构列出用户指定的目录
+- **grep**：在用户指定的目录中搜索内容
+- **glob**：在用户指定的目录中按文件名模式查找文件
 - **bash**：执行用户要求的命令
+- **task**：把范围较大、只需要结论的子任务（如大范围搜索）委派给子代理
 
 # 重要限制
 
@@ -218,7 +223,7 @@ const TOOL_USAGE_INSTRUCTIONS: &str = r#"你是一个友好的 AI 助手。
 - 使用中文回复"#;
 
 /// XML 特殊字符转义
-fn escape_xml(s: &str) -> String {
+pub(crate) fn escape_xml(s: &str) -> String {
     s.replace('&', "&amp;")
         .replace('<', "&lt;")
         .replace('>', "&gt;")
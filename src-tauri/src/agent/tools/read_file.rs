@@ -383,6 +383,10 @@ impl Tool for ReadFileTool {
         )
     }
 
+    fn is_read_only(&self, _args: &serde_json::Value) -> bool {
+        true
+    }
+
     async fn execute(&self, args: serde_json::Value) -> Result<ToolResult, ToolError> {
         // 解析参数
         let path_str = args
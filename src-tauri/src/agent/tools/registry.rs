@@ -4,12 +4,29 @@
 //! 符合 Requirements 2.2, 2.4
 
 use super::types::{ToolDefinition, ToolError, ToolResult, ToolValidationError};
+use crate::agent::types::StreamEvent;
 use async_trait::async_trait;
 use parking_lot::RwLock;
 use std::collections::HashMap;
 use std::sync::Arc;
+use tokio::sync::mpsc;
 use tracing::{debug, info, warn};
 
+/// 工具执行上下文
+///
+/// 由工具循环在执行时提供，需要感知调用方会话或推送进度事件的工具（如子代理）使用
+#[derive(Debug, Clone, Default)]
+pub struct ToolContext {
+    /// 发起调用的会话 ID
+    pub session_id: Option<String>,
+    /// 工具调用 ID
+    pub tool_call_id: String,
+    /// 流式事件通道
+    pub event_tx: Option<mpsc::Sender<StreamEvent>>,
+    /// 调用方的工具循环是否要求修改类调用命中审批规则（子代理沿用）
+    pub require_rule_for_writes: bool,
+}
+
 /// 工具 trait
 ///
 /// 所有工具必须实现此 trait
@@ -29,6 +46,24 @@ pub trait Tool: Send + Sync {
     /// * `Err(ToolError)` - 执行错误
     async fn execute(&self, args: serde_json::Value) -> Result<ToolResult, ToolError>;
 
+    /// 带执行上下文执行工具
+    ///
+    /// 默认忽略上下文，直接调用 `execute`
+    async fn execute_with_context(
+        &self,
+        args: serde_json::Value,
+        _context: &ToolContext,
+    ) -> Result<ToolResult, ToolError> {
+        self.execute(args).await
+    }
+
+    /// 本次调用是否只读（不修改文件、不产生副作用）
+    ///
+    /// 同一轮中相邻的只读调用会并行执行，默认为 false
+    fn is_read_only(&self, _args: &serde_json::Value) -> bool {
+        false
+    }
+
     /// 获取工具名称（便捷方法）
     fn name(&self) -> String {
         self.definition().name
@@ -184,6 +219,19 @@ impl ToolRegistry {
         self.tools.read().is_empty()
     }
 
+    /// 只保留满足条件的工具，返回移除的工具数
+    pub fn retain(&self, mut keep: impl FnMut(&str) -> bool) -> usize {
+        let mut tools = self.tools.write();
+        let before = tools.len();
+        tools.retain(|name, _| keep(name));
+        before - tools.len()
+    }
+
+    /// 检查工具调用是否只读（工具不存在时视为非只读）
+    pub fn is_read_only(&self, name: &str, args: &serde_json::Value) -> bool {
+        self.get(name).is_some_and(|tool| tool.is_read_only(args))
+    }
+
     /// 执行工具
     ///
     /// 查找并执行指定的工具
@@ -191,6 +239,17 @@ impl ToolRegistry {
         &self,
         name: &str,
         args: serde_json::Value,
+    ) -> Result<ToolResult, ToolError> {
+        self.execute_with_context(name, args, &ToolContext::default())
+            .await
+    }
+
+    /// 带执行上下文执行工具
+    pub async fn execute_with_context(
+        &self,
+        name: &str,
+        args: serde_json::Value,
+        context: &ToolContext,
     ) -> Result<ToolResult, ToolError> {
         let tool = self
             .get(name)
@@ -202,7 +261,7 @@ impl ToolRegistry {
         tool.validate_args(&args)?;
 
         // 执行工具
-        let result = tool.execute(args).await?;
+        let result = tool.execute_with_context(args, context).await?;
 
         debug!(
             "[ToolRegistry] 工具执行完成: {} success={}",
@@ -396,4 +455,18 @@ mod tests {
         assert_eq!(registry.len(), 0);
         assert!(registry.is_empty());
     }
+
+    #[test]
+    fn test_registry_retain_and_read_only() {
+        let registry = ToolRegistry::new();
+        registry.register(EchoTool).unwrap();
+
+        // 默认实现视为非只读，不存在的工具同样视为非只读
+        assert!(!registry.is_read_only("echo", &serde_json::json!({})));
+        assert!(!registry.is_read_only("nonexistent", &serde_json::json!({})));
+
+        assert_eq!(registry.retain(|name| name == "echo"), 0);
+        assert_eq!(registry.retain(|name| name != "echo"), 1);
+        assert!(registry.is_empty());
+    }
 }
@@ -0,0 +1,559 @@
+//! Bash 命令隔离执行（仅 Linux）
+//!
+//! 启用后 bash 工具执行的命令：
+//! - 在独立进程组中运行，超时时终止整个进程组
+//! - 通过 rlimit 限制 CPU 时间、内存、文件大小和进程数
+//! - 只继承白名单中的环境变量
+//! - 系统支持非特权用户命名空间时，进入独立的网络命名空间（只有 lo）禁止网络访问，
+//!   并在独立的挂载命名空间中将工作区和可写目录以外的文件系统重新挂载为只读
+//!
+//! 命名空间不可用时只应用前三项，降级说明附加到工具输出中。
+
+use crate::config::BashSandboxSettings;
+use parking_lot::RwLock;
+use std::path::{Path, PathBuf};
+use tokio::process::Command;
+
+/// 始终保留的基础环境变量
+const BASE_ENV_VARS: [&str; 10] = [
+    "PATH", "HOME", "USER", "LOGNAME", "SHELL", "LANG", "LC_ALL", "LC_CTYPE", "TZ", "TMPDIR",
+];
+
+/// bash 工具隔离管理器
+///
+/// 配置可在运行时更新，每次执行命令时按最新配置生成隔离方案
+#[derive(Debug, Default)]
+pub struct BashSandbox {
+    settings: RwLock<BashSandboxSettings>,
+}
+
+impl BashSandbox {
+    pub fn new(settings: BashSandboxSettings) -> Self {
+        Self {
+            settings: RwLock::new(settings),
+        }
+    }
+
+    /// 更新隔离配置
+    pub fn set_settings(&self, settings: BashSandboxSettings) {
+        *self.settings.write() = settings;
+    }
+
+    /// 当前隔离配置
+    pub fn settings(&self) -> BashSandboxSettings {
+        self.settings.read().clone()
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.settings.read().enabled
+    }
+
+    /// 按当前配置生成隔离方案，未启用时返回 None
+    ///
+    /// `workspace` 为命令的工作区目录，只读模式下保持可写
+    pub fn prepare(&self, workspace: &Path) -> Option<SandboxPlan> {
+        let settings = self.settings();
+        if !settings.enabled {
+            return None;
+        }
+        Some(SandboxPlan::new(settings, workspace))
+    }
+}
+
+/// 单次命令的隔离方案
+pub struct SandboxPlan {
+    settings: BashSandboxSettings,
+    env: Vec<(String, String)>,
+    notes: Vec<String>,
+    #[cfg(target_os = "linux")]
+    isolation: linux::Isolation,
+}
+
+impl SandboxPlan {
+    fn new(settings: BashSandboxSettings, workspace: &Path) -> Self {
+        let env = sandbox_env(std::env::vars(), &settings.env_allowlist);
+        let mut notes = Vec::new();
+
+        #[cfg(target_os = "linux")]
+        let isolation = {
+            let wants_namespaces = settings.deny_network || settings.read_only_outside_workspace;
+            let use_namespaces = wants_namespaces && linux::namespaces_available();
+            if wants_namespaces && !use_namespaces {
+                notes.push(
+                    "[沙箱] 系统不支持非特权用户命名空间，网络隔离和只读文件系统未生效".to_string(),
+                );
+            }
+            let writable: Vec<PathBuf> = std::iter::once(workspace.to_path_buf())
+                .chain(settings.writable_paths.iter().map(PathBuf::from))
+                .collect();
+            linux::Isolation::new(&settings, use_namespaces, &writable)
+        };
+
+        #[cfg(not(target_os = "linux"))]
+        {
+            let _ = workspace;
+            notes.push("[沙箱] 当前平台不支持命令隔离，只应用了环境变量白名单".to_string());
+        }
+
+        Self {
+            settings,
+            env,
+            notes,
+            #[cfg(target_os = "linux")]
+            isolation,
+        }
+    }
+
+    /// 传给命令的环境变量（不含防交互变量）
+    pub fn env(&self) -> &[(String, String)] {
+        &self.env
+    }
+
+    /// 隔离降级说明
+    pub fn notes(&self) -> &[String] {
+        &self.notes
+    }
+
+    /// stdout / stderr 各自保留的最大字节数
+    pub fn max_output_bytes(&self) -> usize {
+        self.settings.max_output_bytes
+    }
+
+    /// 配置命令：清理环境变量、独立进程组、资源限制和命名空间
+    pub fn apply(&self, cmd: &mut Command) {
+        cmd.env_clear();
+        cmd.envs(self.env.iter().map(|(k, v)| (k, v)));
+
+        #[cfg(target_os = "linux")]
+        self.isolation.apply(cmd);
+    }
+}
+
+/// 系统是否支持隔离所需的非特权用户命名空间
+pub fn namespaces_available() -> bool {
+    #[cfg(target_os = "linux")]
+    {
+        linux::namespaces_available()
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        false
+    }
+}
+
+/// 终止隔离命令的整个进程组（进程组 ID 即 shell 进程 ID）
+pub fn kill_process_group(pid: u32) {
+    #[cfg(target_os = "linux")]
+    // SAFETY: killpg 只向指定进程组发送信号
+    unsafe {
+        libc::killpg(pid as libc::pid_t, libc::SIGKILL);
+    }
+    #[cfg(not(target_os = "linux"))]
+    let _ = pid;
+}
+
+/// 从当前环境中筛选基础变量和白名单变量
+fn sandbox_env(
+    vars: impl Iterator<Item = (String, String)>,
+    allowlist: &[String],
+) -> Vec<(String, String)> {
+    let allowed = |key: &str| {
+        BASE_ENV_VARS.contains(&key)
+            || allowlist
+                .iter()
+                .any(|pattern| match pattern.strip_suffix('*') {
+                    Some(prefix) => key.starts_with(prefix),
+                    None => pattern == key,
+                })
+    };
+    let mut env: Vec<_> = vars.filter(|(key, _)| allowed(key)).collect();
+    env.sort();
+    env
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use crate::config::BashSandboxSettings;
+    use once_cell::sync::OnceCell;
+    use std::ffi::CString;
+    use std::io;
+    use std::os::unix::ffi::OsStrExt;
+    use std::path::{Path, PathBuf};
+    use tokio::process::Command;
+
+    const MIB: u64 = 1024 * 1024;
+
+    /// 保持原样的虚拟文件系统
+    const SKIPPED_MOUNTS: [&str; 3] = ["/proc", "/sys", "/dev"];
+
+    /// rlimit 资源限制，0 表示不限制
+    #[derive(Debug, Clone, Copy)]
+    struct Limits {
+        cpu_secs: u64,
+        memory_bytes: u64,
+        file_size_bytes: u64,
+        max_processes: u64,
+    }
+
+    impl Limits {
+        /// 在子进程中设置资源限制，不超过已有的硬限制
+        fn apply(&self) -> io::Result<()> {
+            let set = |resource, value: u64| -> io::Result<()> {
+                if value == 0 {
+                    return Ok(());
+                }
+                let mut current = libc::rlimit {
+                    rlim_cur: 0,
+                    rlim_max: 0,
+                };
+                // SAFETY: current 是有效的 rlimit 指针
+                check(unsafe { libc::getrlimit(resource, &mut current) })?;
+                let value = value.min(current.rlim_max);
+                let limit = libc::rlimit {
+                    rlim_cur: value,
+                    rlim_max: value,
+                };
+                // SAFETY: limit 是有效的 rlimit 指针
+                check(unsafe { libc::setrlimit(resource, &limit) })
+            };
+            set(libc::RLIMIT_CPU, self.cpu_secs)?;
+            set(libc::RLIMIT_DATA, self.memory_bytes)?;
+            set(libc::RLIMIT_FSIZE, self.file_size_bytes)?;
+            set(libc::RLIMIT_NPROC, self.max_processes)
+        }
+    }
+
+    /// 用户、挂载和网络命名空间配置
+    ///
+    /// 所有路径和映射内容在 fork 前准备好，子进程中只调用系统调用
+    #[derive(Debug, Clone)]
+    struct Namespaces {
+        deny_network: bool,
+        uid_map: Vec<u8>,
+        gid_map: Vec<u8>,
+        /// 只读模式下的文件系统视图，None 表示不修改挂载
+        filesystem: Option<ReadOnlyView>,
+    }
+
+    #[derive(Debug, Clone)]
+    struct ReadOnlyView {
+        /// 保持可写的目录（绑定挂载到自身）
+        writable: Vec<CString>,
+        /// 需要重新挂载为只读的挂载点及其原有挂载标志
+        mounts: Vec<(CString, libc::c_ulong)>,
+    }
+
+    impl Namespaces {
+        fn new(deny_network: bool, read_only: bool, writable: &[PathBuf]) -> Self {
+            // SAFETY: getuid / getgid 总是成功
+            let (uid, gid) = unsafe { (libc::getuid(), libc::getgid()) };
+            let filesystem = read_only.then(|| {
+                let writable: Vec<PathBuf> = writable
+                    .iter()
+                    .filter_map(|p| p.canonicalize().ok())
+                    .filter(|p| p.is_dir())
+                    .collect();
+                let mounts = std::fs::read_to_string("/proc/self/mountinfo")
+                    .map(|info| read_only_mounts(&info, &writable))
+                    .unwrap_or_default();
+                ReadOnlyView {
+                    writable: writable.iter().filter_map(|p| path_cstring(p)).collect(),
+                    mounts: mounts
+                        .into_iter()
+                        .filter_map(|(p, flags)| Some((path_cstring(&p)?, flags)))
+                        .collect(),
+                }
+            });
+            Self {
+                deny_network,
+                uid_map: format!("{uid} {uid} 1").into_bytes(),
+                gid_map: format!("{gid} {gid} 1").into_bytes(),
+                filesystem,
+            }
+        }
+
+        /// 在子进程中进入新的命名空间
+        fn enter(&self) -> io::Result<()> {
+            let mut flags = libc::CLONE_NEWUSER | libc::CLONE_NEWNS;
+            if self.deny_network {
+                flags |= libc::CLONE_NEWNET;
+            }
+            // SAFETY: fork 后的子进程是单线程的，可以进入新的用户命名空间
+            check(unsafe { libc::unshare(flags) })?;
+
+            // 将当前用户映射为命名空间内的同一用户，exec 后不保留特权
+            write_proc(b"/proc/self/setgroups\0", b"deny")?;
+            write_proc(b"/proc/self/uid_map\0", &self.uid_map)?;
+            write_proc(b"/proc/self/gid_map\0", &self.gid_map)?;
+
+            if let Some(view) = &self.filesystem {
+                view.apply()?;
+            }
+            Ok(())
+        }
+    }
+
+    impl ReadOnlyView {
+        fn apply(&self) -> io::Result<()> {
+            let root = c"/".as_ptr();
+            // 挂载变更不传播到宿主
+            mount(std::ptr::null(), root, libc::MS_REC | libc::MS_PRIVATE)?;
+            for dir in &self.writable {
+                mount(dir.as_ptr(), dir.as_ptr(), libc::MS_BIND | libc::MS_REC)?;
+            }
+            for (target, flags) in &self.mounts {
+                let result = mount(
+                    std::ptr::null(),
+                    target.as_ptr(),
+                    libc::MS_REMOUNT | libc::MS_BIND | libc::MS_RDONLY | flags,
+                );
+                // 根目录必须只读，其余挂载点失败时忽略（如已被覆盖的挂载）
+                if result.is_err() && target.as_bytes() == b"/" {
+                    return result;
+                }
+            }
+            // Command 在 pre_exec 之前已切换工作目录，重新解析路径以进入可写的绑定挂载
+            let mut cwd = [0 as libc::c_char; libc::PATH_MAX as usize];
+            // SAFETY: 缓冲区长度与传入的 size 一致
+            if unsafe { libc::getcwd(cwd.as_mut_ptr(), cwd.len()) }.is_null() {
+                return Err(io::Error::last_os_error());
+            }
+            // SAFETY: getcwd 成功时写入以 NUL 结尾的路径
+            check(unsafe { libc::chdir(cwd.as_ptr()) })
+        }
+    }
+
+    /// 隔离配置（资源限制 + 命名空间）
+    #[derive(Debug, Clone)]
+    pub struct Isolation {
+        limits: Limits,
+        namespaces: Option<Namespaces>,
+    }
+
+    impl Isolation {
+        pub fn new(
+            settings: &BashSandboxSettings,
+            use_namespaces: bool,
+            writable: &[PathBuf],
+        ) -> Self {
+            let limits = Limits {
+                cpu_secs: settings.cpu_time_secs,
+                memory_bytes: settings.memory_mb.saturating_mul(MIB),
+                file_size_bytes: settings.file_size_mb.saturating_mul(MIB),
+                max_processes: settings.max_processes,
+            };
+            let namespaces = use_namespaces.then(|| {
+                Namespaces::new(
+                    settings.deny_network,
+                    settings.read_only_outside_workspace,
+                    writable,
+                )
+            });
+            Self { limits, namespaces }
+        }
+
+        pub fn apply(&self, cmd: &mut Command) {
+            cmd.process_group(0);
+            let isolation = self.clone();
+            // SAFETY: pre_exec 闭包只调用系统调用，不分配内存
+            unsafe {
+                cmd.pre_exec(move || {
+                    if let Some(namespaces) = &isolation.namespaces {
+                        namespaces.enter()?;
+                    }
+                    isolation.limits.apply()
+                });
+            }
+        }
+    }
+
+    /// 检测非特权用户命名空间是否可用（结果缓存）
+    pub fn namespaces_available() -> bool {
+        static AVAILABLE: OnceCell<bool> = OnceCell::new();
+        *AVAILABLE.get_or_init(|| {
+            use std::os::unix::process::CommandExt;
+            let namespaces = Namespaces::new(true, false, &[]);
+            let mut cmd = std::process::Command::new("/bin/sh");
+            cmd.args(["-c", ":"])
+                .stdin(std::process::Stdio::null())
+                .stdout(std::process::Stdio::null())
+                .stderr(std::process::Stdio::null());
+            // SAFETY: pre_exec 闭包只调用系统调用
+            unsafe {
+                cmd.pre_exec(move || namespaces.enter());
+            }
+            let available = cmd.status().map(|s| s.success()).unwrap_or(false);
+            if !available {
+                tracing::warn!("[BashSandbox] 非特权用户命名空间不可用");
+            }
+            available
+        })
+    }
+
+    /// 从 mountinfo 中选出需要设为只读的挂载点（可写目录及其子挂载、虚拟文件系统除外）
+    fn read_only_mounts(mountinfo: &str, writable: &[PathBuf]) -> Vec<(PathBuf, libc::c_ulong)> {
+        mountinfo
+            .lines()
+            .filter_map(|line| {
+                let fields: Vec<&str> = line.split(' ').collect();
+                let mount_point = PathBuf::from(unescape_mount_path(fields.get(4)?));
+                let options = fields.get(5)?;
+                Some((mount_point, mount_flags(options)))
+            })
+            .filter(|(mount_point, _)| {
+                !writable.iter().any(|w| mount_point.starts_with(w))
+                    && !SKIPPED_MOUNTS.iter().any(|s| mount_point.starts_with(s))
+            })
+            .collect()
+    }
+
+    /// 需要保留的挂载标志（用户命名空间中重新挂载时不能清除这些标志）
+    fn mount_flags(options: &str) -> libc::c_ulong {
+        let mut flags = 0;
+        let mut atime = libc::MS_STRICTATIME;
+        for option in options.split(',') {
+            match option {
+                "nosuid" => flags |= libc::MS_NOSUID,
+                "nodev" => flags |= libc::MS_NODEV,
+                "noexec" => flags |= libc::MS_NOEXEC,
+                "noatime" => atime = libc::MS_NOATIME,
+                "relatime" => atime = libc::MS_RELATIME,
+                "nodiratime" => flags |= libc::MS_NODIRATIME,
+                _ => {}
+            }
+        }
+        flags | atime
+    }
+
+    /// 还原 mountinfo 中八进制转义的空白字符（如 `\040`）
+    fn unescape_mount_path(path: &str) -> String {
+        let bytes = path.as_bytes();
+        let mut out = Vec::with_capacity(bytes.len());
+        let mut i = 0;
+        while i < bytes.len() {
+            if bytes[i] == b'\\' && i + 4 <= bytes.len() {
+                let code = std::str::from_utf8(&bytes[i + 1..i + 4])
+                    .ok()
+                    .and_then(|digits| u8::from_str_radix(digits, 8).ok());
+                if let Some(code) = code {
+                    out.push(code);
+                    i += 4;
+                    continue;
+                }
+            }
+            out.push(bytes[i]);
+            i += 1;
+        }
+        String::from_utf8_lossy(&out).to_string()
+    }
+
+    fn path_cstring(path: &Path) -> Option<CString> {
+        CString::new(path.as_os_str().as_bytes()).ok()
+    }
+
+    fn mount(
+        source: *const libc::c_char,
+        target: *const libc::c_char,
+        flags: libc::c_ulong,
+    ) -> io::Result<()> {
+        // SAFETY: target 是以 NUL 结尾的路径，source 为空或以 NUL 结尾
+        check(unsafe { libc::mount(source, target, std::ptr::null(), flags, std::ptr::null()) })
+    }
+
+    /// 写入 /proc 文件（path 以 NUL 结尾）
+    fn write_proc(path: &[u8], content: &[u8]) -> io::Result<()> {
+        // SAFETY: path 以 NUL 结尾，content 指针和长度有效
+        unsafe {
+            let fd = libc::open(path.as_ptr() as *const libc::c_char, libc::O_WRONLY);
+            check(fd)?;
+            let written = libc::write(fd, content.as_ptr() as *const libc::c_void, content.len());
+            libc::close(fd);
+            if written < 0 {
+                return Err(io::Error::last_os_error());
+            }
+        }
+        Ok(())
+    }
+
+    fn check(result: libc::c_int) -> io::Result<()> {
+        if result < 0 {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(())
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn test_read_only_mounts() {
+            let info = "\
+22 1 8:1 / / rw,relatime shared:1 - ext4 /dev/sda1 rw
+23 22 0:5 / /proc rw,nosuid,nodev,noexec,relatime shared:2 - proc proc rw
+24 22 8:2 / /home rw,nosuid,nodev,noatime shared:3 - ext4 /dev/sda2 rw
+25 24 8:3 / /home/me/work rw shared:4 - ext4 /dev/sda3 rw
+26 22 0:40 / /mnt/my\\040disk ro,nosuid shared:5 - ext4 /dev/sdb1 ro";
+            let mounts = read_only_mounts(info, &[PathBuf::from("/home/me")]);
+            let paths: Vec<_> = mounts.iter().map(|(p, _)| p.clone()).collect();
+            assert_eq!(
+                paths,
+                vec![
+                    PathBuf::from("/"),
+                    PathBuf::from("/home"),
+                    PathBuf::from("/mnt/my disk")
+                ]
+            );
+            assert_eq!(mounts[0].1, libc::MS_RELATIME);
+            assert_eq!(
+                mounts[1].1,
+                libc::MS_NOSUID | libc::MS_NODEV | libc::MS_NOATIME
+            );
+            assert_eq!(mounts[2].1, libc::MS_NOSUID | libc::MS_STRICTATIME);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sandbox_env_allowlist() {
+        let vars = vec![
+            ("PATH".to_string(), "/usr/bin".to_string()),
+            ("AWS_SECRET_ACCESS_KEY".to_string(), "secret".to_string()),
+            ("CARGO_HOME".to_string(), "/cargo".to_string()),
+            ("RUSTUP_HOME".to_string(), "/rustup".to_string()),
+            ("HOME".to_string(), "/home/me".to_string()),
+        ];
+        let env = sandbox_env(
+            vars.into_iter(),
+            &["CARGO_*".to_string(), "RUSTUP_HOME".to_string()],
+        );
+        let keys: Vec<_> = env.iter().map(|(k, _)| k.as_str()).collect();
+        assert_eq!(keys, vec!["CARGO_HOME", "HOME", "PATH", "RUSTUP_HOME"]);
+    }
+
+    #[test]
+    fn test_prepare_disabled() {
+        let sandbox = BashSandbox::default();
+        assert!(!sandbox.is_enabled());
+        assert!(sandbox.prepare(Path::new("/tmp")).is_none());
+
+        sandbox.set_settings(BashSandboxSettings {
+            enabled: true,
+            ..Default::default()
+        });
+        let plan = sandbox.prepare(Path::new("/tmp")).unwrap();
+        assert_eq!(
+            plan.max_output_bytes(),
+            BashSandboxSettings::default().max_output_bytes
+        );
+        assert!(plan
+            .env()
+            .iter()
+            .all(|(k, _)| BASE_ENV_VARS.contains(&k.as_str())));
+    }
+}
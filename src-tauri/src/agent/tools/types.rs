@@ -126,10 +126,11 @@ impl JsonSchema {
 /// 定义单个参数的类型、描述和默认值
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PropertySchema {
-    /// 属性类型（string, number, boolean, array, object）
-    #[serde(rename = "type")]
+    /// 属性类型（string, number, boolean, array, object），未指定时为空
+    #[serde(rename = "type", default, skip_serializing_if = "String::is_empty")]
     pub prop_type: String,
     /// 属性描述
+    #[serde(default)]
     pub description: String,
     /// 默认值（可选）
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -137,6 +138,9 @@ pub struct PropertySchema {
     /// 枚举值（可选，用于限制取值范围）
     #[serde(skip_serializing_if = "Option::is_none", rename = "enum")]
     pub enum_values: Option<Vec<serde_json::Value>>,
+    /// 其他 JSON Schema 关键字（items、properties、anyOf 等），保留外部工具的完整定义
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
 }
 
 impl PropertySchema {
@@ -147,6 +151,7 @@ impl PropertySchema {
             description: description.into(),
             default: None,
             enum_values: None,
+            extra: serde_json::Map::new(),
         }
     }
 
@@ -157,6 +162,7 @@ impl PropertySchema {
             description: description.into(),
             default: None,
             enum_values: None,
+            extra: serde_json::Map::new(),
         }
     }
 
@@ -167,6 +173,7 @@ impl PropertySchema {
             description: description.into(),
             default: None,
             enum_values: None,
+            extra: serde_json::Map::new(),
         }
     }
 
@@ -177,6 +184,7 @@ impl PropertySchema {
             description: description.into(),
             default: None,
             enum_values: None,
+            extra: serde_json::Map::new(),
         }
     }
 
@@ -187,6 +195,18 @@ impl PropertySchema {
             description: description.into(),
             default: None,
             enum_values: None,
+            extra: serde_json::Map::new(),
+        }
+    }
+
+    /// 创建对象类型属性
+    pub fn object(description: impl Into<String>) -> Self {
+        Self {
+            prop_type: "object".to_string(),
+            description: description.into(),
+            default: None,
+            enum_values: None,
+            extra: serde_json::Map::new(),
         }
     }
 
@@ -201,6 +221,14 @@ impl PropertySchema {
         self.enum_values = Some(values);
         self
     }
+
+    /// 设置数组元素的 Schema
+    pub fn with_items(mut self, items: PropertySchema) -> Self {
+        if let Ok(items) = serde_json::to_value(items) {
+            self.extra.insert("items".to_string(), items);
+        }
+        self
+    }
 }
 
 /// 工具调用请求
@@ -0,0 +1,149 @@
+//! 目录遍历模块
+//!
+//! grep、glob、list_directory 工具共用的目录遍历，行为与 ripgrep 一致：
+//! - 默认遵循 .gitignore / .ignore，跳过隐藏文件
+//! - 不跟随符号链接，始终跳过 `.git` 目录
+//! - 按路径排序，结果稳定
+
+use super::security::SecurityManager;
+use super::types::ToolError;
+use ignore::{DirEntry, WalkBuilder};
+use std::path::{Path, PathBuf};
+
+/// 遍历选项
+#[derive(Debug, Clone, Copy, Default)]
+pub struct WalkOptions {
+    /// 包含隐藏文件和被忽略规则排除的文件
+    pub include_ignored: bool,
+    /// 最大深度（根目录的直接子项深度为 1）
+    pub max_depth: Option<usize>,
+}
+
+/// 经过安全检查的遍历根路径
+#[derive(Debug, Clone)]
+pub struct WalkRoot {
+    /// 规范化后的绝对路径
+    pub path: PathBuf,
+    /// 输出时使用的路径前缀（用户传入的路径，未指定时为空）
+    pub display: String,
+}
+
+impl WalkRoot {
+    /// 解析并验证遍历根路径，`path` 为空时使用基础目录
+    pub fn resolve(security: &SecurityManager, path: Option<&str>) -> Result<Self, ToolError> {
+        let raw = path.map(str::trim).filter(|p| !p.is_empty() && *p != ".");
+        let target = raw.map(Path::new).unwrap_or_else(|| security.base_dir());
+        let validated = security
+            .validate_path(target)
+            .map_err(|e| ToolError::Security(e.to_string()))?;
+        if !validated.exists() {
+            return Err(ToolError::ExecutionFailed(format!(
+                "路径不存在: {}",
+                target.display()
+            )));
+        }
+        Ok(Self {
+            path: validated,
+            display: raw.unwrap_or_default().trim_end_matches('/').to_string(),
+        })
+    }
+
+    /// 遍历条目相对于根路径的路径（使用 `/` 分隔）
+    pub fn relative(&self, path: &Path) -> String {
+        let relative = path.strip_prefix(&self.path).unwrap_or(path);
+        relative
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+
+    /// 输出用的路径：用户传入的路径 + 相对路径，与 ripgrep 的输出一致
+    pub fn display_path(&self, path: &Path) -> String {
+        let relative = self.relative(path);
+        match (self.display.is_empty(), relative.is_empty()) {
+            (true, true) => ".".to_string(),
+            (true, false) => relative,
+            (false, true) => self.display.clone(),
+            (false, false) => format!("{}/{}", self.display, relative),
+        }
+    }
+}
+
+/// 创建遍历器（目录在前，同类按名称排序）
+pub fn walker(root: &Path, options: WalkOptions) -> ignore::Walk {
+    let mut builder = WalkBuilder::new(root);
+    builder
+        .standard_filters(!options.include_ignored)
+        .follow_links(false)
+        .max_depth(options.max_depth)
+        // 不在 git 仓库中时也遵循 .gitignore
+        .require_git(false)
+        .filter_entry(|entry| entry.file_name() != ".git")
+        .sort_by_file_path(|a, b| b.is_dir().cmp(&a.is_dir()).then_with(|| a.cmp(b)));
+    builder.build()
+}
+
+/// 遍历根路径下的文件（忽略遍历错误）
+pub fn files(root: &Path, options: WalkOptions) -> impl Iterator<Item = DirEntry> {
+    walker(root, options)
+        .flatten()
+        .filter(|entry| entry.file_type().is_some_and(|t| t.is_file()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn setup() -> TempDir {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("src/nested")).unwrap();
+        fs::create_dir_all(root.join("target")).unwrap();
+        fs::write(root.join(".gitignore"), "target/\n").unwrap();
+        fs::write(root.join(".hidden"), "").unwrap();
+        fs::write(root.join("README.md"), "").unwrap();
+        fs::write(root.join("src/main.rs"), "").unwrap();
+        fs::write(root.join("src/nested/mod.rs"), "").unwrap();
+        fs::write(root.join("target/out.bin"), "").unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_files_respect_ignore_rules() {
+        let dir = setup();
+        let root = WalkRoot::resolve(&SecurityManager::new(dir.path()), None).unwrap();
+
+        let names: Vec<String> = files(&root.path, WalkOptions::default())
+            .map(|e| root.display_path(e.path()))
+            .collect();
+        assert_eq!(names, vec!["src/nested/mod.rs", "src/main.rs", "README.md"]);
+
+        let all: Vec<String> = files(
+            &root.path,
+            WalkOptions {
+                include_ignored: true,
+                max_depth: None,
+            },
+        )
+        .map(|e| root.relative(e.path()))
+        .collect();
+        assert!(all.contains(&"target/out.bin".to_string()));
+        assert!(all.contains(&".hidden".to_string()));
+    }
+
+    #[test]
+    fn test_display_path_uses_given_prefix() {
+        let dir = setup();
+        let security = SecurityManager::new(dir.path());
+
+        let root = WalkRoot::resolve(&security, Some("src/")).unwrap();
+        assert_eq!(root.display_path(&root.path.join("main.rs")), "src/main.rs");
+        assert_eq!(root.display_path(&root.path), "src");
+
+        assert!(WalkRoot::resolve(&security, Some("../")).is_err());
+        assert!(WalkRoot::resolve(&security, Some("missing")).is_err());
+    }
+}
@@ -88,6 +88,95 @@ pub struct AgentSession {
     pub created_at: String,
     /// 最后活动时间
     pub updated_at: String,
+    /// 会话标题
+    #[serde(default)]
+    pub title: Option<String>,
+    /// 是否已归档
+    #[serde(default)]
+    pub archived: bool,
+    /// 分叉来源会话 ID
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent_id: Option<String>,
+    /// 上下文压缩记录（按时间顺序，最后一条生效）
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub compactions: Vec<ContextCompaction>,
+}
+
+/// 上下文压缩记录
+///
+/// 压缩不会删除原始消息，只是后续请求中用摘要代替 `compacted_until` 之前的消息。
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ContextCompaction {
+    /// 记录 ID
+    pub id: String,
+    /// 本次新摘要的第一条消息序号
+    pub compacted_from: usize,
+    /// 被摘要代替的消息终点（不含）
+    pub compacted_until: usize,
+    /// 摘要内容（包含之前压缩的内容）
+    pub summary: String,
+    /// 生成摘要的模型
+    pub model: String,
+    /// 压缩前的上下文 token 数（估算）
+    pub tokens_before: u32,
+    /// 压缩后的上下文 token 数（估算）
+    pub tokens_after: u32,
+    /// 创建时间
+    pub created_at: String,
+}
+
+/// Agent 会话摘要（列表展示，不含消息）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AgentSessionSummary {
+    /// 会话 ID
+    pub id: String,
+    /// 会话标题
+    pub title: Option<String>,
+    /// 使用的模型
+    pub model: String,
+    /// 是否已归档
+    pub archived: bool,
+    /// 分叉来源会话 ID
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parent_id: Option<String>,
+    /// 消息数量
+    pub message_count: usize,
+    /// 创建时间
+    pub created_at: String,
+    /// 最后活动时间
+    pub updated_at: String,
+}
+
+impl From<&AgentSession> for AgentSessionSummary {
+    fn from(session: &AgentSession) -> Self {
+        Self {
+            id: session.id.clone(),
+            title: session.title.clone(),
+            model: session.model.clone(),
+            archived: session.archived,
+            parent_id: session.parent_id.clone(),
+            message_count: session.messages.len(),
+            created_at: session.created_at.clone(),
+            updated_at: session.updated_at.clone(),
+        }
+    }
+}
+
+/// 会话搜索命中
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AgentSessionSearchHit {
+    /// 会话 ID
+    pub session_id: String,
+    /// 会话标题
+    pub title: Option<String>,
+    /// 命中消息的序号（标题命中时为 None）
+    pub message_index: Option<usize>,
+    /// 命中消息的角色
+    pub role: Option<String>,
+    /// 命中内容片段
+    pub snippet: String,
+    /// 会话最后活动时间
+    pub updated_at: String,
 }
 
 /// Agent 消息
@@ -105,6 +194,9 @@ pub struct AgentMessage {
     /// 工具调用 ID（tool 角色消息需要）
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tool_call_id: Option<String>,
+    /// 是否固定（上下文压缩时保留原文）
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub pinned: bool,
 }
 
 /// 消息内容类型
@@ -312,6 +404,17 @@ pub enum StreamEvent {
         arguments: Option<String>,
     },
 
+    /// 工具执行过程中的输出增量（bash 命令的 stdout / stderr）
+    #[serde(rename = "tool_output")]
+    ToolOutput {
+        /// 工具调用 ID
+        tool_id: String,
+        /// 输出流：`stdout` 或 `stderr`
+        stream: String,
+        /// 输出文本
+        text: String,
+    },
+
     /// 工具调用结束
     /// Requirements: 7.6 - 工具执行完成后通知前端
     #[serde(rename = "tool_end")]
@@ -322,6 +425,63 @@ pub enum StreamEvent {
         result: ToolExecutionResult,
     },
 
+    /// 工具调用等待用户审批
+    /// 前端通过 `native_agent_resolve_approval` 答复，HTTP 客户端通过 `POST /v1/agent/approvals/:request_id` 答复
+    #[serde(rename = "approval_request")]
+    ApprovalRequest {
+        /// 审批请求 ID
+        request_id: String,
+        /// 工具调用 ID
+        tool_id: String,
+        /// 工具名称
+        tool_name: String,
+        /// 工具参数（JSON 字符串）
+        arguments: String,
+        /// 匹配审批规则的调用对象（命令、路径等）
+        #[serde(skip_serializing_if = "Option::is_none")]
+        subject: Option<String>,
+        /// 审批超时时间（秒）
+        timeout_secs: u64,
+    },
+
+    /// 上下文已压缩
+    #[serde(rename = "context_compacted")]
+    ContextCompacted {
+        /// 压缩记录 ID
+        compaction_id: String,
+        /// 本次摘要的消息数
+        compacted_messages: usize,
+        /// 压缩前的上下文 token 数（估算）
+        tokens_before: u32,
+        /// 压缩后的上下文 token 数（估算）
+        tokens_after: u32,
+    },
+
+    /// 工具执行前已创建工作区检查点
+    #[serde(rename = "checkpoint_created")]
+    CheckpointCreated {
+        /// 检查点 ID
+        checkpoint_id: String,
+        /// 创建时会话的消息数
+        message_index: usize,
+        /// 快照的文件数
+        file_count: usize,
+        /// 是否有无法追踪的改动
+        untracked: bool,
+    },
+
+    /// 子代理（`task` 工具）产生的事件
+    /// 前端按 `parent_tool_id` 将子代理的进度显示在对应工具调用之下
+    #[serde(rename = "subagent_event")]
+    SubAgent {
+        /// 启动子代理的工具调用 ID
+        parent_tool_id: String,
+        /// 子代理会话 ID
+        session_id: String,
+        /// 子代理原始事件
+        event: Box<StreamEvent>,
+    },
+
     /// 完成（单次 API 响应完成，工具循环可能继续）
     /// Requirements: 1.3 - THE Streaming_Handler SHALL emit a done event with token usage statistics
     #[serde(rename = "done")]
@@ -9,10 +9,11 @@ use std::sync::Arc;
 use tauri::State;
 
 use crate::flow_monitor::{
-    get_filter_help, BatchOperation, BatchOperations, BatchResult, DiffConfig, ExportFormat,
-    ExportOptions, FilterExpr, FilterParser, FlowAnnotations, FlowDiff, FlowDiffResult,
-    FlowExporter, FlowFilter, FlowMonitor, FlowQueryResult, FlowQueryService, FlowSearchResult,
-    FlowSortBy, FlowStats, LLMFlow, FILTER_HELP,
+    get_filter_help, AggregateTable, BatchOperation, BatchOperations, BatchResult,
+    CaptureRedactionConfig, DiffConfig, ExportFormat, ExportOptions, FilterExpr, FilterParser,
+    FlowAnnotations, FlowDiff, FlowDiffResult, FlowExporter, FlowFilter, FlowMonitor, FlowQuery,
+    FlowQueryResult, FlowQueryService, FlowSearchResult, FlowSortBy, FlowStats, LLMFlow,
+    FILTER_HELP,
 };
 
 // ============================================================================
@@ -860,6 +861,41 @@ pub async fn disable_flow_monitor(monitor: State<'_, FlowMonitorState>) -> Resul
     Ok(())
 }
 
+/// 获取捕获时脱敏配置
+///
+/// # Arguments
+/// * `monitor` - Flow 监控服务状态
+///
+/// # Returns
+/// * `Ok(CaptureRedactionConfig)` - 成功时返回脱敏配置
+#[tauri::command]
+pub async fn get_capture_redaction_config(
+    monitor: State<'_, FlowMonitorState>,
+) -> Result<CaptureRedactionConfig, String> {
+    Ok(monitor.0.capture_redaction_config().await)
+}
+
+/// 设置捕获时脱敏配置
+///
+/// # Arguments
+/// * `config` - 新的脱敏配置
+/// * `monitor` - Flow 监控服务状态
+///
+/// # Returns
+/// * `Ok(())` - 成功
+/// * `Err(String)` - 自定义规则无效时返回错误消息
+#[tauri::command]
+pub async fn set_capture_redaction_config(
+    config: CaptureRedactionConfig,
+    monitor: State<'_, FlowMonitorState>,
+) -> Result<(), String> {
+    config
+        .validate()
+        .map_err(|e| format!("设置脱敏配置失败: {}", e))?;
+    monitor.0.update_capture_redaction_config(config).await;
+    Ok(())
+}
+
 // ============================================================================
 // 测试模块
 // ============================================================================
@@ -928,17 +964,27 @@ pub async fn subscribe_flow_events(
     monitor: State<'_, FlowMonitorState>,
 ) -> Result<(), String> {
     let mut receiver = monitor.0.subscribe();
+    let mut chunks = monitor.0.subscribe_chunks();
 
     // 启动后台任务来转发事件
     tokio::spawn(async move {
         loop {
-            match receiver.recv().await {
+            // chunk 事件总是先于同一 Flow 的结束事件发送，优先处理以保持顺序
+            let (received, is_chunk) = tokio::select! {
+                biased;
+                received = chunks.recv() => (received, true),
+                received = receiver.recv() => (received, false),
+            };
+            match received {
                 Ok(event) => {
                     // 将事件发送到前端
                     if let Err(e) = app.emit("flow-event", &event) {
                         tracing::warn!("发送 Flow 事件到前端失败: {}", e);
                     }
                 }
+                Err(tokio::sync::broadcast::error::RecvError::Lagged(n)) if is_chunk => {
+                    tracing::debug!("Flow chunk 事件接收器丢弃 {} 条消息", n);
+                }
                 Err(tokio::sync::broadcast::error::RecvError::Lagged(n)) => {
                     tracing::warn!("Flow 事件接收器落后 {} 条消息", n);
                 }
@@ -1108,11 +1154,18 @@ pub struct QueryFlowsWithExpressionRequest {
 pub async fn query_flows_with_expression(
     request: QueryFlowsWithExpressionRequest,
     query_service: State<'_, FlowQueryServiceState>,
+    quick_filter_manager: State<'_, QuickFilterManagerState>,
 ) -> Result<FlowQueryResult, String> {
+    // 展开 ~view 引用的已保存过滤器
+    let expr = quick_filter_manager
+        .0
+        .resolve(&request.filter_expr)
+        .map_err(|e| format!("查询 Flow 失败: {}", e))?;
+
     query_service
         .0
-        .query_with_expression(
-            &request.filter_expr,
+        .query_with_filter_expr(
+            &expr,
             request.sort_by,
             request.sort_desc,
             request.page,
@@ -1122,6 +1175,54 @@ pub async fn query_flows_with_expression(
         .map_err(|e| format!("查询 Flow 失败: {}", e))
 }
 
+/// 聚合查询请求参数
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueryFlowsAggregateRequest {
+    /// 带 `group by` 后缀的查询，如 `~since 1d group by model | count, p95 latency`
+    pub query: String,
+    /// 参与统计的最大 Flow 数量
+    #[serde(default = "default_aggregate_limit")]
+    pub limit: usize,
+}
+
+fn default_aggregate_limit() -> usize {
+    10000
+}
+
+/// 执行聚合查询
+///
+/// 按 `group by` 字段分组统计满足过滤条件的 Flow，返回表格数据。
+///
+/// # Arguments
+/// * `request` - 聚合查询请求参数
+/// * `query_service` - 查询服务状态
+/// * `quick_filter_manager` - 快速过滤器管理器状态（用于展开 ~view 引用）
+///
+/// # Returns
+/// * `Ok(AggregateTable)` - 成功时返回聚合结果表
+/// * `Err(String)` - 失败时返回错误消息
+#[tauri::command]
+pub async fn query_flows_aggregate(
+    request: QueryFlowsAggregateRequest,
+    query_service: State<'_, FlowQueryServiceState>,
+    quick_filter_manager: State<'_, QuickFilterManagerState>,
+) -> Result<AggregateTable, String> {
+    let mut query = FlowQuery::parse(&request.query).map_err(|e| format!("聚合查询失败: {}", e))?;
+    if let Some(filter) = query.filter.take() {
+        let expanded = quick_filter_manager
+            .0
+            .expand(&filter)
+            .map_err(|e| format!("聚合查询失败: {}", e))?;
+        query.filter = Some(expanded);
+    }
+
+    query_service
+        .0
+        .aggregate(&query, request.limit)
+        .await
+        .map_err(|e| format!("聚合查询失败: {}", e))
+}
+
 // ============================================================================
 // 拦截器相关命令
 // ============================================================================
@@ -2026,6 +2127,66 @@ pub async fn register_active_session(
     Ok(())
 }
 
+// ============================================================================
+// 对话线程命令
+// ============================================================================
+
+use crate::flow_monitor::{
+    ConversationThread, ConversationThreader, ConversationTimeline, ThreadingConfig,
+};
+
+/// 线程重建默认扫描的最近 Flow 数量
+const DEFAULT_THREAD_SCAN_LIMIT: usize = 500;
+
+/// 获取自动识别的对话线程
+///
+/// # Arguments
+/// * `limit` - 参与线程重建的最近 Flow 数量（默认 500）
+/// * `config` - 线程重建配置（可选）
+/// * `query_service` - 查询服务状态
+///
+/// # Returns
+/// * `Ok(Vec<ConversationThread>)` - 按最后活动时间降序排列的线程列表
+#[tauri::command]
+pub async fn get_conversation_threads(
+    limit: Option<usize>,
+    config: Option<ThreadingConfig>,
+    query_service: State<'_, FlowQueryServiceState>,
+) -> Result<Vec<ConversationThread>, String> {
+    let flows = query_service
+        .0
+        .get_recent(limit.unwrap_or(DEFAULT_THREAD_SCAN_LIMIT))
+        .await;
+    let threader = ConversationThreader::new(config.unwrap_or_default());
+    Ok(threader.build_threads(&flows))
+}
+
+/// 获取包含指定 Flow 的对话时间线
+///
+/// # Arguments
+/// * `flow_id` - 线程中任意一个 Flow 的 ID
+/// * `limit` - 参与线程重建的最近 Flow 数量（默认 500）
+/// * `config` - 线程重建配置（可选）
+/// * `query_service` - 查询服务状态
+///
+/// # Returns
+/// * `Ok(Some(ConversationTimeline))` - 时间线
+/// * `Ok(None)` - Flow 不在最近的 Flow 中
+#[tauri::command]
+pub async fn get_conversation_timeline(
+    flow_id: String,
+    limit: Option<usize>,
+    config: Option<ThreadingConfig>,
+    query_service: State<'_, FlowQueryServiceState>,
+) -> Result<Option<ConversationTimeline>, String> {
+    let flows = query_service
+        .0
+        .get_recent(limit.unwrap_or(DEFAULT_THREAD_SCAN_LIMIT))
+        .await;
+    let threader = ConversationThreader::new(config.unwrap_or_default());
+    Ok(threader.timeline_for_flow(&flow_id, &flows))
+}
+
 // ============================================================================
 // 快速过滤器命令
 // ============================================================================
@@ -3206,6 +3367,113 @@ pub async fn batch_add_to_session(
         .await)
 }
 
+// ============================================================================
+// 保留策略命令
+// ============================================================================
+
+use crate::flow_monitor::{
+    RetentionManager, RetentionPolicy, RetentionRunReport, StorageUsageReport,
+};
+
+/// 保留策略管理器状态封装
+///
+/// 未启用 Flow 文件存储时为 None。
+pub struct FlowRetentionState(pub Option<Arc<RetentionManager>>);
+
+impl FlowRetentionState {
+    fn manager(&self) -> Result<&Arc<RetentionManager>, String> {
+        self.0
+            .as_ref()
+            .ok_or_else(|| "Flow 文件存储未启用".to_string())
+    }
+}
+
+/// 获取 Flow 保留策略
+///
+/// # Arguments
+/// * `retention` - 保留策略管理器状态
+///
+/// # Returns
+/// * `Ok(RetentionPolicy)` - 成功时返回当前策略
+/// * `Err(String)` - 文件存储未启用时返回错误消息
+#[tauri::command]
+pub async fn get_flow_retention_policy(
+    retention: State<'_, FlowRetentionState>,
+) -> Result<RetentionPolicy, String> {
+    Ok(retention.manager()?.policy())
+}
+
+/// 设置 Flow 保留策略
+///
+/// # Arguments
+/// * `policy` - 新的保留策略
+/// * `retention` - 保留策略管理器状态
+///
+/// # Returns
+/// * `Ok(())` - 成功
+/// * `Err(String)` - 规则无效或保存失败时返回错误消息
+#[tauri::command]
+pub async fn set_flow_retention_policy(
+    policy: RetentionPolicy,
+    retention: State<'_, FlowRetentionState>,
+) -> Result<(), String> {
+    retention
+        .manager()?
+        .set_policy(policy)
+        .map_err(|e| format!("设置保留策略失败: {}", e))
+}
+
+/// 立即按保留策略执行一次压缩
+///
+/// # Arguments
+/// * `retention` - 保留策略管理器状态
+///
+/// # Returns
+/// * `Ok(RetentionRunReport)` - 成功时返回执行报告
+/// * `Err(String)` - 失败时返回错误消息
+#[tauri::command]
+pub async fn run_flow_retention(
+    retention: State<'_, FlowRetentionState>,
+) -> Result<RetentionRunReport, String> {
+    let manager = retention.manager()?.clone();
+    tokio::task::spawn_blocking(move || manager.run_compaction())
+        .await
+        .map_err(|e| format!("执行保留策略失败: {}", e))?
+        .map_err(|e| format!("执行保留策略失败: {}", e))
+}
+
+/// 获取最近一次保留策略执行报告
+///
+/// # Arguments
+/// * `retention` - 保留策略管理器状态
+///
+/// # Returns
+/// * `Ok(Option<RetentionRunReport>)` - 尚未执行过时返回 None
+#[tauri::command]
+pub async fn get_last_flow_retention_run(
+    retention: State<'_, FlowRetentionState>,
+) -> Result<Option<RetentionRunReport>, String> {
+    Ok(retention.manager()?.last_run())
+}
+
+/// 获取 Flow 存储用量报告
+///
+/// # Arguments
+/// * `retention` - 保留策略管理器状态
+///
+/// # Returns
+/// * `Ok(StorageUsageReport)` - 成功时返回用量报告
+/// * `Err(String)` - 失败时返回错误消息
+#[tauri::command]
+pub async fn get_flow_storage_usage(
+    retention: State<'_, FlowRetentionState>,
+) -> Result<StorageUsageReport, String> {
+    retention
+        .manager()?
+        .storage_usage()
+        .map_err(|e| format!("获取存储用量失败: {}", e))
+}
+
 // ============================================================================
 // 实时监控增强命令
 // ============================================================================
@@ -2,10 +2,16 @@
 //!
 //! 提供原生 Rust Agent 的 Tauri 命令，替代 aster sidecar 方案
 
+use crate::agent::approval::DEFAULT_AUDIT_LIMIT;
+use crate::agent::mcp::McpServerStatus;
 use crate::agent::{
-    AgentSession, ImageData, NativeAgentState, NativeChatRequest, NativeChatResponse, ProviderType,
-    StreamEvent, ToolLoopEngine,
+    AgentSession, AgentSessionSearchHit, AgentSessionSummary, ApprovalDecision, Checkpoint,
+    ContextCompaction, FileChange, ImageData, NativeAgentState, NativeChatRequest,
+    NativeChatResponse, PendingApproval, ProviderType, RewindResult, StreamEvent,
+    ToolApprovalAuditEntry, ToolLoopEngine,
 };
+use crate::database::DbConnection;
+use crate::flow_monitor::ExportFormat;
 use crate::AppState;
 use serde::{Deserialize, Serialize};
 use tauri::{Emitter, State};
@@ -21,6 +27,7 @@ pub struct NativeAgentStatus {
 pub async fn native_agent_init(
     agent_state: State<'_, NativeAgentState>,
     app_state: State<'_, AppState>,
+    db: State<'_, DbConnection>,
 ) -> Result<NativeAgentStatus, String> {
     tracing::info!("[NativeAgent] 初始化 Agent");
 
@@ -50,6 +57,7 @@ pub async fn native_agent_init(
     );
 
     agent_state.init(base_url.clone(), api_key, provider_type)?;
+    connect_mcp_servers(&agent_state, &db).await;
 
     tracing::info!("[NativeAgent] Agent 初始化成功: {}", base_url);
 
@@ -59,6 +67,21 @@ pub async fn native_agent_init(
     })
 }
 
+/// 连接 MCP 服务器，失败只记录日志，不影响 Agent 使用内置工具
+async fn connect_mcp_servers(agent_state: &NativeAgentState, db: &DbConnection) {
+    match agent_state.connect_mcp_servers(db).await {
+        Ok(statuses) => {
+            let connected = statuses.iter().filter(|s| s.connected).count();
+            tracing::info!(
+                "[NativeAgent] MCP 服务器已连接: {}/{}",
+                connected,
+                statuses.len()
+            );
+        }
+        Err(e) => tracing::warn!("[NativeAgent] 读取 MCP 服务器配置失败: {}", e),
+    }
+}
+
 #[tauri::command]
 pub async fn native_agent_status(
     agent_state: State<'_, NativeAgentState>,
@@ -142,6 +165,7 @@ pub async fn native_agent_chat_stream(
     app_handle: tauri::AppHandle,
     agent_state: State<'_, NativeAgentState>,
     app_state: State<'_, AppState>,
+    db: State<'_, DbConnection>,
     message: String,
     event_name: String,
     session_id: Option<String>,
@@ -176,11 +200,26 @@ pub async fn native_agent_chat_stream(
         let base_url = format!("http://127.0.0.1:{}", port);
         let provider_type = ProviderType::from_str(&default_provider);
         agent_state.init(base_url, api_key, provider_type)?;
+        connect_mcp_servers(&agent_state, &db).await;
     }
 
     // 获取工具注册表（用于创建 ToolLoopEngine）
     let tool_registry = agent_state.get_tool_registry()?;
 
+    // 使用最新的审批、上下文压缩和 bash 隔离配置
+    let approvals = agent_state.approvals();
+    {
+        let state = app_state.read().await;
+        approvals.set_settings(state.config.tool_approval.clone());
+        agent_state
+            .compactor()
+            .set_settings(state.config.context_compaction.clone());
+        agent_state
+            .bash_sandbox()
+            .set_settings(state.config.bash_sandbox.clone());
+    }
+    let approval_session_id = session_id.clone();
+
     let request = NativeChatRequest {
         session_id, // 使用前端传递的 session_id 以保持上下文
         message,
@@ -209,7 +248,8 @@ pub async fn native_agent_chat_stream(
         eprintln!("[native_agent_chat_stream] 后台任务开始执行");
 
         // 创建工具循环引擎（使用共享的 tool_registry）
-        let tool_loop_engine = ToolLoopEngine::new(tool_registry);
+        let tool_loop_engine =
+            ToolLoopEngine::new(tool_registry).with_approvals(approvals, approval_session_id);
         eprintln!("[native_agent_chat_stream] 工具循环引擎创建成功");
 
         let (tx, mut rx) = mpsc::channel::<StreamEvent>(100);
@@ -294,6 +334,163 @@ pub async fn native_agent_delete_session(
 #[tauri::command]
 pub async fn native_agent_list_sessions(
     agent_state: State<'_, NativeAgentState>,
-) -> Result<Vec<AgentSession>, String> {
-    Ok(agent_state.list_sessions())
+    include_archived: Option<bool>,
+) -> Result<Vec<AgentSessionSummary>, String> {
+    agent_state.list_sessions(include_archived.unwrap_or(false))
+}
+
+#[tauri::command]
+pub async fn native_agent_search_sessions(
+    agent_state: State<'_, NativeAgentState>,
+    query: String,
+    include_archived: Option<bool>,
+    limit: Option<usize>,
+) -> Result<Vec<AgentSessionSearchHit>, String> {
+    agent_state.search_sessions(&query, include_archived.unwrap_or(false), limit)
+}
+
+#[tauri::command]
+pub async fn native_agent_fork_session(
+    agent_state: State<'_, NativeAgentState>,
+    session_id: String,
+    message_index: usize,
+) -> Result<AgentSession, String> {
+    agent_state.fork_session(&session_id, message_index)
+}
+
+#[tauri::command]
+pub async fn native_agent_rename_session(
+    agent_state: State<'_, NativeAgentState>,
+    session_id: String,
+    title: Option<String>,
+) -> Result<bool, String> {
+    agent_state.rename_session(&session_id, title.as_deref())
+}
+
+#[tauri::command]
+pub async fn native_agent_archive_session(
+    agent_state: State<'_, NativeAgentState>,
+    session_id: String,
+    archived: bool,
+) -> Result<bool, String> {
+    agent_state.archive_session(&session_id, archived)
+}
+
+/// 导出会话，format 支持 json、jsonl 和 markdown
+#[tauri::command]
+pub async fn native_agent_export_session(
+    agent_state: State<'_, NativeAgentState>,
+    session_id: String,
+    format: ExportFormat,
+) -> Result<String, String> {
+    agent_state.export_session(&session_id, format)
+}
+
+/// 重新连接 MCP 服务器（配置变更后调用）
+#[tauri::command]
+pub async fn native_agent_mcp_connect(
+    agent_state: State<'_, NativeAgentState>,
+    db: State<'_, DbConnection>,
+) -> Result<Vec<McpServerStatus>, String> {
+    agent_state.connect_mcp_servers(&db).await
+}
+
+#[tauri::command]
+pub async fn native_agent_mcp_status(
+    agent_state: State<'_, NativeAgentState>,
+) -> Result<Vec<McpServerStatus>, String> {
+    Ok(agent_state.mcp_status())
+}
+
+/// 答复工具调用审批请求（批准 / 本会话内允许 / 拒绝 / 修改参数后执行）
+#[tauri::command]
+pub async fn native_agent_resolve_approval(
+    agent_state: State<'_, NativeAgentState>,
+    request_id: String,
+    decision: ApprovalDecision,
+) -> Result<(), String> {
+    agent_state.approvals().resolve(&request_id, decision)
+}
+
+#[tauri::command]
+pub async fn native_agent_pending_approvals(
+    agent_state: State<'_, NativeAgentState>,
+) -> Result<Vec<PendingApproval>, String> {
+    Ok(agent_state.approvals().pending())
+}
+
+/// 查询工具调用审批审计日志，session_id 为空时查询全部会话
+#[tauri::command]
+pub async fn native_agent_approval_audit(
+    agent_state: State<'_, NativeAgentState>,
+    session_id: Option<String>,
+    limit: Option<usize>,
+) -> Result<Vec<ToolApprovalAuditEntry>, String> {
+    agent_state
+        .approvals()
+        .audit_log(session_id.as_deref(), limit.unwrap_or(DEFAULT_AUDIT_LIMIT))
+}
+
+/// 手动压缩会话上下文（保留最近几轮对话和固定消息）
+#[tauri::command]
+pub async fn native_agent_compact_session(
+    agent_state: State<'_, NativeAgentState>,
+    app_state: State<'_, AppState>,
+    session_id: String,
+) -> Result<ContextCompaction, String> {
+    agent_state
+        .compactor()
+        .set_settings(app_state.read().await.config.context_compaction.clone());
+    agent_state.compact_session(&session_id).await
+}
+
+/// 固定 / 取消固定消息，固定的消息在上下文压缩时保留原文
+#[tauri::command]
+pub async fn native_agent_pin_message(
+    agent_state: State<'_, NativeAgentState>,
+    session_id: String,
+    message_index: usize,
+    pinned: bool,
+) -> Result<(), String> {
+    agent_state.set_message_pinned(&session_id, message_index, pinned)
+}
+
+/// 列出会话的工作区检查点
+#[tauri::command]
+pub async fn native_agent_list_checkpoints(
+    agent_state: State<'_, NativeAgentState>,
+    session_id: String,
+) -> Result<Vec<Checkpoint>, String> {
+    agent_state.list_checkpoints(&session_id)
+}
+
+/// 检查点以来 Agent 涉及文件的全部变更（统一 diff）
+#[tauri::command]
+pub async fn native_agent_checkpoint_diff(
+    agent_state: State<'_, NativeAgentState>,
+    session_id: String,
+    checkpoint_id: String,
+) -> Result<Vec<FileChange>, String> {
+    agent_state.checkpoint_diff(&session_id, &checkpoint_id)
+}
+
+/// 将文件恢复到检查点时的状态，paths 为空时恢复全部文件
+#[tauri::command]
+pub async fn native_agent_restore_checkpoint_files(
+    agent_state: State<'_, NativeAgentState>,
+    session_id: String,
+    checkpoint_id: String,
+    paths: Option<Vec<String>>,
+) -> Result<Vec<String>, String> {
+    agent_state.restore_checkpoint_files(&session_id, &checkpoint_id, paths.as_deref())
+}
+
+/// 将会话和文件一起回退到指定用户消息之前
+#[tauri::command]
+pub async fn native_agent_rewind_session(
+    agent_state: State<'_, NativeAgentState>,
+    session_id: String,
+    message_index: usize,
+) -> Result<RewindResult, String> {
+    agent_state.rewind_session(&session_id, message_index)
 }
@@ -5,11 +5,15 @@
 //! - install_plugin_from_url: 从 URL 安装插件
 //! - uninstall_plugin: 卸载插件
 //! - list_installed_plugins: 列出已安装插件
+//! - install_plugin_from_index / check_plugin_updates / update_plugin / rollback_plugin:
+//!   签名插件索引安装、更新检查和回滚
+//! - pin_plugin_version: 固定插件版本
+//! - list/add/remove_plugin_trusted_key(s): 管理发布者公钥信任库
 //!
 //! _需求: 1.1, 2.1, 2.2, 2.4, 3.1, 3.2, 3.3, 4.2, 6.1_
 
 use crate::plugin::installer::{
-    InstallProgress, InstalledPlugin, PluginInstaller, ProgressCallback,
+    InstallProgress, InstalledPlugin, PluginInstaller, PluginUpdate, ProgressCallback, TrustedKey,
 };
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
@@ -54,12 +58,14 @@ impl<R: Runtime> crate::plugin::installer::ProgressCallback for TauriProgressCal
 /// 从本地文件安装插件
 ///
 /// 流程: 验证 → 解压 → 注册 → 复制文件
+/// `allow_unsigned` 为 true 时本次安装接受未签名的包
 /// _需求: 1.1, 3.1, 3.2, 3.3_
 #[tauri::command]
 pub async fn install_plugin_from_file<R: Runtime>(
     app_handle: AppHandle<R>,
     state: tauri::State<'_, PluginInstallerState>,
     file_path: String,
+    allow_unsigned: Option<bool>,
 ) -> Result<InstallResult, String> {
     let installer = state.0.read().await;
     let path = PathBuf::from(&file_path);
@@ -77,7 +83,10 @@ pub async fn install_plugin_from_file<R: Runtime>(
     let progress_callback = TauriProgressCallback::new(app_handle);
 
     // 执行安装
-    match installer.install_from_file(&path, &progress_callback).await {
+    match installer
+        .install_from_file(&path, allow_unsigned.unwrap_or(false), &progress_callback)
+        .await
+    {
         Ok(plugin) => Ok(InstallResult {
             success: true,
             plugin: Some(plugin),
@@ -98,12 +107,14 @@ pub async fn install_plugin_from_file<R: Runtime>(
 /// 从 URL 安装插件
 ///
 /// 流程: 下载 → 验证 → 解压 → 注册 → 复制文件
+/// `allow_unsigned` 为 true 时本次安装接受未签名的包
 /// _需求: 2.1, 2.2, 2.4_
 #[tauri::command]
 pub async fn install_plugin_from_url<R: Runtime>(
     app_handle: AppHandle<R>,
     state: tauri::State<'_, PluginInstallerState>,
     url: String,
+    allow_unsigned: Option<bool>,
 ) -> Result<InstallResult, String> {
     let installer = state.0.read().await;
 
@@ -120,7 +131,10 @@ pub async fn install_plugin_from_url<R: Runtime>(
     let progress_callback = TauriProgressCallback::new(app_handle);
 
     // 执行安装
-    match installer.install_from_url(&url, &progress_callback).await {
+    match installer
+        .install_from_url(&url, allow_unsigned.unwrap_or(false), &progress_callback)
+        .await
+    {
         Ok(plugin) => Ok(InstallResult {
             success: true,
             plugin: Some(plugin),
@@ -187,3 +201,145 @@ pub async fn is_plugin_installed(
         .is_installed(&plugin_id)
         .map_err(|e| e.to_string())
 }
+
+/// 从签名插件索引安装插件
+///
+/// `index` 可为本地目录、file:// URL 或 HTTP(S) URL；`version` 为空时安装兼容的最新版本
+#[tauri::command]
+pub async fn install_plugin_from_index<R: Runtime>(
+    app_handle: AppHandle<R>,
+    state: tauri::State<'_, PluginInstallerState>,
+    index: String,
+    name: String,
+    version: Option<String>,
+) -> Result<InstallResult, String> {
+    let installer = state.0.read().await;
+    let progress_callback = TauriProgressCallback::new(app_handle);
+
+    match installer
+        .install_from_index(&index, &name, version.as_deref(), &progress_callback)
+        .await
+    {
+        Ok(plugin) => Ok(InstallResult {
+            success: true,
+            plugin: Some(plugin),
+            error: None,
+        }),
+        Err(e) => {
+            progress_callback.on_progress(InstallProgress::failed(e.to_string()));
+            Ok(InstallResult {
+                success: false,
+                plugin: None,
+                error: Some(e.to_string()),
+            })
+        }
+    }
+}
+
+/// 检查插件更新
+#[tauri::command]
+pub async fn check_plugin_updates(
+    state: tauri::State<'_, PluginInstallerState>,
+    index: String,
+) -> Result<Vec<PluginUpdate>, String> {
+    let installer = state.0.read().await;
+    installer
+        .check_updates(&index)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// 更新插件（固定版本时更新到固定版本）
+#[tauri::command]
+pub async fn update_plugin<R: Runtime>(
+    app_handle: AppHandle<R>,
+    state: tauri::State<'_, PluginInstallerState>,
+    index: String,
+    plugin_id: String,
+) -> Result<InstallResult, String> {
+    let installer = state.0.read().await;
+    let progress_callback = TauriProgressCallback::new(app_handle);
+
+    match installer
+        .update(&index, &plugin_id, &progress_callback)
+        .await
+    {
+        Ok(plugin) => Ok(InstallResult {
+            success: true,
+            plugin: Some(plugin),
+            error: None,
+        }),
+        Err(e) => {
+            progress_callback.on_progress(InstallProgress::failed(e.to_string()));
+            Ok(InstallResult {
+                success: false,
+                plugin: None,
+                error: Some(e.to_string()),
+            })
+        }
+    }
+}
+
+/// 回滚插件到更新前的版本
+#[tauri::command]
+pub async fn rollback_plugin(
+    state: tauri::State<'_, PluginInstallerState>,
+    plugin_id: String,
+) -> Result<InstalledPlugin, String> {
+    let installer = state.0.read().await;
+    installer.rollback(&plugin_id).map_err(|e| e.to_string())
+}
+
+/// 固定插件版本，`version` 为空时取消固定
+#[tauri::command]
+pub async fn pin_plugin_version(
+    state: tauri::State<'_, PluginInstallerState>,
+    plugin_id: String,
+    version: Option<String>,
+) -> Result<(), String> {
+    let installer = state.0.read().await;
+    installer
+        .pin_version(&plugin_id, version.as_deref())
+        .map_err(|e| e.to_string())
+}
+
+/// 列出受信任的发布者公钥
+#[tauri::command]
+pub async fn list_plugin_trusted_keys(
+    state: tauri::State<'_, PluginInstallerState>,
+) -> Result<Vec<TrustedKey>, String> {
+    let installer = state.0.read().await;
+    installer
+        .registry()
+        .list_trusted_keys()
+        .map_err(|e| e.to_string())
+}
+
+/// 添加受信任的发布者公钥（base64 编码的 ed25519 公钥）
+#[tauri::command]
+pub async fn add_plugin_trusted_key(
+    state: tauri::State<'_, PluginInstallerState>,
+    publisher: String,
+    public_key: String,
+) -> Result<TrustedKey, String> {
+    let installer = state.0.read().await;
+    let key = TrustedKey::new(publisher, &public_key).map_err(|e| e.to_string())?;
+    installer
+        .registry()
+        .add_trusted_key(&key)
+        .map_err(|e| e.to_string())?;
+    Ok(key)
+}
+
+/// 移除受信任的发布者公钥
+#[tauri::command]
+pub async fn remove_plugin_trusted_key(
+    state: tauri::State<'_, PluginInstallerState>,
+    key_id: String,
+) -> Result<(), String> {
+    let installer = state.0.read().await;
+    installer
+        .registry()
+        .remove_trusted_key(&key_id)
+        .map_err(|e| e.to_string())
+}
@@ -19,11 +19,13 @@ pub use hot_reload::{
 pub use import::{ImportOptions, ImportService, ValidationResult};
 pub use path_utils::{collapse_tilde, contains_tilde, expand_tilde};
 pub use types::{
-    generate_secure_api_key, AmpConfig, AmpModelMapping, ApiKeyEntry, Config, CredentialEntry,
-    CredentialPoolConfig, CustomProviderConfig, EndpointProvidersConfig, GeminiApiKeyEntry,
-    IFlowCredentialEntry, InjectionRuleConfig, InjectionSettings, LoggingConfig, ProviderConfig,
-    ProvidersConfig, QuotaExceededConfig, RemoteManagementConfig, RetrySettings, RoutingConfig,
-    ServerConfig, TlsConfig, VertexApiKeyEntry, VertexModelAlias, DEFAULT_API_KEY,
+    generate_secure_api_key, AgentApiSettings, AmpConfig, AmpModelMapping, ApiKeyEntry,
+    BashSandboxSettings, Config, ContextCompactionSettings, CredentialEntry, CredentialPoolConfig,
+    CustomProviderConfig, EndpointProvidersConfig, GeminiApiKeyEntry, IFlowCredentialEntry,
+    InjectionRuleConfig, InjectionSettings, LoggingConfig, ProviderConfig, ProvidersConfig,
+    QuotaExceededConfig, RemoteManagementConfig, RetrySettings, RoutingConfig, ServerConfig,
+    TlsConfig, ToolApprovalPolicy, ToolApprovalRule, ToolApprovalSettings, ToolEmulationFormat,
+    ToolEmulationRule, ToolEmulationSettings, VertexApiKeyEntry, VertexModelAlias, DEFAULT_API_KEY,
 };
 pub use yaml::{load_config, save_config, ConfigError, ConfigManager, YamlService};
 
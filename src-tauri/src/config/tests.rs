@@ -219,6 +219,11 @@ fn arb_config() -> impl Strategy<Value = Config> {
             ampcode: crate::config::AmpConfig::default(),
             endpoint_providers: crate::config::EndpointProvidersConfig::default(),
             minimize_to_tray: true,
+            tool_approval: crate::config::ToolApprovalSettings::default(),
+            context_compaction: crate::config::ContextCompactionSettings::default(),
+            bash_sandbox: crate::config::BashSandboxSettings::default(),
+            tool_emulation: crate::config::ToolEmulationSettings::default(),
+            agent_api: crate::config::AgentApiSettings::default(),
         })
 }
 
@@ -492,6 +497,11 @@ fn arb_valid_config() -> impl Strategy<Value = Config> {
             ampcode: crate::config::AmpConfig::default(),
             endpoint_providers: crate::config::EndpointProvidersConfig::default(),
             minimize_to_tray: true,
+            tool_approval: crate::config::ToolApprovalSettings::default(),
+            context_compaction: crate::config::ContextCompactionSettings::default(),
+            bash_sandbox: crate::config::BashSandboxSettings::default(),
+            tool_emulation: crate::config::ToolEmulationSettings::default(),
+            agent_api: crate::config::AgentApiSettings::default(),
         })
 }
 
@@ -537,6 +547,11 @@ fn arb_invalid_config() -> impl Strategy<Value = Config> {
                     ampcode: crate::config::AmpConfig::default(),
                     endpoint_providers: crate::config::EndpointProvidersConfig::default(),
                     minimize_to_tray: true,
+                    tool_approval: crate::config::ToolApprovalSettings::default(),
+                    context_compaction: crate::config::ContextCompactionSettings::default(),
+                    bash_sandbox: crate::config::BashSandboxSettings::default(),
+                    tool_emulation: crate::config::ToolEmulationSettings::default(),
+                    agent_api: crate::config::AgentApiSettings::default(),
                 };
                 // 根据类型使配置无效
                 match invalid_type {
@@ -4,6 +4,7 @@
 //! 保持与旧版 JSON 配置的向后兼容性
 
 use crate::injection::{InjectionMode, InjectionRule};
+use crate::models::provider_pool_model::pattern_matches;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
@@ -311,6 +312,21 @@ pub struct Config {
     /// 关闭时最小化到托盘（而不是退出应用）
     #[serde(default = "default_minimize_to_tray")]
     pub minimize_to_tray: bool,
+    /// 原生 Agent 工具调用审批配置
+    #[serde(default)]
+    pub tool_approval: ToolApprovalSettings,
+    /// 原生 Agent 上下文压缩配置
+    #[serde(default)]
+    pub context_compaction: ContextCompactionSettings,
+    /// 原生 Agent bash 工具隔离配置
+    #[serde(default)]
+    pub bash_sandbox: BashSandboxSettings,
+    /// 工具调用模拟配置（用于不支持原生函数调用的后端）
+    #[serde(default)]
+    pub tool_emulation: ToolEmulationSettings,
+    /// 原生 Agent HTTP API 配置
+    #[serde(default)]
+    pub agent_api: AgentApiSettings,
 }
 
 fn default_minimize_to_tray() -> bool {
@@ -761,6 +777,282 @@ impl From<&InjectionRule> for InjectionRuleConfig {
     }
 }
 
+/// 工具调用审批策略
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ToolApprovalPolicy {
+    /// 直接执行
+    Allow,
+    /// 执行前请求用户确认
+    #[default]
+    Ask,
+    /// 拒绝执行
+    Deny,
+}
+
+/// 原生 Agent 工具调用审批配置
+///
+/// 规则按工具名和调用对象（bash 的 command、文件工具的 path 等）匹配，
+/// 多条规则同时命中时 deny 优先于 ask，ask 优先于 allow。
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ToolApprovalSettings {
+    /// 未命中任何规则时的策略
+    #[serde(default)]
+    pub default_policy: ToolApprovalPolicy,
+    /// 等待用户审批的超时时间（秒），超时视为拒绝
+    #[serde(default = "default_approval_timeout_secs")]
+    pub timeout_secs: u64,
+    /// 审批规则
+    #[serde(default)]
+    pub rules: Vec<ToolApprovalRule>,
+}
+
+fn default_approval_timeout_secs() -> u64 {
+    300
+}
+
+impl Default for ToolApprovalSettings {
+    fn default() -> Self {
+        Self {
+            default_policy: ToolApprovalPolicy::default(),
+            timeout_secs: default_approval_timeout_secs(),
+            rules: Vec::new(),
+        }
+    }
+}
+
+/// 工具调用审批规则
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ToolApprovalRule {
+    /// 工具名称（支持 `*` 通配符，如 `mcp__github__*`）
+    pub tool: String,
+    /// 调用对象匹配模式（支持 `*` 通配符，如 `git status*`），为空时匹配该工具的所有调用
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pattern: Option<String>,
+    /// 命中后的策略
+    pub policy: ToolApprovalPolicy,
+}
+
+/// 原生 Agent 上下文压缩配置
+///
+/// 会话上下文超过 `context_window_tokens * threshold` 时，
+/// 将较早的对话轮次摘要化，最近 `keep_recent_turns` 轮和固定消息保留原文。
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ContextCompactionSettings {
+    /// 是否启用自动压缩
+    #[serde(default = "default_compaction_enabled")]
+    pub enabled: bool,
+    /// 模型上下文窗口大小（tokens）
+    #[serde(default = "default_context_window_tokens")]
+    pub context_window_tokens: u32,
+    /// 触发压缩的上下文占比（0-1）
+    #[serde(default = "default_compaction_threshold")]
+    pub threshold: f32,
+    /// 保留原文的最近对话轮数
+    #[serde(default = "default_keep_recent_turns")]
+    pub keep_recent_turns: usize,
+    /// 单条工具输出保留的最大字符数，超出部分省略中间内容（最近几轮和固定消息除外）
+    #[serde(default = "default_tool_output_max_chars")]
+    pub tool_output_max_chars: usize,
+    /// 生成摘要使用的模型，为空时使用会话模型
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub summary_model: Option<String>,
+    /// 摘要最大输出 token 数
+    #[serde(default = "default_summary_max_tokens")]
+    pub summary_max_tokens: u32,
+}
+
+fn default_compaction_enabled() -> bool {
+    true
+}
+
+fn default_context_window_tokens() -> u32 {
+    128_000
+}
+
+fn default_compaction_threshold() -> f32 {
+    0.75
+}
+
+fn default_keep_recent_turns() -> usize {
+    4
+}
+
+fn default_tool_output_max_chars() -> usize {
+    8_000
+}
+
+fn default_summary_max_tokens() -> u32 {
+    2_048
+}
+
+impl Default for ContextCompactionSettings {
+    fn default() -> Self {
+        Self {
+            enabled: default_compaction_enabled(),
+            context_window_tokens: default_context_window_tokens(),
+            threshold: default_compaction_threshold(),
+            keep_recent_turns: default_keep_recent_turns(),
+            tool_output_max_chars: default_tool_output_max_chars(),
+            summary_model: None,
+            summary_max_tokens: default_summary_max_tokens(),
+        }
+    }
+}
+
+/// 原生 Agent bash 工具隔离配置（仅 Linux）
+///
+/// 启用后命令在独立进程组中运行，受资源限制，只继承白名单中的环境变量；
+/// 系统支持非特权用户命名空间时，还可以禁止网络访问并将工作区以外的文件系统设为只读。
+/// 资源限制值为 0 表示不限制。
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BashSandboxSettings {
+    /// 是否启用隔离
+    #[serde(default)]
+    pub enabled: bool,
+    /// CPU 时间上限（秒）
+    #[serde(default = "default_sandbox_cpu_time_secs")]
+    pub cpu_time_secs: u64,
+    /// 内存上限（MiB，限制堆和私有匿名映射）
+    #[serde(default = "default_sandbox_memory_mb")]
+    pub memory_mb: u64,
+    /// 单个文件大小上限（MiB）
+    #[serde(default = "default_sandbox_file_size_mb")]
+    pub file_size_mb: u64,
+    /// 进程数上限（按用户统计，包含用户已运行的全部进程和线程）
+    #[serde(default)]
+    pub max_processes: u64,
+    /// 额外保留的环境变量（支持 `*` 后缀，如 `CARGO_*`），PATH、HOME 等基础变量始终保留
+    #[serde(default)]
+    pub env_allowlist: Vec<String>,
+    /// 禁止网络访问（需要用户命名空间）
+    #[serde(default = "default_sandbox_isolation")]
+    pub deny_network: bool,
+    /// 工作区以外的文件系统只读（需要用户命名空间）
+    #[serde(default = "default_sandbox_isolation")]
+    pub read_only_outside_workspace: bool,
+    /// 工作区以外仍可写的目录，默认为系统临时目录
+    #[serde(default = "default_sandbox_writable_paths")]
+    pub writable_paths: Vec<String>,
+    /// stdout / stderr 各自保留的最大字节数，超出部分丢弃
+    #[serde(default = "default_sandbox_max_output_bytes")]
+    pub max_output_bytes: usize,
+}
+
+fn default_sandbox_cpu_time_secs() -> u64 {
+    600
+}
+
+fn default_sandbox_memory_mb() -> u64 {
+    8_192
+}
+
+fn default_sandbox_file_size_mb() -> u64 {
+    1_024
+}
+
+fn default_sandbox_isolation() -> bool {
+    true
+}
+
+fn default_sandbox_writable_paths() -> Vec<String> {
+    vec![std::env::temp_dir().to_string_lossy().to_string()]
+}
+
+fn default_sandbox_max_output_bytes() -> usize {
+    256 * 1024
+}
+
+impl Default for BashSandboxSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            cpu_time_secs: default_sandbox_cpu_time_secs(),
+            memory_mb: default_sandbox_memory_mb(),
+            file_size_mb: default_sandbox_file_size_mb(),
+            max_processes: 0,
+            env_allowlist: Vec::new(),
+            deny_network: true,
+            read_only_outside_workspace: true,
+            writable_paths: default_sandbox_writable_paths(),
+            max_output_bytes: default_sandbox_max_output_bytes(),
+        }
+    }
+}
+
+/// 工具调用模拟使用的调用格式
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ToolEmulationFormat {
+    /// `<function_calls><invoke name="...">` 格式
+    #[default]
+    Xml,
+    /// `<tool_call>{"name": ..., "arguments": ...}</tool_call>` 格式
+    Json,
+}
+
+/// 原生 Agent HTTP API 配置
+///
+/// `/v1/agent/*` 在服务端执行工具（包括 bash），默认关闭。
+/// 启用后，未命中审批规则的修改类工具调用一律需要审批，不使用 `default_policy: allow`。
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub struct AgentApiSettings {
+    /// 是否启用 `/v1/agent/*` 端点
+    #[serde(default)]
+    pub enabled: bool,
+}
+
+/// 工具调用模拟配置
+///
+/// 命中规则的路由不再向上游发送原生工具定义：工具定义写入系统提示，
+/// 历史中的工具调用和工具结果改写为文本，模型输出中的工具调用再解析回
+/// OpenAI `tool_calls` / Anthropic `tool_use`。
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub struct ToolEmulationSettings {
+    /// 提示模型使用的调用格式（两种格式的输出都会被解析）
+    #[serde(default)]
+    pub format: ToolEmulationFormat,
+    /// 需要模拟工具调用的路由
+    #[serde(default)]
+    pub rules: Vec<ToolEmulationRule>,
+}
+
+impl ToolEmulationSettings {
+    /// 判断路由是否需要模拟工具调用
+    pub fn matches(&self, provider: &str, model: &str) -> bool {
+        self.rules.iter().any(|rule| rule.matches(provider, model))
+    }
+}
+
+/// 工具调用模拟规则，`provider` 和 `model` 都为空的规则不匹配任何路由
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ToolEmulationRule {
+    /// Provider 类型（如 `kiro`、`openai`），为空时匹配所有 Provider
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider: Option<String>,
+    /// 模型名匹配模式（支持 `*` 通配符，如 `deepseek-*`），为空时匹配所有模型
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+}
+
+impl ToolEmulationRule {
+    /// 判断规则是否命中
+    pub fn matches(&self, provider: &str, model: &str) -> bool {
+        if self.provider.is_none() && self.model.is_none() {
+            return false;
+        }
+        let provider_ok = self
+            .provider
+            .as_deref()
+            .map_or(true, |p| p.eq_ignore_ascii_case(provider));
+        let model_ok = self
+            .model
+            .as_deref()
+            .map_or(true, |pattern| pattern_matches(pattern, model));
+        provider_ok && model_ok
+    }
+}
+
 impl Default for Config {
     fn default() -> Self {
         Self {
@@ -779,6 +1071,11 @@ impl Default for Config {
             ampcode: AmpConfig::default(),
             endpoint_providers: EndpointProvidersConfig::default(),
             minimize_to_tray: default_minimize_to_tray(),
+            tool_approval: ToolApprovalSettings::default(),
+            context_compaction: ContextCompactionSettings::default(),
+            bash_sandbox: BashSandboxSettings::default(),
+            tool_emulation: ToolEmulationSettings::default(),
+            agent_api: AgentApiSettings::default(),
         }
     }
 }
@@ -1,11 +1,8 @@
-pub mod anthropic_to_openai;
 pub mod cw_to_openai;
 pub mod openai_to_antigravity;
 pub mod openai_to_cw;
 pub mod protocol_selector;
 
-#[allow(unused_imports)]
-pub use anthropic_to_openai::*;
 #[allow(unused_imports)]
 pub use cw_to_openai::*;
 #[allow(unused_imports)]
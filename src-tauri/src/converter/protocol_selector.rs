@@ -64,6 +64,7 @@ impl ProtocolSelector {
             PoolProviderType::Codex => Protocol::OpenAI,        // Codex uses OpenAI protocol
             PoolProviderType::ClaudeOAuth => Protocol::Anthropic, // Claude OAuth uses Anthropic protocol
             PoolProviderType::IFlow => Protocol::OpenAI,          // iFlow uses OpenAI protocol
+            PoolProviderType::Plugin => Protocol::OpenAI, // 插件 Provider 适配 OpenAI 规范协议
         }
     }
 
@@ -132,32 +133,44 @@ impl ProtocolSelector {
     }
 
     /// 检查是否支持直接转换
+    ///
+    /// 规范模型（`translator::canonical`）能解码源协议并编码目标协议时直接转换；
+    /// Kiro（CodeWhisperer）和 Antigravity 后端不在规范模型范围内，只列出专用转换器覆盖的协议对。
     pub fn supports_direct_conversion(source: Protocol, target: Protocol) -> bool {
+        let canonical_source = source != Protocol::CodeWhisperer;
+        let canonical_target = matches!(
+            target,
+            Protocol::OpenAI | Protocol::Anthropic | Protocol::Gemini
+        );
+        if canonical_source && canonical_target {
+            return true;
+        }
         matches!(
             (source, target),
-            (Protocol::OpenAI, Protocol::Anthropic)
-                | (Protocol::Anthropic, Protocol::OpenAI)
-                | (Protocol::OpenAI, Protocol::CodeWhisperer)
+            // translator::kiro 的请求转换
+            (Protocol::OpenAI, Protocol::CodeWhisperer)
+                | (Protocol::Anthropic, Protocol::CodeWhisperer)
+                // translator::kiro / converter::cw_to_openai 的响应转换
                 | (Protocol::CodeWhisperer, Protocol::OpenAI)
-                | (Protocol::OpenAI, Protocol::Gemini)
+                | (Protocol::CodeWhisperer, Protocol::Anthropic)
+                // converter::openai_to_antigravity 和 Gemini 原生入口
                 | (Protocol::OpenAI, Protocol::Antigravity)
-                | (Protocol::Gemini, Protocol::OpenAI)
-                | (Protocol::Antigravity, Protocol::OpenAI)
                 | (Protocol::Gemini, Protocol::Antigravity)
-                | (Protocol::Antigravity, Protocol::Gemini)
         )
     }
 
     /// 获取推荐的中间协议（用于不支持直接转换的情况）
+    ///
+    /// Antigravity 后端只接受 OpenAI 或 Gemini 请求，其余 Kiro 相关的转换经 Anthropic 中转；
+    /// 两段转换都在 `supports_direct_conversion` 范围内。
     pub fn intermediate_protocol(source: Protocol, target: Protocol) -> Option<Protocol> {
-        // 大多数情况下，OpenAI 是最好的中间协议
-        if !Self::supports_direct_conversion(source, target)
-            && source != Protocol::OpenAI
-            && target != Protocol::OpenAI
-        {
-            return Some(Protocol::OpenAI);
+        if source == target || Self::supports_direct_conversion(source, target) {
+            return None;
+        }
+        match target {
+            Protocol::Antigravity => Some(Protocol::OpenAI),
+            _ => Some(Protocol::Anthropic),
         }
-        None
     }
 
     /// 获取 Provider 支持的输入协议列表
@@ -225,4 +238,66 @@ mod tests {
         assert!(path.needs_conversion);
         assert_eq!(path.target, Protocol::CodeWhisperer);
     }
+
+    #[test]
+    fn test_intermediate_protocol() {
+        assert!(ProtocolSelector::supports_direct_conversion(
+            Protocol::Gemini,
+            Protocol::Anthropic
+        ));
+        assert!(ProtocolSelector::supports_direct_conversion(
+            Protocol::Anthropic,
+            Protocol::CodeWhisperer
+        ));
+        assert!(!ProtocolSelector::supports_direct_conversion(
+            Protocol::Anthropic,
+            Protocol::Antigravity
+        ));
+        assert_eq!(
+            ProtocolSelector::intermediate_protocol(Protocol::Anthropic, Protocol::Antigravity),
+            Some(Protocol::OpenAI)
+        );
+        assert_eq!(
+            ProtocolSelector::intermediate_protocol(Protocol::Gemini, Protocol::CodeWhisperer),
+            Some(Protocol::Anthropic)
+        );
+        assert_eq!(
+            ProtocolSelector::intermediate_protocol(Protocol::OpenAI, Protocol::Antigravity),
+            None
+        );
+    }
+
+    #[test]
+    fn test_every_pair_has_a_route() {
+        let protocols = [
+            Protocol::OpenAI,
+            Protocol::Anthropic,
+            Protocol::CodeWhisperer,
+            Protocol::Gemini,
+            Protocol::Antigravity,
+        ];
+        for source in protocols {
+            for target in protocols {
+                if source == target {
+                    continue;
+                }
+                match ProtocolSelector::intermediate_protocol(source, target) {
+                    None => assert!(
+                        ProtocolSelector::supports_direct_conversion(source, target),
+                        "{:?} -> {:?}",
+                        source,
+                        target
+                    ),
+                    Some(hub) => assert!(
+                        ProtocolSelector::supports_direct_conversion(source, hub)
+                            && ProtocolSelector::supports_direct_conversion(hub, target),
+                        "{:?} -> {:?} -> {:?}",
+                        source,
+                        hub,
+                        target
+                    ),
+                }
+            }
+        }
+    }
 }
@@ -237,6 +237,12 @@ impl CredentialSyncService {
                     "iFlow Cookie 凭证暂不支持同步到配置".to_string(),
                 ));
             }
+            CredentialData::Plugin { .. } => {
+                // 插件凭证只保存在凭证池中
+                return Err(SyncError::InvalidCredentialType(
+                    "插件凭证暂不支持同步到配置".to_string(),
+                ));
+            }
         }
 
         self.update_config(config)
@@ -396,6 +402,12 @@ impl CredentialSyncService {
                     "iFlow 凭证暂不支持同步到配置".to_string(),
                 ));
             }
+            PoolProviderType::Plugin => {
+                // 插件凭证只保存在凭证池中
+                return Err(SyncError::InvalidCredentialType(
+                    "插件凭证暂不支持同步到配置".to_string(),
+                ));
+            }
         }
 
         if !found {
@@ -571,6 +583,12 @@ impl CredentialSyncService {
                     "iFlow Cookie 凭证暂不支持同步到配置".to_string(),
                 ));
             }
+            CredentialData::Plugin { .. } => {
+                // 插件凭证只保存在凭证池中
+                return Err(SyncError::InvalidCredentialType(
+                    "插件凭证暂不支持同步到配置".to_string(),
+                ));
+            }
         }
 
         if !found {
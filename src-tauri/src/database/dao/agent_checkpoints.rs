@@ -0,0 +1,151 @@
+//! 原生 Agent 工作区检查点数据访问对象
+//!
+//! 只保存检查点元数据，文件内容和快照清单保存在对象存储中（见 `agent::checkpoint`）。
+
+use rusqlite::{params, Connection, OptionalExtension, Row};
+
+use crate::agent::checkpoint::Checkpoint;
+
+pub struct AgentCheckpointDao;
+
+impl AgentCheckpointDao {
+    pub fn insert(conn: &Connection, checkpoint: &Checkpoint) -> Result<(), rusqlite::Error> {
+        let scopes = serde_json::to_string(&checkpoint.scopes)
+            .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+        conn.execute(
+            "INSERT INTO agent_checkpoints
+             (id, session_id, message_index, label, file_count, scopes, untracked, manifest, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            params![
+                checkpoint.id,
+                checkpoint.session_id,
+                checkpoint.message_index as i64,
+                checkpoint.label,
+                checkpoint.file_count as i64,
+                scopes,
+                checkpoint.untracked as i32,
+                checkpoint.manifest,
+                checkpoint.created_at,
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// 按创建顺序列出会话的检查点
+    pub fn list(conn: &Connection, session_id: &str) -> Result<Vec<Checkpoint>, rusqlite::Error> {
+        let mut stmt = conn.prepare(
+            "SELECT id, session_id, message_index, label, file_count, scopes, untracked, manifest, created_at
+             FROM agent_checkpoints
+             WHERE session_id = ?1
+             ORDER BY message_index, created_at",
+        )?;
+        let rows = stmt.query_map(params![session_id], map_checkpoint)?;
+        rows.collect()
+    }
+
+    pub fn get(conn: &Connection, id: &str) -> Result<Option<Checkpoint>, rusqlite::Error> {
+        conn.query_row(
+            "SELECT id, session_id, message_index, label, file_count, scopes, untracked, manifest, created_at
+             FROM agent_checkpoints WHERE id = ?1",
+            params![id],
+            map_checkpoint,
+        )
+        .optional()
+    }
+
+    /// 更新检查点的快照清单对象
+    pub fn update_manifest(
+        conn: &Connection,
+        id: &str,
+        manifest: &str,
+    ) -> Result<usize, rusqlite::Error> {
+        conn.execute(
+            "UPDATE agent_checkpoints SET manifest = ?1 WHERE id = ?2",
+            params![manifest, id],
+        )
+    }
+
+    /// 删除消息序号大于 `message_index` 的检查点，返回删除数量
+    pub fn delete_after(
+        conn: &Connection,
+        session_id: &str,
+        message_index: usize,
+    ) -> Result<usize, rusqlite::Error> {
+        conn.execute(
+            "DELETE FROM agent_checkpoints WHERE session_id = ?1 AND message_index > ?2",
+            params![session_id, message_index as i64],
+        )
+    }
+
+    /// 删除会话的全部检查点
+    pub fn delete_session(conn: &Connection, session_id: &str) -> Result<usize, rusqlite::Error> {
+        conn.execute(
+            "DELETE FROM agent_checkpoints WHERE session_id = ?1",
+            params![session_id],
+        )
+    }
+}
+
+fn map_checkpoint(row: &Row) -> Result<Checkpoint, rusqlite::Error> {
+    let scopes: String = row.get(5)?;
+    Ok(Checkpoint {
+        id: row.get(0)?,
+        session_id: row.get(1)?,
+        message_index: row.get::<_, i64>(2)? as usize,
+        label: row.get(3)?,
+        file_count: row.get::<_, i64>(4)? as usize,
+        scopes: serde_json::from_str(&scopes).map_err(|e| {
+            rusqlite::Error::FromSqlConversionFailure(5, rusqlite::types::Type::Text, e.into())
+        })?,
+        untracked: row.get::<_, i32>(6)? != 0,
+        manifest: row.get(7)?,
+        created_at: row.get(8)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::schema::create_agent_session_tables;
+
+    fn checkpoint(id: &str, message_index: usize) -> Checkpoint {
+        Checkpoint {
+            id: id.to_string(),
+            session_id: "s1".to_string(),
+            message_index,
+            label: "bash cargo fmt".to_string(),
+            file_count: 2,
+            scopes: vec!["/work/project".to_string()],
+            untracked: false,
+            manifest: "0".repeat(64),
+            created_at: format!("2025-01-01T00:00:0{}+00:00", message_index),
+        }
+    }
+
+    #[test]
+    fn test_insert_list_and_delete_after() {
+        let conn = Connection::open_in_memory().unwrap();
+        create_agent_session_tables(&conn).unwrap();
+        conn.execute(
+            "INSERT INTO agent_sessions (id, model, created_at, updated_at)
+             VALUES ('s1', 'gpt-4o', '2025-01-01T00:00:00+00:00', '2025-01-01T00:00:00+00:00')",
+            [],
+        )
+        .unwrap();
+
+        AgentCheckpointDao::insert(&conn, &checkpoint("c2", 6)).unwrap();
+        AgentCheckpointDao::insert(&conn, &checkpoint("c1", 2)).unwrap();
+
+        let list = AgentCheckpointDao::list(&conn, "s1").unwrap();
+        assert_eq!(
+            list.iter().map(|c| c.id.as_str()).collect::<Vec<_>>(),
+            vec!["c1", "c2"]
+        );
+        assert_eq!(list[0], checkpoint("c1", 2));
+        assert!(AgentCheckpointDao::get(&conn, "c2").unwrap().is_some());
+
+        assert_eq!(AgentCheckpointDao::delete_after(&conn, "s1", 2).unwrap(), 1);
+        assert_eq!(AgentCheckpointDao::delete_session(&conn, "s1").unwrap(), 1);
+        assert!(AgentCheckpointDao::list(&conn, "s1").unwrap().is_empty());
+    }
+}
@@ -0,0 +1,806 @@
+//! 原生 Agent 会话数据访问对象
+//!
+//! 持久化 Agent 会话及其消息（包括工具调用和工具结果消息），
+//! 支持跨会话搜索、从任意消息处分叉、重命名和归档。
+
+use rusqlite::{params, Connection, OptionalExtension};
+
+use crate::agent::{
+    AgentMessage, AgentSession, AgentSessionSearchHit, AgentSessionSummary, ContextCompaction,
+    MessageContent, ToolCall,
+};
+
+/// 搜索结果片段的最大字符数
+const SNIPPET_CHARS: usize = 120;
+
+pub struct AgentSessionDao;
+
+impl AgentSessionDao {
+    /// 保存会话（覆盖已有的会话信息、全部消息和压缩记录）
+    pub fn save(conn: &Connection, session: &AgentSession) -> Result<(), rusqlite::Error> {
+        let tx = conn.unchecked_transaction()?;
+        tx.execute(
+            "INSERT OR REPLACE INTO agent_sessions
+             (id, title, model, system_prompt, archived, parent_id, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                session.id,
+                session.title,
+                session.model,
+                session.system_prompt,
+                session.archived as i32,
+                session.parent_id,
+                session.created_at,
+                session.updated_at,
+            ],
+        )?;
+        tx.execute(
+            "DELETE FROM agent_messages WHERE session_id = ?1",
+            params![session.id],
+        )?;
+        for (seq, message) in session.messages.iter().enumerate() {
+            insert_message(&tx, &session.id, seq, message)?;
+        }
+        tx.execute(
+            "DELETE FROM agent_session_compactions WHERE session_id = ?1",
+            params![session.id],
+        )?;
+        for compaction in &session.compactions {
+            Self::add_compaction(&tx, &session.id, compaction)?;
+        }
+        tx.commit()
+    }
+
+    /// 追加消息，seq 为消息在会话中的序号
+    pub fn append_message(
+        conn: &Connection,
+        session_id: &str,
+        seq: usize,
+        message: &AgentMessage,
+    ) -> Result<(), rusqlite::Error> {
+        insert_message(conn, session_id, seq, message)?;
+        conn.execute(
+            "UPDATE agent_sessions SET updated_at = ?1 WHERE id = ?2",
+            params![message.timestamp, session_id],
+        )?;
+        Ok(())
+    }
+
+    /// 获取会话（包含全部消息）
+    pub fn get(conn: &Connection, session_id: &str) -> Result<Option<AgentSession>, String> {
+        let session = conn
+            .query_row(
+                "SELECT id, title, model, system_prompt, archived, parent_id, created_at, updated_at
+                 FROM agent_sessions WHERE id = ?1",
+                params![session_id],
+                |row| {
+                    Ok(AgentSession {
+                        id: row.get(0)?,
+                        title: row.get(1)?,
+                        model: row.get(2)?,
+                        system_prompt: row.get(3)?,
+                        archived: row.get::<_, i32>(4)? != 0,
+                        parent_id: row.get(5)?,
+                        created_at: row.get(6)?,
+                        updated_at: row.get(7)?,
+                        messages: Vec::new(),
+                        compactions: Vec::new(),
+                    })
+                },
+            )
+            .optional()
+            .map_err(|e| format!("数据库错误: {}", e))?;
+
+        let Some(mut session) = session else {
+            return Ok(None);
+        };
+        session.messages = Self::get_messages(conn, session_id)?;
+        session.compactions =
+            Self::get_compactions(conn, session_id).map_err(|e| format!("数据库错误: {}", e))?;
+        Ok(Some(session))
+    }
+
+    /// 获取会话消息
+    pub fn get_messages(conn: &Connection, session_id: &str) -> Result<Vec<AgentMessage>, String> {
+        let mut stmt = conn
+            .prepare(
+                "SELECT role, content, tool_calls, tool_call_id, timestamp, pinned
+                 FROM agent_messages WHERE session_id = ?1 ORDER BY seq",
+            )
+            .map_err(|e| format!("数据库错误: {}", e))?;
+
+        let rows = stmt
+            .query_map(params![session_id], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, Option<String>>(2)?,
+                    row.get::<_, Option<String>>(3)?,
+                    row.get::<_, String>(4)?,
+                    row.get::<_, i32>(5)? != 0,
+                ))
+            })
+            .map_err(|e| format!("数据库错误: {}", e))?;
+
+        let mut messages = Vec::new();
+        for row in rows {
+            let (role, content, tool_calls, tool_call_id, timestamp, pinned) =
+                row.map_err(|e| format!("数据库错误: {}", e))?;
+            let content: MessageContent =
+                serde_json::from_str(&content).map_err(|e| format!("JSON 解析错误: {}", e))?;
+            let tool_calls: Option<Vec<ToolCall>> = tool_calls
+                .map(|calls| serde_json::from_str(&calls))
+                .transpose()
+                .map_err(|e| format!("JSON 解析错误: {}", e))?;
+            messages.push(AgentMessage {
+                role,
+                content,
+                timestamp,
+                tool_calls,
+                tool_call_id,
+                pinned,
+            });
+        }
+        Ok(messages)
+    }
+
+    /// 设置消息固定状态，消息不存在时返回 false
+    pub fn set_pinned(
+        conn: &Connection,
+        session_id: &str,
+        seq: usize,
+        pinned: bool,
+    ) -> Result<bool, rusqlite::Error> {
+        let rows_affected = conn.execute(
+            "UPDATE agent_messages SET pinned = ?1 WHERE session_id = ?2 AND seq = ?3",
+            params![pinned as i32, session_id, seq as i64],
+        )?;
+        Ok(rows_affected > 0)
+    }
+
+    /// 追加上下文压缩记录
+    pub fn add_compaction(
+        conn: &Connection,
+        session_id: &str,
+        compaction: &ContextCompaction,
+    ) -> Result<(), rusqlite::Error> {
+        conn.execute(
+            "INSERT OR REPLACE INTO agent_session_compactions
+             (id, session_id, compacted_from, compacted_until, summary, model,
+              tokens_before, tokens_after, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            params![
+                compaction.id,
+                session_id,
+                compaction.compacted_from as i64,
+                compaction.compacted_until as i64,
+                compaction.summary,
+                compaction.model,
+                compaction.tokens_before,
+                compaction.tokens_after,
+                compaction.created_at,
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// 获取会话的上下文压缩记录，按压缩范围排序
+    pub fn get_compactions(
+        conn: &Connection,
+        session_id: &str,
+    ) -> Result<Vec<ContextCompaction>, rusqlite::Error> {
+        let mut stmt = conn.prepare(
+            "SELECT id, compacted_from, compacted_until, summary, model,
+                    tokens_before, tokens_after, created_at
+             FROM agent_session_compactions
+             WHERE session_id = ?1
+             ORDER BY compacted_until, created_at",
+        )?;
+        let rows = stmt.query_map(params![session_id], |row| {
+            Ok(ContextCompaction {
+                id: row.get(0)?,
+                compacted_from: row.get::<_, i64>(1)? as usize,
+                compacted_until: row.get::<_, i64>(2)? as usize,
+                summary: row.get(3)?,
+                model: row.get(4)?,
+                tokens_before: row.get(5)?,
+                tokens_after: row.get(6)?,
+                created_at: row.get(7)?,
+            })
+        })?;
+        rows.collect()
+    }
+
+    /// 列出会话摘要，按最后活动时间倒序
+    pub fn list(
+        conn: &Connection,
+        include_archived: bool,
+    ) -> Result<Vec<AgentSessionSummary>, rusqlite::Error> {
+        let mut stmt = conn.prepare(
+            "SELECT s.id, s.title, s.model, s.archived, s.parent_id, s.created_at, s.updated_at,
+                    (SELECT COUNT(*) FROM agent_messages m WHERE m.session_id = s.id)
+             FROM agent_sessions s
+             WHERE ?1 OR s.archived = 0
+             ORDER BY s.updated_at DESC",
+        )?;
+
+        let sessions = stmt.query_map(params![include_archived], |row| {
+            Ok(AgentSessionSummary {
+                id: row.get(0)?,
+                title: row.get(1)?,
+                model: row.get(2)?,
+                archived: row.get::<_, i32>(3)? != 0,
+                parent_id: row.get(4)?,
+                created_at: row.get(5)?,
+                updated_at: row.get(6)?,
+                message_count: row.get::<_, i64>(7)? as usize,
+            })
+        })?;
+
+        sessions.collect()
+    }
+
+    /// 跨会话搜索标题、消息内容和工具调用参数
+    ///
+    /// 标题命中排在前面，消息命中按会话最后活动时间倒序。
+    pub fn search(
+        conn: &Connection,
+        query: &str,
+        include_archived: bool,
+        limit: usize,
+    ) -> Result<Vec<AgentSessionSearchHit>, rusqlite::Error> {
+        let query = query.trim();
+        if query.is_empty() || limit == 0 {
+            return Ok(Vec::new());
+        }
+        let pattern = format!("%{}%", escape_like(query));
+
+        let mut stmt = conn.prepare(
+            "SELECT id, title, updated_at FROM agent_sessions
+             WHERE title LIKE ?1 ESCAPE '\\' AND (?2 OR archived = 0)
+             ORDER BY updated_at DESC LIMIT ?3",
+        )?;
+        let mut hits = stmt
+            .query_map(params![pattern, include_archived, limit as i64], |row| {
+                let title: Option<String> = row.get(1)?;
+                Ok(AgentSessionSearchHit {
+                    session_id: row.get(0)?,
+                    snippet: title.clone().unwrap_or_default(),
+                    title,
+                    message_index: None,
+                    role: None,
+                    updated_at: row.get(2)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut stmt = conn.prepare(
+            "SELECT m.session_id, s.title, m.seq, m.role, m.content_text, m.tool_calls, s.updated_at
+             FROM agent_messages m JOIN agent_sessions s ON s.id = m.session_id
+             WHERE (m.content_text LIKE ?1 ESCAPE '\\' OR m.tool_calls LIKE ?1 ESCAPE '\\')
+               AND (?2 OR s.archived = 0)
+             ORDER BY s.updated_at DESC, m.seq LIMIT ?3",
+        )?;
+        let message_hits =
+            stmt.query_map(params![pattern, include_archived, limit as i64], |row| {
+                let content_text: String = row.get(4)?;
+                let tool_calls: Option<String> = row.get(5)?;
+                let text = match tool_calls {
+                    Some(calls) if find_ignore_case(&content_text, query).is_none() => calls,
+                    _ => content_text,
+                };
+                Ok(AgentSessionSearchHit {
+                    session_id: row.get(0)?,
+                    title: row.get(1)?,
+                    message_index: Some(row.get::<_, i64>(2)? as usize),
+                    role: Some(row.get(3)?),
+                    snippet: snippet(&text, query),
+                    updated_at: row.get(6)?,
+                })
+            })?;
+        for hit in message_hits {
+            hits.push(hit?);
+        }
+
+        hits.truncate(limit);
+        Ok(hits)
+    }
+
+    /// 从指定消息处分叉会话
+    ///
+    /// 新会话包含源会话第 0 条到第 `message_index` 条（含）消息，以及完全落在该范围内的压缩记录，
+    /// 源会话不存在时返回 None。
+    pub fn fork(
+        conn: &Connection,
+        session_id: &str,
+        message_index: usize,
+        new_id: &str,
+        now: &str,
+    ) -> Result<Option<AgentSession>, String> {
+        let Some(mut session) = Self::get(conn, session_id)? else {
+            return Ok(None);
+        };
+        if message_index >= session.messages.len() {
+            return Err(format!(
+                "消息序号越界: {} (会话共 {} 条消息)",
+                message_index,
+                session.messages.len()
+            ));
+        }
+
+        session.messages.truncate(message_index + 1);
+        session
+            .compactions
+            .retain(|c| c.compacted_until <= message_index + 1);
+        for compaction in &mut session.compactions {
+            compaction.id = uuid::Uuid::new_v4().to_string();
+        }
+        session.parent_id = Some(session.id.clone());
+        session.id = new_id.to_string();
+        session.archived = false;
+        session.created_at = now.to_string();
+        session.updated_at = now.to_string();
+
+        Self::save(conn, &session).map_err(|e| format!("数据库错误: {}", e))?;
+        Ok(Some(session))
+    }
+
+    /// 重命名会话
+    pub fn rename(
+        conn: &Connection,
+        session_id: &str,
+        title: Option<&str>,
+    ) -> Result<bool, rusqlite::Error> {
+        let rows_affected = conn.execute(
+            "UPDATE agent_sessions SET title = ?1 WHERE id = ?2",
+            params![title, session_id],
+        )?;
+        Ok(rows_affected > 0)
+    }
+
+    /// 设置会话归档状态
+    pub fn set_archived(
+        conn: &Connection,
+        session_id: &str,
+        archived: bool,
+    ) -> Result<bool, rusqlite::Error> {
+        let rows_affected = conn.execute(
+            "UPDATE agent_sessions SET archived = ?1 WHERE id = ?2",
+            params![archived as i32, session_id],
+        )?;
+        Ok(rows_affected > 0)
+    }
+
+    /// 清空会话消息和压缩记录
+    pub fn clear_messages(
+        conn: &Connection,
+        session_id: &str,
+        now: &str,
+    ) -> Result<bool, rusqlite::Error> {
+        conn.execute(
+            "DELETE FROM agent_messages WHERE session_id = ?1",
+            params![session_id],
+        )?;
+        conn.execute(
+            "DELETE FROM agent_session_compactions WHERE session_id = ?1",
+            params![session_id],
+        )?;
+        let rows_affected = conn.execute(
+            "UPDATE agent_sessions SET updated_at = ?1 WHERE id = ?2",
+            params![now, session_id],
+        )?;
+        Ok(rows_affected > 0)
+    }
+
+    /// 截断会话，只保留前 `len` 条消息，同时删除覆盖被截断消息的压缩记录
+    pub fn truncate_messages(
+        conn: &Connection,
+        session_id: &str,
+        len: usize,
+        now: &str,
+    ) -> Result<usize, rusqlite::Error> {
+        let removed = conn.execute(
+            "DELETE FROM agent_messages WHERE session_id = ?1 AND seq >= ?2",
+            params![session_id, len as i64],
+        )?;
+        conn.execute(
+            "DELETE FROM agent_session_compactions WHERE session_id = ?1 AND compacted_until > ?2",
+            params![session_id, len as i64],
+        )?;
+        conn.execute(
+            "UPDATE agent_sessions SET updated_at = ?1 WHERE id = ?2",
+            params![now, session_id],
+        )?;
+        Ok(removed)
+    }
+
+    /// 记录通过 API Server 创建的会话
+    pub fn mark_api_session(
+        conn: &Connection,
+        session_id: &str,
+        created_at: &str,
+    ) -> Result<(), rusqlite::Error> {
+        conn.execute(
+            "INSERT OR IGNORE INTO agent_api_sessions (session_id, created_at) VALUES (?1, ?2)",
+            params![session_id, created_at],
+        )?;
+        Ok(())
+    }
+
+    /// 会话是否通过 API Server 创建
+    pub fn is_api_session(conn: &Connection, session_id: &str) -> Result<bool, rusqlite::Error> {
+        conn.query_row(
+            "SELECT 1 FROM agent_api_sessions WHERE session_id = ?1",
+            params![session_id],
+            |_| Ok(()),
+        )
+        .optional()
+        .map(|row| row.is_some())
+    }
+
+    /// 删除会话及其消息
+    pub fn delete(conn: &Connection, session_id: &str) -> Result<bool, rusqlite::Error> {
+        conn.execute(
+            "DELETE FROM agent_messages WHERE session_id = ?1",
+            params![session_id],
+        )?;
+        conn.execute(
+            "DELETE FROM agent_api_sessions WHERE session_id = ?1",
+            params![session_id],
+        )?;
+        conn.execute(
+            "DELETE FROM agent_session_compactions WHERE session_id = ?1",
+            params![session_id],
+        )?;
+        let rows_affected = conn.execute(
+            "DELETE FROM agent_sessions WHERE id = ?1",
+            params![session_id],
+        )?;
+        Ok(rows_affected > 0)
+    }
+}
+
+fn insert_message(
+    conn: &Connection,
+    session_id: &str,
+    seq: usize,
+    message: &AgentMessage,
+) -> Result<(), rusqlite::Error> {
+    let content = serde_json::to_string(&message.content)
+        .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+    let tool_calls = message
+        .tool_calls
+        .as_ref()
+        .map(serde_json::to_string)
+        .transpose()
+        .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+
+    conn.execute(
+        "INSERT OR REPLACE INTO agent_messages
+         (session_id, seq, role, content, content_text, tool_calls, tool_call_id, timestamp, pinned)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+        params![
+            session_id,
+            seq as i64,
+            message.role,
+            content,
+            message.content.as_text(),
+            tool_calls,
+            message.tool_call_id,
+            message.timestamp,
+            message.pinned as i32,
+        ],
+    )?;
+    Ok(())
+}
+
+/// 转义 LIKE 通配符
+fn escape_like(query: &str) -> String {
+    let mut escaped = String::with_capacity(query.len());
+    for c in query.chars() {
+        if matches!(c, '\\' | '%' | '_') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// 忽略大小写查找，返回字符位置
+fn find_ignore_case(text: &str, query: &str) -> Option<usize> {
+    let lower = |s: &str| -> Vec<char> {
+        s.chars()
+            .map(|c| c.to_lowercase().next().unwrap_or(c))
+            .collect()
+    };
+    let text = lower(text);
+    let query = lower(query);
+    if query.is_empty() || query.len() > text.len() {
+        return None;
+    }
+    text.windows(query.len())
+        .position(|w| w == query.as_slice())
+}
+
+/// 截取命中位置附近的内容片段
+fn snippet(text: &str, query: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let start = find_ignore_case(text, query)
+        .map(|pos| pos.saturating_sub(SNIPPET_CHARS / 3))
+        .unwrap_or(0);
+    let end = (start + SNIPPET_CHARS).min(chars.len());
+
+    let mut result = String::new();
+    if start > 0 {
+        result.push('…');
+    }
+    result.extend(&chars[start..end]);
+    if end < chars.len() {
+        result.push('…');
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::FunctionCall;
+    use crate::database::schema::create_agent_session_tables;
+
+    fn create_test_connection() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        create_agent_session_tables(&conn).unwrap();
+        conn
+    }
+
+    fn message(role: &str, text: &str) -> AgentMessage {
+        AgentMessage {
+            role: role.to_string(),
+            content: MessageContent::Text(text.to_string()),
+            timestamp: "2025-01-01T00:00:00+00:00".to_string(),
+            tool_calls: None,
+            tool_call_id: None,
+            pinned: false,
+        }
+    }
+
+    fn create_test_session(id: &str) -> AgentSession {
+        let mut assistant = message("assistant", "");
+        assistant.tool_calls = Some(vec![ToolCall {
+            id: "call_1".to_string(),
+            call_type: "function".to_string(),
+            function: FunctionCall {
+                name: "bash".to_string(),
+                arguments: r#"{"command":"cargo test"}"#.to_string(),
+            },
+        }]);
+        let mut tool_result = message("tool", "test result: ok");
+        tool_result.tool_call_id = Some("call_1".to_string());
+
+        AgentSession {
+            id: id.to_string(),
+            model: "claude-sonnet-4-20250514".to_string(),
+            messages: vec![
+                message("user", "运行测试"),
+                assistant,
+                tool_result,
+                message("assistant", "所有测试通过"),
+            ],
+            system_prompt: Some("你是一个有帮助的助手".to_string()),
+            created_at: "2025-01-01T00:00:00+00:00".to_string(),
+            updated_at: "2025-01-01T00:00:00+00:00".to_string(),
+            title: None,
+            archived: false,
+            parent_id: None,
+            compactions: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_save_and_get_roundtrip() {
+        let conn = create_test_connection();
+        AgentSessionDao::save(&conn, &create_test_session("s1")).unwrap();
+
+        let session = AgentSessionDao::get(&conn, "s1").unwrap().unwrap();
+        assert_eq!(session.messages.len(), 4);
+        assert_eq!(
+            session.system_prompt.as_deref(),
+            Some("你是一个有帮助的助手")
+        );
+        let calls = session.messages[1].tool_calls.as_ref().unwrap();
+        assert_eq!(calls[0].function.name, "bash");
+        assert_eq!(session.messages[2].tool_call_id.as_deref(), Some("call_1"));
+
+        assert!(AgentSessionDao::get(&conn, "missing").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_append_message_updates_session() {
+        let conn = create_test_connection();
+        AgentSessionDao::save(&conn, &create_test_session("s1")).unwrap();
+
+        let mut reply = message("user", "再运行一次");
+        reply.timestamp = "2025-01-02T00:00:00+00:00".to_string();
+        AgentSessionDao::append_message(&conn, "s1", 4, &reply).unwrap();
+
+        let session = AgentSessionDao::get(&conn, "s1").unwrap().unwrap();
+        assert_eq!(session.messages.len(), 5);
+        assert_eq!(session.updated_at, "2025-01-02T00:00:00+00:00");
+    }
+
+    #[test]
+    fn test_rename_archive_and_list() {
+        let conn = create_test_connection();
+        AgentSessionDao::save(&conn, &create_test_session("s1")).unwrap();
+        AgentSessionDao::save(&conn, &create_test_session("s2")).unwrap();
+
+        assert!(AgentSessionDao::rename(&conn, "s1", Some("测试会话")).unwrap());
+        assert!(AgentSessionDao::set_archived(&conn, "s2", true).unwrap());
+        assert!(!AgentSessionDao::rename(&conn, "missing", Some("x")).unwrap());
+
+        let active = AgentSessionDao::list(&conn, false).unwrap();
+        assert_eq!(active.len(), 1);
+        assert_eq!(active[0].title.as_deref(), Some("测试会话"));
+        assert_eq!(active[0].message_count, 4);
+        assert_eq!(AgentSessionDao::list(&conn, true).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_search_messages_and_tool_calls() {
+        let conn = create_test_connection();
+        AgentSessionDao::save(&conn, &create_test_session("s1")).unwrap();
+        AgentSessionDao::rename(&conn, "s1", Some("Cargo 测试")).unwrap();
+
+        let hits = AgentSessionDao::search(&conn, "cargo", false, 10).unwrap();
+        assert_eq!(hits.len(), 2);
+        assert_eq!(hits[0].message_index, None);
+        assert_eq!(hits[1].message_index, Some(1));
+        assert!(hits[1].snippet.contains("cargo test"));
+
+        let hits = AgentSessionDao::search(&conn, "测试通过", false, 10).unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].role.as_deref(), Some("assistant"));
+
+        // 通配符按字面匹配
+        assert!(AgentSessionDao::search(&conn, "%", false, 10)
+            .unwrap()
+            .is_empty());
+
+        AgentSessionDao::set_archived(&conn, "s1", true).unwrap();
+        assert!(AgentSessionDao::search(&conn, "cargo", false, 10)
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            AgentSessionDao::search(&conn, "cargo", true, 10)
+                .unwrap()
+                .len(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_fork_at_message() {
+        let conn = create_test_connection();
+        AgentSessionDao::save(&conn, &create_test_session("s1")).unwrap();
+
+        let forked = AgentSessionDao::fork(&conn, "s1", 1, "s2", "2025-02-01T00:00:00+00:00")
+            .unwrap()
+            .unwrap();
+        assert_eq!(forked.parent_id.as_deref(), Some("s1"));
+        assert_eq!(forked.messages.len(), 2);
+
+        let stored = AgentSessionDao::get(&conn, "s2").unwrap().unwrap();
+        assert_eq!(stored.messages.len(), 2);
+        // 源会话不受影响
+        assert_eq!(
+            AgentSessionDao::get(&conn, "s1")
+                .unwrap()
+                .unwrap()
+                .messages
+                .len(),
+            4
+        );
+
+        assert!(AgentSessionDao::fork(&conn, "s1", 10, "s3", "now").is_err());
+        assert!(AgentSessionDao::fork(&conn, "missing", 0, "s3", "now")
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn test_compactions_and_pinned_messages() {
+        let conn = create_test_connection();
+        AgentSessionDao::save(&conn, &create_test_session("s1")).unwrap();
+
+        assert!(AgentSessionDao::set_pinned(&conn, "s1", 0, true).unwrap());
+        assert!(!AgentSessionDao::set_pinned(&conn, "s1", 10, true).unwrap());
+
+        let compaction = ContextCompaction {
+            id: "c1".to_string(),
+            compacted_from: 0,
+            compacted_until: 2,
+            summary: "用户要求运行测试".to_string(),
+            model: "gpt-4o-mini".to_string(),
+            tokens_before: 1000,
+            tokens_after: 200,
+            created_at: "2025-01-01T00:00:02+00:00".to_string(),
+        };
+        AgentSessionDao::add_compaction(&conn, "s1", &compaction).unwrap();
+
+        let session = AgentSessionDao::get(&conn, "s1").unwrap().unwrap();
+        assert!(session.messages[0].pinned);
+        assert!(!session.messages[1].pinned);
+        assert_eq!(session.compactions, vec![compaction]);
+
+        // 分叉只保留完全落在分叉点之前的压缩记录
+        let forked = AgentSessionDao::fork(&conn, "s1", 2, "s2", "now")
+            .unwrap()
+            .unwrap();
+        assert_eq!(forked.compactions.len(), 1);
+        assert_ne!(forked.compactions[0].id, "c1");
+        let forked = AgentSessionDao::fork(&conn, "s1", 0, "s3", "now")
+            .unwrap()
+            .unwrap();
+        assert!(forked.compactions.is_empty());
+
+        AgentSessionDao::clear_messages(&conn, "s1", "now").unwrap();
+        assert!(AgentSessionDao::get_compactions(&conn, "s1")
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_clear_and_delete() {
+        let conn = create_test_connection();
+        AgentSessionDao::save(&conn, &create_test_session("s1")).unwrap();
+
+        assert!(AgentSessionDao::clear_messages(&conn, "s1", "now").unwrap());
+        assert!(AgentSessionDao::get_messages(&conn, "s1")
+            .unwrap()
+            .is_empty());
+
+        assert!(AgentSessionDao::delete(&conn, "s1").unwrap());
+        assert!(!AgentSessionDao::delete(&conn, "s1").unwrap());
+        assert!(AgentSessionDao::get(&conn, "s1").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_api_sessions() {
+        let conn = create_test_connection();
+        AgentSessionDao::save(&conn, &create_test_session("api")).unwrap();
+        AgentSessionDao::save(&conn, &create_test_session("desktop")).unwrap();
+        AgentSessionDao::mark_api_session(&conn, "api", "now").unwrap();
+
+        // 重新保存会话不影响 API 标记
+        AgentSessionDao::save(&conn, &create_test_session("api")).unwrap();
+        assert!(AgentSessionDao::is_api_session(&conn, "api").unwrap());
+        assert!(!AgentSessionDao::is_api_session(&conn, "desktop").unwrap());
+
+        assert!(AgentSessionDao::delete(&conn, "api").unwrap());
+        assert!(!AgentSessionDao::is_api_session(&conn, "api").unwrap());
+    }
+
+    #[test]
+    fn test_truncate_messages() {
+        let conn = create_test_connection();
+        AgentSessionDao::save(&conn, &create_test_session("s1")).unwrap();
+        let count = AgentSessionDao::get_messages(&conn, "s1").unwrap().len();
+        assert!(count > 1);
+
+        assert_eq!(
+            AgentSessionDao::truncate_messages(&conn, "s1", 1, "now").unwrap(),
+            count - 1
+        );
+        assert_eq!(AgentSessionDao::get_messages(&conn, "s1").unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_snippet() {
+        let text = format!("{}needle{}", "a".repeat(200), "b".repeat(200));
+        let result = snippet(&text, "NEEDLE");
+        assert!(result.starts_with('…'));
+        assert!(result.ends_with('…'));
+        assert!(result.contains("needle"));
+        assert_eq!(snippet("short", "x"), "short");
+    }
+}
@@ -0,0 +1,117 @@
+//! 工具调用审批审计日志数据访问对象
+
+use rusqlite::{params, Connection, Row};
+
+use crate::agent::approval::{ApprovalSource, ApprovalVerdictKind, ToolApprovalAuditEntry};
+
+pub struct AgentToolApprovalDao;
+
+impl AgentToolApprovalDao {
+    /// 写入一条审计记录，返回记录 ID
+    pub fn insert(
+        conn: &Connection,
+        entry: &ToolApprovalAuditEntry,
+    ) -> Result<i64, rusqlite::Error> {
+        conn.execute(
+            "INSERT INTO agent_tool_approvals
+             (session_id, tool_call_id, tool_name, arguments, subject, decision, source, reason, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            params![
+                entry.session_id,
+                entry.tool_call_id,
+                entry.tool_name,
+                entry.arguments,
+                entry.subject,
+                entry.decision.as_str(),
+                entry.source.as_str(),
+                entry.reason,
+                entry.created_at,
+            ],
+        )?;
+        Ok(conn.last_insert_rowid())
+    }
+
+    /// 按时间倒序查询审计记录，`session_id` 为空时查询全部会话
+    pub fn list(
+        conn: &Connection,
+        session_id: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<ToolApprovalAuditEntry>, rusqlite::Error> {
+        let mut stmt = conn.prepare(
+            "SELECT id, session_id, tool_call_id, tool_name, arguments, subject,
+                    decision, source, reason, created_at
+             FROM agent_tool_approvals
+             WHERE ?1 IS NULL OR session_id = ?1
+             ORDER BY id DESC
+             LIMIT ?2",
+        )?;
+        let rows = stmt.query_map(params![session_id, limit as i64], map_entry)?;
+        rows.collect()
+    }
+}
+
+fn map_entry(row: &Row) -> Result<ToolApprovalAuditEntry, rusqlite::Error> {
+    let decision: String = row.get(6)?;
+    let source: String = row.get(7)?;
+    Ok(ToolApprovalAuditEntry {
+        id: row.get(0)?,
+        session_id: row.get(1)?,
+        tool_call_id: row.get(2)?,
+        tool_name: row.get(3)?,
+        arguments: row.get(4)?,
+        subject: row.get(5)?,
+        decision: ApprovalVerdictKind::parse(&decision).ok_or_else(|| invalid_text(6, decision))?,
+        source: ApprovalSource::parse(&source).ok_or_else(|| invalid_text(7, source))?,
+        reason: row.get(8)?,
+        created_at: row.get(9)?,
+    })
+}
+
+fn invalid_text(column: usize, value: String) -> rusqlite::Error {
+    rusqlite::Error::FromSqlConversionFailure(
+        column,
+        rusqlite::types::Type::Text,
+        format!("未知的审计字段值: {}", value).into(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::schema::create_agent_session_tables;
+
+    fn entry(session_id: &str, decision: ApprovalVerdictKind) -> ToolApprovalAuditEntry {
+        ToolApprovalAuditEntry {
+            id: 0,
+            session_id: Some(session_id.to_string()),
+            tool_call_id: "call_1".to_string(),
+            tool_name: "bash".to_string(),
+            arguments: r#"{"command":"ls"}"#.to_string(),
+            subject: Some("ls".to_string()),
+            decision,
+            source: ApprovalSource::User,
+            reason: None,
+            created_at: chrono::Utc::now().to_rfc3339(),
+        }
+    }
+
+    #[test]
+    fn test_insert_and_list() {
+        let conn = Connection::open_in_memory().unwrap();
+        create_agent_session_tables(&conn).unwrap();
+
+        AgentToolApprovalDao::insert(&conn, &entry("s1", ApprovalVerdictKind::Allowed)).unwrap();
+        AgentToolApprovalDao::insert(&conn, &entry("s2", ApprovalVerdictKind::Denied)).unwrap();
+        AgentToolApprovalDao::insert(&conn, &entry("s1", ApprovalVerdictKind::Edited)).unwrap();
+
+        let all = AgentToolApprovalDao::list(&conn, None, 10).unwrap();
+        assert_eq!(all.len(), 3);
+        assert_eq!(all[0].decision, ApprovalVerdictKind::Edited);
+
+        let s1 = AgentToolApprovalDao::list(&conn, Some("s1"), 10).unwrap();
+        assert_eq!(s1.len(), 2);
+        assert!(s1.iter().all(|e| e.session_id.as_deref() == Some("s1")));
+
+        assert_eq!(AgentToolApprovalDao::list(&conn, None, 1).unwrap().len(), 1);
+    }
+}
@@ -1,3 +1,6 @@
+pub mod agent_checkpoints;
+pub mod agent_sessions;
+pub mod agent_tool_approvals;
 pub mod api_key_provider;
 pub mod installed_plugins;
 pub mod mcp;
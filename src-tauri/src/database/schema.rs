@@ -246,6 +246,150 @@ pub fn create_tables(conn: &Connection) -> Result<(), rusqlite::Error> {
         [],
     )?;
 
+    create_agent_session_tables(conn)?;
+
+    Ok(())
+}
+
+/// 原生 Agent 会话表
+///
+/// 消息按 (session_id, seq) 顺序保存，content 为 MessageContent 的 JSON，
+/// content_text 为纯文本内容，用于跨会话搜索。
+pub fn create_agent_session_tables(conn: &Connection) -> Result<(), rusqlite::Error> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS agent_sessions (
+            id TEXT PRIMARY KEY,
+            title TEXT,
+            model TEXT NOT NULL,
+            system_prompt TEXT,
+            archived INTEGER NOT NULL DEFAULT 0,
+            parent_id TEXT,
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_agent_sessions_updated ON agent_sessions(updated_at)",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS agent_messages (
+            session_id TEXT NOT NULL,
+            seq INTEGER NOT NULL,
+            role TEXT NOT NULL,
+            content TEXT NOT NULL,
+            content_text TEXT NOT NULL,
+            tool_calls TEXT,
+            tool_call_id TEXT,
+            timestamp TEXT NOT NULL,
+            pinned INTEGER NOT NULL DEFAULT 0,
+            PRIMARY KEY (session_id, seq),
+            FOREIGN KEY (session_id) REFERENCES agent_sessions(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+    migrate_add_agent_message_pinned_column(conn)?;
+
+    // 上下文压缩记录（原始消息保留，仅记录摘要和范围）
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS agent_session_compactions (
+            id TEXT PRIMARY KEY,
+            session_id TEXT NOT NULL,
+            compacted_from INTEGER NOT NULL,
+            compacted_until INTEGER NOT NULL,
+            summary TEXT NOT NULL,
+            model TEXT NOT NULL,
+            tokens_before INTEGER NOT NULL,
+            tokens_after INTEGER NOT NULL,
+            created_at TEXT NOT NULL,
+            FOREIGN KEY (session_id) REFERENCES agent_sessions(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_agent_session_compactions_session
+         ON agent_session_compactions(session_id, compacted_until)",
+        [],
+    )?;
+
+    // 工作区检查点（文件内容保存在对象存储中）
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS agent_checkpoints (
+            id TEXT PRIMARY KEY,
+            session_id TEXT NOT NULL,
+            message_index INTEGER NOT NULL,
+            label TEXT NOT NULL,
+            file_count INTEGER NOT NULL,
+            scopes TEXT NOT NULL,
+            untracked INTEGER NOT NULL DEFAULT 0,
+            manifest TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            FOREIGN KEY (session_id) REFERENCES agent_sessions(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_agent_checkpoints_session
+         ON agent_checkpoints(session_id, message_index)",
+        [],
+    )?;
+
+    // 工具调用审批审计日志（会话删除后保留）
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS agent_tool_approvals (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            session_id TEXT,
+            tool_call_id TEXT NOT NULL,
+            tool_name TEXT NOT NULL,
+            arguments TEXT NOT NULL,
+            subject TEXT,
+            decision TEXT NOT NULL,
+            source TEXT NOT NULL,
+            reason TEXT,
+            created_at TEXT NOT NULL
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_agent_tool_approvals_session
+         ON agent_tool_approvals(session_id, id)",
+        [],
+    )?;
+
+    // 通过 API Server 创建的会话，Agent API 只能操作这些会话
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS agent_api_sessions (
+            session_id TEXT PRIMARY KEY,
+            created_at TEXT NOT NULL
+        )",
+        [],
+    )?;
+
+    Ok(())
+}
+
+/// 迁移：添加 pinned 列到 agent_messages 表
+fn migrate_add_agent_message_pinned_column(conn: &Connection) -> Result<(), rusqlite::Error> {
+    let mut stmt = conn.prepare("PRAGMA table_info(agent_messages)")?;
+    let column_info: Vec<String> = stmt
+        .query_map([], |row| row.get::<_, String>(1))?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if column_info.iter().any(|c| c == "pinned") {
+        return Ok(());
+    }
+
+    tracing::info!("开始迁移：添加pinned列到agent_messages表");
+    conn.execute(
+        "ALTER TABLE agent_messages ADD COLUMN pinned INTEGER NOT NULL DEFAULT 0",
+        [],
+    )?;
     Ok(())
 }
 
@@ -0,0 +1,569 @@
+//! Flow 聚合查询
+//!
+//! 在过滤表达式之后追加 `group by <字段> | <聚合函数>` 后缀，返回表格而不是 Flow 列表：
+//!
+//! ```text
+//! ~since 1d & ~p kiro group by model, client | count, avg latency, p95 latency
+//! ```
+//!
+//! 省略 `| ...` 部分时默认只统计数量。
+
+use chrono::{DateTime, Utc};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+
+use super::filter_parser::{stop_reason_name, FilterExpr, FilterParseError, FilterParser};
+use super::models::{FlowState, LLMFlow};
+use super::pricing::estimate_flow_cost;
+use crate::server::client_detector::ClientType;
+
+// ============================================================================
+// 分组字段
+// ============================================================================
+
+/// 分组字段
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GroupField {
+    Model,
+    Provider,
+    State,
+    Client,
+    Credential,
+    StopReason,
+    Status,
+    /// 创建日期（YYYY-MM-DD）
+    Day,
+    /// 创建小时（YYYY-MM-DD HH:00）
+    Hour,
+}
+
+impl GroupField {
+    fn parse(s: &str) -> Result<Self, FilterParseError> {
+        match s.to_lowercase().as_str() {
+            "model" => Ok(GroupField::Model),
+            "provider" => Ok(GroupField::Provider),
+            "state" => Ok(GroupField::State),
+            "client" => Ok(GroupField::Client),
+            "cred" | "credential" => Ok(GroupField::Credential),
+            "stop" | "stop_reason" => Ok(GroupField::StopReason),
+            "status" => Ok(GroupField::Status),
+            "day" => Ok(GroupField::Day),
+            "hour" => Ok(GroupField::Hour),
+            _ => Err(FilterParseError::InvalidAggregation(format!(
+                "未知的分组字段 '{}'",
+                s
+            ))),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            GroupField::Model => "model",
+            GroupField::Provider => "provider",
+            GroupField::State => "state",
+            GroupField::Client => "client",
+            GroupField::Credential => "credential",
+            GroupField::StopReason => "stop_reason",
+            GroupField::Status => "status",
+            GroupField::Day => "day",
+            GroupField::Hour => "hour",
+        }
+    }
+
+    /// 取 Flow 在该字段上的分组键
+    fn key(&self, flow: &LLMFlow) -> String {
+        match self {
+            GroupField::Model => flow.request.model.clone(),
+            GroupField::Provider => flow.metadata.provider.to_string(),
+            GroupField::State => match flow.state {
+                FlowState::Pending => "pending",
+                FlowState::Streaming => "streaming",
+                FlowState::Completed => "completed",
+                FlowState::Failed => "failed",
+                FlowState::Cancelled => "cancelled",
+            }
+            .to_string(),
+            GroupField::Client => ClientType::from_user_agent(
+                flow.metadata
+                    .client_info
+                    .user_agent
+                    .as_deref()
+                    .unwrap_or_default(),
+            )
+            .to_string(),
+            GroupField::Credential => flow
+                .metadata
+                .credential_name
+                .clone()
+                .or_else(|| flow.metadata.credential_id.clone())
+                .unwrap_or_default(),
+            GroupField::StopReason => flow
+                .response
+                .as_ref()
+                .and_then(|r| r.stop_reason.as_ref())
+                .map(stop_reason_name)
+                .unwrap_or_default(),
+            GroupField::Status => flow
+                .response
+                .as_ref()
+                .map(|r| r.status_code)
+                .filter(|code| *code != 0)
+                .or_else(|| flow.error.as_ref().and_then(|e| e.status_code))
+                .map(|code| code.to_string())
+                .unwrap_or_default(),
+            GroupField::Day => flow.timestamps.created.format("%Y-%m-%d").to_string(),
+            GroupField::Hour => flow.timestamps.created.format("%Y-%m-%d %H:00").to_string(),
+        }
+    }
+}
+
+// ============================================================================
+// 聚合函数
+// ============================================================================
+
+/// 聚合指标
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AggregateMetric {
+    /// 延迟（毫秒）
+    Latency,
+    /// 首字节延迟（毫秒，仅流式响应）
+    Ttfb,
+    /// 总 Token 数
+    Tokens,
+    /// 输入 Token 数
+    InputTokens,
+    /// 输出 Token 数
+    OutputTokens,
+    /// 估算费用（美元）
+    Cost,
+}
+
+impl AggregateMetric {
+    fn parse(s: &str) -> Result<Self, FilterParseError> {
+        match s.to_lowercase().as_str() {
+            "latency" => Ok(AggregateMetric::Latency),
+            "ttfb" => Ok(AggregateMetric::Ttfb),
+            "tokens" => Ok(AggregateMetric::Tokens),
+            "input_tokens" => Ok(AggregateMetric::InputTokens),
+            "output_tokens" => Ok(AggregateMetric::OutputTokens),
+            "cost" => Ok(AggregateMetric::Cost),
+            _ => Err(FilterParseError::InvalidAggregation(format!(
+                "未知的聚合指标 '{}'",
+                s
+            ))),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            AggregateMetric::Latency => "latency",
+            AggregateMetric::Ttfb => "ttfb",
+            AggregateMetric::Tokens => "tokens",
+            AggregateMetric::InputTokens => "input_tokens",
+            AggregateMetric::OutputTokens => "output_tokens",
+            AggregateMetric::Cost => "cost",
+        }
+    }
+
+    /// 取 Flow 的指标值，缺失时返回 None（不参与统计）
+    fn value(&self, flow: &LLMFlow) -> Option<f64> {
+        match self {
+            AggregateMetric::Latency => Some(flow.timestamps.duration_ms as f64),
+            AggregateMetric::Ttfb => flow
+                .response
+                .as_ref()
+                .and_then(|r| r.stream_info.as_ref())
+                .map(|s| s.first_chunk_latency_ms as f64),
+            AggregateMetric::Tokens => flow.response.as_ref().map(|r| r.usage.total_tokens as f64),
+            AggregateMetric::InputTokens => {
+                flow.response.as_ref().map(|r| r.usage.input_tokens as f64)
+            }
+            AggregateMetric::OutputTokens => {
+                flow.response.as_ref().map(|r| r.usage.output_tokens as f64)
+            }
+            AggregateMetric::Cost => estimate_flow_cost(flow),
+        }
+    }
+}
+
+/// 聚合函数
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "fn", content = "metric")]
+pub enum AggregateFn {
+    Count,
+    Sum(AggregateMetric),
+    Avg(AggregateMetric),
+    Min(AggregateMetric),
+    Max(AggregateMetric),
+    /// 百分位数（1-100）
+    Percentile(u8, AggregateMetric),
+}
+
+impl AggregateFn {
+    /// 解析 `count`、`avg latency`、`p95(latency)` 形式的聚合函数
+    fn parse(s: &str) -> Result<Self, FilterParseError> {
+        let invalid = || FilterParseError::InvalidAggregation(format!("无效的聚合函数 '{}'", s));
+
+        let normalized = s.replace(['(', ')'], " ");
+        let mut parts = normalized.split_whitespace();
+        let name = parts.next().ok_or_else(invalid)?.to_lowercase();
+        let metric = parts.next();
+        if parts.next().is_some() {
+            return Err(invalid());
+        }
+
+        if name == "count" {
+            return match metric {
+                None => Ok(AggregateFn::Count),
+                Some(_) => Err(invalid()),
+            };
+        }
+
+        let metric = AggregateMetric::parse(metric.ok_or_else(invalid)?)?;
+        match name.as_str() {
+            "sum" => Ok(AggregateFn::Sum(metric)),
+            "avg" => Ok(AggregateFn::Avg(metric)),
+            "min" => Ok(AggregateFn::Min(metric)),
+            "max" => Ok(AggregateFn::Max(metric)),
+            _ => name
+                .strip_prefix('p')
+                .and_then(|p| p.parse::<u8>().ok())
+                .filter(|p| (1..=100).contains(p))
+                .map(|p| AggregateFn::Percentile(p, metric))
+                .ok_or_else(invalid),
+        }
+    }
+
+    /// 列名，如 `count`、`p95(latency)`
+    fn label(&self) -> String {
+        match self {
+            AggregateFn::Count => "count".to_string(),
+            AggregateFn::Sum(m) => format!("sum({})", m.name()),
+            AggregateFn::Avg(m) => format!("avg({})", m.name()),
+            AggregateFn::Min(m) => format!("min({})", m.name()),
+            AggregateFn::Max(m) => format!("max({})", m.name()),
+            AggregateFn::Percentile(p, m) => format!("p{}({})", p, m.name()),
+        }
+    }
+
+    fn compute(&self, flows: &[&LLMFlow]) -> serde_json::Value {
+        let metric = match self {
+            AggregateFn::Count => return serde_json::json!(flows.len()),
+            AggregateFn::Sum(m)
+            | AggregateFn::Avg(m)
+            | AggregateFn::Min(m)
+            | AggregateFn::Max(m)
+            | AggregateFn::Percentile(_, m) => m,
+        };
+
+        let mut values: Vec<f64> = flows.iter().filter_map(|f| metric.value(f)).collect();
+        if values.is_empty() {
+            return serde_json::Value::Null;
+        }
+
+        let result = match self {
+            AggregateFn::Count => unreachable!(),
+            AggregateFn::Sum(_) => values.iter().sum(),
+            AggregateFn::Avg(_) => values.iter().sum::<f64>() / values.len() as f64,
+            AggregateFn::Min(_) => values.iter().cloned().fold(f64::INFINITY, f64::min),
+            AggregateFn::Max(_) => values.iter().cloned().fold(f64::NEG_INFINITY, f64::max),
+            AggregateFn::Percentile(p, _) => {
+                // 最近秩法
+                values.sort_by(|a, b| a.total_cmp(b));
+                let rank = ((*p as f64 / 100.0) * values.len() as f64).ceil() as usize;
+                values[rank.clamp(1, values.len()) - 1]
+            }
+        };
+        serde_json::json!(result)
+    }
+}
+
+impl fmt::Display for AggregateFn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.label())
+    }
+}
+
+// ============================================================================
+// 聚合与查询
+// ============================================================================
+
+/// 聚合定义
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Aggregation {
+    /// 分组字段
+    pub group_by: Vec<GroupField>,
+    /// 聚合函数
+    pub functions: Vec<AggregateFn>,
+}
+
+/// 聚合结果表
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AggregateTable {
+    /// 列名（分组字段在前，聚合函数在后）
+    pub columns: Vec<String>,
+    /// 行数据（按分组键升序）
+    pub rows: Vec<Vec<serde_json::Value>>,
+}
+
+impl Aggregation {
+    /// 解析 `group by` 之后的部分，如 `model, client | count, p95 latency`
+    fn parse(spec: &str) -> Result<Self, FilterParseError> {
+        let (fields, functions) = match spec.split_once('|') {
+            Some((fields, functions)) => (fields, Some(functions)),
+            None => (spec, None),
+        };
+
+        let group_by = fields
+            .split([',', ' ', '\t', '\n'])
+            .filter(|f| !f.is_empty())
+            .map(GroupField::parse)
+            .collect::<Result<Vec<_>, _>>()?;
+        if group_by.is_empty() {
+            return Err(FilterParseError::InvalidAggregation(
+                "group by 缺少分组字段".to_string(),
+            ));
+        }
+
+        let functions = match functions {
+            Some(functions) => functions
+                .split(',')
+                .map(str::trim)
+                .filter(|f| !f.is_empty())
+                .map(AggregateFn::parse)
+                .collect::<Result<Vec<_>, _>>()?,
+            None => Vec::new(),
+        };
+        let functions = if functions.is_empty() {
+            vec![AggregateFn::Count]
+        } else {
+            functions
+        };
+
+        Ok(Self {
+            group_by,
+            functions,
+        })
+    }
+
+    /// 对 Flow 执行聚合
+    pub fn apply<'a, I>(&self, flows: I) -> AggregateTable
+    where
+        I: IntoIterator<Item = &'a LLMFlow>,
+    {
+        let mut groups: BTreeMap<Vec<String>, Vec<&LLMFlow>> = BTreeMap::new();
+        for flow in flows {
+            let key = self.group_by.iter().map(|field| field.key(flow)).collect();
+            groups.entry(key).or_default().push(flow);
+        }
+
+        let columns = self
+            .group_by
+            .iter()
+            .map(|field| field.name().to_string())
+            .chain(self.functions.iter().map(AggregateFn::label))
+            .collect();
+
+        let rows = groups
+            .into_iter()
+            .map(|(key, flows)| {
+                key.into_iter()
+                    .map(serde_json::Value::String)
+                    .chain(self.functions.iter().map(|f| f.compute(&flows)))
+                    .collect()
+            })
+            .collect();
+
+        AggregateTable { columns, rows }
+    }
+}
+
+/// 带可选聚合后缀的 Flow 查询
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FlowQuery {
+    /// 过滤表达式（为空表示不过滤）
+    pub filter: Option<FilterExpr>,
+    /// 聚合定义
+    pub aggregation: Option<Aggregation>,
+}
+
+impl FlowQuery {
+    /// 解析查询字符串
+    pub fn parse(input: &str) -> Result<Self, FilterParseError> {
+        let input = input.trim();
+        let (filter_part, aggregation) = match find_group_by(input) {
+            Some((start, end)) => (&input[..start], Some(Aggregation::parse(&input[end..])?)),
+            None => (input, None),
+        };
+
+        let filter = if filter_part.trim().is_empty() {
+            if aggregation.is_none() {
+                return Err(FilterParseError::EmptyExpression);
+            }
+            None
+        } else {
+            Some(FilterParser::parse(filter_part)?)
+        };
+
+        Ok(Self {
+            filter,
+            aggregation,
+        })
+    }
+
+    /// 判断 Flow 是否满足过滤条件
+    pub fn matches(&self, flow: &LLMFlow) -> bool {
+        self.filter
+            .as_ref()
+            .is_none_or(|expr| FilterParser::evaluate(expr, flow))
+    }
+
+    /// 以 `now` 为基准编译过滤函数，相对时间只计算一次
+    pub fn compile_at(&self, now: DateTime<Utc>) -> Box<dyn Fn(&LLMFlow) -> bool + Send + Sync> {
+        match &self.filter {
+            Some(expr) => FilterParser::compile_at(expr, now),
+            None => Box::new(|_| true),
+        }
+    }
+}
+
+/// 查找不在引号内的 `group by` 关键字，返回其起止位置
+fn find_group_by(input: &str) -> Option<(usize, usize)> {
+    let keyword = Regex::new(r"(?i)^group\s+by\b").ok()?;
+
+    let mut quote: Option<char> = None;
+    let mut prev: Option<char> = None;
+    let mut chars = input.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match quote {
+            Some(q) => {
+                if c == '\\' {
+                    chars.next();
+                } else if c == q {
+                    quote = None;
+                }
+            }
+            None => {
+                if c == '"' || c == '\'' {
+                    quote = Some(c);
+                } else if prev.is_none_or(|p| p.is_whitespace() || p == ')') {
+                    if let Some(m) = keyword.find(&input[i..]) {
+                        return Some((i, i + m.end()));
+                    }
+                }
+            }
+        }
+        prev = Some(c);
+    }
+    None
+}
+
+// ============================================================================
+// 测试模块
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flow_monitor::models::{FlowMetadata, FlowType, LLMRequest, LLMResponse};
+
+    fn create_flow(model: &str, latency_ms: u64, ua: &str) -> LLMFlow {
+        let request = LLMRequest {
+            model: model.to_string(),
+            ..Default::default()
+        };
+        let mut flow = LLMFlow::new(
+            uuid::Uuid::new_v4().to_string(),
+            FlowType::ChatCompletions,
+            request,
+            FlowMetadata::default(),
+        );
+        flow.timestamps.duration_ms = latency_ms;
+        flow.metadata.client_info.user_agent = Some(ua.to_string());
+        flow.response = Some(LLMResponse {
+            status_code: 200,
+            ..Default::default()
+        });
+        flow
+    }
+
+    #[test]
+    fn test_parse_query_with_aggregation() {
+        let query = FlowQuery::parse("~m claude | ~e group by model, client | count, p95(latency)")
+            .unwrap();
+        assert!(query.filter.is_some());
+        let aggregation = query.aggregation.unwrap();
+        assert_eq!(
+            aggregation.group_by,
+            vec![GroupField::Model, GroupField::Client]
+        );
+        assert_eq!(
+            aggregation.functions,
+            vec![
+                AggregateFn::Count,
+                AggregateFn::Percentile(95, AggregateMetric::Latency)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_query_without_filter_defaults_to_count() {
+        let query = FlowQuery::parse("GROUP BY day").unwrap();
+        assert!(query.filter.is_none());
+        assert_eq!(
+            query.aggregation.unwrap().functions,
+            vec![AggregateFn::Count]
+        );
+    }
+
+    #[test]
+    fn test_group_by_inside_quotes_is_not_keyword() {
+        let query = FlowQuery::parse("~b \"group by model\"").unwrap();
+        assert!(query.aggregation.is_none());
+    }
+
+    #[test]
+    fn test_parse_invalid_aggregation() {
+        assert!(FlowQuery::parse("group by").is_err());
+        assert!(FlowQuery::parse("group by colour").is_err());
+        assert!(FlowQuery::parse("group by model | median latency").is_err());
+        assert!(FlowQuery::parse("group by model | avg").is_err());
+    }
+
+    #[test]
+    fn test_apply_aggregation() {
+        let flows = vec![
+            create_flow("gpt-4", 100, "cursor/1.0"),
+            create_flow("gpt-4", 300, "cursor/1.0"),
+            create_flow("claude", 200, "claude-code/1.0"),
+        ];
+        let query = FlowQuery::parse("group by model | count, avg latency, max latency").unwrap();
+        let table = query.aggregation.unwrap().apply(&flows);
+
+        assert_eq!(
+            table.columns,
+            vec!["model", "count", "avg(latency)", "max(latency)"]
+        );
+        assert_eq!(table.rows.len(), 2);
+        assert_eq!(table.rows[0][0], "claude");
+        assert_eq!(table.rows[1][0], "gpt-4");
+        assert_eq!(table.rows[1][1], 2);
+        assert_eq!(table.rows[1][2], 200.0);
+        assert_eq!(table.rows[1][3], 300.0);
+    }
+
+    #[test]
+    fn test_percentile_nearest_rank() {
+        let flows: Vec<LLMFlow> = (1..=20)
+            .map(|i| create_flow("gpt-4", i * 10, "cursor"))
+            .collect();
+        let query = FlowQuery::parse("group by model | p95 latency, p50 latency").unwrap();
+        let table = query.aggregation.unwrap().apply(&flows);
+        assert_eq!(table.rows[0][1], 190.0);
+        assert_eq!(table.rows[0][2], 100.0);
+    }
+}
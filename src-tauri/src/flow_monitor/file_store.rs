@@ -6,6 +6,7 @@
 use chrono::{DateTime, NaiveDate, Utc};
 use rusqlite::{params, Connection, OptionalExtension};
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
 use std::fs::{self, File, OpenOptions};
 use std::io::{BufRead, BufReader, BufWriter, Seek, SeekFrom, Write};
 use std::path::{Path, PathBuf};
@@ -13,7 +14,7 @@ use std::sync::Mutex;
 use thiserror::Error;
 
 use super::memory_store::FlowFilter;
-use super::models::LLMFlow;
+use super::models::{FlowAnnotations, LLMFlow};
 
 // ============================================================================
 // 错误类型
@@ -82,6 +83,79 @@ pub struct CleanupResult {
     pub bytes_freed: u64,
 }
 
+/// 段压缩时对单条 Flow 的处置方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompactionAction {
+    /// 原样保留
+    Keep,
+    /// Flow 已被修改，需要重新写入并更新索引
+    Rewrite,
+    /// 删除 Flow 及其索引
+    Delete,
+}
+
+/// 段压缩结果
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CompactionResult {
+    /// 扫描的 JSONL 段数
+    pub segments_scanned: usize,
+    /// 被重写的段数
+    pub segments_rewritten: usize,
+    /// 压缩后变为空而被删除的段数
+    pub segments_removed: usize,
+    /// 删除的 Flow 数
+    pub flows_deleted: usize,
+    /// 被重写（如剥离请求/响应体）的 Flow 数
+    pub flows_rewritten: usize,
+    /// 丢弃的过期行数（索引已指向其他位置或已不在索引中）
+    pub stale_entries_dropped: usize,
+    /// 压缩前的段总大小（字节）
+    pub bytes_before: u64,
+    /// 压缩后的段总大小（字节）
+    pub bytes_after: u64,
+}
+
+impl CompactionResult {
+    /// 释放的空间（字节）
+    pub fn bytes_freed(&self) -> u64 {
+        self.bytes_before.saturating_sub(self.bytes_after)
+    }
+}
+
+/// 单日存储用量
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DailyStorageUsage {
+    /// 日期目录名（YYYY-MM-DD）
+    pub date: String,
+    /// JSONL 段数
+    pub segment_count: usize,
+    /// 段总大小（字节）
+    pub bytes: u64,
+    /// 索引中的 Flow 数
+    pub flow_count: usize,
+}
+
+/// 存储用量报告
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StorageUsageReport {
+    /// 总占用（段 + 索引，字节）
+    pub total_bytes: u64,
+    /// JSONL 段总大小（字节）
+    pub segment_bytes: u64,
+    /// SQLite 索引大小（字节）
+    pub index_bytes: u64,
+    /// JSONL 段数
+    pub segment_count: usize,
+    /// 索引中的 Flow 数
+    pub indexed_flows: usize,
+    /// 最早的 Flow 创建时间
+    pub oldest_flow_at: Option<DateTime<Utc>>,
+    /// 最新的 Flow 创建时间
+    pub newest_flow_at: Option<DateTime<Utc>>,
+    /// 按日期的用量明细（按日期升序）
+    pub days: Vec<DailyStorageUsage>,
+}
+
 // ============================================================================
 // 索引记录
 // ============================================================================
@@ -765,11 +839,7 @@ impl FlowFileStore {
     /// # 参数
     /// - `flow_id`: Flow ID
     /// - `annotations`: 新的标注信息
-    pub fn update_annotations(
-        &self,
-        flow_id: &str,
-        annotations: &crate::flow_monitor::models::FlowAnnotations,
-    ) -> Result<()> {
+    pub fn update_annotations(&self, flow_id: &str, annotations: &FlowAnnotations) -> Result<()> {
         let conn = self.index_db.lock().unwrap();
 
         // 更新或插入标注
@@ -906,6 +976,373 @@ impl FlowFileStore {
         let before = Utc::now() - chrono::Duration::days(retention_days as i64);
         self.cleanup(before)
     }
+
+    /// 按回调结果压缩所有 JSONL 段
+    ///
+    /// 对每条仍被索引引用的 Flow 调用 `decide`（调用前会用索引中的最新标注
+    /// 覆盖 Flow 自带的标注）：`Delete` 会连同索引、标注、标签和全文索引一起删除；
+    /// `Rewrite` 表示回调已就地修改了 Flow，需要重新写入并刷新索引预览。
+    /// 索引已不再指向的过期行会被丢弃，无法解析的行原样保留。
+    ///
+    /// 压缩期间持有写入器锁，并发写入会等待压缩结束。
+    pub fn compact<F>(&self, mut decide: F) -> Result<CompactionResult>
+    where
+        F: FnMut(&mut LLMFlow) -> CompactionAction,
+    {
+        let mut writer_guard = self.current_writer.lock().unwrap();
+        // 关闭当前写入器，下次写入时按压缩后的文件大小重新计算偏移量
+        *writer_guard = None;
+
+        let mut result = CompactionResult::default();
+        for segment in self.list_segments()? {
+            result.segments_scanned += 1;
+            self.compact_segment(&segment, &mut decide, &mut result)?;
+        }
+        drop(writer_guard);
+
+        self.cleanup_empty_dirs()?;
+
+        Ok(result)
+    }
+
+    /// 压缩单个 JSONL 段
+    fn compact_segment<F>(
+        &self,
+        segment: &Path,
+        decide: &mut F,
+        result: &mut CompactionResult,
+    ) -> Result<()>
+    where
+        F: FnMut(&mut LLMFlow) -> CompactionAction,
+    {
+        let file_path = segment.to_string_lossy().to_string();
+        let bytes_before = fs::metadata(segment)?.len();
+        result.bytes_before += bytes_before;
+
+        let (indexed, annotations) = {
+            let conn = self.index_db.lock().unwrap();
+            (
+                Self::load_segment_offsets(&conn, &file_path)?,
+                Self::load_segment_annotations(&conn, &file_path)?,
+            )
+        };
+
+        let mut retained: Vec<RetainedLine> = Vec::new();
+        let mut deleted: Vec<String> = Vec::new();
+        let mut changed = false;
+
+        let mut reader = BufReader::new(File::open(segment)?);
+        let mut line = String::new();
+        let mut offset = 0i64;
+        loop {
+            line.clear();
+            let read = reader.read_line(&mut line)?;
+            if read == 0 {
+                break;
+            }
+            let line_offset = offset;
+            offset += read as i64;
+
+            let trimmed = line.trim_end();
+            if trimmed.is_empty() {
+                changed = true;
+                continue;
+            }
+
+            let mut flow: LLMFlow = match serde_json::from_str(trimmed) {
+                Ok(flow) => flow,
+                Err(e) => {
+                    tracing::warn!(
+                        "压缩时跳过无法解析的行 {}@{}: {}",
+                        file_path,
+                        line_offset,
+                        e
+                    );
+                    retained.push(RetainedLine {
+                        id: None,
+                        line: trimmed.to_string(),
+                        rewritten: None,
+                    });
+                    continue;
+                }
+            };
+
+            if indexed.get(&flow.id) != Some(&line_offset) {
+                result.stale_entries_dropped += 1;
+                changed = true;
+                continue;
+            }
+
+            if let Some(current) = annotations.get(&flow.id) {
+                flow.annotations = current.clone();
+            }
+
+            match decide(&mut flow) {
+                CompactionAction::Keep => retained.push(RetainedLine {
+                    id: Some(flow.id.clone()),
+                    line: trimmed.to_string(),
+                    rewritten: None,
+                }),
+                CompactionAction::Rewrite => {
+                    changed = true;
+                    result.flows_rewritten += 1;
+                    retained.push(RetainedLine {
+                        id: Some(flow.id.clone()),
+                        line: serde_json::to_string(&flow)?,
+                        rewritten: Some(flow),
+                    });
+                }
+                CompactionAction::Delete => {
+                    changed = true;
+                    result.flows_deleted += 1;
+                    deleted.push(flow.id);
+                }
+            }
+        }
+
+        if !changed {
+            result.bytes_after += bytes_before;
+            return Ok(());
+        }
+
+        // 先写临时文件再原子替换，避免压缩中途失败损坏原段
+        let mut new_offsets: Vec<(&RetainedLine, i64)> = Vec::with_capacity(retained.len());
+        if retained.is_empty() {
+            fs::remove_file(segment)?;
+            result.segments_removed += 1;
+        } else {
+            let tmp_path = segment.with_extension("jsonl.compact");
+            let mut writer = BufWriter::new(File::create(&tmp_path)?);
+            let mut new_offset = 0i64;
+            for entry in &retained {
+                writer.write_all(entry.line.as_bytes())?;
+                writer.write_all(b"\n")?;
+                new_offsets.push((entry, new_offset));
+                new_offset += entry.line.len() as i64 + 1;
+            }
+            writer.flush()?;
+            writer.get_ref().sync_all()?;
+            drop(writer);
+            fs::rename(&tmp_path, segment)?;
+
+            result.segments_rewritten += 1;
+            result.bytes_after += new_offset as u64;
+        }
+
+        let mut conn = self.index_db.lock().unwrap();
+        let tx = conn.transaction()?;
+        for id in &deleted {
+            tx.execute(
+                "DELETE FROM flow_annotations WHERE flow_id = ?1",
+                params![id],
+            )?;
+            tx.execute("DELETE FROM flow_tags WHERE flow_id = ?1", params![id])?;
+            tx.execute("DELETE FROM flow_fts WHERE id = ?1", params![id])?;
+            tx.execute("DELETE FROM flow_index WHERE id = ?1", params![id])?;
+        }
+        for (entry, new_offset) in new_offsets {
+            let Some(id) = &entry.id else {
+                continue;
+            };
+            match &entry.rewritten {
+                Some(flow) => {
+                    let record = FlowIndexRecord::from_flow(flow, &file_path, new_offset);
+                    tx.execute(
+                        "UPDATE flow_index SET file_offset = ?1, content_preview = ?2, request_preview = ?3 WHERE id = ?4",
+                        params![new_offset, record.content_preview, record.request_preview, id],
+                    )?;
+                    let content_text = flow
+                        .response
+                        .as_ref()
+                        .map_or(String::new(), |r| r.content.clone());
+                    tx.execute("DELETE FROM flow_fts WHERE id = ?1", params![id])?;
+                    tx.execute(
+                        "INSERT INTO flow_fts (id, content_text, request_text, model) VALUES (?1, ?2, ?3, ?4)",
+                        params![
+                            id,
+                            content_text,
+                            Self::get_request_text_for_fts(flow),
+                            flow.request.model
+                        ],
+                    )?;
+                }
+                None => {
+                    tx.execute(
+                        "UPDATE flow_index SET file_offset = ?1 WHERE id = ?2",
+                        params![new_offset, id],
+                    )?;
+                }
+            }
+        }
+        tx.commit()?;
+
+        Ok(())
+    }
+
+    /// 列出所有 JSONL 段（按路径排序，即按日期和序号）
+    fn list_segments(&self) -> Result<Vec<PathBuf>> {
+        let mut segments = Vec::new();
+        for entry in fs::read_dir(&self.base_dir)?.flatten() {
+            let dir = entry.path();
+            if !dir.is_dir() {
+                continue;
+            }
+            for file in fs::read_dir(&dir)?.flatten() {
+                let path = file.path();
+                if path.extension().is_some_and(|ext| ext == "jsonl") {
+                    segments.push(path);
+                }
+            }
+        }
+        segments.sort();
+        Ok(segments)
+    }
+
+    /// 读取某个段内所有 Flow 在索引中的偏移量
+    fn load_segment_offsets(conn: &Connection, file_path: &str) -> Result<HashMap<String, i64>> {
+        let mut stmt =
+            conn.prepare("SELECT id, file_offset FROM flow_index WHERE file_path = ?1")?;
+        let offsets = stmt
+            .query_map(params![file_path], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .filter_map(|r| r.ok())
+            .collect();
+        Ok(offsets)
+    }
+
+    /// 读取某个段内所有 Flow 在索引中的最新标注
+    ///
+    /// 标注更新只写索引不写 JSONL，因此以索引为准。
+    fn load_segment_annotations(
+        conn: &Connection,
+        file_path: &str,
+    ) -> Result<HashMap<String, FlowAnnotations>> {
+        let mut annotations: HashMap<String, FlowAnnotations> = HashMap::new();
+
+        let mut stmt = conn.prepare(
+            r#"
+            SELECT a.flow_id, a.starred, a.marker, a.comment
+            FROM flow_annotations a JOIN flow_index i ON i.id = a.flow_id
+            WHERE i.file_path = ?1
+            "#,
+        )?;
+        let rows = stmt.query_map(params![file_path], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, i32>(1)? != 0,
+                row.get::<_, Option<String>>(2)?,
+                row.get::<_, Option<String>>(3)?,
+            ))
+        })?;
+        for (flow_id, starred, marker, comment) in rows.flatten() {
+            annotations.insert(
+                flow_id,
+                FlowAnnotations {
+                    marker,
+                    comment,
+                    tags: Vec::new(),
+                    starred,
+                },
+            );
+        }
+
+        let mut stmt = conn.prepare(
+            r#"
+            SELECT t.flow_id, t.tag
+            FROM flow_tags t JOIN flow_index i ON i.id = t.flow_id
+            WHERE i.file_path = ?1
+            "#,
+        )?;
+        let rows = stmt.query_map(params![file_path], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })?;
+        for (flow_id, tag) in rows.flatten() {
+            annotations.entry(flow_id).or_default().tags.push(tag);
+        }
+
+        Ok(annotations)
+    }
+
+    /// 生成存储用量报告
+    pub fn storage_usage(&self) -> Result<StorageUsageReport> {
+        let mut report = StorageUsageReport::default();
+        let mut days: BTreeMap<String, DailyStorageUsage> = BTreeMap::new();
+
+        fn date_of(path: &Path) -> String {
+            path.parent()
+                .and_then(|p| p.file_name())
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_default()
+        }
+
+        for segment in self.list_segments()? {
+            let bytes = fs::metadata(&segment).map(|m| m.len()).unwrap_or(0);
+            let date = date_of(&segment);
+            let day = days
+                .entry(date.clone())
+                .or_insert_with(|| DailyStorageUsage {
+                    date,
+                    ..Default::default()
+                });
+            day.segment_count += 1;
+            day.bytes += bytes;
+            report.segment_count += 1;
+            report.segment_bytes += bytes;
+        }
+
+        {
+            let conn = self.index_db.lock().unwrap();
+
+            let mut stmt =
+                conn.prepare("SELECT file_path, COUNT(*) FROM flow_index GROUP BY file_path")?;
+            let rows = stmt.query_map([], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?))
+            })?;
+            for (file_path, count) in rows.flatten() {
+                let date = date_of(Path::new(&file_path));
+                let day = days
+                    .entry(date.clone())
+                    .or_insert_with(|| DailyStorageUsage {
+                        date,
+                        ..Default::default()
+                    });
+                day.flow_count += count as usize;
+                report.indexed_flows += count as usize;
+            }
+
+            let (oldest, newest): (Option<String>, Option<String>) = conn.query_row(
+                "SELECT MIN(created_at), MAX(created_at) FROM flow_index",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )?;
+            let parse = |s: Option<String>| {
+                s.and_then(|s| DateTime::parse_from_rfc3339(&s).ok())
+                    .map(|dt| dt.with_timezone(&Utc))
+            };
+            report.oldest_flow_at = parse(oldest);
+            report.newest_flow_at = parse(newest);
+        }
+
+        for name in ["global_index.sqlite", "global_index.sqlite-wal"] {
+            if let Ok(metadata) = fs::metadata(self.base_dir.join(name)) {
+                report.index_bytes += metadata.len();
+            }
+        }
+
+        report.total_bytes = report.segment_bytes + report.index_bytes;
+        report.days = days.into_values().collect();
+
+        Ok(report)
+    }
+}
+
+/// 压缩时保留下来的一行
+struct RetainedLine {
+    /// Flow ID（无法解析的行为 None）
+    id: Option<String>,
+    /// 要写回的 JSON 行（不含换行符）
+    line: String,
+    /// 被回调修改过的 Flow
+    rewritten: Option<LLMFlow>,
 }
 
 // ============================================================================
@@ -1073,6 +1510,83 @@ mod tests {
         assert_eq!(store.count().unwrap(), 0);
     }
 
+    #[test]
+    fn test_file_store_compact() {
+        let temp_dir = TempDir::new().unwrap();
+        let store =
+            FlowFileStore::new(temp_dir.path().to_path_buf(), RotationConfig::default()).unwrap();
+
+        for i in 0..4 {
+            let flow = create_test_flow(&format!("flow-{}", i), "gpt-4", ProviderType::OpenAI);
+            store.write(&flow).unwrap();
+        }
+        // 重复写入同一个 Flow，旧行成为过期行
+        let mut updated = create_test_flow("flow-0", "gpt-4o", ProviderType::OpenAI);
+        updated.annotations.starred = true;
+        store.write(&updated).unwrap();
+
+        let result = store
+            .compact(|flow| match flow.id.as_str() {
+                "flow-1" => CompactionAction::Delete,
+                "flow-2" => {
+                    flow.request.model = "rewritten".to_string();
+                    CompactionAction::Rewrite
+                }
+                _ => CompactionAction::Keep,
+            })
+            .unwrap();
+
+        assert_eq!(result.segments_scanned, 1);
+        assert_eq!(result.segments_rewritten, 1);
+        assert_eq!(result.flows_deleted, 1);
+        assert_eq!(result.flows_rewritten, 1);
+        assert_eq!(result.stale_entries_dropped, 1);
+        assert!(result.bytes_freed() > 0);
+
+        assert_eq!(store.count().unwrap(), 3);
+        assert!(store.get("flow-1").unwrap().is_none());
+        assert_eq!(
+            store.get("flow-0").unwrap().unwrap().request.model,
+            "gpt-4o"
+        );
+        assert_eq!(
+            store.get("flow-2").unwrap().unwrap().request.model,
+            "rewritten"
+        );
+        assert_eq!(store.get("flow-3").unwrap().unwrap().request.model, "gpt-4");
+
+        // 压缩后继续写入，偏移量应正确
+        let flow = create_test_flow("flow-4", "gpt-4", ProviderType::OpenAI);
+        store.write(&flow).unwrap();
+        assert!(store.get("flow-4").unwrap().is_some());
+        assert!(store.get("flow-3").unwrap().is_some());
+    }
+
+    #[test]
+    fn test_file_store_storage_usage() {
+        let temp_dir = TempDir::new().unwrap();
+        let store =
+            FlowFileStore::new(temp_dir.path().to_path_buf(), RotationConfig::default()).unwrap();
+
+        for i in 0..3 {
+            let flow = create_test_flow(&format!("flow-{}", i), "gpt-4", ProviderType::OpenAI);
+            store.write(&flow).unwrap();
+        }
+
+        let report = store.storage_usage().unwrap();
+        assert_eq!(report.segment_count, 1);
+        assert_eq!(report.indexed_flows, 3);
+        assert!(report.segment_bytes > 0);
+        assert!(report.index_bytes > 0);
+        assert_eq!(
+            report.total_bytes,
+            report.segment_bytes + report.index_bytes
+        );
+        assert_eq!(report.days.len(), 1);
+        assert_eq!(report.days[0].flow_count, 3);
+        assert!(report.oldest_flow_at.is_some());
+    }
+
     #[test]
     fn test_index_record_from_flow() {
         let flow = create_test_flow("test-1", "gpt-4", ProviderType::OpenAI);
@@ -19,17 +19,28 @@
 //! - `~bs <regex>`: 响应内容匹配
 //! - `~tokens <op> <n>`: Token 数量比较
 //! - `~latency <op> <n>`: 延迟比较 (支持 s/ms 后缀)
+//! - `~since <time>` / `~until <time>`: 创建时间范围（相对时间如 `2h`，或绝对时间）
+//! - `~cred <pattern>`: 凭证 ID 或名称匹配
+//! - `~client <type>`: 客户端类型 (cursor/claude_code/codex/windsurf/kiro/other)
+//! - `~stop <reason>`: 停止原因匹配
+//! - `~status <n|op n|4xx>`: HTTP 状态码
+//! - `~cost <op> <usd>`: 估算费用比较（美元）
+//! - `~json <path> [op value]`: 请求/响应体 JSON 路径断言
+//! - `~view <name>`: 引用已保存的快速过滤器（由 `QuickFilterManager` 展开）
 //! - `&`: AND 逻辑
 //! - `|`: OR 逻辑
 //! - `!`: NOT 逻辑
 //! - `()`: 分组
 
+use chrono::{DateTime, NaiveDate, NaiveDateTime, TimeDelta, Utc};
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 use std::fmt;
 use thiserror::Error;
 
-use super::models::{FlowState, LLMFlow, MessageContent};
+use super::models::{FlowState, LLMFlow, MessageContent, StopReason};
+use super::pricing::estimate_flow_cost;
+use crate::server::client_detector::ClientType;
 
 // ============================================================================
 // 错误类型
@@ -81,6 +92,30 @@ pub enum FilterParseError {
     /// 空表达式
     #[error("空表达式")]
     EmptyExpression,
+
+    /// 无效的时间
+    #[error("无效的时间 '{0}'，支持相对时间（如 30m、2h、7d）或 RFC3339/YYYY-MM-DD 格式")]
+    InvalidTime(String),
+
+    /// 无效的客户端类型
+    #[error("无效的客户端类型 '{0}'，有效值: cursor, claude_code, codex, windsurf, kiro, other")]
+    InvalidClientType(String),
+
+    /// 无效的 JSON 路径
+    #[error("无效的 JSON 路径 '{0}'，路径需以 request 或 response 开头")]
+    InvalidJsonPath(String),
+
+    /// 引用了不存在的视图
+    #[error("视图 '{0}' 不存在")]
+    UnknownView(String),
+
+    /// 视图循环引用
+    #[error("视图循环引用: {0}")]
+    CircularViewReference(String),
+
+    /// 无效的聚合语法
+    #[error("无效的聚合语法: {0}")]
+    InvalidAggregation(String),
 }
 
 // ============================================================================
@@ -178,6 +213,28 @@ pub enum FilterToken {
     Tokens(Comparison),
     /// 延迟比较 (~latency <op> <value>)
     Latency(Comparison),
+    /// HTTP 状态码比较 (~status <op> <value>)
+    Status(Comparison),
+    /// HTTP 状态码类别 (~status 4xx)
+    StatusClass(u16),
+    /// 估算费用比较，单位为百万分之一美元 (~cost <op> <usd>)
+    Cost(Comparison),
+
+    // 元数据
+    /// 创建时间不早于 (~since <time>)
+    Since(TimeBound),
+    /// 创建时间不晚于 (~until <time>)
+    Until(TimeBound),
+    /// 凭证 ID 或名称匹配 (~cred <pattern>)
+    Credential(String),
+    /// 客户端类型 (~client <type>)
+    Client(ClientType),
+    /// 停止原因 (~stop <reason>)
+    StopReason(String),
+    /// JSON 路径断言 (~json <path> [op value])
+    Json(JsonPredicate),
+    /// 引用已保存的视图 (~view <name>)
+    View(String),
 
     // 逻辑运算
     /// AND 逻辑 (&)
@@ -210,6 +267,16 @@ impl fmt::Display for FilterToken {
             FilterToken::BodyResponse(s) => write!(f, "~bs {}", s),
             FilterToken::Tokens(c) => write!(f, "~tokens {}", c),
             FilterToken::Latency(c) => write!(f, "~latency {}", c),
+            FilterToken::Status(c) => write!(f, "~status {}", c),
+            FilterToken::StatusClass(class) => write!(f, "~status {}xx", class),
+            FilterToken::Cost(c) => write!(f, "~cost {}{}", c.op, c.value as f64 / 1_000_000.0),
+            FilterToken::Since(t) => write!(f, "~since {}", t),
+            FilterToken::Until(t) => write!(f, "~until {}", t),
+            FilterToken::Credential(s) => write!(f, "~cred {}", s),
+            FilterToken::Client(c) => write!(f, "~client {}", c),
+            FilterToken::StopReason(s) => write!(f, "~stop {}", s),
+            FilterToken::Json(p) => write!(f, "~json {}", p),
+            FilterToken::View(name) => write!(f, "~view {}", quote_if_needed(name)),
             FilterToken::And => write!(f, "&"),
             FilterToken::Or => write!(f, "|"),
             FilterToken::Not => write!(f, "!"),
@@ -242,6 +309,437 @@ fn parse_state(s: &str) -> Result<FlowState, FilterParseError> {
     }
 }
 
+/// 参数包含空白或特殊字符时加引号
+fn quote_if_needed(s: &str) -> String {
+    if s.is_empty()
+        || s.chars()
+            .any(|c| c.is_whitespace() || "&|()\"'".contains(c))
+    {
+        format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+    } else {
+        s.to_string()
+    }
+}
+
+/// 解析客户端类型
+fn parse_client_type(s: &str) -> Result<ClientType, FilterParseError> {
+    ClientType::from_config_key(&s.to_lowercase().replace('-', "_"))
+        .ok_or_else(|| FilterParseError::InvalidClientType(s.to_string()))
+}
+
+/// 停止原因的规范名称
+pub(crate) fn stop_reason_name(reason: &StopReason) -> String {
+    match reason {
+        StopReason::Stop => "stop".to_string(),
+        StopReason::Length => "length".to_string(),
+        StopReason::ToolCalls => "tool_calls".to_string(),
+        StopReason::ContentFilter => "content_filter".to_string(),
+        StopReason::FunctionCall => "function_call".to_string(),
+        StopReason::EndTurn => "end_turn".to_string(),
+        StopReason::Other(s) => s.to_lowercase(),
+    }
+}
+
+/// 规范化用户输入的停止原因（兼容各协议的别名）
+fn normalize_stop_reason(s: &str) -> String {
+    let s = s.to_lowercase().replace('-', "_");
+    match s.as_str() {
+        "max_tokens" => "length".to_string(),
+        "tool_use" => "tool_calls".to_string(),
+        "stop_sequence" => "stop".to_string(),
+        _ => s,
+    }
+}
+
+// ============================================================================
+// 时间边界
+// ============================================================================
+
+/// 时间边界
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TimeBound {
+    /// 相对当前时间之前的秒数
+    Relative(i64),
+    /// 绝对时间
+    Absolute(DateTime<Utc>),
+}
+
+impl TimeBound {
+    /// 解析时间参数
+    ///
+    /// 支持 `30s`、`15m`、`2h`、`7d`、`1w` 形式的相对时间，
+    /// 以及 RFC3339、`YYYY-MM-DD[T ]HH:MM[:SS]`（UTC）和 `YYYY-MM-DD` 形式的绝对时间。
+    /// 超出 `TimeDelta` 表示范围的相对时间返回 `InvalidTime`。
+    pub fn parse(s: &str) -> Result<Self, FilterParseError> {
+        let lower = s.to_lowercase();
+        if let Some(unit) = lower.chars().last() {
+            let multiplier = match unit {
+                's' => Some(1),
+                'm' => Some(60),
+                'h' => Some(3600),
+                'd' => Some(86400),
+                'w' => Some(7 * 86400),
+                _ => None,
+            };
+            if let Some(multiplier) = multiplier {
+                if let Ok(n) = lower[..lower.len() - 1].parse::<i64>() {
+                    return n
+                        .checked_mul(multiplier)
+                        .filter(|seconds| TimeDelta::try_seconds(*seconds).is_some())
+                        .map(TimeBound::Relative)
+                        .ok_or_else(|| FilterParseError::InvalidTime(s.to_string()));
+                }
+            }
+        }
+
+        if let Ok(dt) = DateTime::parse_from_rfc3339(s) {
+            return Ok(TimeBound::Absolute(dt.with_timezone(&Utc)));
+        }
+        for format in [
+            "%Y-%m-%dT%H:%M:%S",
+            "%Y-%m-%dT%H:%M",
+            "%Y-%m-%d %H:%M:%S",
+            "%Y-%m-%d %H:%M",
+        ] {
+            if let Ok(dt) = NaiveDateTime::parse_from_str(s, format) {
+                return Ok(TimeBound::Absolute(dt.and_utc()));
+            }
+        }
+        if let Ok(date) = NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+            return Ok(TimeBound::Absolute(
+                date.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc(),
+            ));
+        }
+
+        Err(FilterParseError::InvalidTime(s.to_string()))
+    }
+
+    /// 计算实际时间点
+    ///
+    /// 超出时间范围的相对时间取最早（或最晚）可表示的时间点，不会 panic。
+    pub fn resolve(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        match self {
+            TimeBound::Relative(seconds) => TimeDelta::try_seconds(*seconds)
+                .and_then(|delta| now.checked_sub_signed(delta))
+                .unwrap_or(if *seconds > 0 {
+                    DateTime::<Utc>::MIN_UTC
+                } else {
+                    DateTime::<Utc>::MAX_UTC
+                }),
+            TimeBound::Absolute(dt) => *dt,
+        }
+    }
+}
+
+impl fmt::Display for TimeBound {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TimeBound::Relative(seconds) => {
+                let units = [(7 * 86400, 'w'), (86400, 'd'), (3600, 'h'), (60, 'm')];
+                for (size, unit) in units {
+                    if *seconds != 0 && seconds % size == 0 {
+                        return write!(f, "{}{}", seconds / size, unit);
+                    }
+                }
+                write!(f, "{}s", seconds)
+            }
+            TimeBound::Absolute(dt) => write!(f, "{}", dt.to_rfc3339()),
+        }
+    }
+}
+
+// ============================================================================
+// JSON 路径断言
+// ============================================================================
+
+/// JSON 路径根
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JsonRoot {
+    /// 请求体
+    Request,
+    /// 响应体
+    Response,
+}
+
+/// JSON 路径片段
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum JsonPathSegment {
+    /// 对象字段
+    Key(String),
+    /// 数组下标
+    Index(usize),
+    /// 所有数组元素或对象值 (`[*]` / `.*`)
+    Wildcard,
+}
+
+/// JSON 路径，如 `request.tools[*].name`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JsonPath {
+    pub root: JsonRoot,
+    pub segments: Vec<JsonPathSegment>,
+}
+
+impl JsonPath {
+    /// 解析 JSON 路径
+    pub fn parse(path: &str) -> Result<Self, FilterParseError> {
+        let invalid = || FilterParseError::InvalidJsonPath(path.to_string());
+
+        let root_end = path.find(['.', '[']).unwrap_or(path.len());
+        let root = match path[..root_end].to_lowercase().as_str() {
+            "request" | "req" => JsonRoot::Request,
+            "response" | "resp" => JsonRoot::Response,
+            _ => return Err(invalid()),
+        };
+
+        let mut segments = Vec::new();
+        let mut rest = &path[root_end..];
+        while !rest.is_empty() {
+            if let Some(after_dot) = rest.strip_prefix('.') {
+                let end = after_dot.find(['.', '[']).unwrap_or(after_dot.len());
+                let key = &after_dot[..end];
+                if key.is_empty() {
+                    return Err(invalid());
+                }
+                segments.push(if key == "*" {
+                    JsonPathSegment::Wildcard
+                } else {
+                    JsonPathSegment::Key(key.to_string())
+                });
+                rest = &after_dot[end..];
+            } else if let Some(after_bracket) = rest.strip_prefix('[') {
+                let end = after_bracket.find(']').ok_or_else(invalid)?;
+                let inner = after_bracket[..end].trim();
+                let segment = if inner == "*" {
+                    JsonPathSegment::Wildcard
+                } else if let Ok(index) = inner.parse::<usize>() {
+                    JsonPathSegment::Index(index)
+                } else if inner.len() >= 2
+                    && (inner.starts_with('"') && inner.ends_with('"')
+                        || inner.starts_with('\'') && inner.ends_with('\''))
+                {
+                    JsonPathSegment::Key(inner[1..inner.len() - 1].to_string())
+                } else {
+                    return Err(invalid());
+                };
+                segments.push(segment);
+                rest = &after_bracket[end + 1..];
+            } else {
+                return Err(invalid());
+            }
+        }
+
+        Ok(Self { root, segments })
+    }
+
+    /// 选出路径匹配的所有值
+    pub fn select<'a>(&self, value: &'a serde_json::Value) -> Vec<&'a serde_json::Value> {
+        let mut current = vec![value];
+        for segment in &self.segments {
+            let mut next = Vec::new();
+            for value in current {
+                match segment {
+                    JsonPathSegment::Key(key) => next.extend(value.get(key.as_str())),
+                    JsonPathSegment::Index(index) => next.extend(value.get(*index)),
+                    JsonPathSegment::Wildcard => match value {
+                        serde_json::Value::Array(items) => next.extend(items.iter()),
+                        serde_json::Value::Object(map) => next.extend(map.values()),
+                        _ => {}
+                    },
+                }
+            }
+            current = next;
+        }
+        current
+    }
+}
+
+impl fmt::Display for JsonPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.root {
+            JsonRoot::Request => write!(f, "request")?,
+            JsonRoot::Response => write!(f, "response")?,
+        }
+        for segment in &self.segments {
+            match segment {
+                JsonPathSegment::Key(key) => write!(f, ".{}", key)?,
+                JsonPathSegment::Index(index) => write!(f, "[{}]", index)?,
+                JsonPathSegment::Wildcard => write!(f, "[*]")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// JSON 断言运算符
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum JsonOp {
+    /// 等于 (==)
+    Eq,
+    /// 不等于 (!=)
+    Ne,
+    /// 大于 (>)
+    Gt,
+    /// 大于等于 (>=)
+    Gte,
+    /// 小于 (<)
+    Lt,
+    /// 小于等于 (<=)
+    Lte,
+    /// 正则匹配 (~=)
+    Matches,
+}
+
+impl fmt::Display for JsonOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            JsonOp::Eq => "==",
+            JsonOp::Ne => "!=",
+            JsonOp::Gt => ">",
+            JsonOp::Gte => ">=",
+            JsonOp::Lt => "<",
+            JsonOp::Lte => "<=",
+            JsonOp::Matches => "~=",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+/// JSON 路径断言
+///
+/// 没有运算符时断言路径存在且不为 null；有运算符时只要任一匹配值满足即成立。
+/// `~=` 的正则在构造时编译，无效的正则在解析阶段报错。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "JsonPredicateFields")]
+pub struct JsonPredicate {
+    pub path: JsonPath,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub op: Option<JsonOp>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub value: Option<serde_json::Value>,
+    /// `~=` 运算符的已编译正则
+    #[serde(skip)]
+    regex: Option<Regex>,
+}
+
+/// `JsonPredicate` 的序列化字段（反序列化时经 `JsonPredicate::new` 重新编译正则）
+#[derive(Deserialize)]
+struct JsonPredicateFields {
+    path: JsonPath,
+    #[serde(default)]
+    op: Option<JsonOp>,
+    #[serde(default)]
+    value: Option<serde_json::Value>,
+}
+
+impl TryFrom<JsonPredicateFields> for JsonPredicate {
+    type Error = FilterParseError;
+
+    fn try_from(fields: JsonPredicateFields) -> Result<Self, Self::Error> {
+        Self::new(fields.path, fields.op, fields.value)
+    }
+}
+
+impl PartialEq for JsonPredicate {
+    fn eq(&self, other: &Self) -> bool {
+        self.path == other.path && self.op == other.op && self.value == other.value
+    }
+}
+
+impl JsonPredicate {
+    /// 创建断言，`~=` 运算符的正则在此编译
+    pub fn new(
+        path: JsonPath,
+        op: Option<JsonOp>,
+        value: Option<serde_json::Value>,
+    ) -> Result<Self, FilterParseError> {
+        let regex = match (op, &value) {
+            (Some(JsonOp::Matches), Some(pattern)) => Some(
+                Regex::new(&json_text(pattern))
+                    .map_err(|e| FilterParseError::InvalidRegex(e.to_string()))?,
+            ),
+            _ => None,
+        };
+        Ok(Self {
+            path,
+            op,
+            value,
+            regex,
+        })
+    }
+
+    /// 对 Flow 求值
+    pub fn matches(&self, flow: &LLMFlow) -> bool {
+        let body = match self.path.root {
+            JsonRoot::Request => &flow.request.body,
+            JsonRoot::Response => match flow.response.as_ref() {
+                Some(response) => &response.body,
+                None => return false,
+            },
+        };
+
+        let selected = self.path.select(body);
+        match (&self.op, &self.value) {
+            (Some(op), Some(expected)) => selected
+                .iter()
+                .any(|actual| self.compare(*op, actual, expected)),
+            _ => selected.iter().any(|v| !v.is_null()),
+        }
+    }
+
+    fn compare(
+        &self,
+        op: JsonOp,
+        actual: &serde_json::Value,
+        expected: &serde_json::Value,
+    ) -> bool {
+        match op {
+            JsonOp::Eq => Self::json_eq(actual, expected),
+            JsonOp::Ne => !Self::json_eq(actual, expected),
+            JsonOp::Matches => self
+                .regex
+                .as_ref()
+                .is_some_and(|re| re.is_match(&json_text(actual))),
+            JsonOp::Gt | JsonOp::Gte | JsonOp::Lt | JsonOp::Lte => {
+                match (actual.as_f64(), expected.as_f64()) {
+                    (Some(a), Some(e)) => match op {
+                        JsonOp::Gt => a > e,
+                        JsonOp::Gte => a >= e,
+                        JsonOp::Lt => a < e,
+                        _ => a <= e,
+                    },
+                    _ => false,
+                }
+            }
+        }
+    }
+
+    fn json_eq(actual: &serde_json::Value, expected: &serde_json::Value) -> bool {
+        match (actual.as_f64(), expected.as_f64()) {
+            (Some(a), Some(e)) => a == e,
+            _ => actual == expected,
+        }
+    }
+}
+
+/// JSON 值的文本形式（字符串取原文，其他值取 JSON 表示）
+fn json_text(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+impl fmt::Display for JsonPredicate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.path)?;
+        if let (Some(op), Some(value)) = (&self.op, &self.value) {
+            write!(f, " {} {}", op, value)?;
+        }
+        Ok(())
+    }
+}
+
 // ============================================================================
 // AST 表达式
 // ============================================================================
@@ -356,6 +854,105 @@ impl<'a> Lexer<'a> {
         Ok(word)
     }
 
+    /// 读取原始参数（可能带引号），直到空白或逻辑运算符
+    ///
+    /// 用于时间和 JSON 路径等包含 `:`、`[`、`+` 等字符的参数。
+    fn read_raw_argument(&mut self) -> Result<String, FilterParseError> {
+        self.skip_whitespace();
+
+        if let Some(&(_, c)) = self.chars.peek() {
+            if c == '"' || c == '\'' {
+                return self.read_quoted_string(c);
+            }
+        }
+
+        let mut word = String::new();
+        while let Some(&(_, c)) = self.chars.peek() {
+            if c.is_whitespace() || matches!(c, '&' | '|' | '(' | ')') {
+                break;
+            }
+            word.push(c);
+            self.chars.next();
+        }
+        if word.is_empty() {
+            return Err(FilterParseError::UnexpectedEof);
+        }
+        Ok(word)
+    }
+
+    /// 读取 JSON 断言运算符（没有运算符时返回 None）
+    fn read_json_op(&mut self) -> Option<JsonOp> {
+        self.skip_whitespace();
+
+        let pos = self.chars.peek()?.0;
+        let rest = &self.input[pos..];
+        let (op, len) = [
+            ("==", JsonOp::Eq),
+            ("!=", JsonOp::Ne),
+            (">=", JsonOp::Gte),
+            ("<=", JsonOp::Lte),
+            ("~=", JsonOp::Matches),
+            (">", JsonOp::Gt),
+            ("<", JsonOp::Lt),
+            ("=", JsonOp::Eq),
+        ]
+        .into_iter()
+        .find(|(symbol, _)| rest.starts_with(symbol))
+        .map(|(symbol, op)| (op, symbol.len()))?;
+
+        for _ in 0..len {
+            self.chars.next();
+        }
+        Some(op)
+    }
+
+    /// 读取 JSON 断言的比较值
+    ///
+    /// 带引号的参数始终作为字符串；否则尝试解析为数字、布尔或 null。
+    fn read_json_value(&mut self) -> Result<serde_json::Value, FilterParseError> {
+        self.skip_whitespace();
+
+        if let Some(&(_, c)) = self.chars.peek() {
+            if c == '"' || c == '\'' {
+                return Ok(serde_json::Value::String(self.read_quoted_string(c)?));
+            }
+        }
+
+        let word = self.read_raw_argument()?;
+        match serde_json::from_str::<serde_json::Value>(&word) {
+            Ok(value) if !value.is_object() && !value.is_array() && !value.is_string() => Ok(value),
+            _ => Ok(serde_json::Value::String(word)),
+        }
+    }
+
+    /// 解析 HTTP 状态码参数（`429`、`>=500` 或 `4xx`）
+    fn parse_status(&mut self) -> Result<FilterToken, FilterParseError> {
+        self.skip_whitespace();
+
+        if let Some(&(_, c)) = self.chars.peek() {
+            if c.is_ascii_digit() {
+                let word = self.read_word().to_lowercase();
+                if let Some(class) = word.strip_suffix("xx") {
+                    return class
+                        .parse::<u16>()
+                        .ok()
+                        .filter(|class| (1..=5).contains(class))
+                        .map(FilterToken::StatusClass)
+                        .ok_or(FilterParseError::InvalidNumber(word.clone()));
+                }
+                let value = word
+                    .parse::<i64>()
+                    .map_err(|_| FilterParseError::InvalidNumber(word.clone()))?;
+                return Ok(FilterToken::Status(Comparison {
+                    op: ComparisonOp::Eq,
+                    value,
+                }));
+            }
+        }
+
+        Ok(FilterToken::Status(self.parse_comparison("status")?))
+    }
+
     /// 解析比较运算符和数值
     fn parse_comparison(&mut self, filter_name: &str) -> Result<Comparison, FilterParseError> {
         self.skip_whitespace();
@@ -423,6 +1020,16 @@ impl<'a> Lexer<'a> {
             }
         }
 
+        // 费用以美元为单位，内部按百万分之一美元存储
+        if filter_name == "cost" {
+            return s
+                .parse::<f64>()
+                .ok()
+                .filter(|v| v.is_finite())
+                .map(|v| (v * 1_000_000.0).round() as i64)
+                .ok_or(FilterParseError::InvalidNumber(s));
+        }
+
         // 尝试直接解析为数字
         s.parse::<i64>()
             .map_err(|_| FilterParseError::InvalidNumber(s))
@@ -481,6 +1088,44 @@ impl<'a> Lexer<'a> {
                 let comparison = self.parse_comparison("latency")?;
                 Ok(FilterToken::Latency(comparison))
             }
+            "status" => self.parse_status(),
+            "cost" => {
+                let comparison = self.parse_comparison("cost")?;
+                Ok(FilterToken::Cost(comparison))
+            }
+            "since" => {
+                let time = self.read_raw_argument()?;
+                Ok(FilterToken::Since(TimeBound::parse(&time)?))
+            }
+            "until" => {
+                let time = self.read_raw_argument()?;
+                Ok(FilterToken::Until(TimeBound::parse(&time)?))
+            }
+            "cred" => {
+                let pattern = self.read_argument()?;
+                Ok(FilterToken::Credential(pattern))
+            }
+            "client" => {
+                let client = self.read_argument()?;
+                Ok(FilterToken::Client(parse_client_type(&client)?))
+            }
+            "stop" => {
+                let reason = self.read_argument()?;
+                Ok(FilterToken::StopReason(normalize_stop_reason(&reason)))
+            }
+            "json" => {
+                let path = JsonPath::parse(&self.read_raw_argument()?)?;
+                let op = self.read_json_op();
+                let value = match op {
+                    Some(_) => Some(self.read_json_value()?),
+                    None => None,
+                };
+                Ok(FilterToken::Json(JsonPredicate::new(path, op, value)?))
+            }
+            "view" => {
+                let name = self.read_argument()?;
+                Ok(FilterToken::View(name))
+            }
             _ => Err(FilterParseError::UnknownFilter(filter_name)),
         }
     }
@@ -690,14 +1335,127 @@ impl FilterParser {
         Ok(())
     }
 
+    /// 展开表达式中的视图引用 (`~view <name>`)
+    ///
+    /// `lookup` 根据视图名称返回其过滤表达式；被引用的视图可以继续引用其他视图，
+    /// 循环引用或引用不存在的视图会返回错误。
+    pub fn expand_views<F>(expr: &FilterExpr, lookup: &F) -> Result<FilterExpr, FilterParseError>
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        let mut stack = Vec::new();
+        Self::expand_views_inner(expr, lookup, &mut stack)
+    }
+
+    fn expand_views_inner<F>(
+        expr: &FilterExpr,
+        lookup: &F,
+        stack: &mut Vec<String>,
+    ) -> Result<FilterExpr, FilterParseError>
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        match expr {
+            FilterExpr::Token(FilterToken::View(name)) => {
+                if stack.iter().any(|n| n == name) {
+                    let mut chain = stack.clone();
+                    chain.push(name.clone());
+                    return Err(FilterParseError::CircularViewReference(chain.join(" -> ")));
+                }
+                let source =
+                    lookup(name).ok_or_else(|| FilterParseError::UnknownView(name.clone()))?;
+                let parsed = Self::parse(&source)?;
+
+                stack.push(name.clone());
+                let expanded = Self::expand_views_inner(&parsed, lookup, stack);
+                stack.pop();
+                expanded
+            }
+            FilterExpr::Token(token) => Ok(FilterExpr::Token(token.clone())),
+            FilterExpr::And(left, right) => Ok(FilterExpr::And(
+                Box::new(Self::expand_views_inner(left, lookup, stack)?),
+                Box::new(Self::expand_views_inner(right, lookup, stack)?),
+            )),
+            FilterExpr::Or(left, right) => Ok(FilterExpr::Or(
+                Box::new(Self::expand_views_inner(left, lookup, stack)?),
+                Box::new(Self::expand_views_inner(right, lookup, stack)?),
+            )),
+            FilterExpr::Not(inner) => Ok(FilterExpr::Not(Box::new(Self::expand_views_inner(
+                inner, lookup, stack,
+            )?))),
+        }
+    }
+
+    /// 表达式是否包含视图引用
+    pub fn has_view_references(expr: &FilterExpr) -> bool {
+        match expr {
+            FilterExpr::Token(token) => matches!(token, FilterToken::View(_)),
+            FilterExpr::And(left, right) | FilterExpr::Or(left, right) => {
+                Self::has_view_references(left) || Self::has_view_references(right)
+            }
+            FilterExpr::Not(inner) => Self::has_view_references(inner),
+        }
+    }
+
+    /// 表达式直接引用的视图名称
+    pub fn view_references(expr: &FilterExpr) -> Vec<&str> {
+        match expr {
+            FilterExpr::Token(FilterToken::View(name)) => vec![name.as_str()],
+            FilterExpr::Token(_) => Vec::new(),
+            FilterExpr::And(left, right) | FilterExpr::Or(left, right) => {
+                let mut names = Self::view_references(left);
+                names.extend(Self::view_references(right));
+                names
+            }
+            FilterExpr::Not(inner) => Self::view_references(inner),
+        }
+    }
+
     /// 将 FilterExpr 编译为可执行的过滤函数
+    ///
+    /// 用于长期持有的过滤器（拦截、保留规则），相对时间在每次调用时按当前时间计算。
+    /// 一次性查询请使用 `compile_at`。
     pub fn compile(expr: &FilterExpr) -> Box<dyn Fn(&LLMFlow) -> bool + Send + Sync> {
         let expr = expr.clone();
         Box::new(move |flow| Self::evaluate(&expr, flow))
     }
 
+    /// 以 `now` 为基准编译过滤函数，相对时间只计算一次
+    pub fn compile_at(
+        expr: &FilterExpr,
+        now: DateTime<Utc>,
+    ) -> Box<dyn Fn(&LLMFlow) -> bool + Send + Sync> {
+        let expr = Self::resolve_time_bounds(expr, now);
+        Box::new(move |flow| Self::evaluate(&expr, flow))
+    }
+
+    /// 将表达式中的相对时间替换为以 `now` 为基准的绝对时间
+    pub fn resolve_time_bounds(expr: &FilterExpr, now: DateTime<Utc>) -> FilterExpr {
+        let resolve = |bound: &TimeBound| TimeBound::Absolute(bound.resolve(now));
+        match expr {
+            FilterExpr::Token(FilterToken::Since(bound)) => {
+                FilterExpr::Token(FilterToken::Since(resolve(bound)))
+            }
+            FilterExpr::Token(FilterToken::Until(bound)) => {
+                FilterExpr::Token(FilterToken::Until(resolve(bound)))
+            }
+            FilterExpr::Token(token) => FilterExpr::Token(token.clone()),
+            FilterExpr::And(left, right) => FilterExpr::And(
+                Box::new(Self::resolve_time_bounds(left, now)),
+                Box::new(Self::resolve_time_bounds(right, now)),
+            ),
+            FilterExpr::Or(left, right) => FilterExpr::Or(
+                Box::new(Self::resolve_time_bounds(left, now)),
+                Box::new(Self::resolve_time_bounds(right, now)),
+            ),
+            FilterExpr::Not(inner) => {
+                FilterExpr::Not(Box::new(Self::resolve_time_bounds(inner, now)))
+            }
+        }
+    }
+
     /// 评估表达式
-    fn evaluate(expr: &FilterExpr, flow: &LLMFlow) -> bool {
+    pub(crate) fn evaluate(expr: &FilterExpr, flow: &LLMFlow) -> bool {
         match expr {
             FilterExpr::Token(token) => Self::evaluate_token(token, flow),
             FilterExpr::And(left, right) => {
@@ -783,6 +1541,36 @@ impl FilterParser {
             FilterToken::Latency(comparison) => {
                 comparison.compare(flow.timestamps.duration_ms as i64)
             }
+            FilterToken::Status(comparison) => {
+                Self::status_code(flow).is_some_and(|code| comparison.compare(code as i64))
+            }
+            FilterToken::StatusClass(class) => {
+                Self::status_code(flow).is_some_and(|code| code / 100 == *class)
+            }
+            FilterToken::Cost(comparison) => estimate_flow_cost(flow)
+                .is_some_and(|cost| comparison.compare((cost * 1_000_000.0).round() as i64)),
+            FilterToken::Since(bound) => flow.timestamps.created >= bound.resolve(Utc::now()),
+            FilterToken::Until(bound) => flow.timestamps.created <= bound.resolve(Utc::now()),
+            FilterToken::Credential(pattern) => {
+                let metadata = &flow.metadata;
+                metadata
+                    .credential_id
+                    .iter()
+                    .chain(metadata.credential_name.iter())
+                    .any(|value| Self::match_pattern(pattern, value))
+            }
+            FilterToken::Client(client) => {
+                let user_agent = flow.metadata.client_info.user_agent.as_deref();
+                ClientType::from_user_agent(user_agent.unwrap_or_default()) == *client
+            }
+            FilterToken::StopReason(reason) => flow
+                .response
+                .as_ref()
+                .and_then(|r| r.stop_reason.as_ref())
+                .is_some_and(|actual| stop_reason_name(actual) == *reason),
+            FilterToken::Json(predicate) => predicate.matches(flow),
+            // 未展开的视图引用不匹配任何 Flow
+            FilterToken::View(_) => false,
             // 逻辑运算符和括号不应该在这里出现
             FilterToken::And
             | FilterToken::Or
@@ -792,6 +1580,15 @@ impl FilterParser {
         }
     }
 
+    /// 获取 Flow 的 HTTP 状态码（优先取响应，其次取错误）
+    fn status_code(flow: &LLMFlow) -> Option<u16> {
+        flow.response
+            .as_ref()
+            .map(|r| r.status_code)
+            .filter(|code| *code != 0)
+            .or_else(|| flow.error.as_ref().and_then(|e| e.status_code))
+    }
+
     /// 模式匹配（支持 * 通配符）
     fn match_pattern(pattern: &str, text: &str) -> bool {
         if pattern == "*" {
@@ -886,6 +1683,31 @@ pub const FILTER_HELP: &[(&str, &str)] = &[
     ("~bs <regex>", "响应内容匹配（正则表达式）"),
     ("~tokens <op> <n>", "Token 数量比较 (>, >=, <, <=, =)"),
     ("~latency <op> <n>", "延迟比较 (支持 s/ms 后缀)"),
+    ("~since <time>", "创建时间不早于（如 2h、7d、2024-01-01）"),
+    (
+        "~until <time>",
+        "创建时间不晚于（如 30m、2024-01-01T12:00:00Z）",
+    ),
+    ("~cred <pattern>", "凭证 ID 或名称匹配（支持 * 通配符）"),
+    (
+        "~client <type>",
+        "客户端类型 (cursor/claude_code/codex/windsurf/kiro/other)",
+    ),
+    (
+        "~stop <reason>",
+        "停止原因 (stop/length/tool_calls/end_turn/...)",
+    ),
+    ("~status <n|op n|4xx>", "HTTP 状态码"),
+    ("~cost <op> <usd>", "估算费用比较（美元）"),
+    (
+        "~json <path> [op value]",
+        "请求/响应体 JSON 路径断言 (==, !=, >, >=, <, <=, ~=)",
+    ),
+    ("~view <name>", "引用已保存的快速过滤器"),
+    (
+        "group by <fields> | <aggs>",
+        "按字段分组聚合 (count/avg/sum/min/max/p50/p95/p99)",
+    ),
     ("&", "AND 逻辑"),
     ("|", "OR 逻辑"),
     ("!", "NOT 逻辑"),
@@ -904,6 +1726,9 @@ pub fn get_filter_help() -> String {
     help.push_str("  ~e | ~latency >5s      有错误或延迟超过 5 秒\n");
     help.push_str("  !~e                    没有错误\n");
     help.push_str("  (~p kiro | ~p gemini) & ~tokens >1000\n");
+    help.push_str("  ~since 2h & ~status 5xx\n");
+    help.push_str("  ~json request.tools[*].name == \"bash\"\n");
+    help.push_str("  ~since 1d group by model | count, avg latency, p95 latency\n");
     help
 }
 
@@ -1251,6 +2076,231 @@ mod tests {
         let reparsed = FilterParser::parse(&display).unwrap();
         assert_eq!(format!("{}", expr), format!("{}", reparsed));
     }
+
+    #[test]
+    fn test_parse_time_bounds() {
+        let expr = FilterParser::parse("~since 2h").unwrap();
+        assert!(matches!(
+            expr,
+            FilterExpr::Token(FilterToken::Since(TimeBound::Relative(7200)))
+        ));
+
+        let expr = FilterParser::parse("~until 2024-01-15").unwrap();
+        assert!(matches!(
+            expr,
+            FilterExpr::Token(FilterToken::Until(TimeBound::Absolute(_)))
+        ));
+
+        let expr = FilterParser::parse("~since \"2024-01-15 08:30\"").unwrap();
+        assert!(matches!(
+            expr,
+            FilterExpr::Token(FilterToken::Since(TimeBound::Absolute(_)))
+        ));
+
+        assert!(matches!(
+            FilterParser::parse("~since yesterday"),
+            Err(FilterParseError::InvalidTime(_))
+        ));
+
+        // 超出范围的相对时间在解析阶段报错
+        for input in [
+            "~since 9999999999999999s",
+            "~since 9223372036854775807w",
+            "~until 99999999999999d",
+        ] {
+            assert!(
+                matches!(
+                    FilterParser::parse(input),
+                    Err(FilterParseError::InvalidTime(_))
+                ),
+                "{}",
+                input
+            );
+        }
+        // 反序列化得到的越界值不会让求值 panic
+        assert_eq!(
+            TimeBound::Relative(i64::MAX).resolve(Utc::now()),
+            DateTime::<Utc>::MIN_UTC
+        );
+    }
+
+    #[test]
+    fn test_compile_at_resolves_relative_bounds_once() {
+        let now = Utc::now();
+        let expr = FilterParser::parse("~since 1h & !~until 2h").unwrap();
+        let resolved = FilterParser::resolve_time_bounds(&expr, now);
+        assert!(!resolved.to_string().contains("1h"));
+        assert!(resolved
+            .to_string()
+            .contains(&(now - chrono::Duration::hours(1)).to_rfc3339()));
+
+        let mut flow = create_test_flow("claude", ProviderType::Kiro);
+        flow.timestamps.created = now - chrono::Duration::minutes(30);
+        assert!(FilterParser::compile_at(&expr, now)(&flow));
+        flow.timestamps.created = now - chrono::Duration::minutes(90);
+        assert!(!FilterParser::compile_at(&expr, now)(&flow));
+    }
+
+    #[test]
+    fn test_evaluate_since_filter() {
+        let mut flow = create_test_flow("claude", ProviderType::Kiro);
+        let filter = FilterParser::compile(&FilterParser::parse("~since 1h").unwrap());
+        assert!(filter(&flow));
+
+        flow.timestamps.created = Utc::now() - chrono::Duration::hours(3);
+        assert!(!filter(&flow));
+
+        let filter = FilterParser::compile(&FilterParser::parse("~until 2h").unwrap());
+        assert!(filter(&flow));
+    }
+
+    #[test]
+    fn test_evaluate_status_filter() {
+        let mut flow = create_test_flow("claude", ProviderType::Kiro);
+        flow.response = Some(LLMResponse {
+            status_code: 429,
+            ..Default::default()
+        });
+
+        let filter = FilterParser::compile(&FilterParser::parse("~status 4xx").unwrap());
+        assert!(filter(&flow));
+        let filter = FilterParser::compile(&FilterParser::parse("~status 429").unwrap());
+        assert!(filter(&flow));
+        let filter = FilterParser::compile(&FilterParser::parse("~status >=500").unwrap());
+        assert!(!filter(&flow));
+    }
+
+    #[test]
+    fn test_evaluate_cost_filter() {
+        let mut flow = create_test_flow("claude-sonnet-4", ProviderType::Kiro);
+        flow.response = Some(LLMResponse {
+            usage: TokenUsage {
+                input_tokens: 10_000,
+                output_tokens: 1_000,
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+
+        // 10k * $3/M + 1k * $15/M = $0.045
+        let filter = FilterParser::compile(&FilterParser::parse("~cost >0.04").unwrap());
+        assert!(filter(&flow));
+        let filter = FilterParser::compile(&FilterParser::parse("~cost >0.05").unwrap());
+        assert!(!filter(&flow));
+    }
+
+    #[test]
+    fn test_evaluate_metadata_filters() {
+        let mut flow = create_test_flow("claude", ProviderType::Kiro);
+        flow.metadata.credential_name = Some("work-account".to_string());
+        flow.metadata.client_info.user_agent = Some("claude-code/1.0".to_string());
+        flow.response = Some(LLMResponse {
+            stop_reason: Some(StopReason::Length),
+            ..Default::default()
+        });
+
+        let filter = FilterParser::compile(&FilterParser::parse("~cred work").unwrap());
+        assert!(filter(&flow));
+        let filter = FilterParser::compile(&FilterParser::parse("~client claude-code").unwrap());
+        assert!(filter(&flow));
+        let filter = FilterParser::compile(&FilterParser::parse("~client cursor").unwrap());
+        assert!(!filter(&flow));
+        let filter = FilterParser::compile(&FilterParser::parse("~stop max_tokens").unwrap());
+        assert!(filter(&flow));
+
+        assert!(matches!(
+            FilterParser::parse("~client vim"),
+            Err(FilterParseError::InvalidClientType(_))
+        ));
+    }
+
+    #[test]
+    fn test_evaluate_json_filter() {
+        let mut flow = create_test_flow("claude", ProviderType::Kiro);
+        flow.request.body = serde_json::json!({
+            "temperature": 0.7,
+            "tools": [{"name": "read_file"}, {"name": "bash"}]
+        });
+
+        let cases = [
+            ("~json request.tools[*].name == \"bash\"", true),
+            ("~json request.tools[0].name == \"bash\"", false),
+            ("~json request.temperature > 0.5", true),
+            ("~json request.tools[1].name ~= ^ba", true),
+            ("~json request.tool_choice", false),
+            ("~json request.tools", true),
+            ("~json response.id", false),
+        ];
+        for (input, expected) in cases {
+            let filter = FilterParser::compile(&FilterParser::parse(input).unwrap());
+            assert_eq!(filter(&flow), expected, "{}", input);
+        }
+
+        assert!(matches!(
+            FilterParser::parse("~json body.tools"),
+            Err(FilterParseError::InvalidJsonPath(_))
+        ));
+        assert!(matches!(
+            FilterParser::parse("~json request.model ~= \"(unclosed\""),
+            Err(FilterParseError::InvalidRegex(_))
+        ));
+
+        // 反序列化时同样编译并校验正则
+        let expr = FilterParser::parse("~json request.tools[1].name ~= ^ba").unwrap();
+        let json = serde_json::to_string(&expr).unwrap();
+        let restored: FilterExpr = serde_json::from_str(&json).unwrap();
+        assert!(FilterParser::compile(&restored)(&flow));
+        let invalid = json.replace("^ba", "(ba");
+        assert!(serde_json::from_str::<FilterExpr>(&invalid).is_err());
+    }
+
+    #[test]
+    fn test_expand_views() {
+        let views: std::collections::HashMap<&str, &str> = [
+            ("errors", "~e | ~status 5xx"),
+            ("kiro errors", "~view errors & ~p kiro"),
+            ("loop a", "~view \"loop b\""),
+            ("loop b", "~view \"loop a\""),
+        ]
+        .into_iter()
+        .collect();
+        let lookup = |name: &str| views.get(name).map(|s| s.to_string());
+
+        let expr = FilterParser::parse("~view \"kiro errors\" & ~m claude").unwrap();
+        assert!(FilterParser::has_view_references(&expr));
+        let expanded = FilterParser::expand_views(&expr, &lookup).unwrap();
+        assert!(!FilterParser::has_view_references(&expanded));
+        assert!(expanded.to_string().contains("~p kiro"));
+
+        let expr = FilterParser::parse("~view \"loop a\"").unwrap();
+        assert!(matches!(
+            FilterParser::expand_views(&expr, &lookup),
+            Err(FilterParseError::CircularViewReference(_))
+        ));
+
+        let expr = FilterParser::parse("~view missing").unwrap();
+        assert!(matches!(
+            FilterParser::expand_views(&expr, &lookup),
+            Err(FilterParseError::UnknownView(_))
+        ));
+    }
+
+    #[test]
+    fn test_round_trip_extended_filters() {
+        let inputs = [
+            "~since 1d & ~until 2024-01-15",
+            "~status 5xx | ~status >=429",
+            "~cost >0.01",
+            "~cred work & ~client claude_code & ~stop length",
+            "~json request.tools[*].name == \"bash\"",
+            "~view \"my errors\"",
+        ];
+        for input in inputs {
+            let expr = FilterParser::parse(input).unwrap();
+            let reparsed = FilterParser::parse(&expr.to_string()).unwrap();
+            assert_eq!(expr, reparsed, "{}", input);
+        }
+    }
 }
 
 // ============================================================================
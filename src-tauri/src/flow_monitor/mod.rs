@@ -13,7 +13,13 @@
 //! - `exporter`: 导出服务，支持 HAR、JSON、JSONL、Markdown、CSV 格式
 //! - `monitor`: 核心监控服务
 //! - `filter_parser`: 高级过滤表达式解析器，支持类似 mitmproxy 的语法
+//! - `redaction`: 捕获时敏感数据脱敏，支持出站请求数据防泄漏
+//! - `thread`: 基于消息前缀匹配的对话线程重建和时间线
+//! - `retention`: 基于过滤表达式的分层保留策略和后台段压缩
+//! - `aggregation`: 过滤表达式的 `group by` 聚合查询
+//! - `pricing`: 按模型标价估算 Flow 费用
 
+pub mod aggregation;
 pub mod batch_ops;
 pub mod bookmark;
 pub mod code_exporter;
@@ -26,11 +32,15 @@ pub mod interceptor;
 pub mod memory_store;
 pub mod models;
 pub mod monitor;
+pub mod pricing;
 pub mod query_service;
 pub mod quick_filter;
+pub mod redaction;
 pub mod replayer;
+pub mod retention;
 pub mod session;
 pub mod stream_rebuilder;
+pub mod thread;
 
 // 重新导出核心类型
 pub use models::{
@@ -54,6 +64,7 @@ pub use models::{
     Message,
     MessageContent,
     MessageRole,
+    RedactionRecord,
     RequestParameters,
     RoutingInfo,
     StopReason,
@@ -75,7 +86,8 @@ pub use memory_store::{FlowFilter, FlowMemoryStore, LatencyRange, TimeRange, Tok
 
 // 重新导出文件存储
 pub use file_store::{
-    CleanupResult, FileStoreError, FlowFileStore, FlowIndexRecord, FtsSearchResult, RotationConfig,
+    CleanupResult, CompactionAction, CompactionResult, DailyStorageUsage, FileStoreError,
+    FlowFileStore, FlowIndexRecord, FtsSearchResult, RotationConfig, StorageUsageReport,
 };
 
 // 重新导出查询服务
@@ -90,6 +102,17 @@ pub use exporter::{
     HarEntry, HarLlmExtension, HarLog, RedactionRule, Redactor,
 };
 
+// 重新导出捕获时脱敏
+pub use redaction::{
+    CaptureRedactionConfig, CaptureRedactor, RedactionConfigError, RedactionMode, SensitiveDataKind,
+};
+
+// 重新导出保留策略
+pub use retention::{
+    strip_flow_bodies, RetentionDecision, RetentionError, RetentionEvaluator, RetentionManager,
+    RetentionPolicy, RetentionRule, RetentionRunReport,
+};
+
 // 重新导出监控服务
 pub use monitor::{
     FlowEvent, FlowMonitor, FlowMonitorConfig, FlowSummary, FlowUpdate, RequestRateTracker,
@@ -99,9 +122,18 @@ pub use monitor::{
 // 重新导出过滤表达式解析器
 pub use filter_parser::{
     get_filter_help, Comparison, ComparisonOp, FilterExpr, FilterParseError, FilterParser,
-    FilterToken, FILTER_HELP,
+    FilterToken, JsonOp, JsonPath, JsonPathSegment, JsonPredicate, JsonRoot, TimeBound,
+    FILTER_HELP,
 };
 
+// 重新导出聚合查询
+pub use aggregation::{
+    AggregateFn, AggregateMetric, AggregateTable, Aggregation, FlowQuery, GroupField,
+};
+
+// 重新导出价格估算
+pub use pricing::{estimate_flow_cost, lookup_pricing, ModelPricing};
+
 // 重新导出拦截器
 pub use interceptor::{
     FlowInterceptor, InterceptAction, InterceptConfig, InterceptEvent, InterceptState,
@@ -123,6 +155,12 @@ pub use session::{
     AutoSessionConfig, FlowSession, SessionError, SessionExportResult, SessionManager,
 };
 
+// 重新导出对话线程重建器
+pub use thread::{
+    ConversationThread, ConversationThreader, ConversationTimeline, ThreadNode, ThreadRelation,
+    ThreadingConfig, TimelineEntry,
+};
+
 // 重新导出快速过滤器管理器
 pub use quick_filter::{
     QuickFilter, QuickFilterError, QuickFilterExport, QuickFilterManager, QuickFilterUpdate,
@@ -36,6 +36,9 @@ pub struct LLMFlow {
     pub state: FlowState,
     /// 用户标记和注释
     pub annotations: FlowAnnotations,
+    /// 捕获时脱敏记录
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub redactions: Vec<RedactionRecord>,
 }
 
 impl LLMFlow {
@@ -65,6 +68,7 @@ impl LLMFlow {
             },
             state: FlowState::Pending,
             annotations: FlowAnnotations::default(),
+            redactions: Vec::new(),
         }
     }
 }
@@ -674,6 +678,20 @@ pub struct FlowAnnotations {
     pub starred: bool,
 }
 
+/// 捕获时脱敏记录
+///
+/// 记录某个检测器在 Flow 的某个位置命中并处理了多少处敏感数据，
+/// 不保存原始值。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RedactionRecord {
+    /// 检测器名称（如 `jwt`、`email`、自定义规则名）
+    pub detector: String,
+    /// 命中位置（如 `request.messages`、`response.body`）
+    pub location: String,
+    /// 命中次数
+    pub count: usize,
+}
+
 // ============================================================================
 // 错误结构
 // ============================================================================
@@ -14,6 +14,7 @@ use chrono::{DateTime, Duration, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, VecDeque};
 use std::sync::Arc;
+use std::time::Instant;
 use tokio::sync::{broadcast, RwLock};
 use uuid::Uuid;
 
@@ -23,6 +24,7 @@ use super::models::{
     FlowAnnotations, FlowError, FlowMetadata, FlowState, FlowType, LLMFlow, LLMRequest,
     LLMResponse, TokenUsage,
 };
+use super::redaction::{CaptureRedactionConfig, CaptureRedactor};
 use super::stream_rebuilder::{StreamFormat, StreamRebuilder};
 
 // ============================================================================
@@ -70,6 +72,9 @@ pub struct FlowMonitorConfig {
     /// 排除的路径列表（支持通配符）
     #[serde(default)]
     pub excluded_paths: Vec<String>,
+    /// 捕获时脱敏配置
+    #[serde(default)]
+    pub capture_redaction: CaptureRedactionConfig,
 }
 
 fn default_enabled() -> bool {
@@ -119,6 +124,7 @@ impl Default for FlowMonitorConfig {
             sampling_rate: default_sampling_rate(),
             excluded_models: Vec::new(),
             excluded_paths: Vec::new(),
+            capture_redaction: CaptureRedactionConfig::default(),
         }
     }
 }
@@ -722,8 +728,27 @@ pub enum FlowEvent {
     ///
     /// **Validates: Requirements 10.7**
     RequestRateUpdate { rate: f64, count: usize },
+    /// 流式响应 chunk
+    FlowStreamChunk {
+        id: String,
+        /// chunk 序号（从 0 开始）
+        index: u32,
+        /// 本 chunk 新增的文本内容
+        content_delta: Option<String>,
+        /// 当前累计内容长度
+        content_length: usize,
+    },
 }
 
+/// 流式 chunk 事件的最小发送间隔，间隔内的增量合并发送
+const STREAM_CHUNK_INTERVAL: std::time::Duration = std::time::Duration::from_millis(100);
+
+/// 合并的增量超过该长度（字节）时立即发送
+const STREAM_CHUNK_MAX_PENDING: usize = 4096;
+
+/// 生命周期事件和流式 chunk 事件的通道容量
+const EVENT_CHANNEL_CAPACITY: usize = 1000;
+
 // ============================================================================
 // 活跃 Flow 状态
 // ============================================================================
@@ -738,6 +763,27 @@ struct ActiveFlow {
     stream_rebuilder: Option<StreamRebuilder>,
     /// 请求开始时间
     request_start: DateTime<Utc>,
+    /// 尚未发送的流式增量
+    pending_delta: String,
+    /// 上次发送流式 chunk 事件的时间
+    last_chunk_sent: Option<Instant>,
+}
+
+impl ActiveFlow {
+    fn new(flow: LLMFlow) -> Self {
+        Self {
+            flow,
+            stream_rebuilder: None,
+            request_start: Utc::now(),
+            pending_delta: String::new(),
+            last_chunk_sent: None,
+        }
+    }
+}
+
+/// 增量末尾可能属于密钥、令牌等敏感内容的字符
+fn is_token_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || "-_.+/=@:".contains(c)
 }
 
 // ============================================================================
@@ -758,12 +804,16 @@ pub struct FlowMonitor {
     active_flows: RwLock<HashMap<String, ActiveFlow>>,
     /// 事件发送器
     event_sender: broadcast::Sender<FlowEvent>,
+    /// 流式 chunk 事件发送器（与生命周期事件分开，chunk 积压不会挤掉完成 / 失败事件）
+    chunk_sender: broadcast::Sender<FlowEvent>,
     /// 阈值配置
     threshold_config: RwLock<ThresholdConfig>,
     /// 请求速率追踪器
     rate_tracker: RwLock<RequestRateTracker>,
     /// 通知配置
     notification_config: RwLock<NotificationConfig>,
+    /// 捕获时脱敏器（未启用时为 None）
+    capture_redactor: RwLock<Option<Arc<CaptureRedactor>>>,
 }
 
 impl FlowMonitor {
@@ -774,7 +824,10 @@ impl FlowMonitor {
     /// - `file_store`: 文件存储（可选）
     pub fn new(config: FlowMonitorConfig, file_store: Option<Arc<FlowFileStore>>) -> Self {
         let memory_store = Arc::new(RwLock::new(FlowMemoryStore::new(config.max_memory_flows)));
-        let (event_sender, _) = broadcast::channel(1000);
+        let (event_sender, _) = broadcast::channel(EVENT_CHANNEL_CAPACITY);
+        let (chunk_sender, _) = broadcast::channel(EVENT_CHANNEL_CAPACITY);
+        let capture_redactor =
+            CaptureRedactor::from_config(&config.capture_redaction).map(Arc::new);
 
         Self {
             config: RwLock::new(config),
@@ -782,9 +835,11 @@ impl FlowMonitor {
             file_store,
             active_flows: RwLock::new(HashMap::new()),
             event_sender,
+            chunk_sender,
             threshold_config: RwLock::new(ThresholdConfig::default()),
             rate_tracker: RwLock::new(RequestRateTracker::default()),
             notification_config: RwLock::new(NotificationConfig::default()),
+            capture_redactor: RwLock::new(capture_redactor),
         }
     }
 
@@ -802,7 +857,10 @@ impl FlowMonitor {
         notification_config: NotificationConfig,
     ) -> Self {
         let memory_store = Arc::new(RwLock::new(FlowMemoryStore::new(config.max_memory_flows)));
-        let (event_sender, _) = broadcast::channel(1000);
+        let (event_sender, _) = broadcast::channel(EVENT_CHANNEL_CAPACITY);
+        let (chunk_sender, _) = broadcast::channel(EVENT_CHANNEL_CAPACITY);
+        let capture_redactor =
+            CaptureRedactor::from_config(&config.capture_redaction).map(Arc::new);
 
         Self {
             config: RwLock::new(config),
@@ -810,9 +868,11 @@ impl FlowMonitor {
             file_store,
             active_flows: RwLock::new(HashMap::new()),
             event_sender,
+            chunk_sender,
             threshold_config: RwLock::new(threshold_config),
             rate_tracker: RwLock::new(RequestRateTracker::default()),
             notification_config: RwLock::new(notification_config),
+            capture_redactor: RwLock::new(capture_redactor),
         }
     }
 
@@ -830,7 +890,10 @@ impl FlowMonitor {
         notification_config: NotificationConfig,
     ) -> Self {
         let memory_store = Arc::new(RwLock::new(FlowMemoryStore::new(config.max_memory_flows)));
-        let (event_sender, _) = broadcast::channel(1000);
+        let (event_sender, _) = broadcast::channel(EVENT_CHANNEL_CAPACITY);
+        let (chunk_sender, _) = broadcast::channel(EVENT_CHANNEL_CAPACITY);
+        let capture_redactor =
+            CaptureRedactor::from_config(&config.capture_redaction).map(Arc::new);
 
         Self {
             config: RwLock::new(config),
@@ -838,9 +901,11 @@ impl FlowMonitor {
             file_store,
             active_flows: RwLock::new(HashMap::new()),
             event_sender,
+            chunk_sender,
             threshold_config: RwLock::new(threshold_config),
             rate_tracker: RwLock::new(RequestRateTracker::default()),
             notification_config: RwLock::new(notification_config),
+            capture_redactor: RwLock::new(capture_redactor),
         }
     }
 
@@ -871,9 +936,66 @@ impl FlowMonitor {
             *store = FlowMemoryStore::new(config.max_memory_flows);
         }
 
+        // 重新编译捕获时脱敏器
+        *self.capture_redactor.write().await =
+            CaptureRedactor::from_config(&config.capture_redaction).map(Arc::new);
+
         *current = config;
     }
 
+    /// 获取捕获时脱敏配置
+    pub async fn capture_redaction_config(&self) -> CaptureRedactionConfig {
+        self.config.read().await.capture_redaction.clone()
+    }
+
+    /// 更新捕获时脱敏配置
+    pub async fn update_capture_redaction_config(&self, redaction: CaptureRedactionConfig) {
+        let mut config = self.config.read().await.clone();
+        config.capture_redaction = redaction;
+        self.update_config(config).await;
+    }
+
+    /// 在 Flow 写入存储之前应用捕获时脱敏
+    async fn apply_capture_redaction(&self, flow: &mut LLMFlow) {
+        let redactor = self.capture_redactor.read().await.clone();
+        if let Some(redactor) = redactor {
+            let count = redactor.scrub_flow(flow);
+            if count > 0 {
+                tracing::debug!("[FLOW_MONITOR] Flow {} 捕获时脱敏 {} 处", flow.id, count);
+            }
+        }
+    }
+
+    /// 对发往上游的请求体应用数据防泄漏脱敏
+    ///
+    /// 仅在捕获时脱敏启用且配置了 `apply_to_outbound` 时生效。
+    /// 命中记录会追加到对应活跃 Flow 的脱敏记录中。
+    ///
+    /// # 参数
+    /// - `flow_id`: 关联的 Flow ID（可选）
+    /// - `body`: 即将发送的请求体
+    ///
+    /// # 返回
+    /// - `Some(body)`: 有命中，返回脱敏后的请求体
+    /// - `None`: 未启用或没有命中，应按原样发送
+    pub async fn scrub_outbound_body(
+        &self,
+        flow_id: Option<&str>,
+        body: &serde_json::Value,
+    ) -> Option<serde_json::Value> {
+        let redactor = self.capture_redactor.read().await.clone()?;
+        let (scrubbed, records) = redactor.scrub_outbound(body)?;
+
+        if let Some(flow_id) = flow_id {
+            let mut active = self.active_flows.write().await;
+            if let Some(active_flow) = active.get_mut(flow_id) {
+                active_flow.flow.redactions.extend(records);
+            }
+        }
+
+        Some(scrubbed)
+    }
+
     /// 获取阈值配置
     ///
     /// **Validates: Requirements 10.3, 10.4**
@@ -1038,6 +1160,14 @@ impl FlowMonitor {
         self.event_sender.subscribe()
     }
 
+    /// 订阅流式 chunk 事件（`FlowStreamChunk`）
+    ///
+    /// 同一 Flow 的 chunk 事件总是在其完成 / 失败事件之前发送；同时订阅两个通道时，
+    /// 应优先处理本通道中已到达的事件以保持顺序。
+    pub fn subscribe_chunks(&self) -> broadcast::Receiver<FlowEvent> {
+        self.chunk_sender.subscribe()
+    }
+
     /// 开始捕获一个新的 Flow
     ///
     /// # 参数
@@ -1067,15 +1197,12 @@ impl FlowMonitor {
         // 确定 Flow 类型
         let flow_type = Self::determine_flow_type(&request.path);
 
-        // 创建 Flow
-        let flow = LLMFlow::new(flow_id.clone(), flow_type, request.clone(), metadata);
+        // 创建 Flow，请求在进入活跃 Flow 前脱敏，实时订阅者看不到原始请求
+        let mut flow = LLMFlow::new(flow_id.clone(), flow_type, request.clone(), metadata);
+        self.apply_capture_redaction(&mut flow).await;
 
         // 创建活跃 Flow 状态
-        let active_flow = ActiveFlow {
-            flow: flow.clone(),
-            stream_rebuilder: None,
-            request_start: Utc::now(),
-        };
+        let active_flow = ActiveFlow::new(flow.clone());
 
         // 添加到活跃 Flow
         {
@@ -1146,24 +1273,117 @@ impl FlowMonitor {
 
     /// 处理流式 chunk
     ///
+    /// 新增文本按 `STREAM_CHUNK_INTERVAL` 合并、脱敏后通过 chunk 通道发送；
+    /// 末尾可能被截断的令牌字符暂缓发送，避免敏感内容被拆到两个事件中而逃过脱敏。
+    ///
     /// # 参数
     /// - `flow_id`: Flow ID
     /// - `event`: SSE 事件类型（可选）
     /// - `data`: SSE 数据内容
     pub async fn process_chunk(&self, flow_id: &str, event: Option<&str>, data: &str) {
+        let redactor = self.capture_redactor.read().await.clone();
         let mut active = self.active_flows.write().await;
-        if let Some(active_flow) = active.get_mut(flow_id) {
-            if let Some(ref mut rebuilder) = active_flow.stream_rebuilder {
-                // 处理 chunk
-                if let Err(e) = rebuilder.process_event(event, data) {
-                    tracing::warn!("处理流式 chunk 失败: {}", e);
-                }
+        let Some(active_flow) = active.get_mut(flow_id) else {
+            return;
+        };
+        let Some(ref mut rebuilder) = active_flow.stream_rebuilder else {
+            return;
+        };
 
-                // 发送更新事件（可选，根据需要调整频率）
-                // 这里简化处理，每个 chunk 都发送事件
-                // 实际应用中可能需要节流
-            }
+        let index = rebuilder.chunk_count();
+        let previous_length = rebuilder.content().len();
+        if let Err(e) = rebuilder.process_event(event, data) {
+            tracing::warn!("处理流式 chunk 失败: {}", e);
+        }
+        let content = rebuilder.content();
+        let content_length = content.len();
+        if let Some(delta) = content.get(previous_length..) {
+            active_flow.pending_delta.push_str(delta);
+        }
+
+        let due = active_flow
+            .last_chunk_sent
+            .map_or(true, |sent| sent.elapsed() >= STREAM_CHUNK_INTERVAL)
+            || active_flow.pending_delta.len() >= STREAM_CHUNK_MAX_PENDING;
+        if due {
+            self.send_stream_chunk(
+                flow_id,
+                active_flow,
+                index,
+                content_length,
+                redactor.as_deref(),
+                false,
+            );
+        }
+    }
+
+    /// 发送合并的流式增量，`flush` 为 true 时发送全部剩余内容
+    fn send_stream_chunk(
+        &self,
+        flow_id: &str,
+        active_flow: &mut ActiveFlow,
+        index: u32,
+        content_length: usize,
+        redactor: Option<&CaptureRedactor>,
+        flush: bool,
+    ) {
+        let pending = &mut active_flow.pending_delta;
+        let split = if flush || pending.len() >= STREAM_CHUNK_MAX_PENDING {
+            pending.len()
+        } else {
+            let tail: usize = pending
+                .chars()
+                .rev()
+                .take_while(|c| is_token_char(*c))
+                .map(char::len_utf8)
+                .sum();
+            pending.len() - tail
+        };
+        if split == 0 {
+            return;
+        }
+
+        let delta: String = pending.drain(..split).collect();
+        let delta = match redactor {
+            Some(redactor) => redactor.scrub_stream_text(&delta),
+            None => delta,
+        };
+        active_flow.last_chunk_sent = Some(Instant::now());
+        let _ = self.chunk_sender.send(FlowEvent::FlowStreamChunk {
+            id: flow_id.to_string(),
+            index,
+            content_delta: Some(delta),
+            content_length,
+        });
+    }
+
+    /// Flow 结束前发送剩余的流式增量
+    async fn flush_stream_chunk(&self, flow_id: &str, active_flow: &mut ActiveFlow) {
+        let Some(rebuilder) = &active_flow.stream_rebuilder else {
+            return;
+        };
+        if active_flow.pending_delta.is_empty() {
+            return;
         }
+        let index = rebuilder.chunk_count().saturating_sub(1);
+        let content_length = rebuilder.content().len();
+        let redactor = self.capture_redactor.read().await.clone();
+        self.send_stream_chunk(
+            flow_id,
+            active_flow,
+            index,
+            content_length,
+            redactor.as_deref(),
+            true,
+        );
+    }
+
+    /// 获取进行中 Flow 的快照
+    pub async fn get_active_flow(&self, flow_id: &str) -> Option<LLMFlow> {
+        let active = self.active_flows.read().await;
+        active
+            .get(flow_id)
+            .map(|active_flow| active_flow.flow.clone())
     }
 
     /// 完成 Flow
@@ -1176,6 +1396,7 @@ impl FlowMonitor {
 
         if let Some(mut active_flow) = active.remove(flow_id) {
             let now = Utc::now();
+            self.flush_stream_chunk(flow_id, &mut active_flow).await;
 
             // 如果有流式重建器，使用重建的响应
             let final_response = if let Some(rebuilder) = active_flow.stream_rebuilder.take() {
@@ -1194,6 +1415,9 @@ impl FlowMonitor {
             // 检查阈值
             let threshold_result = self.check_threshold(&active_flow.flow).await;
 
+            // 捕获时脱敏
+            self.apply_capture_redaction(&mut active_flow.flow).await;
+
             // 保存到内存存储
             {
                 let mut store = self.memory_store.write().await;
@@ -1238,6 +1462,7 @@ impl FlowMonitor {
 
         if let Some(mut active_flow) = active.remove(flow_id) {
             let now = Utc::now();
+            self.flush_stream_chunk(flow_id, &mut active_flow).await;
 
             // 更新 Flow
             active_flow.flow.error = Some(error.clone());
@@ -1245,6 +1470,9 @@ impl FlowMonitor {
             active_flow.flow.timestamps.response_end = Some(now);
             active_flow.flow.timestamps.calculate_duration();
 
+            // 捕获时脱敏
+            self.apply_capture_redaction(&mut active_flow.flow).await;
+
             // 保存到内存存储
             {
                 let mut store = self.memory_store.write().await;
@@ -1258,7 +1486,8 @@ impl FlowMonitor {
                 }
             }
 
-            // 发送失败事件
+            // 发送失败事件（使用脱敏后的错误信息）
+            let error = active_flow.flow.error.clone().unwrap_or(error);
             let _ = self.event_sender.send(FlowEvent::FlowFailed {
                 id: flow_id.to_string(),
                 error: error.clone(),
@@ -1279,12 +1508,27 @@ impl FlowMonitor {
 
         if let Some(mut active_flow) = active.remove(flow_id) {
             let now = Utc::now();
+            self.flush_stream_chunk(flow_id, &mut active_flow).await;
 
             // 更新 Flow
             active_flow.flow.state = FlowState::Cancelled;
             active_flow.flow.timestamps.response_end = Some(now);
             active_flow.flow.timestamps.calculate_duration();
 
+            // 发送状态更新事件
+            let _ = self.event_sender.send(FlowEvent::FlowUpdated {
+                id: flow_id.to_string(),
+                update: FlowUpdate {
+                    state: Some(FlowState::Cancelled),
+                    content_delta: None,
+                    content_length: None,
+                    chunk_count: None,
+                },
+            });
+
+            // 捕获时脱敏
+            self.apply_capture_redaction(&mut active_flow.flow).await;
+
             // 保存到内存存储
             {
                 let mut store = self.memory_store.write().await;
@@ -1622,6 +1866,119 @@ mod tests {
         assert_eq!(monitor.memory_flow_count().await, 1);
     }
 
+    #[tokio::test]
+    async fn test_capture_redaction_on_complete() {
+        let config = FlowMonitorConfig {
+            capture_redaction: CaptureRedactionConfig {
+                enabled: true,
+                apply_to_outbound: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let monitor = FlowMonitor::new(config, None);
+
+        let mut request = create_test_request("gpt-4", "/v1/chat/completions");
+        request.messages[0].content = MessageContent::Text("mail dev@example.com".to_string());
+        let metadata = create_test_metadata(ProviderType::OpenAI);
+        let flow_id = monitor.start_flow(request, metadata).await.unwrap();
+
+        // 活跃 Flow 中的请求已脱敏
+        let active = monitor.get_active_flow(&flow_id).await.unwrap();
+        assert_eq!(
+            active.request.messages[0].content.get_all_text(),
+            "mail [REDACTED:email]"
+        );
+
+        // 出站请求体脱敏并记录到活跃 Flow
+        let body = serde_json::json!({"messages": [{"content": "mail dev@example.com"}]});
+        let scrubbed = monitor
+            .scrub_outbound_body(Some(&flow_id), &body)
+            .await
+            .unwrap();
+        assert_eq!(scrubbed["messages"][0]["content"], "mail [REDACTED:email]");
+
+        monitor.complete_flow(&flow_id, None).await;
+
+        let store = monitor.memory_store();
+        let store = store.read().await;
+        let flow = store.get(&flow_id).unwrap();
+        let flow = flow.read().unwrap();
+        assert_eq!(
+            flow.request.messages[0].content.get_all_text(),
+            "mail [REDACTED:email]"
+        );
+        assert!(flow
+            .redactions
+            .iter()
+            .any(|r| r.location == "outbound.body"));
+        assert!(flow
+            .redactions
+            .iter()
+            .any(|r| r.location == "request.messages"));
+    }
+
+    #[tokio::test]
+    async fn test_stream_chunks_are_redacted_and_coalesced() {
+        let config = FlowMonitorConfig {
+            capture_redaction: CaptureRedactionConfig {
+                enabled: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let monitor = FlowMonitor::new(config, None);
+        let mut events = monitor.subscribe();
+        let mut chunks = monitor.subscribe_chunks();
+
+        let request = create_test_request("gpt-4", "/v1/chat/completions");
+        let metadata = create_test_metadata(ProviderType::OpenAI);
+        let flow_id = monitor.start_flow(request, metadata).await.unwrap();
+        monitor.set_streaming(&flow_id, StreamFormat::OpenAI).await;
+
+        // 邮箱地址被拆到多个 chunk 中
+        for piece in ["mail de", "v@exam", "ple.com", " now"] {
+            let data = serde_json::json!({"choices": [{"index": 0, "delta": {"content": piece}}]});
+            monitor
+                .process_chunk(&flow_id, None, &data.to_string())
+                .await;
+        }
+        monitor.complete_flow(&flow_id, None).await;
+
+        let mut text = String::new();
+        let mut count = 0;
+        while let Ok(event) = chunks.try_recv() {
+            if let FlowEvent::FlowStreamChunk {
+                content_delta: Some(delta),
+                ..
+            } = event
+            {
+                text.push_str(&delta);
+                count += 1;
+            }
+        }
+        assert_eq!(text, "mail [REDACTED:email] now");
+        assert!(count < 4);
+
+        // 生命周期事件不经过 chunk 通道
+        let mut lifecycle = Vec::new();
+        while let Ok(event) = events.try_recv() {
+            assert!(!matches!(event, FlowEvent::FlowStreamChunk { .. }));
+            lifecycle.push(event);
+        }
+        assert!(matches!(
+            lifecycle.last(),
+            Some(FlowEvent::FlowCompleted { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_capture_redaction_disabled_by_default() {
+        let monitor = FlowMonitor::new(FlowMonitorConfig::default(), None);
+        let body = serde_json::json!({"content": "mail dev@example.com"});
+        assert!(monitor.scrub_outbound_body(None, &body).await.is_none());
+    }
+
     #[tokio::test]
     async fn test_config_should_monitor() {
         let config = FlowMonitorConfig {
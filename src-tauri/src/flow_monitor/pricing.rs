@@ -0,0 +1,145 @@
+//! 模型价格估算
+//!
+//! 按模型名称匹配公开标价（美元 / 百万 Token），用于估算单个 Flow 的费用。
+//! 价格表只覆盖常见模型族，未知模型返回 None；结果仅供筛选和统计参考。
+
+use super::models::{LLMFlow, TokenUsage};
+
+/// 模型价格（美元 / 百万 Token）
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ModelPricing {
+    /// 输入价格
+    pub input_per_million: f64,
+    /// 输出价格
+    pub output_per_million: f64,
+    /// 缓存读取价格（未设置时按输入价格的 10% 计算）
+    pub cache_read_per_million: Option<f64>,
+    /// 缓存写入价格（未设置时按输入价格计算）
+    pub cache_write_per_million: Option<f64>,
+}
+
+impl ModelPricing {
+    const fn new(input: f64, output: f64) -> Self {
+        Self {
+            input_per_million: input,
+            output_per_million: output,
+            cache_read_per_million: None,
+            cache_write_per_million: None,
+        }
+    }
+
+    const fn with_cache(input: f64, output: f64, cache_read: f64, cache_write: f64) -> Self {
+        Self {
+            input_per_million: input,
+            output_per_million: output,
+            cache_read_per_million: Some(cache_read),
+            cache_write_per_million: Some(cache_write),
+        }
+    }
+
+    /// 按 Token 用量计算费用（美元）
+    pub fn cost(&self, usage: &TokenUsage) -> f64 {
+        let cache_read = self
+            .cache_read_per_million
+            .unwrap_or(self.input_per_million * 0.1);
+        let cache_write = self
+            .cache_write_per_million
+            .unwrap_or(self.input_per_million);
+
+        (usage.input_tokens as f64 * self.input_per_million
+            + usage.output_tokens as f64 * self.output_per_million
+            + usage.cache_read_tokens.unwrap_or(0) as f64 * cache_read
+            + usage.cache_write_tokens.unwrap_or(0) as f64 * cache_write)
+            / 1_000_000.0
+    }
+}
+
+/// 价格表（按模型名称子串匹配，越具体的条目越靠前）
+const PRICING_TABLE: &[(&str, ModelPricing)] = &[
+    // Anthropic
+    ("opus", ModelPricing::with_cache(15.0, 75.0, 1.5, 18.75)),
+    (
+        "claude-3-haiku",
+        ModelPricing::with_cache(0.25, 1.25, 0.03, 0.3),
+    ),
+    ("haiku", ModelPricing::with_cache(0.8, 4.0, 0.08, 1.0)),
+    ("sonnet", ModelPricing::with_cache(3.0, 15.0, 0.3, 3.75)),
+    // OpenAI
+    ("gpt-4o-mini", ModelPricing::new(0.15, 0.6)),
+    ("gpt-4o", ModelPricing::new(2.5, 10.0)),
+    ("gpt-4.1-nano", ModelPricing::new(0.1, 0.4)),
+    ("gpt-4.1-mini", ModelPricing::new(0.4, 1.6)),
+    ("gpt-4.1", ModelPricing::new(2.0, 8.0)),
+    ("gpt-4-turbo", ModelPricing::new(10.0, 30.0)),
+    ("gpt-4", ModelPricing::new(30.0, 60.0)),
+    ("gpt-3.5", ModelPricing::new(0.5, 1.5)),
+    ("gpt-5-nano", ModelPricing::new(0.05, 0.4)),
+    ("gpt-5-mini", ModelPricing::new(0.25, 2.0)),
+    ("gpt-5", ModelPricing::new(1.25, 10.0)),
+    ("o4-mini", ModelPricing::new(1.1, 4.4)),
+    ("o3-mini", ModelPricing::new(1.1, 4.4)),
+    ("o3", ModelPricing::new(2.0, 8.0)),
+    ("o1-mini", ModelPricing::new(1.1, 4.4)),
+    ("o1", ModelPricing::new(15.0, 60.0)),
+    // Google
+    ("gemini-2.5-pro", ModelPricing::new(1.25, 10.0)),
+    ("gemini-2.5-flash-lite", ModelPricing::new(0.1, 0.4)),
+    ("gemini-2.5-flash", ModelPricing::new(0.3, 2.5)),
+    ("gemini-2.0-flash", ModelPricing::new(0.1, 0.4)),
+    ("gemini-1.5-pro", ModelPricing::new(1.25, 5.0)),
+    ("gemini-1.5-flash", ModelPricing::new(0.075, 0.3)),
+];
+
+/// 查找模型价格
+pub fn lookup_pricing(model: &str) -> Option<&'static ModelPricing> {
+    let model = model.to_lowercase();
+    PRICING_TABLE
+        .iter()
+        .find(|(pattern, _)| model.contains(pattern))
+        .map(|(_, pricing)| pricing)
+}
+
+/// 估算 Flow 的费用（美元）
+///
+/// 没有响应或模型不在价格表中时返回 None。
+pub fn estimate_flow_cost(flow: &LLMFlow) -> Option<f64> {
+    let response = flow.response.as_ref()?;
+    let pricing = lookup_pricing(&flow.request.model)?;
+    Some(pricing.cost(&response.usage))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lookup_prefers_specific_entries() {
+        assert_eq!(
+            lookup_pricing("gpt-4o-mini-2024-07-18")
+                .unwrap()
+                .input_per_million,
+            0.15
+        );
+        assert_eq!(lookup_pricing("gpt-4o").unwrap().input_per_million, 2.5);
+        assert_eq!(
+            lookup_pricing("claude-sonnet-4-20250514")
+                .unwrap()
+                .output_per_million,
+            15.0
+        );
+        assert!(lookup_pricing("qwen-max").is_none());
+    }
+
+    #[test]
+    fn test_cost_includes_cache_tokens() {
+        let pricing = lookup_pricing("claude-3-5-sonnet").unwrap();
+        let usage = TokenUsage {
+            input_tokens: 1_000_000,
+            output_tokens: 100_000,
+            cache_read_tokens: Some(1_000_000),
+            ..Default::default()
+        };
+        let cost = pricing.cost(&usage);
+        assert!((cost - (3.0 + 1.5 + 0.3)).abs() < 1e-9);
+    }
+}
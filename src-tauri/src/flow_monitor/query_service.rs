@@ -10,8 +10,9 @@ use std::sync::Arc;
 use thiserror::Error;
 use tokio::sync::RwLock;
 
+use super::aggregation::{AggregateTable, FlowQuery};
 use super::file_store::{FileStoreError, FlowFileStore};
-use super::filter_parser::{FilterParseError, FilterParser};
+use super::filter_parser::{FilterExpr, FilterParseError, FilterParser};
 use super::memory_store::{FlowFilter, FlowMemoryStore};
 use super::models::{FlowState, LLMFlow};
 
@@ -303,8 +304,23 @@ impl FlowQueryService {
         // 解析过滤表达式
         let expr = FilterParser::parse(filter_expr)?;
 
-        // 编译为过滤函数
-        let filter_fn = FilterParser::compile(&expr);
+        self.query_with_filter_expr(&expr, sort_by, sort_desc, page, page_size)
+            .await
+    }
+
+    /// 使用已解析的过滤表达式查询 Flow
+    ///
+    /// 调用方需要先展开表达式中的视图引用，未展开的 `~view` 不匹配任何 Flow。
+    pub async fn query_with_filter_expr(
+        &self,
+        expr: &FilterExpr,
+        sort_by: FlowSortBy,
+        sort_desc: bool,
+        page: usize,
+        page_size: usize,
+    ) -> Result<FlowQueryResult, QueryWithExpressionError> {
+        // 编译为过滤函数（相对时间只按查询开始时间计算一次）
+        let filter_fn = FilterParser::compile_at(expr, Utc::now());
 
         // 从内存获取所有 Flow 并应用过滤
         let memory_flows = {
@@ -372,6 +388,51 @@ impl FlowQueryService {
         })
     }
 
+    /// 执行聚合查询
+    ///
+    /// 对满足过滤条件的 Flow（内存和文件存储合并去重，最多 `limit` 条）
+    /// 按 `group by` 定义分组统计。
+    ///
+    /// # 参数
+    /// - `query`: 已解析的查询，必须包含聚合定义
+    /// - `limit`: 参与统计的最大 Flow 数量
+    pub async fn aggregate(
+        &self,
+        query: &FlowQuery,
+        limit: usize,
+    ) -> Result<AggregateTable, QueryWithExpressionError> {
+        let aggregation = query.aggregation.as_ref().ok_or_else(|| {
+            FilterParseError::InvalidAggregation("查询缺少 group by 子句".to_string())
+        })?;
+
+        let filter_fn = query.compile_at(Utc::now());
+        let mut flows: Vec<LLMFlow> = {
+            let store = self.memory_store.read().await;
+            store
+                .query(&FlowFilter::default())
+                .into_iter()
+                .filter(|f| filter_fn(f))
+                .take(limit)
+                .collect()
+        };
+
+        if flows.len() < limit {
+            let memory_ids: std::collections::HashSet<_> =
+                flows.iter().map(|f| f.id.clone()).collect();
+            let file_flows = self
+                .file_store
+                .query(&FlowFilter::default(), limit * 2, 0)?;
+            flows.extend(
+                file_flows
+                    .into_iter()
+                    .filter(|f| !memory_ids.contains(&f.id) && filter_fn(f))
+                    .take(limit - memory_ids.len()),
+            );
+        }
+
+        Ok(aggregation.apply(&flows))
+    }
+
     /// 排序 Flow 列表
     fn sort_flows(flows: &mut [LLMFlow], sort_by: FlowSortBy, desc: bool) {
         flows.sort_by(|a, b| {
@@ -8,12 +8,13 @@
 use chrono::{DateTime, Utc};
 use rusqlite::{params, Connection, OptionalExtension};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::path::PathBuf;
 use std::sync::Mutex;
 use thiserror::Error;
 use uuid::Uuid;
 
-use super::filter_parser::FilterParser;
+use super::filter_parser::{FilterExpr, FilterParser, FilterToken};
 
 // ============================================================================
 // 错误类型
@@ -42,6 +43,9 @@ pub enum QuickFilterError {
 
     #[error("过滤器名称已存在: {0}")]
     DuplicateName(String),
+
+    #[error("视图 '{0}' 正被其他视图引用: {1}")]
+    ViewInUse(String, String),
 }
 
 pub type Result<T> = std::result::Result<T, QuickFilterError>;
@@ -307,9 +311,8 @@ impl QuickFilterManager {
         let name = name.into();
         let filter_expr = filter_expr.into();
 
-        // 验证过滤表达式
-        FilterParser::validate(&filter_expr)
-            .map_err(|e| QuickFilterError::InvalidFilterExpr(e.to_string()))?;
+        // 验证过滤表达式（包括引用的视图）
+        self.validate_expr(&name, &filter_expr)?;
 
         let filter = QuickFilter::new(
             name,
@@ -403,10 +406,21 @@ impl QuickFilterManager {
     /// # Returns
     /// 更新后的快速过滤器
     pub fn update(&self, id: &str, updates: QuickFilterUpdate) -> Result<QuickFilter> {
-        // 验证新的过滤表达式（如果有）
-        if let Some(ref expr) = updates.filter_expr {
-            FilterParser::validate(expr)
-                .map_err(|e| QuickFilterError::InvalidFilterExpr(e.to_string()))?;
+        // 验证新的过滤表达式（如果有）；重命名也可能影响视图引用
+        if updates.filter_expr.is_some() || updates.name.is_some() {
+            let existing = self
+                .get(id)?
+                .ok_or_else(|| QuickFilterError::FilterNotFound(id.to_string()))?;
+            let name = updates.name.as_deref().unwrap_or(&existing.name);
+            // 被其他视图引用时不能改名，否则这些视图的引用会失效
+            if name != existing.name {
+                self.ensure_not_referenced(&existing)?;
+            }
+            let expr = updates
+                .filter_expr
+                .as_deref()
+                .unwrap_or(&existing.filter_expr);
+            self.validate_expr(name, expr)?;
         }
 
         let conn = self.db.lock().unwrap();
@@ -475,6 +489,11 @@ impl QuickFilterManager {
     /// # Arguments
     /// * `id` - 过滤器 ID
     pub fn delete(&self, id: &str) -> Result<()> {
+        // 被其他视图引用时不能删除
+        if let Some(existing) = self.get(id)? {
+            self.ensure_not_referenced(&existing)?;
+        }
+
         let conn = self.db.lock().unwrap();
 
         // 检查是否为预设过滤器
@@ -806,6 +825,74 @@ impl QuickFilterManager {
         }
     }
 
+    /// 解析过滤表达式并展开其中的视图引用 (`~view <name>`)
+    pub fn resolve(&self, input: &str) -> Result<FilterExpr> {
+        let expr = FilterParser::parse(input)
+            .map_err(|e| QuickFilterError::InvalidFilterExpr(e.to_string()))?;
+        self.expand(&expr)
+    }
+
+    /// 展开已解析表达式中的视图引用
+    pub fn expand(&self, expr: &FilterExpr) -> Result<FilterExpr> {
+        if !FilterParser::has_view_references(expr) {
+            return Ok(expr.clone());
+        }
+
+        let views = self.view_sources()?;
+        FilterParser::expand_views(expr, &|name| views.get(name).cloned())
+            .map_err(|e| QuickFilterError::InvalidFilterExpr(e.to_string()))
+    }
+
+    /// 验证即将保存的过滤器：语法正确，且引用的视图存在、没有循环引用
+    fn validate_expr(&self, name: &str, filter_expr: &str) -> Result<()> {
+        let expr = FilterParser::parse(filter_expr)
+            .map_err(|e| QuickFilterError::InvalidFilterExpr(e.to_string()))?;
+        if !FilterParser::has_view_references(&expr) {
+            return Ok(());
+        }
+
+        let mut views = self.view_sources()?;
+        views.insert(name.to_string(), filter_expr.to_string());
+        let root = FilterExpr::Token(FilterToken::View(name.to_string()));
+        FilterParser::expand_views(&root, &|view| views.get(view).cloned())
+            .map(|_| ())
+            .map_err(|e| QuickFilterError::InvalidFilterExpr(e.to_string()))
+    }
+
+    /// 确认没有其他视图引用 `filter`，否则返回 `ViewInUse`
+    fn ensure_not_referenced(&self, filter: &QuickFilter) -> Result<()> {
+        let referrers: Vec<String> = self
+            .list()?
+            .into_iter()
+            .filter(|other| other.id != filter.id)
+            .filter(|other| {
+                FilterParser::parse(&other.filter_expr).is_ok_and(|expr| {
+                    FilterParser::view_references(&expr)
+                        .iter()
+                        .any(|name| *name == filter.name)
+                })
+            })
+            .map(|other| other.name)
+            .collect();
+        if referrers.is_empty() {
+            Ok(())
+        } else {
+            Err(QuickFilterError::ViewInUse(
+                filter.name.clone(),
+                referrers.join(", "),
+            ))
+        }
+    }
+
+    /// 视图名称到过滤表达式的映射
+    fn view_sources(&self) -> Result<HashMap<String, String>> {
+        Ok(self
+            .list()?
+            .into_iter()
+            .map(|filter| (filter.name, filter.filter_expr))
+            .collect())
+    }
+
     /// 清除所有非预设过滤器（用于测试）
     #[cfg(test)]
     pub fn clear_custom(&self) -> Result<()> {
@@ -937,6 +1024,111 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn test_resolve_view_references() {
+        let manager = create_test_manager();
+        manager
+            .save("errors", "~e | ~status 5xx", None, None)
+            .unwrap();
+        manager
+            .save("kiro errors", "~view errors & ~p kiro", None, None)
+            .unwrap();
+
+        let expr = manager
+            .resolve("~view \"kiro errors\" & ~m claude")
+            .unwrap();
+        assert!(!FilterParser::has_view_references(&expr));
+        assert!(expr.to_string().contains("~status 5xx"));
+
+        let result = manager.resolve("~view missing");
+        assert!(matches!(
+            result,
+            Err(QuickFilterError::InvalidFilterExpr(_))
+        ));
+    }
+
+    #[test]
+    fn test_reject_unknown_and_circular_views() {
+        let manager = create_test_manager();
+
+        let result = manager.save("broken", "~view missing", None, None);
+        assert!(matches!(
+            result,
+            Err(QuickFilterError::InvalidFilterExpr(_))
+        ));
+
+        let result = manager.save("self", "~view self", None, None);
+        assert!(matches!(
+            result,
+            Err(QuickFilterError::InvalidFilterExpr(_))
+        ));
+
+        let a = manager.save("a", "~e", None, None).unwrap();
+        manager.save("b", "~view a", None, None).unwrap();
+        let result = manager.update(
+            &a.id,
+            QuickFilterUpdate {
+                filter_expr: Some("~view b".to_string()),
+                ..Default::default()
+            },
+        );
+        assert!(matches!(
+            result,
+            Err(QuickFilterError::InvalidFilterExpr(_))
+        ));
+    }
+
+    #[test]
+    fn test_referenced_view_cannot_be_renamed_or_deleted() {
+        let manager = create_test_manager();
+        let errors = manager.save("errors", "~e", None, None).unwrap();
+        let kiro = manager
+            .save("kiro errors", "~view errors & ~p kiro", None, None)
+            .unwrap();
+
+        let result = manager.update(
+            &errors.id,
+            QuickFilterUpdate {
+                name: Some("failures".to_string()),
+                ..Default::default()
+            },
+        );
+        assert!(matches!(
+            result,
+            Err(QuickFilterError::ViewInUse(ref name, ref referrers))
+                if name == "errors" && referrers == "kiro errors"
+        ));
+        assert!(matches!(
+            manager.delete(&errors.id),
+            Err(QuickFilterError::ViewInUse(..))
+        ));
+        assert!(manager.resolve("~view \"kiro errors\"").is_ok());
+
+        // 修改表达式但不改名仍然允许
+        manager
+            .update(
+                &errors.id,
+                QuickFilterUpdate {
+                    filter_expr: Some("~e | ~status 5xx".to_string()),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+
+        // 引用方删除后即可改名和删除
+        manager.delete(&kiro.id).unwrap();
+        manager
+            .update(
+                &errors.id,
+                QuickFilterUpdate {
+                    name: Some("failures".to_string()),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        manager.delete(&errors.id).unwrap();
+    }
+
     #[test]
     fn test_filter_not_found() {
         let manager = create_test_manager();
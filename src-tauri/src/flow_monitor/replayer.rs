@@ -456,6 +456,7 @@ impl FlowReplayer {
                 tags: vec!["replay".to_string()],
                 starred: false,
             },
+            redactions: Vec::new(),
         };
 
         // 保存到内存存储
@@ -951,6 +952,7 @@ mod property_tests {
                     tags: vec!["replay".to_string()],
                     starred: false,
                 },
+                redactions: Vec::new(),
             };
 
             // 验证 1: 重放 Flow 应该有 "replay" 标签
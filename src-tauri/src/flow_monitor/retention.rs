@@ -0,0 +1,740 @@
+//! Flow 保留策略
+//!
+//! 基于过滤表达式的分层保留策略：规则按顺序匹配，第一条匹配的规则决定
+//! Flow 在多少天后剥离请求/响应体（仅保留元数据和 Token 统计），
+//! 以及在多少天后彻底删除。收藏的 Flow 可以通过 `~starred` 规则永久保留，
+//! 书签中的 Flow 始终受保护。
+//!
+//! 策略由后台压缩任务执行：重写 JSONL 段并同步更新 SQLite 索引。
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, RwLock};
+use thiserror::Error;
+
+use super::bookmark::{BookmarkError, BookmarkManager};
+use super::file_store::{
+    CompactionAction, CompactionResult, FileStoreError, FlowFileStore, StorageUsageReport,
+};
+use super::filter_parser::FilterParser;
+use super::models::LLMFlow;
+
+/// 保留策略持久化文件名（位于 Flow 存储目录下）
+const POLICY_FILE_NAME: &str = "retention_policy.json";
+
+/// 后台压缩的最小间隔（分钟）
+const MIN_COMPACTION_INTERVAL_MINUTES: u64 = 1;
+
+// ============================================================================
+// 错误类型
+// ============================================================================
+
+/// 保留策略错误
+#[derive(Debug, Error)]
+pub enum RetentionError {
+    #[error("规则 '{rule}' 的过滤表达式无效: {message}")]
+    InvalidFilter { rule: String, message: String },
+
+    #[error("规则 '{rule}' 的剥离天数 ({strip}) 不能大于删除天数 ({delete})")]
+    InvalidTiers {
+        rule: String,
+        strip: u32,
+        delete: u32,
+    },
+
+    #[error("存储错误: {0}")]
+    Store(#[from] FileStoreError),
+
+    #[error("IO 错误: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("JSON 序列化错误: {0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error("读取书签失败，跳过本次压缩: {0}")]
+    Bookmarks(#[from] BookmarkError),
+}
+
+pub type Result<T> = std::result::Result<T, RetentionError>;
+
+// ============================================================================
+// 策略配置
+// ============================================================================
+
+/// 保留规则
+///
+/// `strip_bodies_after_days` 和 `delete_after_days` 都为空表示永久保留。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RetentionRule {
+    /// 规则名称
+    pub name: String,
+    /// 过滤表达式（为空时匹配所有 Flow）
+    #[serde(default)]
+    pub filter: String,
+    /// 客户端匹配（IP 精确匹配或 User-Agent 包含，不区分大小写）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client: Option<String>,
+    /// 多少天后剥离请求/响应体
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub strip_bodies_after_days: Option<u32>,
+    /// 多少天后删除
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub delete_after_days: Option<u32>,
+    /// 是否启用
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl RetentionRule {
+    /// 创建永久保留规则
+    pub fn keep_forever(name: impl Into<String>, filter: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            filter: filter.into(),
+            client: None,
+            strip_bodies_after_days: None,
+            delete_after_days: None,
+            enabled: true,
+        }
+    }
+
+    /// 验证规则
+    pub fn validate(&self) -> Result<()> {
+        if !self.filter.trim().is_empty() {
+            FilterParser::parse(&self.filter).map_err(|e| RetentionError::InvalidFilter {
+                rule: self.name.clone(),
+                message: e.to_string(),
+            })?;
+        }
+        if let (Some(strip), Some(delete)) = (self.strip_bodies_after_days, self.delete_after_days)
+        {
+            if strip > delete {
+                return Err(RetentionError::InvalidTiers {
+                    rule: self.name.clone(),
+                    strip,
+                    delete,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 保留策略
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RetentionPolicy {
+    /// 是否启用后台压缩
+    #[serde(default)]
+    pub enabled: bool,
+    /// 有序规则列表，第一条匹配的规则生效
+    #[serde(default)]
+    pub rules: Vec<RetentionRule>,
+    /// 未匹配任何规则时，多少天后剥离请求/响应体
+    #[serde(default)]
+    pub default_strip_bodies_after_days: Option<u32>,
+    /// 未匹配任何规则时，多少天后删除
+    #[serde(default)]
+    pub default_delete_after_days: Option<u32>,
+    /// 是否保护书签中的 Flow
+    #[serde(default = "default_true")]
+    pub protect_bookmarked: bool,
+    /// 后台压缩间隔（分钟）
+    #[serde(default = "default_compaction_interval")]
+    pub compaction_interval_minutes: u64,
+}
+
+fn default_compaction_interval() -> u64 {
+    60
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            rules: vec![RetentionRule::keep_forever("收藏", "~starred")],
+            default_strip_bodies_after_days: None,
+            default_delete_after_days: Some(7),
+            protect_bookmarked: true,
+            compaction_interval_minutes: default_compaction_interval(),
+        }
+    }
+}
+
+impl RetentionPolicy {
+    /// 验证策略
+    pub fn validate(&self) -> Result<()> {
+        for rule in &self.rules {
+            rule.validate()?;
+        }
+        RetentionRule {
+            name: "默认".to_string(),
+            filter: String::new(),
+            client: None,
+            strip_bodies_after_days: self.default_strip_bodies_after_days,
+            delete_after_days: self.default_delete_after_days,
+            enabled: true,
+        }
+        .validate()
+    }
+}
+
+/// 保留决策
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RetentionDecision {
+    /// 原样保留
+    Retain,
+    /// 剥离请求/响应体
+    StripBodies,
+    /// 删除
+    Delete,
+}
+
+// ============================================================================
+// 策略求值
+// ============================================================================
+
+/// 编译后的过滤谓词
+type FlowPredicate = Box<dyn Fn(&LLMFlow) -> bool + Send + Sync>;
+
+/// 编译后的规则
+struct CompiledRule {
+    filter: Option<FlowPredicate>,
+    client: Option<String>,
+    strip_after: Option<Duration>,
+    delete_after: Option<Duration>,
+}
+
+impl CompiledRule {
+    fn matches(&self, flow: &LLMFlow) -> bool {
+        if let Some(client) = &self.client {
+            let info = &flow.metadata.client_info;
+            let ip_match = info.ip.as_deref() == Some(client.as_str());
+            let ua_match = info
+                .user_agent
+                .as_ref()
+                .is_some_and(|ua| ua.to_lowercase().contains(&client.to_lowercase()));
+            if !ip_match && !ua_match {
+                return false;
+            }
+        }
+        self.filter.as_ref().is_none_or(|filter| filter(flow))
+    }
+}
+
+fn days(days: Option<u32>) -> Option<Duration> {
+    days.map(|d| Duration::days(d as i64))
+}
+
+/// 保留策略求值器
+pub struct RetentionEvaluator {
+    rules: Vec<CompiledRule>,
+    default_strip_after: Option<Duration>,
+    default_delete_after: Option<Duration>,
+    protected_ids: HashSet<String>,
+}
+
+impl RetentionEvaluator {
+    /// 编译策略
+    ///
+    /// `protected_ids` 中的 Flow 无论匹配哪条规则都会被保留。
+    pub fn new(policy: &RetentionPolicy, protected_ids: HashSet<String>) -> Result<Self> {
+        policy.validate()?;
+
+        let rules = policy
+            .rules
+            .iter()
+            .filter(|rule| rule.enabled)
+            .map(|rule| {
+                let filter = if rule.filter.trim().is_empty() {
+                    None
+                } else {
+                    let expr = FilterParser::parse(&rule.filter).map_err(|e| {
+                        RetentionError::InvalidFilter {
+                            rule: rule.name.clone(),
+                            message: e.to_string(),
+                        }
+                    })?;
+                    Some(FilterParser::compile(&expr))
+                };
+                Ok(CompiledRule {
+                    filter,
+                    client: rule.client.clone().filter(|c| !c.trim().is_empty()),
+                    strip_after: days(rule.strip_bodies_after_days),
+                    delete_after: days(rule.delete_after_days),
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self {
+            rules,
+            default_strip_after: days(policy.default_strip_bodies_after_days),
+            default_delete_after: days(policy.default_delete_after_days),
+            protected_ids,
+        })
+    }
+
+    /// 对单个 Flow 求值
+    pub fn evaluate(&self, flow: &LLMFlow, now: DateTime<Utc>) -> RetentionDecision {
+        if self.protected_ids.contains(&flow.id) {
+            return RetentionDecision::Retain;
+        }
+
+        let (strip_after, delete_after) = self
+            .rules
+            .iter()
+            .find(|rule| rule.matches(flow))
+            .map(|rule| (rule.strip_after, rule.delete_after))
+            .unwrap_or((self.default_strip_after, self.default_delete_after));
+
+        let age = now - flow.timestamps.created;
+        if delete_after.is_some_and(|d| age >= d) {
+            RetentionDecision::Delete
+        } else if strip_after.is_some_and(|d| age >= d) {
+            RetentionDecision::StripBodies
+        } else {
+            RetentionDecision::Retain
+        }
+    }
+}
+
+/// 剥离 Flow 的请求/响应体，保留元数据、Token 统计和工具名称
+///
+/// 返回是否有内容被剥离。
+pub fn strip_flow_bodies(flow: &mut LLMFlow) -> bool {
+    let mut changed = false;
+
+    let request = &mut flow.request;
+    if !request.body.is_null() {
+        request.body = serde_json::Value::Null;
+        changed = true;
+    }
+    if !request.messages.is_empty() {
+        request.messages.clear();
+        changed = true;
+    }
+    if request.system_prompt.take().is_some() {
+        changed = true;
+    }
+    if request.tools.take().is_some() {
+        changed = true;
+    }
+
+    if let Some(response) = flow.response.as_mut() {
+        if !response.body.is_null() {
+            response.body = serde_json::Value::Null;
+            changed = true;
+        }
+        if !response.content.is_empty() {
+            response.content.clear();
+            changed = true;
+        }
+        if response.thinking.take().is_some() {
+            changed = true;
+        }
+        for call in &mut response.tool_calls {
+            if !call.function.arguments.is_empty() {
+                call.function.arguments.clear();
+                changed = true;
+            }
+        }
+        if let Some(stream_info) = response.stream_info.as_mut() {
+            if stream_info.raw_chunks.take().is_some() {
+                changed = true;
+            }
+        }
+    }
+
+    changed
+}
+
+// ============================================================================
+// 保留管理器
+// ============================================================================
+
+/// 一次保留策略执行的报告
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetentionRunReport {
+    /// 开始时间
+    pub started_at: DateTime<Utc>,
+    /// 耗时（毫秒）
+    pub duration_ms: u64,
+    /// 受保护（书签）的 Flow 数
+    pub protected_flows: usize,
+    /// 压缩结果
+    pub compaction: CompactionResult,
+}
+
+/// 保留策略管理器
+///
+/// 持有当前策略（持久化到存储目录下的 `retention_policy.json`），
+/// 负责执行压缩和生成存储用量报告。
+pub struct RetentionManager {
+    store: Arc<FlowFileStore>,
+    bookmarks: Option<Arc<BookmarkManager>>,
+    policy: RwLock<RetentionPolicy>,
+    policy_path: PathBuf,
+    last_run: RwLock<Option<RetentionRunReport>>,
+    run_lock: Mutex<()>,
+}
+
+impl RetentionManager {
+    /// 创建管理器并加载已保存的策略
+    pub fn new(store: Arc<FlowFileStore>, bookmarks: Option<Arc<BookmarkManager>>) -> Self {
+        let policy_path = store.base_dir().join(POLICY_FILE_NAME);
+        let policy = Self::load_policy(&policy_path).unwrap_or_else(|e| {
+            tracing::warn!("加载 Flow 保留策略失败，使用默认策略: {}", e);
+            RetentionPolicy::default()
+        });
+
+        Self {
+            store,
+            bookmarks,
+            policy: RwLock::new(policy),
+            policy_path,
+            last_run: RwLock::new(None),
+            run_lock: Mutex::new(()),
+        }
+    }
+
+    fn load_policy(path: &std::path::Path) -> Result<RetentionPolicy> {
+        if !path.exists() {
+            return Ok(RetentionPolicy::default());
+        }
+        let content = std::fs::read_to_string(path)?;
+        let policy: RetentionPolicy = serde_json::from_str(&content)?;
+        policy.validate()?;
+        Ok(policy)
+    }
+
+    /// 获取当前策略
+    pub fn policy(&self) -> RetentionPolicy {
+        self.policy.read().unwrap().clone()
+    }
+
+    /// 更新并保存策略
+    pub fn set_policy(&self, policy: RetentionPolicy) -> Result<()> {
+        policy.validate()?;
+        std::fs::write(&self.policy_path, serde_json::to_string_pretty(&policy)?)?;
+        *self.policy.write().unwrap() = policy;
+        Ok(())
+    }
+
+    /// 获取最近一次执行报告
+    pub fn last_run(&self) -> Option<RetentionRunReport> {
+        self.last_run.read().unwrap().clone()
+    }
+
+    /// 按当前策略执行一次压缩
+    ///
+    /// 同一时间只会有一次压缩在执行。书签无法读取时不执行压缩，避免删除受保护的 Flow。
+    pub fn run_compaction(&self) -> Result<RetentionRunReport> {
+        let _guard = self.run_lock.lock().unwrap();
+        let policy = self.policy();
+        let started_at = Utc::now();
+
+        let protected_ids = self.protected_ids(&policy)?;
+        let protected_flows = protected_ids.len();
+        let evaluator = RetentionEvaluator::new(&policy, protected_ids)?;
+
+        let compaction = self
+            .store
+            .compact(|flow| match evaluator.evaluate(flow, started_at) {
+                RetentionDecision::Retain => CompactionAction::Keep,
+                RetentionDecision::Delete => CompactionAction::Delete,
+                RetentionDecision::StripBodies => {
+                    if strip_flow_bodies(flow) {
+                        CompactionAction::Rewrite
+                    } else {
+                        CompactionAction::Keep
+                    }
+                }
+            })?;
+
+        let report = RetentionRunReport {
+            started_at,
+            duration_ms: (Utc::now() - started_at).num_milliseconds().max(0) as u64,
+            protected_flows,
+            compaction,
+        };
+        *self.last_run.write().unwrap() = Some(report.clone());
+
+        Ok(report)
+    }
+
+    /// 生成存储用量报告
+    pub fn storage_usage(&self) -> Result<StorageUsageReport> {
+        Ok(self.store.storage_usage()?)
+    }
+
+    /// 后台压缩循环
+    ///
+    /// 按策略中的间隔周期性执行压缩；策略未启用时仅等待。
+    pub async fn run_background(self: Arc<Self>) {
+        loop {
+            let policy = self.policy();
+            let interval = policy
+                .compaction_interval_minutes
+                .max(MIN_COMPACTION_INTERVAL_MINUTES);
+            tokio::time::sleep(std::time::Duration::from_secs(interval * 60)).await;
+
+            if !self.policy().enabled {
+                continue;
+            }
+
+            let manager = self.clone();
+            match tokio::task::spawn_blocking(move || manager.run_compaction()).await {
+                Ok(Ok(report)) => tracing::info!(
+                    "Flow 保留策略执行完成: 删除 {} 条, 剥离 {} 条, 释放 {} 字节",
+                    report.compaction.flows_deleted,
+                    report.compaction.flows_rewritten,
+                    report.compaction.bytes_freed()
+                ),
+                Ok(Err(e)) => tracing::error!("Flow 保留策略执行失败: {}", e),
+                Err(e) => tracing::error!("Flow 保留策略任务异常: {}", e),
+            }
+        }
+    }
+
+    fn protected_ids(&self, policy: &RetentionPolicy) -> Result<HashSet<String>> {
+        if !policy.protect_bookmarked {
+            return Ok(HashSet::new());
+        }
+        match &self.bookmarks {
+            Some(bookmarks) => Ok(bookmarks
+                .list(None)?
+                .into_iter()
+                .map(|b| b.flow_id)
+                .collect()),
+            None => Ok(HashSet::new()),
+        }
+    }
+}
+
+// ============================================================================
+// 测试模块
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flow_monitor::file_store::RotationConfig;
+    use crate::flow_monitor::models::{
+        FlowError, FlowErrorType, FlowMetadata, FlowType, LLMRequest, LLMResponse, Message,
+        MessageContent, MessageRole,
+    };
+    use tempfile::TempDir;
+
+    fn create_flow(id: &str, age_days: i64) -> LLMFlow {
+        let request = LLMRequest {
+            model: "gpt-4".to_string(),
+            body: serde_json::json!({"model": "gpt-4"}),
+            messages: vec![Message {
+                role: MessageRole::User,
+                content: MessageContent::Text("hello".to_string()),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let mut flow = LLMFlow::new(
+            id.to_string(),
+            FlowType::ChatCompletions,
+            request,
+            FlowMetadata::default(),
+        );
+        flow.timestamps.created = Utc::now() - Duration::days(age_days);
+        let mut response = LLMResponse {
+            status_code: 200,
+            content: "world".to_string(),
+            ..Default::default()
+        };
+        response.usage.input_tokens = 10;
+        response.usage.output_tokens = 5;
+        flow.response = Some(response);
+        flow
+    }
+
+    fn example_policy() -> RetentionPolicy {
+        RetentionPolicy {
+            enabled: true,
+            rules: vec![
+                RetentionRule::keep_forever("收藏", "~starred"),
+                RetentionRule {
+                    name: "脚本客户端".to_string(),
+                    filter: String::new(),
+                    client: Some("curl".to_string()),
+                    strip_bodies_after_days: None,
+                    delete_after_days: Some(1),
+                    enabled: true,
+                },
+                RetentionRule {
+                    name: "错误".to_string(),
+                    filter: "~e".to_string(),
+                    client: None,
+                    strip_bodies_after_days: None,
+                    delete_after_days: Some(90),
+                    enabled: true,
+                },
+            ],
+            default_strip_bodies_after_days: Some(7),
+            default_delete_after_days: Some(30),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_first_matching_rule_wins() {
+        let evaluator = RetentionEvaluator::new(&example_policy(), HashSet::new()).unwrap();
+        let now = Utc::now();
+
+        let mut starred = create_flow("starred", 400);
+        starred.annotations.starred = true;
+        assert_eq!(evaluator.evaluate(&starred, now), RetentionDecision::Retain);
+
+        let mut error = create_flow("error", 40);
+        error.error = Some(FlowError::new(FlowErrorType::ServerError, "boom"));
+        assert_eq!(evaluator.evaluate(&error, now), RetentionDecision::Retain);
+        error.timestamps.created = now - Duration::days(91);
+        assert_eq!(evaluator.evaluate(&error, now), RetentionDecision::Delete);
+
+        let mut scripted = create_flow("scripted", 2);
+        scripted.metadata.client_info.user_agent = Some("curl/8.0".to_string());
+        assert_eq!(
+            evaluator.evaluate(&scripted, now),
+            RetentionDecision::Delete
+        );
+    }
+
+    #[test]
+    fn test_default_tiers() {
+        let evaluator = RetentionEvaluator::new(&example_policy(), HashSet::new()).unwrap();
+        let now = Utc::now();
+
+        assert_eq!(
+            evaluator.evaluate(&create_flow("fresh", 1), now),
+            RetentionDecision::Retain
+        );
+        assert_eq!(
+            evaluator.evaluate(&create_flow("warm", 8), now),
+            RetentionDecision::StripBodies
+        );
+        assert_eq!(
+            evaluator.evaluate(&create_flow("cold", 31), now),
+            RetentionDecision::Delete
+        );
+    }
+
+    #[test]
+    fn test_protected_ids_are_retained() {
+        let protected: HashSet<String> = ["bookmarked".to_string()].into_iter().collect();
+        let evaluator = RetentionEvaluator::new(&example_policy(), protected).unwrap();
+        assert_eq!(
+            evaluator.evaluate(&create_flow("bookmarked", 365), Utc::now()),
+            RetentionDecision::Retain
+        );
+    }
+
+    #[test]
+    fn test_invalid_policy_rejected() {
+        let mut policy = example_policy();
+        policy
+            .rules
+            .push(RetentionRule::keep_forever("坏规则", "~unknown x"));
+        assert!(matches!(
+            policy.validate(),
+            Err(RetentionError::InvalidFilter { .. })
+        ));
+
+        let mut policy = example_policy();
+        policy.default_strip_bodies_after_days = Some(60);
+        assert!(matches!(
+            policy.validate(),
+            Err(RetentionError::InvalidTiers { .. })
+        ));
+    }
+
+    #[test]
+    fn test_strip_flow_bodies_keeps_metadata() {
+        let mut flow = create_flow("strip", 0);
+        assert!(strip_flow_bodies(&mut flow));
+
+        assert!(flow.request.body.is_null());
+        assert!(flow.request.messages.is_empty());
+        assert_eq!(flow.request.model, "gpt-4");
+        let response = flow.response.as_ref().unwrap();
+        assert!(response.content.is_empty());
+        assert_eq!(response.usage.input_tokens, 10);
+        assert_eq!(response.usage.output_tokens, 5);
+
+        // 再次剥离不应产生变化
+        assert!(!strip_flow_bodies(&mut flow));
+    }
+
+    #[test]
+    fn test_manager_compaction_applies_policy() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = Arc::new(
+            FlowFileStore::new(temp_dir.path().to_path_buf(), RotationConfig::default()).unwrap(),
+        );
+
+        store.write(&create_flow("fresh", 0)).unwrap();
+        store.write(&create_flow("warm", 10)).unwrap();
+        store.write(&create_flow("cold", 40)).unwrap();
+
+        let manager = RetentionManager::new(store.clone(), None);
+        manager.set_policy(example_policy()).unwrap();
+
+        let report = manager.run_compaction().unwrap();
+        assert_eq!(report.compaction.flows_deleted, 1);
+        assert_eq!(report.compaction.flows_rewritten, 1);
+        assert!(report.compaction.bytes_freed() > 0);
+
+        assert!(store.get("cold").unwrap().is_none());
+        let warm = store.get("warm").unwrap().unwrap();
+        assert!(warm.request.messages.is_empty());
+        assert_eq!(warm.response.unwrap().usage.input_tokens, 10);
+        let fresh = store.get("fresh").unwrap().unwrap();
+        assert_eq!(fresh.request.messages.len(), 1);
+
+        // 再次执行是幂等的
+        let report = manager.run_compaction().unwrap();
+        assert_eq!(report.compaction.flows_deleted, 0);
+        assert_eq!(report.compaction.flows_rewritten, 0);
+
+        // 策略已持久化
+        let reloaded = RetentionManager::new(store, None);
+        assert_eq!(reloaded.policy(), example_policy());
+    }
+
+    #[test]
+    fn test_compaction_skipped_when_bookmarks_unreadable() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = Arc::new(
+            FlowFileStore::new(temp_dir.path().join("flows"), RotationConfig::default()).unwrap(),
+        );
+        store.write(&create_flow("bookmarked", 400)).unwrap();
+
+        let db_path = temp_dir.path().join("bookmarks.db");
+        let bookmarks = Arc::new(BookmarkManager::new(db_path.clone()).unwrap());
+        rusqlite::Connection::open(&db_path)
+            .unwrap()
+            .execute("DROP TABLE flow_bookmarks", [])
+            .unwrap();
+
+        let manager = RetentionManager::new(store.clone(), Some(bookmarks));
+        manager.set_policy(example_policy()).unwrap();
+
+        assert!(matches!(
+            manager.run_compaction(),
+            Err(RetentionError::Bookmarks(_))
+        ));
+        assert!(store.get("bookmarked").unwrap().is_some());
+        assert!(manager.last_run().is_none());
+    }
+}
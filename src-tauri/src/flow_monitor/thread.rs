@@ -0,0 +1,774 @@
+//! 会话线程重建
+//!
+//! `SessionManager` 只能手动或按时间窗口/客户端对 Flow 分组。本模块根据消息内容
+//! 自动识别对话线程：如果 Flow N+1 的消息列表以 Flow N 的消息列表（含工具结果）
+//! 为前缀，则认为它延续了 Flow N，并据此构建线程树。支持：
+//! - 延续（extends）：完整前缀匹配
+//! - 分支（branch）：编辑消息后从同一父节点产生的第二个子节点，或仅部分前缀匹配
+//! - 压缩（compacted）：上下文被压缩后消息数减少，但属于同一客户端和系统提示词
+//!
+//! 在线程基础上提供时间线视图，展示每一轮的 Token 增长、工具调用和延迟。
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+
+use super::models::{FlowState, LLMFlow, Message, MessageContent};
+
+// ============================================================================
+// 配置结构
+// ============================================================================
+
+/// 线程重建配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ThreadingConfig {
+    /// 是否只在同一客户端（IP + User-Agent）内匹配
+    #[serde(default = "default_group_by_client")]
+    pub group_by_client: bool,
+    /// 父子 Flow 之间允许的最大时间间隔（毫秒）
+    #[serde(default = "default_max_gap_ms")]
+    pub max_gap_ms: u64,
+    /// 判定上下文压缩时允许的最大时间间隔（毫秒）
+    #[serde(default = "default_compaction_window_ms")]
+    pub compaction_window_ms: u64,
+    /// 部分前缀匹配时至少共享的消息数
+    #[serde(default = "default_min_shared_messages")]
+    pub min_shared_messages: usize,
+}
+
+fn default_group_by_client() -> bool {
+    true
+}
+
+fn default_max_gap_ms() -> u64 {
+    30 * 60 * 1000 // 30 分钟
+}
+
+fn default_compaction_window_ms() -> u64 {
+    5 * 60 * 1000 // 5 分钟
+}
+
+fn default_min_shared_messages() -> usize {
+    1
+}
+
+impl Default for ThreadingConfig {
+    fn default() -> Self {
+        Self {
+            group_by_client: default_group_by_client(),
+            max_gap_ms: default_max_gap_ms(),
+            compaction_window_ms: default_compaction_window_ms(),
+            min_shared_messages: default_min_shared_messages(),
+        }
+    }
+}
+
+// ============================================================================
+// 数据结构
+// ============================================================================
+
+/// 线程节点与父节点的关系
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ThreadRelation {
+    /// 线程根节点
+    Root,
+    /// 完整延续父节点的消息
+    Extends,
+    /// 从父节点分叉（编辑或重试产生的另一条路径）
+    Branch,
+    /// 父节点上下文被压缩后的延续
+    Compacted,
+}
+
+/// 线程节点
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ThreadNode {
+    /// Flow ID
+    pub flow_id: String,
+    /// 父 Flow ID
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parent_id: Option<String>,
+    /// 与父节点的关系
+    pub relation: ThreadRelation,
+    /// 深度（根节点为 0）
+    pub depth: usize,
+    /// 与父节点共享的消息数
+    pub shared_messages: usize,
+    /// 请求消息数
+    pub message_count: usize,
+    /// 子节点 Flow ID
+    pub children: Vec<String>,
+}
+
+/// 对话线程
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConversationThread {
+    /// 线程 ID（等于根 Flow ID）
+    pub id: String,
+    /// 节点列表（按时间排序）
+    pub nodes: Vec<ThreadNode>,
+    /// 开始时间
+    pub started_at: DateTime<Utc>,
+    /// 最后活动时间
+    pub updated_at: DateTime<Utc>,
+    /// 分支数量
+    pub branch_count: usize,
+    /// 压缩次数
+    pub compaction_count: usize,
+}
+
+impl ConversationThread {
+    /// 线程中的 Flow 数量
+    pub fn flow_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// 是否包含指定 Flow
+    pub fn contains(&self, flow_id: &str) -> bool {
+        self.nodes.iter().any(|n| n.flow_id == flow_id)
+    }
+}
+
+/// 时间线条目（对应线程中的一轮）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TimelineEntry {
+    /// Flow ID
+    pub flow_id: String,
+    /// 父 Flow ID
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parent_id: Option<String>,
+    /// 与父节点的关系
+    pub relation: ThreadRelation,
+    /// 轮次（线程树中的深度）
+    pub turn: usize,
+    /// 请求时间
+    pub timestamp: DateTime<Utc>,
+    /// 模型名称
+    pub model: String,
+    /// Flow 状态
+    pub state: FlowState,
+    /// 请求消息数
+    pub message_count: usize,
+    /// 相对父节点新增的消息数
+    pub new_messages: usize,
+    /// 输入 Token 数
+    pub input_tokens: u32,
+    /// 输出 Token 数
+    pub output_tokens: u32,
+    /// 缓存读取 Token 数
+    pub cache_read_tokens: u32,
+    /// 相对父节点的输入 Token 增长（压缩后为负数）
+    pub input_token_growth: i64,
+    /// 本轮响应中的工具调用名称
+    pub tool_calls: Vec<String>,
+    /// 本轮新增消息中的工具结果数量
+    pub tool_results: usize,
+    /// 总耗时（毫秒）
+    pub latency_ms: u64,
+    /// 首字节时间（毫秒）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ttfb_ms: Option<u64>,
+}
+
+/// 对话时间线
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConversationTimeline {
+    /// 线程 ID
+    pub thread_id: String,
+    /// 时间线条目（按时间排序）
+    pub entries: Vec<TimelineEntry>,
+    /// 输入 Token 总数
+    pub total_input_tokens: u64,
+    /// 输出 Token 总数
+    pub total_output_tokens: u64,
+    /// 单轮最大输入 Token 数（上下文峰值）
+    pub peak_input_tokens: u32,
+    /// 工具调用总数
+    pub total_tool_calls: usize,
+    /// 总耗时（毫秒）
+    pub total_latency_ms: u64,
+    /// 分支数量
+    pub branch_count: usize,
+    /// 压缩次数
+    pub compaction_count: usize,
+}
+
+// ============================================================================
+// 指纹
+// ============================================================================
+
+/// Flow 的对话指纹
+struct FlowFingerprint {
+    /// 分组键（系统提示词 + 客户端）
+    group: u64,
+    /// 每条请求消息的哈希
+    messages: Vec<u64>,
+    /// 响应作为 assistant 消息的哈希
+    response: Option<u64>,
+    /// 创建时间
+    created: DateTime<Utc>,
+}
+
+fn hash_text(hasher: &mut DefaultHasher, text: &str) {
+    text.trim().hash(hasher);
+}
+
+fn message_hash(message: &Message) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    format!("{:?}", message.role).hash(&mut hasher);
+    match &message.content {
+        MessageContent::Text(text) => hash_text(&mut hasher, text),
+        MessageContent::MultiModal(_) => hash_text(&mut hasher, &message.content.get_all_text()),
+    }
+    if let Some(ref tool_calls) = message.tool_calls {
+        for call in tool_calls {
+            call.id.hash(&mut hasher);
+            call.function.name.hash(&mut hasher);
+        }
+    }
+    if let Some(ref tool_result) = message.tool_result {
+        tool_result.tool_call_id.hash(&mut hasher);
+        hash_text(&mut hasher, &tool_result.content);
+    }
+    hasher.finish()
+}
+
+fn response_hash(flow: &LLMFlow) -> Option<u64> {
+    let response = flow.response.as_ref()?;
+    let mut hasher = DefaultHasher::new();
+    "Assistant".hash(&mut hasher);
+    hash_text(&mut hasher, &response.content);
+    for call in &response.tool_calls {
+        call.id.hash(&mut hasher);
+        call.function.name.hash(&mut hasher);
+    }
+    Some(hasher.finish())
+}
+
+fn common_prefix_len(a: &[u64], b: &[u64]) -> usize {
+    a.iter().zip(b.iter()).take_while(|(x, y)| x == y).count()
+}
+
+// ============================================================================
+// 线程重建器
+// ============================================================================
+
+/// 对话线程重建器
+pub struct ConversationThreader {
+    config: ThreadingConfig,
+}
+
+impl Default for ConversationThreader {
+    fn default() -> Self {
+        Self::new(ThreadingConfig::default())
+    }
+}
+
+impl ConversationThreader {
+    /// 创建新的线程重建器
+    pub fn new(config: ThreadingConfig) -> Self {
+        Self { config }
+    }
+
+    fn fingerprint(&self, flow: &LLMFlow) -> FlowFingerprint {
+        let mut hasher = DefaultHasher::new();
+        flow.request
+            .system_prompt
+            .as_deref()
+            .unwrap_or("")
+            .trim()
+            .hash(&mut hasher);
+        if self.config.group_by_client {
+            flow.metadata.client_info.ip.hash(&mut hasher);
+            flow.metadata.client_info.user_agent.hash(&mut hasher);
+        }
+
+        FlowFingerprint {
+            group: hasher.finish(),
+            messages: flow.request.messages.iter().map(message_hash).collect(),
+            response: response_hash(flow),
+            created: flow.timestamps.created,
+        }
+    }
+
+    /// 从 Flow 列表重建对话线程
+    ///
+    /// Flow 会先按创建时间排序；返回的线程按最后活动时间降序排列。
+    pub fn build_threads(&self, flows: &[LLMFlow]) -> Vec<ConversationThread> {
+        let mut order: Vec<usize> = (0..flows.len()).collect();
+        order.sort_by_key(|&i| flows[i].timestamps.created);
+
+        let fingerprints: Vec<FlowFingerprint> =
+            flows.iter().map(|f| self.fingerprint(f)).collect();
+
+        let max_gap = chrono::Duration::milliseconds(self.config.max_gap_ms as i64);
+        let compaction_window =
+            chrono::Duration::milliseconds(self.config.compaction_window_ms as i64);
+
+        // 每个 Flow 的 (父节点索引, 关系, 共享消息数)
+        let mut links: HashMap<usize, (Option<usize>, ThreadRelation, usize)> = HashMap::new();
+        let mut child_count: HashMap<usize, usize> = HashMap::new();
+        // 分组键 -> 已处理的 Flow 索引（按时间顺序）
+        let mut groups: HashMap<u64, Vec<usize>> = HashMap::new();
+
+        for &current in &order {
+            let fp = &fingerprints[current];
+            let candidates = groups.entry(fp.group).or_default();
+
+            // (候选索引, 候选消息数, 共享消息数)
+            let mut full_match: Option<(usize, usize, usize)> = None;
+            // (候选索引, 共享消息数)
+            let mut partial_match: Option<(usize, usize)> = None;
+
+            for &candidate in candidates.iter() {
+                let cfp = &fingerprints[candidate];
+                if fp.created - cfp.created > max_gap {
+                    continue;
+                }
+
+                let mut shared = common_prefix_len(&cfp.messages, &fp.messages);
+                if shared == cfp.messages.len() && fp.messages.len() > cfp.messages.len() {
+                    // 父节点的响应作为下一条 assistant 消息出现时计入共享部分
+                    if cfp.response.is_some() && fp.messages.get(shared).copied() == cfp.response {
+                        shared += 1;
+                    }
+                    // 取最长的完整前缀；相同长度时取最新的
+                    if full_match.is_none_or(|(_, len, _)| cfp.messages.len() >= len) {
+                        full_match = Some((candidate, cfp.messages.len(), shared));
+                    }
+                } else if shared >= self.config.min_shared_messages
+                    && partial_match.is_none_or(|(_, best)| shared >= best)
+                {
+                    partial_match = Some((candidate, shared));
+                }
+            }
+
+            let link = if let Some((parent, _, shared)) = full_match {
+                let relation = if child_count.get(&parent).copied().unwrap_or(0) > 0 {
+                    ThreadRelation::Branch
+                } else {
+                    ThreadRelation::Extends
+                };
+                (Some(parent), relation, shared)
+            } else if let Some((parent, shared)) = partial_match {
+                (Some(parent), ThreadRelation::Branch, shared)
+            } else if let Some(&latest) = candidates.last() {
+                // 上下文压缩：同组最近一个 Flow 之后消息数明显减少
+                let lfp = &fingerprints[latest];
+                if fp.created - lfp.created <= compaction_window
+                    && !fp.messages.is_empty()
+                    && fp.messages.len() < lfp.messages.len()
+                {
+                    (Some(latest), ThreadRelation::Compacted, 0)
+                } else {
+                    (None, ThreadRelation::Root, 0)
+                }
+            } else {
+                (None, ThreadRelation::Root, 0)
+            };
+
+            if let Some(parent) = link.0 {
+                *child_count.entry(parent).or_insert(0) += 1;
+            }
+            links.insert(current, link);
+            candidates.push(current);
+        }
+
+        self.assemble_threads(flows, &order, &links)
+    }
+
+    /// 根据父子关系组装线程
+    fn assemble_threads(
+        &self,
+        flows: &[LLMFlow],
+        order: &[usize],
+        links: &HashMap<usize, (Option<usize>, ThreadRelation, usize)>,
+    ) -> Vec<ConversationThread> {
+        let mut root_of: HashMap<usize, usize> = HashMap::new();
+        let mut depth_of: HashMap<usize, usize> = HashMap::new();
+        let mut threads: Vec<ConversationThread> = Vec::new();
+        let mut thread_index: HashMap<usize, usize> = HashMap::new();
+
+        for &current in order {
+            let (parent, relation, shared) = links[&current];
+            let flow = &flows[current];
+
+            let (root, depth) = match parent {
+                Some(p) => (root_of[&p], depth_of[&p] + 1),
+                None => (current, 0),
+            };
+            root_of.insert(current, root);
+            depth_of.insert(current, depth);
+
+            if parent.is_none() {
+                thread_index.insert(root, threads.len());
+                threads.push(ConversationThread {
+                    id: flow.id.clone(),
+                    nodes: Vec::new(),
+                    started_at: flow.timestamps.created,
+                    updated_at: flow.timestamps.created,
+                    branch_count: 0,
+                    compaction_count: 0,
+                });
+            }
+
+            let thread = &mut threads[thread_index[&root]];
+            if let Some(p) = parent {
+                let parent_id = &flows[p].id;
+                if let Some(node) = thread.nodes.iter_mut().find(|n| &n.flow_id == parent_id) {
+                    node.children.push(flow.id.clone());
+                }
+            }
+            match relation {
+                ThreadRelation::Branch => thread.branch_count += 1,
+                ThreadRelation::Compacted => thread.compaction_count += 1,
+                _ => {}
+            }
+            thread.updated_at = thread.updated_at.max(flow.timestamps.created);
+            thread.nodes.push(ThreadNode {
+                flow_id: flow.id.clone(),
+                parent_id: parent.map(|p| flows[p].id.clone()),
+                relation,
+                depth,
+                shared_messages: shared,
+                message_count: flow.request.messages.len(),
+                children: Vec::new(),
+            });
+        }
+
+        threads.sort_by_key(|t| std::cmp::Reverse(t.updated_at));
+        threads
+    }
+
+    /// 查找包含指定 Flow 的线程
+    pub fn find_thread(&self, flow_id: &str, flows: &[LLMFlow]) -> Option<ConversationThread> {
+        self.build_threads(flows)
+            .into_iter()
+            .find(|t| t.contains(flow_id))
+    }
+
+    /// 构建线程的时间线
+    pub fn build_timeline(
+        &self,
+        thread: &ConversationThread,
+        flows: &[LLMFlow],
+    ) -> ConversationTimeline {
+        let by_id: HashMap<&str, &LLMFlow> = flows.iter().map(|f| (f.id.as_str(), f)).collect();
+
+        let mut entries = Vec::with_capacity(thread.nodes.len());
+        for node in &thread.nodes {
+            let flow = match by_id.get(node.flow_id.as_str()) {
+                Some(flow) => *flow,
+                None => continue,
+            };
+            let parent = node
+                .parent_id
+                .as_deref()
+                .and_then(|id| by_id.get(id).copied());
+
+            let usage = flow.response.as_ref().map(|r| r.usage.clone());
+            let input_tokens = usage.as_ref().map_or(0, |u| u.input_tokens);
+            let parent_input = parent
+                .and_then(|p| p.response.as_ref())
+                .map_or(0, |r| r.usage.input_tokens);
+            let new_messages = node.message_count.saturating_sub(node.shared_messages);
+
+            entries.push(TimelineEntry {
+                flow_id: flow.id.clone(),
+                parent_id: node.parent_id.clone(),
+                relation: node.relation,
+                turn: node.depth,
+                timestamp: flow.timestamps.created,
+                model: flow.request.model.clone(),
+                state: flow.state.clone(),
+                message_count: node.message_count,
+                new_messages,
+                input_tokens,
+                output_tokens: usage.as_ref().map_or(0, |u| u.output_tokens),
+                cache_read_tokens: usage
+                    .as_ref()
+                    .and_then(|u| u.cache_read_tokens)
+                    .unwrap_or(0),
+                input_token_growth: if parent.is_some() {
+                    input_tokens as i64 - parent_input as i64
+                } else {
+                    input_tokens as i64
+                },
+                tool_calls: flow
+                    .response
+                    .as_ref()
+                    .map(|r| {
+                        r.tool_calls
+                            .iter()
+                            .map(|c| c.function.name.clone())
+                            .collect()
+                    })
+                    .unwrap_or_default(),
+                tool_results: flow
+                    .request
+                    .messages
+                    .iter()
+                    .skip(node.message_count - new_messages)
+                    .filter(|m| m.tool_result.is_some())
+                    .count(),
+                latency_ms: flow.timestamps.duration_ms,
+                ttfb_ms: flow.timestamps.ttfb_ms,
+            });
+        }
+
+        ConversationTimeline {
+            thread_id: thread.id.clone(),
+            total_input_tokens: entries.iter().map(|e| e.input_tokens as u64).sum(),
+            total_output_tokens: entries.iter().map(|e| e.output_tokens as u64).sum(),
+            peak_input_tokens: entries.iter().map(|e| e.input_tokens).max().unwrap_or(0),
+            total_tool_calls: entries.iter().map(|e| e.tool_calls.len()).sum(),
+            total_latency_ms: entries.iter().map(|e| e.latency_ms).sum(),
+            branch_count: thread.branch_count,
+            compaction_count: thread.compaction_count,
+            entries,
+        }
+    }
+
+    /// 构建包含指定 Flow 的线程时间线
+    pub fn timeline_for_flow(
+        &self,
+        flow_id: &str,
+        flows: &[LLMFlow],
+    ) -> Option<ConversationTimeline> {
+        let thread = self.find_thread(flow_id, flows)?;
+        Some(self.build_timeline(&thread, flows))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flow_monitor::models::*;
+    use chrono::Duration;
+
+    fn text_message(role: MessageRole, text: &str) -> Message {
+        Message {
+            role,
+            content: MessageContent::Text(text.to_string()),
+            ..Default::default()
+        }
+    }
+
+    fn tool_result_message(call_id: &str, content: &str) -> Message {
+        Message {
+            role: MessageRole::Tool,
+            content: MessageContent::Text(String::new()),
+            tool_result: Some(ToolResult {
+                tool_call_id: call_id.to_string(),
+                content: content.to_string(),
+                is_error: false,
+            }),
+            ..Default::default()
+        }
+    }
+
+    fn create_flow(
+        id: &str,
+        offset_secs: i64,
+        messages: Vec<Message>,
+        reply: &str,
+        input_tokens: u32,
+    ) -> LLMFlow {
+        let request = LLMRequest {
+            model: "claude-sonnet-4".to_string(),
+            system_prompt: Some("You are a coding agent.".to_string()),
+            messages,
+            ..Default::default()
+        };
+        let mut flow = LLMFlow::new(
+            id.to_string(),
+            FlowType::AnthropicMessages,
+            request,
+            FlowMetadata::default(),
+        );
+        flow.timestamps.created =
+            Utc::now() - Duration::minutes(10) + Duration::seconds(offset_secs);
+        flow.timestamps.duration_ms = 1000;
+        flow.state = FlowState::Completed;
+        flow.response = Some(LLMResponse {
+            content: reply.to_string(),
+            usage: TokenUsage {
+                input_tokens,
+                output_tokens: 50,
+                total_tokens: input_tokens + 50,
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+        flow
+    }
+
+    fn node<'a>(thread: &'a ConversationThread, id: &str) -> &'a ThreadNode {
+        thread.nodes.iter().find(|n| n.flow_id == id).unwrap()
+    }
+
+    #[test]
+    fn test_linear_thread() {
+        let u1 = text_message(MessageRole::User, "fix the bug");
+        let a1 = text_message(MessageRole::Assistant, "reading file");
+        let t1 = tool_result_message("call_1", "fn main() {}");
+
+        let flows = vec![
+            create_flow("f1", 0, vec![u1.clone()], "reading file", 100),
+            create_flow(
+                "f2",
+                10,
+                vec![u1.clone(), a1.clone(), t1.clone()],
+                "done",
+                180,
+            ),
+        ];
+
+        let threads = ConversationThreader::default().build_threads(&flows);
+        assert_eq!(threads.len(), 1);
+        let thread = &threads[0];
+        assert_eq!(thread.id, "f1");
+        assert_eq!(node(thread, "f2").parent_id.as_deref(), Some("f1"));
+        assert_eq!(node(thread, "f2").relation, ThreadRelation::Extends);
+        // 父节点的响应被计入共享消息
+        assert_eq!(node(thread, "f2").shared_messages, 2);
+        assert_eq!(node(thread, "f1").children, vec!["f2".to_string()]);
+    }
+
+    #[test]
+    fn test_branch_after_edit() {
+        let u1 = text_message(MessageRole::User, "write a parser");
+        let a1 = text_message(MessageRole::Assistant, "ok");
+        let u2 = text_message(MessageRole::User, "use nom");
+        let u2_edited = text_message(MessageRole::User, "use pest");
+
+        let flows = vec![
+            create_flow("f1", 0, vec![u1.clone()], "ok", 100),
+            create_flow("f2", 10, vec![u1.clone(), a1.clone(), u2], "sure", 150),
+            create_flow(
+                "f3",
+                20,
+                vec![u1.clone(), a1.clone(), u2_edited],
+                "sure",
+                150,
+            ),
+        ];
+
+        let threads = ConversationThreader::default().build_threads(&flows);
+        assert_eq!(threads.len(), 1);
+        let thread = &threads[0];
+        assert_eq!(node(thread, "f3").parent_id.as_deref(), Some("f1"));
+        assert_eq!(node(thread, "f3").relation, ThreadRelation::Branch);
+        assert_eq!(thread.branch_count, 1);
+        assert_eq!(node(thread, "f1").children.len(), 2);
+    }
+
+    #[test]
+    fn test_compaction_links_to_latest_flow() {
+        let u1 = text_message(MessageRole::User, "start");
+        let a1 = text_message(MessageRole::Assistant, "step");
+        let u2 = text_message(MessageRole::User, "continue");
+        let summary = text_message(MessageRole::User, "Summary of the conversation so far");
+
+        let flows = vec![
+            create_flow("f1", 0, vec![u1.clone()], "step", 100),
+            create_flow("f2", 10, vec![u1, a1, u2], "more", 5000),
+            create_flow("f3", 20, vec![summary], "resumed", 800),
+        ];
+
+        let threads = ConversationThreader::default().build_threads(&flows);
+        assert_eq!(threads.len(), 1);
+        let thread = &threads[0];
+        assert_eq!(node(thread, "f3").parent_id.as_deref(), Some("f2"));
+        assert_eq!(node(thread, "f3").relation, ThreadRelation::Compacted);
+        assert_eq!(thread.compaction_count, 1);
+    }
+
+    #[test]
+    fn test_unrelated_conversations_are_separate() {
+        let mut other = create_flow(
+            "g1",
+            5,
+            vec![text_message(MessageRole::User, "title please")],
+            "Title",
+            20,
+        );
+        other.request.system_prompt = Some("Generate a title.".to_string());
+
+        let flows = vec![
+            create_flow(
+                "f1",
+                0,
+                vec![text_message(MessageRole::User, "hello")],
+                "hi",
+                10,
+            ),
+            other,
+        ];
+
+        let threads = ConversationThreader::default().build_threads(&flows);
+        assert_eq!(threads.len(), 2);
+        assert!(threads.iter().all(|t| t.flow_count() == 1));
+    }
+
+    #[test]
+    fn test_max_gap_starts_new_thread() {
+        let u1 = text_message(MessageRole::User, "hello");
+        let a1 = text_message(MessageRole::Assistant, "hi");
+        let u2 = text_message(MessageRole::User, "again");
+        let flows = vec![
+            create_flow("f1", 0, vec![u1.clone()], "hi", 10),
+            create_flow("f2", 400, vec![u1, a1, u2], "yes", 20),
+        ];
+
+        let threader = ConversationThreader::new(ThreadingConfig {
+            max_gap_ms: 60_000,
+            compaction_window_ms: 60_000,
+            ..Default::default()
+        });
+        assert_eq!(threader.build_threads(&flows).len(), 2);
+    }
+
+    #[test]
+    fn test_timeline_token_growth_and_tools() {
+        let call = ToolCall {
+            id: "call_1".to_string(),
+            tool_type: "function".to_string(),
+            function: FunctionCall {
+                name: "read_file".to_string(),
+                arguments: "{}".to_string(),
+            },
+        };
+        let u1 = text_message(MessageRole::User, "fix the bug");
+        let mut a1 = text_message(MessageRole::Assistant, "reading file");
+        a1.tool_calls = Some(vec![call.clone()]);
+        let t1 = tool_result_message("call_1", "fn main() {}");
+
+        let mut first = create_flow("f1", 0, vec![u1.clone()], "reading file", 100);
+        if let Some(ref mut response) = first.response {
+            response.tool_calls.push(call);
+        }
+        let second = create_flow("f2", 10, vec![u1, a1, t1], "done", 180);
+        let flows = vec![first, second];
+
+        let timeline = ConversationThreader::default()
+            .timeline_for_flow("f2", &flows)
+            .unwrap();
+
+        assert_eq!(timeline.thread_id, "f1");
+        assert_eq!(timeline.entries.len(), 2);
+        assert_eq!(
+            timeline.entries[0].tool_calls,
+            vec!["read_file".to_string()]
+        );
+        assert_eq!(timeline.entries[1].input_token_growth, 80);
+        assert_eq!(timeline.entries[1].new_messages, 1);
+        assert_eq!(timeline.entries[1].tool_results, 1);
+        assert_eq!(timeline.peak_input_tokens, 180);
+        assert_eq!(timeline.total_tool_calls, 1);
+        assert_eq!(timeline.total_latency_ms, 2000);
+    }
+}
@@ -37,8 +37,8 @@ use commands::api_key_provider_cmd::ApiKeyProviderServiceState;
 use commands::browser_interceptor_cmd::BrowserInterceptorState;
 use commands::flow_monitor_cmd::{
     BatchOperationsState, BookmarkManagerState, EnhancedStatsServiceState, FlowInterceptorState,
-    FlowMonitorState, FlowQueryServiceState, FlowReplayerState, QuickFilterManagerState,
-    SessionManagerState,
+    FlowMonitorState, FlowQueryServiceState, FlowReplayerState, FlowRetentionState,
+    QuickFilterManagerState, SessionManagerState,
 };
 use commands::machine_id_cmd::MachineIdState;
 use commands::plugin_cmd::PluginManagerState;
@@ -50,7 +50,7 @@ use commands::skill_cmd::SkillServiceState;
 use flow_monitor::{
     BatchOperations, BookmarkManager, EnhancedStatsService, FlowFileStore, FlowInterceptor,
     FlowMonitor, FlowMonitorConfig, FlowQueryService, FlowReplayer, InterceptConfig,
-    QuickFilterManager, SessionManager,
+    QuickFilterManager, RetentionManager, SessionManager,
 };
 use services::api_key_provider_service::ApiKeyProviderService;
 use services::provider_pool_service::ProviderPoolService;
@@ -88,6 +88,8 @@ pub enum ProviderType {
     /// iFlow
     #[serde(rename = "iflow")]
     IFlow,
+    /// 插件提供的 Provider (路由名为 `plugin:<id>`，具体标识保存在凭证中)
+    Plugin,
 }
 
 impl std::fmt::Display for ProviderType {
@@ -104,6 +106,7 @@ impl std::fmt::Display for ProviderType {
             ProviderType::Codex => write!(f, "codex"),
             ProviderType::ClaudeOAuth => write!(f, "claude_oauth"),
             ProviderType::IFlow => write!(f, "iflow"),
+            ProviderType::Plugin => write!(f, "plugin"),
         }
     }
 }
@@ -124,6 +127,8 @@ impl std::str::FromStr for ProviderType {
             "codex" => Ok(ProviderType::Codex),
             "claude_oauth" => Ok(ProviderType::ClaudeOAuth),
             "iflow" => Ok(ProviderType::IFlow),
+            "plugin" => Ok(ProviderType::Plugin),
+            other if plugin::plugin_provider_id(other).is_some() => Ok(ProviderType::Plugin),
             _ => Err(format!("Invalid provider: {s}")),
         }
     }
@@ -169,6 +174,13 @@ mod tests {
             ProviderType::Vertex
         );
 
+        // 插件 Provider
+        assert_eq!(
+            "plugin:my-llm".parse::<ProviderType>().unwrap(),
+            ProviderType::Plugin
+        );
+        assert!("plugin:".parse::<ProviderType>().is_err());
+
         // 测试无效的 provider
         assert!("invalid".parse::<ProviderType>().is_err());
     }
@@ -1137,7 +1149,7 @@ async fn check_api_compatibility(
             ("claude-sonnet-4-5", "tool_call"),
         ],
         ProviderType::IFlow => vec![("gpt-4o", "basic"), ("gpt-4o", "tool_call")],
-        ProviderType::OpenAI | ProviderType::Claude => vec![],
+        ProviderType::OpenAI | ProviderType::Claude | ProviderType::Plugin => vec![],
     };
 
     for (model, test_type) in test_cases {
@@ -1512,7 +1524,6 @@ pub fn run() {
         eprintln!("检测到远程管理已开启，但当前版本未启用 TLS，已中止启动。");
         return;
     }
-    let state: AppState = Arc::new(RwLock::new(server::ServerState::new(config.clone())));
     let logs: LogState = Arc::new(RwLock::new(logger::LogStore::with_config(&config.logging)));
 
     // Initialize database for Switch functionality
@@ -1686,7 +1697,13 @@ pub fn run() {
     // 初始化书签管理器
     let bookmark_manager =
         Arc::new(BookmarkManager::new(db_path).expect("Failed to create BookmarkManager"));
-    let bookmark_manager_state = BookmarkManagerState(bookmark_manager);
+    let bookmark_manager_state = BookmarkManagerState(bookmark_manager.clone());
+
+    // 初始化 Flow 保留策略管理器（仅在文件存储可用时）
+    let flow_retention_manager = flow_file_store
+        .clone()
+        .map(|store| Arc::new(RetentionManager::new(store, Some(bookmark_manager))));
+    let flow_retention_state = FlowRetentionState(flow_retention_manager.clone());
 
     // 初始化增强统计服务
     let enhanced_stats_service = Arc::new(EnhancedStatsService::new(flow_monitor.memory_store()));
@@ -1703,7 +1720,24 @@ pub fn run() {
     let browser_interceptor_state = BrowserInterceptorState::default();
 
     // Initialize NativeAgentState
-    let native_agent_state = NativeAgentState::new();
+    let native_agent_state = {
+        let state = NativeAgentState::with_database(db.clone());
+        // 工具只能访问 home 目录，检查点也只追踪 home 目录内的文件
+        match dirs::home_dir() {
+            Some(home_dir) => {
+                let objects_dir = dirs::data_dir()
+                    .unwrap_or_else(|| std::path::PathBuf::from("."))
+                    .join("proxycast")
+                    .join("agent_checkpoints");
+                state.with_checkpoints(db.clone(), objects_dir, home_dir)
+            }
+            None => state,
+        }
+    };
+    // API Server 与 Tauri 命令共享同一个原生 Agent（会话、审批、检查点）
+    let state: AppState = Arc::new(RwLock::new(
+        server::ServerState::new(config.clone()).with_native_agent(native_agent_state.clone()),
+    ));
 
     // FlowQueryService 需要 file_store，如果没有则创建一个临时的
     let flow_query_service_state = if let Some(file_store) = flow_file_store {
@@ -1739,6 +1773,7 @@ pub fn run() {
     let shared_logger_clone = shared_logger.clone();
     let flow_monitor_clone = flow_monitor.clone();
     let flow_interceptor_clone = flow_interceptor.clone();
+    let flow_retention_clone = flow_retention_manager.clone();
 
     let mut builder = tauri::Builder::default()
         .plugin(tauri_plugin_shell::init())
@@ -1801,6 +1836,7 @@ pub fn run() {
         .manage(session_manager_state)
         .manage(quick_filter_manager_state)
         .manage(bookmark_manager_state)
+        .manage(flow_retention_state)
         .manage(enhanced_stats_service_state)
         .manage(batch_operations_state)
         .manage(browser_interceptor_state)
@@ -1864,6 +1900,10 @@ pub fn run() {
                     app.manage(tray_state);
                 }
             }
+            // 启动 Flow 保留策略后台压缩
+            if let Some(retention) = flow_retention_clone {
+                tauri::async_runtime::spawn(retention.run_background());
+            }
             // 自动启动服务器
             let state = state_clone.clone();
             let logs = logs_clone.clone();
@@ -2269,6 +2309,14 @@ pub fn run() {
             commands::plugin_install_cmd::list_installed_plugins,
             commands::plugin_install_cmd::get_installed_plugin,
             commands::plugin_install_cmd::is_plugin_installed,
+            commands::plugin_install_cmd::install_plugin_from_index,
+            commands::plugin_install_cmd::check_plugin_updates,
+            commands::plugin_install_cmd::update_plugin,
+            commands::plugin_install_cmd::rollback_plugin,
+            commands::plugin_install_cmd::pin_plugin_version,
+            commands::plugin_install_cmd::list_plugin_trusted_keys,
+            commands::plugin_install_cmd::add_plugin_trusted_key,
+            commands::plugin_install_cmd::remove_plugin_trusted_key,
             // Plugin UI commands
             commands::plugin_cmd::get_plugins_with_ui,
             // Flow Monitor commands
@@ -2290,6 +2338,8 @@ pub fn run() {
             commands::flow_monitor_cmd::create_test_flows,
             commands::flow_monitor_cmd::enable_flow_monitor,
             commands::flow_monitor_cmd::disable_flow_monitor,
+            commands::flow_monitor_cmd::get_capture_redaction_config,
+            commands::flow_monitor_cmd::set_capture_redaction_config,
             commands::flow_monitor_cmd::subscribe_flow_events,
             commands::flow_monitor_cmd::get_all_flow_tags,
             // Flow Monitor filter expression commands
@@ -2298,6 +2348,7 @@ pub fn run() {
             commands::flow_monitor_cmd::get_filter_help_items,
             commands::flow_monitor_cmd::get_filter_help_text,
             commands::flow_monitor_cmd::query_flows_with_expression,
+            commands::flow_monitor_cmd::query_flows_aggregate,
             // Flow Interceptor commands
             commands::flow_monitor_cmd::intercept_config_get,
             commands::flow_monitor_cmd::intercept_config_set,
@@ -2338,6 +2389,8 @@ pub fn run() {
             commands::flow_monitor_cmd::get_auto_session_config,
             commands::flow_monitor_cmd::set_auto_session_config,
             commands::flow_monitor_cmd::register_active_session,
+            commands::flow_monitor_cmd::get_conversation_threads,
+            commands::flow_monitor_cmd::get_conversation_timeline,
             // Quick Filter commands
             commands::flow_monitor_cmd::save_quick_filter,
             commands::flow_monitor_cmd::get_quick_filter,
@@ -2381,6 +2434,12 @@ pub fn run() {
             commands::flow_monitor_cmd::batch_export_flows,
             commands::flow_monitor_cmd::batch_delete_flows,
             commands::flow_monitor_cmd::batch_add_to_session,
+            // Flow Retention commands
+            commands::flow_monitor_cmd::get_flow_retention_policy,
+            commands::flow_monitor_cmd::set_flow_retention_policy,
+            commands::flow_monitor_cmd::run_flow_retention,
+            commands::flow_monitor_cmd::get_last_flow_retention_run,
+            commands::flow_monitor_cmd::get_flow_storage_usage,
             // Window control commands
             commands::window_cmd::get_window_size,
             commands::window_cmd::set_window_size,
@@ -2454,6 +2513,22 @@ pub fn run() {
             commands::native_agent_cmd::native_agent_get_session,
             commands::native_agent_cmd::native_agent_delete_session,
             commands::native_agent_cmd::native_agent_list_sessions,
+            commands::native_agent_cmd::native_agent_search_sessions,
+            commands::native_agent_cmd::native_agent_fork_session,
+            commands::native_agent_cmd::native_agent_rename_session,
+            commands::native_agent_cmd::native_agent_archive_session,
+            commands::native_agent_cmd::native_agent_export_session,
+            commands::native_agent_cmd::native_agent_mcp_connect,
+            commands::native_agent_cmd::native_agent_mcp_status,
+            commands::native_agent_cmd::native_agent_resolve_approval,
+            commands::native_agent_cmd::native_agent_pending_approvals,
+            commands::native_agent_cmd::native_agent_approval_audit,
+            commands::native_agent_cmd::native_agent_compact_session,
+            commands::native_agent_cmd::native_agent_pin_message,
+            commands::native_agent_cmd::native_agent_list_checkpoints,
+            commands::native_agent_cmd::native_agent_checkpoint_diff,
+            commands::native_agent_cmd::native_agent_restore_checkpoint_files,
+            commands::native_agent_cmd::native_agent_rewind_session,
             // Network commands
             commands::network_cmd::get_network_info,
         ])
@@ -136,10 +136,10 @@ impl<S> ManagementAuthService<S> {
             .map(|ci| ci.0)
     }
 
-    fn get_client_id(req: &Request<Body>) -> String {
+    fn get_client_id(addr: Option<&SocketAddr>) -> String {
         // 安全修复：只使用真实的连接地址，不信任 X-Forwarded-For
         // X-Forwarded-For 可被伪造，用于绕过限速或导致 failure_map 无界增长
-        if let Some(addr) = Self::get_client_addr(req) {
+        if let Some(addr) = addr {
             return addr.ip().to_string();
         }
         "unknown".to_string()
@@ -225,77 +225,101 @@ where
         let mut inner = self.inner.clone();
 
         Box::pin(async move {
-            let client_id = Self::get_client_id(&req);
-            if !Self::check_rate_limit(&client_id) {
-                return Ok(create_error_response(
-                    StatusCode::TOO_MANY_REQUESTS,
-                    "Too many failed authentication attempts",
-                ));
-            }
-
-            // 1. 检查 secret_key 是否为空（禁用管理 API）
-            let secret_key = match &config.secret_key {
-                Some(key) if !key.is_empty() => key.clone(),
-                _ => {
-                    tracing::debug!("[MANAGEMENT_AUTH] Management API disabled (no secret_key)");
-                    return Ok(create_error_response(
-                        StatusCode::NOT_FOUND,
-                        "Management API is disabled",
-                    ));
-                }
-            };
-
-            // 2. 检查 allow_remote 限制
             let client_addr = Self::get_client_addr(&req);
-            let is_localhost = Self::is_localhost(client_addr.as_ref());
-
-            if !config.allow_remote && !is_localhost {
-                tracing::warn!(
-                    "[MANAGEMENT_AUTH] Remote access denied from {:?}",
-                    client_addr
-                );
-                return Ok(create_error_response(
-                    StatusCode::FORBIDDEN,
-                    "Remote access is not allowed",
-                ));
-            }
-
-            // 3. 验证 secret_key
             let provided_key = Self::extract_secret_key(&req);
-            match provided_key {
-                Some(key) if Self::secret_key_matches(&key, &secret_key) => {
-                    // 认证成功，继续处理请求
-                    tracing::debug!("[MANAGEMENT_AUTH] Auth successful from {:?}", client_addr);
-                    Self::record_success(&client_id);
-                    inner.call(req).await
-                }
-                Some(_) => {
-                    tracing::warn!(
-                        "[MANAGEMENT_AUTH] Invalid secret_key from {:?}",
-                        client_addr
-                    );
-                    Self::record_failure(&client_id);
-                    Ok(create_error_response(
-                        StatusCode::UNAUTHORIZED,
-                        "Invalid secret key",
-                    ))
-                }
-                None => {
-                    tracing::warn!(
-                        "[MANAGEMENT_AUTH] Missing secret_key from {:?}",
-                        client_addr
-                    );
-                    Self::record_failure(&client_id);
-                    Ok(create_error_response(
-                        StatusCode::UNAUTHORIZED,
-                        "Missing secret key",
-                    ))
-                }
+
+            match authorize_management_key(&config, provided_key.as_deref(), client_addr) {
+                Ok(()) => inner.call(req).await,
+                Err(rejection) => Ok(create_error_response(rejection.status, rejection.message)),
             }
         })
     }
 }
 
+/// 管理密钥校验失败
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ManagementAuthRejection {
+    /// HTTP 状态码
+    pub status: StatusCode,
+    /// 错误消息
+    pub message: &'static str,
+}
+
+/// 校验管理密钥
+///
+/// 供不经过 `ManagementAuthLayer` 的入口（如 WebSocket 订阅）复用同一套规则，
+/// 包括失败限速、`allow_remote` 限制和常量时间比较。
+pub fn authorize_management_key(
+    config: &RemoteManagementConfig,
+    provided_key: Option<&str>,
+    client_addr: Option<SocketAddr>,
+) -> Result<(), ManagementAuthRejection> {
+    type Auth = ManagementAuthService<()>;
+
+    let client_id = Auth::get_client_id(client_addr.as_ref());
+    if !Auth::check_rate_limit(&client_id) {
+        return Err(ManagementAuthRejection {
+            status: StatusCode::TOO_MANY_REQUESTS,
+            message: "Too many failed authentication attempts",
+        });
+    }
+
+    // 1. 检查 secret_key 是否为空（禁用管理 API）
+    let secret_key = match &config.secret_key {
+        Some(key) if !key.is_empty() => key,
+        _ => {
+            tracing::debug!("[MANAGEMENT_AUTH] Management API disabled (no secret_key)");
+            return Err(ManagementAuthRejection {
+                status: StatusCode::NOT_FOUND,
+                message: "Management API is disabled",
+            });
+        }
+    };
+
+    // 2. 检查 allow_remote 限制
+    if !config.allow_remote && !Auth::is_localhost(client_addr.as_ref()) {
+        tracing::warn!(
+            "[MANAGEMENT_AUTH] Remote access denied from {:?}",
+            client_addr
+        );
+        return Err(ManagementAuthRejection {
+            status: StatusCode::FORBIDDEN,
+            message: "Remote access is not allowed",
+        });
+    }
+
+    // 3. 验证 secret_key
+    match provided_key {
+        Some(key) if Auth::secret_key_matches(key, secret_key) => {
+            tracing::debug!("[MANAGEMENT_AUTH] Auth successful from {:?}", client_addr);
+            Auth::record_success(&client_id);
+            Ok(())
+        }
+        Some(_) => {
+            tracing::warn!(
+                "[MANAGEMENT_AUTH] Invalid secret_key from {:?}",
+                client_addr
+            );
+            Auth::record_failure(&client_id);
+            Err(ManagementAuthRejection {
+                status: StatusCode::UNAUTHORIZED,
+                message: "Invalid secret key",
+            })
+        }
+        None => {
+            tracing::warn!(
+                "[MANAGEMENT_AUTH] Missing secret_key from {:?}",
+                client_addr
+            );
+            Auth::record_failure(&client_id);
+            Err(ManagementAuthRejection {
+                status: StatusCode::UNAUTHORIZED,
+                message: "Missing secret key",
+            })
+        }
+    }
+}
+
 /// 创建错误响应
 fn create_error_response(status: StatusCode, message: &str) -> Response<Body> {
     let body = serde_json::json!({
@@ -84,6 +84,13 @@ pub enum CredentialData {
     IFlowOAuth { creds_file_path: String },
     /// iFlow Cookie 凭证
     IFlowCookie { creds_file_path: String },
+    /// 插件 Provider 凭证（字段结构由插件声明的 credential_schema 定义）
+    Plugin {
+        /// 插件 Provider 标识（路由名为 `plugin:<provider>`）
+        provider: String,
+        #[serde(default)]
+        fields: serde_json::Value,
+    },
 }
 
 impl CredentialData {
@@ -132,6 +139,7 @@ impl CredentialData {
             CredentialData::IFlowCookie { creds_file_path } => {
                 format!("iFlow Cookie: {}", mask_path(creds_file_path))
             }
+            CredentialData::Plugin { provider, .. } => format!("Plugin: {}", provider),
         }
     }
 
@@ -150,6 +158,15 @@ impl CredentialData {
             CredentialData::ClaudeOAuth { .. } => PoolProviderType::ClaudeOAuth,
             CredentialData::IFlowOAuth { .. } => PoolProviderType::IFlow,
             CredentialData::IFlowCookie { .. } => PoolProviderType::IFlow,
+            CredentialData::Plugin { .. } => PoolProviderType::Plugin,
+        }
+    }
+
+    /// 插件 Provider 标识（仅插件凭证）
+    pub fn plugin_provider(&self) -> Option<&str> {
+        match self {
+            CredentialData::Plugin { provider, .. } => Some(provider),
+            _ => None,
         }
     }
 }
@@ -508,6 +525,8 @@ pub fn get_default_check_model(provider_type: PoolProviderType) -> &'static str
         PoolProviderType::Codex => "gpt-4o-mini",
         PoolProviderType::ClaudeOAuth => "claude-sonnet-4-5-20250929",
         PoolProviderType::IFlow => "deepseek-chat",
+        // 插件 Provider 的模型由插件声明，需在凭证上配置 check_model_name
+        PoolProviderType::Plugin => "",
     }
 }
 
@@ -560,6 +579,7 @@ fn get_credential_type(cred: &CredentialData) -> String {
         CredentialData::ClaudeOAuth { .. } => "claude_oauth".to_string(),
         CredentialData::IFlowOAuth { .. } => "iflow_oauth".to_string(),
         CredentialData::IFlowCookie { .. } => "iflow_cookie".to_string(),
+        CredentialData::Plugin { .. } => "plugin".to_string(),
     }
 }
 
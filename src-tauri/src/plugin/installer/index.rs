@@ -0,0 +1,387 @@
+//! 签名插件索引
+//!
+//! 索引为 `index.json` 及其分离签名 `index.json.sig`，列出插件、版本和兼容范围。
+//! 索引可放在本地目录、`file://` URL 或 HTTP(S) 地址，便于离线环境自建插件源；
+//! 版本条目中的相对 `url` 按索引所在位置解析。
+//!
+//! ```json
+//! {
+//!   "plugins": [{
+//!     "name": "my-plugin",
+//!     "description": "...",
+//!     "versions": [{
+//!       "version": "1.2.0",
+//!       "url": "packages/my-plugin-1.2.0.zip",
+//!       "sha256": "…",
+//!       "proxycast": ">=0.27, <0.30"
+//!     }]
+//!   }]
+//! }
+//! ```
+
+use std::path::{Path, PathBuf};
+
+use semver::{Version, VersionReq};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use super::signature::{signature_path, verify_signature, PackageSignature, TrustedKey};
+use super::types::InstallError;
+
+/// 索引文件名
+pub const INDEX_FILE: &str = "index.json";
+
+/// 插件索引
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PluginIndex {
+    /// 索引名称
+    #[serde(default)]
+    pub name: Option<String>,
+    /// 插件列表
+    #[serde(default)]
+    pub plugins: Vec<IndexPlugin>,
+}
+
+/// 索引中的插件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexPlugin {
+    /// 插件名称
+    pub name: String,
+    /// 描述
+    #[serde(default)]
+    pub description: String,
+    /// 作者
+    #[serde(default)]
+    pub author: Option<String>,
+    /// 可用版本
+    #[serde(default)]
+    pub versions: Vec<IndexVersion>,
+}
+
+/// 索引中的插件版本
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexVersion {
+    /// 版本号
+    pub version: String,
+    /// 插件包地址（可为相对索引位置的路径）
+    pub url: String,
+    /// 插件包 SHA-256
+    pub sha256: String,
+    /// 插件包签名，未提供时读取 `<url>.sig`
+    #[serde(default)]
+    pub signature: Option<PackageSignature>,
+    /// 兼容的 ProxyCast 版本范围 (semver，如 `>=0.27, <0.30`)，为空表示不限制
+    #[serde(default)]
+    pub proxycast: Option<String>,
+}
+
+impl IndexVersion {
+    /// 是否兼容指定的宿主版本
+    pub fn is_compatible(&self, host_version: &Version) -> bool {
+        match &self.proxycast {
+            None => true,
+            Some(range) => VersionReq::parse(range)
+                .map(|req| req.matches(host_version))
+                .unwrap_or(false),
+        }
+    }
+}
+
+impl IndexPlugin {
+    /// 选择要安装的版本：指定版本时精确匹配，否则取兼容的最新版本
+    pub fn resolve(
+        &self,
+        version: Option<&str>,
+        host_version: &Version,
+    ) -> Result<&IndexVersion, InstallError> {
+        if let Some(version) = version {
+            let entry = self
+                .versions
+                .iter()
+                .find(|v| v.version == version)
+                .ok_or_else(|| InstallError::NotFound(format!("{}@{}", self.name, version)))?;
+            if !entry.is_compatible(host_version) {
+                return Err(InstallError::Incompatible(format!(
+                    "{}@{} 要求 ProxyCast {}",
+                    self.name,
+                    version,
+                    entry.proxycast.as_deref().unwrap_or_default()
+                )));
+            }
+            return Ok(entry);
+        }
+
+        self.versions
+            .iter()
+            .filter(|v| v.is_compatible(host_version))
+            .filter_map(|v| parse_version(&v.version).map(|parsed| (parsed, v)))
+            .max_by(|a, b| a.0.cmp(&b.0))
+            .map(|(_, v)| v)
+            .ok_or_else(|| {
+                InstallError::Incompatible(format!(
+                    "{} 没有兼容 ProxyCast {} 的版本",
+                    self.name, host_version
+                ))
+            })
+    }
+}
+
+impl PluginIndex {
+    /// 查找插件
+    pub fn find(&self, name: &str) -> Option<&IndexPlugin> {
+        self.plugins.iter().find(|p| p.name == name)
+    }
+}
+
+/// 解析版本号，允许省略补丁号 (如 `1.2`)
+pub fn parse_version(version: &str) -> Option<Version> {
+    let version = version.trim().trim_start_matches('v');
+    Version::parse(version)
+        .or_else(|_| Version::parse(&format!("{}.0", version)))
+        .ok()
+}
+
+/// 当前宿主版本
+pub fn host_version() -> Version {
+    Version::parse(env!("CARGO_PKG_VERSION")).unwrap_or_else(|_| Version::new(0, 0, 0))
+}
+
+/// 索引位置
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IndexLocation {
+    /// 本地目录
+    Local(PathBuf),
+    /// HTTP(S) 地址（指向索引所在目录）
+    Remote(String),
+}
+
+impl IndexLocation {
+    /// 解析索引位置：本地目录、`file://` URL 或 HTTP(S) URL，
+    /// 指向 `index.json` 本身时取其所在目录
+    pub fn parse(location: &str) -> Result<Self, InstallError> {
+        let location = location.trim();
+        if location.starts_with("http://") || location.starts_with("https://") {
+            let base = location
+                .strip_suffix(INDEX_FILE)
+                .unwrap_or(location)
+                .trim_end_matches('/');
+            return Ok(IndexLocation::Remote(base.to_string()));
+        }
+
+        let path = if location.starts_with("file://") {
+            url::Url::parse(location)
+                .ok()
+                .and_then(|u| u.to_file_path().ok())
+                .ok_or_else(|| InstallError::UrlParseError(location.to_string()))?
+        } else {
+            PathBuf::from(location)
+        };
+        let dir = if path.file_name().and_then(|n| n.to_str()) == Some(INDEX_FILE) {
+            path.parent().map(Path::to_path_buf).unwrap_or_default()
+        } else {
+            path
+        };
+        Ok(IndexLocation::Local(dir))
+    }
+
+    /// 解析相对索引位置的地址
+    pub fn resolve(&self, url: &str) -> IndexLocation {
+        if url.starts_with("http://") || url.starts_with("https://") {
+            return IndexLocation::Remote(url.to_string());
+        }
+        if url.starts_with("file://") {
+            if let Ok(IndexLocation::Local(path)) = IndexLocation::parse(url) {
+                return IndexLocation::Local(path);
+            }
+        }
+        match self {
+            IndexLocation::Local(dir) => IndexLocation::Local(dir.join(url)),
+            IndexLocation::Remote(base) => {
+                IndexLocation::Remote(format!("{}/{}", base, url.trim_start_matches('/')))
+            }
+        }
+    }
+
+    /// 读取内容
+    pub async fn read(&self, client: &reqwest::Client) -> Result<Vec<u8>, InstallError> {
+        match self {
+            IndexLocation::Local(path) => Ok(tokio::fs::read(path).await?),
+            IndexLocation::Remote(url) => {
+                let response = client
+                    .get(url)
+                    .header("User-Agent", "ProxyCast-Plugin-Installer")
+                    .send()
+                    .await
+                    .map_err(|e| InstallError::NetworkError(e.to_string()))?;
+                if !response.status().is_success() {
+                    return Err(InstallError::DownloadFailed(format!(
+                        "{}: HTTP {}",
+                        url,
+                        response.status()
+                    )));
+                }
+                let bytes = response
+                    .bytes()
+                    .await
+                    .map_err(|e| InstallError::NetworkError(e.to_string()))?;
+                Ok(bytes.to_vec())
+            }
+        }
+    }
+
+    /// 读取分离签名 (`<location>.sig`)
+    pub async fn read_signature(
+        &self,
+        client: &reqwest::Client,
+    ) -> Result<PackageSignature, InstallError> {
+        let sig_location = match self {
+            IndexLocation::Local(path) => IndexLocation::Local(signature_path(path)),
+            IndexLocation::Remote(url) => IndexLocation::Remote(format!("{}.sig", url)),
+        };
+        let bytes = sig_location
+            .read(client)
+            .await
+            .map_err(|e| InstallError::SignatureMissing(format!("{}: {}", self, e)))?;
+        Ok(serde_json::from_slice(&bytes)?)
+    }
+}
+
+impl std::fmt::Display for IndexLocation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            IndexLocation::Local(path) => write!(f, "{}", path.display()),
+            IndexLocation::Remote(url) => write!(f, "{}", url),
+        }
+    }
+}
+
+/// 已验证签名的索引
+#[derive(Debug, Clone)]
+pub struct SignedIndex {
+    /// 索引位置
+    pub location: IndexLocation,
+    /// 索引内容
+    pub index: PluginIndex,
+    /// 索引签名公钥 ID
+    pub signer: String,
+}
+
+impl SignedIndex {
+    /// 读取索引并用受信任公钥验证签名
+    pub async fn load(
+        location: &str,
+        trusted: &[TrustedKey],
+        client: &reqwest::Client,
+    ) -> Result<Self, InstallError> {
+        let location = IndexLocation::parse(location)?;
+        let index_file = location.resolve(INDEX_FILE);
+        let bytes = index_file.read(client).await?;
+        let signature = index_file.read_signature(client).await?;
+        let digest: [u8; 32] = Sha256::digest(&bytes).into();
+        let signer = verify_signature(trusted, &digest, &signature)?;
+        let index: PluginIndex = serde_json::from_slice(&bytes)?;
+        Ok(Self {
+            location,
+            index,
+            signer: signer.key_id.clone(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::signature::tests::{sign_file, signing_key, trusted_key};
+    use super::*;
+
+    fn version(version: &str, proxycast: Option<&str>) -> IndexVersion {
+        IndexVersion {
+            version: version.to_string(),
+            url: format!("packages/p-{}.zip", version),
+            sha256: String::new(),
+            signature: None,
+            proxycast: proxycast.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_resolve_latest_compatible_and_pinned() {
+        let plugin = IndexPlugin {
+            name: "p".to_string(),
+            description: String::new(),
+            author: None,
+            versions: vec![
+                version("1.0.0", None),
+                version("1.10.0", Some(">=0.27, <0.30")),
+                version("2.0.0", Some(">=1.0")),
+            ],
+        };
+        let host = Version::new(0, 27, 0);
+
+        assert_eq!(plugin.resolve(None, &host).unwrap().version, "1.10.0");
+        assert_eq!(
+            plugin.resolve(Some("1.0.0"), &host).unwrap().version,
+            "1.0.0"
+        );
+        assert!(matches!(
+            plugin.resolve(Some("2.0.0"), &host),
+            Err(InstallError::Incompatible(_))
+        ));
+        assert!(matches!(
+            plugin.resolve(Some("3.0.0"), &host),
+            Err(InstallError::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_index_location_parse_and_resolve() {
+        let local = IndexLocation::parse("/srv/plugins/index.json").unwrap();
+        assert_eq!(local, IndexLocation::Local(PathBuf::from("/srv/plugins")));
+        assert_eq!(
+            local.resolve("packages/a.zip"),
+            IndexLocation::Local(PathBuf::from("/srv/plugins/packages/a.zip"))
+        );
+
+        let file = IndexLocation::parse("file:///srv/plugins").unwrap();
+        assert_eq!(file, IndexLocation::Local(PathBuf::from("/srv/plugins")));
+
+        let remote = IndexLocation::parse("https://example.com/plugins/").unwrap();
+        assert_eq!(
+            remote.resolve("a.zip"),
+            IndexLocation::Remote("https://example.com/plugins/a.zip".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_signed_index_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let index_path = dir.path().join(INDEX_FILE);
+        let index = PluginIndex {
+            name: Some("local".to_string()),
+            plugins: vec![],
+        };
+        std::fs::write(&index_path, serde_json::to_vec(&index).unwrap()).unwrap();
+
+        let key = signing_key(3);
+        let client = reqwest::Client::new();
+        let location = dir.path().to_string_lossy().to_string();
+
+        // 缺少签名
+        assert!(matches!(
+            SignedIndex::load(&location, &[trusted_key(&key)], &client).await,
+            Err(InstallError::SignatureMissing(_))
+        ));
+
+        sign_file(&key, &index_path);
+        let loaded = SignedIndex::load(&location, &[trusted_key(&key)], &client)
+            .await
+            .unwrap();
+        assert_eq!(loaded.index.name.as_deref(), Some("local"));
+        assert_eq!(loaded.signer, trusted_key(&key).key_id);
+
+        // 签名公钥不受信任
+        assert!(matches!(
+            SignedIndex::load(&location, &[trusted_key(&signing_key(4))], &client).await,
+            Err(InstallError::UntrustedKey(_))
+        ));
+    }
+}
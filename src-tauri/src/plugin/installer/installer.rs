@@ -4,6 +4,10 @@
 //! - install_from_file: 从本地文件安装
 //! - install_from_url: 从 URL 下载安装
 //! - uninstall: 卸载插件
+//! - install_from_index: 从签名索引安装（版本固定、更新检查、回滚）
+//!
+//! 默认拒绝未签名的包，单次安装可通过 `allow_unsigned` 放行；带有分离签名时
+//! 总是验证签名。从索引安装的包必须签名。
 //!
 //! _需求: 1.1, 1.2, 1.3, 2.1, 2.2, 4.2_
 
@@ -14,12 +18,18 @@ use std::sync::{Arc, Mutex};
 use rusqlite::Connection;
 
 use super::downloader::PluginDownloader;
+use super::index::{host_version, parse_version, IndexLocation, SignedIndex};
 use super::registry::PluginRegistry;
+use super::signature::{hex_digest, sha256_file, verify_signature, PackageSignature};
 use super::types::{
-    InstallError, InstallProgress, InstallSource, InstalledPlugin, PackageFormat, ProgressCallback,
+    InstallError, InstallProgress, InstallSource, InstalledPlugin, PackageFormat, PluginUpdate,
+    ProgressCallback,
 };
 use super::validator::PackageValidator;
 
+/// 回滚备份目录（位于插件目录下，加载器会忽略没有清单的目录）
+const ROLLBACK_DIR: &str = ".rollback";
+
 /// 插件安装器
 ///
 /// 负责协调整个安装流程
@@ -34,6 +44,8 @@ pub struct PluginInstaller {
     downloader: PluginDownloader,
     /// 验证器
     validator: PackageValidator,
+    /// 是否拒绝未签名的插件包（默认开启）
+    require_signatures: bool,
 }
 
 impl PluginInstaller {
@@ -45,6 +57,7 @@ impl PluginInstaller {
             registry: PluginRegistry::new(db_conn),
             downloader: PluginDownloader::new(),
             validator: PackageValidator::new(),
+            require_signatures: true,
         }
     }
 
@@ -63,22 +76,35 @@ impl PluginInstaller {
             registry,
             downloader: PluginDownloader::new(),
             validator: PackageValidator::new(),
+            require_signatures: true,
         })
     }
 
+    /// 设置是否拒绝未签名的插件包
+    pub fn with_require_signatures(mut self, require: bool) -> Self {
+        self.require_signatures = require;
+        self
+    }
+
     /// 从本地文件安装插件
     ///
     /// 流程: 验证 → 解压 → 注册 → 复制文件
+    /// `allow_unsigned` 为 true 时本次安装接受未签名的包
     /// _需求: 1.1, 1.2, 1.3_
     pub async fn install_from_file(
         &self,
         path: &Path,
+        allow_unsigned: bool,
         progress: &dyn ProgressCallback,
     ) -> Result<InstalledPlugin, InstallError> {
         // 阶段 1: 验证包格式
         progress.on_progress(InstallProgress::validating("验证包格式..."));
         let format = self.validator.validate_format(path)?;
 
+        // 验证分离签名 (<file>.sig)
+        let signer =
+            self.verify_package(path, PackageSignature::load_for(path)?, allow_unsigned)?;
+
         // 阶段 2: 提取并验证清单
         progress.on_progress(InstallProgress::validating("验证清单文件..."));
         let manifest = self.validator.extract_and_validate_manifest(path, format)?;
@@ -108,7 +134,8 @@ impl PluginInstaller {
                 path: path.to_string_lossy().to_string(),
             },
         )
-        .with_author(manifest.author.clone().unwrap_or_default());
+        .with_author(manifest.author.clone().unwrap_or_default())
+        .with_signer(signer);
 
         self.registry.register(&installed_plugin)?;
 
@@ -126,10 +153,12 @@ impl PluginInstaller {
     /// 从 URL 安装插件
     ///
     /// 流程: 下载 → 验证 → 解压 → 注册 → 复制文件
+    /// `allow_unsigned` 为 true 时本次安装接受未签名的包
     /// _需求: 2.1, 2.2_
     pub async fn install_from_url(
         &self,
         url: &str,
+        allow_unsigned: bool,
         progress: &dyn ProgressCallback,
     ) -> Result<InstalledPlugin, InstallError> {
         // 确保临时目录存在
@@ -145,6 +174,19 @@ impl PluginInstaller {
         progress.on_progress(InstallProgress::validating("验证包格式..."));
         let format = self.validator.validate_format(&download_path)?;
 
+        // 验证分离签名 (<url>.sig)，签名不存在或无法下载时视为未签名
+        let signature = IndexLocation::Remote(url.to_string())
+            .read_signature(self.downloader.client())
+            .await
+            .ok();
+        let signer = match self.verify_package(&download_path, signature, allow_unsigned) {
+            Ok(signer) => signer,
+            Err(e) => {
+                let _ = fs::remove_file(&download_path);
+                return Err(e);
+            }
+        };
+
         // 阶段 3: 提取并验证清单
         progress.on_progress(InstallProgress::validating("验证清单文件..."));
         let manifest = self
@@ -190,7 +232,8 @@ impl PluginInstaller {
             install_path.clone(),
             source,
         )
-        .with_author(manifest.author.clone().unwrap_or_default());
+        .with_author(manifest.author.clone().unwrap_or_default())
+        .with_signer(signer);
 
         self.registry.register(&installed_plugin)?;
 
@@ -217,10 +260,14 @@ impl PluginInstaller {
             .get(plugin_id)?
             .ok_or_else(|| InstallError::NotFound(plugin_id.to_string()))?;
 
-        // 删除插件文件
+        // 删除插件文件和回滚备份
         if plugin.install_path.exists() {
             fs::remove_dir_all(&plugin.install_path)?;
         }
+        let backup_dir = self.rollback_dir(plugin_id);
+        if backup_dir.exists() {
+            fs::remove_dir_all(&backup_dir)?;
+        }
 
         // 注销注册表
         self.registry.unregister(plugin_id)?;
@@ -228,6 +275,326 @@ impl PluginInstaller {
         Ok(())
     }
 
+    /// 从签名索引安装插件
+    ///
+    /// 指定 `version` 时安装该版本，否则安装兼容当前版本的最新版本。
+    /// 插件已安装时替换为目标版本，原版本保留用于回滚。
+    pub async fn install_from_index(
+        &self,
+        index: &str,
+        name: &str,
+        version: Option<&str>,
+        progress: &dyn ProgressCallback,
+    ) -> Result<InstalledPlugin, InstallError> {
+        fs::create_dir_all(&self.temp_dir)?;
+
+        // 阶段 1: 读取并验证索引
+        progress.on_progress(InstallProgress::validating("验证插件索引签名..."));
+        let trusted = self.registry.list_trusted_keys()?;
+        let signed = SignedIndex::load(index, &trusted, self.downloader.client()).await?;
+        let plugin = signed
+            .index
+            .find(name)
+            .ok_or_else(|| InstallError::NotFound(name.to_string()))?;
+        let entry = plugin.resolve(version, &host_version())?;
+
+        // 阶段 2: 获取插件包
+        progress.on_progress(InstallProgress::downloading(
+            0,
+            format!("获取 {} v{}...", name, entry.version),
+        ));
+        let package = signed.location.resolve(&entry.url);
+        let file_name = entry.url.rsplit('/').next().unwrap_or(&entry.url);
+        let package_path = self.temp_dir.join(format!("index_{}", file_name));
+        fs::write(&package_path, package.read(self.downloader.client()).await?)?;
+        progress.on_progress(InstallProgress::downloading(100, "获取完成"));
+
+        let result = self
+            .install_index_package(&signed, plugin, &package, &package_path, entry, progress)
+            .await;
+        let _ = fs::remove_file(&package_path);
+        result
+    }
+
+    async fn install_index_package(
+        &self,
+        signed: &SignedIndex,
+        plugin: &super::index::IndexPlugin,
+        package: &IndexLocation,
+        package_path: &Path,
+        entry: &super::index::IndexVersion,
+        progress: &dyn ProgressCallback,
+    ) -> Result<InstalledPlugin, InstallError> {
+        // 阶段 3: 验证校验和、签名和清单
+        progress.on_progress(InstallProgress::validating("验证包签名..."));
+        let format = self.validator.validate_format(package_path)?;
+        self.validator
+            .validate_integrity(package_path, Some(&entry.sha256))?;
+        let signature = match &entry.signature {
+            Some(signature) => signature.clone(),
+            None => package.read_signature(self.downloader.client()).await?,
+        };
+        let digest = sha256_file(package_path)?;
+        let trusted = self.registry.list_trusted_keys()?;
+        let signer = verify_signature(&trusted, &digest, &signature)?
+            .key_id
+            .clone();
+
+        let manifest = self
+            .validator
+            .extract_and_validate_manifest(package_path, format)?;
+        // 包名必须与索引条目一致，否则签名包可以覆盖其他已安装插件
+        if manifest.name != plugin.name {
+            return Err(InstallError::InvalidManifest(format!(
+                "清单名称 {} 与索引条目 {} 不一致",
+                manifest.name, plugin.name
+            )));
+        }
+        if manifest.version != entry.version {
+            return Err(InstallError::InvalidManifest(format!(
+                "清单版本 {} 与索引版本 {} 不一致",
+                manifest.version, entry.version
+            )));
+        }
+
+        // 阶段 4: 解压并替换插件目录，已安装版本移入回滚目录
+        progress.on_progress(InstallProgress::extracting(0, "解压插件包..."));
+        let temp_extract_dir = self.extract_package(package_path, format, progress)?;
+        let existing = self.registry.get(&manifest.name)?;
+        let backed_up = match existing {
+            Some(_) => self.backup_for_rollback(&manifest.name)?,
+            None => false,
+        };
+        progress.on_progress(InstallProgress::installing(50, "安装插件文件..."));
+        let result = self
+            .copy_to_plugins_dir(&manifest.name, &temp_extract_dir, progress)
+            .and_then(|install_path| {
+                // 阶段 5: 注册插件
+                progress.on_progress(InstallProgress::registering("注册插件..."));
+                let mut installed_plugin = InstalledPlugin::new(
+                    manifest.name.clone(),
+                    manifest.name.clone(),
+                    manifest.version.clone(),
+                    manifest.description.clone(),
+                    install_path,
+                    InstallSource::Index {
+                        index: signed.location.to_string(),
+                    },
+                )
+                .with_author(manifest.author.clone().unwrap_or_default())
+                .with_signer(Some(signer));
+                if let Some(existing) = existing {
+                    installed_plugin.enabled = existing.enabled;
+                    installed_plugin.pinned_version = existing.pinned_version;
+                    installed_plugin.previous_version = Some(existing.version);
+                }
+                self.registry.register(&installed_plugin)?;
+                Ok(installed_plugin)
+            });
+        let _ = fs::remove_dir_all(&temp_extract_dir);
+
+        // 安装失败时恢复原版本，成功后才丢弃被替换的旧备份
+        let installed_plugin = match result {
+            Ok(installed_plugin) => {
+                if backed_up {
+                    let _ = fs::remove_dir_all(self.stale_rollback_dir(&manifest.name));
+                }
+                installed_plugin
+            }
+            Err(e) => {
+                if backed_up {
+                    self.restore_from_rollback(&manifest.name);
+                }
+                return Err(e);
+            }
+        };
+
+        progress.on_progress(InstallProgress::complete(format!(
+            "插件 {} v{} 安装成功",
+            manifest.name, manifest.version
+        )));
+
+        Ok(installed_plugin)
+    }
+
+    /// 检查索引中已安装插件的可用更新
+    ///
+    /// 固定版本的插件只在已安装版本与固定版本不一致时报告
+    pub async fn check_updates(&self, index: &str) -> Result<Vec<PluginUpdate>, InstallError> {
+        let trusted = self.registry.list_trusted_keys()?;
+        let signed = SignedIndex::load(index, &trusted, self.downloader.client()).await?;
+        let host = host_version();
+
+        let mut updates = Vec::new();
+        for installed in self.registry.list()? {
+            let Some(plugin) = signed.index.find(&installed.id) else {
+                continue;
+            };
+            let target = match &installed.pinned_version {
+                Some(pinned) if *pinned == installed.version => continue,
+                Some(pinned) => plugin.resolve(Some(pinned), &host),
+                None => plugin.resolve(None, &host),
+            };
+            let Ok(target) = target else {
+                continue;
+            };
+            let newer = match (
+                parse_version(&target.version),
+                parse_version(&installed.version),
+            ) {
+                (Some(target), Some(current)) => target > current,
+                _ => target.version != installed.version,
+            };
+            if newer || installed.pinned_version.is_some() {
+                updates.push(PluginUpdate {
+                    id: installed.id.clone(),
+                    current_version: installed.version.clone(),
+                    available_version: target.version.clone(),
+                    pinned: installed.pinned_version.is_some(),
+                });
+            }
+        }
+
+        Ok(updates)
+    }
+
+    /// 将插件更新到索引中的最新兼容版本（固定版本时更新到固定版本）
+    pub async fn update(
+        &self,
+        index: &str,
+        plugin_id: &str,
+        progress: &dyn ProgressCallback,
+    ) -> Result<InstalledPlugin, InstallError> {
+        let installed = self
+            .registry
+            .get(plugin_id)?
+            .ok_or_else(|| InstallError::NotFound(plugin_id.to_string()))?;
+        self.install_from_index(
+            index,
+            plugin_id,
+            installed.pinned_version.as_deref(),
+            progress,
+        )
+        .await
+    }
+
+    /// 固定插件版本，None 表示取消固定
+    pub fn pin_version(&self, plugin_id: &str, version: Option<&str>) -> Result<(), InstallError> {
+        self.registry.set_pinned_version(plugin_id, version)
+    }
+
+    /// 回滚到更新前的版本
+    ///
+    /// 当前版本与备份互换，再次回滚可恢复
+    pub fn rollback(&self, plugin_id: &str) -> Result<InstalledPlugin, InstallError> {
+        let mut plugin = self
+            .registry
+            .get(plugin_id)?
+            .ok_or_else(|| InstallError::NotFound(plugin_id.to_string()))?;
+        let previous_version = plugin.previous_version.clone().ok_or_else(|| {
+            InstallError::InstallFailed(format!("插件 {} 没有可回滚的版本", plugin_id))
+        })?;
+        let backup_dir = self.rollback_dir(plugin_id);
+        if !backup_dir.exists() {
+            return Err(InstallError::InstallFailed(format!(
+                "插件 {} 的回滚备份不存在",
+                plugin_id
+            )));
+        }
+
+        let swap_dir = self.rollback_dir(&format!("{}.swap", plugin_id));
+        if swap_dir.exists() {
+            fs::remove_dir_all(&swap_dir)?;
+        }
+        fs::rename(&plugin.install_path, &swap_dir)?;
+        fs::rename(&backup_dir, &plugin.install_path)?;
+        fs::rename(&swap_dir, &backup_dir)?;
+
+        plugin.previous_version = Some(std::mem::replace(&mut plugin.version, previous_version));
+        self.registry.register(&plugin)?;
+        Ok(plugin)
+    }
+
+    /// 将当前安装目录移入回滚备份，返回是否已移动
+    ///
+    /// 已有的备份暂存到 `<name>.stale`，安装成功后删除，失败时由
+    /// `restore_from_rollback` 放回
+    fn backup_for_rollback(&self, plugin_name: &str) -> Result<bool, InstallError> {
+        let current = self.plugins_dir.join(plugin_name);
+        if !current.exists() {
+            return Ok(false);
+        }
+        let backup_dir = self.rollback_dir(plugin_name);
+        if backup_dir.exists() {
+            let stale_dir = self.stale_rollback_dir(plugin_name);
+            if stale_dir.exists() {
+                fs::remove_dir_all(&stale_dir)?;
+            }
+            fs::rename(&backup_dir, &stale_dir)?;
+        } else if let Some(parent) = backup_dir.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::rename(&current, &backup_dir)?;
+        Ok(true)
+    }
+
+    /// 撤销 `backup_for_rollback`：删除未完成的安装目录并放回原版本
+    fn restore_from_rollback(&self, plugin_name: &str) {
+        let current = self.plugins_dir.join(plugin_name);
+        let backup_dir = self.rollback_dir(plugin_name);
+        let stale_dir = self.stale_rollback_dir(plugin_name);
+        let restored = (|| -> std::io::Result<()> {
+            if current.exists() {
+                fs::remove_dir_all(&current)?;
+            }
+            fs::rename(&backup_dir, &current)?;
+            if stale_dir.exists() {
+                fs::rename(&stale_dir, &backup_dir)?;
+            }
+            Ok(())
+        })();
+        if let Err(e) = restored {
+            tracing::error!(
+                "[PluginInstaller] 恢复插件 {} 原版本失败: {}",
+                plugin_name,
+                e
+            );
+        }
+    }
+
+    fn rollback_dir(&self, plugin_name: &str) -> PathBuf {
+        self.plugins_dir.join(ROLLBACK_DIR).join(plugin_name)
+    }
+
+    fn stale_rollback_dir(&self, plugin_name: &str) -> PathBuf {
+        self.rollback_dir(&format!("{}.stale", plugin_name))
+    }
+
+    /// 验证插件包签名，返回签名公钥 ID
+    fn verify_package(
+        &self,
+        path: &Path,
+        signature: Option<PackageSignature>,
+        allow_unsigned: bool,
+    ) -> Result<Option<String>, InstallError> {
+        let Some(signature) = signature else {
+            if self.require_signatures && !allow_unsigned {
+                return Err(InstallError::SignatureMissing(path.display().to_string()));
+            }
+            return Ok(None);
+        };
+        let digest = sha256_file(path)?;
+        let trusted = self.registry.list_trusted_keys()?;
+        let signer = verify_signature(&trusted, &digest, &signature)?;
+        tracing::info!(
+            "插件包 {} 签名验证通过 (发布者: {}, sha256: {})",
+            path.display(),
+            signer.publisher,
+            hex_digest(&digest)
+        );
+        Ok(Some(signer.key_id.clone()))
+    }
+
     /// 获取已安装插件列表
     pub fn list_installed(&self) -> Result<Vec<InstalledPlugin>, InstallError> {
         self.registry.list()
@@ -543,7 +910,9 @@ mod tests {
         let package_path = create_test_plugin_zip(temp_dir.path(), "test-plugin", "1.0.0");
 
         let progress = NoopProgressCallback;
-        let result = installer.install_from_file(&package_path, &progress).await;
+        let result = installer
+            .install_from_file(&package_path, true, &progress)
+            .await;
 
         assert!(result.is_ok(), "安装应该成功: {:?}", result);
 
@@ -571,11 +940,15 @@ mod tests {
         let progress = NoopProgressCallback;
 
         // 第一次安装
-        let result1 = installer.install_from_file(&package_path, &progress).await;
+        let result1 = installer
+            .install_from_file(&package_path, true, &progress)
+            .await;
         assert!(result1.is_ok());
 
         // 第二次安装应该失败
-        let result2 = installer.install_from_file(&package_path, &progress).await;
+        let result2 = installer
+            .install_from_file(&package_path, true, &progress)
+            .await;
         assert!(result2.is_err());
         match result2.unwrap_err() {
             InstallError::AlreadyExists(name) => {
@@ -594,7 +967,9 @@ mod tests {
         fs::write(&invalid_path, "not a zip file").unwrap();
 
         let progress = NoopProgressCallback;
-        let result = installer.install_from_file(&invalid_path, &progress).await;
+        let result = installer
+            .install_from_file(&invalid_path, true, &progress)
+            .await;
 
         assert!(result.is_err());
         match result.unwrap_err() {
@@ -612,7 +987,7 @@ mod tests {
 
         // 先安装
         installer
-            .install_from_file(&package_path, &progress)
+            .install_from_file(&package_path, true, &progress)
             .await
             .unwrap();
 
@@ -654,8 +1029,14 @@ mod tests {
         let pkg1 = create_test_plugin_zip(temp_dir.path(), "plugin-a", "1.0.0");
         let pkg2 = create_test_plugin_zip(temp_dir.path(), "plugin-b", "2.0.0");
 
-        installer.install_from_file(&pkg1, &progress).await.unwrap();
-        installer.install_from_file(&pkg2, &progress).await.unwrap();
+        installer
+            .install_from_file(&pkg1, true, &progress)
+            .await
+            .unwrap();
+        installer
+            .install_from_file(&pkg2, true, &progress)
+            .await
+            .unwrap();
 
         // 列出已安装插件
         let plugins = installer.list_installed().unwrap();
@@ -673,7 +1054,7 @@ mod tests {
 
         let progress = NoopProgressCallback;
         installer
-            .install_from_file(&package_path, &progress)
+            .install_from_file(&package_path, true, &progress)
             .await
             .unwrap();
 
@@ -688,6 +1069,196 @@ mod tests {
         let not_found = installer.get_plugin("not-found").unwrap();
         assert!(not_found.is_none());
     }
+
+    #[tokio::test]
+    async fn test_install_requires_trusted_signature() {
+        use crate::plugin::installer::signature::tests::{sign_file, signing_key, trusted_key};
+
+        let (installer, _plugins_dir, temp_dir, _db_dir) = create_test_installer();
+        let package_path = create_test_plugin_zip(temp_dir.path(), "signed-plugin", "1.0.0");
+        let progress = NoopProgressCallback;
+
+        // 默认拒绝未签名的包
+        let result = installer
+            .install_from_file(&package_path, false, &progress)
+            .await;
+        assert!(matches!(result, Err(InstallError::SignatureMissing(_))));
+
+        // 签名公钥不在信任库中，放行未签名也不跳过签名验证
+        let key = signing_key(7);
+        sign_file(&key, &package_path);
+        let result = installer
+            .install_from_file(&package_path, true, &progress)
+            .await;
+        assert!(matches!(result, Err(InstallError::UntrustedKey(_))));
+
+        installer
+            .registry()
+            .add_trusted_key(&trusted_key(&key))
+            .unwrap();
+        let installed = installer
+            .install_from_file(&package_path, false, &progress)
+            .await
+            .unwrap();
+        assert_eq!(installed.signer, Some(trusted_key(&key).key_id));
+
+        // 单次安装放行未签名的包
+        let unsigned = create_test_plugin_zip(temp_dir.path(), "unsigned-plugin", "1.0.0");
+        let installed = installer
+            .install_from_file(&unsigned, true, &progress)
+            .await
+            .unwrap();
+        assert_eq!(installed.signer, None);
+    }
+
+    #[tokio::test]
+    async fn test_index_install_update_and_rollback() {
+        use crate::plugin::installer::index::{IndexPlugin, IndexVersion, PluginIndex, INDEX_FILE};
+        use crate::plugin::installer::signature::tests::{sign_file, signing_key, trusted_key};
+
+        let (installer, plugins_dir, _temp_dir, _db_dir) = create_test_installer();
+        let index_dir = TempDir::new().unwrap();
+        let key = signing_key(9);
+        installer
+            .registry()
+            .add_trusted_key(&trusted_key(&key))
+            .unwrap();
+
+        // 构建带两个版本的签名索引
+        let mut versions = Vec::new();
+        for version in ["1.0.0", "1.1.0"] {
+            let dir = index_dir.path().join(version);
+            fs::create_dir_all(&dir).unwrap();
+            let package = create_test_plugin_zip(&dir, "indexed", version);
+            sign_file(&key, &package);
+            versions.push(IndexVersion {
+                version: version.to_string(),
+                url: format!("{}/indexed.zip", version),
+                sha256: hex_digest(&sha256_file(&package).unwrap()),
+                signature: None,
+                proxycast: None,
+            });
+        }
+        let index = PluginIndex {
+            name: None,
+            plugins: vec![IndexPlugin {
+                name: "indexed".to_string(),
+                description: String::new(),
+                author: None,
+                versions,
+            }],
+        };
+        let index_path = index_dir.path().join(INDEX_FILE);
+        fs::write(&index_path, serde_json::to_vec(&index).unwrap()).unwrap();
+        sign_file(&key, &index_path);
+        let location = format!("file://{}", index_dir.path().display());
+        let progress = NoopProgressCallback;
+
+        // 安装指定版本
+        let installed = installer
+            .install_from_index(&location, "indexed", Some("1.0.0"), &progress)
+            .await
+            .unwrap();
+        assert_eq!(installed.version, "1.0.0");
+        assert!(installed.signer.is_some());
+
+        let updates = installer.check_updates(&location).await.unwrap();
+        assert_eq!(updates.len(), 1);
+        assert_eq!(updates[0].available_version, "1.1.0");
+
+        // 更新后可回滚
+        let updated = installer
+            .update(&location, "indexed", &progress)
+            .await
+            .unwrap();
+        assert_eq!(updated.version, "1.1.0");
+        assert_eq!(updated.previous_version.as_deref(), Some("1.0.0"));
+        assert!(installer.check_updates(&location).await.unwrap().is_empty());
+
+        let rolled_back = installer.rollback("indexed").unwrap();
+        assert_eq!(rolled_back.version, "1.0.0");
+        let manifest =
+            fs::read_to_string(plugins_dir.path().join("indexed").join("plugin.json")).unwrap();
+        assert!(manifest.contains("1.0.0"));
+
+        // 固定版本后不再报告更新
+        installer.pin_version("indexed", Some("1.0.0")).unwrap();
+        assert!(installer.check_updates(&location).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_index_install_rejects_mismatched_name() {
+        use crate::plugin::installer::index::{IndexPlugin, IndexVersion, PluginIndex, INDEX_FILE};
+        use crate::plugin::installer::signature::tests::{sign_file, signing_key, trusted_key};
+
+        let (installer, plugins_dir, _temp_dir, _db_dir) = create_test_installer();
+        let index_dir = TempDir::new().unwrap();
+        let key = signing_key(11);
+        installer
+            .registry()
+            .add_trusted_key(&trusted_key(&key))
+            .unwrap();
+
+        // 索引条目 "harmless" 指向清单名称为 "victim" 的包
+        let package = create_test_plugin_zip(index_dir.path(), "victim", "1.0.0");
+        sign_file(&key, &package);
+        let index = PluginIndex {
+            name: None,
+            plugins: vec![IndexPlugin {
+                name: "harmless".to_string(),
+                description: String::new(),
+                author: None,
+                versions: vec![IndexVersion {
+                    version: "1.0.0".to_string(),
+                    url: "victim.zip".to_string(),
+                    sha256: hex_digest(&sha256_file(&package).unwrap()),
+                    signature: None,
+                    proxycast: None,
+                }],
+            }],
+        };
+        let index_path = index_dir.path().join(INDEX_FILE);
+        fs::write(&index_path, serde_json::to_vec(&index).unwrap()).unwrap();
+        sign_file(&key, &index_path);
+        let location = format!("file://{}", index_dir.path().display());
+
+        let result = installer
+            .install_from_index(&location, "harmless", None, &NoopProgressCallback)
+            .await;
+        assert!(matches!(result, Err(InstallError::InvalidManifest(_))));
+        assert!(!installer.is_installed("victim").unwrap());
+        assert!(!plugins_dir.path().join("victim").exists());
+    }
+
+    #[tokio::test]
+    async fn test_failed_replace_restores_backup() {
+        let (installer, plugins_dir, temp_dir, _db_dir) = create_test_installer();
+        let progress = NoopProgressCallback;
+        let package_path = create_test_plugin_zip(temp_dir.path(), "restored", "1.0.0");
+        installer
+            .install_from_file(&package_path, true, &progress)
+            .await
+            .unwrap();
+        let install_dir = plugins_dir.path().join("restored");
+        let old_backup = installer.rollback_dir("restored");
+        fs::create_dir_all(&old_backup).unwrap();
+        fs::write(old_backup.join("plugin.json"), "0.9.0").unwrap();
+
+        // 备份后复制中途失败，留下不完整的安装目录
+        assert!(installer.backup_for_rollback("restored").unwrap());
+        fs::create_dir_all(&install_dir).unwrap();
+        fs::write(install_dir.join("partial"), "").unwrap();
+        installer.restore_from_rollback("restored");
+
+        assert!(!install_dir.join("partial").exists());
+        let manifest = fs::read_to_string(install_dir.join("plugin.json")).unwrap();
+        assert!(manifest.contains("1.0.0"));
+        assert_eq!(
+            fs::read_to_string(old_backup.join("plugin.json")).unwrap(),
+            "0.9.0"
+        );
+        assert!(!installer.stale_rollback_dir("restored").exists());
+    }
 }
 
 /// 属性测试模块
@@ -801,7 +1372,7 @@ mod property_tests {
                 let package_path = create_valid_plugin_zip(temp_dir.path(), &name, &version);
 
                 let progress = NoopProgressCallback;
-                let result = installer.install_from_file(&package_path, &progress).await;
+                let result = installer.install_from_file(&package_path, true, &progress).await;
 
                 // 成功安装时，验证所有状态都已更新
                 prop_assert!(result.is_ok(), "安装应该成功: {:?}", result);
@@ -849,7 +1420,7 @@ mod property_tests {
                 let invalid_package = create_invalid_plugin_zip_no_manifest(temp_dir.path(), &name);
 
                 let progress = NoopProgressCallback;
-                let result = installer.install_from_file(&invalid_package, &progress).await;
+                let result = installer.install_from_file(&invalid_package, true, &progress).await;
 
                 // 安装应该失败
                 prop_assert!(result.is_err(), "无效包安装应该失败");
@@ -894,7 +1465,7 @@ mod property_tests {
                 // 先安装插件
                 let package_path = create_valid_plugin_zip(temp_dir.path(), &name, &version);
                 let progress = NoopProgressCallback;
-                let install_result = installer.install_from_file(&package_path, &progress).await;
+                let install_result = installer.install_from_file(&package_path, true, &progress).await;
                 prop_assert!(install_result.is_ok(), "安装应该成功");
 
                 // 验证安装成功
@@ -5,19 +5,24 @@
 //! - 从 URL（如 GitHub releases）下载安装插件
 //! - 插件包验证
 //! - 插件注册表管理
+//! - 插件包签名验证和签名插件索引（版本固定、更新检查、回滚）
 //! - 安装进度回调
 
 mod downloader;
+mod index;
 mod installer;
 mod registry;
+mod signature;
 mod types;
 mod validator;
 
 pub use downloader::PluginDownloader;
+pub use index::{IndexLocation, IndexPlugin, IndexVersion, PluginIndex, SignedIndex};
 pub use installer::PluginInstaller;
 pub use registry::PluginRegistry;
+pub use signature::{PackageSignature, TrustedKey};
 pub use types::{
     GitHubRelease, InstallError, InstallProgress, InstallSource, InstallStage, InstalledPlugin,
-    NoopProgressCallback, PackageFormat, ProgressCallback,
+    NoopProgressCallback, PackageFormat, PluginUpdate, ProgressCallback,
 };
 pub use validator::PackageValidator;
@@ -6,6 +6,7 @@ use rusqlite::{params, Connection, OptionalExtension};
 use std::path::Path;
 use std::sync::{Arc, Mutex};
 
+use super::signature::TrustedKey;
 use super::types::{InstallError, InstallSource, InstalledPlugin};
 
 /// 插件注册表
@@ -54,6 +55,28 @@ impl PluginRegistry {
         )
         .map_err(|e| InstallError::DatabaseError(e.to_string()))?;
 
+        // 迁移：签名、版本固定和回滚信息
+        let _ = conn.execute("ALTER TABLE installed_plugins ADD COLUMN signer TEXT", []);
+        let _ = conn.execute(
+            "ALTER TABLE installed_plugins ADD COLUMN pinned_version TEXT",
+            [],
+        );
+        let _ = conn.execute(
+            "ALTER TABLE installed_plugins ADD COLUMN previous_version TEXT",
+            [],
+        );
+
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS plugin_trusted_keys (
+                key_id TEXT PRIMARY KEY,
+                publisher TEXT NOT NULL,
+                public_key TEXT NOT NULL,
+                added_at TEXT NOT NULL
+            )",
+            [],
+        )
+        .map_err(|e| InstallError::DatabaseError(e.to_string()))?;
+
         Ok(())
     }
 
@@ -70,8 +93,9 @@ impl PluginRegistry {
 
         conn.execute(
             "INSERT OR REPLACE INTO installed_plugins 
-             (id, name, version, description, author, install_path, installed_at, source_type, source_data, enabled)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+             (id, name, version, description, author, install_path, installed_at, source_type, source_data, enabled,
+              signer, pinned_version, previous_version)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
             params![
                 plugin.id,
                 plugin.name,
@@ -83,6 +107,9 @@ impl PluginRegistry {
                 source_type,
                 source_data,
                 plugin.enabled as i32,
+                plugin.signer,
+                plugin.pinned_version,
+                plugin.previous_version,
             ],
         )
         .map_err(|e| InstallError::DatabaseError(e.to_string()))?;
@@ -122,7 +149,8 @@ impl PluginRegistry {
 
         let result = conn
             .query_row(
-                "SELECT id, name, version, description, author, install_path, installed_at, source_type, source_data, enabled
+                "SELECT id, name, version, description, author, install_path, installed_at, source_type, source_data, enabled,
+                        signer, pinned_version, previous_version
                  FROM installed_plugins WHERE id = ?1",
                 params![plugin_id],
                 |row| {
@@ -137,6 +165,9 @@ impl PluginRegistry {
                         source_type: row.get(7)?,
                         source_data: row.get(8)?,
                         enabled: row.get(9)?,
+                        signer: row.get(10)?,
+                        pinned_version: row.get(11)?,
+                        previous_version: row.get(12)?,
                     })
                 },
             )
@@ -158,7 +189,8 @@ impl PluginRegistry {
 
         let mut stmt = conn
             .prepare(
-                "SELECT id, name, version, description, author, install_path, installed_at, source_type, source_data, enabled
+                "SELECT id, name, version, description, author, install_path, installed_at, source_type, source_data, enabled,
+                        signer, pinned_version, previous_version
                  FROM installed_plugins ORDER BY installed_at DESC",
             )
             .map_err(|e| InstallError::DatabaseError(e.to_string()))?;
@@ -176,6 +208,9 @@ impl PluginRegistry {
                     source_type: row.get(7)?,
                     source_data: row.get(8)?,
                     enabled: row.get(9)?,
+                    signer: row.get(10)?,
+                    pinned_version: row.get(11)?,
+                    previous_version: row.get(12)?,
                 })
             })
             .map_err(|e| InstallError::DatabaseError(e.to_string()))?;
@@ -227,6 +262,117 @@ impl PluginRegistry {
 
         Ok(())
     }
+
+    /// 设置固定版本，None 表示取消固定
+    pub fn set_pinned_version(
+        &self,
+        plugin_id: &str,
+        version: Option<&str>,
+    ) -> Result<(), InstallError> {
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|e| InstallError::DatabaseError(e.to_string()))?;
+
+        let rows_affected = conn
+            .execute(
+                "UPDATE installed_plugins SET pinned_version = ?1 WHERE id = ?2",
+                params![version, plugin_id],
+            )
+            .map_err(|e| InstallError::DatabaseError(e.to_string()))?;
+
+        if rows_affected == 0 {
+            return Err(InstallError::NotFound(plugin_id.to_string()));
+        }
+
+        Ok(())
+    }
+
+    /// 添加受信任的发布者公钥
+    pub fn add_trusted_key(&self, key: &TrustedKey) -> Result<(), InstallError> {
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|e| InstallError::DatabaseError(e.to_string()))?;
+
+        conn.execute(
+            "INSERT OR REPLACE INTO plugin_trusted_keys (key_id, publisher, public_key, added_at)
+             VALUES (?1, ?2, ?3, ?4)",
+            params![
+                key.key_id,
+                key.publisher,
+                key.public_key,
+                key.added_at.to_rfc3339()
+            ],
+        )
+        .map_err(|e| InstallError::DatabaseError(e.to_string()))?;
+
+        Ok(())
+    }
+
+    /// 移除受信任的发布者公钥
+    pub fn remove_trusted_key(&self, key_id: &str) -> Result<(), InstallError> {
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|e| InstallError::DatabaseError(e.to_string()))?;
+
+        let rows_affected = conn
+            .execute(
+                "DELETE FROM plugin_trusted_keys WHERE key_id = ?1",
+                params![key_id],
+            )
+            .map_err(|e| InstallError::DatabaseError(e.to_string()))?;
+
+        if rows_affected == 0 {
+            return Err(InstallError::NotFound(key_id.to_string()));
+        }
+
+        Ok(())
+    }
+
+    /// 列出受信任的发布者公钥
+    pub fn list_trusted_keys(&self) -> Result<Vec<TrustedKey>, InstallError> {
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|e| InstallError::DatabaseError(e.to_string()))?;
+
+        let mut stmt = conn
+            .prepare(
+                "SELECT key_id, publisher, public_key, added_at
+                 FROM plugin_trusted_keys ORDER BY added_at",
+            )
+            .map_err(|e| InstallError::DatabaseError(e.to_string()))?;
+
+        let rows = stmt
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, String>(3)?,
+                ))
+            })
+            .map_err(|e| InstallError::DatabaseError(e.to_string()))?;
+
+        let mut keys = Vec::new();
+        for row in rows {
+            let (key_id, publisher, public_key, added_at) =
+                row.map_err(|e| InstallError::DatabaseError(e.to_string()))?;
+            let added_at = chrono::DateTime::parse_from_rfc3339(&added_at)
+                .map_err(|e| InstallError::DatabaseError(format!("无效的时间格式: {}", e)))?
+                .with_timezone(&chrono::Utc);
+            keys.push(TrustedKey {
+                key_id,
+                publisher,
+                public_key,
+                added_at,
+            });
+        }
+
+        Ok(keys)
+    }
 }
 
 /// 数据库行结构
@@ -241,6 +387,9 @@ struct PluginRow {
     source_type: String,
     source_data: Option<String>,
     enabled: i32,
+    signer: Option<String>,
+    pinned_version: Option<String>,
+    previous_version: Option<String>,
 }
 
 impl PluginRow {
@@ -260,6 +409,9 @@ impl PluginRow {
             installed_at,
             source,
             enabled: self.enabled != 0,
+            signer: self.signer,
+            pinned_version: self.pinned_version,
+            previous_version: self.previous_version,
         })
     }
 }
@@ -277,6 +429,7 @@ fn serialize_source(source: &InstallSource) -> (String, Option<String>) {
             });
             ("github".to_string(), Some(data.to_string()))
         }
+        InstallSource::Index { index } => ("index".to_string(), Some(index.clone())),
     }
 }
 
@@ -300,6 +453,9 @@ fn deserialize_source(
                 tag: data["tag"].as_str().unwrap_or_default().to_string(),
             })
         }
+        "index" => Ok(InstallSource::Index {
+            index: source_data.unwrap_or_default().to_string(),
+        }),
         _ => Err(InstallError::DatabaseError(format!(
             "未知的来源类型: {}",
             source_type
@@ -334,6 +490,9 @@ mod tests {
                 path: "/tmp/plugin.zip".to_string(),
             },
             enabled: true,
+            signer: None,
+            pinned_version: None,
+            previous_version: None,
         }
     }
 
@@ -396,6 +555,36 @@ mod tests {
         assert!(retrieved.enabled);
     }
 
+    #[test]
+    fn test_pinned_version_and_trusted_keys() {
+        let registry = create_test_registry();
+        registry.register(&create_test_plugin("test-pin")).unwrap();
+
+        registry
+            .set_pinned_version("test-pin", Some("1.0.0"))
+            .unwrap();
+        let retrieved = registry.get("test-pin").unwrap().unwrap();
+        assert_eq!(retrieved.pinned_version.as_deref(), Some("1.0.0"));
+
+        registry.set_pinned_version("test-pin", None).unwrap();
+        assert!(registry
+            .get("test-pin")
+            .unwrap()
+            .unwrap()
+            .pinned_version
+            .is_none());
+
+        let key =
+            TrustedKey::new("publisher", "O2onvM62pC1io6jQKm8Nc2UyFXcd4kOmOsBIoYtZ2ik=").unwrap();
+        registry.add_trusted_key(&key).unwrap();
+        let keys = registry.list_trusted_keys().unwrap();
+        assert_eq!(keys.len(), 1);
+        assert_eq!(keys[0].key_id, key.key_id);
+
+        registry.remove_trusted_key(&key.key_id).unwrap();
+        assert!(registry.list_trusted_keys().unwrap().is_empty());
+    }
+
     #[test]
     fn test_github_source_serialization() {
         let registry = create_test_registry();
@@ -0,0 +1,215 @@
+//! 插件包签名验证
+//!
+//! 插件包和插件索引使用 ed25519 分离签名：与文件同名的 `.sig` 文件
+//! (如 `my-plugin-1.0.0.zip.sig`) 保存 [`PackageSignature`]，签名内容为文件的 SHA-256 摘要。
+//! 发布者公钥保存在信任库中，只有信任库中的公钥签名的包才能通过验证。
+
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use super::types::InstallError;
+
+/// 分离签名文件扩展名
+pub const SIGNATURE_EXTENSION: &str = "sig";
+
+/// 分离签名
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PackageSignature {
+    /// 签名公钥 ID
+    pub key_id: String,
+    /// base64 编码的 ed25519 签名
+    pub signature: String,
+}
+
+impl PackageSignature {
+    /// 读取文件对应的分离签名 (`<file>.sig`)，不存在时返回 None
+    pub fn load_for(path: &Path) -> Result<Option<Self>, InstallError> {
+        let sig_path = signature_path(path);
+        if !sig_path.exists() {
+            return Ok(None);
+        }
+        let content = std::fs::read_to_string(&sig_path)?;
+        Ok(Some(serde_json::from_str(&content)?))
+    }
+}
+
+/// 文件对应的分离签名路径
+pub fn signature_path(path: &Path) -> std::path::PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".");
+    name.push(SIGNATURE_EXTENSION);
+    name.into()
+}
+
+/// 受信任的发布者公钥
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrustedKey {
+    /// 公钥 ID (公钥 SHA-256 的前 16 位十六进制)
+    pub key_id: String,
+    /// 发布者名称
+    pub publisher: String,
+    /// base64 编码的 ed25519 公钥
+    pub public_key: String,
+    /// 添加时间
+    pub added_at: DateTime<Utc>,
+}
+
+impl TrustedKey {
+    /// 从 base64 公钥创建，公钥 ID 由公钥派生
+    pub fn new(publisher: impl Into<String>, public_key: &str) -> Result<Self, InstallError> {
+        let bytes = decode_public_key(public_key)?;
+        Ok(Self {
+            key_id: key_id(&bytes),
+            publisher: publisher.into(),
+            public_key: public_key.trim().to_string(),
+            added_at: Utc::now(),
+        })
+    }
+
+    /// 验证 SHA-256 摘要的签名
+    pub fn verify(
+        &self,
+        digest: &[u8; 32],
+        signature: &PackageSignature,
+    ) -> Result<(), InstallError> {
+        if signature.key_id != self.key_id {
+            return Err(InstallError::SignatureInvalid(format!(
+                "签名公钥 {} 与 {} 不匹配",
+                signature.key_id, self.key_id
+            )));
+        }
+        let key = VerifyingKey::from_bytes(&decode_public_key(&self.public_key)?)
+            .map_err(|e| InstallError::SignatureInvalid(format!("无效的公钥: {}", e)))?;
+        let bytes = base64::engine::general_purpose::STANDARD
+            .decode(signature.signature.trim())
+            .map_err(|e| InstallError::SignatureInvalid(format!("签名不是有效的 base64: {}", e)))?;
+        let signature = Signature::from_slice(&bytes)
+            .map_err(|e| InstallError::SignatureInvalid(format!("无效的签名: {}", e)))?;
+        key.verify(digest, &signature)
+            .map_err(|_| InstallError::SignatureInvalid("签名与内容不匹配".to_string()))
+    }
+}
+
+/// 在受信任公钥中查找签名公钥并验证，返回签名者
+pub fn verify_signature<'a>(
+    trusted: &'a [TrustedKey],
+    digest: &[u8; 32],
+    signature: &PackageSignature,
+) -> Result<&'a TrustedKey, InstallError> {
+    let key = trusted
+        .iter()
+        .find(|k| k.key_id == signature.key_id)
+        .ok_or_else(|| InstallError::UntrustedKey(signature.key_id.clone()))?;
+    key.verify(digest, signature)?;
+    Ok(key)
+}
+
+/// 计算文件的 SHA-256 摘要
+pub fn sha256_file(path: &Path) -> Result<[u8; 32], InstallError> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut hasher = Sha256::new();
+    let mut buffer = [0u8; 8192];
+    loop {
+        let bytes_read = reader.read(&mut buffer)?;
+        if bytes_read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..bytes_read]);
+    }
+    Ok(hasher.finalize().into())
+}
+
+/// 摘要的十六进制表示
+pub fn hex_digest(digest: &[u8; 32]) -> String {
+    digest.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn key_id(public_key: &[u8; 32]) -> String {
+    hex_digest(&Sha256::digest(public_key).into())[..16].to_string()
+}
+
+fn decode_public_key(public_key: &str) -> Result<[u8; 32], InstallError> {
+    let bytes = base64::engine::general_purpose::STANDARD
+        .decode(public_key.trim())
+        .map_err(|e| InstallError::SignatureInvalid(format!("公钥不是有效的 base64: {}", e)))?;
+    bytes
+        .try_into()
+        .map_err(|_| InstallError::SignatureInvalid("ed25519 公钥长度必须为 32 字节".to_string()))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use ed25519_dalek::{Signer, SigningKey};
+
+    /// 测试用发布者密钥
+    pub(crate) fn signing_key(seed: u8) -> SigningKey {
+        SigningKey::from_bytes(&[seed; 32])
+    }
+
+    pub(crate) fn trusted_key(key: &SigningKey) -> TrustedKey {
+        let public_key =
+            base64::engine::general_purpose::STANDARD.encode(key.verifying_key().as_bytes());
+        TrustedKey::new("test-publisher", &public_key).unwrap()
+    }
+
+    /// 为文件写入分离签名
+    pub(crate) fn sign_file(key: &SigningKey, path: &Path) -> PackageSignature {
+        let digest = sha256_file(path).unwrap();
+        let signature = PackageSignature {
+            key_id: trusted_key(key).key_id,
+            signature: base64::engine::general_purpose::STANDARD
+                .encode(key.sign(&digest).to_bytes()),
+        };
+        std::fs::write(
+            signature_path(path),
+            serde_json::to_string(&signature).unwrap(),
+        )
+        .unwrap();
+        signature
+    }
+
+    #[test]
+    fn test_verify_detached_signature() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("plugin.zip");
+        std::fs::write(&path, b"package").unwrap();
+
+        let key = signing_key(1);
+        sign_file(&key, &path);
+        let signature = PackageSignature::load_for(&path).unwrap().unwrap();
+        let trusted = vec![trusted_key(&key)];
+
+        let digest = sha256_file(&path).unwrap();
+        let signer = verify_signature(&trusted, &digest, &signature).unwrap();
+        assert_eq!(signer.publisher, "test-publisher");
+
+        // 内容被篡改
+        std::fs::write(&path, b"tampered").unwrap();
+        let digest = sha256_file(&path).unwrap();
+        assert!(matches!(
+            verify_signature(&trusted, &digest, &signature),
+            Err(InstallError::SignatureInvalid(_))
+        ));
+
+        // 签名者不在信任库中
+        let other = vec![trusted_key(&signing_key(2))];
+        assert!(matches!(
+            verify_signature(&other, &digest, &signature),
+            Err(InstallError::UntrustedKey(_))
+        ));
+    }
+
+    #[test]
+    fn test_trusted_key_rejects_invalid_public_key() {
+        assert!(TrustedKey::new("p", "not base64!").is_err());
+        assert!(TrustedKey::new("p", "AAAA").is_err());
+    }
+}
@@ -74,6 +74,22 @@ pub enum InstallError {
     /// 不支持的平台
     #[error("不支持的平台: {0}")]
     UnsupportedPlatform(String),
+
+    /// 签名无效
+    #[error("签名无效: {0}")]
+    SignatureInvalid(String),
+
+    /// 签名公钥不受信任
+    #[error("签名公钥不受信任: {0}")]
+    UntrustedKey(String),
+
+    /// 缺少签名
+    #[error("缺少签名: {0}")]
+    SignatureMissing(String),
+
+    /// 没有兼容的版本
+    #[error("没有兼容的版本: {0}")]
+    Incompatible(String),
 }
 
 /// 安装阶段
@@ -268,6 +284,11 @@ pub enum InstallSource {
         /// release tag
         tag: String,
     },
+    /// 签名插件索引
+    Index {
+        /// 索引位置（目录、file:// 或 http(s) URL）
+        index: String,
+    },
 }
 
 /// GitHub release 信息
@@ -330,6 +351,15 @@ pub struct InstalledPlugin {
     pub source: InstallSource,
     /// 是否启用
     pub enabled: bool,
+    /// 签名公钥 ID（未签名时为空）
+    #[serde(default)]
+    pub signer: Option<String>,
+    /// 固定版本，设置后更新检查跳过此插件
+    #[serde(default)]
+    pub pinned_version: Option<String>,
+    /// 更新前的版本，可回滚到此版本
+    #[serde(default)]
+    pub previous_version: Option<String>,
 }
 
 impl InstalledPlugin {
@@ -352,6 +382,9 @@ impl InstalledPlugin {
             installed_at: Utc::now(),
             source,
             enabled: true,
+            signer: None,
+            pinned_version: None,
+            previous_version: None,
         }
     }
 
@@ -366,6 +399,25 @@ impl InstalledPlugin {
         self.enabled = enabled;
         self
     }
+
+    /// 设置签名公钥 ID
+    pub fn with_signer(mut self, signer: Option<String>) -> Self {
+        self.signer = signer;
+        self
+    }
+}
+
+/// 可用的插件更新
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PluginUpdate {
+    /// 插件 ID
+    pub id: String,
+    /// 已安装版本
+    pub current_version: String,
+    /// 索引中的目标版本
+    pub available_version: String,
+    /// 是否为固定版本
+    pub pinned: bool,
 }
 
 #[cfg(test)]
@@ -417,6 +417,10 @@ mod tests {
             min_proxycast_version: None,
             binary: None,
             ui: None,
+            script_limits: None,
+            wasm: None,
+            rpc: None,
+            provider: None,
         }
     }
 
@@ -965,6 +969,10 @@ mod property_tests {
                 min_proxycast_version: None,
                 binary: None,
                 ui: None,
+                script_limits: None,
+                wasm: None,
+                rpc: None,
+                provider: None,
             };
 
             let validator = PackageValidator::new();
@@ -0,0 +1,84 @@
+//! 插件私有 KV 存储
+//!
+//! 同一插件的所有调用共享，修改后通过 `flush` 落盘到插件目录
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use parking_lot::Mutex;
+
+use super::types::PluginError;
+
+/// 插件 KV 存储
+pub struct PluginKvStore {
+    path: Option<PathBuf>,
+    entries: Mutex<BTreeMap<String, serde_json::Value>>,
+    dirty: AtomicBool,
+    max_entries: usize,
+}
+
+impl PluginKvStore {
+    /// 打开 KV 存储，`path` 为空时仅保存在内存中
+    pub fn open(path: Option<PathBuf>, max_entries: usize) -> Self {
+        let entries = path
+            .as_deref()
+            .and_then(|p| std::fs::read_to_string(p).ok())
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+        Self {
+            path,
+            entries: Mutex::new(entries),
+            dirty: AtomicBool::new(false),
+            max_entries,
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<serde_json::Value> {
+        self.entries.lock().get(key).cloned()
+    }
+
+    /// 写入键值，新键超过最大条目数时返回错误
+    pub fn set(&self, key: String, value: serde_json::Value) -> Result<(), PluginError> {
+        let mut entries = self.entries.lock();
+        if !entries.contains_key(&key) && entries.len() >= self.max_entries {
+            return Err(PluginError::ConfigError(format!(
+                "KV 存储已达到最大条目数: {}",
+                self.max_entries
+            )));
+        }
+        entries.insert(key, value);
+        self.dirty.store(true, Ordering::Release);
+        Ok(())
+    }
+
+    pub fn delete(&self, key: &str) -> bool {
+        let removed = self.entries.lock().remove(key).is_some();
+        if removed {
+            self.dirty.store(true, Ordering::Release);
+        }
+        removed
+    }
+
+    pub fn keys(&self) -> Vec<String> {
+        self.entries.lock().keys().cloned().collect()
+    }
+
+    /// 有未保存的修改时写入磁盘 (先写临时文件再替换)
+    pub fn flush(&self) -> Result<(), PluginError> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if !self.dirty.swap(false, Ordering::AcqRel) {
+            return Ok(());
+        }
+        let content = serde_json::to_string_pretty(&*self.entries.lock())?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let tmp_path = path.with_extension("json.tmp");
+        std::fs::write(&tmp_path, content)?;
+        std::fs::rename(&tmp_path, path)?;
+        Ok(())
+    }
+}
@@ -1,8 +1,11 @@
 //! 插件加载器
 
+use super::rpc_runtime::RpcPlugin;
+use super::script_runtime::EmbeddedScriptPlugin;
 use super::types::{
     HookResult, Plugin, PluginConfig, PluginContext, PluginError, PluginManifest, PluginType,
 };
+use super::wasm_runtime::WasmPlugin;
 use async_trait::async_trait;
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
@@ -68,10 +71,8 @@ impl PluginLoader {
         let manifest = self.load_manifest(plugin_dir).await?;
         match manifest.plugin_type {
             PluginType::Script => self.load_script_plugin(plugin_dir, manifest, config).await,
-            PluginType::Native => Err(PluginError::LoadError("原生插件暂不支持".to_string())),
-            PluginType::Binary => Err(PluginError::LoadError(
-                "二进制组件不通过插件加载器加载".to_string(),
-            )),
+            PluginType::Native => self.load_wasm_plugin(plugin_dir, manifest, config).await,
+            PluginType::Binary => self.load_rpc_plugin(plugin_dir, manifest, config).await,
         }
     }
 
@@ -79,21 +80,96 @@ impl PluginLoader {
         &self,
         plugin_dir: &Path,
         manifest: PluginManifest,
-        _config: &PluginConfig,
+        config: &PluginConfig,
     ) -> Result<Arc<dyn Plugin>, PluginError> {
-        let config_path = plugin_dir.join("config.json");
-        let plugin_settings = if config_path.exists() {
-            let content = fs::read_to_string(&config_path)
+        let plugin_settings = self.load_plugin_settings(plugin_dir).await?;
+
+        // 入口为 .rhai 脚本时使用嵌入式脚本引擎
+        if manifest.entry.ends_with(".rhai") {
+            let source = fs::read_to_string(plugin_dir.join(&manifest.entry))
                 .await
-                .map_err(|e| PluginError::LoadError(format!("无法读取配置文件: {}", e)))?;
-            serde_json::from_str(&content).unwrap_or_default()
-        } else {
-            serde_json::Value::Object(serde_json::Map::new())
-        };
+                .map_err(|e| PluginError::LoadError(format!("无法读取脚本文件: {}", e)))?;
+            let kv_path = plugin_dir.join("data").join("kv.json");
+            let plugin = EmbeddedScriptPlugin::new(
+                manifest,
+                &source,
+                plugin_settings,
+                config,
+                Some(kv_path),
+            )?;
+            return Ok(Arc::new(plugin));
+        }
+
         let plugin = ScriptPlugin::new(manifest, plugin_settings);
         Ok(Arc::new(plugin))
     }
 
+    async fn load_wasm_plugin(
+        &self,
+        plugin_dir: &Path,
+        manifest: PluginManifest,
+        config: &PluginConfig,
+    ) -> Result<Arc<dyn Plugin>, PluginError> {
+        if !manifest.entry.ends_with(".wasm") {
+            return Err(PluginError::InvalidManifest(format!(
+                "原生插件入口必须是 .wasm 文件: {}",
+                manifest.entry
+            )));
+        }
+        let module_path = plugin_dir.join(&manifest.entry);
+        let wasm = fs::read(&module_path)
+            .await
+            .map_err(|e| PluginError::LoadError(format!("无法读取 WASM 模块: {}", e)))?;
+        let settings = self.load_plugin_settings(plugin_dir).await?;
+        let kv_path = plugin_dir.join("data").join("kv.json");
+
+        let mut plugin = WasmPlugin::new(manifest, &wasm, settings, config, Some(kv_path))?;
+        if let Err(e) = plugin.watch(&module_path) {
+            tracing::warn!("插件 {} 无法启用热重载: {}", plugin.name(), e);
+        }
+        Ok(Arc::new(plugin))
+    }
+
+    /// 声明了钩子或 Provider 的二进制插件以子进程方式参与请求管道
+    async fn load_rpc_plugin(
+        &self,
+        plugin_dir: &Path,
+        manifest: PluginManifest,
+        config: &PluginConfig,
+    ) -> Result<Arc<dyn Plugin>, PluginError> {
+        if manifest.hooks.is_empty() && manifest.provider.is_none() {
+            return Err(PluginError::LoadError(
+                "未声明钩子的二进制组件不通过插件加载器加载".to_string(),
+            ));
+        }
+        // 优先使用下载的当前平台二进制，否则使用入口文件
+        let executable = manifest
+            .binary
+            .as_ref()
+            .and_then(|b| b.platform_binaries.get_current_platform())
+            .map(|name| plugin_dir.join(name))
+            .filter(|path| path.exists())
+            .unwrap_or_else(|| plugin_dir.join(&manifest.entry));
+        let settings = self.load_plugin_settings(plugin_dir).await?;
+
+        let plugin = RpcPlugin::start(manifest, executable, settings, config).await?;
+        Ok(Arc::new(plugin))
+    }
+
+    async fn load_plugin_settings(
+        &self,
+        plugin_dir: &Path,
+    ) -> Result<serde_json::Value, PluginError> {
+        let config_path = plugin_dir.join("config.json");
+        if !config_path.exists() {
+            return Ok(serde_json::Value::Object(serde_json::Map::new()));
+        }
+        let content = fs::read_to_string(&config_path)
+            .await
+            .map_err(|e| PluginError::LoadError(format!("无法读取配置文件: {}", e)))?;
+        Ok(serde_json::from_str(&content).unwrap_or_default())
+    }
+
     pub async fn load_all(
         &self,
         configs: &HashMap<String, PluginConfig>,
@@ -12,6 +12,8 @@ use tokio::sync::RwLock;
 use tokio::time::timeout;
 
 use super::loader::PluginLoader;
+use super::provider::PluginProvider;
+use super::stream_session::PluginStreamSession;
 use super::types::{
     HookResult, PluginConfig, PluginContext, PluginError, PluginInfo, PluginInstance, PluginStatus,
 };
@@ -296,7 +298,13 @@ impl PluginManager {
                     .record_execution(result.success, result.error.clone());
             }
 
+            // 插件返回短路动作时，后续插件不再执行
+            let short_circuit = result.action.is_some();
             results.push(result);
+            if short_circuit {
+                tracing::info!("插件 {} 短路了请求", plugin_name);
+                break;
+            }
         }
 
         results
@@ -350,7 +358,12 @@ impl PluginManager {
                     .record_execution(result.success, result.error.clone());
             }
 
+            let short_circuit = result.action.is_some();
             results.push(result);
+            if short_circuit {
+                tracing::info!("插件 {} 替换了响应", plugin_name);
+                break;
+            }
         }
 
         results
@@ -406,6 +419,52 @@ impl PluginManager {
         results
     }
 
+    /// 为流式响应创建插件钩子会话
+    ///
+    /// 会话在创建时固定当前启用的插件，流处理期间插件的加载/卸载不影响该会话
+    pub async fn stream_session(&self, ctx: PluginContext) -> PluginStreamSession {
+        if !self.config.enabled {
+            return PluginStreamSession::new(ctx, Vec::new());
+        }
+
+        let mut plugins = Vec::new();
+        for entry in self.plugins.iter() {
+            let instance = entry.value().read().await;
+            if instance.is_enabled() {
+                plugins.push((instance.plugin.clone(), instance.config.timeout_ms));
+            }
+        }
+        PluginStreamSession::new(ctx, plugins)
+    }
+
+    /// 获取启用插件提供的上游 Provider
+    pub async fn providers(&self) -> Vec<PluginProvider> {
+        if !self.config.enabled {
+            return Vec::new();
+        }
+
+        let mut providers = Vec::new();
+        for entry in self.plugins.iter() {
+            let instance = entry.value().read().await;
+            if !instance.is_enabled() {
+                continue;
+            }
+            if let Some(manifest) = instance.plugin.manifest().provider.clone() {
+                providers.push(PluginProvider::new(
+                    instance.plugin.clone(),
+                    manifest,
+                    instance.config.timeout_ms,
+                ));
+            }
+        }
+        providers
+    }
+
+    /// 按标识查找插件 Provider
+    pub async fn provider(&self, id: &str) -> Option<PluginProvider> {
+        self.providers().await.into_iter().find(|p| p.id() == id)
+    }
+
     /// 获取已加载插件数量
     pub fn count(&self) -> usize {
         self.plugins.len()
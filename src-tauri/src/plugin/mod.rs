@@ -3,29 +3,53 @@
 //! 提供插件扩展功能，支持：
 //! - 插件加载和初始化
 //! - 请求前/响应后钩子
+//! - 短路响应与请求拒绝
+//! - 流式响应逐事件钩子
 //! - 插件隔离和错误处理
 //! - 插件配置管理
 //! - 二进制组件下载和管理
 //! - 声明式插件 UI 系统
 //! - 插件安装和卸载
+//! - 嵌入式沙箱脚本引擎 (Rhai)
+//! - WASM 原生插件运行时
+//! - 进程外二进制插件 (stdio JSON-RPC)
+//! - 插件提供的上游 Provider
 
 pub mod binary_downloader;
 pub mod examples;
 pub mod installer;
+mod kv_store;
 mod loader;
 mod manager;
+mod provider;
+mod rpc_runtime;
+mod script_runtime;
+mod short_circuit;
+mod stream_session;
 mod types;
 pub mod ui_builder;
 pub mod ui_events;
 pub mod ui_trait;
 pub mod ui_types;
+mod wasm_runtime;
 
 pub use binary_downloader::BinaryDownloader;
 pub use loader::PluginLoader;
 pub use manager::PluginManager;
+pub use provider::{
+    openai_response_to_events, plugin_provider_id, PluginProvider, PluginProviderError,
+    PLUGIN_PROVIDER_PREFIX,
+};
+pub use rpc_runtime::{RpcHook, RpcPlugin, RPC_PROTOCOL_VERSION};
+pub use script_runtime::{EmbeddedScriptPlugin, ScriptHook, ScriptRuntime};
+pub use short_circuit::ResponseFormat;
+pub use stream_session::PluginStreamSession;
 pub use types::{
-    BinaryComponentStatus, BinaryManifest, HookResult, PlatformBinaries, Plugin, PluginConfig,
-    PluginContext, PluginError, PluginInfo, PluginManifest, PluginState, PluginStatus, PluginType,
+    BinaryComponentStatus, BinaryManifest, HookAction, HookResult, PlatformBinaries, Plugin,
+    PluginConfig, PluginContext, PluginError, PluginHealth, PluginInfo, PluginManifest,
+    PluginRejection, PluginState, PluginStatus, PluginType, ProcessStatus, ProviderManifest,
+    RpcManifest, ScriptLimits, SyntheticResponse, UpstreamRequest, UpstreamResponse,
+    WasmCapabilities, WasmLimits, WasmManifest,
 };
 pub use ui_events::{PluginUIEmitter, PluginUIEmitterState, PluginUIEventPayload};
 pub use ui_trait::{NoUI, PluginUI};
@@ -33,6 +57,7 @@ pub use ui_types::{
     Action, BoundValue, ChildrenDef, ComponentDef, ComponentType, DataEntry, DataModelUpdate,
     SurfaceDefinition, SurfaceUpdate, UIMessage, UserAction,
 };
+pub use wasm_runtime::{WasmHook, WasmPlugin, WasmRuntime, WASM_ABI_VERSION};
 
 #[cfg(test)]
 mod tests;
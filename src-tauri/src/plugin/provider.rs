@@ -0,0 +1,585 @@
+//! 插件提供的上游 Provider
+//!
+//! 清单声明了 `provider` 的插件以 `plugin:<id>` 名称注册为上游 Provider。
+//! 宿主负责凭证池选择、健康标记、故障转移和 Flow 记录；插件负责凭证刷新，
+//! 以及规范协议 (OpenAI Chat Completions) 与上游协议之间的转换。
+//!
+//! 上游调用始终为非流式，客户端的流式请求由完整响应合成 SSE 事件。
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use thiserror::Error;
+use tokio::time::timeout;
+
+use super::types::{Plugin, PluginError, ProviderManifest, UpstreamRequest, UpstreamResponse};
+use crate::models::provider_pool_model::pattern_matches;
+use crate::stream::{ContentBlockType, StopReason, StreamEvent};
+
+/// 插件 Provider 名称前缀
+pub const PLUGIN_PROVIDER_PREFIX: &str = "plugin:";
+
+/// 从 Provider 名称 (`plugin:<id>`) 中解析插件 Provider 标识
+pub fn plugin_provider_id(provider: &str) -> Option<&str> {
+    provider
+        .strip_prefix(PLUGIN_PROVIDER_PREFIX)
+        .filter(|id| !id.is_empty())
+}
+
+/// 插件 Provider 调用错误
+#[derive(Debug, Error)]
+pub enum PluginProviderError {
+    #[error(transparent)]
+    Plugin(#[from] PluginError),
+
+    #[error("凭证无效: {0}")]
+    InvalidCredential(String),
+
+    #[error("上游请求失败: {0}")]
+    Http(String),
+
+    #[error("上游返回错误: HTTP {status} - {body}")]
+    Upstream { status: u16, body: String },
+}
+
+impl PluginProviderError {
+    /// 返回给客户端的 HTTP 状态码
+    pub fn status_code(&self) -> u16 {
+        match self {
+            PluginProviderError::Upstream { status, .. } => *status,
+            PluginProviderError::InvalidCredential(_) => 401,
+            PluginProviderError::Http(_) => 502,
+            PluginProviderError::Plugin(_) => 500,
+        }
+    }
+}
+
+/// 插件提供的上游 Provider
+#[derive(Clone)]
+pub struct PluginProvider {
+    plugin: Arc<dyn Plugin>,
+    manifest: ProviderManifest,
+    /// 插件钩子超时 (毫秒)
+    hook_timeout_ms: u64,
+}
+
+impl PluginProvider {
+    /// 创建插件 Provider
+    pub fn new(plugin: Arc<dyn Plugin>, manifest: ProviderManifest, hook_timeout_ms: u64) -> Self {
+        Self {
+            plugin,
+            manifest,
+            hook_timeout_ms,
+        }
+    }
+
+    /// Provider 标识
+    pub fn id(&self) -> &str {
+        &self.manifest.id
+    }
+
+    /// Provider 名称 (`plugin:<id>`)，用于路由配置和凭证池
+    pub fn key(&self) -> String {
+        format!("{}{}", PLUGIN_PROVIDER_PREFIX, self.manifest.id)
+    }
+
+    /// 提供该 Provider 的插件名称
+    pub fn plugin_name(&self) -> &str {
+        self.plugin.name()
+    }
+
+    /// Provider 声明
+    pub fn manifest(&self) -> &ProviderManifest {
+        &self.manifest
+    }
+
+    /// 是否支持指定模型
+    pub fn supports_model(&self, model: &str) -> bool {
+        self.manifest.models.is_empty()
+            || self
+                .manifest
+                .models
+                .iter()
+                .any(|pattern| pattern_matches(pattern, model))
+    }
+
+    /// 按 `credential_schema` 校验凭证字段
+    pub fn validate_credential(
+        &self,
+        fields: &serde_json::Value,
+    ) -> Result<(), PluginProviderError> {
+        let Some(object) = fields.as_object() else {
+            return Err(PluginProviderError::InvalidCredential(
+                "凭证字段必须是 JSON 对象".to_string(),
+            ));
+        };
+        let Some(schema) = &self.manifest.credential_schema else {
+            return Ok(());
+        };
+
+        let required = schema["required"].as_array().into_iter().flatten();
+        for name in required.filter_map(|v| v.as_str()) {
+            if object.get(name).is_none_or(|v| v.is_null()) {
+                return Err(PluginProviderError::InvalidCredential(format!(
+                    "缺少字段: {}",
+                    name
+                )));
+            }
+        }
+
+        if let Some(properties) = schema["properties"].as_object() {
+            for (name, value) in object {
+                let Some(expected) = properties.get(name).and_then(|p| p["type"].as_str()) else {
+                    continue;
+                };
+                let matches = match expected {
+                    "string" => value.is_string(),
+                    "number" => value.is_number(),
+                    "integer" => value.is_i64() || value.is_u64(),
+                    "boolean" => value.is_boolean(),
+                    "object" => value.is_object(),
+                    "array" => value.is_array(),
+                    _ => true,
+                };
+                if !matches && !value.is_null() {
+                    return Err(PluginProviderError::InvalidCredential(format!(
+                        "字段 {} 应为 {}",
+                        name, expected
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// 调用插件刷新凭证，返回凭证是否有变更
+    pub async fn refresh_credential(
+        &self,
+        fields: &mut serde_json::Value,
+    ) -> Result<bool, PluginProviderError> {
+        let result = timeout(
+            self.hook_timeout(),
+            self.plugin.provider_refresh_token(fields),
+        )
+        .await
+        .map_err(|_| self.timeout_error())??;
+        Ok(result)
+    }
+
+    /// 发送规范协议请求，返回规范协议响应
+    pub async fn chat_completions(
+        &self,
+        client: &reqwest::Client,
+        fields: &serde_json::Value,
+        request: &serde_json::Value,
+    ) -> Result<serde_json::Value, PluginProviderError> {
+        let upstream = timeout(
+            self.hook_timeout(),
+            self.plugin.provider_build_request(fields, request),
+        )
+        .await
+        .map_err(|_| self.timeout_error())??;
+
+        let response = self.send(client, upstream).await?;
+        if !(200..300).contains(&response.status) {
+            let body = match &response.body {
+                serde_json::Value::String(text) => text.clone(),
+                other => other.to_string(),
+            };
+            return Err(PluginProviderError::Upstream {
+                status: response.status,
+                body,
+            });
+        }
+
+        let parsed = timeout(
+            self.hook_timeout(),
+            self.plugin.provider_parse_response(fields, response),
+        )
+        .await
+        .map_err(|_| self.timeout_error())??;
+        Ok(parsed)
+    }
+
+    async fn send(
+        &self,
+        client: &reqwest::Client,
+        upstream: UpstreamRequest,
+    ) -> Result<UpstreamResponse, PluginProviderError> {
+        let method = reqwest::Method::from_bytes(upstream.method.to_uppercase().as_bytes())
+            .map_err(|e| PluginProviderError::Http(format!("无效的请求方法: {}", e)))?;
+        let mut builder = client
+            .request(method, &upstream.url)
+            .timeout(Duration::from_millis(self.manifest.request_timeout_ms));
+        for (name, value) in &upstream.headers {
+            builder = builder.header(name, value);
+        }
+        if let Some(body) = &upstream.body {
+            builder = builder.json(body);
+        }
+
+        let resp = builder
+            .send()
+            .await
+            .map_err(|e| PluginProviderError::Http(e.to_string()))?;
+        let status = resp.status().as_u16();
+        let headers: HashMap<String, String> = resp
+            .headers()
+            .iter()
+            .filter_map(|(k, v)| Some((k.to_string(), v.to_str().ok()?.to_string())))
+            .collect();
+        let text = resp
+            .text()
+            .await
+            .map_err(|e| PluginProviderError::Http(e.to_string()))?;
+        let body = serde_json::from_str(&text).unwrap_or(serde_json::Value::String(text));
+
+        Ok(UpstreamResponse {
+            status,
+            headers,
+            body,
+        })
+    }
+
+    fn hook_timeout(&self) -> Duration {
+        Duration::from_millis(self.hook_timeout_ms)
+    }
+
+    fn timeout_error(&self) -> PluginProviderError {
+        PluginProviderError::Plugin(PluginError::Timeout {
+            plugin_name: self.plugin.name().to_string(),
+            timeout_ms: self.hook_timeout_ms,
+        })
+    }
+}
+
+/// 将规范协议 (OpenAI Chat Completions) 的完整响应转换为统一流事件，用于合成流式响应
+pub fn openai_response_to_events(response: &serde_json::Value, model: &str) -> Vec<StreamEvent> {
+    let message = &response["choices"][0]["message"];
+    let mut events = vec![StreamEvent::MessageStart {
+        id: response["id"].as_str().unwrap_or_default().to_string(),
+        model: response["model"].as_str().unwrap_or(model).to_string(),
+    }];
+
+    let mut index = 0;
+    if let Some(text) = message["content"].as_str().filter(|t| !t.is_empty()) {
+        events.push(StreamEvent::ContentBlockStart {
+            index,
+            block_type: ContentBlockType::Text,
+        });
+        events.push(StreamEvent::TextDelta {
+            text: text.to_string(),
+        });
+        events.push(StreamEvent::ContentBlockStop { index });
+        index += 1;
+    }
+
+    let tool_calls = message["tool_calls"].as_array().into_iter().flatten();
+    for call in tool_calls {
+        let id = call["id"].as_str().unwrap_or_default().to_string();
+        let name = call["function"]["name"]
+            .as_str()
+            .unwrap_or_default()
+            .to_string();
+        events.push(StreamEvent::ContentBlockStart {
+            index,
+            block_type: ContentBlockType::ToolUse {
+                id: id.clone(),
+                name: name.clone(),
+            },
+        });
+        events.push(StreamEvent::ToolUseStart {
+            id: id.clone(),
+            name,
+        });
+        events.push(StreamEvent::ToolUseInputDelta {
+            id: id.clone(),
+            partial_json: call["function"]["arguments"]
+                .as_str()
+                .unwrap_or("{}")
+                .to_string(),
+        });
+        events.push(StreamEvent::ToolUseStop { id });
+        events.push(StreamEvent::ContentBlockStop { index });
+        index += 1;
+    }
+
+    let usage = &response["usage"];
+    events.push(StreamEvent::Usage {
+        input_tokens: usage["prompt_tokens"].as_u64().unwrap_or(0) as u32,
+        output_tokens: usage["completion_tokens"].as_u64().unwrap_or(0) as u32,
+        cache_read_input_tokens: None,
+        cache_creation_input_tokens: None,
+    });
+    let stop_reason = response["choices"][0]["finish_reason"]
+        .as_str()
+        .map(StopReason::from_str)
+        .unwrap_or_default();
+    events.push(StreamEvent::MessageStop { stop_reason });
+    events
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plugin::{HookResult, PluginConfig, PluginContext, PluginManifest, PluginType};
+    use async_trait::async_trait;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    /// 将请求转发到 `base_url`，并把上游的 `{"text"}` 响应转换为 OpenAI 格式的测试插件
+    struct EchoProvider {
+        manifest: PluginManifest,
+    }
+
+    #[async_trait]
+    impl Plugin for EchoProvider {
+        fn name(&self) -> &str {
+            &self.manifest.name
+        }
+
+        fn version(&self) -> &str {
+            &self.manifest.version
+        }
+
+        fn manifest(&self) -> &PluginManifest {
+            &self.manifest
+        }
+
+        async fn init(&mut self, _config: &PluginConfig) -> Result<(), PluginError> {
+            Ok(())
+        }
+
+        async fn on_request(
+            &self,
+            _ctx: &mut PluginContext,
+            _request: &mut serde_json::Value,
+        ) -> Result<HookResult, PluginError> {
+            Ok(HookResult::success(false, 0))
+        }
+
+        async fn on_response(
+            &self,
+            _ctx: &mut PluginContext,
+            _response: &mut serde_json::Value,
+        ) -> Result<HookResult, PluginError> {
+            Ok(HookResult::success(false, 0))
+        }
+
+        async fn on_error(
+            &self,
+            _ctx: &mut PluginContext,
+            _error: &str,
+        ) -> Result<HookResult, PluginError> {
+            Ok(HookResult::success(false, 0))
+        }
+
+        async fn provider_refresh_token(
+            &self,
+            credential: &mut serde_json::Value,
+        ) -> Result<bool, PluginError> {
+            if credential["access_token"].is_null() {
+                credential["access_token"] = serde_json::json!("fresh");
+                return Ok(true);
+            }
+            Ok(false)
+        }
+
+        async fn provider_build_request(
+            &self,
+            credential: &serde_json::Value,
+            request: &serde_json::Value,
+        ) -> Result<UpstreamRequest, PluginError> {
+            Ok(UpstreamRequest {
+                url: format!("{}/generate", credential["base_url"].as_str().unwrap()),
+                method: "POST".to_string(),
+                headers: HashMap::from([(
+                    "authorization".to_string(),
+                    format!("Bearer {}", credential["access_token"].as_str().unwrap()),
+                )]),
+                body: Some(serde_json::json!({ "prompt": request["messages"][0]["content"] })),
+            })
+        }
+
+        async fn provider_parse_response(
+            &self,
+            _credential: &serde_json::Value,
+            response: UpstreamResponse,
+        ) -> Result<serde_json::Value, PluginError> {
+            Ok(serde_json::json!({
+                "id": "chatcmpl-echo",
+                "object": "chat.completion",
+                "model": "echo-1",
+                "choices": [{
+                    "index": 0,
+                    "message": { "role": "assistant", "content": response.body["text"] },
+                    "finish_reason": "stop",
+                }],
+                "usage": { "prompt_tokens": 1, "completion_tokens": 2, "total_tokens": 3 },
+            }))
+        }
+
+        async fn shutdown(&mut self) -> Result<(), PluginError> {
+            Ok(())
+        }
+    }
+
+    fn provider() -> PluginProvider {
+        let manifest = ProviderManifest {
+            id: "echo".to_string(),
+            display_name: None,
+            credential_schema: Some(serde_json::json!({
+                "required": ["base_url"],
+                "properties": {
+                    "base_url": { "type": "string" },
+                    "access_token": { "type": "string" },
+                },
+            })),
+            models: vec!["echo-*".to_string()],
+            request_timeout_ms: 5_000,
+        };
+        let plugin = EchoProvider {
+            manifest: PluginManifest {
+                name: "echo-provider".to_string(),
+                version: "1.0.0".to_string(),
+                description: String::new(),
+                author: None,
+                homepage: None,
+                license: None,
+                entry: "config.json".to_string(),
+                plugin_type: PluginType::Script,
+                config_schema: None,
+                hooks: vec![],
+                min_proxycast_version: None,
+                binary: None,
+                ui: None,
+                script_limits: None,
+                wasm: None,
+                rpc: None,
+                provider: Some(manifest.clone()),
+            },
+        };
+        PluginProvider::new(Arc::new(plugin), manifest, 1_000)
+    }
+
+    /// 只响应一次的 HTTP 上游，返回收到的原始请求
+    async fn one_shot_upstream(
+        status: &'static str,
+        body: &'static str,
+    ) -> (String, tokio::task::JoinHandle<String>) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let handle = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buf = vec![0u8; 8192];
+            let n = socket.read(&mut buf).await.unwrap();
+            let response = format!(
+                "HTTP/1.1 {}\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                status,
+                body.len(),
+                body
+            );
+            socket.write_all(response.as_bytes()).await.unwrap();
+            String::from_utf8_lossy(&buf[..n]).to_string()
+        });
+        (format!("http://{}", addr), handle)
+    }
+
+    #[test]
+    fn test_provider_key_and_models() {
+        let provider = provider();
+        assert_eq!(provider.key(), "plugin:echo");
+        assert_eq!(plugin_provider_id("plugin:echo"), Some("echo"));
+        assert_eq!(plugin_provider_id("plugin:"), None);
+        assert_eq!(plugin_provider_id("kiro"), None);
+        assert!(provider.supports_model("echo-1"));
+        assert!(!provider.supports_model("gpt-4"));
+    }
+
+    #[test]
+    fn test_validate_credential_against_schema() {
+        let provider = provider();
+        assert!(provider
+            .validate_credential(&serde_json::json!({ "base_url": "http://x" }))
+            .is_ok());
+        assert!(provider
+            .validate_credential(&serde_json::json!({}))
+            .is_err());
+        assert!(provider
+            .validate_credential(&serde_json::json!({ "base_url": 1 }))
+            .is_err());
+        assert!(provider
+            .validate_credential(&serde_json::json!("x"))
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_refresh_and_round_trip() {
+        let provider = provider();
+        let (base_url, upstream) = one_shot_upstream("200 OK", r#"{"text":"pong"}"#).await;
+        let mut fields = serde_json::json!({ "base_url": base_url });
+
+        assert!(provider.refresh_credential(&mut fields).await.unwrap());
+        assert!(!provider.refresh_credential(&mut fields).await.unwrap());
+
+        let request = serde_json::json!({
+            "model": "echo-1",
+            "messages": [{ "role": "user", "content": "ping" }],
+        });
+        let response = provider
+            .chat_completions(&reqwest::Client::new(), &fields, &request)
+            .await
+            .unwrap();
+        assert_eq!(response["choices"][0]["message"]["content"], "pong");
+
+        let raw = upstream.await.unwrap();
+        assert!(raw.starts_with("POST /generate"));
+        assert!(raw.contains("Bearer fresh"));
+        assert!(raw.contains(r#""prompt":"ping""#));
+    }
+
+    #[tokio::test]
+    async fn test_upstream_error_is_reported() {
+        let provider = provider();
+        let (base_url, _upstream) = one_shot_upstream("429 Too Many Requests", r#"{}"#).await;
+        let fields = serde_json::json!({ "base_url": base_url, "access_token": "t" });
+        let err = provider
+            .chat_completions(&reqwest::Client::new(), &fields, &serde_json::json!({}))
+            .await
+            .unwrap_err();
+        assert_eq!(err.status_code(), 429);
+    }
+
+    #[test]
+    fn test_openai_response_to_events() {
+        let events = openai_response_to_events(
+            &serde_json::json!({
+                "id": "chatcmpl-1",
+                "choices": [{
+                    "message": {
+                        "content": "hi",
+                        "tool_calls": [{
+                            "id": "call_1",
+                            "function": { "name": "search", "arguments": "{\"q\":1}" },
+                        }],
+                    },
+                    "finish_reason": "tool_calls",
+                }],
+            }),
+            "echo-1",
+        );
+        assert!(matches!(&events[0], StreamEvent::MessageStart { model, .. } if model == "echo-1"));
+        assert!(events
+            .iter()
+            .any(|e| matches!(e, StreamEvent::TextDelta { text } if text == "hi")));
+        assert!(events
+            .iter()
+            .any(|e| matches!(e, StreamEvent::ToolUseStart { name, .. } if name == "search")));
+        assert!(matches!(
+            events.last(),
+            Some(StreamEvent::MessageStop {
+                stop_reason: StopReason::ToolUse
+            })
+        ));
+    }
+}
@@ -0,0 +1,1125 @@
+//! 进程外插件运行时
+//!
+//! Binary 类型插件声明钩子后作为子进程运行，宿主通过 stdio 与其交换
+//! JSON-RPC 2.0 消息。子进程崩溃或挂起时由监督任务按指数退避重启，
+//! 健康信息通过 [`Plugin::health`] 汇报到 `PluginState`。
+//!
+//! # 协议 (v1)
+//!
+//! 每条消息为单行 JSON，以 `\n` 结尾；stdout 仅用于协议消息，stderr 会转发到宿主日志。
+//!
+//! 宿主发往插件的请求：
+//! - `init`：`{"protocol_version", "name", "version", "settings"}`，每次 (重新) 启动后首先调用
+//! - `on_request` / `on_response`：`{"ctx", "payload"}`
+//! - `on_error`：`{"ctx", "error"}`
+//! - `on_stream_start`：`{"ctx"}`
+//! - `on_stream_event`：`{"ctx", "event"}`，`event` 为序列化的 `StreamEvent`
+//! - `on_stream_end`：`{"ctx", "events"}`，`events` 为结束阶段待发送的事件
+//! - `shutdown`：无参数，插件应在响应后退出，超时未退出将被强制终止
+//!
+//! 钩子返回 `null` 表示不做修改，否则返回 `{"payload"?, "model"?, "metadata"?}`，
+//! `on_request` / `on_response` 还可返回 `{"action": {"type": "respond", "content", ...}}`
+//! 直接响应，或 `{"action": {"type": "reject", "status", "message"}}` 拒绝请求；
+//! 流事件钩子返回 `{"events"}` 替换当前事件 (空数组表示丢弃)；
+//! 返回 JSON-RPC `error` 对象表示执行失败。插件可发送 `log` 通知
+//! `{"level": "debug|info|warn|error", "message"}` 写入宿主日志。
+//!
+//! 仅清单 `hooks` 中声明的钩子会被调用。
+//!
+//! 清单声明了 `provider` 的插件还需实现上游适配方法：
+//! - `provider_refresh_token`：`{"credential"}`，返回 `null` 或 `{"credential"}` (更新后的凭证字段)
+//! - `provider_build_request`：`{"credential", "request"}`，`request` 为 OpenAI Chat Completions 请求，
+//!   返回 `{"url", "method"?, "headers"?, "body"?}`
+//! - `provider_parse_response`：`{"credential", "response"}`，`response` 为 `{"status", "headers", "body"}`，
+//!   返回 OpenAI Chat Completions 响应，`null` 表示响应体已是该格式
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::process::{ExitStatus, Stdio};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use chrono::Utc;
+use parking_lot::Mutex;
+use serde::Deserialize;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWriteExt, BufReader};
+use tokio::process::{Child, ChildStdin, Command};
+use tokio::sync::{oneshot, Notify};
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+
+use crate::stream::StreamEvent;
+
+use super::types::{
+    HookAction, HookResult, Plugin, PluginConfig, PluginContext, PluginError, PluginHealth,
+    PluginManifest, ProcessStatus, RpcManifest, UpstreamRequest, UpstreamResponse,
+};
+
+/// 进程外插件协议版本
+pub const RPC_PROTOCOL_VERSION: u32 = 1;
+
+/// 未配置超时时的调用超时 (毫秒)
+const DEFAULT_CALL_TIMEOUT_MS: u64 = 5_000;
+
+/// `init` 握手的最短超时 (毫秒)，为进程启动留出时间
+const INIT_TIMEOUT_MS: u64 = 10_000;
+
+/// 进程外插件钩子
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RpcHook {
+    Request,
+    Response,
+    Error,
+    StreamStart,
+    StreamEvent,
+    StreamEnd,
+}
+
+impl RpcHook {
+    /// JSON-RPC 方法名，同时也是清单 `hooks` 中的钩子名
+    pub fn method(&self) -> &'static str {
+        match self {
+            RpcHook::Request => "on_request",
+            RpcHook::Response => "on_response",
+            RpcHook::Error => "on_error",
+            RpcHook::StreamStart => "on_stream_start",
+            RpcHook::StreamEvent => "on_stream_event",
+            RpcHook::StreamEnd => "on_stream_end",
+        }
+    }
+}
+
+/// 插件发来的消息 (响应或通知)
+#[derive(Debug, Deserialize)]
+struct RpcMessage {
+    #[serde(default)]
+    id: Option<u64>,
+    #[serde(default)]
+    method: Option<String>,
+    #[serde(default)]
+    params: Option<serde_json::Value>,
+    #[serde(default)]
+    result: Option<serde_json::Value>,
+    #[serde(default)]
+    error: Option<RpcErrorObject>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RpcErrorObject {
+    #[serde(default)]
+    code: i64,
+    #[serde(default)]
+    message: String,
+}
+
+/// `log` 通知参数
+#[derive(Debug, Deserialize)]
+struct RpcLogParams {
+    #[serde(default)]
+    level: String,
+    #[serde(default)]
+    message: String,
+}
+
+/// 钩子返回值
+#[derive(Debug, Default, Deserialize)]
+struct RpcHookOutput {
+    #[serde(default)]
+    payload: Option<serde_json::Value>,
+    #[serde(default)]
+    model: Option<String>,
+    #[serde(default)]
+    metadata: Option<HashMap<String, serde_json::Value>>,
+    #[serde(default)]
+    events: Option<Vec<StreamEvent>>,
+    #[serde(default)]
+    action: Option<HookAction>,
+}
+
+type PendingCalls = Mutex<HashMap<u64, oneshot::Sender<Result<serde_json::Value, String>>>>;
+
+/// 与单个子进程的连接，进程重启后替换为新连接
+struct RpcConnection {
+    stdin: tokio::sync::Mutex<ChildStdin>,
+    pending: PendingCalls,
+    /// 请求监督任务强制终止进程
+    kill: Notify,
+}
+
+impl RpcConnection {
+    fn fail_pending(&self, reason: &str) {
+        for (_, tx) in self.pending.lock().drain() {
+            let _ = tx.send(Err(reason.to_string()));
+        }
+    }
+
+    fn dispatch(&self, plugin_name: &str, line: &str) {
+        let message: RpcMessage = match serde_json::from_str(line) {
+            Ok(message) => message,
+            Err(_) => {
+                tracing::debug!("[插件 {}] 忽略非协议输出: {}", plugin_name, line);
+                return;
+            }
+        };
+
+        if let Some(method) = message.method {
+            if method == "log" {
+                let params: RpcLogParams = message
+                    .params
+                    .and_then(|p| serde_json::from_value(p).ok())
+                    .unwrap_or(RpcLogParams {
+                        level: String::new(),
+                        message: String::new(),
+                    });
+                match params.level.as_str() {
+                    "debug" => tracing::debug!("[插件 {}] {}", plugin_name, params.message),
+                    "warn" => tracing::warn!("[插件 {}] {}", plugin_name, params.message),
+                    "error" => tracing::error!("[插件 {}] {}", plugin_name, params.message),
+                    _ => tracing::info!("[插件 {}] {}", plugin_name, params.message),
+                }
+            } else {
+                tracing::debug!("[插件 {}] 忽略未知通知: {}", plugin_name, method);
+            }
+            return;
+        }
+
+        let Some(id) = message.id else {
+            return;
+        };
+        let Some(tx) = self.pending.lock().remove(&id) else {
+            // 已超时的调用
+            return;
+        };
+        let result = match message.error {
+            Some(error) => Err(format!("{} (code {})", error.message, error.code)),
+            None => Ok(message.result.unwrap_or(serde_json::Value::Null)),
+        };
+        let _ = tx.send(result);
+    }
+}
+
+/// 子进程监督者，负责启动、调用、重启和关闭
+struct RpcSupervisor {
+    plugin_name: String,
+    plugin_version: String,
+    executable: PathBuf,
+    options: RpcManifest,
+    settings: serde_json::Value,
+    timeout_ms: AtomicU64,
+    next_id: AtomicU64,
+    connection: Mutex<Option<Arc<RpcConnection>>>,
+    health: Mutex<PluginHealth>,
+    cancel: CancellationToken,
+}
+
+/// 监督任务等待到的事件
+enum ProcessEvent {
+    Exited(String),
+    Shutdown,
+}
+
+impl RpcSupervisor {
+    /// 启动子进程并完成 `init` 握手
+    async fn spawn(self: &Arc<Self>) -> Result<Child, PluginError> {
+        {
+            let mut health = self.health.lock();
+            health.process = ProcessStatus::Starting;
+            health.pid = None;
+        }
+
+        let mut command = Command::new(&self.executable);
+        command
+            .args(&self.options.args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+        if let Some(dir) = self.executable.parent() {
+            command.current_dir(dir);
+        }
+        let mut child = command.spawn().map_err(|e| {
+            PluginError::LoadError(format!(
+                "无法启动插件进程 {}: {}",
+                self.executable.display(),
+                e
+            ))
+        })?;
+
+        let (Some(stdin), Some(stdout), Some(stderr)) =
+            (child.stdin.take(), child.stdout.take(), child.stderr.take())
+        else {
+            return Err(PluginError::LoadError(
+                "无法连接插件进程的标准输入输出".to_string(),
+            ));
+        };
+
+        let connection = Arc::new(RpcConnection {
+            stdin: tokio::sync::Mutex::new(stdin),
+            pending: Mutex::new(HashMap::new()),
+            kill: Notify::new(),
+        });
+        tokio::spawn(read_stdout(
+            self.plugin_name.clone(),
+            connection.clone(),
+            stdout,
+        ));
+        tokio::spawn(read_stderr(self.plugin_name.clone(), stderr));
+        *self.connection.lock() = Some(connection);
+
+        {
+            let mut health = self.health.lock();
+            health.pid = child.id();
+            health.started_at = Some(Utc::now());
+            health.consecutive_timeouts = 0;
+        }
+
+        let params = serde_json::json!({
+            "protocol_version": RPC_PROTOCOL_VERSION,
+            "name": self.plugin_name,
+            "version": self.plugin_version,
+            "settings": self.settings,
+        });
+        let init_timeout_ms = self.call_timeout_ms().max(INIT_TIMEOUT_MS);
+        if let Err(e) = self
+            .call_with_timeout("init", params, init_timeout_ms)
+            .await
+        {
+            self.connection.lock().take();
+            let _ = child.kill().await;
+            return Err(PluginError::InitError(e.to_string()));
+        }
+
+        self.health.lock().process = ProcessStatus::Running;
+        Ok(child)
+    }
+
+    fn call_timeout_ms(&self) -> u64 {
+        match self.timeout_ms.load(Ordering::Relaxed) {
+            0 => DEFAULT_CALL_TIMEOUT_MS,
+            timeout_ms => timeout_ms,
+        }
+    }
+
+    async fn call(
+        &self,
+        method: &str,
+        params: serde_json::Value,
+    ) -> Result<serde_json::Value, PluginError> {
+        self.call_with_timeout(method, params, self.call_timeout_ms())
+            .await
+    }
+
+    /// 发送请求并等待响应，超过 `timeout_ms` 时返回超时错误
+    async fn call_with_timeout(
+        &self,
+        method: &str,
+        params: serde_json::Value,
+        timeout_ms: u64,
+    ) -> Result<serde_json::Value, PluginError> {
+        let connection = self
+            .connection
+            .lock()
+            .clone()
+            .ok_or_else(|| self.execution_error(method, "插件进程未运行"))?;
+
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let mut line = serde_json::to_vec(&serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "method": method,
+            "params": params,
+        }))?;
+        line.push(b'\n');
+
+        let (tx, rx) = oneshot::channel();
+        connection.pending.lock().insert(id, tx);
+
+        let exchange = async {
+            let mut stdin = connection.stdin.lock().await;
+            stdin.write_all(&line).await?;
+            stdin.flush().await?;
+            drop(stdin);
+            Ok::<_, std::io::Error>(rx.await)
+        };
+
+        match tokio::time::timeout(Duration::from_millis(timeout_ms), exchange).await {
+            Ok(Ok(response)) => {
+                self.health.lock().consecutive_timeouts = 0;
+                match response {
+                    Ok(Ok(value)) => Ok(value),
+                    Ok(Err(message)) => Err(self.execution_error(method, message)),
+                    Err(_) => Err(self.execution_error(method, "插件进程已退出")),
+                }
+            }
+            Ok(Err(e)) => {
+                connection.pending.lock().remove(&id);
+                Err(self.execution_error(method, format!("写入请求失败: {}", e)))
+            }
+            Err(_) => {
+                connection.pending.lock().remove(&id);
+                self.record_timeout(&connection);
+                Err(PluginError::Timeout {
+                    plugin_name: self.plugin_name.clone(),
+                    timeout_ms,
+                })
+            }
+        }
+    }
+
+    fn record_timeout(&self, connection: &RpcConnection) {
+        let max = self.options.max_consecutive_timeouts;
+        let mut health = self.health.lock();
+        health.consecutive_timeouts += 1;
+        if max > 0 && health.consecutive_timeouts >= max {
+            tracing::warn!(
+                "插件 {} 连续 {} 次调用超时，强制重启进程",
+                self.plugin_name,
+                health.consecutive_timeouts
+            );
+            connection.kill.notify_one();
+        }
+    }
+
+    /// 监督子进程，退出后按指数退避重启，收到关闭信号时优雅退出
+    async fn supervise(self: Arc<Self>, mut child: Child) {
+        let initial_backoff = Duration::from_millis(self.options.initial_backoff_ms);
+        let max_backoff = Duration::from_millis(self.options.max_backoff_ms).max(initial_backoff);
+        let mut backoff = initial_backoff;
+        let mut attempts = 0u32;
+
+        loop {
+            let started = Instant::now();
+            let connection = self.connection.lock().clone();
+            let event = tokio::select! {
+                status = child.wait() => ProcessEvent::Exited(describe_exit(status)),
+                _ = wait_kill(connection.as_deref()) => {
+                    let _ = child.kill().await;
+                    ProcessEvent::Exited("连续调用超时，已强制终止".to_string())
+                }
+                _ = self.cancel.cancelled() => ProcessEvent::Shutdown,
+            };
+
+            let mut reason = match event {
+                ProcessEvent::Shutdown => {
+                    self.stop(child).await;
+                    return;
+                }
+                ProcessEvent::Exited(reason) => reason,
+            };
+            if let Some(connection) = self.connection.lock().take() {
+                connection.fail_pending(&reason);
+            }
+            tracing::warn!("插件 {} 进程退出: {}", self.plugin_name, reason);
+
+            // 稳定运行一段时间后重新计算退避
+            if started.elapsed() >= max_backoff {
+                attempts = 0;
+                backoff = initial_backoff;
+            }
+
+            child = loop {
+                if attempts >= self.options.max_restarts {
+                    tracing::error!(
+                        "插件 {} 连续重启 {} 次失败，停止重启",
+                        self.plugin_name,
+                        attempts
+                    );
+                    let mut health = self.health.lock();
+                    health.process = ProcessStatus::Failed;
+                    health.pid = None;
+                    health.last_exit = Some(reason);
+                    return;
+                }
+                attempts += 1;
+                {
+                    let mut health = self.health.lock();
+                    health.process = ProcessStatus::Restarting;
+                    health.pid = None;
+                    health.last_exit = Some(reason.clone());
+                }
+
+                tokio::select! {
+                    _ = tokio::time::sleep(backoff) => {}
+                    _ = self.cancel.cancelled() => {
+                        self.health.lock().process = ProcessStatus::Stopped;
+                        return;
+                    }
+                }
+                backoff = (backoff * 2).min(max_backoff);
+
+                self.health.lock().restart_count += 1;
+                match self.spawn().await {
+                    Ok(child) => {
+                        tracing::info!("插件 {} 进程已重启", self.plugin_name);
+                        break child;
+                    }
+                    Err(e) => reason = e.to_string(),
+                }
+            };
+        }
+    }
+
+    /// 发送 `shutdown` 并等待退出，超时后强制终止
+    async fn stop(&self, mut child: Child) {
+        let timeout = Duration::from_millis(self.call_timeout_ms());
+        if let Err(e) = self.call("shutdown", serde_json::Value::Null).await {
+            tracing::debug!("插件 {} 未响应 shutdown: {}", self.plugin_name, e);
+        }
+        if let Some(connection) = self.connection.lock().take() {
+            connection.fail_pending("插件已关闭");
+        }
+        if tokio::time::timeout(timeout, child.wait()).await.is_err() {
+            let _ = child.kill().await;
+        }
+        let mut health = self.health.lock();
+        health.process = ProcessStatus::Stopped;
+        health.pid = None;
+    }
+
+    fn execution_error(&self, method: &str, message: impl std::fmt::Display) -> PluginError {
+        PluginError::ExecutionError {
+            plugin_name: self.plugin_name.clone(),
+            message: format!("{}: {}", method, message),
+        }
+    }
+}
+
+async fn wait_kill(connection: Option<&RpcConnection>) {
+    match connection {
+        Some(connection) => connection.kill.notified().await,
+        None => std::future::pending().await,
+    }
+}
+
+fn describe_exit(status: std::io::Result<ExitStatus>) -> String {
+    match status {
+        Ok(status) => match status.code() {
+            Some(code) => format!("退出码 {}", code),
+            None => format!("被信号终止 ({})", status),
+        },
+        Err(e) => format!("等待进程失败: {}", e),
+    }
+}
+
+async fn read_stdout(
+    plugin_name: String,
+    connection: Arc<RpcConnection>,
+    stdout: impl AsyncRead + Unpin,
+) {
+    let mut lines = BufReader::new(stdout).lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        if !line.trim().is_empty() {
+            connection.dispatch(&plugin_name, &line);
+        }
+    }
+    connection.fail_pending("插件进程已退出");
+}
+
+async fn read_stderr(plugin_name: String, stderr: impl AsyncRead + Unpin) {
+    let mut lines = BufReader::new(stderr).lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        tracing::warn!("[插件 {} stderr] {}", plugin_name, line);
+    }
+}
+
+/// 以子进程运行的 Binary 插件
+pub struct RpcPlugin {
+    manifest: PluginManifest,
+    supervisor: Arc<RpcSupervisor>,
+    task: Option<JoinHandle<()>>,
+}
+
+impl RpcPlugin {
+    /// 启动插件进程，首次启动或 `init` 失败时返回错误
+    pub async fn start(
+        manifest: PluginManifest,
+        executable: PathBuf,
+        settings: serde_json::Value,
+        config: &PluginConfig,
+    ) -> Result<Self, PluginError> {
+        let supervisor = Arc::new(RpcSupervisor {
+            plugin_name: manifest.name.clone(),
+            plugin_version: manifest.version.clone(),
+            executable,
+            options: manifest.rpc.clone().unwrap_or_default(),
+            settings,
+            timeout_ms: AtomicU64::new(config.timeout_ms),
+            next_id: AtomicU64::new(1),
+            connection: Mutex::new(None),
+            health: Mutex::new(PluginHealth::default()),
+            cancel: CancellationToken::new(),
+        });
+        let child = supervisor.spawn().await?;
+        let task = tokio::spawn(supervisor.clone().supervise(child));
+        Ok(Self {
+            manifest,
+            supervisor,
+            task: Some(task),
+        })
+    }
+
+    fn has_hook(&self, hook: RpcHook) -> bool {
+        self.manifest.hooks.iter().any(|h| h == hook.method())
+    }
+
+    async fn call_hook(
+        &self,
+        hook: RpcHook,
+        ctx: &mut PluginContext,
+        params: serde_json::Value,
+    ) -> Result<Option<RpcHookOutput>, PluginError> {
+        let result = self.supervisor.call(hook.method(), params).await?;
+        if result.is_null() {
+            return Ok(None);
+        }
+        let output: RpcHookOutput = serde_json::from_value(result).map_err(|e| {
+            self.supervisor
+                .execution_error(hook.method(), format!("返回值格式无效: {}", e))
+        })?;
+        if let Some(model) = &output.model {
+            ctx.model = model.clone();
+        }
+        if let Some(metadata) = &output.metadata {
+            ctx.metadata = metadata.clone();
+        }
+        Ok(Some(output))
+    }
+
+    async fn run_payload_hook(
+        &self,
+        hook: RpcHook,
+        ctx: &mut PluginContext,
+        payload: &mut serde_json::Value,
+    ) -> Result<HookResult, PluginError> {
+        if !self.has_hook(hook) {
+            return Ok(HookResult::success(false, 0));
+        }
+        let start = Instant::now();
+        let params = serde_json::json!({ "ctx": ctx, "payload": payload });
+        let Some(output) = self.call_hook(hook, ctx, params).await? else {
+            return Ok(HookResult::success(
+                false,
+                start.elapsed().as_millis() as u64,
+            ));
+        };
+        let modified = match output.payload {
+            Some(updated) if updated != *payload => {
+                *payload = updated;
+                true
+            }
+            _ => false,
+        };
+        let mut result = HookResult::success(modified, start.elapsed().as_millis() as u64);
+        result.action = output.action;
+        Ok(result)
+    }
+}
+
+#[async_trait]
+impl Plugin for RpcPlugin {
+    fn name(&self) -> &str {
+        &self.manifest.name
+    }
+
+    fn version(&self) -> &str {
+        &self.manifest.version
+    }
+
+    fn manifest(&self) -> &PluginManifest {
+        &self.manifest
+    }
+
+    /// 进程已在加载时完成 `init` 握手，这里仅更新调用超时
+    async fn init(&mut self, config: &PluginConfig) -> Result<(), PluginError> {
+        self.supervisor
+            .timeout_ms
+            .store(config.timeout_ms, Ordering::Relaxed);
+        Ok(())
+    }
+
+    async fn on_request(
+        &self,
+        ctx: &mut PluginContext,
+        request: &mut serde_json::Value,
+    ) -> Result<HookResult, PluginError> {
+        self.run_payload_hook(RpcHook::Request, ctx, request).await
+    }
+
+    async fn on_response(
+        &self,
+        ctx: &mut PluginContext,
+        response: &mut serde_json::Value,
+    ) -> Result<HookResult, PluginError> {
+        self.run_payload_hook(RpcHook::Response, ctx, response)
+            .await
+    }
+
+    async fn on_error(
+        &self,
+        ctx: &mut PluginContext,
+        error: &str,
+    ) -> Result<HookResult, PluginError> {
+        if !self.has_hook(RpcHook::Error) {
+            return Ok(HookResult::success(false, 0));
+        }
+        let start = Instant::now();
+        let params = serde_json::json!({ "ctx": ctx, "error": error });
+        self.call_hook(RpcHook::Error, ctx, params).await?;
+        Ok(HookResult::success(
+            false,
+            start.elapsed().as_millis() as u64,
+        ))
+    }
+
+    async fn on_stream_start(&self, ctx: &mut PluginContext) -> Result<HookResult, PluginError> {
+        if !self.has_hook(RpcHook::StreamStart) {
+            return Ok(HookResult::success(false, 0));
+        }
+        let start = Instant::now();
+        let params = serde_json::json!({ "ctx": ctx });
+        self.call_hook(RpcHook::StreamStart, ctx, params).await?;
+        Ok(HookResult::success(
+            false,
+            start.elapsed().as_millis() as u64,
+        ))
+    }
+
+    async fn on_stream_event(
+        &self,
+        ctx: &mut PluginContext,
+        events: &mut Vec<StreamEvent>,
+    ) -> Result<HookResult, PluginError> {
+        let Some(event) = events.first() else {
+            return Ok(HookResult::success(false, 0));
+        };
+        if !self.has_hook(RpcHook::StreamEvent) {
+            return Ok(HookResult::success(false, 0));
+        }
+        let start = Instant::now();
+        let params = serde_json::json!({ "ctx": ctx, "event": event });
+        let output = self.call_hook(RpcHook::StreamEvent, ctx, params).await?;
+        let modified = replace_events(events, output);
+        Ok(HookResult::success(
+            modified,
+            start.elapsed().as_millis() as u64,
+        ))
+    }
+
+    async fn on_stream_end(
+        &self,
+        ctx: &mut PluginContext,
+        events: &mut Vec<StreamEvent>,
+    ) -> Result<HookResult, PluginError> {
+        if !self.has_hook(RpcHook::StreamEnd) {
+            return Ok(HookResult::success(false, 0));
+        }
+        let start = Instant::now();
+        let params = serde_json::json!({ "ctx": ctx, "events": events });
+        let output = self.call_hook(RpcHook::StreamEnd, ctx, params).await?;
+        let modified = replace_events(events, output);
+        Ok(HookResult::success(
+            modified,
+            start.elapsed().as_millis() as u64,
+        ))
+    }
+
+    async fn provider_refresh_token(
+        &self,
+        credential: &mut serde_json::Value,
+    ) -> Result<bool, PluginError> {
+        if self.manifest.provider.is_none() {
+            return Ok(false);
+        }
+        let method = "provider_refresh_token";
+        let params = serde_json::json!({ "credential": credential });
+        let result = self.supervisor.call(method, params).await?;
+        match result.get("credential") {
+            Some(updated) if updated != credential => {
+                *credential = updated.clone();
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    async fn provider_build_request(
+        &self,
+        credential: &serde_json::Value,
+        request: &serde_json::Value,
+    ) -> Result<UpstreamRequest, PluginError> {
+        let method = "provider_build_request";
+        if self.manifest.provider.is_none() {
+            return Err(self
+                .supervisor
+                .execution_error(method, "插件未声明 provider"));
+        }
+        let params = serde_json::json!({ "credential": credential, "request": request });
+        let result = self.supervisor.call(method, params).await?;
+        serde_json::from_value(result).map_err(|e| {
+            self.supervisor
+                .execution_error(method, format!("返回值格式无效: {}", e))
+        })
+    }
+
+    async fn provider_parse_response(
+        &self,
+        credential: &serde_json::Value,
+        response: UpstreamResponse,
+    ) -> Result<serde_json::Value, PluginError> {
+        if self.manifest.provider.is_none() {
+            return Ok(response.body);
+        }
+        let params = serde_json::json!({ "credential": credential, "response": response });
+        let result = self
+            .supervisor
+            .call("provider_parse_response", params)
+            .await?;
+        if result.is_null() {
+            return Ok(response.body);
+        }
+        Ok(result)
+    }
+
+    async fn shutdown(&mut self) -> Result<(), PluginError> {
+        self.supervisor.cancel.cancel();
+        if let Some(task) = self.task.take() {
+            task.await.map_err(|e| PluginError::ExecutionError {
+                plugin_name: self.manifest.name.clone(),
+                message: format!("监督任务异常退出: {}", e),
+            })?;
+        }
+        Ok(())
+    }
+
+    fn health(&self) -> Option<PluginHealth> {
+        Some(self.supervisor.health.lock().clone())
+    }
+}
+
+/// 用钩子返回的事件替换原事件，返回是否有变化
+fn replace_events(events: &mut Vec<StreamEvent>, output: Option<RpcHookOutput>) -> bool {
+    match output.and_then(|o| o.events) {
+        Some(updated) if updated != *events => {
+            *events = updated;
+            true
+        }
+        _ => false,
+    }
+}
+
+impl Drop for RpcPlugin {
+    fn drop(&mut self) {
+        // 监督任务收到信号后关闭进程；若运行时已关闭，kill_on_drop 保证进程被回收
+        self.supervisor.cancel.cancel();
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::ProviderType;
+    use std::os::unix::fs::PermissionsExt;
+
+    /// 生成按方法名应答的 shell 插件，`handlers` 为 case 分支
+    fn script_plugin(dir: &std::path::Path, handlers: &str) -> PathBuf {
+        let script = format!(
+            r#"#!/bin/sh
+while IFS= read -r line; do
+  id=$(printf '%s' "$line" | sed -n 's/.*"id":\([0-9]*\).*/\1/p')
+  method=$(printf '%s' "$line" | sed -n 's/.*"method":"\([a-z_]*\)".*/\1/p')
+  reply() {{ printf '{{"jsonrpc":"2.0","id":%s,"result":%s}}\n' "$id" "$1"; }}
+  case "$method" in
+{handlers}
+    init) reply null ;;
+    shutdown) reply null; exit 0 ;;
+    *) reply null ;;
+  esac
+done
+"#
+        );
+        let path = dir.join("plugin.sh");
+        std::fs::write(&path, script).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        path
+    }
+
+    fn manifest(rpc: RpcManifest) -> PluginManifest {
+        PluginManifest {
+            name: "rpc-test".to_string(),
+            version: "1.0.0".to_string(),
+            description: String::new(),
+            author: None,
+            homepage: None,
+            license: None,
+            entry: "plugin.sh".to_string(),
+            plugin_type: crate::plugin::PluginType::Binary,
+            config_schema: None,
+            hooks: vec![
+                "on_request".to_string(),
+                "on_error".to_string(),
+                "on_stream_event".to_string(),
+            ],
+            min_proxycast_version: None,
+            binary: None,
+            ui: None,
+            script_limits: None,
+            wasm: None,
+            rpc: Some(rpc),
+            provider: None,
+        }
+    }
+
+    fn fast_restart() -> RpcManifest {
+        RpcManifest {
+            max_restarts: 2,
+            initial_backoff_ms: 10,
+            max_backoff_ms: 1_000,
+            ..Default::default()
+        }
+    }
+
+    fn ctx() -> PluginContext {
+        PluginContext::new("req-1".to_string(), ProviderType::Kiro, "m1".to_string())
+    }
+
+    async fn wait_for(plugin: &RpcPlugin, check: impl Fn(&PluginHealth) -> bool) -> PluginHealth {
+        for _ in 0..300 {
+            let health = plugin.health().unwrap();
+            if check(&health) {
+                return health;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("插件未达到预期状态: {:?}", plugin.health());
+    }
+
+    #[tokio::test]
+    async fn test_request_hook_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let exe = script_plugin(
+            dir.path(),
+            r#"    on_request) reply '{"payload":{"rewritten":true},"model":"m2"}' ;;"#,
+        );
+        let mut plugin = RpcPlugin::start(
+            manifest(RpcManifest::default()),
+            exe,
+            serde_json::json!({}),
+            &PluginConfig::default(),
+        )
+        .await
+        .unwrap();
+
+        let mut ctx = ctx();
+        let mut payload = serde_json::json!({ "model": "m1" });
+        let result = plugin.on_request(&mut ctx, &mut payload).await.unwrap();
+        assert!(result.modified);
+        assert_eq!(payload, serde_json::json!({ "rewritten": true }));
+        assert_eq!(ctx.model, "m2");
+
+        // 未声明的钩子不会调用插件
+        let result = plugin.on_response(&mut ctx, &mut payload).await.unwrap();
+        assert!(!result.modified);
+
+        let health = plugin.health().unwrap();
+        assert_eq!(health.process, ProcessStatus::Running);
+        assert!(health.pid.is_some());
+
+        plugin.shutdown().await.unwrap();
+        assert_eq!(plugin.health().unwrap().process, ProcessStatus::Stopped);
+    }
+
+    #[tokio::test]
+    async fn test_provider_methods() {
+        let dir = tempfile::tempdir().unwrap();
+        let exe = script_plugin(
+            dir.path(),
+            r#"    provider_refresh_token) reply '{"credential":{"access_token":"new"}}' ;;
+    provider_build_request) reply '{"url":"http://127.0.0.1/v1/chat","headers":{"authorization":"Bearer new"}}' ;;"#,
+        );
+        let mut manifest = manifest(RpcManifest::default());
+        manifest.hooks.clear();
+        manifest.provider = Some(crate::plugin::ProviderManifest {
+            id: "my-llm".to_string(),
+            display_name: None,
+            credential_schema: None,
+            models: vec![],
+            request_timeout_ms: 1_000,
+        });
+        let plugin = RpcPlugin::start(
+            manifest,
+            exe,
+            serde_json::json!({}),
+            &PluginConfig::default(),
+        )
+        .await
+        .unwrap();
+
+        let mut credential = serde_json::json!({ "access_token": "old" });
+        assert!(plugin
+            .provider_refresh_token(&mut credential)
+            .await
+            .unwrap());
+        assert_eq!(credential["access_token"], "new");
+
+        let request = plugin
+            .provider_build_request(&credential, &serde_json::json!({ "model": "m1" }))
+            .await
+            .unwrap();
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.headers["authorization"], "Bearer new");
+
+        // 返回 null 时响应体原样作为规范响应
+        let body = serde_json::json!({ "choices": [] });
+        let parsed = plugin
+            .provider_parse_response(
+                &credential,
+                UpstreamResponse {
+                    status: 200,
+                    headers: HashMap::new(),
+                    body: body.clone(),
+                },
+            )
+            .await
+            .unwrap();
+        assert_eq!(parsed, body);
+    }
+
+    #[tokio::test]
+    async fn test_stream_event_hook_replaces_events() {
+        let dir = tempfile::tempdir().unwrap();
+        let exe = script_plugin(
+            dir.path(),
+            r#"    on_stream_event) reply '{"events":[{"TextDelta":{"text":"a"}},"Ping"]}' ;;"#,
+        );
+        let plugin = RpcPlugin::start(
+            manifest(RpcManifest::default()),
+            exe,
+            serde_json::json!({}),
+            &PluginConfig::default(),
+        )
+        .await
+        .unwrap();
+
+        let mut ctx = ctx();
+        let mut events = vec![StreamEvent::TextDelta {
+            text: "b".to_string(),
+        }];
+        let result = plugin.on_stream_event(&mut ctx, &mut events).await.unwrap();
+        assert!(result.modified);
+        assert_eq!(
+            events,
+            vec![
+                StreamEvent::TextDelta {
+                    text: "a".to_string()
+                },
+                StreamEvent::Ping
+            ]
+        );
+
+        // 未声明 on_stream_end 时原样保留
+        let mut events = vec![StreamEvent::Ping];
+        let result = plugin.on_stream_end(&mut ctx, &mut events).await.unwrap();
+        assert!(!result.modified);
+    }
+
+    #[tokio::test]
+    async fn test_error_response_and_timeout() {
+        let dir = tempfile::tempdir().unwrap();
+        let exe = script_plugin(
+            dir.path(),
+            r#"    on_request) sleep 1; reply null ;;
+    on_error) printf '{"jsonrpc":"2.0","id":%s,"error":{"code":-32000,"message":"boom"}}\n' "$id" ;;"#,
+        );
+        let config = PluginConfig::default().with_timeout(100);
+        let plugin = RpcPlugin::start(
+            manifest(RpcManifest::default()),
+            exe,
+            serde_json::json!({}),
+            &config,
+        )
+        .await
+        .unwrap();
+
+        let mut ctx = ctx();
+        let err = plugin.on_error(&mut ctx, "upstream").await.unwrap_err();
+        assert!(err.to_string().contains("boom"));
+
+        let mut payload = serde_json::json!({});
+        let err = plugin.on_request(&mut ctx, &mut payload).await.unwrap_err();
+        assert!(matches!(
+            err,
+            PluginError::Timeout {
+                timeout_ms: 100,
+                ..
+            }
+        ));
+        assert_eq!(plugin.health().unwrap().consecutive_timeouts, 1);
+    }
+
+    #[tokio::test]
+    async fn test_crash_restarts_with_backoff() {
+        let dir = tempfile::tempdir().unwrap();
+        let exe = script_plugin(dir.path(), r#"    on_request) exit 3 ;;"#);
+        let plugin = RpcPlugin::start(
+            manifest(fast_restart()),
+            exe,
+            serde_json::json!({}),
+            &PluginConfig::default(),
+        )
+        .await
+        .unwrap();
+        let first_pid = plugin.health().unwrap().pid;
+
+        let mut ctx = ctx();
+        let mut payload = serde_json::json!({});
+        assert!(plugin.on_request(&mut ctx, &mut payload).await.is_err());
+
+        let health = wait_for(&plugin, |h| {
+            h.restart_count == 1 && h.process == ProcessStatus::Running
+        })
+        .await;
+        assert_eq!(health.restart_count, 1);
+        assert_eq!(health.last_exit.as_deref(), Some("退出码 3"));
+        assert_ne!(health.pid, first_pid);
+
+        // 重启后的进程可以继续处理调用
+        assert!(plugin.on_error(&mut ctx, "x").await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_gives_up_after_max_restarts() {
+        let dir = tempfile::tempdir().unwrap();
+        let exe = script_plugin(dir.path(), r#"    init) reply null; exit 1 ;;"#);
+        let plugin = RpcPlugin::start(
+            manifest(fast_restart()),
+            exe,
+            serde_json::json!({}),
+            &PluginConfig::default(),
+        )
+        .await
+        .unwrap();
+
+        let health = wait_for(&plugin, |h| h.process == ProcessStatus::Failed).await;
+        assert_eq!(health.restart_count, 2);
+        assert!(health.pid.is_none());
+
+        let mut ctx = ctx();
+        let mut payload = serde_json::json!({});
+        assert!(plugin.on_request(&mut ctx, &mut payload).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_start_fails_when_executable_missing() {
+        let dir = tempfile::tempdir().unwrap();
+        let result = RpcPlugin::start(
+            manifest(RpcManifest::default()),
+            dir.path().join("missing"),
+            serde_json::json!({}),
+            &PluginConfig::default(),
+        )
+        .await;
+        assert!(matches!(result, Err(PluginError::LoadError(_))));
+    }
+}
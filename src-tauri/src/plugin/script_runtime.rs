@@ -0,0 +1,801 @@
+//! 脚本插件运行时
+//!
+//! 基于 Rhai 的嵌入式沙箱脚本引擎。入口为 `.rhai` 文件的 Script 插件可实现以下钩子：
+//!
+//! ```rhai
+//! fn on_request(ctx) { this.model = "gpt-4o"; }      // this 为请求 JSON
+//! fn on_response(ctx) { this.proxied = true; }       // this 为响应 JSON
+//! fn on_error(ctx, error) { log_warn(error); }
+//! ```
+//!
+//! - `ctx` 可读写 PluginContext（model、provider、metadata），并可读取插件配置 `ctx.settings`
+//! - 每次调用受操作数、CPU 时间和字符串/数组/对象大小限制，禁止 `eval` 和 `import`
+//! - 标准库：regex、json、hash、log、kv
+
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use base64::Engine as _;
+use parking_lot::Mutex;
+use regex::{Regex, RegexBuilder};
+use rhai::module_resolvers::DummyModuleResolver;
+use rhai::serde::{from_dynamic, to_dynamic};
+use rhai::{Array, CallFnOptions, Dynamic, Engine, EvalAltResult, Position, Scope, AST};
+use sha2::{Digest, Sha256};
+
+use super::kv_store::PluginKvStore;
+use super::types::{
+    HookResult, Plugin, PluginConfig, PluginContext, PluginError, PluginManifest, ScriptLimits,
+};
+
+/// on_progress 回调检查 CPU 时间的间隔 (操作数)
+const DEADLINE_CHECK_INTERVAL: u64 = 256;
+/// 正则缓存最大条目数
+const MAX_CACHED_REGEX: usize = 64;
+/// 单个正则编译后的大小上限 (字节)
+const REGEX_SIZE_LIMIT: usize = 1 << 20;
+
+thread_local! {
+    /// 当前线程上正在执行的脚本调用的截止时间
+    static DEADLINE: Cell<Option<Instant>> = const { Cell::new(None) };
+}
+
+/// 脚本钩子
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScriptHook {
+    Request,
+    Response,
+    Error,
+}
+
+impl ScriptHook {
+    /// 脚本中的函数名
+    pub fn fn_name(&self) -> &'static str {
+        match self {
+            ScriptHook::Request => "on_request",
+            ScriptHook::Response => "on_response",
+            ScriptHook::Error => "on_error",
+        }
+    }
+
+    /// 脚本函数的参数个数
+    fn arity(&self) -> usize {
+        match self {
+            ScriptHook::Request | ScriptHook::Response => 1,
+            ScriptHook::Error => 2,
+        }
+    }
+}
+
+/// 脚本中的 `ctx` 对象
+///
+/// 以共享句柄形式传入脚本，脚本对它的修改在调用结束后写回 PluginContext
+#[derive(Clone)]
+struct ScriptContext {
+    inner: Arc<Mutex<PluginContext>>,
+    settings: Arc<serde_json::Value>,
+}
+
+impl ScriptContext {
+    fn request_id(&mut self) -> String {
+        self.inner.lock().request_id.clone()
+    }
+
+    fn model(&mut self) -> String {
+        self.inner.lock().model.clone()
+    }
+
+    fn set_model(&mut self, model: String) {
+        self.inner.lock().model = model;
+    }
+
+    fn provider(&mut self) -> String {
+        self.inner.lock().provider.to_string()
+    }
+
+    fn set_provider(&mut self, provider: String) -> Result<(), Box<EvalAltResult>> {
+        let provider = provider.parse().map_err(|e: String| script_error(e))?;
+        self.inner.lock().provider = provider;
+        Ok(())
+    }
+
+    fn timestamp(&mut self) -> String {
+        self.inner.lock().timestamp.to_rfc3339()
+    }
+
+    fn metadata(&mut self) -> Result<Dynamic, Box<EvalAltResult>> {
+        to_dynamic(&self.inner.lock().metadata)
+    }
+
+    fn settings(&mut self) -> Result<Dynamic, Box<EvalAltResult>> {
+        to_dynamic(self.settings.as_ref())
+    }
+
+    fn get_metadata(&mut self, key: &str) -> Result<Dynamic, Box<EvalAltResult>> {
+        match self.inner.lock().metadata.get(key) {
+            Some(value) => to_dynamic(value),
+            None => Ok(Dynamic::UNIT),
+        }
+    }
+
+    fn set_metadata(&mut self, key: &str, value: Dynamic) -> Result<(), Box<EvalAltResult>> {
+        let value: serde_json::Value = from_dynamic(&value)?;
+        self.inner.lock().set_metadata(key, value);
+        Ok(())
+    }
+
+    fn remove_metadata(&mut self, key: &str) -> bool {
+        self.inner.lock().metadata.remove(key).is_some()
+    }
+}
+
+/// 已编译的脚本及其沙箱引擎
+pub struct ScriptRuntime {
+    plugin_name: String,
+    engine: Engine,
+    ast: AST,
+    hooks: Vec<ScriptHook>,
+    cpu_time: Duration,
+    settings: Arc<serde_json::Value>,
+    kv: Arc<PluginKvStore>,
+}
+
+impl ScriptRuntime {
+    /// 编译脚本
+    ///
+    /// `cpu_time_ms` 为 `limits.cpu_time_ms` 未设置时使用的 CPU 时间上限，
+    /// `kv_path` 为空时 KV 存储仅保存在内存中
+    pub fn compile(
+        plugin_name: &str,
+        source: &str,
+        limits: &ScriptLimits,
+        cpu_time_ms: u64,
+        settings: serde_json::Value,
+        kv_path: Option<PathBuf>,
+    ) -> Result<Self, PluginError> {
+        let kv = Arc::new(PluginKvStore::open(kv_path, limits.max_kv_entries));
+        let engine = build_engine(plugin_name, limits, kv.clone());
+        let ast = engine
+            .compile(source)
+            .map_err(|e| PluginError::LoadError(format!("脚本编译失败: {}", e)))?;
+
+        let mut hooks = Vec::new();
+        for hook in [ScriptHook::Request, ScriptHook::Response, ScriptHook::Error] {
+            let Some(func) = ast.iter_functions().find(|f| f.name == hook.fn_name()) else {
+                continue;
+            };
+            if func.params.len() != hook.arity() {
+                return Err(PluginError::LoadError(format!(
+                    "脚本函数 {} 应有 {} 个参数，实际为 {}",
+                    hook.fn_name(),
+                    hook.arity(),
+                    func.params.len()
+                )));
+            }
+            hooks.push(hook);
+        }
+
+        Ok(Self {
+            plugin_name: plugin_name.to_string(),
+            engine,
+            ast,
+            hooks,
+            cpu_time: Duration::from_millis(limits.cpu_time_ms.unwrap_or(cpu_time_ms)),
+            settings: Arc::new(settings),
+            kv,
+        })
+    }
+
+    /// 脚本是否实现了指定钩子
+    pub fn has_hook(&self, hook: ScriptHook) -> bool {
+        self.hooks.contains(&hook)
+    }
+
+    /// 执行 on_request / on_response，`payload` 作为 `this` 传入
+    ///
+    /// 返回 payload 是否被修改
+    pub fn call_payload_hook(
+        &self,
+        hook: ScriptHook,
+        ctx: &mut PluginContext,
+        payload: &mut serde_json::Value,
+    ) -> Result<bool, PluginError> {
+        let mut this = to_dynamic(&*payload).map_err(|e| self.execution_error(hook, &e))?;
+        let script_ctx = self.script_context(ctx);
+        self.call(hook, Some(&mut this), (script_ctx.clone(),))?;
+
+        let updated: serde_json::Value =
+            from_dynamic(&this).map_err(|e| self.execution_error(hook, &e))?;
+        *ctx = script_ctx.inner.lock().clone();
+        let modified = updated != *payload;
+        *payload = updated;
+        Ok(modified)
+    }
+
+    /// 执行 on_error
+    pub fn call_error_hook(&self, ctx: &mut PluginContext, error: &str) -> Result<(), PluginError> {
+        let script_ctx = self.script_context(ctx);
+        self.call(
+            ScriptHook::Error,
+            None,
+            (script_ctx.clone(), error.to_string()),
+        )?;
+        *ctx = script_ctx.inner.lock().clone();
+        Ok(())
+    }
+
+    fn script_context(&self, ctx: &PluginContext) -> ScriptContext {
+        ScriptContext {
+            inner: Arc::new(Mutex::new(ctx.clone())),
+            settings: self.settings.clone(),
+        }
+    }
+
+    fn call(
+        &self,
+        hook: ScriptHook,
+        this: Option<&mut Dynamic>,
+        args: impl rhai::FuncArgs,
+    ) -> Result<(), PluginError> {
+        let mut options = CallFnOptions::new().eval_ast(false);
+        if let Some(this) = this {
+            options = options.bind_this_ptr(this);
+        }
+
+        let _deadline = DeadlineGuard::set(Instant::now() + self.cpu_time);
+        let result = self.engine.call_fn_with_options::<Dynamic>(
+            options,
+            &mut Scope::new(),
+            &self.ast,
+            hook.fn_name(),
+            args,
+        );
+
+        if let Err(e) = self.kv.flush() {
+            tracing::warn!("插件 {} KV 存储写入失败: {}", self.plugin_name, e);
+        }
+
+        match result {
+            Ok(_) => Ok(()),
+            Err(e) if matches!(*e, EvalAltResult::ErrorTerminated(..)) => {
+                Err(PluginError::Timeout {
+                    plugin_name: self.plugin_name.clone(),
+                    timeout_ms: self.cpu_time.as_millis() as u64,
+                })
+            }
+            Err(e) => Err(self.execution_error(hook, &e)),
+        }
+    }
+
+    fn execution_error(&self, hook: ScriptHook, error: &EvalAltResult) -> PluginError {
+        PluginError::ExecutionError {
+            plugin_name: self.plugin_name.clone(),
+            message: format!("{}: {}", hook.fn_name(), error),
+        }
+    }
+}
+
+/// 为当前线程设置调用截止时间，离开作用域时清除
+struct DeadlineGuard;
+
+impl DeadlineGuard {
+    fn set(deadline: Instant) -> Self {
+        DEADLINE.with(|d| d.set(Some(deadline)));
+        Self
+    }
+}
+
+impl Drop for DeadlineGuard {
+    fn drop(&mut self) {
+        DEADLINE.with(|d| d.set(None));
+    }
+}
+
+fn script_error(message: impl Into<String>) -> Box<EvalAltResult> {
+    EvalAltResult::ErrorRuntime(Dynamic::from(message.into()), Position::NONE).into()
+}
+
+/// 创建带资源限制和标准库的沙箱引擎
+fn build_engine(plugin_name: &str, limits: &ScriptLimits, kv: Arc<PluginKvStore>) -> Engine {
+    let mut engine = Engine::new();
+
+    // 资源限制
+    engine
+        .set_max_operations(limits.max_operations)
+        .set_max_string_size(limits.max_string_size)
+        .set_max_array_size(limits.max_array_size)
+        .set_max_map_size(limits.max_map_size)
+        .set_max_call_levels(limits.max_call_levels)
+        .on_progress(|operations| {
+            if operations % DEADLINE_CHECK_INTERVAL != 0 {
+                return None;
+            }
+            DEADLINE.with(|d| match d.get() {
+                Some(deadline) if Instant::now() >= deadline => Some(Dynamic::from("timeout")),
+                _ => None,
+            })
+        });
+
+    // 沙箱：禁止动态求值和加载外部模块
+    engine
+        .disable_symbol("eval")
+        .set_module_resolver(DummyModuleResolver::new());
+
+    // ctx 对象
+    engine
+        .register_type_with_name::<ScriptContext>("PluginContext")
+        .register_get("request_id", ScriptContext::request_id)
+        .register_get_set("model", ScriptContext::model, ScriptContext::set_model)
+        .register_get_set(
+            "provider",
+            ScriptContext::provider,
+            ScriptContext::set_provider,
+        )
+        .register_get("timestamp", ScriptContext::timestamp)
+        .register_get("metadata", ScriptContext::metadata)
+        .register_get("settings", ScriptContext::settings)
+        .register_fn("get_metadata", ScriptContext::get_metadata)
+        .register_fn("set_metadata", ScriptContext::set_metadata)
+        .register_fn("remove_metadata", ScriptContext::remove_metadata);
+
+    register_log_functions(&mut engine, plugin_name);
+    register_regex_functions(&mut engine);
+    register_json_functions(&mut engine);
+    register_hash_functions(&mut engine);
+    register_kv_functions(&mut engine, kv);
+
+    engine
+}
+
+fn register_log_functions(engine: &mut Engine, plugin_name: &str) {
+    let name = plugin_name.to_string();
+    engine.on_print(move |s| tracing::info!("[插件 {}] {}", name, s));
+    let name = plugin_name.to_string();
+    engine.on_debug(move |s, _, pos| tracing::debug!("[插件 {}] {} ({})", name, s, pos));
+
+    let name = plugin_name.to_string();
+    engine.register_fn("log_debug", move |msg: &str| {
+        tracing::debug!("[插件 {}] {}", name, msg)
+    });
+    let name = plugin_name.to_string();
+    engine.register_fn("log_info", move |msg: &str| {
+        tracing::info!("[插件 {}] {}", name, msg)
+    });
+    let name = plugin_name.to_string();
+    engine.register_fn("log_warn", move |msg: &str| {
+        tracing::warn!("[插件 {}] {}", name, msg)
+    });
+    let name = plugin_name.to_string();
+    engine.register_fn("log_error", move |msg: &str| {
+        tracing::error!("[插件 {}] {}", name, msg)
+    });
+}
+
+fn register_regex_functions(engine: &mut Engine) {
+    let cache: Arc<Mutex<HashMap<String, Regex>>> = Arc::new(Mutex::new(HashMap::new()));
+    let compile = move |pattern: &str| -> Result<Regex, Box<EvalAltResult>> {
+        let mut cache = cache.lock();
+        if let Some(re) = cache.get(pattern) {
+            return Ok(re.clone());
+        }
+        let re = RegexBuilder::new(pattern)
+            .size_limit(REGEX_SIZE_LIMIT)
+            .build()
+            .map_err(|e| script_error(format!("无效的正则表达式: {}", e)))?;
+        if cache.len() >= MAX_CACHED_REGEX {
+            cache.clear();
+        }
+        cache.insert(pattern.to_string(), re.clone());
+        Ok(re)
+    };
+
+    let re = compile.clone();
+    engine.register_fn("regex_match", move |text: &str, pattern: &str| {
+        re(pattern).map(|re| re.is_match(text))
+    });
+    let re = compile.clone();
+    engine.register_fn(
+        "regex_replace",
+        move |text: &str, pattern: &str, replacement: &str| {
+            re(pattern).map(|re| re.replace_all(text, replacement).into_owned())
+        },
+    );
+    let re = compile.clone();
+    engine.register_fn("regex_find_all", move |text: &str, pattern: &str| {
+        re(pattern).map(|re| {
+            re.find_iter(text)
+                .map(|m| Dynamic::from(m.as_str().to_string()))
+                .collect::<Array>()
+        })
+    });
+    let re = compile;
+    engine.register_fn("regex_captures", move |text: &str, pattern: &str| {
+        re(pattern).map(|re| match re.captures(text) {
+            Some(caps) => caps
+                .iter()
+                .map(|m| match m {
+                    Some(m) => Dynamic::from(m.as_str().to_string()),
+                    None => Dynamic::UNIT,
+                })
+                .collect::<Array>()
+                .into(),
+            None => Dynamic::UNIT,
+        })
+    });
+}
+
+fn register_json_functions(engine: &mut Engine) {
+    engine.register_fn(
+        "json_encode",
+        |value: Dynamic| -> Result<String, Box<EvalAltResult>> {
+            let value: serde_json::Value = from_dynamic(&value)?;
+            serde_json::to_string(&value).map_err(|e| script_error(e.to_string()))
+        },
+    );
+    engine.register_fn(
+        "json_decode",
+        |text: &str| -> Result<Dynamic, Box<EvalAltResult>> {
+            let value: serde_json::Value =
+                serde_json::from_str(text).map_err(|e| script_error(e.to_string()))?;
+            to_dynamic(&value)
+        },
+    );
+}
+
+fn register_hash_functions(engine: &mut Engine) {
+    engine.register_fn("sha256", |text: &str| {
+        format!("{:x}", Sha256::digest(text.as_bytes()))
+    });
+    engine.register_fn("md5", |text: &str| format!("{:x}", md5::compute(text)));
+    engine.register_fn("base64_encode", |text: &str| {
+        base64::engine::general_purpose::STANDARD.encode(text)
+    });
+    engine.register_fn(
+        "base64_decode",
+        |text: &str| -> Result<String, Box<EvalAltResult>> {
+            let bytes = base64::engine::general_purpose::STANDARD
+                .decode(text)
+                .map_err(|e| script_error(e.to_string()))?;
+            String::from_utf8(bytes).map_err(|e| script_error(e.to_string()))
+        },
+    );
+}
+
+fn register_kv_functions(engine: &mut Engine, kv: Arc<PluginKvStore>) {
+    let store = kv.clone();
+    engine.register_fn(
+        "kv_get",
+        move |key: &str| -> Result<Dynamic, Box<EvalAltResult>> {
+            match store.get(key) {
+                Some(value) => to_dynamic(&value),
+                None => Ok(Dynamic::UNIT),
+            }
+        },
+    );
+    let store = kv.clone();
+    engine.register_fn(
+        "kv_set",
+        move |key: &str, value: Dynamic| -> Result<(), Box<EvalAltResult>> {
+            let value: serde_json::Value = from_dynamic(&value)?;
+            store
+                .set(key.to_string(), value)
+                .map_err(|e| script_error(e.to_string()))
+        },
+    );
+    let store = kv.clone();
+    engine.register_fn("kv_delete", move |key: &str| store.delete(key));
+    engine.register_fn("kv_keys", move || {
+        kv.keys().into_iter().map(Dynamic::from).collect::<Array>()
+    });
+}
+
+/// 由 Rhai 脚本驱动的 Script 插件
+pub struct EmbeddedScriptPlugin {
+    manifest: PluginManifest,
+    runtime: Arc<ScriptRuntime>,
+}
+
+impl EmbeddedScriptPlugin {
+    /// 编译插件入口脚本
+    ///
+    /// KV 存储保存在 `kv_path`，为空时仅保存在内存中
+    pub fn new(
+        manifest: PluginManifest,
+        source: &str,
+        settings: serde_json::Value,
+        config: &PluginConfig,
+        kv_path: Option<PathBuf>,
+    ) -> Result<Self, PluginError> {
+        let limits = manifest.script_limits.unwrap_or_default();
+        let runtime = ScriptRuntime::compile(
+            &manifest.name,
+            source,
+            &limits,
+            config.timeout_ms,
+            settings,
+            kv_path,
+        )?;
+        Ok(Self {
+            manifest,
+            runtime: Arc::new(runtime),
+        })
+    }
+
+    /// 在阻塞线程池中执行 on_request / on_response，避免脚本占用异步运行时
+    async fn run_payload_hook(
+        &self,
+        hook: ScriptHook,
+        ctx: &mut PluginContext,
+        payload: &mut serde_json::Value,
+    ) -> Result<HookResult, PluginError> {
+        let start = Instant::now();
+        if !self.runtime.has_hook(hook) {
+            return Ok(HookResult::success(false, 0));
+        }
+
+        let runtime = self.runtime.clone();
+        let mut task_ctx = ctx.clone();
+        let mut task_payload = payload.clone();
+        let (task_ctx, task_payload, modified) = tokio::task::spawn_blocking(move || {
+            let modified = runtime.call_payload_hook(hook, &mut task_ctx, &mut task_payload)?;
+            Ok::<_, PluginError>((task_ctx, task_payload, modified))
+        })
+        .await
+        .map_err(|e| self.join_error(e))??;
+
+        *ctx = task_ctx;
+        *payload = task_payload;
+        Ok(HookResult::success(
+            modified,
+            start.elapsed().as_millis() as u64,
+        ))
+    }
+
+    fn join_error(&self, error: tokio::task::JoinError) -> PluginError {
+        PluginError::ExecutionError {
+            plugin_name: self.manifest.name.clone(),
+            message: format!("脚本任务异常退出: {}", error),
+        }
+    }
+}
+
+#[async_trait]
+impl Plugin for EmbeddedScriptPlugin {
+    fn name(&self) -> &str {
+        &self.manifest.name
+    }
+
+    fn version(&self) -> &str {
+        &self.manifest.version
+    }
+
+    fn manifest(&self) -> &PluginManifest {
+        &self.manifest
+    }
+
+    async fn init(&mut self, _config: &PluginConfig) -> Result<(), PluginError> {
+        Ok(())
+    }
+
+    async fn on_request(
+        &self,
+        ctx: &mut PluginContext,
+        request: &mut serde_json::Value,
+    ) -> Result<HookResult, PluginError> {
+        self.run_payload_hook(ScriptHook::Request, ctx, request)
+            .await
+    }
+
+    async fn on_response(
+        &self,
+        ctx: &mut PluginContext,
+        response: &mut serde_json::Value,
+    ) -> Result<HookResult, PluginError> {
+        self.run_payload_hook(ScriptHook::Response, ctx, response)
+            .await
+    }
+
+    async fn on_error(
+        &self,
+        ctx: &mut PluginContext,
+        error: &str,
+    ) -> Result<HookResult, PluginError> {
+        let start = Instant::now();
+        if !self.runtime.has_hook(ScriptHook::Error) {
+            return Ok(HookResult::success(false, 0));
+        }
+
+        let runtime = self.runtime.clone();
+        let mut task_ctx = ctx.clone();
+        let error = error.to_string();
+        let task_ctx = tokio::task::spawn_blocking(move || {
+            runtime.call_error_hook(&mut task_ctx, &error)?;
+            Ok::<_, PluginError>(task_ctx)
+        })
+        .await
+        .map_err(|e| self.join_error(e))??;
+
+        *ctx = task_ctx;
+        Ok(HookResult::success(
+            false,
+            start.elapsed().as_millis() as u64,
+        ))
+    }
+
+    async fn shutdown(&mut self) -> Result<(), PluginError> {
+        self.runtime.kv.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ProviderType;
+
+    fn compile(source: &str, limits: ScriptLimits) -> ScriptRuntime {
+        ScriptRuntime::compile(
+            "test-script",
+            source,
+            &limits,
+            1000,
+            serde_json::json!({"default_model": "gpt-4o"}),
+            None,
+        )
+        .unwrap()
+    }
+
+    fn test_ctx() -> PluginContext {
+        PluginContext::new(
+            "req-1".to_string(),
+            ProviderType::OpenAI,
+            "gpt-3.5".to_string(),
+        )
+    }
+
+    #[test]
+    fn test_request_hook_rewrites_payload_and_context() {
+        let runtime = compile(
+            r#"
+            fn on_request(ctx) {
+                this.model = ctx.settings.default_model;
+                this.messages[0].content = this.messages[0].content.regex_replace("secret-\\d+", "***");
+                ctx.model = this.model;
+                ctx.set_metadata("hash", sha256("abc"));
+            }
+            "#,
+            ScriptLimits::default(),
+        );
+        assert!(runtime.has_hook(ScriptHook::Request));
+        assert!(!runtime.has_hook(ScriptHook::Error));
+
+        let mut ctx = test_ctx();
+        let mut request = serde_json::json!({
+            "model": "gpt-3.5",
+            "messages": [{"role": "user", "content": "token secret-42 here"}]
+        });
+        let modified = runtime
+            .call_payload_hook(ScriptHook::Request, &mut ctx, &mut request)
+            .unwrap();
+
+        assert!(modified);
+        assert_eq!(request["model"], "gpt-4o");
+        assert_eq!(request["messages"][0]["content"], "token *** here");
+        assert_eq!(ctx.model, "gpt-4o");
+        assert_eq!(
+            ctx.get_metadata("hash").unwrap(),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    #[test]
+    fn test_kv_and_json_helpers() {
+        let runtime = compile(
+            r#"
+            fn on_response(ctx) {
+                let count = kv_get("count") ?? 0;
+                kv_set("count", count + 1);
+                this.count = kv_get("count");
+                this.decoded = json_decode(json_encode(#{ a: [1, 2] })).a.len();
+            }
+            "#,
+            ScriptLimits::default(),
+        );
+
+        let mut ctx = test_ctx();
+        let mut response = serde_json::json!({});
+        for _ in 0..2 {
+            runtime
+                .call_payload_hook(ScriptHook::Response, &mut ctx, &mut response)
+                .unwrap();
+        }
+        assert_eq!(response["count"], 2);
+        assert_eq!(response["decoded"], 2);
+    }
+
+    #[test]
+    fn test_limits_terminate_runaway_scripts() {
+        let source = "fn on_request(ctx) { loop { } }";
+
+        let runtime = compile(source, ScriptLimits::default());
+        let err = runtime
+            .call_payload_hook(
+                ScriptHook::Request,
+                &mut test_ctx(),
+                &mut serde_json::json!({}),
+            )
+            .unwrap_err();
+        assert!(matches!(err, PluginError::ExecutionError { .. }));
+
+        let runtime = compile(
+            source,
+            ScriptLimits {
+                max_operations: 0,
+                cpu_time_ms: Some(50),
+                ..Default::default()
+            },
+        );
+        let err = runtime
+            .call_payload_hook(
+                ScriptHook::Request,
+                &mut test_ctx(),
+                &mut serde_json::json!({}),
+            )
+            .unwrap_err();
+        assert!(matches!(err, PluginError::Timeout { timeout_ms: 50, .. }));
+    }
+
+    #[test]
+    fn test_sandbox_rejects_eval_and_bad_hooks() {
+        let err = ScriptRuntime::compile(
+            "test-script",
+            r#"fn on_request(ctx) { eval("1 + 1"); }"#,
+            &ScriptLimits::default(),
+            1000,
+            serde_json::Value::Null,
+            None,
+        );
+        assert!(matches!(err, Err(PluginError::LoadError(_))));
+
+        let err = ScriptRuntime::compile(
+            "test-script",
+            "fn on_error(ctx) { }",
+            &ScriptLimits::default(),
+            1000,
+            serde_json::Value::Null,
+            None,
+        );
+        assert!(matches!(err, Err(PluginError::LoadError(_))));
+    }
+
+    #[tokio::test]
+    async fn test_embedded_plugin_error_hook() {
+        let manifest: PluginManifest = serde_json::from_value(serde_json::json!({
+            "name": "error-recorder",
+            "version": "0.1.0",
+            "entry": "main.rhai",
+            "plugin_type": "script"
+        }))
+        .unwrap();
+        let plugin = EmbeddedScriptPlugin::new(
+            manifest,
+            r#"fn on_error(ctx, error) { ctx.set_metadata("last_error", error); }"#,
+            serde_json::Value::Null,
+            &PluginConfig::default(),
+            None,
+        )
+        .unwrap();
+
+        let mut ctx = test_ctx();
+        let result = plugin.on_error(&mut ctx, "upstream 500").await.unwrap();
+        assert!(result.success);
+        assert_eq!(ctx.get_metadata("last_error").unwrap(), "upstream 500");
+
+        let mut request = serde_json::json!({"model": "x"});
+        let result = plugin.on_request(&mut ctx, &mut request).await.unwrap();
+        assert!(!result.modified);
+    }
+}
@@ -0,0 +1,283 @@
+//! 插件短路响应渲染
+//!
+//! 将插件返回的 [`SyntheticResponse`] / [`PluginRejection`] 渲染为客户端请求的协议格式。
+//! 流式请求的合成响应复用 `stream` 模块的 SSE 生成器，保证与上游流式响应的事件序列一致。
+
+use chrono::Utc;
+
+use super::types::{PluginRejection, SyntheticResponse};
+use crate::stream::{
+    AnthropicSseGenerator, ContentBlockType, OpenAiSseGenerator, StopReason, StreamEvent,
+};
+
+/// 客户端期望的响应格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResponseFormat {
+    /// OpenAI Chat Completions
+    OpenAi,
+    /// Anthropic Messages
+    Anthropic,
+    /// Gemini generateContent
+    Gemini,
+}
+
+impl SyntheticResponse {
+    fn model_or<'a>(&'a self, request_model: &'a str) -> &'a str {
+        self.model.as_deref().unwrap_or(request_model)
+    }
+
+    fn stop(&self) -> StopReason {
+        self.stop_reason
+            .as_deref()
+            .map(StopReason::from_str)
+            .unwrap_or_default()
+    }
+
+    /// 转换为统一流事件序列
+    pub fn to_stream_events(&self, id: &str, request_model: &str) -> Vec<StreamEvent> {
+        vec![
+            StreamEvent::MessageStart {
+                id: id.to_string(),
+                model: self.model_or(request_model).to_string(),
+            },
+            StreamEvent::ContentBlockStart {
+                index: 0,
+                block_type: ContentBlockType::Text,
+            },
+            StreamEvent::TextDelta {
+                text: self.content.clone(),
+            },
+            StreamEvent::ContentBlockStop { index: 0 },
+            StreamEvent::Usage {
+                input_tokens: self.input_tokens,
+                output_tokens: self.output_tokens,
+                cache_read_input_tokens: None,
+                cache_creation_input_tokens: None,
+            },
+            StreamEvent::MessageStop {
+                stop_reason: self.stop(),
+            },
+        ]
+    }
+
+    /// 渲染为非流式响应体
+    pub fn to_json(&self, format: ResponseFormat, request_model: &str) -> serde_json::Value {
+        let model = self.model_or(request_model);
+        let stop = self.stop();
+        match format {
+            ResponseFormat::OpenAi => serde_json::json!({
+                "id": format!("chatcmpl-{}", uuid::Uuid::new_v4()),
+                "object": "chat.completion",
+                "created": Utc::now().timestamp(),
+                "model": model,
+                "choices": [{
+                    "index": 0,
+                    "message": { "role": "assistant", "content": self.content },
+                    "finish_reason": stop.to_openai_str(),
+                }],
+                "usage": {
+                    "prompt_tokens": self.input_tokens,
+                    "completion_tokens": self.output_tokens,
+                    "total_tokens": self.input_tokens + self.output_tokens,
+                },
+            }),
+            ResponseFormat::Anthropic => serde_json::json!({
+                "id": format!("msg_{}", uuid::Uuid::new_v4()),
+                "type": "message",
+                "role": "assistant",
+                "content": [{ "type": "text", "text": self.content }],
+                "model": model,
+                "stop_reason": stop.to_anthropic_str(),
+                "stop_sequence": null,
+                "usage": {
+                    "input_tokens": self.input_tokens,
+                    "output_tokens": self.output_tokens,
+                },
+            }),
+            ResponseFormat::Gemini => serde_json::json!({
+                "candidates": [{
+                    "content": { "role": "model", "parts": [{ "text": self.content }] },
+                    "finishReason": gemini_finish_reason(&stop),
+                    "index": 0,
+                }],
+                "usageMetadata": {
+                    "promptTokenCount": self.input_tokens,
+                    "candidatesTokenCount": self.output_tokens,
+                    "totalTokenCount": self.input_tokens + self.output_tokens,
+                },
+                "modelVersion": model,
+            }),
+        }
+    }
+
+    /// 渲染为 SSE 事件序列
+    pub fn to_sse(&self, format: ResponseFormat, request_model: &str) -> Vec<String> {
+        let model = self.model_or(request_model).to_string();
+        match format {
+            ResponseFormat::OpenAi => {
+                let id = format!("chatcmpl-{}", uuid::Uuid::new_v4());
+                let mut generator = OpenAiSseGenerator::with_id(id.clone(), model.clone());
+                self.to_stream_events(&id, &model)
+                    .iter()
+                    .filter_map(|event| generator.generate(event))
+                    .collect()
+            }
+            ResponseFormat::Anthropic => {
+                let id = format!("msg_{}", uuid::Uuid::new_v4());
+                let mut generator = AnthropicSseGenerator::with_id(id.clone(), model.clone());
+                self.to_stream_events(&id, &model)
+                    .iter()
+                    .flat_map(|event| generator.generate(event))
+                    .collect()
+            }
+            // Gemini 流式响应 (alt=sse) 的每个分块都是完整的 GenerateContentResponse
+            ResponseFormat::Gemini => {
+                vec![format!("data: {}\n\n", self.to_json(format, request_model))]
+            }
+        }
+    }
+}
+
+fn gemini_finish_reason(stop: &StopReason) -> &'static str {
+    match stop {
+        StopReason::MaxTokens => "MAX_TOKENS",
+        _ => "STOP",
+    }
+}
+
+impl PluginRejection {
+    /// HTTP 状态码，非错误状态码按 400 处理
+    pub fn status_code(&self) -> u16 {
+        if (400..=599).contains(&self.status) {
+            self.status
+        } else {
+            400
+        }
+    }
+
+    /// 错误类型，未指定时按状态码推断
+    pub fn error_type(&self, format: ResponseFormat) -> String {
+        if let Some(error_type) = &self.error_type {
+            return error_type.clone();
+        }
+        let status = self.status_code();
+        let error_type = match format {
+            ResponseFormat::Gemini => match status {
+                401 => "UNAUTHENTICATED",
+                403 => "PERMISSION_DENIED",
+                404 => "NOT_FOUND",
+                429 => "RESOURCE_EXHAUSTED",
+                500..=599 => "INTERNAL",
+                _ => "INVALID_ARGUMENT",
+            },
+            ResponseFormat::OpenAi | ResponseFormat::Anthropic => match status {
+                401 => "authentication_error",
+                403 => "permission_error",
+                404 => "not_found_error",
+                429 => "rate_limit_error",
+                500..=599 => "api_error",
+                _ => "invalid_request_error",
+            },
+        };
+        error_type.to_string()
+    }
+
+    /// 渲染为错误响应体
+    pub fn to_json(&self, format: ResponseFormat) -> serde_json::Value {
+        let error_type = self.error_type(format);
+        match format {
+            ResponseFormat::OpenAi => serde_json::json!({
+                "error": {
+                    "message": self.message,
+                    "type": error_type,
+                    "param": null,
+                    "code": null,
+                }
+            }),
+            ResponseFormat::Anthropic => serde_json::json!({
+                "type": "error",
+                "error": { "type": error_type, "message": self.message },
+            }),
+            ResponseFormat::Gemini => serde_json::json!({
+                "error": {
+                    "code": self.status_code(),
+                    "message": self.message,
+                    "status": error_type,
+                }
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response() -> SyntheticResponse {
+        SyntheticResponse {
+            content: "cached answer".to_string(),
+            model: None,
+            stop_reason: None,
+            input_tokens: 3,
+            output_tokens: 2,
+        }
+    }
+
+    #[test]
+    fn test_synthetic_response_json_formats() {
+        let openai = response().to_json(ResponseFormat::OpenAi, "gpt-4");
+        assert_eq!(openai["choices"][0]["message"]["content"], "cached answer");
+        assert_eq!(openai["choices"][0]["finish_reason"], "stop");
+        assert_eq!(openai["usage"]["total_tokens"], 5);
+
+        let anthropic = response().to_json(ResponseFormat::Anthropic, "claude");
+        assert_eq!(anthropic["content"][0]["text"], "cached answer");
+        assert_eq!(anthropic["stop_reason"], "end_turn");
+        assert_eq!(anthropic["model"], "claude");
+
+        let gemini = response().to_json(ResponseFormat::Gemini, "gemini-pro");
+        assert_eq!(
+            gemini["candidates"][0]["content"]["parts"][0]["text"],
+            "cached answer"
+        );
+        assert_eq!(gemini["candidates"][0]["finishReason"], "STOP");
+    }
+
+    #[test]
+    fn test_synthetic_response_sse() {
+        let openai = response().to_sse(ResponseFormat::OpenAi, "gpt-4");
+        assert!(openai
+            .iter()
+            .any(|s| s.contains("\"content\":\"cached answer\"")));
+        assert!(openai.last().unwrap().ends_with("data: [DONE]\n\n"));
+
+        let anthropic = response().to_sse(ResponseFormat::Anthropic, "claude");
+        assert!(anthropic[0].starts_with("event: message_start"));
+        assert!(anthropic.iter().any(|s| s.contains("cached answer")));
+        assert!(anthropic.last().unwrap().starts_with("event: message_stop"));
+
+        let gemini = response().to_sse(ResponseFormat::Gemini, "gemini-pro");
+        assert_eq!(gemini.len(), 1);
+        assert!(gemini[0].starts_with("data: {"));
+    }
+
+    #[test]
+    fn test_rejection_rendering() {
+        let rejection = PluginRejection::new(403, "blocked by policy");
+        assert_eq!(
+            rejection.to_json(ResponseFormat::OpenAi)["error"]["type"],
+            "permission_error"
+        );
+        assert_eq!(
+            rejection.to_json(ResponseFormat::Anthropic)["error"]["message"],
+            "blocked by policy"
+        );
+        assert_eq!(
+            rejection.to_json(ResponseFormat::Gemini)["error"]["status"],
+            "PERMISSION_DENIED"
+        );
+
+        let invalid = PluginRejection::new(200, "bad");
+        assert_eq!(invalid.status_code(), 400);
+    }
+}
@@ -0,0 +1,318 @@
+//! 插件流式钩子会话
+//!
+//! 在流开始时固定启用的插件列表，之后每个 `StreamEvent` 依次经过各插件的
+//! `on_stream_event`。单个插件失败或超时时保留该插件处理前的事件，不影响其他插件和流本身。
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::time::error::Elapsed;
+use tokio::time::timeout;
+
+use super::types::{Plugin, PluginContext, PluginError};
+use crate::stream::StreamEvent;
+
+/// 参与流处理的插件
+struct StreamPlugin {
+    plugin: Arc<dyn Plugin>,
+    timeout_ms: u64,
+}
+
+/// 单个流式响应的插件钩子会话
+pub struct PluginStreamSession {
+    ctx: PluginContext,
+    plugins: Vec<StreamPlugin>,
+    started: bool,
+}
+
+impl PluginStreamSession {
+    /// 创建会话，`plugins` 为插件及其钩子超时 (毫秒)
+    pub fn new(ctx: PluginContext, plugins: Vec<(Arc<dyn Plugin>, u64)>) -> Self {
+        Self {
+            ctx,
+            plugins: plugins
+                .into_iter()
+                .map(|(plugin, timeout_ms)| StreamPlugin { plugin, timeout_ms })
+                .collect(),
+            started: false,
+        }
+    }
+
+    /// 是否没有任何插件参与
+    pub fn is_empty(&self) -> bool {
+        self.plugins.is_empty()
+    }
+
+    /// 插件上下文
+    pub fn context(&self) -> &PluginContext {
+        &self.ctx
+    }
+
+    /// 执行 on_stream_start，重复调用时只执行一次
+    pub async fn start(&mut self) {
+        if self.started {
+            return;
+        }
+        self.started = true;
+        for entry in &self.plugins {
+            let result = timeout(
+                Duration::from_millis(entry.timeout_ms),
+                entry.plugin.on_stream_start(&mut self.ctx),
+            )
+            .await;
+            log_failure(entry.plugin.name(), "on_stream_start", result);
+        }
+    }
+
+    /// 依次经过所有插件的 on_stream_event，返回处理后的事件
+    pub async fn process(&mut self, mut events: Vec<StreamEvent>) -> Vec<StreamEvent> {
+        if self.plugins.is_empty() || events.is_empty() {
+            return events;
+        }
+        self.start().await;
+
+        for entry in &self.plugins {
+            let mut output = Vec::with_capacity(events.len());
+            for event in events {
+                let mut batch = vec![event.clone()];
+                let result = timeout(
+                    Duration::from_millis(entry.timeout_ms),
+                    entry.plugin.on_stream_event(&mut self.ctx, &mut batch),
+                )
+                .await;
+                if log_failure(entry.plugin.name(), "on_stream_event", result) {
+                    output.append(&mut batch);
+                } else {
+                    output.push(event);
+                }
+            }
+            events = output;
+        }
+        events
+    }
+
+    /// 处理结束阶段的事件并执行 on_stream_end
+    pub async fn finish(&mut self, events: Vec<StreamEvent>) -> Vec<StreamEvent> {
+        if self.plugins.is_empty() {
+            return events;
+        }
+        let mut events = self.process(events).await;
+        self.start().await;
+
+        for entry in &self.plugins {
+            let mut batch = events.clone();
+            let result = timeout(
+                Duration::from_millis(entry.timeout_ms),
+                entry.plugin.on_stream_end(&mut self.ctx, &mut batch),
+            )
+            .await;
+            if log_failure(entry.plugin.name(), "on_stream_end", result) {
+                events = batch;
+            }
+        }
+        events
+    }
+}
+
+/// 记录钩子失败，返回钩子是否成功
+fn log_failure<T>(
+    plugin_name: &str,
+    hook: &str,
+    result: Result<Result<T, PluginError>, Elapsed>,
+) -> bool {
+    match result {
+        Ok(Ok(_)) => true,
+        Ok(Err(e)) => {
+            tracing::warn!("插件 {} {} 执行失败: {}", plugin_name, hook, e);
+            false
+        }
+        Err(_) => {
+            tracing::warn!("插件 {} {} 执行超时", plugin_name, hook);
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plugin::{HookResult, PluginConfig, PluginManifest, PluginType};
+    use crate::stream::StopReason;
+    use crate::ProviderType;
+    use async_trait::async_trait;
+
+    /// 屏蔽敏感词、丢弃 Ping、在结束前追加引用的测试插件
+    struct FilterPlugin {
+        manifest: PluginManifest,
+        fail: bool,
+    }
+
+    impl FilterPlugin {
+        fn shared(fail: bool) -> Arc<dyn Plugin> {
+            Arc::new(Self {
+                manifest: PluginManifest {
+                    name: "filter".to_string(),
+                    version: "1.0.0".to_string(),
+                    description: String::new(),
+                    author: None,
+                    homepage: None,
+                    license: None,
+                    entry: "config.json".to_string(),
+                    plugin_type: PluginType::Script,
+                    config_schema: None,
+                    hooks: vec![],
+                    min_proxycast_version: None,
+                    binary: None,
+                    ui: None,
+                    script_limits: None,
+                    wasm: None,
+                    rpc: None,
+                    provider: None,
+                },
+                fail,
+            })
+        }
+    }
+
+    #[async_trait]
+    impl Plugin for FilterPlugin {
+        fn name(&self) -> &str {
+            &self.manifest.name
+        }
+
+        fn version(&self) -> &str {
+            &self.manifest.version
+        }
+
+        fn manifest(&self) -> &PluginManifest {
+            &self.manifest
+        }
+
+        async fn init(&mut self, _config: &PluginConfig) -> Result<(), PluginError> {
+            Ok(())
+        }
+
+        async fn on_request(
+            &self,
+            _ctx: &mut PluginContext,
+            _request: &mut serde_json::Value,
+        ) -> Result<HookResult, PluginError> {
+            Ok(HookResult::success(false, 0))
+        }
+
+        async fn on_response(
+            &self,
+            _ctx: &mut PluginContext,
+            _response: &mut serde_json::Value,
+        ) -> Result<HookResult, PluginError> {
+            Ok(HookResult::success(false, 0))
+        }
+
+        async fn on_error(
+            &self,
+            _ctx: &mut PluginContext,
+            _error: &str,
+        ) -> Result<HookResult, PluginError> {
+            Ok(HookResult::success(false, 0))
+        }
+
+        async fn on_stream_start(
+            &self,
+            ctx: &mut PluginContext,
+        ) -> Result<HookResult, PluginError> {
+            ctx.set_metadata("stream_started", serde_json::json!(true));
+            Ok(HookResult::success(false, 0))
+        }
+
+        async fn on_stream_event(
+            &self,
+            _ctx: &mut PluginContext,
+            events: &mut Vec<StreamEvent>,
+        ) -> Result<HookResult, PluginError> {
+            if self.fail {
+                events.clear();
+                return Err(PluginError::ExecutionError {
+                    plugin_name: "filter".to_string(),
+                    message: "boom".to_string(),
+                });
+            }
+            events.retain(|e| !matches!(e, StreamEvent::Ping));
+            for event in events.iter_mut() {
+                if let StreamEvent::TextDelta { text } = event {
+                    *text = text.replace("secret", "******");
+                }
+            }
+            Ok(HookResult::success(true, 0))
+        }
+
+        async fn on_stream_end(
+            &self,
+            _ctx: &mut PluginContext,
+            events: &mut Vec<StreamEvent>,
+        ) -> Result<HookResult, PluginError> {
+            let at = events
+                .iter()
+                .position(|e| matches!(e, StreamEvent::MessageStop { .. }))
+                .unwrap_or(events.len());
+            events.insert(
+                at,
+                StreamEvent::TextDelta {
+                    text: "[1] source".to_string(),
+                },
+            );
+            Ok(HookResult::success(true, 0))
+        }
+
+        async fn shutdown(&mut self) -> Result<(), PluginError> {
+            Ok(())
+        }
+    }
+
+    fn session(plugin: Arc<dyn Plugin>) -> PluginStreamSession {
+        let ctx = PluginContext::new("req".to_string(), ProviderType::Kiro, "m".to_string());
+        PluginStreamSession::new(ctx, vec![(plugin, 1000)])
+    }
+
+    #[tokio::test]
+    async fn test_process_modifies_and_drops_events() {
+        let mut session = session(FilterPlugin::shared(false));
+        let events = session
+            .process(vec![
+                StreamEvent::Ping,
+                StreamEvent::TextDelta {
+                    text: "my secret".to_string(),
+                },
+            ])
+            .await;
+        assert_eq!(
+            events,
+            vec![StreamEvent::TextDelta {
+                text: "my ******".to_string()
+            }]
+        );
+        assert_eq!(
+            session.context().get_metadata("stream_started"),
+            Some(&serde_json::json!(true))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_finish_injects_before_message_stop() {
+        let mut session = session(FilterPlugin::shared(false));
+        let events = session
+            .finish(vec![StreamEvent::MessageStop {
+                stop_reason: StopReason::EndTurn,
+            }])
+            .await;
+        assert_eq!(events.len(), 2);
+        assert!(matches!(&events[0], StreamEvent::TextDelta { text } if text == "[1] source"));
+        assert!(matches!(events[1], StreamEvent::MessageStop { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_failed_hook_keeps_original_events() {
+        let mut session = session(FilterPlugin::shared(true));
+        let events = session.process(vec![StreamEvent::Ping]).await;
+        assert_eq!(events, vec![StreamEvent::Ping]);
+    }
+}
@@ -20,6 +20,10 @@ fn test_plugin_manifest_validation() {
         min_proxycast_version: None,
         binary: None,
         ui: None,
+        script_limits: None,
+        wasm: None,
+        rpc: None,
+        provider: None,
     };
     assert!(valid.validate().is_ok());
 
@@ -158,6 +162,10 @@ fn test_plugin_manifest_serde() {
         min_proxycast_version: Some("0.13.0".to_string()),
         binary: None,
         ui: None,
+        script_limits: None,
+        wasm: None,
+        rpc: None,
+        provider: None,
     };
 
     // 序列化
@@ -11,6 +11,7 @@ use std::path::PathBuf;
 use std::sync::Arc;
 use thiserror::Error;
 
+use crate::stream::StreamEvent;
 use crate::ProviderType;
 
 /// 插件错误类型
@@ -124,6 +125,18 @@ pub struct PluginManifest {
     /// _需求: 5.3_
     #[serde(default)]
     pub ui: Option<UiManifest>,
+    /// Script 类型插件的沙箱资源限制
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub script_limits: Option<ScriptLimits>,
+    /// Native (WASM) 类型插件的能力声明和资源限制
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wasm: Option<WasmManifest>,
+    /// Binary 类型插件作为进程外钩子运行时的启动参数和重启策略
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rpc: Option<RpcManifest>,
+    /// 插件提供的上游 Provider
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider: Option<ProviderManifest>,
 }
 
 fn default_entry() -> String {
@@ -139,6 +152,9 @@ impl PluginManifest {
         if self.version.is_empty() {
             return Err(PluginError::InvalidManifest("插件版本不能为空".to_string()));
         }
+        if let Some(provider) = &self.provider {
+            provider.validate()?;
+        }
         Ok(())
     }
 }
@@ -147,16 +163,205 @@ impl PluginManifest {
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
 #[serde(rename_all = "lowercase")]
 pub enum PluginType {
-    /// 脚本插件 (JSON 配置驱动)
+    /// 脚本插件 (入口为 `.rhai` 时使用嵌入式脚本引擎，否则为 JSON 配置驱动)
     #[default]
     #[serde(alias = "lua")]
     Script,
-    /// 原生 Rust 插件 (预留)
+    /// 原生插件 (WASM 模块)
     Native,
-    /// 二进制可执行文件
+    /// 二进制可执行文件 (声明钩子时通过 stdio JSON-RPC 参与请求管道)
     Binary,
 }
 
+/// 脚本插件沙箱资源限制
+///
+/// 每次钩子调用独立计量，超出任一限制时脚本被终止
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct ScriptLimits {
+    /// 单次调用最大操作数 (0 表示不限制)
+    pub max_operations: u64,
+    /// 单次调用 CPU 时间上限 (毫秒)，为空时使用插件配置的 timeout_ms
+    pub cpu_time_ms: Option<u64>,
+    /// 字符串最大长度 (字节)
+    pub max_string_size: usize,
+    /// 数组最大元素数
+    pub max_array_size: usize,
+    /// 对象最大键数
+    pub max_map_size: usize,
+    /// 最大函数调用深度
+    pub max_call_levels: usize,
+    /// KV 存储最大条目数
+    pub max_kv_entries: usize,
+}
+
+impl Default for ScriptLimits {
+    fn default() -> Self {
+        Self {
+            max_operations: 1_000_000,
+            cpu_time_ms: None,
+            max_string_size: 16 * 1024 * 1024,
+            max_array_size: 100_000,
+            max_map_size: 10_000,
+            max_call_levels: 32,
+            max_kv_entries: 1_000,
+        }
+    }
+}
+
+/// WASM 插件清单扩展字段
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub struct WasmManifest {
+    /// 宿主能力授权 (默认全部关闭)
+    #[serde(default)]
+    pub capabilities: WasmCapabilities,
+    /// 资源限制
+    #[serde(default)]
+    pub limits: WasmLimits,
+}
+
+/// WASM 插件可申请的宿主能力
+///
+/// 未授权的宿主函数不会链接，导入它们的模块在加载时被拒绝
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(default)]
+pub struct WasmCapabilities {
+    /// 写入宿主日志
+    pub log: bool,
+    /// 插件私有 KV 存储
+    pub kv: bool,
+    /// 读写 PluginContext 元数据
+    pub flow_metadata: bool,
+    /// 允许访问的网络主机 (为空表示禁止网络访问)
+    pub network: Vec<String>,
+}
+
+/// WASM 插件资源限制
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct WasmLimits {
+    /// 单次调用可消耗的燃料 (约等于指令数)
+    pub fuel: u64,
+    /// 线性内存上限 (字节)
+    pub max_memory_bytes: usize,
+    /// KV 存储最大条目数
+    pub max_kv_entries: usize,
+}
+
+impl Default for WasmLimits {
+    fn default() -> Self {
+        Self {
+            fuel: 100_000_000,
+            max_memory_bytes: 64 * 1024 * 1024,
+            max_kv_entries: 1_000,
+        }
+    }
+}
+
+/// 进程外 (JSON-RPC) 插件配置
+///
+/// 插件进程崩溃后按指数退避重启，连续重启超过 `max_restarts` 次后停止
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct RpcManifest {
+    /// 启动参数
+    pub args: Vec<String>,
+    /// 最大连续重启次数
+    pub max_restarts: u32,
+    /// 首次重启前的等待时间 (毫秒)
+    pub initial_backoff_ms: u64,
+    /// 重启等待时间上限 (毫秒)，进程稳定运行超过该时长后重置退避
+    pub max_backoff_ms: u64,
+    /// 连续超时达到该次数时视为进程挂起并强制重启 (0 表示不检测)
+    pub max_consecutive_timeouts: u32,
+}
+
+impl Default for RpcManifest {
+    fn default() -> Self {
+        Self {
+            args: Vec::new(),
+            max_restarts: 5,
+            initial_backoff_ms: 500,
+            max_backoff_ms: 30_000,
+            max_consecutive_timeouts: 3,
+        }
+    }
+}
+
+/// 插件提供的上游 Provider 声明
+///
+/// 注册后以 `plugin:<id>` 作为 Provider 名称参与凭证池选择、路由和故障转移。
+/// 插件负责在规范协议 (OpenAI Chat Completions) 与上游协议之间转换
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ProviderManifest {
+    /// Provider 标识 (小写字母、数字、`-`、`_`)
+    pub id: String,
+    /// 显示名称
+    #[serde(default)]
+    pub display_name: Option<String>,
+    /// 凭证字段的 JSON Schema (校验 `required` 和顶层字段类型)
+    #[serde(default)]
+    pub credential_schema: Option<serde_json::Value>,
+    /// 支持的模型 (支持通配符，为空表示不限制)
+    #[serde(default)]
+    pub models: Vec<String>,
+    /// 上游请求超时 (毫秒)
+    #[serde(default = "default_upstream_timeout_ms")]
+    pub request_timeout_ms: u64,
+}
+
+fn default_upstream_timeout_ms() -> u64 {
+    120_000
+}
+
+impl ProviderManifest {
+    fn validate(&self) -> Result<(), PluginError> {
+        let valid = !self.id.is_empty()
+            && self
+                .id
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_');
+        if !valid {
+            return Err(PluginError::InvalidManifest(format!(
+                "Provider 标识无效: {:?}",
+                self.id
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// 插件 Provider 构造的上游 HTTP 请求
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct UpstreamRequest {
+    /// 完整请求 URL
+    pub url: String,
+    /// HTTP 方法
+    #[serde(default = "default_upstream_method")]
+    pub method: String,
+    /// 请求头
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    /// JSON 请求体
+    #[serde(default)]
+    pub body: Option<serde_json::Value>,
+}
+
+fn default_upstream_method() -> String {
+    "POST".to_string()
+}
+
+/// 上游 HTTP 响应，交给插件转换为规范协议响应
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct UpstreamResponse {
+    /// HTTP 状态码
+    pub status: u16,
+    /// 响应头
+    pub headers: HashMap<String, String>,
+    /// 响应体 (非 JSON 时为字符串)
+    pub body: serde_json::Value,
+}
+
 /// 平台二进制文件名映射
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct PlatformBinaries {
@@ -307,6 +512,9 @@ pub struct HookResult {
     pub error: Option<String>,
     /// 执行时间 (毫秒)
     pub duration_ms: u64,
+    /// 短路动作 (直接响应或拒绝请求)，设置后不再执行后续插件
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub action: Option<HookAction>,
 }
 
 impl HookResult {
@@ -317,6 +525,7 @@ impl HookResult {
             modified,
             error: None,
             duration_ms,
+            action: None,
         }
     }
 
@@ -327,6 +536,90 @@ impl HookResult {
             modified: false,
             error: Some(error),
             duration_ms,
+            action: None,
+        }
+    }
+
+    /// 创建直接响应结果，跳过上游调用
+    pub fn respond(response: SyntheticResponse, duration_ms: u64) -> Self {
+        Self {
+            action: Some(HookAction::Respond(response)),
+            ..Self::success(false, duration_ms)
+        }
+    }
+
+    /// 创建拒绝请求结果
+    pub fn reject(rejection: PluginRejection, duration_ms: u64) -> Self {
+        Self {
+            action: Some(HookAction::Reject(rejection)),
+            ..Self::success(false, duration_ms)
+        }
+    }
+}
+
+/// 插件短路动作
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum HookAction {
+    /// 返回插件生成的响应
+    Respond(SyntheticResponse),
+    /// 以指定状态码拒绝请求
+    Reject(PluginRejection),
+}
+
+/// 插件生成的响应 (与协议无关，按客户端请求的格式渲染)
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct SyntheticResponse {
+    /// 文本内容
+    pub content: String,
+    /// 响应中的模型名称，为空时使用请求的模型
+    #[serde(default)]
+    pub model: Option<String>,
+    /// 停止原因 (如 `end_turn`、`max_tokens`)，默认 `end_turn`
+    #[serde(default)]
+    pub stop_reason: Option<String>,
+    /// 输入 token 数
+    #[serde(default)]
+    pub input_tokens: u32,
+    /// 输出 token 数
+    #[serde(default)]
+    pub output_tokens: u32,
+}
+
+impl SyntheticResponse {
+    /// 创建文本响应
+    pub fn text(content: impl Into<String>) -> Self {
+        Self {
+            content: content.into(),
+            ..Default::default()
+        }
+    }
+}
+
+/// 插件拒绝请求
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PluginRejection {
+    /// HTTP 状态码
+    #[serde(default = "default_rejection_status")]
+    pub status: u16,
+    /// 错误信息
+    pub message: String,
+    /// 错误类型，为空时按状态码推断
+    #[serde(default)]
+    pub error_type: Option<String>,
+}
+
+fn default_rejection_status() -> u16 {
+    400
+}
+
+impl PluginRejection {
+    /// 创建拒绝
+    pub fn new(status: u16, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            message: message.into(),
+            error_type: None,
         }
     }
 }
@@ -395,6 +688,43 @@ pub struct PluginState {
     pub error_count: u64,
     /// 最后错误信息
     pub last_error: Option<String>,
+    /// 进程外插件的健康状态
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub health: Option<PluginHealth>,
+}
+
+/// 插件进程状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ProcessStatus {
+    /// 正在启动
+    #[default]
+    Starting,
+    /// 运行中
+    Running,
+    /// 等待重启
+    Restarting,
+    /// 已停止
+    Stopped,
+    /// 超过重启次数后放弃
+    Failed,
+}
+
+/// 进程外插件健康信息
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PluginHealth {
+    /// 进程状态
+    pub process: ProcessStatus,
+    /// 当前进程 ID
+    pub pid: Option<u32>,
+    /// 累计重启次数
+    pub restart_count: u32,
+    /// 当前进程启动时间
+    pub started_at: Option<DateTime<Utc>>,
+    /// 最后一次退出原因
+    pub last_exit: Option<String>,
+    /// 连续超时次数
+    pub consecutive_timeouts: u32,
 }
 
 impl PluginState {
@@ -408,6 +738,7 @@ impl PluginState {
             execution_count: 0,
             error_count: 0,
             last_error: None,
+            health: None,
         }
     }
 
@@ -483,8 +814,72 @@ pub trait Plugin: Send + Sync {
         error: &str,
     ) -> Result<HookResult, PluginError>;
 
+    /// 流式响应开始钩子
+    async fn on_stream_start(&self, _ctx: &mut PluginContext) -> Result<HookResult, PluginError> {
+        Ok(HookResult::success(false, 0))
+    }
+
+    /// 流事件钩子
+    ///
+    /// `events` 初始仅包含当前事件，插件可就地修改、清空 (丢弃) 或追加 (注入) 事件
+    async fn on_stream_event(
+        &self,
+        _ctx: &mut PluginContext,
+        _events: &mut Vec<StreamEvent>,
+    ) -> Result<HookResult, PluginError> {
+        Ok(HookResult::success(false, 0))
+    }
+
+    /// 流式响应结束钩子
+    ///
+    /// `events` 为结束阶段待发送的事件 (通常以 `MessageStop` 结尾)，插件可在其前插入事件
+    async fn on_stream_end(
+        &self,
+        _ctx: &mut PluginContext,
+        _events: &mut Vec<StreamEvent>,
+    ) -> Result<HookResult, PluginError> {
+        Ok(HookResult::success(false, 0))
+    }
+
+    /// 刷新 Provider 凭证 (仅声明了 `provider` 的插件调用)
+    ///
+    /// 每次上游请求前调用，插件就地更新 `credential` 字段 (如 access_token)，
+    /// 返回 `true` 时宿主将更新后的凭证写回凭证池
+    async fn provider_refresh_token(
+        &self,
+        _credential: &mut serde_json::Value,
+    ) -> Result<bool, PluginError> {
+        Ok(false)
+    }
+
+    /// 将规范协议 (OpenAI Chat Completions) 请求转换为上游 HTTP 请求
+    async fn provider_build_request(
+        &self,
+        _credential: &serde_json::Value,
+        _request: &serde_json::Value,
+    ) -> Result<UpstreamRequest, PluginError> {
+        Err(PluginError::ExecutionError {
+            plugin_name: self.name().to_string(),
+            message: "插件未实现 Provider 请求适配".to_string(),
+        })
+    }
+
+    /// 将上游响应转换为规范协议 (OpenAI Chat Completions) 响应，默认原样返回响应体
+    async fn provider_parse_response(
+        &self,
+        _credential: &serde_json::Value,
+        response: UpstreamResponse,
+    ) -> Result<serde_json::Value, PluginError> {
+        Ok(response.body)
+    }
+
     /// 关闭插件
     async fn shutdown(&mut self) -> Result<(), PluginError>;
+
+    /// 健康信息 (仅进程外插件提供)
+    fn health(&self) -> Option<PluginHealth> {
+        None
+    }
 }
 
 /// 插件实例包装器 - 用于管理插件生命周期
@@ -514,6 +909,8 @@ impl PluginInstance {
     /// 获取插件信息
     pub fn info(&self) -> PluginInfo {
         let manifest = self.plugin.manifest();
+        let mut state = self.state.clone();
+        state.health = self.plugin.health();
         PluginInfo {
             name: manifest.name.clone(),
             version: manifest.version.clone(),
@@ -524,7 +921,7 @@ impl PluginInstance {
             hooks: manifest.hooks.clone(),
             config_schema: manifest.config_schema.clone(),
             config: self.config.clone(),
-            state: self.state.clone(),
+            state,
         }
     }
 
@@ -657,6 +1054,10 @@ mod tests {
                         min_proxycast_version,
                         binary,
                         ui,
+                        script_limits: None,
+                        wasm: None,
+                        rpc: None,
+                        provider: None,
                     }
                 },
             )
@@ -735,6 +1136,10 @@ mod tests {
                 default_width: None,
                 default_height: None,
             }),
+            script_limits: None,
+            wasm: None,
+            rpc: None,
+            provider: None,
         };
 
         // 序列化
@@ -754,4 +1159,28 @@ mod tests {
         assert_eq!(binary.binary_name, "machine-id-tool");
         assert_eq!(binary.github_owner, "user");
     }
+
+    #[test]
+    fn test_hook_action_deserialization() {
+        let result: HookResult = serde_json::from_value(serde_json::json!({
+            "success": true,
+            "modified": false,
+            "duration_ms": 1,
+            "action": { "type": "reject", "message": "blocked" }
+        }))
+        .unwrap();
+        assert_eq!(
+            result.action,
+            Some(HookAction::Reject(PluginRejection::new(400, "blocked")))
+        );
+
+        let action: HookAction =
+            serde_json::from_value(serde_json::json!({ "type": "respond", "content": "hi" }))
+                .unwrap();
+        assert_eq!(action, HookAction::Respond(SyntheticResponse::text("hi")));
+
+        // 旧版插件返回的结果不含 action
+        let json = serde_json::to_string(&HookResult::success(true, 0)).unwrap();
+        assert!(!json.contains("action"));
+    }
 }
@@ -0,0 +1,1217 @@
+//! WASM 插件运行时
+//!
+//! Native 类型插件以 WASM 模块交付，由 wasmi 解释执行。每次钩子调用使用全新实例，
+//! 受燃料 (指令数) 和线性内存上限约束；模块文件变化时自动热重载。
+//!
+//! # 宿主 ABI (v1)
+//!
+//! 模块必须导出：
+//! - `memory`：线性内存
+//! - `proxycast_abi_version() -> i32`：返回 [`WASM_ABI_VERSION`]
+//! - `proxycast_alloc(len: i32) -> i32`：分配 `len` 字节并返回指针
+//!
+//! 可选导出钩子 `proxycast_on_request` / `proxycast_on_response` / `proxycast_on_error`，
+//! 签名均为 `(ptr: i32, len: i32) -> i64`。输入为 JSON：
+//! `{"abi_version", "hook", "ctx", "settings", "payload"?, "error"?}`；
+//! 返回值为输出 JSON 的 `(ptr << 32) | len`，返回 0 表示不做修改。输出 JSON：
+//! `{"payload"?, "model"?, "metadata"?, "error"?}`。
+//!
+//! 宿主函数位于 `proxycast` 模块，按清单中的能力授权链接：
+//! - `log(level, ptr, len)`：需要 `log`，level 0-3 对应 debug/info/warn/error
+//! - `kv_get(kptr, klen) -> i64`、`kv_set(kptr, klen, vptr, vlen) -> i32`、
+//!   `kv_delete(kptr, klen) -> i32`：需要 `kv`，值为 JSON
+//! - `http_request(ptr, len) -> i64`：需要 `network` 中列出目标主机，
+//!   请求 `{"method", "url", "headers"?, "body"?}`，响应 `{"status", "headers", "body"}` 或 `{"error"}`
+//!
+//! `ctx.metadata` 仅在授权 `flow_metadata` 时传入，输出中的 `metadata` 也仅在此时生效。
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use wasmi::core::TrapCode;
+use wasmi::{
+    AsContext, AsContextMut, Caller, Config, Engine, Extern, Instance, Linker, Memory, Module,
+    Store, StoreLimits, StoreLimitsBuilder, TypedFunc,
+};
+
+use super::kv_store::PluginKvStore;
+use super::types::{
+    HookResult, Plugin, PluginConfig, PluginContext, PluginError, PluginManifest, WasmManifest,
+};
+
+/// 宿主 ABI 版本
+pub const WASM_ABI_VERSION: i32 = 1;
+
+/// 宿主函数所在的导入模块名
+const HOST_MODULE: &str = "proxycast";
+
+/// 宿主函数及其所需能力
+const HOST_FUNCTIONS: &[(&str, WasmCapability)] = &[
+    ("log", WasmCapability::Log),
+    ("kv_get", WasmCapability::Kv),
+    ("kv_set", WasmCapability::Kv),
+    ("kv_delete", WasmCapability::Kv),
+    ("http_request", WasmCapability::Network),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum WasmCapability {
+    Log,
+    Kv,
+    Network,
+}
+
+impl WasmCapability {
+    fn name(&self) -> &'static str {
+        match self {
+            WasmCapability::Log => "log",
+            WasmCapability::Kv => "kv",
+            WasmCapability::Network => "network",
+        }
+    }
+
+    fn granted(&self, manifest: &WasmManifest) -> bool {
+        match self {
+            WasmCapability::Log => manifest.capabilities.log,
+            WasmCapability::Kv => manifest.capabilities.kv,
+            WasmCapability::Network => !manifest.capabilities.network.is_empty(),
+        }
+    }
+}
+
+/// WASM 钩子
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WasmHook {
+    Request,
+    Response,
+    Error,
+}
+
+impl WasmHook {
+    /// 模块中的导出名
+    pub fn export_name(&self) -> &'static str {
+        match self {
+            WasmHook::Request => "proxycast_on_request",
+            WasmHook::Response => "proxycast_on_response",
+            WasmHook::Error => "proxycast_on_error",
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            WasmHook::Request => "on_request",
+            WasmHook::Response => "on_response",
+            WasmHook::Error => "on_error",
+        }
+    }
+}
+
+/// 传给模块的 PluginContext 视图
+#[derive(Debug, Serialize)]
+struct WasmContextView<'a> {
+    request_id: &'a str,
+    provider: String,
+    model: &'a str,
+    timestamp: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    metadata: Option<&'a HashMap<String, serde_json::Value>>,
+}
+
+/// 钩子输入
+#[derive(Debug, Serialize)]
+struct WasmHookInput<'a> {
+    abi_version: i32,
+    hook: &'static str,
+    ctx: WasmContextView<'a>,
+    settings: &'a serde_json::Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    payload: Option<&'a serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<&'a str>,
+}
+
+/// 钩子输出
+#[derive(Debug, Default, Deserialize)]
+struct WasmHookOutput {
+    #[serde(default)]
+    payload: Option<serde_json::Value>,
+    #[serde(default)]
+    model: Option<String>,
+    #[serde(default)]
+    metadata: Option<HashMap<String, serde_json::Value>>,
+    #[serde(default)]
+    error: Option<String>,
+}
+
+/// `http_request` 的请求体
+#[derive(Debug, Deserialize)]
+struct HostHttpRequest {
+    #[serde(default = "default_http_method")]
+    method: String,
+    url: String,
+    #[serde(default)]
+    headers: HashMap<String, String>,
+    #[serde(default)]
+    body: Option<String>,
+}
+
+fn default_http_method() -> String {
+    "GET".to_string()
+}
+
+/// 网络访问授权
+#[derive(Clone)]
+struct NetworkAccess {
+    client: reqwest::Client,
+    allowed_hosts: Arc<Vec<String>>,
+}
+
+impl NetworkAccess {
+    /// 创建 HTTP 客户端，重定向的每一跳都重新检查授权列表
+    fn new(allowed_hosts: Vec<String>, timeout: Duration) -> reqwest::Result<Self> {
+        let allowed_hosts = Arc::new(allowed_hosts);
+        let redirect_hosts = allowed_hosts.clone();
+        let policy = reqwest::redirect::Policy::custom(move |attempt| {
+            if attempt.previous().len() >= MAX_HTTP_REDIRECTS {
+                return attempt.error("重定向次数过多");
+            }
+            match attempt.url().host_str() {
+                Some(host) if host_allowed(&redirect_hosts, host) => attempt.follow(),
+                host => {
+                    let message = format!("重定向到未授权主机: {}", host.unwrap_or_default());
+                    attempt.error(message)
+                }
+            }
+        });
+        let client = reqwest::Client::builder()
+            .timeout(timeout)
+            .redirect(policy)
+            .build()?;
+        Ok(Self {
+            client,
+            allowed_hosts,
+        })
+    }
+
+    /// 主机是否在授权列表中
+    fn allows(&self, host: &str) -> bool {
+        host_allowed(&self.allowed_hosts, host)
+    }
+}
+
+/// 宿主 HTTP 请求最多跟随的重定向次数
+const MAX_HTTP_REDIRECTS: usize = 5;
+
+/// 主机是否在授权列表中，支持 `*.example.com` 通配子域名
+fn host_allowed(allowed_hosts: &[String], host: &str) -> bool {
+    allowed_hosts
+        .iter()
+        .any(|allowed| match allowed.strip_prefix("*.") {
+            Some(suffix) => host
+                .strip_suffix(suffix)
+                .is_some_and(|prefix| prefix.ends_with('.')),
+            None => allowed.eq_ignore_ascii_case(host),
+        })
+}
+
+/// 单次调用的宿主状态
+struct HostState {
+    plugin_name: Arc<str>,
+    limits: StoreLimits,
+    kv: Arc<PluginKvStore>,
+    network: Option<NetworkAccess>,
+}
+
+/// 模块导出的内存与分配函数
+struct GuestMemory {
+    memory: Memory,
+    alloc: TypedFunc<i32, i32>,
+}
+
+impl GuestMemory {
+    fn from_caller(caller: &Caller<'_, HostState>) -> Result<Self, wasmi::Error> {
+        let memory = caller
+            .get_export("memory")
+            .and_then(Extern::into_memory)
+            .ok_or_else(|| wasmi::Error::new("模块未导出 memory"))?;
+        let alloc = caller
+            .get_export("proxycast_alloc")
+            .and_then(Extern::into_func)
+            .ok_or_else(|| wasmi::Error::new("模块未导出 proxycast_alloc"))?
+            .typed::<i32, i32>(caller)?;
+        Ok(Self { memory, alloc })
+    }
+
+    fn from_instance(instance: &Instance, store: &Store<HostState>) -> Result<Self, wasmi::Error> {
+        let memory = instance
+            .get_memory(store, "memory")
+            .ok_or_else(|| wasmi::Error::new("模块未导出 memory"))?;
+        let alloc = instance.get_typed_func::<i32, i32>(store, "proxycast_alloc")?;
+        Ok(Self { memory, alloc })
+    }
+
+    fn read(&self, ctx: impl AsContext, ptr: i32, len: i32) -> Result<Vec<u8>, wasmi::Error> {
+        let start = ptr as u32 as usize;
+        let end = start.saturating_add(len as u32 as usize);
+        self.memory
+            .data(&ctx)
+            .get(start..end)
+            .map(<[u8]>::to_vec)
+            .ok_or_else(|| wasmi::Error::new("读取模块内存越界"))
+    }
+
+    /// 在模块内分配并写入数据，返回打包后的 `(ptr << 32) | len`
+    fn write(&self, mut ctx: impl AsContextMut, bytes: &[u8]) -> Result<i64, wasmi::Error> {
+        let len = i32::try_from(bytes.len()).map_err(|_| wasmi::Error::new("数据过大"))?;
+        let ptr = self.alloc.call(&mut ctx, len)?;
+        self.memory
+            .write(&mut ctx, ptr as u32 as usize, bytes)
+            .map_err(|e| wasmi::Error::new(format!("写入模块内存失败: {}", e)))?;
+        Ok(pack(ptr, len))
+    }
+}
+
+fn pack(ptr: i32, len: i32) -> i64 {
+    (((ptr as u32 as u64) << 32) | len as u32 as u64) as i64
+}
+
+fn unpack(packed: i64) -> (i32, i32) {
+    let packed = packed as u64;
+    ((packed >> 32) as u32 as i32, packed as u32 as i32)
+}
+
+/// 已编译的模块
+struct CompiledModule {
+    module: Module,
+    hooks: Vec<WasmHook>,
+    fingerprint: [u8; 32],
+}
+
+/// 沙箱：引擎、按能力链接的宿主函数和资源限制
+struct WasmSandbox {
+    plugin_name: Arc<str>,
+    manifest: WasmManifest,
+    engine: Engine,
+    linker: Linker<HostState>,
+    kv: Arc<PluginKvStore>,
+    network: Option<NetworkAccess>,
+}
+
+impl WasmSandbox {
+    fn new(
+        plugin_name: &str,
+        manifest: WasmManifest,
+        config: &PluginConfig,
+        kv_path: Option<PathBuf>,
+    ) -> Result<Self, PluginError> {
+        let mut engine_config = Config::default();
+        engine_config.consume_fuel(true);
+        let engine = Engine::new(&engine_config);
+
+        let network = if manifest.capabilities.network.is_empty() {
+            None
+        } else {
+            let network = NetworkAccess::new(
+                manifest.capabilities.network.clone(),
+                Duration::from_millis(config.timeout_ms),
+            )
+            .map_err(|e| PluginError::InitError(format!("创建 HTTP 客户端失败: {}", e)))?;
+            Some(network)
+        };
+
+        let mut linker = Linker::new(&engine);
+        define_host_functions(&mut linker, &manifest)
+            .map_err(|e| PluginError::LoadError(format!("注册宿主函数失败: {}", e)))?;
+
+        Ok(Self {
+            plugin_name: Arc::from(plugin_name),
+            kv: Arc::new(PluginKvStore::open(kv_path, manifest.limits.max_kv_entries)),
+            manifest,
+            engine,
+            linker,
+            network,
+        })
+    }
+
+    /// 编译模块并校验能力和 ABI
+    fn compile(&self, wasm: &[u8]) -> Result<CompiledModule, PluginError> {
+        let module = Module::new(&self.engine, wasm)
+            .map_err(|e| PluginError::LoadError(format!("WASM 模块无效: {}", e)))?;
+
+        // 能力检查：只允许导入已授权的宿主函数
+        for import in module.imports() {
+            if import.module() != HOST_MODULE {
+                return Err(PluginError::LoadError(format!(
+                    "不支持的导入模块: {}",
+                    import.module()
+                )));
+            }
+            let capability = HOST_FUNCTIONS
+                .iter()
+                .find(|(name, _)| *name == import.name())
+                .map(|(_, capability)| *capability)
+                .ok_or_else(|| {
+                    PluginError::LoadError(format!("未知的宿主函数: {}", import.name()))
+                })?;
+            if !capability.granted(&self.manifest) {
+                return Err(PluginError::LoadError(format!(
+                    "宿主函数 {} 需要能力 {}",
+                    import.name(),
+                    capability.name()
+                )));
+            }
+        }
+
+        let hooks: Vec<WasmHook> = [WasmHook::Request, WasmHook::Response, WasmHook::Error]
+            .into_iter()
+            .filter(|hook| module.exports().any(|e| e.name() == hook.export_name()))
+            .collect();
+
+        // 试实例化：校验内存上限和 ABI 版本
+        let mut store = self.new_store()?;
+        let instance = self.instantiate(&mut store, &module)?;
+        let version = instance
+            .get_typed_func::<(), i32>(&store, "proxycast_abi_version")
+            .and_then(|f| f.call(&mut store, ()))
+            .map_err(|e| PluginError::LoadError(format!("读取 ABI 版本失败: {}", e)))?;
+        if version != WASM_ABI_VERSION {
+            return Err(PluginError::LoadError(format!(
+                "ABI 版本不兼容: 模块为 {}，宿主为 {}",
+                version, WASM_ABI_VERSION
+            )));
+        }
+        GuestMemory::from_instance(&instance, &store)
+            .map_err(|e| PluginError::LoadError(e.to_string()))?;
+
+        Ok(CompiledModule {
+            module,
+            hooks,
+            fingerprint: fingerprint(wasm),
+        })
+    }
+
+    fn new_store(&self) -> Result<Store<HostState>, PluginError> {
+        let limits = StoreLimitsBuilder::new()
+            .memory_size(self.manifest.limits.max_memory_bytes)
+            .instances(1)
+            .memories(1)
+            .tables(1)
+            .build();
+        let mut store = Store::new(
+            &self.engine,
+            HostState {
+                plugin_name: self.plugin_name.clone(),
+                limits,
+                kv: self.kv.clone(),
+                network: self.network.clone(),
+            },
+        );
+        store.limiter(|state| &mut state.limits);
+        store
+            .set_fuel(self.manifest.limits.fuel)
+            .map_err(|e| PluginError::InitError(e.to_string()))?;
+        Ok(store)
+    }
+
+    fn instantiate(
+        &self,
+        store: &mut Store<HostState>,
+        module: &Module,
+    ) -> Result<Instance, PluginError> {
+        self.linker
+            .instantiate(&mut *store, module)
+            .and_then(|pre| pre.start(&mut *store))
+            .map_err(|e| PluginError::LoadError(format!("实例化 WASM 模块失败: {}", e)))
+    }
+}
+
+/// WASM 模块运行时
+pub struct WasmRuntime {
+    sandbox: WasmSandbox,
+    module: RwLock<Arc<CompiledModule>>,
+    settings: serde_json::Value,
+}
+
+impl WasmRuntime {
+    /// 编译模块，`kv_path` 为空时 KV 存储仅保存在内存中
+    pub fn new(
+        plugin_name: &str,
+        manifest: WasmManifest,
+        wasm: &[u8],
+        settings: serde_json::Value,
+        config: &PluginConfig,
+        kv_path: Option<PathBuf>,
+    ) -> Result<Self, PluginError> {
+        let sandbox = WasmSandbox::new(plugin_name, manifest, config, kv_path)?;
+        let compiled = sandbox.compile(wasm)?;
+        Ok(Self {
+            sandbox,
+            module: RwLock::new(Arc::new(compiled)),
+            settings,
+        })
+    }
+
+    /// 模块是否导出了指定钩子
+    pub fn has_hook(&self, hook: WasmHook) -> bool {
+        self.module.read().hooks.contains(&hook)
+    }
+
+    /// 用新的模块字节替换当前模块，内容未变化时返回 false
+    pub fn reload(&self, wasm: &[u8]) -> Result<bool, PluginError> {
+        if self.module.read().fingerprint == fingerprint(wasm) {
+            return Ok(false);
+        }
+        let compiled = self.sandbox.compile(wasm)?;
+        *self.module.write() = Arc::new(compiled);
+        Ok(true)
+    }
+
+    /// 执行 on_request / on_response，返回 payload 是否被修改
+    pub fn call_payload_hook(
+        &self,
+        hook: WasmHook,
+        ctx: &mut PluginContext,
+        payload: &mut serde_json::Value,
+    ) -> Result<bool, PluginError> {
+        let output = self.call(hook, ctx, Some(payload), None)?;
+        let Some(output) = output else {
+            return Ok(false);
+        };
+        self.apply_context(ctx, &output);
+        match output.payload {
+            Some(updated) if updated != *payload => {
+                *payload = updated;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    /// 执行 on_error
+    pub fn call_error_hook(&self, ctx: &mut PluginContext, error: &str) -> Result<(), PluginError> {
+        if let Some(output) = self.call(WasmHook::Error, ctx, None, Some(error))? {
+            self.apply_context(ctx, &output);
+        }
+        Ok(())
+    }
+
+    fn apply_context(&self, ctx: &mut PluginContext, output: &WasmHookOutput) {
+        if let Some(model) = &output.model {
+            ctx.model = model.clone();
+        }
+        if let Some(metadata) = &output.metadata {
+            if self.sandbox.manifest.capabilities.flow_metadata {
+                ctx.metadata = metadata.clone();
+            }
+        }
+    }
+
+    fn call(
+        &self,
+        hook: WasmHook,
+        ctx: &PluginContext,
+        payload: Option<&serde_json::Value>,
+        error: Option<&str>,
+    ) -> Result<Option<WasmHookOutput>, PluginError> {
+        let input = WasmHookInput {
+            abi_version: WASM_ABI_VERSION,
+            hook: hook.name(),
+            ctx: WasmContextView {
+                request_id: &ctx.request_id,
+                provider: ctx.provider.to_string(),
+                model: &ctx.model,
+                timestamp: ctx.timestamp.to_rfc3339(),
+                metadata: self
+                    .sandbox
+                    .manifest
+                    .capabilities
+                    .flow_metadata
+                    .then_some(&ctx.metadata),
+            },
+            settings: &self.settings,
+            payload,
+            error,
+        };
+        let input = serde_json::to_vec(&input)?;
+
+        let compiled = self.module.read().clone();
+        let mut store = self.sandbox.new_store()?;
+        let instance = self.sandbox.instantiate(&mut store, &compiled.module)?;
+        let result = GuestMemory::from_instance(&instance, &store).and_then(|guest| {
+            let (ptr, len) = unpack(guest.write(&mut store, &input)?);
+            let func = instance.get_typed_func::<(i32, i32), i64>(&store, hook.export_name())?;
+            match func.call(&mut store, (ptr, len))? {
+                0 => Ok(None),
+                packed => {
+                    let (ptr, len) = unpack(packed);
+                    guest.read(&store, ptr, len).map(Some)
+                }
+            }
+        });
+
+        if let Err(e) = self.sandbox.kv.flush() {
+            tracing::warn!("插件 {} KV 存储写入失败: {}", self.sandbox.plugin_name, e);
+        }
+
+        let output = match result {
+            Ok(Some(bytes)) => serde_json::from_slice::<WasmHookOutput>(&bytes)
+                .map_err(|e| self.execution_error(hook, format!("输出不是有效的 JSON: {}", e)))?,
+            Ok(None) => return Ok(None),
+            Err(e) if e.as_trap_code() == Some(TrapCode::OutOfFuel) => {
+                return Err(self.execution_error(
+                    hook,
+                    format!("燃料耗尽 (上限 {})", self.sandbox.manifest.limits.fuel),
+                ));
+            }
+            Err(e) => return Err(self.execution_error(hook, e.to_string())),
+        };
+        if let Some(message) = output.error {
+            return Err(self.execution_error(hook, message));
+        }
+        Ok(Some(output))
+    }
+
+    fn execution_error(&self, hook: WasmHook, message: impl std::fmt::Display) -> PluginError {
+        PluginError::ExecutionError {
+            plugin_name: self.sandbox.plugin_name.to_string(),
+            message: format!("{}: {}", hook.name(), message),
+        }
+    }
+}
+
+fn fingerprint(wasm: &[u8]) -> [u8; 32] {
+    Sha256::digest(wasm).into()
+}
+
+/// 按能力授权链接宿主函数
+fn define_host_functions(
+    linker: &mut Linker<HostState>,
+    manifest: &WasmManifest,
+) -> Result<(), wasmi::errors::LinkerError> {
+    if manifest.capabilities.log {
+        linker.func_wrap(
+            HOST_MODULE,
+            "log",
+            |caller: Caller<'_, HostState>, level: i32, ptr: i32, len: i32| {
+                let guest = GuestMemory::from_caller(&caller)?;
+                let message = String::from_utf8_lossy(&guest.read(&caller, ptr, len)?).into_owned();
+                let name = &caller.data().plugin_name;
+                match level {
+                    0 => tracing::debug!("[插件 {}] {}", name, message),
+                    1 => tracing::info!("[插件 {}] {}", name, message),
+                    2 => tracing::warn!("[插件 {}] {}", name, message),
+                    _ => tracing::error!("[插件 {}] {}", name, message),
+                }
+                Ok(())
+            },
+        )?;
+    }
+
+    if manifest.capabilities.kv {
+        linker.func_wrap(
+            HOST_MODULE,
+            "kv_get",
+            |mut caller: Caller<'_, HostState>, kptr: i32, klen: i32| {
+                let guest = GuestMemory::from_caller(&caller)?;
+                let key = read_string(&guest, &caller, kptr, klen)?;
+                match caller.data().kv.get(&key) {
+                    Some(value) => guest.write(&mut caller, value.to_string().as_bytes()),
+                    None => Ok(0),
+                }
+            },
+        )?;
+        linker.func_wrap(
+            HOST_MODULE,
+            "kv_set",
+            |caller: Caller<'_, HostState>, kptr: i32, klen: i32, vptr: i32, vlen: i32| {
+                let guest = GuestMemory::from_caller(&caller)?;
+                let key = read_string(&guest, &caller, kptr, klen)?;
+                let Ok(value) = serde_json::from_slice(&guest.read(&caller, vptr, vlen)?) else {
+                    return Ok(-1);
+                };
+                Ok(match caller.data().kv.set(key, value) {
+                    Ok(()) => 0,
+                    Err(_) => -1,
+                })
+            },
+        )?;
+        linker.func_wrap(
+            HOST_MODULE,
+            "kv_delete",
+            |caller: Caller<'_, HostState>, kptr: i32, klen: i32| {
+                let guest = GuestMemory::from_caller(&caller)?;
+                let key = read_string(&guest, &caller, kptr, klen)?;
+                Ok(caller.data().kv.delete(&key) as i32)
+            },
+        )?;
+    }
+
+    if !manifest.capabilities.network.is_empty() {
+        linker.func_wrap(
+            HOST_MODULE,
+            "http_request",
+            |mut caller: Caller<'_, HostState>, ptr: i32, len: i32| {
+                let guest = GuestMemory::from_caller(&caller)?;
+                let request = guest.read(&caller, ptr, len)?;
+                let response = match caller.data().network.clone() {
+                    Some(network) => host_http_request(&network, &request),
+                    None => serde_json::json!({"error": "网络访问未授权"}),
+                };
+                guest.write(&mut caller, response.to_string().as_bytes())
+            },
+        )?;
+    }
+
+    Ok(())
+}
+
+fn read_string(
+    guest: &GuestMemory,
+    caller: &Caller<'_, HostState>,
+    ptr: i32,
+    len: i32,
+) -> Result<String, wasmi::Error> {
+    String::from_utf8(guest.read(caller, ptr, len)?)
+        .map_err(|_| wasmi::Error::new("字符串不是有效的 UTF-8"))
+}
+
+/// 执行模块发起的 HTTP 请求 (运行在阻塞线程中)
+fn host_http_request(network: &NetworkAccess, request: &[u8]) -> serde_json::Value {
+    let error = |message: String| serde_json::json!({ "error": message });
+
+    let request: HostHttpRequest = match serde_json::from_slice(request) {
+        Ok(request) => request,
+        Err(e) => return error(format!("请求格式无效: {}", e)),
+    };
+    let url = match url::Url::parse(&request.url) {
+        Ok(url) => url,
+        Err(e) => return error(format!("URL 无效: {}", e)),
+    };
+    if !url.host_str().is_some_and(|host| network.allows(host)) {
+        return error(format!(
+            "主机未授权: {}",
+            url.host_str().unwrap_or_default()
+        ));
+    }
+    let method = match reqwest::Method::from_bytes(request.method.to_uppercase().as_bytes()) {
+        Ok(method) => method,
+        Err(_) => return error(format!("不支持的方法: {}", request.method)),
+    };
+    let Ok(handle) = tokio::runtime::Handle::try_current() else {
+        return error("没有可用的异步运行时".to_string());
+    };
+
+    let mut builder = network.client.request(method, url);
+    for (name, value) in &request.headers {
+        builder = builder.header(name, value);
+    }
+    if let Some(body) = request.body {
+        builder = builder.body(body);
+    }
+
+    handle.block_on(async move {
+        let response = match builder.send().await {
+            Ok(response) => response,
+            // 带上底层原因，例如重定向被拒绝
+            Err(e) => {
+                let mut message = e.to_string();
+                let mut source = std::error::Error::source(&e);
+                while let Some(cause) = source {
+                    message.push_str(&format!(": {}", cause));
+                    source = cause.source();
+                }
+                return error(message);
+            }
+        };
+        let status = response.status().as_u16();
+        let headers: HashMap<String, String> = response
+            .headers()
+            .iter()
+            .filter_map(|(k, v)| Some((k.to_string(), v.to_str().ok()?.to_string())))
+            .collect();
+        match response.text().await {
+            Ok(body) => serde_json::json!({ "status": status, "headers": headers, "body": body }),
+            Err(e) => error(e.to_string()),
+        }
+    })
+}
+
+/// 由 WASM 模块驱动的 Native 插件
+pub struct WasmPlugin {
+    manifest: PluginManifest,
+    runtime: Arc<WasmRuntime>,
+    watcher: Option<RecommendedWatcher>,
+}
+
+impl WasmPlugin {
+    /// 编译模块，KV 存储保存在 `kv_path`
+    pub fn new(
+        manifest: PluginManifest,
+        wasm: &[u8],
+        settings: serde_json::Value,
+        config: &PluginConfig,
+        kv_path: Option<PathBuf>,
+    ) -> Result<Self, PluginError> {
+        let runtime = WasmRuntime::new(
+            &manifest.name,
+            manifest.wasm.clone().unwrap_or_default(),
+            wasm,
+            settings,
+            config,
+            kv_path,
+        )?;
+        Ok(Self {
+            manifest,
+            runtime: Arc::new(runtime),
+            watcher: None,
+        })
+    }
+
+    /// 监控模块文件，内容变化时热重载
+    ///
+    /// 新模块编译或校验失败时保留旧模块
+    pub fn watch(&mut self, module_path: &Path) -> Result<(), PluginError> {
+        let runtime = self.runtime.clone();
+        let target = module_path.to_path_buf();
+        let mut watcher = notify::recommended_watcher(move |res: Result<Event, notify::Error>| {
+            let event = match res {
+                Ok(event) => event,
+                Err(e) => {
+                    tracing::error!("插件文件监控错误: {:?}", e);
+                    return;
+                }
+            };
+            if !matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_))
+                || !event
+                    .paths
+                    .iter()
+                    .any(|p| p.file_name() == target.file_name())
+            {
+                return;
+            }
+            let Ok(wasm) = std::fs::read(&target) else {
+                return;
+            };
+            match runtime.reload(&wasm) {
+                Ok(true) => tracing::info!("插件 {} 已热重载", runtime.sandbox.plugin_name),
+                Ok(false) => {}
+                Err(e) => tracing::warn!("插件 {} 热重载失败: {}", runtime.sandbox.plugin_name, e),
+            }
+        })
+        .map_err(|e| PluginError::LoadError(format!("无法监控插件文件: {}", e)))?;
+
+        // 监控所在目录（某些工具会删除并重建文件）
+        let watch_dir = module_path.parent().unwrap_or(module_path);
+        watcher
+            .watch(watch_dir, RecursiveMode::NonRecursive)
+            .map_err(|e| PluginError::LoadError(format!("无法监控插件文件: {}", e)))?;
+        self.watcher = Some(watcher);
+        Ok(())
+    }
+
+    /// 在阻塞线程池中执行钩子，避免模块占用异步运行时
+    async fn run_hook<R: Send + 'static>(
+        &self,
+        hook: WasmHook,
+        call: impl FnOnce(&WasmRuntime) -> Result<R, PluginError> + Send + 'static,
+    ) -> Result<Option<R>, PluginError> {
+        if !self.runtime.has_hook(hook) {
+            return Ok(None);
+        }
+        let runtime = self.runtime.clone();
+        tokio::task::spawn_blocking(move || call(&runtime))
+            .await
+            .map_err(|e| PluginError::ExecutionError {
+                plugin_name: self.manifest.name.clone(),
+                message: format!("WASM 任务异常退出: {}", e),
+            })?
+            .map(Some)
+    }
+
+    async fn run_payload_hook(
+        &self,
+        hook: WasmHook,
+        ctx: &mut PluginContext,
+        payload: &mut serde_json::Value,
+    ) -> Result<HookResult, PluginError> {
+        let start = Instant::now();
+        let mut task_ctx = ctx.clone();
+        let mut task_payload = payload.clone();
+        let result = self
+            .run_hook(hook, move |runtime| {
+                let modified = runtime.call_payload_hook(hook, &mut task_ctx, &mut task_payload)?;
+                Ok((task_ctx, task_payload, modified))
+            })
+            .await?;
+
+        let Some((task_ctx, task_payload, modified)) = result else {
+            return Ok(HookResult::success(false, 0));
+        };
+        *ctx = task_ctx;
+        *payload = task_payload;
+        Ok(HookResult::success(
+            modified,
+            start.elapsed().as_millis() as u64,
+        ))
+    }
+}
+
+#[async_trait]
+impl Plugin for WasmPlugin {
+    fn name(&self) -> &str {
+        &self.manifest.name
+    }
+
+    fn version(&self) -> &str {
+        &self.manifest.version
+    }
+
+    fn manifest(&self) -> &PluginManifest {
+        &self.manifest
+    }
+
+    async fn init(&mut self, _config: &PluginConfig) -> Result<(), PluginError> {
+        Ok(())
+    }
+
+    async fn on_request(
+        &self,
+        ctx: &mut PluginContext,
+        request: &mut serde_json::Value,
+    ) -> Result<HookResult, PluginError> {
+        self.run_payload_hook(WasmHook::Request, ctx, request).await
+    }
+
+    async fn on_response(
+        &self,
+        ctx: &mut PluginContext,
+        response: &mut serde_json::Value,
+    ) -> Result<HookResult, PluginError> {
+        self.run_payload_hook(WasmHook::Response, ctx, response)
+            .await
+    }
+
+    async fn on_error(
+        &self,
+        ctx: &mut PluginContext,
+        error: &str,
+    ) -> Result<HookResult, PluginError> {
+        let start = Instant::now();
+        let mut task_ctx = ctx.clone();
+        let error = error.to_string();
+        let result = self
+            .run_hook(WasmHook::Error, move |runtime| {
+                runtime.call_error_hook(&mut task_ctx, &error)?;
+                Ok(task_ctx)
+            })
+            .await?;
+
+        let Some(task_ctx) = result else {
+            return Ok(HookResult::success(false, 0));
+        };
+        *ctx = task_ctx;
+        Ok(HookResult::success(
+            false,
+            start.elapsed().as_millis() as u64,
+        ))
+    }
+
+    async fn shutdown(&mut self) -> Result<(), PluginError> {
+        self.watcher = None;
+        self.runtime.sandbox.kv.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plugin::{WasmCapabilities, WasmLimits};
+    use crate::ProviderType;
+
+    /// 生成返回固定输出的测试模块
+    fn guest(imports: &str, body: &str, output: &str) -> Vec<u8> {
+        let escaped = output.replace('\\', "\\\\").replace('"', "\\\"");
+        wat::parse_str(format!(
+            r#"(module
+                {imports}
+                (memory (export "memory") 1)
+                (global $heap (mut i32) (i32.const 4096))
+                (data (i32.const 0) "{escaped}")
+                (data (i32.const 2048) "counter")
+                (func (export "proxycast_abi_version") (result i32) i32.const 1)
+                (func (export "proxycast_alloc") (param $len i32) (result i32)
+                    (local $ptr i32)
+                    global.get $heap
+                    local.set $ptr
+                    global.get $heap
+                    local.get $len
+                    i32.add
+                    global.set $heap
+                    local.get $ptr)
+                (func (export "proxycast_on_request") (param i32 i32) (result i64)
+                    {body}
+                    i64.const {len}))"#,
+            len = output.len(),
+        ))
+        .unwrap()
+    }
+
+    fn manifest(capabilities: WasmCapabilities, limits: WasmLimits) -> WasmManifest {
+        WasmManifest {
+            capabilities,
+            limits,
+        }
+    }
+
+    fn runtime(wasm: &[u8], manifest: WasmManifest) -> Result<WasmRuntime, PluginError> {
+        WasmRuntime::new(
+            "wasm-test",
+            manifest,
+            wasm,
+            serde_json::Value::Null,
+            &PluginConfig::default(),
+            None,
+        )
+    }
+
+    fn test_ctx() -> PluginContext {
+        PluginContext::new("req-1".to_string(), ProviderType::Claude, "m".to_string())
+            .with_metadata("tenant", serde_json::json!("a"))
+    }
+
+    #[test]
+    fn test_request_hook_rewrites_payload() {
+        let wasm = guest(
+            "",
+            "",
+            r#"{"payload":{"model":"wasm-model"},"model":"wasm-model","metadata":{}}"#,
+        );
+        let runtime = runtime(&wasm, WasmManifest::default()).unwrap();
+        assert!(runtime.has_hook(WasmHook::Request));
+        assert!(!runtime.has_hook(WasmHook::Response));
+
+        let mut ctx = test_ctx();
+        let mut request = serde_json::json!({"model": "claude"});
+        let modified = runtime
+            .call_payload_hook(WasmHook::Request, &mut ctx, &mut request)
+            .unwrap();
+        assert!(modified);
+        assert_eq!(request["model"], "wasm-model");
+        assert_eq!(ctx.model, "wasm-model");
+        // 未授权 flow_metadata 时不能修改元数据
+        assert_eq!(ctx.get_metadata("tenant").unwrap(), "a");
+    }
+
+    #[test]
+    fn test_capabilities_gate_host_imports() {
+        let imports =
+            r#"(import "proxycast" "kv_set" (func $kv_set (param i32 i32 i32 i32) (result i32)))"#;
+        let body = "i32.const 2048 i32.const 7 i32.const 0 i32.const 2 call $kv_set drop";
+        let wasm = guest(imports, body, "{}");
+
+        let err = runtime(&wasm, WasmManifest::default()).err().unwrap();
+        assert!(err.to_string().contains("kv"));
+
+        let runtime = runtime(
+            &wasm,
+            manifest(
+                WasmCapabilities {
+                    kv: true,
+                    ..Default::default()
+                },
+                WasmLimits::default(),
+            ),
+        )
+        .unwrap();
+        runtime
+            .call_payload_hook(
+                WasmHook::Request,
+                &mut test_ctx(),
+                &mut serde_json::json!({}),
+            )
+            .unwrap();
+        assert_eq!(
+            runtime.sandbox.kv.get("counter"),
+            Some(serde_json::json!({}))
+        );
+    }
+
+    #[test]
+    fn test_fuel_and_memory_limits() {
+        let wasm = guest("", "(loop $spin br $spin)", "{}");
+        let runtime = runtime(
+            &wasm,
+            manifest(
+                WasmCapabilities::default(),
+                WasmLimits {
+                    fuel: 100_000,
+                    ..Default::default()
+                },
+            ),
+        )
+        .unwrap();
+        let err = runtime
+            .call_payload_hook(
+                WasmHook::Request,
+                &mut test_ctx(),
+                &mut serde_json::json!({}),
+            )
+            .unwrap_err();
+        assert!(err.to_string().contains("燃料耗尽"));
+
+        let wasm = guest("", "", "{}");
+        let err = WasmRuntime::new(
+            "wasm-test",
+            manifest(
+                WasmCapabilities::default(),
+                WasmLimits {
+                    max_memory_bytes: 1024,
+                    ..Default::default()
+                },
+            ),
+            &wasm,
+            serde_json::Value::Null,
+            &PluginConfig::default(),
+            None,
+        );
+        assert!(matches!(err, Err(PluginError::LoadError(_))));
+    }
+
+    #[test]
+    fn test_rejects_incompatible_abi_and_guest_errors() {
+        let wasm = wat::parse_str(
+            r#"(module
+                (memory (export "memory") 1)
+                (func (export "proxycast_abi_version") (result i32) i32.const 99)
+                (func (export "proxycast_alloc") (param i32) (result i32) i32.const 0))"#,
+        )
+        .unwrap();
+        let err = runtime(&wasm, WasmManifest::default()).err().unwrap();
+        assert!(err.to_string().contains("ABI"));
+
+        let wasm = guest("", "", r#"{"error":"blocked"}"#);
+        let runtime = runtime(&wasm, WasmManifest::default()).unwrap();
+        let err = runtime
+            .call_payload_hook(
+                WasmHook::Request,
+                &mut test_ctx(),
+                &mut serde_json::json!({}),
+            )
+            .unwrap_err();
+        assert!(err.to_string().contains("blocked"));
+    }
+
+    #[test]
+    fn test_network_allowlist() {
+        let network = NetworkAccess::new(
+            vec!["api.example.com".to_string(), "*.corp.io".to_string()],
+            Duration::from_secs(5),
+        )
+        .unwrap();
+        assert!(network.allows("api.example.com"));
+        assert!(network.allows("a.corp.io"));
+        assert!(!network.allows("corp.io"));
+        assert!(!network.allows("evilcorp.io"));
+        assert!(!network.allows("example.com"));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_redirects_are_checked_against_allowlist() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        // 本地服务：/hop 重定向到授权主机，/escape 重定向到未授权的 localhost
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut buf = [0u8; 1024];
+                let n = socket.read(&mut buf).await.unwrap_or(0);
+                let request = String::from_utf8_lossy(&buf[..n]);
+                let path = request.split_whitespace().nth(1).unwrap_or("/");
+                let response = match path {
+                    "/hop" => format!(
+                        "HTTP/1.1 302 Found\r\nLocation: http://127.0.0.1:{}/ok\r\nContent-Length: 0\r\n\r\n",
+                        port
+                    ),
+                    "/escape" => format!(
+                        "HTTP/1.1 302 Found\r\nLocation: http://localhost:{}/ok\r\nContent-Length: 0\r\n\r\n",
+                        port
+                    ),
+                    _ => "HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok".to_string(),
+                };
+                let _ = socket.write_all(response.as_bytes()).await;
+            }
+        });
+
+        let network =
+            NetworkAccess::new(vec!["127.0.0.1".to_string()], Duration::from_secs(5)).unwrap();
+        let fetch = |path: &str| {
+            let network = network.clone();
+            let request =
+                serde_json::json!({ "url": format!("http://127.0.0.1:{}{}", port, path) });
+            tokio::task::spawn_blocking(move || {
+                host_http_request(&network, request.to_string().as_bytes())
+            })
+        };
+
+        let allowed = fetch("/hop").await.unwrap();
+        assert_eq!(allowed["status"], 200);
+        assert_eq!(allowed["body"], "ok");
+
+        let escaped = fetch("/escape").await.unwrap();
+        assert!(escaped["error"]
+            .as_str()
+            .is_some_and(|e| e.contains("localhost")));
+    }
+
+    #[tokio::test]
+    async fn test_hot_reload_on_file_change() {
+        let dir = tempfile::tempdir().unwrap();
+        let module_path = dir.path().join("plugin.wasm");
+        std::fs::write(&module_path, guest("", "", r#"{"model":"v1"}"#)).unwrap();
+
+        let plugin_manifest: PluginManifest = serde_json::from_value(serde_json::json!({
+            "name": "wasm-reload",
+            "version": "0.1.0",
+            "entry": "plugin.wasm",
+            "plugin_type": "native"
+        }))
+        .unwrap();
+        let mut plugin = WasmPlugin::new(
+            plugin_manifest,
+            &std::fs::read(&module_path).unwrap(),
+            serde_json::Value::Null,
+            &PluginConfig::default(),
+            None,
+        )
+        .unwrap();
+        plugin.watch(&module_path).unwrap();
+
+        let mut ctx = test_ctx();
+        plugin
+            .on_request(&mut ctx, &mut serde_json::json!({}))
+            .await
+            .unwrap();
+        assert_eq!(ctx.model, "v1");
+
+        std::fs::write(&module_path, guest("", "", r#"{"model":"v2"}"#)).unwrap();
+        for _ in 0..50 {
+            plugin
+                .on_request(&mut ctx, &mut serde_json::json!({}))
+                .await
+                .unwrap();
+            if ctx.model == "v2" {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        assert_eq!(ctx.model, "v2");
+    }
+}
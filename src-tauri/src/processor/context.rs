@@ -2,7 +2,7 @@
 //!
 //! 定义请求处理过程中的上下文信息
 
-use crate::plugin::PluginContext;
+use crate::plugin::{HookAction, PluginContext};
 use crate::ProviderType;
 use chrono::{DateTime, Utc};
 use std::time::Instant;
@@ -32,6 +32,8 @@ pub struct RequestContext {
     pub is_stream: bool,
     /// 插件上下文
     pub plugin_ctx: Option<PluginContext>,
+    /// 插件返回的短路动作（合成响应或拒绝）
+    pub plugin_action: Option<HookAction>,
     /// 元数据
     pub metadata: std::collections::HashMap<String, serde_json::Value>,
 }
@@ -51,6 +53,7 @@ impl RequestContext {
             retry_count: 0,
             is_stream: false,
             plugin_ctx: None,
+            plugin_action: None,
             metadata: std::collections::HashMap::new(),
         }
     }
@@ -100,6 +103,11 @@ impl RequestContext {
         self.plugin_ctx.as_mut()
     }
 
+    /// 取出插件短路动作
+    pub fn take_plugin_action(&mut self) -> Option<HookAction> {
+        self.plugin_action.take()
+    }
+
     /// 添加元数据
     pub fn set_metadata(&mut self, key: &str, value: serde_json::Value) {
         self.metadata.insert(key.to_string(), value);
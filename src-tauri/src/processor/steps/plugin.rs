@@ -3,7 +3,7 @@
 //! 执行插件的前置和后置钩子
 
 use super::traits::{PipelineStep, StepError};
-use crate::plugin::PluginManager;
+use crate::plugin::{PluginContext, PluginManager, PluginStreamSession};
 use crate::processor::RequestContext;
 use crate::ProviderType;
 use async_trait::async_trait;
@@ -11,7 +11,7 @@ use std::sync::Arc;
 
 /// 插件前置钩子步骤
 ///
-/// 在 Provider 调用前执行所有启用插件的 on_request 钩子
+/// 在 Provider 路由之后、调用之前执行所有启用插件的 on_request 钩子
 pub struct PluginPreStep {
     /// 插件管理器
     plugins: Arc<PluginManager>,
@@ -31,8 +31,10 @@ impl PipelineStep for PluginPreStep {
         ctx: &mut RequestContext,
         payload: &mut serde_json::Value,
     ) -> Result<(), StepError> {
-        // 初始化插件上下文
-        let provider = ctx.provider.unwrap_or(ProviderType::Kiro);
+        // 初始化插件上下文，插件需要看到路由选中的 Provider
+        let provider = ctx.provider.ok_or_else(|| {
+            StepError::Routing("插件前置钩子需要在 Provider 路由之后执行".to_string())
+        })?;
         ctx.init_plugin_context(provider);
 
         // 获取插件上下文的可变引用
@@ -47,6 +49,11 @@ impl PipelineStep for PluginPreStep {
                 }
             }
 
+            // 插件短路：由处理器直接返回合成响应或拒绝
+            if let Some(action) = results.iter().find_map(|r| r.action.clone()) {
+                ctx.plugin_action = Some(action);
+            }
+
             // 记录插件执行结果到元数据
             ctx.set_metadata(
                 "plugin_pre_results",
@@ -71,7 +78,8 @@ impl PipelineStep for PluginPreStep {
 
 /// 插件后置钩子步骤
 ///
-/// 在 Provider 调用后执行所有启用插件的 on_response 钩子
+/// 在 Provider 调用后执行所有启用插件的 on_response 钩子；
+/// 流式响应通过 `stream_session` 创建的会话逐事件执行流式钩子
 pub struct PluginPostStep {
     /// 插件管理器
     plugins: Arc<PluginManager>,
@@ -95,6 +103,18 @@ impl PluginPostStep {
             }
         }
     }
+
+    /// 创建流式钩子会话，沿用前置步骤初始化的插件上下文
+    pub async fn stream_session(&self, ctx: &RequestContext) -> PluginStreamSession {
+        let plugin_ctx = ctx.plugin_ctx.clone().unwrap_or_else(|| {
+            PluginContext::new(
+                ctx.request_id.clone(),
+                ctx.provider.unwrap_or(ProviderType::Kiro),
+                ctx.resolved_model.clone(),
+            )
+        });
+        self.plugins.stream_session(plugin_ctx).await
+    }
 }
 
 #[async_trait]
@@ -114,6 +134,11 @@ impl PipelineStep for PluginPostStep {
                 }
             }
 
+            // 插件短路：由处理器直接返回合成响应或拒绝
+            if let Some(action) = results.iter().find_map(|r| r.action.clone()) {
+                ctx.plugin_action = Some(action);
+            }
+
             // 记录插件执行结果到元数据
             ctx.set_metadata(
                 "plugin_post_results",
@@ -154,6 +179,19 @@ mod tests {
         assert!(ctx.plugin_ctx.is_some());
     }
 
+    #[tokio::test]
+    async fn test_plugin_pre_step_requires_routing() {
+        let plugins = Arc::new(PluginManager::with_defaults());
+        let step = PluginPreStep::new(plugins);
+
+        let mut ctx = RequestContext::new("model".to_string());
+        let mut payload = serde_json::json!({"model": "model"});
+
+        let result = step.execute(&mut ctx, &mut payload).await;
+        assert!(matches!(result, Err(StepError::Routing(_))));
+        assert!(ctx.plugin_ctx.is_none());
+    }
+
     #[tokio::test]
     async fn test_plugin_post_step_execute() {
         let plugins = Arc::new(PluginManager::with_defaults());
@@ -167,4 +205,17 @@ mod tests {
         let result = step.execute(&mut ctx, &mut payload).await;
         assert!(result.is_ok());
     }
+
+    #[tokio::test]
+    async fn test_plugin_post_step_stream_session() {
+        let plugins = Arc::new(PluginManager::with_defaults());
+        let step = PluginPostStep::new(plugins);
+
+        let mut ctx = RequestContext::new("model".to_string()).with_stream(true);
+        ctx.init_plugin_context(ProviderType::Kiro);
+
+        let session = step.stream_session(&ctx).await;
+        assert!(session.is_empty());
+        assert_eq!(session.context().request_id, ctx.request_id);
+    }
 }
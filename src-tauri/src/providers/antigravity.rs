@@ -449,6 +449,58 @@ impl AntigravityProvider {
         Err(last_error.unwrap_or_else(|| "All Antigravity base URLs failed".into()))
     }
 
+    /// 调用流式 API（`streamGenerateContent`），支持多环境降级
+    ///
+    /// `body` 为已包装的原生请求，返回未解析的 SSE 响应
+    pub async fn call_api_stream_raw(
+        &self,
+        body: &serde_json::Value,
+    ) -> Result<reqwest::Response, Box<dyn Error + Send + Sync>> {
+        let token = self
+            .credentials
+            .access_token
+            .as_ref()
+            .ok_or("No access token")?;
+
+        let mut last_error: Option<Box<dyn Error + Send + Sync>> = None;
+        for base_url in &self.base_urls {
+            let url = format!(
+                "{}/{ANTIGRAVITY_API_VERSION}:streamGenerateContent?alt=sse",
+                base_url
+            );
+            let result = self
+                .client
+                .post(&url)
+                .header("Authorization", format!("Bearer {token}"))
+                .header("Content-Type", "application/json")
+                .header("Accept", "text/event-stream")
+                .header("User-Agent", "antigravity/1.11.5 windows/amd64")
+                .json(body)
+                .send()
+                .await;
+            match result {
+                Ok(resp) if resp.status().is_success() => return Ok(resp),
+                Ok(resp) => {
+                    let status = resp.status();
+                    let body = resp.text().await.unwrap_or_default();
+                    tracing::warn!(
+                        "[Antigravity] Failed on {}: {} - {}",
+                        base_url,
+                        status,
+                        body
+                    );
+                    last_error = Some(format!("API call failed: {status} - {body}").into());
+                }
+                Err(e) => {
+                    tracing::warn!("[Antigravity] Failed on {}: {}", base_url, e);
+                    last_error = Some(e.into());
+                }
+            }
+        }
+
+        Err(last_error.unwrap_or_else(|| "All Antigravity base URLs failed".into()))
+    }
+
     /// 发现项目 ID
     pub async fn discover_project(&mut self) -> Result<String, Box<dyn Error + Send + Sync>> {
         if let Some(ref project_id) = self.project_id {
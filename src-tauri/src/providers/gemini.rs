@@ -401,6 +401,38 @@ impl GeminiProvider {
         Ok(data)
     }
 
+    /// 调用流式 API，返回未解析的 SSE 响应（分块带 `response` 包装）
+    pub async fn call_api_stream(
+        &self,
+        action: &str,
+        body: &serde_json::Value,
+    ) -> Result<reqwest::Response, Box<dyn Error + Send + Sync>> {
+        let token = self
+            .credentials
+            .access_token
+            .as_ref()
+            .ok_or("No access token")?;
+
+        let url = format!("{}?alt=sse", self.get_api_url(action));
+
+        let resp = self
+            .client
+            .post(&url)
+            .header("Authorization", format!("Bearer {token}"))
+            .header("Content-Type", "application/json")
+            .json(body)
+            .send()
+            .await?;
+
+        if !resp.status().is_success() {
+            let status = resp.status();
+            let body = resp.text().await.unwrap_or_default();
+            return Err(format!("API call failed: {status} - {body}").into());
+        }
+
+        Ok(resp)
+    }
+
     pub async fn discover_project(&mut self) -> Result<String, Box<dyn Error + Send + Sync>> {
         if let Some(ref project_id) = self.project_id {
             return Ok(project_id.clone());
@@ -0,0 +1,453 @@
+//! 原生 Agent HTTP API
+//!
+//! 通过 API Server 暴露原生 Agent：创建会话、发送消息（SSE 流式返回文本和工具事件）、
+//! 查询与答复工具审批、获取会话历史。工具在服务端执行，所有端点使用服务器 API Key 认证。
+//! 会话和审批端点只能访问通过 API 创建的会话，不会暴露桌面端会话。
+//!
+//! 端点默认关闭，需要在配置中设置 `agent_api.enabled: true`。
+
+use axum::{
+    body::Body,
+    extract::{Path, Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+use crate::agent::{
+    AgentSession, AgentSessionSummary, ApprovalDecision, ImageData, NativeAgentState,
+    NativeChatRequest, PendingApproval, ProviderType, StreamEvent, ToolLoopEngine,
+};
+use crate::server::AppState;
+
+use super::verify_api_key;
+
+type AgentApiError = (StatusCode, Json<serde_json::Value>);
+
+fn agent_api_error(status: StatusCode, message: impl Into<String>) -> AgentApiError {
+    (status, Json(json!({"error": {"message": message.into()}})))
+}
+
+/// 创建会话请求
+#[derive(Debug, Default, Deserialize)]
+pub struct CreateAgentSessionRequest {
+    #[serde(default)]
+    pub model: Option<String>,
+    #[serde(default)]
+    pub system_prompt: Option<String>,
+}
+
+/// 创建会话响应
+#[derive(Debug, Serialize)]
+pub struct CreateAgentSessionResponse {
+    pub session_id: String,
+}
+
+/// 会话列表查询参数
+#[derive(Debug, Default, Deserialize)]
+pub struct ListAgentSessionsQuery {
+    #[serde(default)]
+    pub include_archived: bool,
+}
+
+/// 发送消息请求
+#[derive(Debug, Deserialize)]
+pub struct AgentMessageRequest {
+    pub message: String,
+    #[serde(default)]
+    pub model: Option<String>,
+    #[serde(default)]
+    pub images: Option<Vec<ImageData>>,
+}
+
+/// 待审批列表查询参数
+#[derive(Debug, Default, Deserialize)]
+pub struct PendingApprovalsQuery {
+    #[serde(default)]
+    pub session_id: Option<String>,
+}
+
+/// 配置中是否启用了 Agent API（没有配置管理器时视为未启用）
+fn agent_api_enabled(state: &AppState) -> bool {
+    state
+        .hot_reload_manager
+        .as_ref()
+        .is_some_and(|manager| manager.config_ref().read().agent_api.enabled)
+}
+
+/// 获取 Agent 状态，未初始化时使用本服务器地址和 API Key 自动初始化
+///
+/// 配置未启用 `agent_api.enabled` 时返回 404，不会初始化 Agent。
+async fn ready_agent(state: &AppState) -> Result<NativeAgentState, AgentApiError> {
+    if !agent_api_enabled(state) {
+        return Err(agent_api_error(
+            StatusCode::NOT_FOUND,
+            "原生 Agent API 未启用（配置 agent_api.enabled）",
+        ));
+    }
+    let agent = state
+        .native_agent
+        .clone()
+        .ok_or_else(|| agent_api_error(StatusCode::SERVICE_UNAVAILABLE, "原生 Agent 不可用"))?;
+
+    if !agent.is_initialized() {
+        let default_provider = state.default_provider.read().await.clone();
+        let provider_type = ProviderType::from_str(&default_provider);
+        agent
+            .init(state.base_url.clone(), state.api_key.clone(), provider_type)
+            .map_err(|e| agent_api_error(StatusCode::INTERNAL_SERVER_ERROR, e))?;
+
+        // MCP 连接失败不影响内置工具
+        if let Some(db) = &state.db {
+            if let Err(e) = agent.connect_mcp_servers(db).await {
+                tracing::warn!("[AGENT_API] 读取 MCP 服务器配置失败: {}", e);
+            }
+        }
+    }
+
+    // 使用最新的审批、上下文压缩和 bash 隔离配置
+    if let Some(manager) = &state.hot_reload_manager {
+        let config = manager.config();
+        agent.approvals().set_settings(config.tool_approval);
+        agent.compactor().set_settings(config.context_compaction);
+        agent.bash_sandbox().set_settings(config.bash_sandbox);
+    }
+
+    Ok(agent)
+}
+
+/// 确认会话通过 API 创建，其他会话（如桌面端会话）按不存在处理
+fn ensure_api_session(agent: &NativeAgentState, session_id: &str) -> Result<(), AgentApiError> {
+    if agent.is_api_session(session_id) {
+        Ok(())
+    } else {
+        Err(agent_api_error(
+            StatusCode::NOT_FOUND,
+            format!("会话不存在: {}", session_id),
+        ))
+    }
+}
+
+/// 加载通过 API 创建的会话
+fn load_api_session(
+    agent: &NativeAgentState,
+    session_id: &str,
+) -> Result<AgentSession, AgentApiError> {
+    ensure_api_session(agent, session_id)?;
+    agent
+        .get_session(session_id)
+        .map_err(|e| agent_api_error(StatusCode::INTERNAL_SERVER_ERROR, e))?
+        .ok_or_else(|| {
+            agent_api_error(StatusCode::NOT_FOUND, format!("会话不存在: {}", session_id))
+        })
+}
+
+/// 列出通过 API 创建的会话摘要
+fn list_api_sessions(
+    agent: &NativeAgentState,
+    include_archived: bool,
+) -> Result<Vec<AgentSessionSummary>, AgentApiError> {
+    let sessions = agent
+        .list_sessions(include_archived)
+        .map_err(|e| agent_api_error(StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    Ok(sessions
+        .into_iter()
+        .filter(|s| agent.is_api_session(&s.id))
+        .collect())
+}
+
+/// 将流式事件编码为 SSE 帧，事件名与 JSON 中的 `type` 字段一致
+pub fn format_agent_sse_event(event: &StreamEvent) -> String {
+    let data = serde_json::to_value(event)
+        .unwrap_or_else(|e| json!({"type": "error", "message": format!("事件序列化失败: {}", e)}));
+    let name = data
+        .get("type")
+        .and_then(|t| t.as_str())
+        .unwrap_or("message")
+        .to_string();
+    format!("event: {}\ndata: {}\n\n", name, data)
+}
+
+/// 对话任务句柄，被丢弃时（如客户端断开、响应流结束）中止任务
+struct AbortOnDrop<T>(JoinHandle<T>);
+
+impl<T> Drop for AbortOnDrop<T> {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+/// POST /v1/agent/sessions - 创建会话
+pub async fn agent_create_session(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    body: Option<Json<CreateAgentSessionRequest>>,
+) -> Result<Json<CreateAgentSessionResponse>, AgentApiError> {
+    verify_api_key(&headers, &state.api_key).await?;
+    let agent = ready_agent(&state).await?;
+    let Json(request) = body.unwrap_or_default();
+
+    let session_id = agent
+        .create_api_session(request.model, request.system_prompt)
+        .map_err(|e| agent_api_error(StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    tracing::info!("[AGENT_API] 创建会话: {}", session_id);
+
+    Ok(Json(CreateAgentSessionResponse { session_id }))
+}
+
+/// GET /v1/agent/sessions - 列出 API 会话摘要
+pub async fn agent_list_sessions(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<ListAgentSessionsQuery>,
+) -> Result<Json<Vec<AgentSessionSummary>>, AgentApiError> {
+    verify_api_key(&headers, &state.api_key).await?;
+    let agent = ready_agent(&state).await?;
+
+    list_api_sessions(&agent, query.include_archived).map(Json)
+}
+
+/// GET /v1/agent/sessions/:session_id - 获取会话及消息历史
+pub async fn agent_get_session(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(session_id): Path<String>,
+) -> Result<Json<AgentSession>, AgentApiError> {
+    verify_api_key(&headers, &state.api_key).await?;
+    let agent = ready_agent(&state).await?;
+
+    load_api_session(&agent, &session_id).map(Json)
+}
+
+/// POST /v1/agent/sessions/:session_id/messages - 发送消息，以 SSE 流式返回 Agent 事件
+///
+/// 工具在服务端执行；需要审批的调用会先推送 `approval_request` 事件，
+/// 客户端通过 `POST /v1/agent/approvals/:request_id` 答复后继续执行。
+pub async fn agent_send_message(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(session_id): Path<String>,
+    Json(request): Json<AgentMessageRequest>,
+) -> Response {
+    if let Err(e) = verify_api_key(&headers, &state.api_key).await {
+        return e.into_response();
+    }
+    let agent = match ready_agent(&state).await {
+        Ok(agent) => agent,
+        Err(e) => return e.into_response(),
+    };
+    if let Err(e) = load_api_session(&agent, &session_id) {
+        return e.into_response();
+    }
+    let tool_registry = match agent.get_tool_registry() {
+        Ok(registry) => registry,
+        Err(e) => return agent_api_error(StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    };
+
+    tracing::info!(
+        "[AGENT_API] 发送消息: session={}, message_len={}, model={:?}",
+        session_id,
+        request.message.len(),
+        request.model
+    );
+
+    // 远程会话中未命中审批规则的修改类调用必须由客户端确认
+    let engine = ToolLoopEngine::new(tool_registry)
+        .with_approvals(agent.approvals(), Some(session_id.clone()))
+        .require_rule_for_writes();
+    let chat_request = NativeChatRequest {
+        session_id: Some(session_id),
+        message: request.message,
+        model: request.model,
+        images: request.images,
+        stream: true,
+    };
+
+    let (tx, mut rx) = mpsc::channel::<StreamEvent>(100);
+    // 任务归响应流所有：客户端断开时流被丢弃，工具循环随之中止
+    let mut chat_task = AbortOnDrop(tokio::spawn(async move {
+        agent
+            .chat_stream_with_tools(chat_request, tx, &engine)
+            .await
+    }));
+
+    let body_stream = async_stream::stream! {
+        let mut errored = false;
+        // 工具循环可能在 done 之后继续，直到 channel 关闭才结束
+        while let Some(event) = rx.recv().await {
+            errored = matches!(event, StreamEvent::Error { .. });
+            yield Ok::<axum::body::Bytes, std::io::Error>(format_agent_sse_event(&event).into());
+            if errored {
+                break;
+            }
+        }
+
+        if !errored {
+            let failure = match (&mut chat_task.0).await {
+                Ok(Ok(_)) => None,
+                Ok(Err(e)) => Some(e),
+                Err(e) => Some(e.to_string()),
+            };
+            if let Some(message) = failure {
+                tracing::error!("[AGENT_API] 对话失败: {}", message);
+                yield Ok(format_agent_sse_event(&StreamEvent::Error { message }).into());
+            }
+        }
+    };
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, "text/event-stream")
+        .header(header::CACHE_CONTROL, "no-cache")
+        .header(header::CONNECTION, "keep-alive")
+        .header("X-Accel-Buffering", "no")
+        .body(Body::from_stream(body_stream))
+        .unwrap_or_else(|_| {
+            agent_api_error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to build stream response",
+            )
+            .into_response()
+        })
+}
+
+/// GET /v1/agent/approvals - 列出 API 会话中等待审批的工具调用，可按会话过滤
+pub async fn agent_pending_approvals(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<PendingApprovalsQuery>,
+) -> Result<Json<Vec<PendingApproval>>, AgentApiError> {
+    verify_api_key(&headers, &state.api_key).await?;
+    let agent = ready_agent(&state).await?;
+
+    let pending = agent
+        .approvals()
+        .pending()
+        .into_iter()
+        .filter(|p| query.session_id.is_none() || p.session_id == query.session_id)
+        .filter(|p| {
+            p.session_id
+                .as_deref()
+                .is_some_and(|id| agent.is_api_session(id))
+        })
+        .collect();
+    Ok(Json(pending))
+}
+
+/// POST /v1/agent/approvals/:request_id - 答复 API 会话中的工具调用审批
+///
+/// 其他会话的请求按不存在处理，返回 404。
+pub async fn agent_resolve_approval(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(request_id): Path<String>,
+    Json(decision): Json<ApprovalDecision>,
+) -> Result<StatusCode, AgentApiError> {
+    verify_api_key(&headers, &state.api_key).await?;
+    let agent = ready_agent(&state).await?;
+
+    let owned_by_api = agent
+        .approvals()
+        .pending()
+        .into_iter()
+        .find(|p| p.request_id == request_id)
+        .and_then(|p| p.session_id)
+        .is_some_and(|id| agent.is_api_session(&id));
+    if !owned_by_api {
+        return Err(agent_api_error(
+            StatusCode::NOT_FOUND,
+            format!("审批请求不存在或已处理: {}", request_id),
+        ));
+    }
+
+    agent
+        .approvals()
+        .resolve(&request_id, decision)
+        .map_err(|e| agent_api_error(StatusCode::NOT_FOUND, e))?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::ToolExecutionResult;
+
+    #[test]
+    fn test_format_agent_sse_event() {
+        let frame = format_agent_sse_event(&StreamEvent::ToolEnd {
+            tool_id: "call_1".to_string(),
+            result: ToolExecutionResult::success("ok"),
+        });
+        assert!(frame.starts_with("event: tool_end\ndata: "));
+        assert!(frame.ends_with("\n\n"));
+        let data: serde_json::Value = serde_json::from_str(
+            frame
+                .trim_end()
+                .split_once("data: ")
+                .map(|(_, d)| d)
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(data["type"], "tool_end");
+        assert_eq!(data["tool_id"], "call_1");
+        assert_eq!(data["result"]["output"], "ok");
+    }
+
+    #[test]
+    fn test_only_api_sessions_are_accessible() {
+        let agent = NativeAgentState::new();
+        agent
+            .init(
+                "http://127.0.0.1:8999".to_string(),
+                "test-key".to_string(),
+                ProviderType::Claude,
+            )
+            .unwrap();
+
+        let api_session = agent.create_api_session(None, None).unwrap();
+        let desktop_session = agent.create_session(None, None).unwrap();
+        assert!(ensure_api_session(&agent, &api_session).is_ok());
+        let (status, _) = ensure_api_session(&agent, &desktop_session).unwrap_err();
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        // 列表和历史同样不暴露桌面端会话
+        let listed: Vec<_> = list_api_sessions(&agent, true)
+            .unwrap()
+            .into_iter()
+            .map(|s| s.id)
+            .collect();
+        assert_eq!(listed, vec![api_session.clone()]);
+        assert!(load_api_session(&agent, &api_session).is_ok());
+        let (status, _) = load_api_session(&agent, &desktop_session).unwrap_err();
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        assert!(agent.delete_session(&api_session));
+        assert!(ensure_api_session(&agent, &api_session).is_err());
+        assert!(list_api_sessions(&agent, true).unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_abort_on_drop() {
+        let (tx, rx) = tokio::sync::oneshot::channel::<()>();
+        let task = AbortOnDrop(tokio::spawn(async move {
+            let _tx = tx;
+            std::future::pending::<()>().await
+        }));
+        drop(task);
+        // 任务中止后持有的发送端被释放
+        assert!(rx.await.is_err());
+    }
+
+    #[test]
+    fn test_message_request_defaults() {
+        let request: AgentMessageRequest =
+            serde_json::from_str(r#"{"message": "列出当前目录"}"#).unwrap();
+        assert_eq!(request.message, "列出当前目录");
+        assert!(request.model.is_none());
+        assert!(request.images.is_none());
+    }
+}
@@ -25,7 +25,6 @@ use axum::{
 use chrono::Utc;
 use std::collections::HashMap;
 
-use crate::converter::anthropic_to_openai::convert_anthropic_to_openai;
 use crate::flow_monitor::{
     ClientInfo, FlowError, FlowErrorType, FlowMetadata, FlowType, InterceptAction, InterceptType,
     LLMFlow, LLMRequest, LLMResponse, Message, MessageContent, MessageRole, RequestParameters,
@@ -33,16 +32,19 @@ use crate::flow_monitor::{
 };
 use crate::models::anthropic::AnthropicMessagesRequest;
 use crate::models::openai::ChatCompletionRequest;
+use crate::plugin::{HookAction, ResponseFormat};
 use crate::processor::RequestContext;
 use crate::server::client_detector::ClientType;
 use crate::server::{record_request_telemetry, record_token_usage, AppState};
 use crate::server_utils::{
-    build_anthropic_response, build_anthropic_stream_response, message_content_len,
-    parse_cw_response, safe_truncate,
+    build_anthropic_response, build_anthropic_stream_response, build_plugin_action_response,
+    message_content_len, parse_cw_response, safe_truncate,
 };
 use crate::streaming::StreamFormat as StreamingFormat;
+use crate::translator::canonical;
 use crate::ProviderType;
 
+use super::plugin_hooks::{run_plugin_request_hooks, run_plugin_response_hooks};
 use super::{call_provider_anthropic, call_provider_openai};
 
 // ============================================================================
@@ -329,6 +331,43 @@ fn build_llm_response(status_code: u16, content: &str, usage: Option<(u32, u32)>
     }
 }
 
+/// 将插件短路的请求记录为已完成或失败的 Flow
+async fn record_plugin_short_circuit_flow(
+    state: &AppState,
+    action: &HookAction,
+    llm_request: LLMRequest,
+    flow_metadata: FlowMetadata,
+) {
+    let Some(flow_id) = state
+        .flow_monitor
+        .start_flow(llm_request, flow_metadata)
+        .await
+    else {
+        return;
+    };
+    match action {
+        HookAction::Respond(response) => {
+            let llm_response = build_llm_response(
+                200,
+                &response.content,
+                Some((response.input_tokens, response.output_tokens)),
+            );
+            state
+                .flow_monitor
+                .complete_flow(&flow_id, Some(llm_response))
+                .await;
+        }
+        HookAction::Reject(rejection) => {
+            let error = FlowError::new(
+                FlowErrorType::from_status_code(rejection.status_code()),
+                &format!("插件拒绝请求: {}", rejection.message),
+            )
+            .with_status_code(rejection.status_code());
+            state.flow_monitor.fail_flow(&flow_id, error).await;
+        }
+    }
+}
+
 // ============================================================================
 // Provider 选择辅助函数
 // ============================================================================
@@ -345,7 +384,10 @@ fn build_llm_response(status_code: u16, content: &str, usage: Option<(u32, u32)>
 ///
 /// # 返回
 /// 选择的 Provider 名称和检测到的客户端类型
-async fn select_provider_for_client(headers: &HeaderMap, state: &AppState) -> (String, ClientType) {
+pub(crate) async fn select_provider_for_client(
+    headers: &HeaderMap,
+    state: &AppState,
+) -> (String, ClientType) {
     // 从 User-Agent 检测客户端类型
     let user_agent = headers
         .get("user-agent")
@@ -539,6 +581,35 @@ async fn check_response_intercept(
     }
 }
 
+/// 对发往上游的请求应用数据防泄漏脱敏
+///
+/// 仅在 Flow Monitor 启用捕获时脱敏且开启 `apply_to_outbound` 时生效。
+/// 脱敏后的请求体无法反序列化时保持原请求不变。
+async fn apply_outbound_redaction<T>(state: &AppState, flow_id: Option<&str>, request: &mut T)
+where
+    T: serde::Serialize + serde::de::DeserializeOwned,
+{
+    let body = match serde_json::to_value(&*request) {
+        Ok(body) => body,
+        Err(_) => return,
+    };
+
+    if let Some(scrubbed) = state.flow_monitor.scrub_outbound_body(flow_id, &body).await {
+        match serde_json::from_value(scrubbed) {
+            Ok(updated) => {
+                *request = updated;
+                state.logs.write().await.add(
+                    "info",
+                    &format!("[DLP] 出站请求已脱敏: flow_id={:?}", flow_id),
+                );
+            }
+            Err(e) => {
+                tracing::warn!("[DLP] 脱敏后的请求体无法解析，保持原请求: {}", e);
+            }
+        }
+    }
+}
+
 // ============================================================================
 // API Key 验证
 // ============================================================================
@@ -620,7 +691,7 @@ pub async fn verify_api_key_anthropic(
 pub async fn chat_completions(
     State(state): State<AppState>,
     headers: HeaderMap,
-    Json(mut request): Json<ChatCompletionRequest>,
+    Json(request): Json<ChatCompletionRequest>,
 ) -> Response {
     if let Err(e) = verify_api_key(&headers, &state.api_key).await {
         state
@@ -633,7 +704,19 @@ pub async fn chat_completions(
 
     // 创建请求上下文
     let mut ctx = RequestContext::new(request.model.clone()).with_stream(request.stream);
+    let response = process_chat_completions(&state, &headers, &mut ctx, request).await;
+
+    // 执行插件响应钩子
+    run_plugin_response_hooks(&state, &mut ctx, response, ResponseFormat::OpenAi).await
+}
 
+/// 处理 OpenAI 格式请求：路由、插件请求钩子、Flow 捕获与 Provider 调用
+async fn process_chat_completions(
+    state: &AppState,
+    headers: &HeaderMap,
+    ctx: &mut RequestContext,
+    mut request: ChatCompletionRequest,
+) -> Response {
     state.logs.write().await.add(
         "info",
         &format!(
@@ -643,7 +726,7 @@ pub async fn chat_completions(
     );
 
     // 使用 RequestProcessor 解析模型别名和路由
-    let provider = state.processor.resolve_and_route(&mut ctx).await;
+    let provider = state.processor.resolve_and_route(ctx).await;
 
     // 更新请求中的模型名为解析后的模型
     if ctx.resolved_model != ctx.original_model {
@@ -680,7 +763,7 @@ pub async fn chat_completions(
 
     // 根据客户端类型选择 Provider
     // **Validates: Requirements 3.1, 3.3, 3.4**
-    let (selected_provider, client_type) = select_provider_for_client(&headers, &state).await;
+    let (selected_provider, client_type) = select_provider_for_client(headers, state).await;
 
     // 记录客户端检测和 Provider 选择结果
     state.logs.write().await.add(
@@ -691,6 +774,28 @@ pub async fn chat_completions(
         ),
     );
 
+    // 执行插件请求钩子，插件可直接返回响应或拒绝请求
+    if state.processor.plugins.count() > 0 {
+        let mut payload = serde_json::to_value(&request).unwrap_or_default();
+        if let Some(action) =
+            run_plugin_request_hooks(state, ctx, &selected_provider, &mut payload).await
+        {
+            let llm_request =
+                build_llm_request_from_openai(&request, "/v1/chat/completions", headers);
+            let flow_metadata = build_flow_metadata(provider, None, None, headers, &ctx.request_id);
+            record_plugin_short_circuit_flow(state, &action, llm_request, flow_metadata).await;
+            return build_plugin_action_response(
+                &action,
+                ResponseFormat::OpenAi,
+                &request.model,
+                request.stream,
+            );
+        }
+        if let Ok(updated) = serde_json::from_value(payload) {
+            request = updated;
+        }
+    }
+
     // 记录路由结果
     state.logs.write().await.add(
         "info",
@@ -723,12 +828,12 @@ pub async fn chat_completions(
         );
 
         // 启动 Flow 捕获
-        let llm_request = build_llm_request_from_openai(&request, "/v1/chat/completions", &headers);
+        let llm_request = build_llm_request_from_openai(&request, "/v1/chat/completions", headers);
         let flow_metadata = build_flow_metadata(
             provider,
             Some(&cred.uuid),
             cred.name.as_deref(),
-            &headers,
+            headers,
             &ctx.request_id,
         );
         let flow_id = state
@@ -739,7 +844,7 @@ pub async fn chat_completions(
         // 检查是否需要拦截请求
         // **Validates: Requirements 2.1, 2.3, 2.5**
         if let Some(ref fid) = flow_id {
-            match check_request_intercept(&state, fid, &llm_request, &flow_metadata).await {
+            match check_request_intercept(state, fid, &llm_request, &flow_metadata).await {
                 InterceptCheckResult::Continue(modified_request) => {
                     // 如果有修改后的请求，更新请求
                     if let Some(modified) = modified_request {
@@ -764,7 +869,10 @@ pub async fn chat_completions(
             }
         }
 
-        let response = call_provider_openai(&state, &cred, &request, flow_id.as_deref()).await;
+        // 出站请求数据防泄漏脱敏
+        apply_outbound_redaction(state, flow_id.as_deref(), &mut request).await;
+
+        let response = call_provider_openai(state, &cred, &request, flow_id.as_deref()).await;
 
         // 记录请求统计
         let is_success = response.status().is_success();
@@ -773,7 +881,7 @@ pub async fn chat_completions(
         } else {
             crate::telemetry::RequestStatus::Failed
         };
-        record_request_telemetry(&state, &ctx, status, None);
+        record_request_telemetry(state, ctx, status, None);
 
         // 如果成功，记录估算的 Token 使用量
         let estimated_input_tokens = request
@@ -791,8 +899,8 @@ pub async fn chat_completions(
 
         if is_success {
             record_token_usage(
-                &state,
-                &ctx,
+                state,
+                ctx,
                 Some(estimated_input_tokens),
                 Some(estimated_output_tokens),
             );
@@ -810,7 +918,7 @@ pub async fn chat_completions(
 
                 // 检查是否需要拦截响应
                 if let Some(modified_response) = check_response_intercept(
-                    &state,
+                    state,
                     &fid,
                     &llm_response,
                     &llm_request,
@@ -911,8 +1019,8 @@ pub async fn chat_completions(
     );
 
     // 启动 Flow 捕获（legacy mode）
-    let llm_request = build_llm_request_from_openai(&request, "/v1/chat/completions", &headers);
-    let flow_metadata = build_flow_metadata(provider, None, None, &headers, &ctx.request_id);
+    let llm_request = build_llm_request_from_openai(&request, "/v1/chat/completions", headers);
+    let flow_metadata = build_flow_metadata(provider, None, None, headers, &ctx.request_id);
     let flow_id = state
         .flow_monitor
         .start_flow(llm_request.clone(), flow_metadata.clone())
@@ -921,7 +1029,7 @@ pub async fn chat_completions(
     // 检查是否需要拦截请求（legacy mode）
     // **Validates: Requirements 2.1, 2.3, 2.5**
     if let Some(ref fid) = flow_id {
-        match check_request_intercept(&state, fid, &llm_request, &flow_metadata).await {
+        match check_request_intercept(state, fid, &llm_request, &flow_metadata).await {
             InterceptCheckResult::Continue(modified_request) => {
                 // 如果有修改后的请求，更新请求
                 if let Some(modified) = modified_request {
@@ -943,6 +1051,9 @@ pub async fn chat_completions(
         }
     }
 
+    // 出站请求数据防泄漏脱敏
+    apply_outbound_redaction(state, flow_id.as_deref(), &mut request).await;
+
     // 检查是否需要刷新 token（无 token 或即将过期）
     {
         let _guard = state.kiro_refresh_lock.lock().await;
@@ -1052,15 +1163,15 @@ pub async fn chat_completions(
                         });
                         // 记录成功请求统计
                         record_request_telemetry(
-                            &state,
-                            &ctx,
+                            state,
+                            ctx,
                             crate::telemetry::RequestStatus::Success,
                             None,
                         );
                         // 记录 Token 使用量
                         record_token_usage(
-                            &state,
-                            &ctx,
+                            state,
+                            ctx,
                             Some(estimated_input_tokens),
                             Some(estimated_output_tokens),
                         );
@@ -1075,7 +1186,7 @@ pub async fn chat_completions(
 
                             // 检查是否需要拦截响应
                             if let Some(modified_response) = check_response_intercept(
-                                &state,
+                                state,
                                 fid,
                                 &llm_response,
                                 &llm_request,
@@ -1134,8 +1245,8 @@ pub async fn chat_completions(
                     Err(e) => {
                         // 记录失败请求统计
                         record_request_telemetry(
-                            &state,
-                            &ctx,
+                            state,
+                            ctx,
                             crate::telemetry::RequestStatus::Failed,
                             Some(e.to_string()),
                         );
@@ -1245,7 +1356,7 @@ pub async fn chat_completions(
                                                 // 检查是否需要拦截响应
                                                 if let Some(modified_response) =
                                                     check_response_intercept(
-                                                        &state,
+                                                        state,
                                                         fid,
                                                         &llm_response,
                                                         &llm_request,
@@ -1416,7 +1527,7 @@ pub async fn chat_completions(
 pub async fn anthropic_messages(
     State(state): State<AppState>,
     headers: HeaderMap,
-    Json(mut request): Json<AnthropicMessagesRequest>,
+    Json(request): Json<AnthropicMessagesRequest>,
 ) -> Response {
     // 使用 Anthropic 格式的认证验证（优先检查 x-api-key）
     if let Err(e) = verify_api_key_anthropic(&headers, &state.api_key).await {
@@ -1430,7 +1541,19 @@ pub async fn anthropic_messages(
 
     // 创建请求上下文
     let mut ctx = RequestContext::new(request.model.clone()).with_stream(request.stream);
+    let response = process_anthropic_messages(&state, &headers, &mut ctx, request).await;
+
+    // 执行插件响应钩子
+    run_plugin_response_hooks(&state, &mut ctx, response, ResponseFormat::Anthropic).await
+}
 
+/// 处理 Anthropic 格式请求：路由、插件请求钩子、Flow 捕获与 Provider 调用
+async fn process_anthropic_messages(
+    state: &AppState,
+    headers: &HeaderMap,
+    ctx: &mut RequestContext,
+    mut request: AnthropicMessagesRequest,
+) -> Response {
     // 详细记录请求信息
     let msg_count = request.messages.len();
     let has_tools = request.tools.as_ref().map(|t| t.len()).unwrap_or(0);
@@ -1444,7 +1567,7 @@ pub async fn anthropic_messages(
     );
 
     // 使用 RequestProcessor 解析模型别名和路由
-    let provider = state.processor.resolve_and_route(&mut ctx).await;
+    let provider = state.processor.resolve_and_route(ctx).await;
 
     // 更新请求中的模型名为解析后的模型
     if ctx.resolved_model != ctx.original_model {
@@ -1507,7 +1630,7 @@ pub async fn anthropic_messages(
 
     // 根据客户端类型选择 Provider
     // **Validates: Requirements 3.1, 3.3, 3.4**
-    let (selected_provider, client_type) = select_provider_for_client(&headers, &state).await;
+    let (selected_provider, client_type) = select_provider_for_client(headers, state).await;
 
     // 记录客户端检测和 Provider 选择结果
     state.logs.write().await.add(
@@ -1518,6 +1641,27 @@ pub async fn anthropic_messages(
         ),
     );
 
+    // 执行插件请求钩子，插件可直接返回响应或拒绝请求
+    if state.processor.plugins.count() > 0 {
+        let mut payload = serde_json::to_value(&request).unwrap_or_default();
+        if let Some(action) =
+            run_plugin_request_hooks(state, ctx, &selected_provider, &mut payload).await
+        {
+            let llm_request = build_llm_request_from_anthropic(&request, "/v1/messages", headers);
+            let flow_metadata = build_flow_metadata(provider, None, None, headers, &ctx.request_id);
+            record_plugin_short_circuit_flow(state, &action, llm_request, flow_metadata).await;
+            return build_plugin_action_response(
+                &action,
+                ResponseFormat::Anthropic,
+                &request.model,
+                request.stream,
+            );
+        }
+        if let Ok(updated) = serde_json::from_value(payload) {
+            request = updated;
+        }
+    }
+
     // 记录路由结果
     state.logs.write().await.add(
         "info",
@@ -1553,12 +1697,12 @@ pub async fn anthropic_messages(
         );
 
         // 启动 Flow 捕获
-        let llm_request = build_llm_request_from_anthropic(&request, "/v1/messages", &headers);
+        let llm_request = build_llm_request_from_anthropic(&request, "/v1/messages", headers);
         let flow_metadata = build_flow_metadata(
             provider,
             Some(&cred.uuid),
             cred.name.as_deref(),
-            &headers,
+            headers,
             &ctx.request_id,
         );
         let flow_id = state
@@ -1569,7 +1713,7 @@ pub async fn anthropic_messages(
         // 检查是否需要拦截请求
         // **Validates: Requirements 2.1, 2.3, 2.5**
         if let Some(ref fid) = flow_id {
-            match check_request_intercept(&state, fid, &llm_request, &flow_metadata).await {
+            match check_request_intercept(state, fid, &llm_request, &flow_metadata).await {
                 InterceptCheckResult::Continue(modified_request) => {
                     // 如果有修改后的请求，更新请求
                     if let Some(modified) = modified_request {
@@ -1598,7 +1742,10 @@ pub async fn anthropic_messages(
             }
         }
 
-        let response = call_provider_anthropic(&state, &cred, &request, flow_id.as_deref()).await;
+        // 出站请求数据防泄漏脱敏
+        apply_outbound_redaction(state, flow_id.as_deref(), &mut request).await;
+
+        let response = call_provider_anthropic(state, &cred, &request, flow_id.as_deref()).await;
 
         // 记录请求统计
         let is_success = response.status().is_success();
@@ -1607,7 +1754,7 @@ pub async fn anthropic_messages(
         } else {
             crate::telemetry::RequestStatus::Failed
         };
-        record_request_telemetry(&state, &ctx, status, None);
+        record_request_telemetry(state, ctx, status, None);
 
         // 估算 Token 使用量
         let estimated_input_tokens = request
@@ -1630,8 +1777,8 @@ pub async fn anthropic_messages(
 
         if is_success {
             record_token_usage(
-                &state,
-                &ctx,
+                state,
+                ctx,
                 Some(estimated_input_tokens),
                 Some(estimated_output_tokens),
             );
@@ -1649,7 +1796,7 @@ pub async fn anthropic_messages(
 
                 // 检查是否需要拦截响应
                 if let Some(modified_response) = check_response_intercept(
-                    &state,
+                    state,
                     &fid,
                     &llm_response,
                     &llm_request,
@@ -1743,8 +1890,8 @@ pub async fn anthropic_messages(
     );
 
     // 启动 Flow 捕获（legacy mode）
-    let llm_request = build_llm_request_from_anthropic(&request, "/v1/messages", &headers);
-    let flow_metadata = build_flow_metadata(provider, None, None, &headers, &ctx.request_id);
+    let llm_request = build_llm_request_from_anthropic(&request, "/v1/messages", headers);
+    let flow_metadata = build_flow_metadata(provider, None, None, headers, &ctx.request_id);
     let flow_id = state
         .flow_monitor
         .start_flow(llm_request.clone(), flow_metadata.clone())
@@ -1753,7 +1900,7 @@ pub async fn anthropic_messages(
     // 检查是否需要拦截请求（legacy mode）
     // **Validates: Requirements 2.1, 2.3, 2.5**
     if let Some(ref fid) = flow_id {
-        match check_request_intercept(&state, fid, &llm_request, &flow_metadata).await {
+        match check_request_intercept(state, fid, &llm_request, &flow_metadata).await {
             InterceptCheckResult::Continue(modified_request) => {
                 // 如果有修改后的请求，更新请求
                 if let Some(modified) = modified_request {
@@ -1781,6 +1928,9 @@ pub async fn anthropic_messages(
         }
     }
 
+    // 出站请求数据防泄漏脱敏
+    apply_outbound_redaction(state, flow_id.as_deref(), &mut request).await;
+
     // 检查是否需要刷新 token（无 token 或即将过期）
     {
         let _guard = state.kiro_refresh_lock.lock().await;
@@ -1821,7 +1971,25 @@ pub async fn anthropic_messages(
     }
 
     // 转换为 OpenAI 格式
-    let openai_request = convert_anthropic_to_openai(&request);
+    let openai_request = match canonical::anthropic_to_chat_completion(&request) {
+        Ok(openai_request) => openai_request,
+        Err(e) => {
+            state
+                .logs
+                .write()
+                .await
+                .add("error", &format!("[CONVERT] Anthropic 请求转换失败: {e}"));
+            if let Some(fid) = &flow_id {
+                let error = FlowError::new(FlowErrorType::BadRequest, &e.to_string());
+                state.flow_monitor.fail_flow(fid, error).await;
+            }
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({"error": {"message": e.to_string()}})),
+            )
+                .into_response();
+        }
+    };
 
     // 记录转换后的请求信息
     state.logs.write().await.add(
@@ -1912,7 +2080,7 @@ pub async fn anthropic_messages(
 
                                 // 检查是否需要拦截响应
                                 if let Some(modified_response) = check_response_intercept(
-                                    &state,
+                                    state,
                                     fid,
                                     &llm_response,
                                     &llm_request,
@@ -1976,7 +2144,7 @@ pub async fn anthropic_messages(
 
                             // 检查是否需要拦截响应
                             if let Some(modified_response) = check_response_intercept(
-                                &state,
+                                state,
                                 fid,
                                 &llm_response,
                                 &llm_request,
@@ -2108,7 +2276,7 @@ pub async fn anthropic_messages(
                                                 // 检查是否需要拦截响应
                                                 if let Some(modified_response) =
                                                     check_response_intercept(
-                                                        &state,
+                                                        state,
                                                         fid,
                                                         &llm_response,
                                                         &llm_request,
@@ -0,0 +1,1029 @@
+//! Gemini 原生协议处理器
+//!
+//! 路由: `POST /v1/gemini/{model}:{method}`，支持的方法：
+//!
+//! - `generateContent` - 非流式生成
+//! - `streamGenerateContent` - 流式生成，`?alt=sse` 返回 SSE，否则返回 JSON 数组
+//! - `countTokens` - 本地估算 token 数
+//!
+//! 请求经 `translator::canonical` 解码为规范模型后，按路由选择的凭证编码为目标协议：
+//!
+//! - Gemini 系（Antigravity、Gemini OAuth、Gemini API Key、Vertex AI）：直接透传 Gemini 请求体
+//! - OpenAI 兼容 / Claude API / Qwen OAuth：编码为对应协议直接调用，响应再编码回 Gemini 格式
+//! - 其他（Kiro 等）：编码为 Anthropic 请求后复用 `call_provider_anthropic`
+//!
+//! 命中 `tool_emulation` 规则的路由不走原生直连，统一经 `call_provider_anthropic` 模拟工具调用。
+//!
+//! 插件前置钩子看到的是 Gemini 格式的请求体，修改后重新解码；插件短路时直接返回
+//! Gemini 格式的合成响应或错误。
+//!
+//! 流式响应统一通过 `StreamPipeline`（前端为 `FrontendType::Gemini`）转换。
+//! safetySettings 只有 Gemini 系后端能识别，其他后端的内容过滤结果映射为 `SAFETY`。
+
+use std::collections::HashMap;
+use std::fmt::Display;
+
+use axum::{
+    body::{Body, Bytes},
+    extract::{Path, Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use futures::{Stream, StreamExt};
+use serde_json::{json, Value};
+
+use crate::agent::context::estimate_tokens;
+use crate::converter::protocol_selector::Protocol;
+use crate::models::anthropic::AnthropicMessagesRequest;
+use crate::models::provider_pool_model::{CredentialData, ProviderCredential};
+use crate::plugin::{HookAction, ResponseFormat};
+use crate::processor::RequestContext;
+use crate::providers::antigravity::AntigravityProvider;
+use crate::providers::claude_custom::ClaudeCustomProvider;
+use crate::providers::gemini::{GeminiApiKeyCredential, GeminiProvider};
+use crate::providers::openai_custom::OpenAICustomProvider;
+use crate::providers::qwen::QwenProvider;
+use crate::providers::vertex::VertexProvider;
+use crate::server::{record_request_telemetry, AppState};
+use crate::server_utils::{build_gemini_native_request, build_plugin_action_response};
+use crate::stream::{create_sse_stream, BackendType, FrontendType, PipelineConfig};
+use crate::translator::canonical::{
+    self, anthropic, gemini, openai, CanonicalRequest, ContentBlock,
+};
+
+use super::api::select_provider_for_client;
+use super::call_provider_anthropic;
+use super::plugin_hooks::{run_plugin_request_hooks, run_plugin_response_hooks};
+use super::tool_emulation::emulation_format;
+
+/// Gemini 对每张图片按固定 token 数计费
+const IMAGE_TOKENS: u32 = 258;
+
+/// 每条消息的格式化开销
+const TOKENS_PER_MESSAGE: u32 = 4;
+
+/// Gemini API 方法
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GeminiMethod {
+    GenerateContent,
+    StreamGenerateContent,
+    CountTokens,
+}
+
+impl GeminiMethod {
+    /// 从 URL 中的方法名解析
+    pub fn parse(method: &str) -> Option<Self> {
+        match method {
+            "generateContent" => Some(Self::GenerateContent),
+            "streamGenerateContent" => Some(Self::StreamGenerateContent),
+            "countTokens" => Some(Self::CountTokens),
+            _ => None,
+        }
+    }
+}
+
+/// 流式响应格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamMode {
+    /// `?alt=sse`，每个分块一个 `data:` 事件
+    Sse,
+    /// 默认格式，所有分块组成一个 JSON 数组
+    JsonArray,
+}
+
+impl StreamMode {
+    /// 根据 `alt` 查询参数选择格式
+    pub fn from_alt(alt: Option<&str>) -> Self {
+        match alt {
+            Some(alt) if alt.eq_ignore_ascii_case("sse") => Self::Sse,
+            _ => Self::JsonArray,
+        }
+    }
+}
+
+/// 解析路径 `[v1beta/][models/]{model}:{method}`
+///
+/// 只取最后一段，兼容把 `/v1/gemini` 当作 Gemini SDK base URL 的客户端。
+pub fn parse_gemini_path(path: &str) -> Option<(&str, &str)> {
+    let last = path.rsplit('/').next()?;
+    let (model, method) = last.split_once(':')?;
+    if model.is_empty() || method.is_empty() {
+        return None;
+    }
+    Some((model, method))
+}
+
+/// 构建 Gemini 格式的错误响应
+pub fn gemini_error(status: StatusCode, message: impl Into<String>) -> Response {
+    let status_name = match status.as_u16() {
+        400 => "INVALID_ARGUMENT",
+        401 => "UNAUTHENTICATED",
+        403 => "PERMISSION_DENIED",
+        404 => "NOT_FOUND",
+        429 => "RESOURCE_EXHAUSTED",
+        501 => "UNIMPLEMENTED",
+        503 => "UNAVAILABLE",
+        504 => "DEADLINE_EXCEEDED",
+        _ => "INTERNAL",
+    };
+    (
+        status,
+        Json(json!({
+            "error": {
+                "code": status.as_u16(),
+                "message": message.into(),
+                "status": status_name,
+            }
+        })),
+    )
+        .into_response()
+}
+
+/// 从上游错误响应体中提取错误信息
+pub fn upstream_error_message(body: &str) -> String {
+    let Ok(value) = serde_json::from_str::<Value>(body) else {
+        return body.to_string();
+    };
+    match value.get("error") {
+        Some(Value::String(message)) => message.clone(),
+        Some(error) => error
+            .get("message")
+            .and_then(|m| m.as_str())
+            .map(str::to_string)
+            .unwrap_or_else(|| error.to_string()),
+        None => body.to_string(),
+    }
+}
+
+/// 估算请求的输入 token 数
+pub fn estimate_request_tokens(request: &CanonicalRequest) -> u32 {
+    let model = request.model.as_str();
+    let mut tokens = request
+        .system
+        .iter()
+        .map(|block| estimate_block_tokens(block, model))
+        .sum::<u32>();
+    for message in &request.messages {
+        tokens += TOKENS_PER_MESSAGE;
+        tokens += message
+            .content
+            .iter()
+            .map(|block| estimate_block_tokens(block, model))
+            .sum::<u32>();
+    }
+    for tool in &request.tools {
+        tokens += estimate_tokens(&tool.name, model);
+        tokens += estimate_tokens(tool.description.as_deref().unwrap_or_default(), model);
+        tokens += estimate_tokens(&tool.input_schema.to_string(), model);
+    }
+    tokens
+}
+
+fn estimate_block_tokens(block: &ContentBlock, model: &str) -> u32 {
+    match block {
+        ContentBlock::Text { text, .. } => estimate_tokens(text, model),
+        ContentBlock::Image { .. } => IMAGE_TOKENS,
+        ContentBlock::Thinking { thinking, .. } => estimate_tokens(thinking, model),
+        ContentBlock::RedactedThinking { .. } => 0,
+        ContentBlock::ToolUse { name, input, .. } => {
+            estimate_tokens(name, model) + estimate_tokens(&input.to_string(), model)
+        }
+        ContentBlock::ToolResult { content, .. } => content
+            .iter()
+            .map(|block| estimate_block_tokens(block, model))
+            .sum(),
+    }
+}
+
+/// 将 Gemini SSE 生成器的输出组装为客户端请求的流格式
+#[derive(Debug)]
+pub struct StreamFramer {
+    mode: StreamMode,
+    started: bool,
+}
+
+impl StreamFramer {
+    pub fn new(mode: StreamMode) -> Self {
+        Self {
+            mode,
+            started: false,
+        }
+    }
+
+    /// 处理一个 SSE 事件（`data: {...}\n\n`）
+    pub fn frame_sse(&mut self, sse: &str) -> Option<String> {
+        let payload = sse.trim().strip_prefix("data:")?.trim();
+        if payload.is_empty() {
+            return None;
+        }
+        Some(self.frame_payload(payload))
+    }
+
+    /// 处理一个完整的 `GenerateContentResponse`
+    pub fn frame_value(&mut self, value: &Value) -> String {
+        self.frame_payload(&value.to_string())
+    }
+
+    /// 结束流，JSON 数组模式下补上结尾的 `]`
+    pub fn finish(&mut self) -> Option<String> {
+        match self.mode {
+            StreamMode::Sse => None,
+            StreamMode::JsonArray if self.started => Some("]".to_string()),
+            StreamMode::JsonArray => Some("[]".to_string()),
+        }
+    }
+
+    fn frame_payload(&mut self, payload: &str) -> String {
+        let first = !self.started;
+        self.started = true;
+        match self.mode {
+            StreamMode::Sse => format!("data: {}\r\n\r\n", payload),
+            StreamMode::JsonArray if first => format!("[{}", payload),
+            StreamMode::JsonArray => format!(",\r\n{}", payload),
+        }
+    }
+}
+
+fn stream_error_value(message: &str) -> Value {
+    json!({
+        "error": {
+            "code": 500,
+            "message": message,
+            "status": "INTERNAL",
+        }
+    })
+}
+
+/// 将 Gemini SSE 字符串流包装为 HTTP 响应
+fn gemini_stream_response<S, E>(sse_stream: S, mode: StreamMode) -> Response
+where
+    S: Stream<Item = Result<String, E>> + Send + 'static,
+    E: Display + Send + 'static,
+{
+    let body_stream = async_stream::stream! {
+        let mut framer = StreamFramer::new(mode);
+        let mut sse_stream = std::pin::pin!(sse_stream);
+        while let Some(result) = sse_stream.next().await {
+            match result {
+                Ok(sse) => {
+                    if let Some(chunk) = framer.frame_sse(&sse) {
+                        yield Ok::<Bytes, std::io::Error>(Bytes::from(chunk));
+                    }
+                }
+                Err(e) => {
+                    tracing::error!("[GEMINI] 流式传输错误: {}", e);
+                    let chunk = framer.frame_value(&stream_error_value(&e.to_string()));
+                    yield Ok(Bytes::from(chunk));
+                    break;
+                }
+            }
+        }
+        if let Some(chunk) = framer.finish() {
+            yield Ok(Bytes::from(chunk));
+        }
+    };
+
+    let content_type = match mode {
+        StreamMode::Sse => "text/event-stream",
+        StreamMode::JsonArray => "application/json",
+    };
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, content_type)
+        .header(header::CACHE_CONTROL, "no-cache")
+        .header("X-Accel-Buffering", "no")
+        .body(Body::from_stream(body_stream))
+        .unwrap_or_else(|_| {
+            gemini_error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to build stream response",
+            )
+        })
+}
+
+/// 将完整的 Gemini 响应作为单个分块返回（后端不支持流式时使用）
+fn gemini_single_chunk_response(response: Value, mode: StreamMode) -> Response {
+    let chunks: Vec<Result<String, std::io::Error>> = vec![Ok(format!("data: {}\n\n", response))];
+    gemini_stream_response(futures::stream::iter(chunks), mode)
+}
+
+/// 将后端完整响应转换为 Gemini 格式
+fn translate_full_response(protocol: Protocol, body: &Value) -> Response {
+    match canonical::decode_response(protocol, body) {
+        Ok(response) => Json(gemini::encode_response(&response)).into_response(),
+        Err(e) => gemini_error(StatusCode::BAD_GATEWAY, format!("无法解析后端响应: {}", e)),
+    }
+}
+
+/// Gemini 原生协议处理
+/// 路由: POST /v1/gemini/{model}:{method}
+/// 例如: /v1/gemini/gemini-3-pro-preview:generateContent
+pub async fn gemini_generate_content(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(path): Path<String>,
+    Query(query): Query<HashMap<String, String>>,
+    Json(mut body): Json<Value>,
+) -> Response {
+    if let Err(message) = verify_gemini_api_key(&headers, query.get("key"), &state.api_key) {
+        return gemini_error(StatusCode::UNAUTHORIZED, message);
+    }
+
+    let Some((model, method_name)) = parse_gemini_path(&path) else {
+        return gemini_error(
+            StatusCode::BAD_REQUEST,
+            format!("无效的路径格式: {}，期望格式: model:method", path),
+        );
+    };
+    let Some(method) = GeminiMethod::parse(method_name) else {
+        return gemini_error(
+            StatusCode::BAD_REQUEST,
+            format!(
+                "不支持的方法: {}，支持 generateContent / streamGenerateContent / countTokens",
+                method_name
+            ),
+        );
+    };
+
+    state.logs.write().await.add(
+        "info",
+        &format!(
+            "[GEMINI] POST /v1/gemini/{} model={} method={}",
+            path, model, method_name
+        ),
+    );
+
+    // countTokens 的请求体可以是 {contents} 或 {generateContentRequest: {...}}
+    let request_body = match method {
+        GeminiMethod::CountTokens => body.get("generateContentRequest").unwrap_or(&body),
+        _ => &body,
+    };
+    let mut request = match gemini::decode_request(request_body) {
+        Ok(request) => request,
+        Err(e) => return gemini_error(StatusCode::BAD_REQUEST, e.to_string()),
+    };
+
+    let stream_mode = match method {
+        GeminiMethod::StreamGenerateContent => {
+            Some(StreamMode::from_alt(query.get("alt").map(String::as_str)))
+        }
+        _ => None,
+    };
+    request.stream = stream_mode.is_some();
+
+    // 解析模型别名
+    let mut ctx = RequestContext::new(model.to_string()).with_stream(request.stream);
+    state.processor.resolve_and_route(&mut ctx).await;
+    request.model = ctx.resolved_model.clone();
+
+    if method == GeminiMethod::CountTokens {
+        return Json(json!({ "totalTokens": estimate_request_tokens(&request) })).into_response();
+    }
+
+    let (selected_provider, client_type) = select_provider_for_client(&headers, &state).await;
+
+    // 执行插件请求钩子，插件可直接返回响应或拒绝请求
+    if state.processor.plugins.count() > 0 {
+        let mut payload = body.clone();
+        if let Some(action) =
+            run_plugin_request_hooks(&state, &mut ctx, &selected_provider, &mut payload).await
+        {
+            return plugin_action_response(&action, &request.model, stream_mode);
+        }
+        if payload != body {
+            match gemini::decode_request(&payload) {
+                Ok(mut updated) => {
+                    updated.model = request.model.clone();
+                    updated.stream = request.stream;
+                    request = updated;
+                    body = payload;
+                }
+                Err(e) => tracing::warn!("[GEMINI] 插件修改后的请求体无效，已忽略: {}", e),
+            }
+        }
+    }
+    let request_body = &body;
+
+    let credential = match &state.db {
+        Some(db) => state
+            .pool_service
+            .select_credential(db, &selected_provider, Some(&ctx.resolved_model))
+            .ok()
+            .flatten(),
+        None => None,
+    };
+    let Some(cred) = credential else {
+        return gemini_error(
+            StatusCode::NOT_FOUND,
+            format!("没有可用的 {} 凭证，请先添加凭证", selected_provider),
+        );
+    };
+
+    state.logs.write().await.add(
+        "info",
+        &format!(
+            "[GEMINI] request_id={} client_type={} 使用凭证: type={} name={:?} uuid={}",
+            ctx.request_id,
+            client_type,
+            cred.provider_type,
+            cred.name,
+            &cred.uuid[..8]
+        ),
+    );
+
+    // 需要模拟工具调用的路由统一经 Anthropic 格式调用，由 call_provider_anthropic 处理
+    let emulate_tools = emulation_format(&state, &cred, &request.model).is_some();
+    let response = match &cred.credential {
+        _ if emulate_tools => call_via_anthropic(&state, &cred, &request, stream_mode).await,
+        CredentialData::AntigravityOAuth {
+            creds_file_path,
+            project_id,
+        } => {
+            call_antigravity_native(
+                &state,
+                &cred,
+                creds_file_path,
+                project_id.as_deref(),
+                request_body,
+                &request,
+                stream_mode,
+            )
+            .await
+        }
+        CredentialData::OpenAIKey { api_key, base_url } => {
+            let provider = OpenAICustomProvider::with_config(api_key.clone(), base_url.clone());
+            let result = provider
+                .chat_completions(&openai::encode_request(&request))
+                .await;
+            relay_upstream(
+                &state,
+                &cred,
+                result,
+                Protocol::OpenAI,
+                &request,
+                stream_mode,
+            )
+            .await
+        }
+        CredentialData::GeminiOAuth {
+            creds_file_path,
+            project_id,
+        } => {
+            call_gemini_oauth_native(
+                &state,
+                &cred,
+                creds_file_path,
+                project_id.as_deref(),
+                request_body,
+                &request,
+                stream_mode,
+            )
+            .await
+        }
+        CredentialData::GeminiApiKey {
+            api_key, base_url, ..
+        } => {
+            let credential = GeminiApiKeyCredential::new(cred.uuid.clone(), api_key.clone())
+                .with_base_url(base_url.clone());
+            let endpoint = format!(
+                "{}/v1beta/models/{}",
+                credential.get_base_url(),
+                request.model
+            );
+            let result = post_gemini_api(&endpoint, api_key, request_body, stream_mode).await;
+            relay_upstream(
+                &state,
+                &cred,
+                result,
+                Protocol::Gemini,
+                &request,
+                stream_mode,
+            )
+            .await
+        }
+        CredentialData::VertexKey {
+            api_key,
+            base_url,
+            model_aliases,
+        } => {
+            let mut vertex = VertexProvider::with_config(api_key.clone(), base_url.clone());
+            vertex.config.model_aliases = model_aliases.clone();
+            let endpoint = format!(
+                "{}/models/{}",
+                vertex.get_base_url(),
+                vertex.resolve_model_alias(&request.model)
+            );
+            let result = post_gemini_api(&endpoint, api_key, request_body, stream_mode).await;
+            relay_upstream(
+                &state,
+                &cred,
+                result,
+                Protocol::Gemini,
+                &request,
+                stream_mode,
+            )
+            .await
+        }
+        CredentialData::QwenOAuth { creds_file_path } => {
+            let mut qwen = QwenProvider::new();
+            let result = async {
+                qwen.load_credentials_from_path(creds_file_path).await?;
+                qwen.ensure_valid_token().await?;
+                qwen.chat_completions(&openai::encode_request(&request))
+                    .await
+            }
+            .await;
+            relay_upstream(
+                &state,
+                &cred,
+                result,
+                Protocol::OpenAI,
+                &request,
+                stream_mode,
+            )
+            .await
+        }
+        CredentialData::ClaudeKey { api_key, base_url } => {
+            let provider = ClaudeCustomProvider::with_config(api_key.clone(), base_url.clone());
+            let result = provider
+                .messages(&anthropic::encode_request(&request))
+                .await;
+            relay_upstream(
+                &state,
+                &cred,
+                result,
+                Protocol::Anthropic,
+                &request,
+                stream_mode,
+            )
+            .await
+        }
+        _ => call_via_anthropic(&state, &cred, &request, stream_mode).await,
+    };
+
+    let status = if response.status().is_success() {
+        crate::telemetry::RequestStatus::Success
+    } else {
+        crate::telemetry::RequestStatus::Failed
+    };
+    record_request_telemetry(&state, &ctx, status, None);
+
+    // 执行插件响应钩子
+    run_plugin_response_hooks(&state, &mut ctx, response, ResponseFormat::Gemini).await
+}
+
+/// 渲染插件短路结果，流式请求按客户端要求的格式输出单个分块
+fn plugin_action_response(
+    action: &HookAction,
+    model: &str,
+    stream_mode: Option<StreamMode>,
+) -> Response {
+    match (action, stream_mode) {
+        (HookAction::Respond(response), Some(mode)) => {
+            gemini_single_chunk_response(response.to_json(ResponseFormat::Gemini, model), mode)
+        }
+        _ => build_plugin_action_response(action, ResponseFormat::Gemini, model, false),
+    }
+}
+
+/// Gemini 客户端的 API key 验证
+///
+/// 除 `Authorization` / `x-api-key` 外，还接受 Gemini SDK 使用的 `x-goog-api-key` 头和 `?key=` 参数。
+fn verify_gemini_api_key(
+    headers: &HeaderMap,
+    query_key: Option<&String>,
+    expected_key: &str,
+) -> Result<(), &'static str> {
+    let key = headers
+        .get("x-goog-api-key")
+        .or_else(|| headers.get("authorization"))
+        .or_else(|| headers.get("x-api-key"))
+        .and_then(|v| v.to_str().ok())
+        .map(|s| s.strip_prefix("Bearer ").unwrap_or(s))
+        .or(query_key.map(String::as_str));
+
+    match key {
+        None => Err("No API key provided"),
+        Some(key) if key != expected_key => Err("Invalid API key"),
+        Some(_) => Ok(()),
+    }
+}
+
+fn mark_credential(state: &AppState, credential: &ProviderCredential, result: Result<&str, &str>) {
+    let Some(db) = &state.db else {
+        return;
+    };
+    match result {
+        Ok(model) => {
+            let _ = state
+                .pool_service
+                .mark_healthy(db, &credential.uuid, Some(model));
+            let _ = state.pool_service.record_usage(db, &credential.uuid);
+        }
+        Err(message) => {
+            let _ = state
+                .pool_service
+                .mark_unhealthy(db, &credential.uuid, Some(message));
+        }
+    }
+}
+
+/// 调用 Antigravity，直接透传 Gemini 请求体
+async fn call_antigravity_native(
+    state: &AppState,
+    credential: &ProviderCredential,
+    creds_file_path: &str,
+    project_id: Option<&str>,
+    body: &Value,
+    request: &CanonicalRequest,
+    stream_mode: Option<StreamMode>,
+) -> Response {
+    let model = request.model.as_str();
+    let mut antigravity = AntigravityProvider::new();
+    if let Err(e) = antigravity
+        .load_credentials_from_path(creds_file_path)
+        .await
+    {
+        return gemini_error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("加载 Antigravity 凭证失败: {}", e),
+        );
+    }
+
+    // 检查并刷新 token
+    if antigravity.is_token_expiring_soon() {
+        if let Err(e) = antigravity.refresh_token().await {
+            mark_credential(
+                state,
+                credential,
+                Err(&format!("Token refresh failed: {}", e)),
+            );
+            return gemini_error(StatusCode::UNAUTHORIZED, format!("Token 刷新失败: {}", e));
+        }
+    }
+
+    // 设置项目 ID
+    if let Some(pid) = project_id {
+        antigravity.project_id = Some(pid.to_string());
+    } else if antigravity.project_id.is_none() {
+        if let Err(e) = antigravity.discover_project().await {
+            tracing::warn!("[Antigravity] 获取项目 ID 失败: {}，使用随机生成的 ID", e);
+        }
+    }
+    let proj_id = antigravity.project_id.clone().unwrap_or_else(|| {
+        let uuid = uuid::Uuid::new_v4();
+        format!("proxycast-{}", &uuid.to_string()[..8])
+    });
+
+    let antigravity_request = build_gemini_native_request(body, model, &proj_id);
+    state.logs.write().await.add(
+        "debug",
+        &format!(
+            "[GEMINI] Antigravity 请求体: {}",
+            serde_json::to_string(&antigravity_request).unwrap_or_default()
+        ),
+    );
+
+    if stream_mode.is_some() {
+        let result = antigravity.call_api_stream_raw(&antigravity_request).await;
+        return relay_upstream(
+            state,
+            credential,
+            result,
+            Protocol::Antigravity,
+            request,
+            stream_mode,
+        )
+        .await;
+    }
+
+    match antigravity
+        .call_api("generateContent", &antigravity_request)
+        .await
+    {
+        Ok(resp) => {
+            mark_credential(state, credential, Ok(model));
+            // Antigravity 把 Gemini 响应包在 response 字段中
+            Json(resp.get("response").cloned().unwrap_or(resp)).into_response()
+        }
+        Err(e) => {
+            state
+                .logs
+                .write()
+                .await
+                .add("error", &format!("[GEMINI] 请求失败: {}", e));
+            mark_credential(state, credential, Err(&e.to_string()));
+            gemini_error(StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
+        }
+    }
+}
+
+/// 转发直接调用的上游响应
+///
+/// Gemini 系后端的响应直接透传（去掉 Antigravity / Code Assist 的 `response` 包装），
+/// 其他协议再编码为 Gemini 格式
+async fn relay_upstream(
+    state: &AppState,
+    credential: &ProviderCredential,
+    result: Result<reqwest::Response, Box<dyn std::error::Error + Send + Sync>>,
+    protocol: Protocol,
+    request: &CanonicalRequest,
+    stream_mode: Option<StreamMode>,
+) -> Response {
+    let resp = match result {
+        Ok(resp) => resp,
+        Err(e) => {
+            mark_credential(state, credential, Err(&e.to_string()));
+            return gemini_error(StatusCode::INTERNAL_SERVER_ERROR, e.to_string());
+        }
+    };
+
+    let status =
+        StatusCode::from_u16(resp.status().as_u16()).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+    if !status.is_success() {
+        let body = resp.text().await.unwrap_or_default();
+        state.logs.write().await.add(
+            "error",
+            &format!(
+                "[GEMINI] 上游请求失败: status={} body={}",
+                status,
+                body.chars().take(200).collect::<String>()
+            ),
+        );
+        mark_credential(state, credential, Err(&body));
+        return gemini_error(status, upstream_error_message(&body));
+    }
+    mark_credential(state, credential, Ok(&request.model));
+
+    match stream_mode {
+        Some(mode) => {
+            let backend = match protocol {
+                Protocol::OpenAI => BackendType::OpenAi,
+                Protocol::Gemini | Protocol::Antigravity => BackendType::Gemini,
+                _ => BackendType::Anthropic,
+            };
+            let config = PipelineConfig::new(backend, FrontendType::Gemini, request.model.clone());
+            gemini_stream_response(create_sse_stream(resp.bytes_stream(), config), mode)
+        }
+        None => match resp.json::<Value>().await {
+            Ok(body) if matches!(protocol, Protocol::Gemini | Protocol::Antigravity) => {
+                Json(body.get("response").cloned().unwrap_or(body)).into_response()
+            }
+            Ok(body) => translate_full_response(protocol, &body),
+            Err(e) => gemini_error(StatusCode::BAD_GATEWAY, format!("无法解析后端响应: {}", e)),
+        },
+    }
+}
+
+/// 调用 Gemini CLI OAuth（Code Assist），请求体包装为 `{model, project, request}`
+async fn call_gemini_oauth_native(
+    state: &AppState,
+    credential: &ProviderCredential,
+    creds_file_path: &str,
+    project_id: Option<&str>,
+    body: &Value,
+    request: &CanonicalRequest,
+    stream_mode: Option<StreamMode>,
+) -> Response {
+    let mut gemini = GeminiProvider::new();
+    if let Err(e) = gemini.load_credentials_from_path(creds_file_path).await {
+        return gemini_error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("加载 Gemini 凭证失败: {}", e),
+        );
+    }
+    if let Err(e) = gemini.ensure_valid_token().await {
+        mark_credential(
+            state,
+            credential,
+            Err(&format!("Token refresh failed: {}", e)),
+        );
+        return gemini_error(StatusCode::UNAUTHORIZED, format!("Token 刷新失败: {}", e));
+    }
+
+    let project = match project_id {
+        Some(pid) => pid.to_string(),
+        None => match gemini.discover_project().await {
+            Ok(pid) => pid,
+            Err(e) => {
+                return gemini_error(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("获取 Gemini 项目 ID 失败: {}", e),
+                )
+            }
+        },
+    };
+    let code_assist_request = json!({
+        "model": request.model,
+        "project": project,
+        "request": body,
+    });
+
+    let result = match stream_mode {
+        Some(_) => {
+            gemini
+                .call_api_stream("streamGenerateContent", &code_assist_request)
+                .await
+        }
+        None => match gemini
+            .call_api("generateContent", &code_assist_request)
+            .await
+        {
+            Ok(resp) => {
+                mark_credential(state, credential, Ok(&request.model));
+                return Json(resp.get("response").cloned().unwrap_or(resp)).into_response();
+            }
+            Err(e) => Err(e),
+        },
+    };
+    relay_upstream(
+        state,
+        credential,
+        result,
+        Protocol::Gemini,
+        request,
+        stream_mode,
+    )
+    .await
+}
+
+/// 以 API Key 调用 Gemini API / Vertex AI，`endpoint` 为 `.../models/{model}`
+async fn post_gemini_api(
+    endpoint: &str,
+    api_key: &str,
+    body: &Value,
+    stream_mode: Option<StreamMode>,
+) -> Result<reqwest::Response, Box<dyn std::error::Error + Send + Sync>> {
+    let url = match stream_mode {
+        Some(_) => format!("{}:streamGenerateContent?alt=sse", endpoint),
+        None => format!("{}:generateContent", endpoint),
+    };
+    let resp = reqwest::Client::new()
+        .post(&url)
+        .header("x-goog-api-key", api_key)
+        .header(header::CONTENT_TYPE, "application/json")
+        .json(body)
+        .send()
+        .await?;
+    Ok(resp)
+}
+
+/// 编码为 Anthropic 请求并复用 `call_provider_anthropic`（Kiro 等后端）
+async fn call_via_anthropic(
+    state: &AppState,
+    credential: &ProviderCredential,
+    request: &CanonicalRequest,
+    stream_mode: Option<StreamMode>,
+) -> Response {
+    let anthropic_request: AnthropicMessagesRequest =
+        match serde_json::from_value(anthropic::encode_request(request)) {
+            Ok(anthropic_request) => anthropic_request,
+            Err(e) => return gemini_error(StatusCode::BAD_REQUEST, e.to_string()),
+        };
+
+    let response = call_provider_anthropic(state, credential, &anthropic_request, None).await;
+    let status = response.status();
+    let is_event_stream = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("text/event-stream"));
+
+    if status.is_success() && is_event_stream {
+        let mode = stream_mode.unwrap_or(StreamMode::JsonArray);
+        let config = PipelineConfig::new(
+            BackendType::Anthropic,
+            FrontendType::Gemini,
+            request.model.clone(),
+        );
+        let byte_stream = response.into_body().into_data_stream();
+        return gemini_stream_response(create_sse_stream(byte_stream, config), mode);
+    }
+
+    let bytes = match axum::body::to_bytes(response.into_body(), usize::MAX).await {
+        Ok(bytes) => bytes,
+        Err(e) => return gemini_error(StatusCode::BAD_GATEWAY, e.to_string()),
+    };
+    if !status.is_success() {
+        return gemini_error(
+            status,
+            upstream_error_message(&String::from_utf8_lossy(&bytes)),
+        );
+    }
+    let body: Value = match serde_json::from_slice(&bytes) {
+        Ok(body) => body,
+        Err(e) => return gemini_error(StatusCode::BAD_GATEWAY, format!("无法解析后端响应: {}", e)),
+    };
+    let Some(mode) = stream_mode else {
+        return translate_full_response(Protocol::Anthropic, &body);
+    };
+    // 后端没有返回流式响应时，把完整响应作为单个分块
+    match canonical::decode_response(Protocol::Anthropic, &body) {
+        Ok(response) => gemini_single_chunk_response(gemini::encode_response(&response), mode),
+        Err(e) => gemini_error(StatusCode::BAD_GATEWAY, format!("无法解析后端响应: {}", e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_gemini_path() {
+        assert_eq!(
+            parse_gemini_path("gemini-2.5-pro:generateContent"),
+            Some(("gemini-2.5-pro", "generateContent"))
+        );
+        assert_eq!(
+            parse_gemini_path("v1beta/models/gemini-2.5-flash:streamGenerateContent"),
+            Some(("gemini-2.5-flash", "streamGenerateContent"))
+        );
+        assert_eq!(parse_gemini_path("gemini-2.5-pro"), None);
+        assert_eq!(parse_gemini_path(":countTokens"), None);
+        assert_eq!(GeminiMethod::parse("embedContent"), None);
+    }
+
+    #[test]
+    fn test_stream_framer_json_array() {
+        let mut framer = StreamFramer::new(StreamMode::JsonArray);
+        let mut output = String::new();
+        output.push_str(&framer.frame_sse("data: {\"a\":1}\n\n").unwrap());
+        output.push_str(&framer.frame_sse("data: {\"a\":2}\n\n").unwrap());
+        output.push_str(&framer.finish().unwrap());
+        let parsed: Value = serde_json::from_str(&output).unwrap();
+        assert_eq!(parsed, json!([{"a": 1}, {"a": 2}]));
+
+        let mut empty = StreamFramer::new(StreamMode::JsonArray);
+        assert_eq!(empty.finish().as_deref(), Some("[]"));
+    }
+
+    #[test]
+    fn test_stream_framer_sse() {
+        let mut framer = StreamFramer::new(StreamMode::from_alt(Some("sse")));
+        assert_eq!(
+            framer.frame_sse("data: {\"a\":1}\n\n").as_deref(),
+            Some("data: {\"a\":1}\r\n\r\n")
+        );
+        assert_eq!(framer.frame_sse(": ping\n\n"), None);
+        assert_eq!(framer.finish(), None);
+    }
+
+    #[tokio::test]
+    async fn test_plugin_action_response_follows_stream_mode() {
+        let action = HookAction::Respond(crate::plugin::SyntheticResponse {
+            content: "cached".to_string(),
+            model: None,
+            stop_reason: None,
+            input_tokens: 1,
+            output_tokens: 1,
+        });
+
+        let response = plugin_action_response(&action, "gemini-2.5-pro", None);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            body["candidates"][0]["content"]["parts"][0]["text"],
+            "cached"
+        );
+
+        // 不带 alt=sse 的流式请求返回 JSON 数组
+        let response =
+            plugin_action_response(&action, "gemini-2.5-pro", Some(StreamMode::JsonArray));
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let chunks: Vec<Value> = serde_json::from_slice(&body).unwrap();
+        assert_eq!(chunks.len(), 1);
+        assert_eq!(chunks[0]["modelVersion"], "gemini-2.5-pro");
+    }
+
+    #[test]
+    fn test_upstream_error_message() {
+        assert_eq!(
+            upstream_error_message(
+                r#"{"type":"error","error":{"type":"overloaded_error","message":"Overloaded"}}"#
+            ),
+            "Overloaded"
+        );
+        assert_eq!(upstream_error_message(r#"{"error":"bad key"}"#), "bad key");
+        assert_eq!(upstream_error_message("gateway timeout"), "gateway timeout");
+    }
+
+    #[test]
+    fn test_estimate_request_tokens_counts_images_and_tools() {
+        let body = json!({
+            "systemInstruction": {"parts": [{"text": "You are helpful."}]},
+            "contents": [{"role": "user", "parts": [
+                {"text": "What is in this picture?"},
+                {"inlineData": {"mimeType": "image/png", "data": "iVBORw0KGgo="}}
+            ]}],
+            "tools": [{"functionDeclarations": [{"name": "lookup", "parameters": {"type": "object"}}]}]
+        });
+        let request = gemini::decode_request(&body).unwrap();
+        let tokens = estimate_request_tokens(&request);
+        assert!(tokens > IMAGE_TOKENS + TOKENS_PER_MESSAGE);
+    }
+}
@@ -74,6 +74,9 @@ pub struct AddCredentialRequest {
     /// 代理 URL
     #[serde(default)]
     pub proxy_url: Option<String>,
+    /// 凭证字段（用于插件 Provider，按插件声明的 credential_schema 校验）
+    #[serde(default)]
+    pub fields: Option<serde_json::Value>,
 }
 
 /// 添加凭证响应
@@ -431,6 +434,39 @@ pub async fn management_add_credential(
                 );
             }
         }
+        PoolProviderType::Plugin => {
+            let plugin_provider = match crate::plugin::plugin_provider_id(&request.provider_type) {
+                Some(id) => state.processor.plugins.provider(id).await,
+                None => None,
+            };
+            let Some(plugin_provider) = plugin_provider else {
+                return (
+                    StatusCode::BAD_REQUEST,
+                    Json(AddCredentialResponse {
+                        success: false,
+                        message: format!("Plugin provider not found: {}", request.provider_type),
+                        id: None,
+                    }),
+                );
+            };
+            let fields = request
+                .fields
+                .unwrap_or_else(|| serde_json::Value::Object(Default::default()));
+            if let Err(e) = plugin_provider.validate_credential(&fields) {
+                return (
+                    StatusCode::BAD_REQUEST,
+                    Json(AddCredentialResponse {
+                        success: false,
+                        message: e.to_string(),
+                        id: None,
+                    }),
+                );
+            }
+            CredentialData::Plugin {
+                provider: plugin_provider.id().to_string(),
+                fields,
+            }
+        }
     };
 
     // 创建凭证
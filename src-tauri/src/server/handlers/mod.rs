@@ -2,16 +2,24 @@
 //!
 //! 将 server 中的各类处理器拆分到独立文件
 
+pub mod agent_api;
 pub mod api;
 pub mod credentials_api;
+pub mod gemini_api;
 pub mod kiro_credential;
 pub mod management;
+pub mod plugin_hooks;
+pub mod plugin_provider;
 pub mod provider_calls;
+pub mod tool_emulation;
 pub mod websocket;
 
+pub use agent_api::*;
 pub use api::*;
 pub use credentials_api::*;
+pub use gemini_api::*;
 pub use kiro_credential::*;
 pub use management::*;
+pub use plugin_provider::*;
 pub use provider_calls::*;
 pub use websocket::*;
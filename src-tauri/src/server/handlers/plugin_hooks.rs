@@ -0,0 +1,195 @@
+//! 插件钩子
+//!
+//! OpenAI / Anthropic / Gemini 端点共用的插件钩子调用：
+//!
+//! 1. 请求钩子在客户端 Provider 选择之后执行，插件上下文使用实际选中的 Provider；
+//!    插件短路时记录日志和遥测，由端点按客户端格式渲染响应
+//! 2. 响应钩子在 Provider 返回非流式成功响应后执行，插件可修改响应体或短路替换；
+//!    失败响应执行错误钩子
+//! 3. SSE 响应按客户端格式重新解析为 `StreamEvent`，逐事件经过插件流式钩子后再生成 SSE，
+//!    对所有后端（Kiro、OpenAI、Claude、Gemini 等）生效
+
+use axum::{
+    body::Body,
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use futures::StreamExt;
+use serde_json::Value;
+
+use crate::plugin::{HookAction, ResponseFormat};
+use crate::processor::{PipelineStep, PluginPostStep, PluginPreStep, RequestContext};
+use crate::server::{record_request_telemetry, AppState};
+use crate::server_utils::build_plugin_action_response;
+use crate::stream::{BackendType, FrontendType, PipelineConfig, StreamPipeline};
+use crate::ProviderType;
+
+/// 请求被插件短路时写入的元数据键，响应钩子据此跳过插件合成的响应
+const PLUGIN_SHORT_CIRCUIT_KEY: &str = "plugin_short_circuit";
+
+/// 执行插件请求钩子
+///
+/// 调用方在存在已加载插件时调用。插件修改后的请求写回 `payload`；
+/// 插件短路时返回短路动作，并已记录日志和遥测。
+pub(crate) async fn run_plugin_request_hooks(
+    state: &AppState,
+    ctx: &mut RequestContext,
+    selected_provider: &str,
+    payload: &mut Value,
+) -> Option<HookAction> {
+    if let Ok(provider) = selected_provider.parse::<ProviderType>() {
+        ctx.set_provider(provider);
+    }
+
+    let _ = PluginPreStep::new(state.processor.plugins.clone())
+        .execute(ctx, payload)
+        .await;
+    let action = ctx.take_plugin_action()?;
+    ctx.set_metadata(PLUGIN_SHORT_CIRCUIT_KEY, Value::Bool(true));
+
+    state.logs.write().await.add(
+        "info",
+        &format!(
+            "[PLUGIN] request_id={} short-circuited by plugin",
+            ctx.request_id
+        ),
+    );
+    let (status, error) = match &action {
+        HookAction::Respond(_) => (crate::telemetry::RequestStatus::Success, None),
+        HookAction::Reject(rejection) => (
+            crate::telemetry::RequestStatus::Failed,
+            Some(rejection.message.clone()),
+        ),
+    };
+    record_request_telemetry(state, ctx, status, error);
+    Some(action)
+}
+
+/// 执行插件响应钩子
+///
+/// 仅在请求钩子执行过且未短路时生效；插件短路时按 `format` 渲染替换响应。
+pub(crate) async fn run_plugin_response_hooks(
+    state: &AppState,
+    ctx: &mut RequestContext,
+    response: Response,
+    format: ResponseFormat,
+) -> Response {
+    if ctx.plugin_ctx.is_none() || ctx.get_metadata(PLUGIN_SHORT_CIRCUIT_KEY).is_some() {
+        return response;
+    }
+
+    let step = PluginPostStep::new(state.processor.plugins.clone());
+    if !response.status().is_success() {
+        step.run_on_error(ctx, &format!("HTTP {}", response.status().as_u16()))
+            .await;
+        return response;
+    }
+    if ctx.is_stream {
+        return attach_stream_hooks(&step, ctx, response, format).await;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let bytes = match axum::body::to_bytes(body, usize::MAX).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            tracing::error!("[PLUGIN] 读取响应体失败: {}", e);
+            return (
+                StatusCode::BAD_GATEWAY,
+                Json(serde_json::json!({"error": {"message": e.to_string()}})),
+            )
+                .into_response();
+        }
+    };
+    let Ok(original) = serde_json::from_slice::<Value>(&bytes) else {
+        return Response::from_parts(parts, Body::from(bytes));
+    };
+
+    let mut payload = original.clone();
+    let _ = step.execute(ctx, &mut payload).await;
+    if let Some(action) = ctx.take_plugin_action() {
+        state.logs.write().await.add(
+            "info",
+            &format!(
+                "[PLUGIN] request_id={} response replaced by plugin",
+                ctx.request_id
+            ),
+        );
+        return build_plugin_action_response(&action, format, &ctx.resolved_model, false);
+    }
+    if payload == original {
+        return Response::from_parts(parts, Body::from(bytes));
+    }
+
+    match serde_json::to_vec(&payload) {
+        Ok(body) => {
+            parts.headers.remove(header::CONTENT_LENGTH);
+            Response::from_parts(parts, Body::from(body))
+        }
+        Err(e) => {
+            tracing::warn!("[PLUGIN] 插件修改后的响应无法序列化，已忽略: {}", e);
+            Response::from_parts(parts, Body::from(bytes))
+        }
+    }
+}
+
+/// 为 SSE 响应挂载插件流式钩子会话
+///
+/// 会话沿用请求钩子初始化的插件上下文；没有启用的插件或响应不是 SSE
+/// （如 Gemini 不带 `alt=sse` 的 JSON 数组流）时原样返回。
+async fn attach_stream_hooks(
+    step: &PluginPostStep,
+    ctx: &RequestContext,
+    response: Response,
+    format: ResponseFormat,
+) -> Response {
+    let is_event_stream = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("text/event-stream"));
+    if !is_event_stream {
+        return response;
+    }
+    let session = step.stream_session(ctx).await;
+    if session.is_empty() {
+        return response;
+    }
+
+    let (backend, frontend) = match format {
+        ResponseFormat::OpenAi => (BackendType::OpenAi, FrontendType::OpenAi),
+        ResponseFormat::Anthropic => (BackendType::Anthropic, FrontendType::Anthropic),
+        ResponseFormat::Gemini => (BackendType::Gemini, FrontendType::Gemini),
+    };
+    let mut pipeline = StreamPipeline::new(PipelineConfig::new(
+        backend,
+        frontend,
+        ctx.resolved_model.clone(),
+    ))
+    .with_plugins(session);
+
+    let (mut parts, body) = response.into_parts();
+    // 内容已重新生成，原长度不再有效
+    parts.headers.remove(header::CONTENT_LENGTH);
+
+    let mut byte_stream = body.into_data_stream();
+    let sse_stream = async_stream::stream! {
+        while let Some(chunk) = byte_stream.next().await {
+            match chunk {
+                Ok(bytes) => {
+                    for sse in pipeline.process_chunk_with_plugins(&bytes).await {
+                        yield Ok::<String, axum::Error>(sse);
+                    }
+                }
+                Err(e) => {
+                    yield Err(e);
+                    return;
+                }
+            }
+        }
+        for sse in pipeline.finish_with_plugins().await {
+            yield Ok(sse);
+        }
+    };
+    Response::from_parts(parts, Body::from_stream(sse_stream))
+}
@@ -0,0 +1,216 @@
+//! 插件 Provider 调用处理器
+//!
+//! 凭证类型为 `CredentialData::Plugin` 时，请求转换为规范协议 (OpenAI Chat Completions)
+//! 后交给插件适配上游；插件刷新后的凭证写回凭证池。
+//! 上游调用为非流式，客户端的流式请求由完整响应合成 SSE。
+
+use axum::{
+    body::Body,
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use futures::stream;
+
+use crate::converter::protocol_selector::Protocol;
+use crate::models::anthropic::AnthropicMessagesRequest;
+use crate::models::openai::ChatCompletionRequest;
+use crate::models::provider_pool_model::{CredentialData, ProviderCredential};
+use crate::plugin::{openai_response_to_events, PluginProviderError};
+use crate::proxy::{ProxyClientFactory, ProxyError};
+use crate::server::AppState;
+use crate::stream::{AnthropicSseGenerator, OpenAiSseGenerator};
+use crate::translator::canonical;
+
+/// 使用插件 Provider 处理 OpenAI 格式请求
+pub async fn call_plugin_provider_openai(
+    state: &AppState,
+    credential: &ProviderCredential,
+    provider_id: &str,
+    fields: &serde_json::Value,
+    request: &ChatCompletionRequest,
+) -> Response {
+    let body = match serde_json::to_value(request) {
+        Ok(body) => body,
+        Err(e) => return error_response(StatusCode::BAD_REQUEST, e.to_string()),
+    };
+    let response =
+        match call_plugin_provider(state, credential, provider_id, fields, &request.model, body)
+            .await
+        {
+            Ok(response) => response,
+            Err(resp) => return resp,
+        };
+
+    if !request.stream {
+        return Json(response).into_response();
+    }
+
+    let id = response["id"]
+        .as_str()
+        .map(str::to_string)
+        .unwrap_or_else(|| format!("chatcmpl-{}", uuid::Uuid::new_v4()));
+    let mut generator = OpenAiSseGenerator::with_id(id, request.model.clone());
+    let events = openai_response_to_events(&response, &request.model)
+        .iter()
+        .filter_map(|event| generator.generate(event))
+        .collect();
+    sse_response(events)
+}
+
+/// 使用插件 Provider 处理 Anthropic 格式请求
+pub async fn call_plugin_provider_anthropic(
+    state: &AppState,
+    credential: &ProviderCredential,
+    provider_id: &str,
+    fields: &serde_json::Value,
+    request: &AnthropicMessagesRequest,
+) -> Response {
+    let body = match canonical::anthropic_to_chat_completion(request)
+        .map_err(|e| e.to_string())
+        .and_then(|openai_request| serde_json::to_value(openai_request).map_err(|e| e.to_string()))
+    {
+        Ok(body) => body,
+        Err(e) => return error_response(StatusCode::BAD_REQUEST, e),
+    };
+    let response =
+        match call_plugin_provider(state, credential, provider_id, fields, &request.model, body)
+            .await
+        {
+            Ok(response) => response,
+            Err(resp) => return resp,
+        };
+
+    if request.stream {
+        let id = format!("msg_{}", uuid::Uuid::new_v4());
+        let mut generator = AnthropicSseGenerator::with_id(id, request.model.clone());
+        let events = openai_response_to_events(&response, &request.model)
+            .iter()
+            .flat_map(|event| generator.generate(event))
+            .collect();
+        return sse_response(events);
+    }
+
+    match canonical::convert_response(Protocol::OpenAI, Protocol::Anthropic, &response) {
+        Ok(message) => Json(message).into_response(),
+        Err(e) => error_response(StatusCode::BAD_GATEWAY, e.to_string()),
+    }
+}
+
+/// 刷新凭证、调用插件 Provider 并更新凭证健康状态，返回规范协议响应
+async fn call_plugin_provider(
+    state: &AppState,
+    credential: &ProviderCredential,
+    provider_id: &str,
+    fields: &serde_json::Value,
+    model: &str,
+    mut body: serde_json::Value,
+) -> Result<serde_json::Value, Response> {
+    let Some(provider) = state.processor.plugins.provider(provider_id).await else {
+        return Err(error_response(
+            StatusCode::SERVICE_UNAVAILABLE,
+            format!("插件 Provider 未加载或已禁用: {}", provider_id),
+        ));
+    };
+    if !provider.supports_model(model) {
+        return Err(error_response(
+            StatusCode::BAD_REQUEST,
+            format!("插件 Provider {} 不支持模型: {}", provider_id, model),
+        ));
+    }
+
+    let mut fields = fields.clone();
+    match provider.refresh_credential(&mut fields).await {
+        Ok(true) => {
+            if let Some(db) = &state.db {
+                let updated = CredentialData::Plugin {
+                    provider: provider_id.to_string(),
+                    fields: fields.clone(),
+                };
+                if let Err(e) =
+                    state
+                        .pool_service
+                        .update_credential_data(db, &credential.uuid, updated)
+                {
+                    tracing::warn!("[PLUGIN_PROVIDER] 保存刷新后的凭证失败: {}", e);
+                }
+            }
+        }
+        Ok(false) => {}
+        Err(e) => return Err(fail(state, credential, e)),
+    }
+
+    let client = match plugin_http_client(state, credential) {
+        Ok(client) => client,
+        Err(e) => {
+            return Err(error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("创建 HTTP 客户端失败: {}", e),
+            ))
+        }
+    };
+
+    body["stream"] = serde_json::Value::Bool(false);
+    match provider.chat_completions(&client, &fields, &body).await {
+        Ok(response) => {
+            if let Some(db) = &state.db {
+                let _ = state
+                    .pool_service
+                    .mark_healthy(db, &credential.uuid, Some(model));
+                let _ = state.pool_service.record_usage(db, &credential.uuid);
+            }
+            Ok(response)
+        }
+        Err(e) => Err(fail(state, credential, e)),
+    }
+}
+
+/// 创建插件使用的 HTTP 客户端：优先使用凭证的代理，其次使用全局代理
+fn plugin_http_client(
+    state: &AppState,
+    credential: &ProviderCredential,
+) -> Result<reqwest::Client, ProxyError> {
+    let global_proxy = state
+        .hot_reload_manager
+        .as_ref()
+        .and_then(|manager| manager.config_ref().read().proxy_url.clone());
+    ProxyClientFactory::new()
+        .with_global_proxy(global_proxy)
+        .create_client(credential.proxy_url.as_deref())
+}
+
+fn fail(state: &AppState, credential: &ProviderCredential, error: PluginProviderError) -> Response {
+    let message = error.to_string();
+    tracing::error!("[PLUGIN_PROVIDER] 调用失败: {}", message);
+    if let Some(db) = &state.db {
+        let _ = state
+            .pool_service
+            .mark_unhealthy(db, &credential.uuid, Some(&message));
+    }
+    let status = StatusCode::from_u16(error.status_code()).unwrap_or(StatusCode::BAD_GATEWAY);
+    error_response(status, message)
+}
+
+fn error_response(status: StatusCode, message: String) -> Response {
+    (
+        status,
+        Json(serde_json::json!({"error": {"message": message}})),
+    )
+        .into_response()
+}
+
+fn sse_response(events: Vec<String>) -> Response {
+    let body_stream = stream::iter(events.into_iter().map(Ok::<_, std::convert::Infallible>));
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, "text/event-stream")
+        .header(header::CACHE_CONTROL, "no-cache")
+        .header(header::CONNECTION, "keep-alive")
+        .body(Body::from_stream(body_stream))
+        .unwrap_or_else(|_| {
+            error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to build response".to_string(),
+            )
+        })
+}
@@ -49,7 +49,7 @@ use axum::{
 };
 use futures::StreamExt;
 
-use crate::converter::anthropic_to_openai::convert_anthropic_to_openai;
+use crate::converter::protocol_selector::Protocol;
 use crate::converter::openai_to_antigravity::{
     convert_antigravity_to_openai_response, convert_openai_to_antigravity_with_context,
 };
@@ -72,9 +72,15 @@ use crate::streaming::{
     StreamConfig, StreamContext, StreamError, StreamFormat as StreamingFormat, StreamManager,
     StreamResponse,
 };
+use crate::translator::canonical;
+
+use super::plugin_provider::{call_plugin_provider_anthropic, call_plugin_provider_openai};
+use super::tool_emulation::{emulate_request, emulate_response, emulation_format};
 
 /// 根据凭证调用 Provider (Anthropic 格式)
 ///
+/// 路由命中 `tool_emulation` 规则时，工具调用由代理模拟（见 `tool_emulation` 模块）。
+///
 /// # 参数
 /// - `state`: 应用状态
 /// - `credential`: 凭证信息
@@ -85,6 +91,48 @@ pub async fn call_provider_anthropic(
     credential: &ProviderCredential,
     request: &AnthropicMessagesRequest,
     flow_id: Option<&str>,
+) -> Response {
+    let Some(format) = emulation_format(state, credential, &request.model) else {
+        return dispatch_provider_anthropic(state, credential, request, flow_id).await;
+    };
+    match emulate_request(Protocol::Anthropic, request, format) {
+        Ok(Some((emulated, emulation))) => {
+            let response = dispatch_provider_anthropic(state, credential, &emulated, flow_id).await;
+            match emulation {
+                Some(emulation) => {
+                    emulate_response(response, Protocol::Anthropic, emulation, &request.model)
+                        .await
+                }
+                None => response,
+            }
+        }
+        Ok(None) => dispatch_provider_anthropic(state, credential, request, flow_id).await,
+        Err(e) => {
+            tracing::warn!("[TOOL_EMULATION] 改写请求失败，按原请求发送: {}", e);
+            dispatch_provider_anthropic(state, credential, request, flow_id).await
+        }
+    }
+}
+
+/// 经规范模型将 Anthropic 请求转换为 OpenAI 请求，无法转换时返回 400 响应
+fn anthropic_to_openai_request(
+    request: &AnthropicMessagesRequest,
+) -> Result<ChatCompletionRequest, Response> {
+    canonical::anthropic_to_chat_completion(request).map_err(|e| {
+        tracing::error!("[CONVERT] Anthropic 请求转换失败: {}", e);
+        (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({"error": {"message": e.to_string()}})),
+        )
+            .into_response()
+    })
+}
+
+async fn dispatch_provider_anthropic(
+    state: &AppState,
+    credential: &ProviderCredential,
+    request: &AnthropicMessagesRequest,
+    flow_id: Option<&str>,
 ) -> Response {
     // 如果是流式请求且有 flow_id，设置流式状态
     if request.stream {
@@ -167,7 +215,10 @@ pub async fn call_provider_anthropic(
             let _ = kiro.load_credentials_from_path(creds_file_path).await;
             // 使用缓存的 token 覆盖文件中的 token（缓存的 token 更新）
             kiro.credentials.access_token = Some(token);
-            let openai_request = convert_anthropic_to_openai(request);
+            let openai_request = match anthropic_to_openai_request(request) {
+                Ok(openai_request) => openai_request,
+                Err(response) => return response,
+            };
             let resp = match kiro.call_api(&openai_request).await {
                 Ok(r) => r,
                 Err(e) => {
@@ -377,7 +428,10 @@ pub async fn call_provider_anthropic(
             // 获取 project_id 用于请求
             let proj_id = antigravity.project_id.clone().unwrap_or_default();
             // 先转换为 OpenAI 格式，再转换为 Antigravity 格式
-            let openai_request = convert_anthropic_to_openai(request);
+            let openai_request = match anthropic_to_openai_request(request) {
+                Ok(openai_request) => openai_request,
+                Err(response) => return response,
+            };
             let antigravity_request = convert_openai_to_antigravity_with_context(&openai_request, &proj_id);
             match antigravity
                 .generate_content(&request.model, &antigravity_request)
@@ -428,7 +482,10 @@ pub async fn call_provider_anthropic(
         }
         CredentialData::OpenAIKey { api_key, base_url } => {
             let openai = OpenAICustomProvider::with_config(api_key.clone(), base_url.clone());
-            let openai_request = convert_anthropic_to_openai(request);
+            let openai_request = match anthropic_to_openai_request(request) {
+                Ok(openai_request) => openai_request,
+                Err(response) => return response,
+            };
             match openai.call_api(&openai_request).await {
                 Ok(resp) => {
                     if resp.status().is_success() {
@@ -440,9 +497,13 @@ pub async fn call_provider_anthropic(
                                     let content = openai_resp["choices"][0]["message"]["content"]
                                         .as_str()
                                         .unwrap_or("");
+                                    let tool_calls = serde_json::from_value(
+                                        openai_resp["choices"][0]["message"]["tool_calls"].clone(),
+                                    )
+                                    .unwrap_or_default();
                                     let parsed = CWParsedResponse {
                                         content: content.to_string(),
-                                        tool_calls: Vec::new(),
+                                        tool_calls,
                                         usage_credits: 0.0,
                                         context_usage_percentage: 0.0,
                                     };
@@ -459,7 +520,17 @@ pub async fn call_provider_anthropic(
                                     if request.stream {
                                         build_anthropic_stream_response(&request.model, &parsed)
                                     } else {
-                                        build_anthropic_response(&request.model, &parsed)
+                                        // 经由 canonical 模型转换，保留工具调用与 usage
+                                        match canonical::convert_response(
+                                            Protocol::OpenAI,
+                                            Protocol::Anthropic,
+                                            &openai_resp,
+                                        ) {
+                                            Ok(body) => Json(body).into_response(),
+                                            Err(_) => {
+                                                build_anthropic_response(&request.model, &parsed)
+                                            }
+                                        }
                                     }
                                 } else {
                                     // 记录解析失败
@@ -720,7 +791,10 @@ pub async fn call_provider_anthropic(
         }
         CredentialData::VertexKey { api_key, base_url, .. } => {
             // Vertex AI uses Gemini-compatible API, convert Anthropic to OpenAI format first
-            let openai_request = convert_anthropic_to_openai(request);
+            let openai_request = match anthropic_to_openai_request(request) {
+                Ok(openai_request) => openai_request,
+                Err(response) => return response,
+            };
             let vertex = VertexProvider::with_config(api_key.clone(), base_url.clone());
             match vertex.chat_completions(&serde_json::to_value(&openai_request).unwrap_or_default()).await {
                 Ok(resp) => {
@@ -770,6 +844,10 @@ pub async fn call_provider_anthropic(
             )
                 .into_response()
         }
+        // 插件 Provider 凭证
+        CredentialData::Plugin { provider, fields } => {
+            call_plugin_provider_anthropic(state, credential, provider, fields, request).await
+        }
         // 新增的凭证类型暂不支持 Anthropic 格式
         CredentialData::CodexOAuth { .. }
         | CredentialData::ClaudeOAuth { .. }
@@ -786,12 +864,41 @@ pub async fn call_provider_anthropic(
 
 /// 根据凭证调用 Provider (OpenAI 格式)
 ///
+/// 路由命中 `tool_emulation` 规则时，工具调用由代理模拟（见 `tool_emulation` 模块）。
+///
 /// # 参数
 /// - `state`: 应用状态
 /// - `credential`: 凭证信息
 /// - `request`: OpenAI 格式请求
 /// - `flow_id`: Flow ID（可选，用于流式响应处理）
 pub async fn call_provider_openai(
+    state: &AppState,
+    credential: &ProviderCredential,
+    request: &ChatCompletionRequest,
+    flow_id: Option<&str>,
+) -> Response {
+    let Some(format) = emulation_format(state, credential, &request.model) else {
+        return dispatch_provider_openai(state, credential, request, flow_id).await;
+    };
+    match emulate_request(Protocol::OpenAI, request, format) {
+        Ok(Some((emulated, emulation))) => {
+            let response = dispatch_provider_openai(state, credential, &emulated, flow_id).await;
+            match emulation {
+                Some(emulation) => {
+                    emulate_response(response, Protocol::OpenAI, emulation, &request.model).await
+                }
+                None => response,
+            }
+        }
+        Ok(None) => dispatch_provider_openai(state, credential, request, flow_id).await,
+        Err(e) => {
+            tracing::warn!("[TOOL_EMULATION] 改写请求失败，按原请求发送: {}", e);
+            dispatch_provider_openai(state, credential, request, flow_id).await
+        }
+    }
+}
+
+async fn dispatch_provider_openai(
     state: &AppState,
     credential: &ProviderCredential,
     request: &ChatCompletionRequest,
@@ -1375,6 +1482,10 @@ pub async fn call_provider_openai(
             )
                 .into_response()
         }
+        // 插件 Provider 凭证
+        CredentialData::Plugin { provider, fields } => {
+            call_plugin_provider_openai(state, credential, provider, fields, request).await
+        }
         // 新增的凭证类型暂不支持 OpenAI 格式
         CredentialData::CodexOAuth { .. }
         | CredentialData::ClaudeOAuth { .. }
@@ -0,0 +1,352 @@
+//! 工具调用模拟
+//!
+//! 对配置中标记为不支持原生函数调用的路由（`tool_emulation.rules`），
+//! `call_provider_anthropic` / `call_provider_openai` 在调用后端前后分别：
+//!
+//! 1. 将请求中的工具定义写入系统提示，工具调用历史和工具结果改写为文本
+//! 2. 从后端的完整响应或 SSE 流中解析工具调用，输出 Anthropic `tool_use` /
+//!    OpenAI `tool_calls`
+//!
+//! 具体的文本格式和解析逻辑见 `translator::tool_emulation`。
+
+use axum::{
+    body::Body,
+    http::{header, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use futures::StreamExt;
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+
+use crate::agent::tools::prompt::PromptFormat;
+use crate::config::{ToolEmulationFormat, ToolEmulationSettings};
+use crate::converter::protocol_selector::Protocol;
+use crate::models::provider_pool_model::ProviderCredential;
+use crate::server::AppState;
+use crate::stream::{BackendType, FrontendType, PipelineConfig, StreamPipeline};
+use crate::translator::canonical;
+use crate::translator::tool_emulation::ToolEmulation;
+
+/// 非流式后端响应体的读取上限，与请求体大小限制一致
+const MAX_RESPONSE_BODY_BYTES: usize = 100 * 1024 * 1024;
+
+/// 读取当前的工具调用模拟配置
+pub(crate) fn tool_emulation_settings(state: &AppState) -> ToolEmulationSettings {
+    state
+        .hot_reload_manager
+        .as_ref()
+        .map(|manager| manager.config_ref().read().tool_emulation.clone())
+        .unwrap_or_default()
+}
+
+/// 判断路由是否需要模拟工具调用，需要时返回提示格式
+pub(crate) fn emulation_format(
+    state: &AppState,
+    credential: &ProviderCredential,
+    model: &str,
+) -> Option<PromptFormat> {
+    let settings = tool_emulation_settings(state);
+    if !settings.matches(&credential.provider_type.to_string(), model) {
+        return None;
+    }
+    Some(match settings.format {
+        ToolEmulationFormat::Xml => PromptFormat::Xml,
+        ToolEmulationFormat::Json => PromptFormat::Json,
+    })
+}
+
+/// 改写请求，返回改写后的请求和响应解析状态
+///
+/// 请求不包含任何工具字段时返回 None；请求没有声明工具时解析状态为 None，
+/// 此时只改写了历史中的工具调用。
+pub(crate) fn emulate_request<T>(
+    protocol: Protocol,
+    request: &T,
+    format: PromptFormat,
+) -> Result<Option<(T, Option<ToolEmulation>)>, String>
+where
+    T: Serialize + DeserializeOwned,
+{
+    let body = serde_json::to_value(request).map_err(|e| e.to_string())?;
+    if !has_tool_content(protocol, &body) {
+        return Ok(None);
+    }
+    let mut canonical = canonical::decode_request(protocol, &body).map_err(|e| e.to_string())?;
+    let emulation = ToolEmulation::prepare(&mut canonical, format);
+    let body = canonical::encode_request(protocol, &canonical).map_err(|e| e.to_string())?;
+    let request = serde_json::from_value(body).map_err(|e| e.to_string())?;
+    Ok(Some((request, emulation)))
+}
+
+/// 请求是否包含需要改写的工具字段
+fn has_tool_content(protocol: Protocol, body: &Value) -> bool {
+    match protocol {
+        Protocol::Anthropic => {
+            body.get("tools").is_some_and(|t| !t.is_null())
+                || body["messages"].as_array().is_some_and(|messages| {
+                    messages.iter().any(|m| {
+                        m["content"].as_array().is_some_and(|blocks| {
+                            blocks.iter().any(|b| {
+                                matches!(b["type"].as_str(), Some("tool_use" | "tool_result"))
+                            })
+                        })
+                    })
+                })
+        }
+        _ => {
+            body.get("tools").is_some_and(|t| !t.is_null())
+                || body["messages"].as_array().is_some_and(|messages| {
+                    messages.iter().any(|m| {
+                        m["role"] == "tool" || m.get("tool_calls").is_some_and(|t| !t.is_null())
+                    })
+                })
+        }
+    }
+}
+
+/// 从后端响应中解析工具调用
+///
+/// 非成功响应原样返回；SSE 响应逐块解析，JSON 响应只在解析到调用时重新编码。
+pub(crate) async fn emulate_response(
+    response: Response,
+    protocol: Protocol,
+    emulation: ToolEmulation,
+    model: &str,
+) -> Response {
+    if !response.status().is_success() {
+        return response;
+    }
+    let is_event_stream = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("text/event-stream"));
+
+    if is_event_stream {
+        return emulate_stream(response, protocol, emulation, model);
+    }
+
+    let (parts, body) = response.into_parts();
+    let bytes = match axum::body::to_bytes(body, MAX_RESPONSE_BODY_BYTES).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            let message = format!("读取后端响应失败: {}", e);
+            tracing::error!("[TOOL_EMULATION] {}", message);
+            return (
+                StatusCode::BAD_GATEWAY,
+                Json(serde_json::json!({"error": {"message": message}})),
+            )
+                .into_response();
+        }
+    };
+    let rewritten = serde_json::from_slice::<Value>(&bytes)
+        .ok()
+        .and_then(|body| canonical::decode_response(protocol, &body).ok())
+        .and_then(|mut decoded| {
+            emulation
+                .apply_to_response(&mut decoded)
+                .then(|| canonical::encode_response(protocol, &decoded).ok())
+                .flatten()
+        });
+    match rewritten {
+        Some(body) => (parts.status, Json(body)).into_response(),
+        None => Response::from_parts(parts, Body::from(bytes)),
+    }
+}
+
+fn emulate_stream(
+    response: Response,
+    protocol: Protocol,
+    emulation: ToolEmulation,
+    model: &str,
+) -> Response {
+    let (backend, frontend) = match protocol {
+        Protocol::OpenAI => (BackendType::OpenAi, FrontendType::OpenAi),
+        _ => (BackendType::Anthropic, FrontendType::Anthropic),
+    };
+    let mut pipeline =
+        StreamPipeline::new(PipelineConfig::new(backend, frontend, model.to_string()))
+            .with_tool_filter(emulation.stream_filter());
+
+    let (mut parts, body) = response.into_parts();
+    // 内容已重新生成，原长度不再有效
+    parts.headers.remove(header::CONTENT_LENGTH);
+    parts.headers.insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static("text/event-stream"),
+    );
+
+    let mut byte_stream = body.into_data_stream();
+    let sse_stream = async_stream::stream! {
+        while let Some(chunk) = byte_stream.next().await {
+            match chunk {
+                Ok(bytes) => {
+                    for sse in pipeline.process_chunk(&bytes) {
+                        yield Ok::<String, axum::Error>(sse);
+                    }
+                }
+                Err(e) => {
+                    yield Err(e);
+                    return;
+                }
+            }
+        }
+        for sse in pipeline.finish() {
+            yield Ok(sse);
+        }
+    };
+    Response::from_parts(parts, Body::from_stream(sse_stream))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::anthropic::AnthropicMessagesRequest;
+    use crate::models::openai::ChatCompletionRequest;
+    use serde_json::json;
+
+    fn weather_tool_schema() -> Value {
+        json!({"type": "object", "properties": {"city": {"type": "string"}}})
+    }
+
+    #[test]
+    fn test_emulate_anthropic_request() {
+        let request: AnthropicMessagesRequest = serde_json::from_value(json!({
+            "model": "claude-sonnet-4-5",
+            "max_tokens": 1024,
+            "system": "Be brief.",
+            "tools": [{"name": "get_weather", "input_schema": weather_tool_schema()}],
+            "messages": [
+                {"role": "user", "content": "Weather in Paris?"},
+                {"role": "assistant", "content": [
+                    {"type": "tool_use", "id": "toolu_1", "name": "get_weather", "input": {"city": "Paris"}}
+                ]},
+                {"role": "user", "content": [
+                    {"type": "tool_result", "tool_use_id": "toolu_1", "content": "Sunny"}
+                ]}
+            ]
+        }))
+        .unwrap();
+
+        let (rewritten, emulation) =
+            emulate_request(Protocol::Anthropic, &request, PromptFormat::Xml)
+                .unwrap()
+                .unwrap();
+        assert!(emulation.is_some());
+
+        let body = serde_json::to_value(&rewritten).unwrap();
+        assert!(!has_tool_content(Protocol::Anthropic, &body));
+        assert_eq!(body["max_tokens"], 1024);
+        let system = serde_json::to_string(&body["system"]).unwrap();
+        assert!(system.contains("Be brief."));
+        assert!(system.contains("get_weather"));
+        let history = serde_json::to_string(&body["messages"]).unwrap();
+        assert!(
+            history.contains("<tool_result tool_use_id=\\\"toolu_1\\\" name=\\\"get_weather\\\">")
+        );
+    }
+
+    #[test]
+    fn test_emulate_openai_request() {
+        let request: ChatCompletionRequest = serde_json::from_value(json!({
+            "model": "deepseek-chat",
+            "messages": [
+                {"role": "user", "content": "Weather in Paris?"},
+                {"role": "assistant", "content": null, "tool_calls": [{
+                    "id": "call_1", "type": "function",
+                    "function": {"name": "get_weather", "arguments": "{\"city\":\"Paris\"}"}
+                }]},
+                {"role": "tool", "tool_call_id": "call_1", "content": "Sunny"}
+            ],
+            "tools": [{"type": "function", "function": {
+                "name": "get_weather", "parameters": weather_tool_schema()
+            }}],
+            "tool_choice": "required"
+        }))
+        .unwrap();
+        let body = serde_json::to_value(&request).unwrap();
+        assert!(has_tool_content(Protocol::OpenAI, &body));
+
+        let (rewritten, emulation) =
+            emulate_request(Protocol::OpenAI, &request, PromptFormat::Json)
+                .unwrap()
+                .unwrap();
+        assert!(emulation.is_some());
+        assert!(rewritten.tools.is_none());
+        assert!(rewritten.tool_choice.is_none());
+        assert_eq!(rewritten.messages[0].role, "system");
+        assert!(rewritten.messages[0]
+            .get_content_text()
+            .contains("You must call at least one tool"));
+        assert!(rewritten
+            .messages
+            .iter()
+            .all(|m| m.role != "tool" && m.tool_calls.is_none()));
+        assert!(rewritten.messages[2]
+            .get_content_text()
+            .starts_with("<tool_call>"));
+    }
+
+    #[tokio::test]
+    async fn test_emulate_streaming_response() {
+        let request = json!({
+            "model": "m",
+            "messages": [{"role": "user", "content": "hi"}],
+            "tools": [{"type": "function", "function": {
+                "name": "get_weather", "parameters": weather_tool_schema()
+            }}]
+        });
+        let mut decoded = canonical::decode_request(Protocol::OpenAI, &request).unwrap();
+        let emulation = ToolEmulation::prepare(&mut decoded, PromptFormat::Xml).unwrap();
+
+        let chunks = [
+            r#"{"id":"c1","object":"chat.completion.chunk","model":"m","choices":[{"index":0,"delta":{"role":"assistant","content":"<invoke name=\"get_"}}]}"#,
+            r#"{"id":"c1","object":"chat.completion.chunk","model":"m","choices":[{"index":0,"delta":{"content":"weather\"><parameter name=\"city\">Oslo</parameter></invoke>"}}]}"#,
+            r#"{"id":"c1","object":"chat.completion.chunk","model":"m","choices":[{"index":0,"delta":{},"finish_reason":"stop"}]}"#,
+        ];
+        let upstream: String = chunks
+            .iter()
+            .map(|c| format!("data: {}\n\n", c))
+            .chain(std::iter::once("data: [DONE]\n\n".to_string()))
+            .collect();
+        let response = Response::builder()
+            .header(header::CONTENT_TYPE, "text/event-stream")
+            .body(Body::from(upstream))
+            .unwrap();
+
+        let response = emulate_response(response, Protocol::OpenAI, emulation, "m").await;
+        let bytes = axum::body::to_bytes(response.into_body(), MAX_RESPONSE_BODY_BYTES)
+            .await
+            .unwrap();
+        let text = String::from_utf8_lossy(&bytes);
+        assert!(text.contains("\"tool_calls\""));
+        assert!(text.contains("get_weather"));
+        assert!(text.contains("\"finish_reason\":\"tool_calls\""));
+        assert!(!text.contains("<invoke"));
+    }
+
+    #[tokio::test]
+    async fn test_unreadable_response_is_bad_gateway() {
+        let request = json!({
+            "model": "m",
+            "messages": [{"role": "user", "content": "hi"}],
+            "tools": [{"type": "function", "function": {
+                "name": "get_weather", "parameters": weather_tool_schema()
+            }}]
+        });
+        let mut decoded = canonical::decode_request(Protocol::OpenAI, &request).unwrap();
+        let emulation = ToolEmulation::prepare(&mut decoded, PromptFormat::Xml).unwrap();
+
+        let failing = futures::stream::iter([Err::<axum::body::Bytes, std::io::Error>(
+            std::io::Error::other("connection reset"),
+        )]);
+        let response = Response::builder()
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from_stream(failing))
+            .unwrap();
+
+        let response = emulate_response(response, Protocol::OpenAI, emulation, "m").await;
+        assert_eq!(response.status(), StatusCode::BAD_GATEWAY);
+    }
+}
@@ -6,20 +6,24 @@ use axum::{
     body::Body,
     extract::{
         ws::{Message as WsMessage, WebSocket, WebSocketUpgrade},
-        Query, State,
+        ConnectInfo, Query, State,
     },
     http::HeaderMap,
     response::IntoResponse,
 };
+use futures::stream::SplitSink;
 use futures::{SinkExt, StreamExt as FuturesStreamExt};
 use serde::Deserialize;
+use std::net::SocketAddr;
 use std::sync::Arc;
-use tokio::sync::Mutex;
+use tokio::sync::{mpsc, Mutex};
+use tokio::task::JoinHandle;
 
-use crate::converter::anthropic_to_openai::convert_anthropic_to_openai;
+use crate::converter::protocol_selector::Protocol;
 use crate::converter::openai_to_antigravity::{
     convert_antigravity_to_openai_response, convert_openai_to_antigravity_with_context,
 };
+use crate::middleware::authorize_management_key;
 use crate::models::anthropic::AnthropicMessagesRequest;
 use crate::models::openai::ChatCompletionRequest;
 use crate::models::provider_pool_model::ProviderCredential;
@@ -29,8 +33,10 @@ use crate::providers::{
 };
 use crate::server::AppState;
 use crate::server_utils::parse_cw_response;
+use crate::translator::canonical;
 use crate::websocket::{
-    WsApiRequest, WsApiResponse, WsEndpoint, WsError, WsFlowEvent, WsMessage as WsProtoMessage,
+    run_flow_tail, FlowTailFilter, WsApiRequest, WsApiResponse, WsEndpoint, WsError,
+    WsFlowEventMessage, WsFlowSubscription, WsMessage as WsProtoMessage,
 };
 
 /// WebSocket 查询参数
@@ -40,6 +46,8 @@ pub struct WsQueryParams {
     pub api_key: Option<String>,
     /// Token（通过 URL 参数传递，与 api_key 等效）
     pub token: Option<String>,
+    /// 管理密钥（通过 URL 参数传递，用于订阅 Flow 事件）
+    pub management_key: Option<String>,
 }
 
 /// WebSocket 升级处理器
@@ -47,6 +55,7 @@ pub async fn ws_upgrade_handler(
     ws: WebSocketUpgrade,
     State(state): State<AppState>,
     Query(params): Query<WsQueryParams>,
+    connect_info: Option<ConnectInfo<SocketAddr>>,
     headers: HeaderMap,
 ) -> impl IntoResponse {
     // 验证 API 密钥：优先从 header 获取，其次从 URL 参数获取
@@ -64,10 +73,23 @@ pub async fn ws_upgrade_handler(
         }
     };
 
+    // 管理密钥：优先从 x-management-key header 获取，其次从 URL 参数获取
+    let management_key = headers
+        .get("x-management-key")
+        .and_then(|v| v.to_str().ok())
+        .or(params.management_key.as_deref());
+    let management_enabled = state
+        .remote_management
+        .secret_key
+        .as_deref()
+        .is_some_and(|k| !k.is_empty());
+
     // 如果没有提供任何认证信息，允许连接（用于内部 Flow Monitor）
     // 但会在日志中记录
     let authenticated = match key {
         Some(k) if k == state.api_key => true,
+        // 启用远程管理时，主认证位置也可以携带管理密钥
+        Some(_) if management_enabled && management_key.is_none() => false,
         Some(_) => {
             return axum::http::Response::builder()
                 .status(401)
@@ -82,13 +104,41 @@ pub async fn ws_upgrade_handler(
         }
     };
 
+    // 校验管理密钥，通过后才允许订阅 Flow 事件
+    let management_candidate = management_key.or(if authenticated { None } else { key });
+    let management_authorized = match management_candidate {
+        Some(candidate) => match authorize_management_key(
+            &state.remote_management,
+            Some(candidate),
+            connect_info.map(|ConnectInfo(addr)| addr),
+        ) {
+            Ok(()) => true,
+            Err(rejection) => {
+                return axum::http::Response::builder()
+                    .status(rejection.status)
+                    .body(Body::from(rejection.message))
+                    .unwrap()
+                    .into_response();
+            }
+        },
+        None => false,
+    };
+
     // 获取客户端信息
     let client_info = headers
         .get("user-agent")
         .and_then(|v| v.to_str().ok())
         .map(|s| s.to_string());
 
-    ws.on_upgrade(move |socket| handle_websocket(socket, state, client_info, authenticated))
+    ws.on_upgrade(move |socket| {
+        handle_websocket(
+            socket,
+            state,
+            client_info,
+            authenticated,
+            management_authorized,
+        )
+    })
 }
 
 /// 处理 WebSocket 连接
@@ -97,6 +147,7 @@ pub async fn handle_websocket(
     state: AppState,
     client_info: Option<String>,
     authenticated: bool,
+    management_authorized: bool,
 ) {
     let conn_id = uuid::Uuid::new_v4().to_string();
 
@@ -115,73 +166,23 @@ pub async fn handle_websocket(
     state.logs.write().await.add(
         "info",
         &format!(
-            "[WS] New connection: {} (client: {:?}, authenticated: {})",
+            "[WS] New connection: {} (client: {:?}, authenticated: {}, management: {})",
             &conn_id[..8],
             client_info,
-            authenticated
+            authenticated,
+            management_authorized
         ),
     );
 
     let (sender, mut receiver) = socket.split();
     let sender = Arc::new(Mutex::new(sender));
 
-    // Flow 事件订阅状态
-    let flow_subscribed = Arc::new(std::sync::atomic::AtomicBool::new(false));
-
-    // 启动 Flow 事件转发任务
-    let flow_sender = sender.clone();
-    let flow_subscribed_clone = flow_subscribed.clone();
-    let flow_monitor = state.flow_monitor.clone();
-    let conn_id_clone = conn_id.clone();
-    let _logs_clone = state.logs.clone();
-
-    let flow_task = tokio::spawn(async move {
-        let mut flow_receiver = flow_monitor.subscribe();
-
-        loop {
-            match flow_receiver.recv().await {
-                Ok(event) => {
-                    // 只有在订阅状态下才转发事件
-                    if !flow_subscribed_clone.load(std::sync::atomic::Ordering::Relaxed) {
-                        continue;
-                    }
-
-                    // 转换为 WebSocket 消息
-                    let ws_event: WsFlowEvent = event.into();
-                    let ws_msg = WsProtoMessage::FlowEvent(ws_event);
-
-                    if let Ok(msg_text) = serde_json::to_string(&ws_msg) {
-                        let mut sender_guard = flow_sender.lock().await;
-                        if sender_guard
-                            .send(WsMessage::Text(msg_text.into()))
-                            .await
-                            .is_err()
-                        {
-                            tracing::debug!(
-                                "[WS] Flow event send failed for connection {}",
-                                &conn_id_clone[..8]
-                            );
-                            break;
-                        }
-                    }
-                }
-                Err(tokio::sync::broadcast::error::RecvError::Lagged(n)) => {
-                    tracing::warn!(
-                        "[WS] Flow event receiver lagged by {} messages for connection {}",
-                        n,
-                        &conn_id_clone[..8]
-                    );
-                }
-                Err(tokio::sync::broadcast::error::RecvError::Closed) => {
-                    tracing::debug!(
-                        "[WS] Flow event channel closed for connection {}",
-                        &conn_id_clone[..8]
-                    );
-                    break;
-                }
-            }
-        }
-    });
+    // Flow 事件订阅状态（默认不订阅，需要管理密钥）
+    let mut flow_tail = FlowTailSession {
+        management_authorized,
+        sender: sender.clone(),
+        tasks: None,
+    };
 
     // 消息处理循环
     while let Some(msg) = receiver.next().await {
@@ -193,7 +194,7 @@ pub async fn handle_websocket(
                 match serde_json::from_str::<WsProtoMessage>(&text) {
                     Ok(ws_msg) => {
                         let response =
-                            handle_ws_message(&state, &conn_id, ws_msg, &flow_subscribed).await;
+                            handle_ws_message(&state, &conn_id, ws_msg, &mut flow_tail).await;
                         if let Some(resp) = response {
                             let resp_text = serde_json::to_string(&resp).unwrap_or_default();
                             let mut sender_guard = sender.lock().await;
@@ -262,7 +263,7 @@ pub async fn handle_websocket(
     }
 
     // 取消 Flow 事件转发任务
-    flow_task.abort();
+    flow_tail.stop();
 
     // 清理连接
     state.ws_manager.unregister(&conn_id);
@@ -272,24 +273,108 @@ pub async fn handle_websocket(
     );
 }
 
+type WsSender = Arc<Mutex<SplitSink<WebSocket, WsMessage>>>;
+
+/// 连接级 Flow 事件订阅
+///
+/// 每次订阅启动一个 tail 任务（过滤、序号、背压）和一个写出任务，
+/// 重新订阅、取消订阅或断开连接时中止
+struct FlowTailSession {
+    management_authorized: bool,
+    sender: WsSender,
+    tasks: Option<(JoinHandle<()>, JoinHandle<()>)>,
+}
+
+impl FlowTailSession {
+    fn start(
+        &mut self,
+        state: &AppState,
+        subscription: WsFlowSubscription,
+        filter: FlowTailFilter,
+    ) {
+        self.stop();
+
+        let queue_size = state.ws_manager.config().flow_event_queue_size.max(1);
+        let (tx, mut rx) = mpsc::channel::<WsFlowEventMessage>(queue_size);
+        let tail = tokio::spawn(run_flow_tail(
+            state.flow_event_journal.clone(),
+            subscription,
+            filter,
+            tx,
+        ));
+
+        let sender = self.sender.clone();
+        let writer = tokio::spawn(async move {
+            while let Some(message) = rx.recv().await {
+                let Ok(text) = serde_json::to_string(&WsProtoMessage::FlowEvent(message)) else {
+                    continue;
+                };
+                let mut sender_guard = sender.lock().await;
+                if sender_guard
+                    .send(WsMessage::Text(text.into()))
+                    .await
+                    .is_err()
+                {
+                    break;
+                }
+            }
+        });
+
+        self.tasks = Some((tail, writer));
+    }
+
+    fn stop(&mut self) {
+        if let Some((tail, writer)) = self.tasks.take() {
+            tail.abort();
+            writer.abort();
+        }
+    }
+}
+
+impl Drop for FlowTailSession {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
 /// 处理 WebSocket 消息
 async fn handle_ws_message(
     state: &AppState,
     conn_id: &str,
     msg: WsProtoMessage,
-    flow_subscribed: &Arc<std::sync::atomic::AtomicBool>,
+    flow_tail: &mut FlowTailSession,
 ) -> Option<WsProtoMessage> {
     match msg {
         WsProtoMessage::Ping { timestamp } => Some(WsProtoMessage::Pong { timestamp }),
         WsProtoMessage::Pong { .. } => None,
-        WsProtoMessage::SubscribeFlowEvents => {
+        WsProtoMessage::SubscribeFlowEvents(subscription) => {
+            if !flow_tail.management_authorized {
+                return Some(WsProtoMessage::Error(WsError::unauthorized(
+                    "Flow event subscription requires a valid management key",
+                )));
+            }
+
+            let filter = match FlowTailFilter::new(&subscription) {
+                Ok(filter) => filter,
+                Err(e) => {
+                    return Some(WsProtoMessage::Error(WsError::invalid_request(
+                        Some("subscribe_flow_events".to_string()),
+                        format!("Invalid flow filter: {}", e),
+                    )));
+                }
+            };
+
             // 订阅 Flow 事件
-            flow_subscribed.store(true, std::sync::atomic::Ordering::Relaxed);
+            let filter_text = subscription.filter.clone();
+            let resume_from = subscription.resume_from;
+            flow_tail.start(state, subscription, filter);
             state.logs.write().await.add(
                 "info",
                 &format!(
-                    "[WS] Connection {} subscribed to flow events",
-                    &conn_id[..8]
+                    "[WS] Connection {} subscribed to flow events (filter: {:?}, resume_from: {:?})",
+                    &conn_id[..8],
+                    filter_text,
+                    resume_from
                 ),
             );
             // 返回确认消息
@@ -297,13 +382,16 @@ async fn handle_ws_message(
                 request_id: "subscribe_flow_events".to_string(),
                 payload: serde_json::json!({
                     "status": "subscribed",
-                    "message": "Successfully subscribed to flow events"
+                    "message": "Successfully subscribed to flow events",
+                    "filter": filter_text,
+                    "latest_seq": state.flow_event_journal.latest_seq(),
+                    "epoch": state.flow_event_journal.epoch(),
                 }),
             }))
         }
         WsProtoMessage::UnsubscribeFlowEvents => {
             // 取消订阅 Flow 事件
-            flow_subscribed.store(false, std::sync::atomic::Ordering::Relaxed);
+            flow_tail.stop();
             state.logs.write().await.add(
                 "info",
                 &format!(
@@ -616,7 +704,15 @@ async fn handle_ws_anthropic_messages(
         let kiro = state.kiro.read().await;
 
         // 转换为 OpenAI 格式
-        let openai_request = convert_anthropic_to_openai(&request);
+        let openai_request = match canonical::anthropic_to_chat_completion(&request) {
+            Ok(openai_request) => openai_request,
+            Err(e) => {
+                return WsProtoMessage::Error(WsError::invalid_request(
+                    Some(request_id.to_string()),
+                    e.to_string(),
+                ));
+            }
+        };
 
         match kiro.call_api(&openai_request).await {
             Ok(resp) => {
@@ -981,30 +1077,13 @@ pub async fn call_provider_anthropic_for_ws(
         }
         _ => {
             // 转换为 OpenAI 格式并调用（健康状态更新在 call_provider_openai_for_ws 中处理）
-            let openai_request = convert_anthropic_to_openai(request);
+            let openai_request =
+                canonical::anthropic_to_chat_completion(request).map_err(|e| e.to_string())?;
             let result = call_provider_openai_for_ws(state, credential, &openai_request).await?;
 
-            // 转换响应为 Anthropic 格式
-            Ok(serde_json::json!({
-                "id": format!("msg_{}", uuid::Uuid::new_v4()),
-                "type": "message",
-                "role": "assistant",
-                "content": [{
-                    "type": "text",
-                    "text": result.get("choices")
-                        .and_then(|c| c.get(0))
-                        .and_then(|c| c.get("message"))
-                        .and_then(|m| m.get("content"))
-                        .and_then(|c| c.as_str())
-                        .unwrap_or("")
-                }],
-                "model": request.model,
-                "stop_reason": "end_turn",
-                "usage": {
-                    "input_tokens": 0,
-                    "output_tokens": 0
-                }
-            }))
+            // 经由 canonical 模型转换响应，保留工具调用与 usage
+            canonical::convert_response(Protocol::OpenAI, Protocol::Anthropic, &result)
+                .map_err(|e| e.to_string())
         }
     }
 }
@@ -2,11 +2,11 @@
 
 pub mod client_detector;
 
+use crate::agent::NativeAgentState;
 use crate::config::{
     Config, ConfigChangeEvent, ConfigChangeKind, ConfigManager, EndpointProvidersConfig,
-    FileWatcher, HotReloadManager, ReloadResult,
+    FileWatcher, HotReloadManager, ReloadResult, RemoteManagementConfig,
 };
-use crate::converter::anthropic_to_openai::convert_anthropic_to_openai;
 use crate::credential::CredentialSyncService;
 use crate::database::dao::provider_pool::ProviderPoolDao;
 use crate::database::DbConnection;
@@ -15,23 +15,21 @@ use crate::injection::Injector;
 use crate::logger::LogStore;
 use crate::models::anthropic::*;
 use crate::models::openai::*;
-use crate::models::provider_pool_model::CredentialData;
 use crate::models::route_model::{RouteInfo, RouteListResponse};
 use crate::processor::{RequestContext, RequestProcessor};
-use crate::providers::antigravity::AntigravityProvider;
 use crate::providers::claude_custom::ClaudeCustomProvider;
 use crate::providers::gemini::GeminiProvider;
 use crate::providers::kiro::KiroProvider;
 use crate::providers::openai_custom::OpenAICustomProvider;
 use crate::providers::qwen::QwenProvider;
 use crate::server_utils::{
-    build_anthropic_response, build_anthropic_stream_response, build_gemini_native_request, health,
-    models, parse_cw_response,
+    build_anthropic_response, build_anthropic_stream_response, health, models, parse_cw_response,
 };
 use crate::services::kiro_event_service::KiroEventService;
 use crate::services::provider_pool_service::ProviderPoolService;
 use crate::services::token_cache_service::TokenCacheService;
-use crate::websocket::{WsConfig, WsConnectionManager, WsStats};
+use crate::translator::canonical;
+use crate::websocket::{FlowEventJournal, WsConfig, WsConnectionManager, WsStats};
 use axum::{
     body::Body,
     extract::{DefaultBodyLimit, Path, State},
@@ -169,6 +167,8 @@ pub struct ServerState {
     /// 服务器运行时使用的 API key（启动时从配置复制）
     /// 用于 test_api 命令，确保测试使用的 API key 和服务器一致
     pub running_api_key: Option<String>,
+    /// 原生 Agent 状态（与 Tauri 命令共享会话），用于 `/v1/agent` HTTP 端点
+    pub native_agent: Option<NativeAgentState>,
 }
 
 impl ServerState {
@@ -193,9 +193,16 @@ impl ServerState {
             default_provider_ref,
             shutdown_tx: None,
             running_api_key: None,
+            native_agent: None,
         }
     }
 
+    /// 设置原生 Agent 状态，启用 `/v1/agent` HTTP 端点
+    pub fn with_native_agent(mut self, native_agent: NativeAgentState) -> Self {
+        self.native_agent = Some(native_agent);
+        self
+    }
+
     pub fn status(&self) -> ServerStatus {
         ServerStatus {
             running: self.running,
@@ -298,6 +305,7 @@ impl ServerState {
         // 获取配置和配置路径用于热重载
         let config = self.config.clone();
         let config_path = crate::config::ConfigManager::default_config_path();
+        let native_agent = self.native_agent.clone();
 
         tokio::spawn(async move {
             if let Err(e) = run_server(
@@ -320,6 +328,7 @@ impl ServerState {
                 shared_flow_interceptor,
                 Some(config),
                 Some(config_path),
+                native_agent,
             )
             .await
             {
@@ -394,6 +403,12 @@ pub struct AppState {
     pub endpoint_providers: Arc<RwLock<EndpointProvidersConfig>>,
     /// Kiro 事件服务
     pub kiro_event_service: Arc<KiroEventService>,
+    /// 远程管理配置（用于 WebSocket Flow 事件订阅认证）
+    pub remote_management: RemoteManagementConfig,
+    /// Flow 事件日志（WebSocket 实时订阅）
+    pub flow_event_journal: Arc<FlowEventJournal>,
+    /// 原生 Agent 状态（未提供时 `/v1/agent` 端点返回 503）
+    pub native_agent: Option<NativeAgentState>,
 }
 
 /// 启动配置文件监控
@@ -675,6 +690,7 @@ async fn run_server(
     shared_flow_interceptor: Option<Arc<FlowInterceptor>>,
     config: Option<Config>,
     config_path: Option<PathBuf>,
+    native_agent: Option<NativeAgentState>,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     let base_url = format!("http://{}:{}", host, port);
 
@@ -745,6 +761,18 @@ async fn run_server(
     // 创建 Kiro 事件服务
     let kiro_event_service = Arc::new(KiroEventService::new());
 
+    // 远程管理配置
+    let management_config = config
+        .as_ref()
+        .map(|c| c.remote_management.clone())
+        .unwrap_or_default();
+
+    // 创建 Flow 事件日志，供 WebSocket 订阅续订
+    let flow_event_journal = Arc::new(FlowEventJournal::new(
+        ws_manager.config().flow_event_history,
+    ));
+    let flow_journal_source = (flow_event_journal.clone(), flow_monitor.clone());
+
     let state = AppState {
         api_key: api_key.to_string(),
         base_url,
@@ -769,6 +797,9 @@ async fn run_server(
         flow_interceptor,
         endpoint_providers,
         kiro_event_service,
+        remote_management: management_config.clone(),
+        flow_event_journal,
+        native_agent,
     };
 
     // 启动配置文件监控
@@ -790,11 +821,6 @@ async fn run_server(
     let body_limit = 100 * 1024 * 1024; // 100MB
 
     // 创建管理 API 路由（带认证中间件）
-    let management_config = config
-        .as_ref()
-        .map(|c| c.remote_management.clone())
-        .unwrap_or_default();
-
     let management_routes = Router::new()
         .route("/v0/management/status", get(handlers::management_status))
         .route(
@@ -844,6 +870,29 @@ async fn run_server(
             get(handlers::credentials_get_token),
         );
 
+    // 原生 Agent API 路由（服务端执行工具）
+    let agent_api_routes = Router::new()
+        .route(
+            "/v1/agent/sessions",
+            get(handlers::agent_list_sessions).post(handlers::agent_create_session),
+        )
+        .route(
+            "/v1/agent/sessions/:session_id",
+            get(handlers::agent_get_session),
+        )
+        .route(
+            "/v1/agent/sessions/:session_id/messages",
+            post(handlers::agent_send_message),
+        )
+        .route(
+            "/v1/agent/approvals",
+            get(handlers::agent_pending_approvals),
+        )
+        .route(
+            "/v1/agent/approvals/:request_id",
+            post(handlers::agent_resolve_approval),
+        );
+
     let app = Router::new()
         .route("/health", get(health))
         .route("/v1/models", get(models))
@@ -852,7 +901,10 @@ async fn run_server(
         .route("/v1/messages", post(handlers::anthropic_messages))
         .route("/v1/messages/count_tokens", post(count_tokens))
         // Gemini 原生协议路由
-        .route("/v1/gemini/*path", post(gemini_generate_content))
+        .route(
+            "/v1/gemini/*path",
+            post(handlers::gemini_generate_content),
+        )
         // WebSocket 路由
         .route("/v1/ws", get(handlers::ws_upgrade_handler))
         .route("/ws", get(handlers::ws_upgrade_handler))
@@ -886,6 +938,8 @@ async fn run_server(
         .merge(kiro_api_routes)
         // 凭证 API 路由（用于 aster Agent 集成）
         .merge(credentials_api_routes)
+        // 原生 Agent API 路由
+        .merge(agent_api_routes)
         .layer(DefaultBodyLimit::max(body_limit))
         .with_state(state);
 
@@ -894,11 +948,21 @@ async fn run_server(
 
     tracing::info!("Server listening on {}", addr);
 
-    axum::serve(listener, app)
-        .with_graceful_shutdown(async move {
-            let _ = shutdown.await;
-        })
-        .await?;
+    let (journal, monitor) = flow_journal_source;
+    let flow_journal_task = journal.spawn(monitor);
+
+    // 记录连接地址，供管理密钥认证判断是否来自 localhost
+    let serve_result = axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<std::net::SocketAddr>(),
+    )
+    .with_graceful_shutdown(async move {
+        let _ = shutdown.await;
+    })
+    .await;
+
+    flow_journal_task.abort();
+    serve_result?;
 
     Ok(())
 }
@@ -919,243 +983,6 @@ async fn count_tokens(
     .into_response()
 }
 
-/// Gemini 原生协议处理
-/// 路由: POST /v1/gemini/{model}:{method}
-/// 例如: /v1/gemini/gemini-3-pro-preview:generateContent
-async fn gemini_generate_content(
-    State(state): State<AppState>,
-    headers: HeaderMap,
-    Path(path): Path<String>,
-    Json(request): Json<serde_json::Value>,
-) -> Response {
-    if let Err(e) = handlers::verify_api_key(&headers, &state.api_key).await {
-        return e.into_response();
-    }
-
-    // 解析路径: {model}:{method}
-    // 例如: gemini-3-pro-preview:generateContent
-    let parts: Vec<&str> = path.splitn(2, ':').collect();
-    if parts.len() != 2 {
-        return (
-            StatusCode::BAD_REQUEST,
-            Json(serde_json::json!({
-                "error": {
-                    "message": format!("无效的路径格式: {}，期望格式: model:method", path)
-                }
-            })),
-        )
-            .into_response();
-    }
-
-    let model = parts[0];
-    let method = parts[1];
-
-    state.logs.write().await.add(
-        "info",
-        &format!(
-            "[GEMINI] POST /v1/gemini/{} model={} method={}",
-            path, model, method
-        ),
-    );
-
-    // 目前只支持 generateContent 方法
-    if method != "generateContent" && method != "streamGenerateContent" {
-        return (
-            StatusCode::BAD_REQUEST,
-            Json(serde_json::json!({
-                "error": {
-                    "message": format!("不支持的方法: {}，目前只支持 generateContent", method)
-                }
-            })),
-        )
-            .into_response();
-    }
-
-    let is_stream = method == "streamGenerateContent";
-
-    // 获取默认 provider
-    let default_provider = state.default_provider.read().await.clone();
-
-    // 尝试从凭证池中选择 Antigravity 凭证
-    let credential = match &state.db {
-        Some(db) => state
-            .pool_service
-            .select_credential(db, &default_provider, Some(model))
-            .ok()
-            .flatten(),
-        None => None,
-    };
-
-    let cred = match credential {
-        Some(c) => c,
-        None => {
-            return (
-                StatusCode::NOT_FOUND,
-                Json(serde_json::json!({
-                    "error": {
-                        "message": "没有可用的 Antigravity 凭证，请先添加凭证"
-                    }
-                })),
-            )
-                .into_response();
-        }
-    };
-
-    state.logs.write().await.add(
-        "info",
-        &format!(
-            "[GEMINI] 使用凭证: type={} name={:?} uuid={}",
-            cred.provider_type,
-            cred.name,
-            &cred.uuid[..8]
-        ),
-    );
-
-    // 调用 Antigravity Provider
-    match &cred.credential {
-        CredentialData::AntigravityOAuth {
-            creds_file_path,
-            project_id,
-        } => {
-            let mut antigravity = AntigravityProvider::new();
-            if let Err(e) = antigravity
-                .load_credentials_from_path(creds_file_path)
-                .await
-            {
-                return (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    Json(serde_json::json!({
-                        "error": {
-                            "message": format!("加载 Antigravity 凭证失败: {}", e)
-                        }
-                    })),
-                )
-                    .into_response();
-            }
-
-            // 检查并刷新 token
-            if antigravity.is_token_expiring_soon() {
-                if let Err(e) = antigravity.refresh_token().await {
-                    return (
-                        StatusCode::UNAUTHORIZED,
-                        Json(serde_json::json!({
-                            "error": {
-                                "message": format!("Token 刷新失败: {}", e)
-                            }
-                        })),
-                    )
-                        .into_response();
-                }
-            }
-
-            // 设置项目 ID
-            if let Some(pid) = project_id {
-                antigravity.project_id = Some(pid.clone());
-            } else if antigravity.project_id.is_none() {
-                // 如果凭证中没有 project_id，尝试从 API 获取或生成随机 ID
-                if let Err(e) = antigravity.discover_project().await {
-                    tracing::warn!("[Antigravity] 获取项目 ID 失败: {}，使用随机生成的 ID", e);
-                    // 生成随机项目 ID
-                    let uuid = uuid::Uuid::new_v4();
-                    let bytes = uuid.as_bytes();
-                    let adjectives = ["useful", "bright", "swift", "calm", "bold"];
-                    let nouns = ["fuze", "wave", "spark", "flow", "core"];
-                    let adj = adjectives[(bytes[0] as usize) % adjectives.len()];
-                    let noun = nouns[(bytes[1] as usize) % nouns.len()];
-                    let random_part: String = uuid.to_string()[..5].to_lowercase();
-                    antigravity.project_id = Some(format!("{}-{}-{}", adj, noun, random_part));
-                }
-            }
-
-            let proj_id = antigravity.project_id.clone().unwrap_or_else(|| {
-                // 最后的后备：生成随机 ID
-                let uuid = uuid::Uuid::new_v4();
-                format!("proxycast-{}", &uuid.to_string()[..8])
-            });
-
-            state
-                .logs
-                .write()
-                .await
-                .add("debug", &format!("[GEMINI] 使用 project_id: {}", proj_id));
-
-            // 构建 Antigravity 请求体
-            // 直接使用用户传入的 Gemini 格式请求，只添加必要的字段
-            let antigravity_request = build_gemini_native_request(&request, model, &proj_id);
-
-            state.logs.write().await.add(
-                "debug",
-                &format!(
-                    "[GEMINI] 请求体: {}",
-                    serde_json::to_string(&antigravity_request).unwrap_or_default()
-                ),
-            );
-
-            if is_stream {
-                // 流式响应 - 暂不支持，返回错误
-                return (
-                    StatusCode::NOT_IMPLEMENTED,
-                    Json(serde_json::json!({
-                        "error": {
-                            "message": "流式响应暂不支持，请使用 generateContent"
-                        }
-                    })),
-                )
-                    .into_response();
-            }
-
-            // 非流式响应
-            match antigravity
-                .call_api("generateContent", &antigravity_request)
-                .await
-            {
-                Ok(resp) => {
-                    state.logs.write().await.add(
-                        "info",
-                        &format!(
-                            "[GEMINI] 响应成功: {}",
-                            serde_json::to_string(&resp)
-                                .unwrap_or_default()
-                                .chars()
-                                .take(200)
-                                .collect::<String>()
-                        ),
-                    );
-
-                    // 直接返回 Gemini 格式响应
-                    Json(resp).into_response()
-                }
-                Err(e) => {
-                    state
-                        .logs
-                        .write()
-                        .await
-                        .add("error", &format!("[GEMINI] 请求失败: {}", e));
-
-                    (
-                        StatusCode::INTERNAL_SERVER_ERROR,
-                        Json(serde_json::json!({
-                            "error": {
-                                "message": e.to_string()
-                            }
-                        })),
-                    )
-                        .into_response()
-                }
-            }
-        }
-        _ => (
-            StatusCode::BAD_REQUEST,
-            Json(serde_json::json!({
-                "error": {
-                    "message": "Gemini 原生协议只支持 Antigravity 凭证"
-                }
-            })),
-        )
-            .into_response(),
-    }
-}
-
 /// 列出所有可用路由
 async fn list_routes(State(state): State<AppState>) -> impl IntoResponse {
     let routes = match &state.db {
@@ -1772,7 +1599,16 @@ async fn anthropic_messages_internal(
         }
     }
 
-    let openai_request = convert_anthropic_to_openai(request);
+    let openai_request = match canonical::anthropic_to_chat_completion(request) {
+        Ok(openai_request) => openai_request,
+        Err(e) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({"error": {"message": e.to_string()}})),
+            )
+                .into_response();
+        }
+    };
     let kiro = state.kiro.read().await;
 
     match kiro.call_api(&openai_request).await {
@@ -3,6 +3,8 @@
 //! 包含响应解析、字符串处理、响应构建等公共工具函数。
 
 use crate::models::openai::{ContentPart, FunctionCall, MessageContent, ToolCall};
+use crate::plugin::{HookAction, ResponseFormat};
+use crate::translator::tool_emulation::extract_bracket_tool_calls;
 use axum::{
     body::Body,
     http::{header, StatusCode},
@@ -289,37 +291,26 @@ pub fn extract_json_object(s: &str) -> Option<&str> {
 ///
 /// 格式: [Called xxx with args: {...}]
 pub fn parse_bracket_tool_calls(result: &mut CWParsedResponse) {
-    let re =
-        regex::Regex::new(r"\[Called\s+(\w+)\s+with\s+args:\s*(\{[^}]*(?:\{[^}]*\}[^}]*)*\})\]")
-            .ok();
-
-    if let Some(re) = re {
-        let mut to_remove = Vec::new();
-        for cap in re.captures_iter(&result.content) {
-            if let (Some(name), Some(args)) = (cap.get(1), cap.get(2)) {
-                let tool_id = format!(
-                    "call_{}",
-                    &uuid::Uuid::new_v4().to_string().replace('-', "")[..8]
-                );
-                result.tool_calls.push(ToolCall {
-                    id: tool_id,
-                    call_type: "function".to_string(),
-                    function: FunctionCall {
-                        name: name.as_str().to_string(),
-                        arguments: args.as_str().to_string(),
-                    },
-                });
-                if let Some(full_match) = cap.get(0) {
-                    to_remove.push(full_match.as_str().to_string());
-                }
-            }
-        }
-        // 从 content 中移除 tool call 文本
-        for s in to_remove {
-            result.content = result.content.replace(&s, "");
-        }
-        result.content = result.content.trim().to_string();
+    let (content, calls) = extract_bracket_tool_calls(&result.content);
+    if calls.is_empty() {
+        return;
     }
+    for call in calls {
+        let tool_id = format!(
+            "call_{}",
+            &uuid::Uuid::new_v4().to_string().replace('-', "")[..8]
+        );
+        result.tool_calls.push(ToolCall {
+            id: tool_id,
+            call_type: "function".to_string(),
+            function: FunctionCall {
+                name: call.name,
+                arguments: call.arguments.to_string(),
+            },
+        });
+    }
+    // 从 content 中移除 tool call 文本
+    result.content = content.trim().to_string();
 }
 
 /// 构建 Anthropic 非流式响应
@@ -522,6 +513,44 @@ pub fn build_anthropic_stream_response(model: &str, parsed: &CWParsedResponse) -
         })
 }
 
+/// 构建插件短路响应
+///
+/// 合成响应按客户端格式渲染为 JSON 或 SSE，拒绝渲染为带对应状态码的错误响应
+pub fn build_plugin_action_response(
+    action: &HookAction,
+    format: ResponseFormat,
+    model: &str,
+    stream: bool,
+) -> Response {
+    match action {
+        HookAction::Reject(rejection) => {
+            let status =
+                StatusCode::from_u16(rejection.status_code()).unwrap_or(StatusCode::BAD_REQUEST);
+            (status, Json(rejection.to_json(format))).into_response()
+        }
+        HookAction::Respond(response) if stream => {
+            let events = response.to_sse(format, model);
+            let body_stream =
+                stream::iter(events.into_iter().map(Ok::<_, std::convert::Infallible>));
+
+            Response::builder()
+                .status(StatusCode::OK)
+                .header(header::CONTENT_TYPE, "text/event-stream")
+                .header(header::CACHE_CONTROL, "no-cache")
+                .header(header::CONNECTION, "keep-alive")
+                .body(Body::from_stream(body_stream))
+                .unwrap_or_else(|e| {
+                    tracing::error!("Failed to build SSE response: {}", e);
+                    Response::builder()
+                        .status(StatusCode::INTERNAL_SERVER_ERROR)
+                        .body(Body::empty())
+                        .unwrap_or_default()
+                })
+        }
+        HookAction::Respond(response) => Json(response.to_json(format, model)).into_response(),
+    }
+}
+
 /// 构建 Gemini 原生请求体
 ///
 /// 将用户传入的 Gemini 格式请求转换为 Antigravity 请求格式
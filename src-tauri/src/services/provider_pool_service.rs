@@ -12,6 +12,7 @@ use crate::models::provider_pool_model::{
     ProviderPoolOverview,
 };
 use crate::models::route_model::RouteInfo;
+use crate::plugin::plugin_provider_id;
 use crate::providers::kiro::KiroProvider;
 use chrono::Utc;
 use reqwest::Client;
@@ -167,6 +168,22 @@ impl ProviderPoolService {
         Ok(cred)
     }
 
+    /// 更新凭证数据（如插件刷新后的凭证字段）
+    pub fn update_credential_data(
+        &self,
+        db: &DbConnection,
+        uuid: &str,
+        credential: CredentialData,
+    ) -> Result<(), String> {
+        let conn = db.lock().map_err(|e| e.to_string())?;
+        let mut cred = ProviderPoolDao::get_by_uuid(&conn, uuid)
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| format!("Credential not found: {}", uuid))?;
+        cred.credential = credential;
+        cred.updated_at = Utc::now();
+        ProviderPoolDao::update(&conn, &cred).map_err(|e| e.to_string())
+    }
+
     /// 删除凭证
     pub fn delete_credential(&self, db: &DbConnection, uuid: &str) -> Result<bool, String> {
         let conn = db.lock().map_err(|e| e.to_string())?;
@@ -197,6 +214,11 @@ impl ProviderPoolService {
             .filter(|c| c.is_available())
             .collect();
 
+        // 插件 Provider 共用 Plugin 类型，按 "plugin:<id>" 中的插件 Provider ID 过滤
+        if let Some(id) = plugin_provider_id(provider_type) {
+            available.retain(|c| c.credential.plugin_provider() == Some(id));
+        }
+
         // 如果指定了模型，进一步过滤支持该模型的凭证
         if let Some(m) = model {
             available.retain(|c| c.supports_model(m));
@@ -392,6 +414,20 @@ impl ProviderPoolService {
             .clone()
             .unwrap_or_else(|| get_default_check_model(cred.provider_type).to_string());
 
+        // 插件 Provider 没有主动健康检查钩子，跳过检查且不改变健康状态
+        if let CredentialData::Plugin { provider, .. } = &cred.credential {
+            return Ok(HealthCheckResult {
+                uuid: uuid.to_string(),
+                success: true,
+                model: Some(check_model),
+                message: Some(format!(
+                    "插件 Provider {} 不支持主动健康检查，已跳过",
+                    provider
+                )),
+                duration_ms: 0,
+            });
+        }
+
         let start = std::time::Instant::now();
         let result = self
             .perform_health_check(&cred.credential, &check_model)
@@ -576,6 +612,8 @@ impl ProviderPoolService {
             CredentialData::IFlowCookie { creds_file_path } => {
                 self.check_iflow_cookie_health(creds_file_path, model).await
             }
+            // 由 check_credential_health 提前跳过
+            CredentialData::Plugin { .. } => Ok(()),
         }
     }
 
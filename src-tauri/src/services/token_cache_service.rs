@@ -494,6 +494,10 @@ impl TokenCacheService {
             CredentialData::IFlowCookie { creds_file_path } => {
                 self.refresh_iflow_cookie(creds_file_path).await
             }
+            CredentialData::Plugin { provider, .. } => Err(format!(
+                "插件 Provider {} 的凭证由插件在请求时刷新",
+                provider
+            )),
         }
     }
 
@@ -1057,6 +1061,17 @@ impl TokenCacheService {
                     last_refresh_error: None,
                 })
             }
+            CredentialData::Plugin { fields, .. } => Ok(CachedTokenInfo {
+                access_token: fields["access_token"]
+                    .as_str()
+                    .or_else(|| fields["api_key"].as_str())
+                    .map(|s| s.to_string()),
+                refresh_token: None,
+                expiry_time: None,
+                last_refresh: None,
+                refresh_error_count: 0,
+                last_refresh_error: None,
+            }),
         }
     }
 
@@ -47,6 +47,30 @@ pub enum StreamEvent {
         text: String,
     },
 
+    /// 思维链内容增量
+    ///
+    /// 对应 Thinking 内容块的增量输出
+    ThinkingDelta {
+        /// 思维链文本
+        text: String,
+    },
+
+    /// 签名增量
+    ///
+    /// 附加到当前内容块的签名（Anthropic thinking 签名、Gemini thoughtSignature）
+    SignatureDelta {
+        /// 签名
+        signature: String,
+    },
+
+    /// 引用增量
+    ///
+    /// 附加到当前文本块的引用信息（Anthropic citations），保持原始 JSON 结构
+    CitationDelta {
+        /// 引用对象
+        citation: serde_json::Value,
+    },
+
     /// 工具调用开始
     ///
     /// 表示一个新的工具调用开始
@@ -143,6 +167,13 @@ pub enum ContentBlockType {
         /// 工具名称
         name: String,
     },
+    /// 思维链
+    Thinking,
+    /// 已加密的思维链（内容在块开始时一次性给出）
+    RedactedThinking {
+        /// 加密数据
+        data: String,
+    },
 }
 
 /// 停止原因
@@ -63,6 +63,8 @@ pub struct AnthropicSseGenerator {
     cache_creation_input_tokens: u32,
     /// 累积的停止原因
     stop_reason: Option<StopReason>,
+    /// 当前打开的内容块索引（文本、思维链增量写入该块）
+    current_block_index: u32,
 }
 
 impl Default for AnthropicSseGenerator {
@@ -84,6 +86,7 @@ impl AnthropicSseGenerator {
             cache_read_input_tokens: 0,
             cache_creation_input_tokens: 0,
             stop_reason: None,
+            current_block_index: 0,
         }
     }
 
@@ -99,6 +102,7 @@ impl AnthropicSseGenerator {
             cache_read_input_tokens: 0,
             cache_creation_input_tokens: 0,
             stop_reason: None,
+            current_block_index: 0,
         }
     }
 
@@ -129,10 +133,17 @@ impl AnthropicSseGenerator {
             }
 
             StreamEvent::ContentBlockStart { index, block_type } => {
+                self.current_block_index = *index;
                 match block_type {
                     ContentBlockType::Text => {
                         sse_events.push(self.create_content_block_start_text(*index));
                     }
+                    ContentBlockType::Thinking => {
+                        sse_events.push(self.create_content_block_start_thinking(*index));
+                    }
+                    ContentBlockType::RedactedThinking { data } => {
+                        sse_events.push(self.create_content_block_start_redacted(*index, data));
+                    }
                     ContentBlockType::ToolUse { id, name } => {
                         // 记录工具调用状态
                         self.tool_calls.insert(
@@ -150,8 +161,28 @@ impl AnthropicSseGenerator {
             }
 
             StreamEvent::TextDelta { text } => {
-                // 假设文本块索引为 0（大多数情况下）
-                sse_events.push(self.create_text_delta(0, text));
+                sse_events.push(self.create_text_delta(self.current_block_index, text));
+            }
+
+            StreamEvent::ThinkingDelta { text } => {
+                sse_events.push(self.create_block_delta(
+                    self.current_block_index,
+                    serde_json::json!({"type": "thinking_delta", "thinking": text}),
+                ));
+            }
+
+            StreamEvent::SignatureDelta { signature } => {
+                sse_events.push(self.create_block_delta(
+                    self.current_block_index,
+                    serde_json::json!({"type": "signature_delta", "signature": signature}),
+                ));
+            }
+
+            StreamEvent::CitationDelta { citation } => {
+                sse_events.push(self.create_block_delta(
+                    self.current_block_index,
+                    serde_json::json!({"type": "citations_delta", "citation": citation}),
+                ));
             }
 
             StreamEvent::ToolUseStart { id, name } => {
@@ -280,6 +311,30 @@ impl AnthropicSseGenerator {
         format!("event: content_block_start\ndata: {}\n\n", event)
     }
 
+    fn create_content_block_start_thinking(&self, index: u32) -> String {
+        let event = serde_json::json!({
+            "type": "content_block_start",
+            "index": index,
+            "content_block": {
+                "type": "thinking",
+                "thinking": ""
+            }
+        });
+        format!("event: content_block_start\ndata: {}\n\n", event)
+    }
+
+    fn create_content_block_start_redacted(&self, index: u32, data: &str) -> String {
+        let event = serde_json::json!({
+            "type": "content_block_start",
+            "index": index,
+            "content_block": {
+                "type": "redacted_thinking",
+                "data": data
+            }
+        });
+        format!("event: content_block_start\ndata: {}\n\n", event)
+    }
+
     fn create_content_block_start_tool(&self, index: u32, id: &str, name: &str) -> String {
         let event = serde_json::json!({
             "type": "content_block_start",
@@ -295,13 +350,17 @@ impl AnthropicSseGenerator {
     }
 
     fn create_text_delta(&self, index: u32, text: &str) -> String {
+        self.create_block_delta(
+            index,
+            serde_json::json!({"type": "text_delta", "text": text}),
+        )
+    }
+
+    fn create_block_delta(&self, index: u32, delta: serde_json::Value) -> String {
         let event = serde_json::json!({
             "type": "content_block_delta",
             "index": index,
-            "delta": {
-                "type": "text_delta",
-                "text": text
-            }
+            "delta": delta
         });
         format!("event: content_block_delta\ndata: {}\n\n", event)
     }
@@ -327,15 +386,23 @@ impl AnthropicSseGenerator {
     }
 
     fn create_message_delta(&self, stop_reason: &StopReason) -> String {
+        let mut usage = serde_json::json!({
+            "input_tokens": self.input_tokens,
+            "output_tokens": self.output_tokens
+        });
+        if self.cache_read_input_tokens > 0 {
+            usage["cache_read_input_tokens"] = self.cache_read_input_tokens.into();
+        }
+        if self.cache_creation_input_tokens > 0 {
+            usage["cache_creation_input_tokens"] = self.cache_creation_input_tokens.into();
+        }
         let event = serde_json::json!({
             "type": "message_delta",
             "delta": {
                 "stop_reason": stop_reason.to_anthropic_str(),
                 "stop_sequence": serde_json::Value::Null
             },
-            "usage": {
-                "output_tokens": self.output_tokens
-            }
+            "usage": usage
         });
         format!("event: message_delta\ndata: {}\n\n", event)
     }
@@ -0,0 +1,363 @@
+//! Gemini SSE 生成器
+//!
+//! 将 `StreamEvent` 转换为 Gemini `streamGenerateContent?alt=sse` 格式。
+//!
+//! # 格式说明
+//!
+//! Gemini 流式响应的每个分块都是完整的 `GenerateContentResponse`：
+//! ```text
+//! data: {"candidates":[{"content":{"role":"model","parts":[{"text":"Hello"}]},"index":0}]}
+//!
+//! data: {"candidates":[{"content":{"role":"model","parts":[{"functionCall":{"name":"read_file","args":{"path":"a.txt"}}}]},"index":0}]}
+//!
+//! data: {"candidates":[{"content":{"role":"model","parts":[]},"finishReason":"STOP","index":0}],"usageMetadata":{...}}
+//! ```
+//!
+//! Gemini 的 functionCall 不支持增量参数，生成器会累积参数并在工具调用块结束时一次性输出。
+
+use crate::stream::events::{ContentBlockType, StopReason, StreamEvent};
+use serde_json::{json, Value};
+
+/// 工具调用累积状态
+#[derive(Debug, Clone, Default)]
+struct PendingFunctionCall {
+    /// 工具调用 ID
+    id: String,
+    /// 工具名称
+    name: String,
+    /// 累积的参数 JSON
+    arguments: String,
+    /// thoughtSignature
+    signature: Option<String>,
+}
+
+/// 当前内容块
+#[derive(Debug, Clone)]
+enum CurrentBlock {
+    Text,
+    Thinking,
+    ToolUse(PendingFunctionCall),
+    Other,
+}
+
+/// Gemini SSE 生成器
+#[derive(Debug)]
+pub struct GeminiSseGenerator {
+    /// 响应 ID
+    response_id: String,
+    /// 模型名称
+    model: String,
+    /// 当前内容块
+    current: Option<CurrentBlock>,
+    /// 使用量（promptTokenCount, candidatesTokenCount, cachedContentTokenCount）
+    usage: Option<(u32, u32, Option<u32>)>,
+}
+
+impl Default for GeminiSseGenerator {
+    fn default() -> Self {
+        Self::new("unknown".to_string())
+    }
+}
+
+impl GeminiSseGenerator {
+    /// 创建新的生成器
+    pub fn new(model: String) -> Self {
+        Self::with_id(uuid::Uuid::new_v4().simple().to_string(), model)
+    }
+
+    /// 使用指定的响应 ID 创建生成器
+    pub fn with_id(id: String, model: String) -> Self {
+        Self {
+            response_id: id,
+            model,
+            current: None,
+            usage: None,
+        }
+    }
+
+    /// 将 StreamEvent 转换为 Gemini SSE 字符串
+    ///
+    /// # 返回
+    ///
+    /// - `Some(String)` - 生成的 SSE 字符串（包含 `data: ` 前缀和换行）
+    /// - `None` - 该事件不需要生成 SSE 输出
+    pub fn generate(&mut self, event: &StreamEvent) -> Option<String> {
+        match event {
+            StreamEvent::MessageStart { id, model } => {
+                self.response_id = id.clone();
+                self.model = model.clone();
+                None
+            }
+
+            StreamEvent::ContentBlockStart { block_type, .. } => {
+                self.current = Some(match block_type {
+                    ContentBlockType::Text => CurrentBlock::Text,
+                    ContentBlockType::Thinking => CurrentBlock::Thinking,
+                    ContentBlockType::ToolUse { id, name } => {
+                        CurrentBlock::ToolUse(PendingFunctionCall {
+                            id: id.clone(),
+                            name: name.clone(),
+                            ..Default::default()
+                        })
+                    }
+                    // Gemini 没有加密思维链
+                    ContentBlockType::RedactedThinking { .. } => CurrentBlock::Other,
+                });
+                None
+            }
+
+            StreamEvent::TextDelta { text } => Some(self.chunk(vec![json!({ "text": text })])),
+
+            StreamEvent::ThinkingDelta { text } => {
+                Some(self.chunk(vec![json!({ "text": text, "thought": true })]))
+            }
+
+            StreamEvent::SignatureDelta { signature } => match &mut self.current {
+                Some(CurrentBlock::ToolUse(call)) => {
+                    call.signature = Some(signature.clone());
+                    None
+                }
+                Some(CurrentBlock::Thinking) => Some(self.chunk(vec![json!({
+                    "text": "",
+                    "thought": true,
+                    "thoughtSignature": signature,
+                })])),
+                _ => None,
+            },
+
+            StreamEvent::ToolUseStart { id, name } => {
+                if !matches!(self.current, Some(CurrentBlock::ToolUse(_))) {
+                    self.current = Some(CurrentBlock::ToolUse(PendingFunctionCall {
+                        id: id.clone(),
+                        name: name.clone(),
+                        ..Default::default()
+                    }));
+                }
+                None
+            }
+
+            StreamEvent::ToolUseInputDelta { partial_json, .. } => {
+                if let Some(CurrentBlock::ToolUse(call)) = &mut self.current {
+                    call.arguments.push_str(partial_json);
+                }
+                None
+            }
+
+            StreamEvent::ToolUseStop { .. } | StreamEvent::ContentBlockStop { .. } => {
+                match self.current.take() {
+                    Some(CurrentBlock::ToolUse(call)) => {
+                        Some(self.chunk(vec![function_call_part(&call)]))
+                    }
+                    _ => None,
+                }
+            }
+
+            StreamEvent::MessageStop { stop_reason } => {
+                let mut response = self.response(Vec::new());
+                response["candidates"][0]["finishReason"] = json!(finish_reason(stop_reason));
+                if let Some((prompt, candidates, cached)) = self.usage {
+                    let mut usage = json!({
+                        "promptTokenCount": prompt,
+                        "candidatesTokenCount": candidates,
+                        "totalTokenCount": prompt + candidates,
+                    });
+                    if let Some(cached) = cached {
+                        usage["cachedContentTokenCount"] = json!(cached);
+                    }
+                    response["usageMetadata"] = usage;
+                }
+                Some(format!("data: {}\n\n", response))
+            }
+
+            StreamEvent::Usage {
+                input_tokens,
+                output_tokens,
+                cache_read_input_tokens,
+                cache_creation_input_tokens,
+            } => {
+                // promptTokenCount 包含缓存命中的 token
+                let prompt = input_tokens
+                    + cache_read_input_tokens.unwrap_or(0)
+                    + cache_creation_input_tokens.unwrap_or(0);
+                self.usage = Some((prompt, *output_tokens, *cache_read_input_tokens));
+                None
+            }
+
+            StreamEvent::Error {
+                error_type,
+                message,
+            } => {
+                let error = json!({
+                    "error": {
+                        "code": 500,
+                        "message": message,
+                        "status": error_type,
+                    }
+                });
+                Some(format!("data: {}\n\n", error))
+            }
+
+            StreamEvent::CitationDelta { .. } | StreamEvent::BackendUsage { .. } => None,
+
+            StreamEvent::Ping => None,
+        }
+    }
+
+    /// 获取响应 ID
+    pub fn response_id(&self) -> &str {
+        &self.response_id
+    }
+
+    fn chunk(&self, parts: Vec<Value>) -> String {
+        format!("data: {}\n\n", self.response(parts))
+    }
+
+    fn response(&self, parts: Vec<Value>) -> Value {
+        json!({
+            "candidates": [{
+                "content": { "role": "model", "parts": parts },
+                "index": 0,
+            }],
+            "modelVersion": self.model,
+            "responseId": self.response_id,
+        })
+    }
+}
+
+fn function_call_part(call: &PendingFunctionCall) -> Value {
+    let args = if call.arguments.trim().is_empty() {
+        json!({})
+    } else {
+        serde_json::from_str(&call.arguments).unwrap_or_else(|_| json!({}))
+    };
+    let mut part = json!({
+        "functionCall": {
+            "id": call.id,
+            "name": call.name,
+            "args": args,
+        }
+    });
+    if let Some(signature) = &call.signature {
+        part["thoughtSignature"] = json!(signature);
+    }
+    part
+}
+
+/// 转换为 Gemini finishReason
+pub fn finish_reason(stop_reason: &StopReason) -> &str {
+    match stop_reason {
+        StopReason::EndTurn | StopReason::ToolUse | StopReason::StopSequence => "STOP",
+        StopReason::MaxTokens => "MAX_TOKENS",
+        // OpenAI 的内容过滤和 Anthropic 的拒答都对应 Gemini 的安全拦截
+        StopReason::Other(reason) if reason == "content_filter" || reason == "refusal" => "SAFETY",
+        StopReason::Other(reason) => reason,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn data(sse: &str) -> Value {
+        serde_json::from_str(sse.trim().strip_prefix("data: ").unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_generate_text_and_thinking() {
+        let mut generator = GeminiSseGenerator::new("gemini-2.5-pro".to_string());
+        let sse = generator
+            .generate(&StreamEvent::ThinkingDelta {
+                text: "想一想".to_string(),
+            })
+            .unwrap();
+        let part = &data(&sse)["candidates"][0]["content"]["parts"][0];
+        assert_eq!(part["thought"], true);
+        assert_eq!(part["text"], "想一想");
+
+        let sse = generator
+            .generate(&StreamEvent::TextDelta {
+                text: "Hello".to_string(),
+            })
+            .unwrap();
+        assert_eq!(
+            data(&sse)["candidates"][0]["content"]["parts"][0]["text"],
+            "Hello"
+        );
+    }
+
+    #[test]
+    fn test_generate_function_call_at_block_stop() {
+        let mut generator = GeminiSseGenerator::new("gemini-2.5-pro".to_string());
+        generator.generate(&StreamEvent::ContentBlockStart {
+            index: 0,
+            block_type: ContentBlockType::ToolUse {
+                id: "call_1".to_string(),
+                name: "read_file".to_string(),
+            },
+        });
+        assert!(generator
+            .generate(&StreamEvent::ToolUseInputDelta {
+                id: "call_1".to_string(),
+                partial_json: "{\"path\":".to_string(),
+            })
+            .is_none());
+        generator.generate(&StreamEvent::ToolUseInputDelta {
+            id: "call_1".to_string(),
+            partial_json: "\"a.txt\"}".to_string(),
+        });
+        generator.generate(&StreamEvent::SignatureDelta {
+            signature: "sig".to_string(),
+        });
+
+        let sse = generator
+            .generate(&StreamEvent::ToolUseStop {
+                id: "call_1".to_string(),
+            })
+            .unwrap();
+        let part = &data(&sse)["candidates"][0]["content"]["parts"][0];
+        assert_eq!(part["functionCall"]["name"], "read_file");
+        assert_eq!(part["functionCall"]["args"]["path"], "a.txt");
+        assert_eq!(part["thoughtSignature"], "sig");
+        // 块结束事件不再重复输出
+        assert!(generator
+            .generate(&StreamEvent::ContentBlockStop { index: 0 })
+            .is_none());
+    }
+
+    #[test]
+    fn test_generate_finish_with_usage() {
+        let mut generator = GeminiSseGenerator::new("gemini-2.5-pro".to_string());
+        generator.generate(&StreamEvent::Usage {
+            input_tokens: 10,
+            output_tokens: 5,
+            cache_read_input_tokens: Some(4),
+            cache_creation_input_tokens: None,
+        });
+        let sse = generator
+            .generate(&StreamEvent::MessageStop {
+                stop_reason: StopReason::MaxTokens,
+            })
+            .unwrap();
+        let response = data(&sse);
+        assert_eq!(response["candidates"][0]["finishReason"], "MAX_TOKENS");
+        assert_eq!(response["usageMetadata"]["promptTokenCount"], 14);
+        assert_eq!(response["usageMetadata"]["cachedContentTokenCount"], 4);
+    }
+
+    #[test]
+    fn test_finish_reason_maps_content_filter_to_safety() {
+        assert_eq!(finish_reason(&StopReason::ToolUse), "STOP");
+        assert_eq!(
+            finish_reason(&StopReason::Other("content_filter".to_string())),
+            "SAFETY"
+        );
+        assert_eq!(
+            finish_reason(&StopReason::Other("refusal".to_string())),
+            "SAFETY"
+        );
+        assert_eq!(
+            finish_reason(&StopReason::Other("RECITATION".to_string())),
+            "RECITATION"
+        );
+    }
+}
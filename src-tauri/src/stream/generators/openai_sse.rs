@@ -29,6 +29,8 @@ pub struct OpenAiSseGenerator {
     tool_calls: HashMap<String, ToolCallState>,
     /// 下一个工具调用索引
     next_tool_index: usize,
+    /// 后端上报的使用量，随结束分块一起发送
+    usage: Option<OpenAiUsage>,
 }
 
 #[derive(Debug, Clone)]
@@ -61,6 +63,7 @@ impl OpenAiSseGenerator {
             created,
             tool_calls: HashMap::new(),
             next_tool_index: 0,
+            usage: None,
         }
     }
 
@@ -77,6 +80,7 @@ impl OpenAiSseGenerator {
             created,
             tool_calls: HashMap::new(),
             next_tool_index: 0,
+            usage: None,
         }
     }
 
@@ -124,14 +128,43 @@ impl OpenAiSseGenerator {
                         delta: OpenAiDelta {
                             role: None,
                             content: Some(text.as_str()),
+                            reasoning_content: None,
                             tool_calls: None,
                         },
                         finish_reason: None,
                     }],
+                    usage: None,
                 };
                 Some(format!("data: {}\n\n", serde_json::to_string(&chunk).ok()?))
             }
 
+            StreamEvent::ThinkingDelta { text } => {
+                // 思维链使用 reasoning_content 字段（DeepSeek 等兼容实现的约定）
+                let chunk = OpenAiStreamChunk {
+                    id: &self.response_id,
+                    object: "chat.completion.chunk",
+                    created: self.created,
+                    model: &self.model,
+                    choices: vec![OpenAiChoice {
+                        index: 0,
+                        delta: OpenAiDelta {
+                            role: None,
+                            content: None,
+                            reasoning_content: Some(text.as_str()),
+                            tool_calls: None,
+                        },
+                        finish_reason: None,
+                    }],
+                    usage: None,
+                };
+                Some(format!("data: {}\n\n", serde_json::to_string(&chunk).ok()?))
+            }
+
+            StreamEvent::SignatureDelta { .. } | StreamEvent::CitationDelta { .. } => {
+                // OpenAI 格式没有签名和引用字段
+                None
+            }
+
             StreamEvent::ToolUseStart { id, name } => {
                 // 确保工具调用状态存在
                 let index = if let Some(state) = self.tool_calls.get(id) {
@@ -160,6 +193,7 @@ impl OpenAiSseGenerator {
                         delta: OpenAiDelta {
                             role: None,
                             content: None,
+                            reasoning_content: None,
                             tool_calls: Some(vec![OpenAiToolCallDelta {
                                 index,
                                 id: Some(id.as_str()),
@@ -172,6 +206,7 @@ impl OpenAiSseGenerator {
                         },
                         finish_reason: None,
                     }],
+                    usage: None,
                 };
                 Some(format!("data: {}\n\n", serde_json::to_string(&chunk).ok()?))
             }
@@ -194,6 +229,7 @@ impl OpenAiSseGenerator {
                         delta: OpenAiDelta {
                             role: None,
                             content: None,
+                            reasoning_content: None,
                             tool_calls: Some(vec![OpenAiToolCallDelta {
                                 index,
                                 id: None,
@@ -206,6 +242,7 @@ impl OpenAiSseGenerator {
                         },
                         finish_reason: None,
                     }],
+                    usage: None,
                 };
                 Some(format!("data: {}\n\n", serde_json::to_string(&chunk).ok()?))
             }
@@ -234,10 +271,12 @@ impl OpenAiSseGenerator {
                         delta: OpenAiDelta {
                             role: None,
                             content: None,
+                            reasoning_content: None,
                             tool_calls: None,
                         },
                         finish_reason: Some(finish_reason),
                     }],
+                    usage: self.usage.clone(),
                 };
 
                 let chunk_str = format!("data: {}\n\n", serde_json::to_string(&chunk).ok()?);
@@ -247,11 +286,20 @@ impl OpenAiSseGenerator {
             StreamEvent::Usage {
                 input_tokens,
                 output_tokens,
-                ..
+                cache_read_input_tokens,
+                cache_creation_input_tokens,
             } => {
-                // OpenAI 在流式响应中通常不发送 usage
-                // 但某些实现可能需要，这里可以选择性地生成
-                let _ = (input_tokens, output_tokens);
+                // 记录使用量，在结束分块中发送；prompt_tokens 包含缓存命中的 token
+                let cached = cache_read_input_tokens.unwrap_or(0);
+                let prompt_tokens =
+                    input_tokens + cached + cache_creation_input_tokens.unwrap_or(0);
+                self.usage = Some(OpenAiUsage {
+                    prompt_tokens,
+                    completion_tokens: *output_tokens,
+                    total_tokens: prompt_tokens + output_tokens,
+                    prompt_tokens_details: cache_read_input_tokens
+                        .map(|cached_tokens| OpenAiPromptTokensDetails { cached_tokens }),
+                });
                 None
             }
 
@@ -303,6 +351,22 @@ struct OpenAiStreamChunk<'a> {
     created: u64,
     model: &'a str,
     choices: Vec<OpenAiChoice<'a>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    usage: Option<OpenAiUsage>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct OpenAiUsage {
+    prompt_tokens: u32,
+    completion_tokens: u32,
+    total_tokens: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    prompt_tokens_details: Option<OpenAiPromptTokensDetails>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct OpenAiPromptTokensDetails {
+    cached_tokens: u32,
 }
 
 #[derive(Debug, Serialize)]
@@ -320,6 +384,8 @@ struct OpenAiDelta<'a> {
     #[serde(skip_serializing_if = "Option::is_none")]
     content: Option<&'a str>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    reasoning_content: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     tool_calls: Option<Vec<OpenAiToolCallDelta<'a>>>,
 }
 
@@ -13,16 +13,24 @@
 //! 例如：
 //! AWS Event Stream ──> [AwsEventStreamParser] ──> StreamEvent ──> [AnthropicSseGenerator] ──> Anthropic SSE
 //! AWS Event Stream ──> [AwsEventStreamParser] ──> StreamEvent ──> [OpenAiSseGenerator] ──> OpenAI SSE
+//! Anthropic SSE    ──> [AnthropicSseParser]   ──> StreamEvent ──> [GeminiSseGenerator] ──> Gemini SSE
 //! ```
 //!
+//! 任意解析器都可以与任意生成器组合；`translator::canonical` 在此基础上提供非流式的
+//! 规范请求/响应模型。
+//!
 //! # 模块结构
 //!
 //! - `events`: 统一的流事件类型定义 (`StreamEvent`)
 //! - `parsers`: 后端流格式解析器
 //!   - `aws_event_stream`: AWS Event Stream 解析器 (Kiro/CodeWhisperer)
+//!   - `openai_sse`: OpenAI SSE 解析器
+//!   - `anthropic_sse`: Anthropic SSE 解析器
+//!   - `gemini_sse`: Gemini / Antigravity SSE 解析器
 //! - `generators`: 前端流格式生成器
 //!   - `openai_sse`: OpenAI SSE 格式生成器
 //!   - `anthropic_sse`: Anthropic SSE 格式生成器
+//!   - `gemini_sse`: Gemini SSE 格式生成器
 
 pub mod events;
 pub mod generators;
@@ -31,6 +39,8 @@ pub mod pipeline;
 
 // 重新导出核心类型
 pub use events::{ContentBlockType, StopReason, StreamContext, StreamEvent};
-pub use generators::{AnthropicSseGenerator, OpenAiSseGenerator};
-pub use parsers::{AwsEventStreamParser, ParserState};
+pub use generators::{AnthropicSseGenerator, GeminiSseGenerator, OpenAiSseGenerator};
+pub use parsers::{
+    AnthropicSseParser, AwsEventStreamParser, GeminiSseParser, OpenAiSseParser, ParserState,
+};
 pub use pipeline::{create_sse_stream, BackendType, FrontendType, PipelineConfig, StreamPipeline};
@@ -0,0 +1,335 @@
+//! Anthropic SSE 解析器
+//!
+//! 解析 Anthropic Messages API 流式响应，输出统一的 `StreamEvent`。
+//!
+//! # 协议格式
+//!
+//! - `message_start` - 消息开始，携带输入 token 数
+//! - `content_block_start` - 内容块开始（text / thinking / redacted_thinking / tool_use）
+//! - `content_block_delta` - 内容增量（text_delta / thinking_delta / signature_delta /
+//!   citations_delta / input_json_delta）
+//! - `content_block_stop` - 内容块结束
+//! - `message_delta` - 停止原因和使用量
+//! - `message_stop` - 消息结束
+//!
+//! 内容块索引与上游保持一致。
+
+use crate::stream::events::{ContentBlockType, StopReason, StreamEvent};
+use crate::stream::parsers::sse::SseDecoder;
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// 使用量
+#[derive(Debug, Clone, Copy, Default)]
+struct UsageState {
+    input_tokens: u32,
+    output_tokens: u32,
+    cache_read_input_tokens: Option<u32>,
+    cache_creation_input_tokens: Option<u32>,
+}
+
+impl UsageState {
+    /// 合并使用量，后到的非零值覆盖先前的值
+    fn merge(&mut self, usage: &Value) {
+        let field = |key: &str| usage.get(key).and_then(|v| v.as_u64()).map(|v| v as u32);
+        if let Some(v) = field("input_tokens").filter(|v| *v > 0) {
+            self.input_tokens = v;
+        }
+        if let Some(v) = field("output_tokens").filter(|v| *v > 0) {
+            self.output_tokens = v;
+        }
+        if let Some(v) = field("cache_read_input_tokens").filter(|v| *v > 0) {
+            self.cache_read_input_tokens = Some(v);
+        }
+        if let Some(v) = field("cache_creation_input_tokens").filter(|v| *v > 0) {
+            self.cache_creation_input_tokens = Some(v);
+        }
+    }
+}
+
+/// Anthropic SSE 解析器
+#[derive(Debug, Default)]
+pub struct AnthropicSseParser {
+    /// SSE 帧解码器
+    decoder: SseDecoder,
+    /// 内容块索引 → 工具调用 ID
+    tool_blocks: HashMap<u32, String>,
+    /// 已开始的内容块索引（忽略不支持的块类型的增量）
+    open_blocks: Vec<u32>,
+    /// 是否已发送消息结束事件
+    message_stopped: bool,
+    /// 是否已发送消息开始事件
+    message_started: bool,
+    /// 停止原因
+    stop_reason: Option<StopReason>,
+    /// 使用量
+    usage: UsageState,
+}
+
+impl AnthropicSseParser {
+    /// 创建新的解析器
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 处理接收到的字节
+    pub fn process(&mut self, bytes: &[u8]) -> Vec<StreamEvent> {
+        let frames = self.decoder.process(bytes);
+        let mut events = Vec::new();
+        for frame in frames {
+            self.process_data(&frame.data, &mut events);
+        }
+        events
+    }
+
+    /// 完成解析，上游未发送 message_stop 时补齐结束事件
+    pub fn finish(&mut self) -> Vec<StreamEvent> {
+        let mut events = Vec::new();
+        for frame in self.decoder.finish() {
+            self.process_data(&frame.data, &mut events);
+        }
+        if self.message_started && !self.message_stopped {
+            for index in std::mem::take(&mut self.open_blocks) {
+                if let Some(id) = self.tool_blocks.remove(&index) {
+                    events.push(StreamEvent::ToolUseStop { id });
+                }
+                events.push(StreamEvent::ContentBlockStop { index });
+            }
+            self.stop_message(&mut events);
+        }
+        events
+    }
+
+    /// 重置解析器状态
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+
+    fn process_data(&mut self, data: &str, events: &mut Vec<StreamEvent>) {
+        let value: Value = match serde_json::from_str(data) {
+            Ok(value) => value,
+            Err(e) => {
+                events.push(StreamEvent::Error {
+                    error_type: "parse_error".to_string(),
+                    message: format!("JSON 解析错误: {}", e),
+                });
+                return;
+            }
+        };
+        let index = value.get("index").and_then(|v| v.as_u64()).unwrap_or(0) as u32;
+
+        match value.get("type").and_then(|v| v.as_str()).unwrap_or("") {
+            "message_start" => {
+                let message = value.get("message").cloned().unwrap_or_default();
+                if let Some(usage) = message.get("usage") {
+                    self.usage.merge(usage);
+                }
+                self.message_started = true;
+                events.push(StreamEvent::MessageStart {
+                    id: str_field(&message, "id"),
+                    model: str_field(&message, "model"),
+                });
+            }
+            "content_block_start" => {
+                let block = value.get("content_block").cloned().unwrap_or_default();
+                let block_type = match block.get("type").and_then(|v| v.as_str()) {
+                    Some("text") => ContentBlockType::Text,
+                    Some("thinking") => ContentBlockType::Thinking,
+                    Some("redacted_thinking") => ContentBlockType::RedactedThinking {
+                        data: str_field(&block, "data"),
+                    },
+                    Some("tool_use") => ContentBlockType::ToolUse {
+                        id: str_field(&block, "id"),
+                        name: str_field(&block, "name"),
+                    },
+                    // server_tool_use、web_search_tool_result 等服务端块不转换
+                    _ => return,
+                };
+                self.open_blocks.push(index);
+                events.push(StreamEvent::ContentBlockStart {
+                    index,
+                    block_type: block_type.clone(),
+                });
+                match block_type {
+                    ContentBlockType::ToolUse { id, name } => {
+                        self.tool_blocks.insert(index, id.clone());
+                        events.push(StreamEvent::ToolUseStart { id, name });
+                    }
+                    ContentBlockType::Text => {
+                        let text = str_field(&block, "text");
+                        if !text.is_empty() {
+                            events.push(StreamEvent::TextDelta { text });
+                        }
+                    }
+                    ContentBlockType::Thinking => {
+                        let text = str_field(&block, "thinking");
+                        if !text.is_empty() {
+                            events.push(StreamEvent::ThinkingDelta { text });
+                        }
+                    }
+                    ContentBlockType::RedactedThinking { .. } => {}
+                }
+            }
+            "content_block_delta" => {
+                if !self.open_blocks.contains(&index) {
+                    return;
+                }
+                let delta = value.get("delta").cloned().unwrap_or_default();
+                let event = match delta.get("type").and_then(|v| v.as_str()) {
+                    Some("text_delta") => StreamEvent::TextDelta {
+                        text: str_field(&delta, "text"),
+                    },
+                    Some("thinking_delta") => StreamEvent::ThinkingDelta {
+                        text: str_field(&delta, "thinking"),
+                    },
+                    Some("signature_delta") => StreamEvent::SignatureDelta {
+                        signature: str_field(&delta, "signature"),
+                    },
+                    Some("citations_delta") => StreamEvent::CitationDelta {
+                        citation: delta.get("citation").cloned().unwrap_or_default(),
+                    },
+                    Some("input_json_delta") => match self.tool_blocks.get(&index) {
+                        Some(id) => StreamEvent::ToolUseInputDelta {
+                            id: id.clone(),
+                            partial_json: str_field(&delta, "partial_json"),
+                        },
+                        None => return,
+                    },
+                    _ => return,
+                };
+                events.push(event);
+            }
+            "content_block_stop" => {
+                let Some(position) = self.open_blocks.iter().position(|i| *i == index) else {
+                    return;
+                };
+                self.open_blocks.remove(position);
+                if let Some(id) = self.tool_blocks.remove(&index) {
+                    events.push(StreamEvent::ToolUseStop { id });
+                }
+                events.push(StreamEvent::ContentBlockStop { index });
+            }
+            "message_delta" => {
+                if let Some(reason) = value.pointer("/delta/stop_reason").and_then(|v| v.as_str()) {
+                    self.stop_reason = Some(StopReason::from_str(reason));
+                }
+                if let Some(usage) = value.get("usage") {
+                    self.usage.merge(usage);
+                }
+            }
+            "message_stop" => self.stop_message(events),
+            "ping" => events.push(StreamEvent::Ping),
+            "error" => {
+                let error = value.get("error").cloned().unwrap_or_default();
+                events.push(StreamEvent::Error {
+                    error_type: str_field(&error, "type"),
+                    message: str_field(&error, "message"),
+                });
+            }
+            _ => {}
+        }
+    }
+
+    fn stop_message(&mut self, events: &mut Vec<StreamEvent>) {
+        events.push(StreamEvent::Usage {
+            input_tokens: self.usage.input_tokens,
+            output_tokens: self.usage.output_tokens,
+            cache_read_input_tokens: self.usage.cache_read_input_tokens,
+            cache_creation_input_tokens: self.usage.cache_creation_input_tokens,
+        });
+        events.push(StreamEvent::MessageStop {
+            stop_reason: self.stop_reason.take().unwrap_or_default(),
+        });
+        self.message_stopped = true;
+    }
+}
+
+fn str_field(value: &Value, key: &str) -> String {
+    value
+        .get(key)
+        .and_then(|v| v.as_str())
+        .unwrap_or_default()
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_thinking_and_tool_use() {
+        let mut parser = AnthropicSseParser::new();
+        let input = concat!(
+            "event: message_start\ndata: {\"type\":\"message_start\",\"message\":{\"id\":\"msg_1\",\"model\":\"claude-sonnet-4-5\",\"usage\":{\"input_tokens\":12,\"output_tokens\":1,\"cache_read_input_tokens\":4}}}\n\n",
+            "event: content_block_start\ndata: {\"type\":\"content_block_start\",\"index\":0,\"content_block\":{\"type\":\"thinking\",\"thinking\":\"\"}}\n\n",
+            "event: content_block_delta\ndata: {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"thinking_delta\",\"thinking\":\"hmm\"}}\n\n",
+            "event: content_block_delta\ndata: {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"signature_delta\",\"signature\":\"sig\"}}\n\n",
+            "event: content_block_stop\ndata: {\"type\":\"content_block_stop\",\"index\":0}\n\n",
+            "event: content_block_start\ndata: {\"type\":\"content_block_start\",\"index\":1,\"content_block\":{\"type\":\"tool_use\",\"id\":\"toolu_1\",\"name\":\"ls\",\"input\":{}}}\n\n",
+            "event: content_block_delta\ndata: {\"type\":\"content_block_delta\",\"index\":1,\"delta\":{\"type\":\"input_json_delta\",\"partial_json\":\"{}\"}}\n\n",
+            "event: content_block_stop\ndata: {\"type\":\"content_block_stop\",\"index\":1}\n\n",
+            "event: message_delta\ndata: {\"type\":\"message_delta\",\"delta\":{\"stop_reason\":\"tool_use\"},\"usage\":{\"output_tokens\":30}}\n\n",
+            "event: message_stop\ndata: {\"type\":\"message_stop\"}\n\n",
+        );
+        let events = parser.process(input.as_bytes());
+
+        assert_eq!(
+            events,
+            vec![
+                StreamEvent::MessageStart {
+                    id: "msg_1".to_string(),
+                    model: "claude-sonnet-4-5".to_string()
+                },
+                StreamEvent::ContentBlockStart {
+                    index: 0,
+                    block_type: ContentBlockType::Thinking
+                },
+                StreamEvent::ThinkingDelta {
+                    text: "hmm".to_string()
+                },
+                StreamEvent::SignatureDelta {
+                    signature: "sig".to_string()
+                },
+                StreamEvent::ContentBlockStop { index: 0 },
+                StreamEvent::ContentBlockStart {
+                    index: 1,
+                    block_type: ContentBlockType::ToolUse {
+                        id: "toolu_1".to_string(),
+                        name: "ls".to_string()
+                    }
+                },
+                StreamEvent::ToolUseStart {
+                    id: "toolu_1".to_string(),
+                    name: "ls".to_string()
+                },
+                StreamEvent::ToolUseInputDelta {
+                    id: "toolu_1".to_string(),
+                    partial_json: "{}".to_string()
+                },
+                StreamEvent::ToolUseStop {
+                    id: "toolu_1".to_string()
+                },
+                StreamEvent::ContentBlockStop { index: 1 },
+                StreamEvent::Usage {
+                    input_tokens: 12,
+                    output_tokens: 30,
+                    cache_read_input_tokens: Some(4),
+                    cache_creation_input_tokens: None,
+                },
+                StreamEvent::MessageStop {
+                    stop_reason: StopReason::ToolUse
+                },
+            ]
+        );
+        assert!(parser.finish().is_empty());
+    }
+
+    #[test]
+    fn test_skip_server_tool_blocks() {
+        let mut parser = AnthropicSseParser::new();
+        let events = parser.process(
+            b"data: {\"type\":\"content_block_start\",\"index\":2,\"content_block\":{\"type\":\"server_tool_use\",\"id\":\"srvtoolu_1\",\"name\":\"web_search\"}}\n\ndata: {\"type\":\"content_block_delta\",\"index\":2,\"delta\":{\"type\":\"input_json_delta\",\"partial_json\":\"{}\"}}\n\ndata: {\"type\":\"content_block_stop\",\"index\":2}\n\n",
+        );
+        assert!(events.is_empty());
+    }
+}
@@ -0,0 +1,363 @@
+//! Gemini SSE 解析器
+//!
+//! 解析 Gemini `streamGenerateContent?alt=sse` 流式响应，输出统一的 `StreamEvent`。
+//! 同时支持 Antigravity 的 `{"response": {...}}` 包装格式。
+//!
+//! # 协议格式
+//!
+//! 每个分块都是完整的 `GenerateContentResponse`，`candidates[0].content.parts` 中：
+//! - `{"text": "..."}` - 文本增量
+//! - `{"text": "...", "thought": true}` - 思维链增量
+//! - `{"functionCall": {"name": "...", "args": {...}}}` - 完整的工具调用
+//! - `thoughtSignature` - 附加在 part 上的签名
+//!
+//! `finishReason` 和 `usageMetadata` 出现在最后一个分块中。
+
+use crate::stream::events::{ContentBlockType, StopReason, StreamContext, StreamEvent};
+use crate::stream::parsers::sse::SseDecoder;
+use serde_json::Value;
+
+/// 当前打开的内容块
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OpenBlock {
+    Text(u32),
+    Thinking(u32),
+}
+
+/// Gemini SSE 解析器
+#[derive(Debug, Default)]
+pub struct GeminiSseParser {
+    /// SSE 帧解码器
+    decoder: SseDecoder,
+    /// 流上下文
+    context: StreamContext,
+    /// 是否已发送消息开始事件
+    message_started: bool,
+    /// 是否已发送消息结束事件
+    message_stopped: bool,
+    /// 当前文本/思维链块
+    open_block: Option<OpenBlock>,
+    /// 是否出现过工具调用
+    saw_tool_use: bool,
+    /// 使用量
+    usage: Option<(u32, u32, Option<u32>)>,
+}
+
+impl GeminiSseParser {
+    /// 创建新的解析器
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 创建带模型名称的解析器（响应未携带 modelVersion 时使用）
+    pub fn with_model(model: String) -> Self {
+        let mut parser = Self::new();
+        parser.context.model = Some(model);
+        parser
+    }
+
+    /// 处理接收到的字节
+    pub fn process(&mut self, bytes: &[u8]) -> Vec<StreamEvent> {
+        let frames = self.decoder.process(bytes);
+        let mut events = Vec::new();
+        for frame in frames {
+            self.process_data(&frame.data, &mut events);
+        }
+        events
+    }
+
+    /// 完成解析，补齐未结束的内容块和消息结束事件
+    pub fn finish(&mut self) -> Vec<StreamEvent> {
+        let mut events = Vec::new();
+        for frame in self.decoder.finish() {
+            self.process_data(&frame.data, &mut events);
+        }
+        if self.message_started && !self.message_stopped {
+            self.stop_message(None, &mut events);
+        }
+        events
+    }
+
+    /// 重置解析器状态
+    pub fn reset(&mut self) {
+        let model = self.context.model.take();
+        *self = Self::new();
+        self.context.model = model;
+    }
+
+    /// 处理单个 GenerateContentResponse（JSON 数组模式的流也可以逐个元素调用）
+    pub fn process_response(&mut self, response: &Value) -> Vec<StreamEvent> {
+        let mut events = Vec::new();
+        self.process_value(response, &mut events);
+        events
+    }
+
+    fn process_data(&mut self, data: &str, events: &mut Vec<StreamEvent>) {
+        match serde_json::from_str::<Value>(data) {
+            Ok(value) => self.process_value(&value, events),
+            Err(e) => events.push(StreamEvent::Error {
+                error_type: "parse_error".to_string(),
+                message: format!("JSON 解析错误: {}", e),
+            }),
+        }
+    }
+
+    fn process_value(&mut self, value: &Value, events: &mut Vec<StreamEvent>) {
+        // Antigravity 包装格式
+        let response = value.get("response").unwrap_or(value);
+
+        if let Some(error) = response.get("error") {
+            events.push(StreamEvent::Error {
+                error_type: error
+                    .get("status")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("INTERNAL")
+                    .to_string(),
+                message: error
+                    .get("message")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or_default()
+                    .to_string(),
+            });
+            return;
+        }
+
+        if !self.message_started {
+            self.message_started = true;
+            let id = response
+                .get("responseId")
+                .and_then(|v| v.as_str())
+                .map(|s| s.to_string())
+                .unwrap_or_else(|| uuid::Uuid::new_v4().simple().to_string());
+            let model = response
+                .get("modelVersion")
+                .and_then(|v| v.as_str())
+                .map(|s| s.to_string())
+                .or_else(|| self.context.model.clone())
+                .unwrap_or_else(|| "unknown".to_string());
+            events.push(StreamEvent::MessageStart { id, model });
+        }
+
+        if let Some(usage) = response.get("usageMetadata") {
+            let field = |key: &str| usage.get(key).and_then(|v| v.as_u64()).map(|v| v as u32);
+            let cached = field("cachedContentTokenCount");
+            let prompt = field("promptTokenCount").unwrap_or(0);
+            // candidatesTokenCount 不包含思维链 token
+            let output = field("candidatesTokenCount").unwrap_or(0)
+                + field("thoughtsTokenCount").unwrap_or(0);
+            self.usage = Some((prompt.saturating_sub(cached.unwrap_or(0)), output, cached));
+        }
+
+        let Some(candidate) = response
+            .get("candidates")
+            .and_then(|c| c.as_array())
+            .and_then(|c| c.first())
+        else {
+            return;
+        };
+
+        if let Some(parts) = candidate
+            .pointer("/content/parts")
+            .and_then(|p| p.as_array())
+        {
+            for part in parts {
+                self.process_part(part, events);
+            }
+        }
+
+        if let Some(reason) = candidate.get("finishReason").and_then(|v| v.as_str()) {
+            if !self.message_stopped {
+                self.stop_message(Some(reason), events);
+            }
+        }
+    }
+
+    fn process_part(&mut self, part: &Value, events: &mut Vec<StreamEvent>) {
+        let signature = part
+            .get("thoughtSignature")
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string());
+
+        if let Some(call) = part.get("functionCall") {
+            self.close_block(events);
+            self.saw_tool_use = true;
+            let id = call
+                .get("id")
+                .and_then(|v| v.as_str())
+                .map(|s| s.to_string())
+                .unwrap_or_else(|| format!("call_{}", uuid::Uuid::new_v4().simple()));
+            let name = call
+                .get("name")
+                .and_then(|v| v.as_str())
+                .unwrap_or_default()
+                .to_string();
+            let args = call.get("args").cloned().unwrap_or(serde_json::json!({}));
+            let index = self.context.next_block_index();
+
+            events.push(StreamEvent::ContentBlockStart {
+                index,
+                block_type: ContentBlockType::ToolUse {
+                    id: id.clone(),
+                    name: name.clone(),
+                },
+            });
+            events.push(StreamEvent::ToolUseStart {
+                id: id.clone(),
+                name,
+            });
+            events.push(StreamEvent::ToolUseInputDelta {
+                id: id.clone(),
+                partial_json: args.to_string(),
+            });
+            if let Some(signature) = signature {
+                events.push(StreamEvent::SignatureDelta { signature });
+            }
+            events.push(StreamEvent::ToolUseStop { id });
+            events.push(StreamEvent::ContentBlockStop { index });
+            return;
+        }
+
+        let Some(text) = part.get("text").and_then(|v| v.as_str()) else {
+            return;
+        };
+        let thought = part
+            .get("thought")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+        if text.is_empty() && signature.is_none() {
+            return;
+        }
+
+        let wanted = if thought {
+            matches!(self.open_block, Some(OpenBlock::Thinking(_)))
+        } else {
+            matches!(self.open_block, Some(OpenBlock::Text(_)))
+        };
+        if !wanted {
+            self.close_block(events);
+            let index = self.context.next_block_index();
+            let (block, block_type) = if thought {
+                (OpenBlock::Thinking(index), ContentBlockType::Thinking)
+            } else {
+                (OpenBlock::Text(index), ContentBlockType::Text)
+            };
+            self.open_block = Some(block);
+            events.push(StreamEvent::ContentBlockStart { index, block_type });
+        }
+
+        if !text.is_empty() {
+            events.push(if thought {
+                StreamEvent::ThinkingDelta {
+                    text: text.to_string(),
+                }
+            } else {
+                StreamEvent::TextDelta {
+                    text: text.to_string(),
+                }
+            });
+        }
+        if let Some(signature) = signature {
+            events.push(StreamEvent::SignatureDelta { signature });
+        }
+    }
+
+    fn close_block(&mut self, events: &mut Vec<StreamEvent>) {
+        if let Some(OpenBlock::Text(index) | OpenBlock::Thinking(index)) = self.open_block.take() {
+            events.push(StreamEvent::ContentBlockStop { index });
+        }
+    }
+
+    fn stop_message(&mut self, reason: Option<&str>, events: &mut Vec<StreamEvent>) {
+        self.close_block(events);
+        if let Some((input_tokens, output_tokens, cached)) = self.usage {
+            events.push(StreamEvent::Usage {
+                input_tokens,
+                output_tokens,
+                cache_read_input_tokens: cached,
+                cache_creation_input_tokens: None,
+            });
+        }
+        let stop_reason = match reason {
+            Some("MAX_TOKENS") => StopReason::MaxTokens,
+            Some("STOP") | Some("FINISH_REASON_UNSPECIFIED") | None => {
+                if self.saw_tool_use {
+                    StopReason::ToolUse
+                } else {
+                    StopReason::EndTurn
+                }
+            }
+            Some(other) => StopReason::Other(other.to_string()),
+        };
+        events.push(StreamEvent::MessageStop { stop_reason });
+        self.message_stopped = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_text_thought_and_function_call() {
+        let mut parser = GeminiSseParser::with_model("gemini-2.5-pro".to_string());
+        let input = concat!(
+            "data: {\"candidates\":[{\"content\":{\"role\":\"model\",\"parts\":[{\"text\":\"plan\",\"thought\":true}]}}]}\r\n\r\n",
+            "data: {\"candidates\":[{\"content\":{\"role\":\"model\",\"parts\":[{\"text\":\"Hi\"}]}}]}\r\n\r\n",
+            "data: {\"candidates\":[{\"content\":{\"role\":\"model\",\"parts\":[{\"functionCall\":{\"name\":\"ls\",\"args\":{\"path\":\".\"}},\"thoughtSignature\":\"sig\"}]},\"finishReason\":\"STOP\"}],\"usageMetadata\":{\"promptTokenCount\":10,\"candidatesTokenCount\":4,\"thoughtsTokenCount\":2}}\r\n\r\n",
+        );
+        let events = parser.process(input.as_bytes());
+
+        assert!(
+            matches!(&events[0], StreamEvent::MessageStart { model, .. } if model == "gemini-2.5-pro")
+        );
+        assert_eq!(
+            events[1],
+            StreamEvent::ContentBlockStart {
+                index: 0,
+                block_type: ContentBlockType::Thinking
+            }
+        );
+        assert!(events.contains(&StreamEvent::TextDelta {
+            text: "Hi".to_string()
+        }));
+        assert!(events.contains(&StreamEvent::SignatureDelta {
+            signature: "sig".to_string()
+        }));
+        assert!(events.iter().any(|e| matches!(
+            e,
+            StreamEvent::ToolUseInputDelta { partial_json, .. } if partial_json == "{\"path\":\".\"}"
+        )));
+        assert!(events.contains(&StreamEvent::Usage {
+            input_tokens: 10,
+            output_tokens: 6,
+            cache_read_input_tokens: None,
+            cache_creation_input_tokens: None,
+        }));
+        assert_eq!(
+            events.last(),
+            Some(&StreamEvent::MessageStop {
+                stop_reason: StopReason::ToolUse
+            })
+        );
+        assert!(parser.finish().is_empty());
+    }
+
+    #[test]
+    fn test_parse_antigravity_envelope() {
+        let mut parser = GeminiSseParser::new();
+        let mut events = parser.process(
+            b"data: {\"response\":{\"candidates\":[{\"content\":{\"parts\":[{\"text\":\"ok\"}]},\"finishReason\":\"SAFETY\"}],\"modelVersion\":\"gemini-3-pro\"}}\n\n",
+        );
+        events.extend(parser.finish());
+
+        assert!(
+            matches!(&events[0], StreamEvent::MessageStart { model, .. } if model == "gemini-3-pro")
+        );
+        assert_eq!(
+            events.last(),
+            Some(&StreamEvent::MessageStop {
+                stop_reason: StopReason::Other("SAFETY".to_string())
+            })
+        );
+    }
+}
@@ -5,9 +5,18 @@
 //! # 支持的格式
 //!
 //! - AWS Event Stream (Kiro/CodeWhisperer)
-//! - OpenAI SSE (待实现)
-//! - Anthropic SSE (待实现)
+//! - OpenAI SSE
+//! - Anthropic SSE
+//! - Gemini SSE（含 Antigravity 包装格式）
 
+pub mod anthropic_sse;
 pub mod aws_event_stream;
+pub mod gemini_sse;
+pub mod openai_sse;
+pub mod sse;
 
+pub use anthropic_sse::AnthropicSseParser;
 pub use aws_event_stream::{AwsEventStreamParser, ParserState};
+pub use gemini_sse::GeminiSseParser;
+pub use openai_sse::OpenAiSseParser;
+pub use sse::{SseDecoder, SseFrame};
@@ -0,0 +1,406 @@
+//! OpenAI SSE 解析器
+//!
+//! 解析 OpenAI Chat Completions 流式响应，输出统一的 `StreamEvent`。
+//!
+//! # 协议格式
+//!
+//! - `delta.content` - 文本增量
+//! - `delta.reasoning_content` - 思维链增量（DeepSeek 等兼容实现）
+//! - `delta.tool_calls[]` - 工具调用，首个分块包含 `id` 和 `function.name`，后续分块为参数增量
+//! - `finish_reason` - 停止原因
+//! - `usage` - 使用量（`stream_options.include_usage` 或兼容实现在结束分块中携带）
+//! - `data: [DONE]` - 流结束
+
+use crate::stream::events::{ContentBlockType, StopReason, StreamContext, StreamEvent};
+use crate::stream::parsers::sse::SseDecoder;
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// 当前打开的内容块
+#[derive(Debug, Clone, PartialEq)]
+enum OpenBlock {
+    Text(u32),
+    Thinking(u32),
+    ToolUse { index: u32, id: String },
+}
+
+/// 使用量
+#[derive(Debug, Clone, Copy, Default)]
+struct UsageState {
+    input_tokens: u32,
+    output_tokens: u32,
+    cache_read_input_tokens: Option<u32>,
+}
+
+/// OpenAI SSE 解析器
+#[derive(Debug, Default)]
+pub struct OpenAiSseParser {
+    /// SSE 帧解码器
+    decoder: SseDecoder,
+    /// 流上下文
+    context: StreamContext,
+    /// 是否已发送消息开始事件
+    message_started: bool,
+    /// 是否已发送消息结束事件
+    message_stopped: bool,
+    /// 当前内容块
+    open_block: Option<OpenBlock>,
+    /// tool_calls 数组索引 → 工具调用 ID
+    tool_ids: HashMap<u64, String>,
+    /// 是否出现过工具调用
+    saw_tool_use: bool,
+    /// 停止原因
+    stop_reason: Option<StopReason>,
+    /// 使用量
+    usage: Option<UsageState>,
+}
+
+impl OpenAiSseParser {
+    /// 创建新的解析器
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 创建带模型名称的解析器（响应未携带 model 时使用）
+    pub fn with_model(model: String) -> Self {
+        let mut parser = Self::new();
+        parser.context.model = Some(model);
+        parser
+    }
+
+    /// 处理接收到的字节
+    pub fn process(&mut self, bytes: &[u8]) -> Vec<StreamEvent> {
+        let frames = self.decoder.process(bytes);
+        let mut events = Vec::new();
+        for frame in frames {
+            self.process_data(&frame.data, &mut events);
+        }
+        events
+    }
+
+    /// 完成解析，补齐未结束的内容块和消息结束事件
+    pub fn finish(&mut self) -> Vec<StreamEvent> {
+        let mut events = Vec::new();
+        for frame in self.decoder.finish() {
+            self.process_data(&frame.data, &mut events);
+        }
+        if self.message_started && !self.message_stopped {
+            self.stop_message(&mut events);
+        }
+        events
+    }
+
+    /// 重置解析器状态
+    pub fn reset(&mut self) {
+        let model = self.context.model.take();
+        *self = Self::new();
+        self.context.model = model;
+    }
+
+    fn process_data(&mut self, data: &str, events: &mut Vec<StreamEvent>) {
+        let data = data.trim();
+        if data == "[DONE]" {
+            if self.message_started && !self.message_stopped {
+                self.stop_message(events);
+            }
+            return;
+        }
+
+        let chunk: Value = match serde_json::from_str(data) {
+            Ok(chunk) => chunk,
+            Err(e) => {
+                events.push(StreamEvent::Error {
+                    error_type: "parse_error".to_string(),
+                    message: format!("JSON 解析错误: {}", e),
+                });
+                return;
+            }
+        };
+
+        if let Some(error) = chunk.get("error") {
+            events.push(StreamEvent::Error {
+                error_type: error
+                    .get("type")
+                    .and_then(|t| t.as_str())
+                    .unwrap_or("api_error")
+                    .to_string(),
+                message: error
+                    .get("message")
+                    .and_then(|m| m.as_str())
+                    .unwrap_or_default()
+                    .to_string(),
+            });
+            return;
+        }
+
+        if !self.message_started {
+            self.message_started = true;
+            let id = chunk
+                .get("id")
+                .and_then(|v| v.as_str())
+                .map(|s| s.to_string())
+                .unwrap_or_else(|| format!("chatcmpl-{}", uuid::Uuid::new_v4().simple()));
+            let model = chunk
+                .get("model")
+                .and_then(|v| v.as_str())
+                .map(|s| s.to_string())
+                .or_else(|| self.context.model.clone())
+                .unwrap_or_else(|| "unknown".to_string());
+            self.context.message_id = Some(id.clone());
+            events.push(StreamEvent::MessageStart { id, model });
+        }
+
+        if let Some(usage) = chunk.get("usage").filter(|u| u.is_object()) {
+            let prompt = u32_field(usage, "prompt_tokens");
+            let cached = usage
+                .pointer("/prompt_tokens_details/cached_tokens")
+                .and_then(|v| v.as_u64())
+                .map(|v| v as u32);
+            self.usage = Some(UsageState {
+                input_tokens: prompt.saturating_sub(cached.unwrap_or(0)),
+                output_tokens: u32_field(usage, "completion_tokens"),
+                cache_read_input_tokens: cached,
+            });
+        }
+
+        let Some(choice) = chunk
+            .get("choices")
+            .and_then(|c| c.as_array())
+            .and_then(|c| c.first())
+        else {
+            return;
+        };
+
+        if let Some(delta) = choice.get("delta") {
+            if let Some(text) = delta
+                .get("reasoning_content")
+                .and_then(|v| v.as_str())
+                .filter(|s| !s.is_empty())
+            {
+                if !matches!(self.open_block, Some(OpenBlock::Thinking(_))) {
+                    self.close_block(events);
+                    let index = self.context.next_block_index();
+                    self.open_block = Some(OpenBlock::Thinking(index));
+                    events.push(StreamEvent::ContentBlockStart {
+                        index,
+                        block_type: ContentBlockType::Thinking,
+                    });
+                }
+                events.push(StreamEvent::ThinkingDelta {
+                    text: text.to_string(),
+                });
+            }
+
+            if let Some(text) = delta
+                .get("content")
+                .and_then(|v| v.as_str())
+                .filter(|s| !s.is_empty())
+            {
+                if !matches!(self.open_block, Some(OpenBlock::Text(_))) {
+                    self.close_block(events);
+                    let index = self.context.next_block_index();
+                    self.open_block = Some(OpenBlock::Text(index));
+                    events.push(StreamEvent::ContentBlockStart {
+                        index,
+                        block_type: ContentBlockType::Text,
+                    });
+                }
+                events.push(StreamEvent::TextDelta {
+                    text: text.to_string(),
+                });
+            }
+
+            if let Some(tool_calls) = delta.get("tool_calls").and_then(|v| v.as_array()) {
+                for (position, call) in tool_calls.iter().enumerate() {
+                    self.process_tool_call(position as u64, call, events);
+                }
+            }
+        }
+
+        if let Some(reason) = choice.get("finish_reason").and_then(|v| v.as_str()) {
+            self.close_block(events);
+            let mut stop_reason = StopReason::from_str(reason);
+            if self.saw_tool_use && stop_reason == StopReason::EndTurn {
+                stop_reason = StopReason::ToolUse;
+            }
+            self.stop_reason = Some(stop_reason);
+        }
+    }
+
+    fn process_tool_call(&mut self, position: u64, call: &Value, events: &mut Vec<StreamEvent>) {
+        let tool_index = call
+            .get("index")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(position);
+        let function = call.get("function");
+        let name = function
+            .and_then(|f| f.get("name"))
+            .and_then(|v| v.as_str())
+            .unwrap_or("");
+        let arguments = function
+            .and_then(|f| f.get("arguments"))
+            .and_then(|v| v.as_str())
+            .unwrap_or("");
+
+        let id = match self.tool_ids.get(&tool_index) {
+            Some(id) => id.clone(),
+            None => {
+                let id = call
+                    .get("id")
+                    .and_then(|v| v.as_str())
+                    .map(|s| s.to_string())
+                    .unwrap_or_else(|| format!("call_{}", uuid::Uuid::new_v4().simple()));
+                self.close_block(events);
+                self.tool_ids.insert(tool_index, id.clone());
+                self.saw_tool_use = true;
+
+                let index = self.context.next_block_index();
+                self.context.add_tool_call(id.clone());
+                self.open_block = Some(OpenBlock::ToolUse {
+                    index,
+                    id: id.clone(),
+                });
+                events.push(StreamEvent::ContentBlockStart {
+                    index,
+                    block_type: ContentBlockType::ToolUse {
+                        id: id.clone(),
+                        name: name.to_string(),
+                    },
+                });
+                events.push(StreamEvent::ToolUseStart {
+                    id: id.clone(),
+                    name: name.to_string(),
+                });
+                id
+            }
+        };
+
+        if !arguments.is_empty() {
+            events.push(StreamEvent::ToolUseInputDelta {
+                id,
+                partial_json: arguments.to_string(),
+            });
+        }
+    }
+
+    fn close_block(&mut self, events: &mut Vec<StreamEvent>) {
+        match self.open_block.take() {
+            Some(OpenBlock::Text(index)) | Some(OpenBlock::Thinking(index)) => {
+                events.push(StreamEvent::ContentBlockStop { index });
+            }
+            Some(OpenBlock::ToolUse { index, id }) => {
+                self.context.remove_tool_call(&id);
+                events.push(StreamEvent::ToolUseStop { id });
+                events.push(StreamEvent::ContentBlockStop { index });
+            }
+            None => {}
+        }
+    }
+
+    fn stop_message(&mut self, events: &mut Vec<StreamEvent>) {
+        self.close_block(events);
+        if let Some(usage) = self.usage {
+            events.push(StreamEvent::Usage {
+                input_tokens: usage.input_tokens,
+                output_tokens: usage.output_tokens,
+                cache_read_input_tokens: usage.cache_read_input_tokens,
+                cache_creation_input_tokens: None,
+            });
+        }
+        let stop_reason = self.stop_reason.take().unwrap_or(if self.saw_tool_use {
+            StopReason::ToolUse
+        } else {
+            StopReason::EndTurn
+        });
+        events.push(StreamEvent::MessageStop { stop_reason });
+        self.message_stopped = true;
+    }
+}
+
+fn u32_field(value: &Value, key: &str) -> u32 {
+    value.get(key).and_then(|v| v.as_u64()).unwrap_or(0) as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_text_and_tool_call() {
+        let mut parser = OpenAiSseParser::new();
+        let input = concat!(
+            "data: {\"id\":\"chatcmpl-1\",\"model\":\"gpt-4o\",\"choices\":[{\"index\":0,\"delta\":{\"role\":\"assistant\",\"content\":\"Hi\"}}]}\n\n",
+            "data: {\"id\":\"chatcmpl-1\",\"choices\":[{\"index\":0,\"delta\":{\"tool_calls\":[{\"index\":0,\"id\":\"call_1\",\"type\":\"function\",\"function\":{\"name\":\"read_file\",\"arguments\":\"\"}}]}}]}\n\n",
+            "data: {\"id\":\"chatcmpl-1\",\"choices\":[{\"index\":0,\"delta\":{\"tool_calls\":[{\"index\":0,\"function\":{\"arguments\":\"{\\\"path\\\":1}\"}}]}}]}\n\n",
+            "data: {\"id\":\"chatcmpl-1\",\"choices\":[{\"index\":0,\"delta\":{},\"finish_reason\":\"tool_calls\"}],\"usage\":{\"prompt_tokens\":10,\"completion_tokens\":3,\"total_tokens\":13}}\n\n",
+            "data: [DONE]\n\n",
+        );
+        let events = parser.process(input.as_bytes());
+
+        assert_eq!(
+            events[0],
+            StreamEvent::MessageStart {
+                id: "chatcmpl-1".to_string(),
+                model: "gpt-4o".to_string()
+            }
+        );
+        assert!(events.contains(&StreamEvent::TextDelta {
+            text: "Hi".to_string()
+        }));
+        assert!(events.contains(&StreamEvent::ToolUseInputDelta {
+            id: "call_1".to_string(),
+            partial_json: "{\"path\":1}".to_string()
+        }));
+        assert!(events.contains(&StreamEvent::ToolUseStop {
+            id: "call_1".to_string()
+        }));
+        assert_eq!(
+            events.last(),
+            Some(&StreamEvent::MessageStop {
+                stop_reason: StopReason::ToolUse
+            })
+        );
+        assert!(events.contains(&StreamEvent::Usage {
+            input_tokens: 10,
+            output_tokens: 3,
+            cache_read_input_tokens: None,
+            cache_creation_input_tokens: None,
+        }));
+        assert!(parser.finish().is_empty());
+    }
+
+    #[test]
+    fn test_parse_reasoning_content() {
+        let mut parser = OpenAiSseParser::with_model("deepseek-reasoner".to_string());
+        let mut events = parser.process(
+            b"data: {\"choices\":[{\"delta\":{\"reasoning_content\":\"think\"}}]}\n\ndata: {\"choices\":[{\"delta\":{\"content\":\"answer\"}}]}\n\n",
+        );
+        events.extend(parser.finish());
+
+        assert!(
+            matches!(&events[0], StreamEvent::MessageStart { model, .. } if model == "deepseek-reasoner")
+        );
+        assert_eq!(
+            events[1],
+            StreamEvent::ContentBlockStart {
+                index: 0,
+                block_type: ContentBlockType::Thinking
+            }
+        );
+        assert_eq!(events[3], StreamEvent::ContentBlockStop { index: 0 });
+        assert_eq!(
+            events[4],
+            StreamEvent::ContentBlockStart {
+                index: 1,
+                block_type: ContentBlockType::Text
+            }
+        );
+        // 流在没有 [DONE] 的情况下结束，finish 补齐结束事件
+        assert_eq!(
+            events.last(),
+            Some(&StreamEvent::MessageStop {
+                stop_reason: StopReason::EndTurn
+            })
+        );
+    }
+}
@@ -0,0 +1,132 @@
+//! SSE 帧解码
+//!
+//! 将分块到达的 SSE 字节流切分为完整事件，供各协议的 SSE 解析器使用。
+
+/// 单个 SSE 事件
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SseFrame {
+    /// `event:` 字段（可选）
+    pub event: Option<String>,
+    /// `data:` 字段，多行 data 以换行拼接
+    pub data: String,
+}
+
+/// SSE 帧解码器
+///
+/// 按空行切分事件，支持 `\n` 和 `\r\n` 换行，忽略注释行（以 `:` 开头）。
+#[derive(Debug, Default)]
+pub struct SseDecoder {
+    /// 未完成的行缓冲
+    buffer: Vec<u8>,
+    /// 当前事件名
+    event: Option<String>,
+    /// 当前事件的 data 行
+    data: Vec<String>,
+}
+
+impl SseDecoder {
+    /// 创建新的解码器
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 处理字节块，返回已完整接收的事件
+    pub fn process(&mut self, bytes: &[u8]) -> Vec<SseFrame> {
+        self.buffer.extend_from_slice(bytes);
+        let mut frames = Vec::new();
+
+        while let Some(pos) = self.buffer.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=pos).collect();
+            let line = String::from_utf8_lossy(&line);
+            let line = line.trim_end_matches(['\n', '\r']);
+            if let Some(frame) = self.process_line(line) {
+                frames.push(frame);
+            }
+        }
+
+        frames
+    }
+
+    /// 完成解码，输出缓冲区中剩余的事件（流末尾缺少空行时）
+    pub fn finish(&mut self) -> Vec<SseFrame> {
+        let mut frames = Vec::new();
+        if !self.buffer.is_empty() {
+            let line = String::from_utf8_lossy(&std::mem::take(&mut self.buffer)).to_string();
+            if let Some(frame) = self.process_line(line.trim_end_matches('\r')) {
+                frames.push(frame);
+            }
+        }
+        frames.extend(self.flush());
+        frames
+    }
+
+    /// 重置解码器
+    pub fn reset(&mut self) {
+        self.buffer.clear();
+        self.event = None;
+        self.data.clear();
+    }
+
+    fn process_line(&mut self, line: &str) -> Option<SseFrame> {
+        if line.is_empty() {
+            return self.flush();
+        }
+        if line.starts_with(':') {
+            return None;
+        }
+
+        let (field, value) = match line.split_once(':') {
+            Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+            None => (line, ""),
+        };
+        match field {
+            "event" => self.event = Some(value.to_string()),
+            "data" => self.data.push(value.to_string()),
+            _ => {}
+        }
+        None
+    }
+
+    fn flush(&mut self) -> Option<SseFrame> {
+        if self.data.is_empty() {
+            self.event = None;
+            return None;
+        }
+        Some(SseFrame {
+            event: self.event.take(),
+            data: std::mem::take(&mut self.data).join("\n"),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_split_frames() {
+        let mut decoder = SseDecoder::new();
+        assert!(decoder.process(b"event: ping\nda").is_empty());
+        let frames = decoder.process(b"ta: {\"a\":1}\r\n\r\n: comment\n\ndata: x\n");
+        assert_eq!(
+            frames,
+            vec![SseFrame {
+                event: Some("ping".to_string()),
+                data: "{\"a\":1}".to_string(),
+            }]
+        );
+
+        // 缺少结尾空行的事件在 finish 时输出
+        let frames = decoder.finish();
+        assert_eq!(frames.len(), 1);
+        assert_eq!(frames[0].data, "x");
+    }
+
+    #[test]
+    fn test_decode_multiline_data() {
+        let mut decoder = SseDecoder::new();
+        let frames = decoder.process(b"data: a\ndata: b\n\n");
+        assert_eq!(frames[0].data, "a\nb");
+        assert_eq!(frames[0].event, None);
+    }
+}
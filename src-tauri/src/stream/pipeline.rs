@@ -13,10 +13,19 @@
 //! // 处理字节流
 //! let sse_stream = pipeline.process_stream(byte_stream);
 //! ```
+//!
+//! 通过 `with_plugins` 挂载插件会话后，解析出的每个 `StreamEvent` 会先经过插件的
+//! 流式钩子再生成 SSE，此时应使用 `process_chunk_with_plugins` / `finish_with_plugins`。
+//! 通过 `with_tool_filter` 挂载工具调用模拟过滤器后，文本中的工具调用在解析阶段
+//! 转换为工具调用事件，插件钩子看到的是转换后的事件。
 
+use crate::plugin::PluginStreamSession;
 use crate::stream::events::StreamEvent;
-use crate::stream::generators::{AnthropicSseGenerator, OpenAiSseGenerator};
-use crate::stream::parsers::AwsEventStreamParser;
+use crate::stream::generators::{AnthropicSseGenerator, GeminiSseGenerator, OpenAiSseGenerator};
+use crate::stream::parsers::{
+    AnthropicSseParser, AwsEventStreamParser, GeminiSseParser, OpenAiSseParser,
+};
+use crate::translator::tool_emulation::ToolCallStreamFilter;
 use bytes::Bytes;
 use futures::{Stream, StreamExt};
 
@@ -29,6 +38,8 @@ pub enum BackendType {
     OpenAi,
     /// Anthropic (SSE)
     Anthropic,
+    /// Gemini / Antigravity (SSE)
+    Gemini,
 }
 
 /// 前端类型
@@ -38,6 +49,8 @@ pub enum FrontendType {
     OpenAi,
     /// Anthropic SSE 格式
     Anthropic,
+    /// Gemini SSE 格式
+    Gemini,
 }
 
 /// 流处理管道配置
@@ -74,6 +87,16 @@ impl PipelineConfig {
         }
     }
 
+    /// 创建任意后端 → 前端的配置
+    pub fn new(backend: BackendType, frontend: FrontendType, model: String) -> Self {
+        Self {
+            backend,
+            frontend,
+            model,
+            message_id: None,
+        }
+    }
+
     /// 设置消息 ID
     pub fn with_message_id(mut self, id: String) -> Self {
         self.message_id = Some(id);
@@ -81,17 +104,84 @@ impl PipelineConfig {
     }
 }
 
+/// 后端流解析器封装
+enum StreamParser {
+    Kiro(AwsEventStreamParser),
+    OpenAi(OpenAiSseParser),
+    Anthropic(AnthropicSseParser),
+    Gemini(GeminiSseParser),
+}
+
+impl StreamParser {
+    fn new(backend: BackendType, model: String) -> Self {
+        match backend {
+            BackendType::Kiro => StreamParser::Kiro(AwsEventStreamParser::with_model(model)),
+            BackendType::OpenAi => StreamParser::OpenAi(OpenAiSseParser::with_model(model)),
+            BackendType::Anthropic => StreamParser::Anthropic(AnthropicSseParser::new()),
+            BackendType::Gemini => StreamParser::Gemini(GeminiSseParser::with_model(model)),
+        }
+    }
+
+    fn process(&mut self, bytes: &[u8]) -> Vec<StreamEvent> {
+        match self {
+            StreamParser::Kiro(p) => p.process(bytes),
+            StreamParser::OpenAi(p) => p.process(bytes),
+            StreamParser::Anthropic(p) => p.process(bytes),
+            StreamParser::Gemini(p) => p.process(bytes),
+        }
+    }
+
+    fn finish(&mut self) -> Vec<StreamEvent> {
+        match self {
+            StreamParser::Kiro(p) => p.finish(),
+            StreamParser::OpenAi(p) => p.finish(),
+            StreamParser::Anthropic(p) => p.finish(),
+            StreamParser::Gemini(p) => p.finish(),
+        }
+    }
+
+    fn reset(&mut self) {
+        match self {
+            StreamParser::Kiro(p) => p.reset(),
+            StreamParser::OpenAi(p) => p.reset(),
+            StreamParser::Anthropic(p) => p.reset(),
+            StreamParser::Gemini(p) => p.reset(),
+        }
+    }
+}
+
 /// SSE 生成器封装
 enum SseGenerator {
     Anthropic(AnthropicSseGenerator),
     OpenAi(OpenAiSseGenerator),
+    Gemini(GeminiSseGenerator),
 }
 
 impl SseGenerator {
+    fn new(frontend: FrontendType, model: String, message_id: Option<String>) -> Self {
+        match (frontend, message_id) {
+            (FrontendType::Anthropic, Some(id)) => {
+                SseGenerator::Anthropic(AnthropicSseGenerator::with_id(id, model))
+            }
+            (FrontendType::Anthropic, None) => {
+                SseGenerator::Anthropic(AnthropicSseGenerator::new(model))
+            }
+            (FrontendType::OpenAi, Some(id)) => {
+                SseGenerator::OpenAi(OpenAiSseGenerator::with_id(id, model))
+            }
+            (FrontendType::OpenAi, None) => SseGenerator::OpenAi(OpenAiSseGenerator::new(model)),
+            (FrontendType::Gemini, Some(id)) => {
+                SseGenerator::Gemini(GeminiSseGenerator::with_id(id, model))
+            }
+            (FrontendType::Gemini, None) => SseGenerator::Gemini(GeminiSseGenerator::new(model)),
+        }
+    }
+
     fn generate(&mut self, event: &StreamEvent) -> Vec<String> {
         match self {
             SseGenerator::Anthropic(g) => g.generate(event),
             SseGenerator::OpenAi(g) => g.generate(event).into_iter().collect(),
+            SseGenerator::Gemini(g) => g.generate(event).into_iter().collect(),
         }
     }
 }
@@ -102,48 +192,47 @@ impl SseGenerator {
 pub struct StreamPipeline {
     /// 配置
     config: PipelineConfig,
-    /// AWS Event Stream 解析器（用于 Kiro 后端）
-    aws_parser: Option<AwsEventStreamParser>,
+    /// 后端流解析器
+    parser: StreamParser,
     /// SSE 生成器
     generator: SseGenerator,
+    /// 插件流式钩子会话（可选）
+    plugins: Option<PluginStreamSession>,
+    /// 工具调用模拟过滤器（可选）
+    tool_filter: Option<ToolCallStreamFilter>,
 }
 
 impl StreamPipeline {
     /// 创建新的管道
     pub fn new(config: PipelineConfig) -> Self {
-        let aws_parser = match config.backend {
-            BackendType::Kiro => Some(AwsEventStreamParser::with_model(config.model.clone())),
-            _ => None,
-        };
-
-        let generator = match config.frontend {
-            FrontendType::Anthropic => {
-                if let Some(id) = &config.message_id {
-                    SseGenerator::Anthropic(AnthropicSseGenerator::with_id(
-                        id.clone(),
-                        config.model.clone(),
-                    ))
-                } else {
-                    SseGenerator::Anthropic(AnthropicSseGenerator::new(config.model.clone()))
-                }
-            }
-            FrontendType::OpenAi => {
-                if let Some(id) = &config.message_id {
-                    SseGenerator::OpenAi(OpenAiSseGenerator::with_id(
-                        id.clone(),
-                        config.model.clone(),
-                    ))
-                } else {
-                    SseGenerator::OpenAi(OpenAiSseGenerator::new(config.model.clone()))
-                }
-            }
-        };
+        let parser = StreamParser::new(config.backend, config.model.clone());
+        let generator = SseGenerator::new(
+            config.frontend,
+            config.model.clone(),
+            config.message_id.clone(),
+        );
 
         Self {
             config,
-            aws_parser,
+            parser,
             generator,
+            plugins: None,
+            tool_filter: None,
+        }
+    }
+
+    /// 挂载插件流式钩子会话，没有插件参与时忽略
+    pub fn with_plugins(mut self, session: PluginStreamSession) -> Self {
+        if !session.is_empty() {
+            self.plugins = Some(session);
         }
+        self
+    }
+
+    /// 挂载工具调用模拟过滤器，从文本增量中解析工具调用
+    pub fn with_tool_filter(mut self, filter: ToolCallStreamFilter) -> Self {
+        self.tool_filter = Some(filter);
+        self
     }
 
     /// 处理单个字节块
@@ -166,19 +255,44 @@ impl StreamPipeline {
         self.generate_sse(&events)
     }
 
+    /// 处理单个字节块，事件先经过插件流式钩子
+    pub async fn process_chunk_with_plugins(&mut self, bytes: &[u8]) -> Vec<String> {
+        let mut events = self.parse_bytes(bytes);
+        if let Some(plugins) = &mut self.plugins {
+            events = plugins.process(events).await;
+        }
+        self.generate_sse(&events)
+    }
+
+    /// 完成处理，结束阶段的事件先经过插件流式钩子并执行 on_stream_end
+    pub async fn finish_with_plugins(&mut self) -> Vec<String> {
+        let mut events = self.finish_parsing();
+        if let Some(plugins) = &mut self.plugins {
+            events = plugins.finish(events).await;
+        }
+        self.generate_sse(&events)
+    }
+
     /// 解析字节为 StreamEvent
     fn parse_bytes(&mut self, bytes: &[u8]) -> Vec<StreamEvent> {
-        match &mut self.aws_parser {
-            Some(parser) => parser.process(bytes),
-            None => Vec::new(), // TODO: 支持其他后端格式的解析
+        let events = self.parser.process(bytes);
+        match &mut self.tool_filter {
+            Some(filter) => events.into_iter().flat_map(|e| filter.process(e)).collect(),
+            None => events,
         }
     }
 
     /// 完成解析
     fn finish_parsing(&mut self) -> Vec<StreamEvent> {
-        match &mut self.aws_parser {
-            Some(parser) => parser.finish(),
-            None => Vec::new(),
+        let events = self.parser.finish();
+        match &mut self.tool_filter {
+            Some(filter) => {
+                let mut filtered: Vec<StreamEvent> =
+                    events.into_iter().flat_map(|e| filter.process(e)).collect();
+                filtered.extend(filter.finish());
+                filtered
+            }
+            None => events,
         }
     }
 
@@ -199,17 +313,11 @@ impl StreamPipeline {
 
     /// 重置管道状态
     pub fn reset(&mut self) {
-        if let Some(ref mut parser) = self.aws_parser {
-            parser.reset();
+        self.parser.reset();
+        if let Some(filter) = &mut self.tool_filter {
+            filter.reset();
         }
-        self.generator = match self.config.frontend {
-            FrontendType::Anthropic => {
-                SseGenerator::Anthropic(AnthropicSseGenerator::new(self.config.model.clone()))
-            }
-            FrontendType::OpenAi => {
-                SseGenerator::OpenAi(OpenAiSseGenerator::new(self.config.model.clone()))
-            }
-        };
+        self.generator = SseGenerator::new(self.config.frontend, self.config.model.clone(), None);
     }
 }
 
@@ -324,4 +432,51 @@ mod tests {
         assert!(sse.iter().any(|s| s.starts_with("data: ")));
         assert!(sse.iter().any(|s| s.contains("\"content\":\"Hello\"")));
     }
+
+    #[test]
+    fn test_pipeline_anthropic_to_gemini() {
+        let config = PipelineConfig::new(
+            BackendType::Anthropic,
+            FrontendType::Gemini,
+            "claude-sonnet-4-5".to_string(),
+        );
+        let mut pipeline = StreamPipeline::new(config);
+
+        let mut sse = pipeline.process_chunk(
+            concat!(
+                "event: message_start\ndata: {\"type\":\"message_start\",\"message\":{\"id\":\"msg_1\",\"model\":\"claude-sonnet-4-5\",\"usage\":{\"input_tokens\":5}}}\n\n",
+                "event: content_block_start\ndata: {\"type\":\"content_block_start\",\"index\":0,\"content_block\":{\"type\":\"text\",\"text\":\"\"}}\n\n",
+                "event: content_block_delta\ndata: {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"text_delta\",\"text\":\"Hello\"}}\n\n",
+            )
+            .as_bytes(),
+        );
+        sse.extend(pipeline.process_chunk(
+            b"event: message_delta\ndata: {\"type\":\"message_delta\",\"delta\":{\"stop_reason\":\"max_tokens\"},\"usage\":{\"output_tokens\":2}}\n\nevent: message_stop\ndata: {\"type\":\"message_stop\"}\n\n",
+        ));
+
+        assert!(sse.iter().all(|s| s.starts_with("data: ")));
+        assert!(sse.iter().any(|s| s.contains("\"text\":\"Hello\"")));
+        assert!(sse
+            .iter()
+            .any(|s| s.contains("\"finishReason\":\"MAX_TOKENS\"")));
+        assert!(pipeline.finish().is_empty());
+    }
+
+    #[test]
+    fn test_pipeline_gemini_to_openai() {
+        let config = PipelineConfig::new(
+            BackendType::Gemini,
+            FrontendType::OpenAi,
+            "gemini-2.5-pro".to_string(),
+        );
+        let mut pipeline = StreamPipeline::new(config);
+
+        let mut sse = pipeline.process_chunk(
+            b"data: {\"candidates\":[{\"content\":{\"parts\":[{\"text\":\"Hi\"}]}}]}\n\n",
+        );
+        sse.extend(pipeline.finish());
+
+        assert!(sse.iter().any(|s| s.contains("\"content\":\"Hi\"")));
+        assert!(sse.last().unwrap().ends_with("data: [DONE]\n\n"));
+    }
 }
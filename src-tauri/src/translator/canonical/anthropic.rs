@@ -0,0 +1,552 @@
+//! Anthropic Messages 编解码
+//!
+//! Anthropic 的内容块模型与规范模型基本一一对应：
+//! - 缓存标记（cache_control）、引用（citations）、思维链签名原样保留
+//! - 服务端工具（带 `type` 的工具定义，如 web_search）不映射，只随透传字段输出
+//! - 没有签名的思维链无法回传给 Anthropic，编码请求时丢弃
+
+use serde_json::{json, Map, Value};
+
+use super::types::*;
+use crate::converter::protocol_selector::Protocol;
+use crate::stream::StopReason;
+use crate::translator::traits::TranslateError;
+
+/// 未指定 max_tokens 时的默认值（Anthropic 要求必填）
+const DEFAULT_MAX_TOKENS: u32 = 4096;
+
+/// 请求中已映射的字段
+const REQUEST_FIELDS: &[&str] = &[
+    "model",
+    "system",
+    "messages",
+    "tools",
+    "tool_choice",
+    "max_tokens",
+    "temperature",
+    "top_p",
+    "top_k",
+    "stop_sequences",
+    "stream",
+    "thinking",
+    "metadata",
+];
+
+// ============================================================================
+// 内容块
+// ============================================================================
+
+fn decode_content(content: Option<&Value>) -> Vec<ContentBlock> {
+    match content {
+        Some(Value::String(s)) if !s.is_empty() => vec![ContentBlock::text(s.clone())],
+        Some(Value::Array(blocks)) => blocks.iter().filter_map(decode_block).collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn decode_block(block: &Value) -> Option<ContentBlock> {
+    let cache_control = block.get("cache_control").cloned();
+    match block.get("type").and_then(|t| t.as_str())? {
+        "text" => Some(ContentBlock::Text {
+            text: str_field(block, "text").unwrap_or_default(),
+            cache_control,
+            citations: block
+                .get("citations")
+                .and_then(|c| c.as_array())
+                .cloned()
+                .unwrap_or_default(),
+        }),
+        "image" => {
+            let source = block.get("source")?;
+            let source = match source.get("type").and_then(|t| t.as_str()) {
+                Some("url") => ImageSource::Url {
+                    url: str_field(source, "url")?,
+                    media_type: None,
+                },
+                _ => ImageSource::Base64 {
+                    media_type: str_field(source, "media_type")?,
+                    data: str_field(source, "data")?,
+                },
+            };
+            Some(ContentBlock::Image {
+                source,
+                detail: None,
+                cache_control,
+            })
+        }
+        "thinking" => Some(ContentBlock::Thinking {
+            thinking: str_field(block, "thinking").unwrap_or_default(),
+            signature: str_field(block, "signature").filter(|s| !s.is_empty()),
+        }),
+        "redacted_thinking" => Some(ContentBlock::RedactedThinking {
+            data: str_field(block, "data").unwrap_or_default(),
+        }),
+        "tool_use" => Some(ContentBlock::ToolUse {
+            id: str_field(block, "id").unwrap_or_default(),
+            name: str_field(block, "name").unwrap_or_default(),
+            input: block.get("input").cloned().unwrap_or_else(|| json!({})),
+            signature: None,
+            cache_control,
+        }),
+        "tool_result" => Some(ContentBlock::ToolResult {
+            tool_use_id: str_field(block, "tool_use_id").unwrap_or_default(),
+            content: decode_content(block.get("content")),
+            is_error: block
+                .get("is_error")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false),
+            cache_control,
+        }),
+        // document、server_tool_use 等块没有对应的规范表示
+        _ => None,
+    }
+}
+
+/// 编码内容块，`for_request` 为 true 时丢弃无签名的思维链
+fn encode_block(block: &ContentBlock, for_request: bool) -> Option<Value> {
+    let (mut value, cache_control) = match block {
+        ContentBlock::Text {
+            text,
+            cache_control,
+            citations,
+        } => {
+            let mut value = json!({"type": "text", "text": text});
+            if !citations.is_empty() {
+                value["citations"] = json!(citations);
+            }
+            (value, cache_control)
+        }
+        ContentBlock::Image {
+            source,
+            cache_control,
+            ..
+        } => {
+            let source = match source {
+                ImageSource::Base64 { media_type, data } => {
+                    json!({"type": "base64", "media_type": media_type, "data": data})
+                }
+                ImageSource::Url { url, .. } => json!({"type": "url", "url": url}),
+            };
+            (json!({"type": "image", "source": source}), cache_control)
+        }
+        ContentBlock::Thinking {
+            thinking,
+            signature,
+        } => {
+            if for_request && signature.is_none() {
+                return None;
+            }
+            let value = json!({
+                "type": "thinking",
+                "thinking": thinking,
+                "signature": signature.clone().unwrap_or_default(),
+            });
+            (value, &None)
+        }
+        ContentBlock::RedactedThinking { data } => {
+            (json!({"type": "redacted_thinking", "data": data}), &None)
+        }
+        ContentBlock::ToolUse {
+            id,
+            name,
+            input,
+            cache_control,
+            ..
+        } => (
+            json!({"type": "tool_use", "id": id, "name": name, "input": input}),
+            cache_control,
+        ),
+        ContentBlock::ToolResult {
+            tool_use_id,
+            content,
+            is_error,
+            cache_control,
+        } => {
+            let content: Vec<Value> = content
+                .iter()
+                .filter_map(|b| encode_block(b, for_request))
+                .collect();
+            let mut value = json!({
+                "type": "tool_result",
+                "tool_use_id": tool_use_id,
+                "content": content,
+            });
+            if *is_error {
+                value["is_error"] = json!(true);
+            }
+            (value, cache_control)
+        }
+    };
+    if let Some(cache_control) = cache_control {
+        value["cache_control"] = cache_control.clone();
+    }
+    Some(value)
+}
+
+// ============================================================================
+// 请求
+// ============================================================================
+
+/// 解码 Anthropic 请求
+pub fn decode_request(body: &Value) -> Result<CanonicalRequest, TranslateError> {
+    let model = str_field(body, "model").ok_or_else(|| TranslateError::missing_field("model"))?;
+    let messages = body
+        .get("messages")
+        .and_then(|m| m.as_array())
+        .ok_or_else(|| TranslateError::missing_field("messages"))?;
+
+    let mut request = CanonicalRequest::new(model);
+    request.system = decode_content(body.get("system"));
+
+    for message in messages {
+        let role = match message.get("role").and_then(|r| r.as_str()) {
+            Some("user") => Role::User,
+            Some("assistant") => Role::Assistant,
+            other => {
+                return Err(TranslateError::invalid_request(format!(
+                    "未知的消息角色: {:?}",
+                    other
+                )))
+            }
+        };
+        request.push_message(role, decode_content(message.get("content")));
+    }
+
+    // 服务端工具（web_search、bash 等）带 type 字段，保留到透传字段
+    let mut server_tools = Vec::new();
+    for tool in body
+        .get("tools")
+        .and_then(|t| t.as_array())
+        .into_iter()
+        .flatten()
+    {
+        if !matches!(
+            tool.get("type").and_then(|v| v.as_str()),
+            None | Some("custom")
+        ) {
+            server_tools.push(tool.clone());
+            continue;
+        }
+        request.tools.push(ToolDefinition {
+            name: str_field(tool, "name").unwrap_or_default(),
+            description: str_field(tool, "description"),
+            input_schema: tool
+                .get("input_schema")
+                .cloned()
+                .unwrap_or_else(|| json!({"type": "object", "properties": {}})),
+            cache_control: tool.get("cache_control").cloned(),
+        });
+    }
+
+    if let Some(choice) = body.get("tool_choice") {
+        request.tool_choice = match choice.get("type").and_then(|t| t.as_str()) {
+            Some("auto") => Some(ToolChoice::Auto),
+            Some("none") => Some(ToolChoice::None),
+            Some("any") => Some(ToolChoice::Any),
+            Some("tool") => str_field(choice, "name").map(|name| ToolChoice::Tool { name }),
+            _ => None,
+        };
+        request.parallel_tool_calls = choice
+            .get("disable_parallel_tool_use")
+            .and_then(|v| v.as_bool())
+            .map(|disabled| !disabled);
+    }
+
+    request.max_tokens = u32_field(body, "max_tokens");
+    request.temperature = body.get("temperature").and_then(|v| v.as_f64());
+    request.top_p = body.get("top_p").and_then(|v| v.as_f64());
+    request.top_k = u32_field(body, "top_k");
+    request.stop_sequences = body
+        .get("stop_sequences")
+        .and_then(|s| s.as_array())
+        .map(|items| {
+            items
+                .iter()
+                .filter_map(|s| s.as_str().map(|s| s.to_string()))
+                .collect()
+        })
+        .unwrap_or_default();
+    request.stream = body
+        .get("stream")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+    request.thinking = body.get("thinking").map(|thinking| ThinkingConfig {
+        enabled: thinking.get("type").and_then(|t| t.as_str()) != Some("disabled"),
+        budget_tokens: u32_field(thinking, "budget_tokens"),
+        effort: None,
+    });
+    request.user = body
+        .pointer("/metadata/user_id")
+        .and_then(|v| v.as_str())
+        .map(|s| s.to_string());
+    request.passthrough = collect_passthrough(Protocol::Anthropic, body, REQUEST_FIELDS);
+    if !server_tools.is_empty() {
+        request
+            .passthrough
+            .get_or_insert_with(|| Passthrough {
+                protocol: Protocol::Anthropic,
+                fields: Map::new(),
+            })
+            .fields
+            .insert("tools".to_string(), Value::Array(server_tools));
+    }
+
+    Ok(request)
+}
+
+/// 编码为 Anthropic 请求
+pub fn encode_request(request: &CanonicalRequest) -> Value {
+    let mut body = Map::new();
+    body.insert("model".into(), json!(request.model));
+    body.insert(
+        "max_tokens".into(),
+        json!(request.max_tokens.unwrap_or(DEFAULT_MAX_TOKENS)),
+    );
+
+    let plain_system = matches!(
+        request.system.as_slice(),
+        [ContentBlock::Text { cache_control: None, citations, .. }] if citations.is_empty()
+    );
+    if plain_system {
+        body.insert("system".into(), json!(request.system_text()));
+    } else if !request.system.is_empty() {
+        let system: Vec<Value> = request
+            .system
+            .iter()
+            .filter_map(|b| encode_block(b, true))
+            .collect();
+        body.insert("system".into(), Value::Array(system));
+    }
+
+    let messages: Vec<Value> = request
+        .messages
+        .iter()
+        .map(|message| {
+            let content: Vec<Value> = message
+                .content
+                .iter()
+                .filter_map(|b| encode_block(b, true))
+                .collect();
+            json!({"role": message.role.as_str(), "content": content})
+        })
+        .collect();
+    body.insert("messages".into(), Value::Array(messages));
+
+    let passthrough = request.passthrough_for(Protocol::Anthropic);
+    if !request.tools.is_empty() || passthrough.is_some_and(|p| p.contains_key("tools")) {
+        let mut tools: Vec<Value> = request
+            .tools
+            .iter()
+            .map(|tool| {
+                let mut value = json!({"name": tool.name, "input_schema": tool.input_schema});
+                if let Some(description) = &tool.description {
+                    value["description"] = json!(description);
+                }
+                if let Some(cache_control) = &tool.cache_control {
+                    value["cache_control"] = cache_control.clone();
+                }
+                value
+            })
+            .collect();
+        if let Some(Value::Array(server_tools)) = passthrough.and_then(|p| p.get("tools")) {
+            tools.extend(server_tools.iter().cloned());
+        }
+        body.insert("tools".into(), Value::Array(tools));
+    }
+
+    let choice = match &request.tool_choice {
+        Some(ToolChoice::Auto) => Some(json!({"type": "auto"})),
+        Some(ToolChoice::None) => Some(json!({"type": "none"})),
+        Some(ToolChoice::Any) => Some(json!({"type": "any"})),
+        Some(ToolChoice::Tool { name }) => Some(json!({"type": "tool", "name": name})),
+        None if request.parallel_tool_calls.is_some() => Some(json!({"type": "auto"})),
+        None => None,
+    };
+    if let Some(mut choice) = choice {
+        if let Some(parallel) = request.parallel_tool_calls {
+            choice["disable_parallel_tool_use"] = json!(!parallel);
+        }
+        body.insert("tool_choice".into(), choice);
+    }
+
+    if let Some(temperature) = request.temperature {
+        body.insert("temperature".into(), json!(temperature));
+    }
+    if let Some(top_p) = request.top_p {
+        body.insert("top_p".into(), json!(top_p));
+    }
+    if let Some(top_k) = request.top_k {
+        body.insert("top_k".into(), json!(top_k));
+    }
+    if !request.stop_sequences.is_empty() {
+        body.insert("stop_sequences".into(), json!(request.stop_sequences));
+    }
+    body.insert("stream".into(), json!(request.stream));
+    if let Some(thinking) = &request.thinking {
+        let thinking = if thinking.enabled {
+            json!({"type": "enabled", "budget_tokens": thinking.budget_or_default()})
+        } else {
+            json!({"type": "disabled"})
+        };
+        body.insert("thinking".into(), thinking);
+    }
+    if let Some(user) = &request.user {
+        body.insert("metadata".into(), json!({"user_id": user}));
+    }
+    if let Some(fields) = passthrough {
+        for (key, value) in fields {
+            body.entry(key.clone()).or_insert_with(|| value.clone());
+        }
+    }
+
+    Value::Object(body)
+}
+
+// ============================================================================
+// 响应
+// ============================================================================
+
+/// 解码 Anthropic 响应
+pub fn decode_response(body: &Value) -> Result<CanonicalResponse, TranslateError> {
+    let content = body
+        .get("content")
+        .ok_or_else(|| TranslateError::missing_field("content"))?;
+    let usage = body.get("usage").cloned().unwrap_or_default();
+    let cache_field = |key: &str| u32_field(&usage, key).filter(|v| *v > 0);
+
+    Ok(CanonicalResponse {
+        id: str_field(body, "id").unwrap_or_default(),
+        model: str_field(body, "model").unwrap_or_default(),
+        content: decode_content(Some(content)),
+        stop_reason: body
+            .get("stop_reason")
+            .and_then(|v| v.as_str())
+            .map(StopReason::from_str)
+            .unwrap_or_default(),
+        stop_sequence: str_field(body, "stop_sequence"),
+        usage: CanonicalUsage {
+            input_tokens: u32_field(&usage, "input_tokens").unwrap_or(0),
+            output_tokens: u32_field(&usage, "output_tokens").unwrap_or(0),
+            cache_read_input_tokens: cache_field("cache_read_input_tokens"),
+            cache_creation_input_tokens: cache_field("cache_creation_input_tokens"),
+            reasoning_tokens: None,
+        },
+    })
+}
+
+/// 编码为 Anthropic 响应
+pub fn encode_response(response: &CanonicalResponse) -> Value {
+    let content: Vec<Value> = response
+        .content
+        .iter()
+        .filter_map(|b| encode_block(b, false))
+        .collect();
+
+    let usage = &response.usage;
+    let mut usage_value = json!({
+        "input_tokens": usage.input_tokens,
+        "output_tokens": usage.output_tokens,
+    });
+    if let Some(cache_read) = usage.cache_read_input_tokens {
+        usage_value["cache_read_input_tokens"] = json!(cache_read);
+    }
+    if let Some(cache_creation) = usage.cache_creation_input_tokens {
+        usage_value["cache_creation_input_tokens"] = json!(cache_creation);
+    }
+
+    json!({
+        "id": response.id,
+        "type": "message",
+        "role": "assistant",
+        "model": response.model,
+        "content": content,
+        "stop_reason": response.stop_reason.to_anthropic_str(),
+        "stop_sequence": response.stop_sequence,
+        "usage": usage_value,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_preserves_cache_and_thinking() {
+        let body = json!({
+            "model": "claude-sonnet-4-5",
+            "max_tokens": 1024,
+            "system": [{"type": "text", "text": "sys", "cache_control": {"type": "ephemeral"}}],
+            "messages": [
+                {"role": "user", "content": "hi"},
+                {"role": "assistant", "content": [
+                    {"type": "thinking", "thinking": "hmm", "signature": "sig"},
+                    {"type": "tool_use", "id": "toolu_1", "name": "ls", "input": {}}
+                ]},
+                {"role": "user", "content": [
+                    {"type": "tool_result", "tool_use_id": "toolu_1", "content": "a.txt", "is_error": true}
+                ]}
+            ],
+            "tools": [
+                {"name": "ls", "input_schema": {"type": "object"}},
+                {"type": "web_search_20250305", "name": "web_search"}
+            ],
+            "tool_choice": {"type": "auto", "disable_parallel_tool_use": true},
+            "thinking": {"type": "enabled", "budget_tokens": 2048},
+            "metadata": {"user_id": "u1"}
+        });
+        let request = decode_request(&body).unwrap();
+
+        assert_eq!(
+            request.system[0],
+            ContentBlock::Text {
+                text: "sys".to_string(),
+                cache_control: Some(json!({"type": "ephemeral"})),
+                citations: Vec::new(),
+            }
+        );
+        assert!(matches!(
+            &request.messages[1].content[0],
+            ContentBlock::Thinking { signature: Some(s), .. } if s == "sig"
+        ));
+        assert!(matches!(
+            &request.messages[2].content[0],
+            ContentBlock::ToolResult { is_error: true, content, .. } if content.len() == 1
+        ));
+        assert_eq!(request.tools.len(), 1);
+        assert_eq!(
+            request.passthrough_for(Protocol::Anthropic).unwrap()["tools"][0]["name"],
+            "web_search"
+        );
+        assert_eq!(request.parallel_tool_calls, Some(false));
+        assert_eq!(request.thinking.as_ref().unwrap().budget_tokens, Some(2048));
+        assert_eq!(request.user.as_deref(), Some("u1"));
+
+        let encoded = encode_request(&request);
+        assert_eq!(encoded["system"][0]["cache_control"]["type"], "ephemeral");
+        assert_eq!(encoded["tool_choice"]["disable_parallel_tool_use"], true);
+        assert_eq!(encoded["tools"][1]["type"], "web_search_20250305");
+        assert_eq!(encoded["messages"][1]["content"][0]["signature"], "sig");
+    }
+
+    #[test]
+    fn test_unsigned_thinking_dropped_from_request() {
+        let mut request = CanonicalRequest::new("claude-sonnet-4-5");
+        request.push_message(Role::User, vec![ContentBlock::text("hi")]);
+        request.push_message(
+            Role::Assistant,
+            vec![
+                ContentBlock::Thinking {
+                    thinking: "from openai".to_string(),
+                    signature: None,
+                },
+                ContentBlock::text("hello"),
+            ],
+        );
+        let encoded = encode_request(&request);
+        assert_eq!(encoded["max_tokens"], DEFAULT_MAX_TOKENS);
+        assert_eq!(
+            encoded["messages"][1]["content"].as_array().unwrap().len(),
+            1
+        );
+    }
+}
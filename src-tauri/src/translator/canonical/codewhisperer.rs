@@ -0,0 +1,21 @@
+//! CodeWhisperer（Kiro）响应解码
+//!
+//! CodeWhisperer 只作为后端，本模块只负责响应：AWS Event Stream 二进制流
+//! 经 `AwsEventStreamParser` 解析后聚合。请求由 `translator::kiro` 生成，不经过规范模型。
+
+use super::stream::ResponseAccumulator;
+use super::types::CanonicalResponse;
+use crate::stream::parsers::AwsEventStreamParser;
+use crate::translator::traits::TranslateError;
+
+/// 解码 AWS Event Stream 响应体
+pub fn decode_response_bytes(
+    bytes: &[u8],
+    model: &str,
+) -> Result<CanonicalResponse, TranslateError> {
+    let mut parser = AwsEventStreamParser::with_model(model.to_string());
+    let mut accumulator = ResponseAccumulator::new();
+    accumulator.extend(&parser.process(bytes));
+    accumulator.extend(&parser.finish());
+    accumulator.finish()
+}
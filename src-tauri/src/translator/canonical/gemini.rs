@@ -0,0 +1,767 @@
+//! Gemini generateContent 编解码（含 Antigravity 包装格式）
+//!
+//! - 工具调用 ID 可选：请求中缺失时生成，functionResponse 按 ID 或同名的最早未匹配调用关联
+//! - 工具结果编码为 `{"result": 文本}`，错误编码为 `{"error": 文本}`
+//! - 非函数工具（googleSearch、codeExecution 等）、safetySettings 等字段只随透传字段输出
+//! - Gemini 3 使用 thinkingLevel，其余模型使用 thinkingBudget
+
+use std::collections::HashMap;
+
+use serde_json::{json, Map, Value};
+
+use super::types::*;
+use crate::converter::protocol_selector::Protocol;
+use crate::stream::generators::gemini_sse::finish_reason;
+use crate::stream::StopReason;
+use crate::translator::traits::TranslateError;
+
+/// 请求中已映射的字段
+const REQUEST_FIELDS: &[&str] = &[
+    "model",
+    "contents",
+    "systemInstruction",
+    "system_instruction",
+    "tools",
+    "toolConfig",
+    "generationConfig",
+];
+
+/// generationConfig 中已映射的字段
+const GENERATION_FIELDS: &[&str] = &[
+    "maxOutputTokens",
+    "temperature",
+    "topP",
+    "topK",
+    "stopSequences",
+    "thinkingConfig",
+];
+
+/// 生成工具调用 ID
+fn generate_call_id() -> String {
+    format!("call_{}", uuid::Uuid::new_v4().simple())
+}
+
+/// 是否为使用 thinkingLevel 的 Gemini 3 模型
+fn uses_thinking_level(model: &str) -> bool {
+    model.contains("gemini-3")
+}
+
+// ============================================================================
+// parts
+// ============================================================================
+
+/// 解码 parts 时的工具调用关联状态
+#[derive(Default)]
+struct CallTracker {
+    /// (id, name, 是否已有结果)
+    calls: Vec<(String, String, bool)>,
+}
+
+impl CallTracker {
+    fn record_call(&mut self, id: String, name: String) {
+        self.calls.push((id, name, false));
+    }
+
+    /// 为 functionResponse 找到对应的调用 ID
+    fn match_response(&mut self, id: Option<String>, name: &str) -> String {
+        let index = match &id {
+            Some(id) => self.calls.iter().position(|(call_id, _, _)| call_id == id),
+            None => self
+                .calls
+                .iter()
+                .position(|(_, call_name, matched)| !matched && call_name == name),
+        };
+        match index {
+            Some(index) => {
+                self.calls[index].2 = true;
+                self.calls[index].0.clone()
+            }
+            None => id.unwrap_or_else(generate_call_id),
+        }
+    }
+}
+
+fn decode_parts(parts: &[Value], tracker: &mut CallTracker) -> Vec<ContentBlock> {
+    parts
+        .iter()
+        .filter_map(|part| decode_part(part, tracker))
+        .collect()
+}
+
+fn decode_part(part: &Value, tracker: &mut CallTracker) -> Option<ContentBlock> {
+    let signature = str_field(part, "thoughtSignature");
+
+    if let Some(call) = part.get("functionCall") {
+        let name = str_field(call, "name").unwrap_or_default();
+        let id = str_field(call, "id").unwrap_or_else(generate_call_id);
+        tracker.record_call(id.clone(), name.clone());
+        return Some(ContentBlock::ToolUse {
+            id,
+            name,
+            input: call.get("args").cloned().unwrap_or_else(|| json!({})),
+            signature,
+            cache_control: None,
+        });
+    }
+
+    if let Some(response) = part.get("functionResponse") {
+        let name = str_field(response, "name").unwrap_or_default();
+        let tool_use_id = tracker.match_response(str_field(response, "id"), &name);
+        let (text, is_error) = decode_function_response(response.get("response"));
+        let content = if text.is_empty() {
+            Vec::new()
+        } else {
+            vec![ContentBlock::text(text)]
+        };
+        return Some(ContentBlock::ToolResult {
+            tool_use_id,
+            content,
+            is_error,
+            cache_control: None,
+        });
+    }
+
+    if let Some(data) = part.get("inlineData") {
+        return Some(ContentBlock::Image {
+            source: ImageSource::Base64 {
+                media_type: str_field(data, "mimeType").unwrap_or_default(),
+                data: str_field(data, "data").unwrap_or_default(),
+            },
+            detail: None,
+            cache_control: None,
+        });
+    }
+
+    if let Some(data) = part.get("fileData") {
+        return Some(ContentBlock::Image {
+            source: ImageSource::Url {
+                url: str_field(data, "fileUri")?,
+                media_type: str_field(data, "mimeType"),
+            },
+            detail: None,
+            cache_control: None,
+        });
+    }
+
+    let text = str_field(part, "text")?;
+    if part.get("thought").and_then(|v| v.as_bool()) == Some(true) {
+        if text.is_empty() && signature.is_none() {
+            return None;
+        }
+        return Some(ContentBlock::Thinking {
+            thinking: text,
+            signature,
+        });
+    }
+    if text.is_empty() {
+        return None;
+    }
+    Some(ContentBlock::text(text))
+}
+
+/// 解析 functionResponse.response，返回 (文本, 是否为错误)
+fn decode_function_response(response: Option<&Value>) -> (String, bool) {
+    let as_text = |value: &Value| match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    };
+    match response {
+        None | Some(Value::Null) => (String::new(), false),
+        Some(Value::Object(obj)) if obj.len() == 1 => {
+            let (key, value) = obj.iter().next().unwrap();
+            match key.as_str() {
+                "result" | "output" | "content" => (as_text(value), false),
+                "error" => (as_text(value), true),
+                _ => (as_text(response.unwrap()), false),
+            }
+        }
+        Some(value) => (as_text(value), false),
+    }
+}
+
+fn encode_image(source: &ImageSource) -> Value {
+    match source {
+        ImageSource::Base64 { media_type, data } => {
+            json!({"inlineData": {"mimeType": media_type, "data": data}})
+        }
+        ImageSource::Url { url, media_type } => {
+            let mut file_data = json!({"fileUri": url});
+            if let Some(media_type) = media_type {
+                file_data["mimeType"] = json!(media_type);
+            }
+            json!({"fileData": file_data})
+        }
+    }
+}
+
+fn with_signature(mut part: Value, signature: &Option<String>) -> Value {
+    if let Some(signature) = signature {
+        part["thoughtSignature"] = json!(signature);
+    }
+    part
+}
+
+/// 编码内容块，`tool_names` 用于为 functionResponse 填写函数名
+fn encode_parts(content: &[ContentBlock], tool_names: &HashMap<String, String>) -> Vec<Value> {
+    let mut parts = Vec::new();
+    for block in content {
+        match block {
+            ContentBlock::Text { text, .. } => parts.push(json!({"text": text})),
+            ContentBlock::Image { source, .. } => parts.push(encode_image(source)),
+            ContentBlock::Thinking {
+                thinking,
+                signature,
+            } => parts.push(with_signature(
+                json!({"text": thinking, "thought": true}),
+                signature,
+            )),
+            ContentBlock::RedactedThinking { .. } => {}
+            ContentBlock::ToolUse {
+                id,
+                name,
+                input,
+                signature,
+                ..
+            } => parts.push(with_signature(
+                json!({"functionCall": {"id": id, "name": name, "args": input}}),
+                signature,
+            )),
+            ContentBlock::ToolResult {
+                tool_use_id,
+                content,
+                is_error,
+                ..
+            } => {
+                let text: String = content.iter().filter_map(|b| b.as_text()).collect();
+                let response = if *is_error {
+                    json!({"error": text})
+                } else {
+                    json!({"result": text})
+                };
+                parts.push(json!({"functionResponse": {
+                    "id": tool_use_id,
+                    "name": tool_names.get(tool_use_id).cloned().unwrap_or_default(),
+                    "response": response,
+                }}));
+                // functionResponse 不支持图片，作为独立 part 附加
+                for inner in content {
+                    if let ContentBlock::Image { source, .. } = inner {
+                        parts.push(encode_image(source));
+                    }
+                }
+            }
+        }
+    }
+    parts
+}
+
+// ============================================================================
+// 请求
+// ============================================================================
+
+/// 解码 Gemini 请求
+///
+/// Gemini 请求体不包含模型名（在 URL 中），调用方需要自行设置 `model`。
+pub fn decode_request(body: &Value) -> Result<CanonicalRequest, TranslateError> {
+    decode_request_as(Protocol::Gemini, body)
+}
+
+/// 解码 Antigravity 请求（`{"model": ..., "request": {...}}`）
+pub fn decode_antigravity_request(body: &Value) -> Result<CanonicalRequest, TranslateError> {
+    let inner = body
+        .get("request")
+        .ok_or_else(|| TranslateError::missing_field("request"))?;
+    let mut request = decode_request_as(Protocol::Antigravity, inner)?;
+    if let Some(model) = str_field(body, "model") {
+        request.model = model;
+    }
+    Ok(request)
+}
+
+fn decode_request_as(protocol: Protocol, body: &Value) -> Result<CanonicalRequest, TranslateError> {
+    let contents = body
+        .get("contents")
+        .and_then(|c| c.as_array())
+        .ok_or_else(|| TranslateError::missing_field("contents"))?;
+
+    let model = str_field(body, "model")
+        .map(|m| m.trim_start_matches("models/").to_string())
+        .unwrap_or_default();
+    let mut request = CanonicalRequest::new(model);
+
+    if let Some(parts) = body
+        .get("systemInstruction")
+        .or_else(|| body.get("system_instruction"))
+        .and_then(|s| s.get("parts"))
+        .and_then(|p| p.as_array())
+    {
+        request.system = parts
+            .iter()
+            .filter_map(|p| str_field(p, "text"))
+            .map(ContentBlock::text)
+            .collect();
+    }
+
+    let mut tracker = CallTracker::default();
+    for content in contents {
+        let role = match content.get("role").and_then(|r| r.as_str()) {
+            Some("model") => Role::Assistant,
+            _ => Role::User,
+        };
+        let parts = content
+            .get("parts")
+            .and_then(|p| p.as_array())
+            .map(|p| decode_parts(p, &mut tracker))
+            .unwrap_or_default();
+        request.push_message(role, parts);
+    }
+
+    // 非函数工具保留到透传字段
+    let mut other_tools = Vec::new();
+    for tool in body
+        .get("tools")
+        .and_then(|t| t.as_array())
+        .into_iter()
+        .flatten()
+    {
+        let declarations = tool
+            .get("functionDeclarations")
+            .or_else(|| tool.get("function_declarations"))
+            .and_then(|d| d.as_array());
+        match declarations {
+            Some(declarations) => request.tools.extend(declarations.iter().map(|d| {
+                ToolDefinition {
+                    name: str_field(d, "name").unwrap_or_default(),
+                    description: str_field(d, "description"),
+                    input_schema: d
+                        .get("parametersJsonSchema")
+                        .or_else(|| d.get("parameters"))
+                        .cloned()
+                        .unwrap_or_else(|| json!({"type": "object", "properties": {}})),
+                    cache_control: None,
+                }
+            })),
+            None => other_tools.push(tool.clone()),
+        }
+    }
+
+    if let Some(config) = body.pointer("/toolConfig/functionCallingConfig") {
+        let allowed: Vec<String> = config
+            .get("allowedFunctionNames")
+            .and_then(|a| a.as_array())
+            .map(|a| {
+                a.iter()
+                    .filter_map(|n| n.as_str().map(|s| s.to_string()))
+                    .collect()
+            })
+            .unwrap_or_default();
+        request.tool_choice = match config.get("mode").and_then(|m| m.as_str()) {
+            Some("NONE") => Some(ToolChoice::None),
+            Some("ANY") if allowed.len() == 1 => Some(ToolChoice::Tool {
+                name: allowed[0].clone(),
+            }),
+            Some("ANY") => Some(ToolChoice::Any),
+            Some(_) => Some(ToolChoice::Auto),
+            None => None,
+        };
+    }
+
+    let mut generation_rest = Map::new();
+    if let Some(config) = body.get("generationConfig") {
+        request.max_tokens = u32_field(config, "maxOutputTokens");
+        request.temperature = config.get("temperature").and_then(|v| v.as_f64());
+        request.top_p = config.get("topP").and_then(|v| v.as_f64());
+        request.top_k = u32_field(config, "topK");
+        request.stop_sequences = config
+            .get("stopSequences")
+            .and_then(|s| s.as_array())
+            .map(|items| {
+                items
+                    .iter()
+                    .filter_map(|s| s.as_str().map(|s| s.to_string()))
+                    .collect()
+            })
+            .unwrap_or_default();
+        request.thinking = config.get("thinkingConfig").map(decode_thinking);
+        if let Some(config) = config.as_object() {
+            generation_rest = config
+                .iter()
+                .filter(|(key, _)| !GENERATION_FIELDS.contains(&key.as_str()))
+                .map(|(key, value)| (key.clone(), value.clone()))
+                .collect();
+        }
+    }
+
+    let mut passthrough =
+        collect_passthrough(protocol, body, REQUEST_FIELDS).unwrap_or(Passthrough {
+            protocol,
+            fields: Map::new(),
+        });
+    if !other_tools.is_empty() {
+        passthrough
+            .fields
+            .insert("tools".to_string(), Value::Array(other_tools));
+    }
+    if !generation_rest.is_empty() {
+        passthrough.fields.insert(
+            "generationConfig".to_string(),
+            Value::Object(generation_rest),
+        );
+    }
+    if !passthrough.fields.is_empty() {
+        request.passthrough = Some(passthrough);
+    }
+
+    Ok(request)
+}
+
+fn decode_thinking(config: &Value) -> ThinkingConfig {
+    let effort = str_field(config, "thinkingLevel").map(|s| s.to_lowercase());
+    let include_thoughts = config.get("includeThoughts").and_then(|v| v.as_bool());
+    match config.get("thinkingBudget").and_then(|v| v.as_i64()) {
+        Some(0) => ThinkingConfig {
+            enabled: false,
+            budget_tokens: None,
+            effort: None,
+        },
+        Some(budget) if budget > 0 => ThinkingConfig {
+            enabled: true,
+            budget_tokens: Some(budget as u32),
+            effort,
+        },
+        // -1 为动态预算
+        _ => ThinkingConfig {
+            enabled: effort.is_some() || include_thoughts != Some(false),
+            budget_tokens: None,
+            effort,
+        },
+    }
+}
+
+fn encode_thinking(model: &str, thinking: &ThinkingConfig) -> Value {
+    if !thinking.enabled {
+        return json!({"thinkingBudget": 0});
+    }
+    if uses_thinking_level(model) {
+        return json!({"includeThoughts": true, "thinkingLevel": thinking.effort_or_default()});
+    }
+    let budget = match (thinking.budget_tokens, &thinking.effort) {
+        (Some(budget), _) => budget as i64,
+        (None, Some(_)) => thinking.budget_or_default() as i64,
+        (None, None) => -1,
+    };
+    json!({"includeThoughts": true, "thinkingBudget": budget})
+}
+
+/// 编码为 Gemini 请求
+pub fn encode_request(request: &CanonicalRequest) -> Value {
+    let mut tool_names = HashMap::new();
+    for message in &request.messages {
+        for block in &message.content {
+            if let ContentBlock::ToolUse { id, name, .. } = block {
+                tool_names.insert(id.clone(), name.clone());
+            }
+        }
+    }
+
+    let contents: Vec<Value> = request
+        .messages
+        .iter()
+        .map(|message| {
+            let role = match message.role {
+                Role::User => "user",
+                Role::Assistant => "model",
+            };
+            json!({"role": role, "parts": encode_parts(&message.content, &tool_names)})
+        })
+        .collect();
+
+    let passthrough = request.passthrough_for(Protocol::Gemini);
+    let mut body = Map::new();
+    body.insert("contents".into(), Value::Array(contents));
+
+    if !request.system.is_empty() {
+        let parts: Vec<Value> = request
+            .system
+            .iter()
+            .filter_map(|b| b.as_text())
+            .map(|text| json!({"text": text}))
+            .collect();
+        body.insert("systemInstruction".into(), json!({"parts": parts}));
+    }
+
+    let mut tools = Vec::new();
+    if !request.tools.is_empty() {
+        let declarations: Vec<Value> = request
+            .tools
+            .iter()
+            .map(|tool| {
+                let mut declaration =
+                    json!({"name": tool.name, "parametersJsonSchema": tool.input_schema});
+                if let Some(description) = &tool.description {
+                    declaration["description"] = json!(description);
+                }
+                declaration
+            })
+            .collect();
+        tools.push(json!({"functionDeclarations": declarations}));
+    }
+    if let Some(Value::Array(other)) = passthrough.and_then(|p| p.get("tools")) {
+        tools.extend(other.iter().cloned());
+    }
+    if !tools.is_empty() {
+        body.insert("tools".into(), Value::Array(tools));
+    }
+
+    if let Some(choice) = &request.tool_choice {
+        let config = match choice {
+            ToolChoice::Auto => json!({"mode": "AUTO"}),
+            ToolChoice::None => json!({"mode": "NONE"}),
+            ToolChoice::Any => json!({"mode": "ANY"}),
+            ToolChoice::Tool { name } => json!({"mode": "ANY", "allowedFunctionNames": [name]}),
+        };
+        body.insert(
+            "toolConfig".into(),
+            json!({"functionCallingConfig": config}),
+        );
+    }
+
+    let mut generation = Map::new();
+    if let Some(max_tokens) = request.max_tokens {
+        generation.insert("maxOutputTokens".into(), json!(max_tokens));
+    }
+    if let Some(temperature) = request.temperature {
+        generation.insert("temperature".into(), json!(temperature));
+    }
+    if let Some(top_p) = request.top_p {
+        generation.insert("topP".into(), json!(top_p));
+    }
+    if let Some(top_k) = request.top_k {
+        generation.insert("topK".into(), json!(top_k));
+    }
+    if !request.stop_sequences.is_empty() {
+        generation.insert("stopSequences".into(), json!(request.stop_sequences));
+    }
+    if let Some(thinking) = &request.thinking {
+        generation.insert(
+            "thinkingConfig".into(),
+            encode_thinking(&request.model, thinking),
+        );
+    }
+    if let Some(Value::Object(rest)) = passthrough.and_then(|p| p.get("generationConfig")) {
+        for (key, value) in rest {
+            generation
+                .entry(key.clone())
+                .or_insert_with(|| value.clone());
+        }
+    }
+    if !generation.is_empty() {
+        body.insert("generationConfig".into(), Value::Object(generation));
+    }
+
+    if let Some(fields) = passthrough {
+        for (key, value) in fields {
+            body.entry(key.clone()).or_insert_with(|| value.clone());
+        }
+    }
+
+    Value::Object(body)
+}
+
+/// 编码为 Antigravity 请求（project、requestId 等字段由调用方补充）
+pub fn encode_antigravity_request(request: &CanonicalRequest) -> Value {
+    json!({
+        "model": request.model,
+        "request": encode_request(request),
+    })
+}
+
+// ============================================================================
+// 响应
+// ============================================================================
+
+/// 解码 Gemini 响应（同时接受 Antigravity 的 `{"response": {...}}` 包装）
+pub fn decode_response(body: &Value) -> Result<CanonicalResponse, TranslateError> {
+    let body = body.get("response").unwrap_or(body);
+    if let Some(error) = body.get("error") {
+        return Err(TranslateError::invalid_request(
+            str_field(error, "message").unwrap_or_else(|| error.to_string()),
+        ));
+    }
+    let candidate = body
+        .get("candidates")
+        .and_then(|c| c.as_array())
+        .and_then(|c| c.first())
+        .ok_or_else(|| TranslateError::missing_field("candidates"))?;
+
+    let content = candidate
+        .pointer("/content/parts")
+        .and_then(|p| p.as_array())
+        .map(|parts| decode_parts(parts, &mut CallTracker::default()))
+        .unwrap_or_default();
+    let has_tool_use = content
+        .iter()
+        .any(|b| matches!(b, ContentBlock::ToolUse { .. }));
+
+    let stop_reason = match candidate.get("finishReason").and_then(|v| v.as_str()) {
+        Some("MAX_TOKENS") => StopReason::MaxTokens,
+        Some("STOP") | Some("FINISH_REASON_UNSPECIFIED") | None => {
+            if has_tool_use {
+                StopReason::ToolUse
+            } else {
+                StopReason::EndTurn
+            }
+        }
+        Some(other) => StopReason::Other(other.to_string()),
+    };
+
+    let usage = body.get("usageMetadata").cloned().unwrap_or_default();
+    let cached = u32_field(&usage, "cachedContentTokenCount");
+    let reasoning = u32_field(&usage, "thoughtsTokenCount");
+
+    Ok(CanonicalResponse {
+        id: str_field(body, "responseId").unwrap_or_default(),
+        model: str_field(body, "modelVersion").unwrap_or_default(),
+        content,
+        stop_reason,
+        stop_sequence: None,
+        usage: CanonicalUsage {
+            input_tokens: u32_field(&usage, "promptTokenCount")
+                .unwrap_or(0)
+                .saturating_sub(cached.unwrap_or(0)),
+            output_tokens: u32_field(&usage, "candidatesTokenCount").unwrap_or(0)
+                + reasoning.unwrap_or(0),
+            cache_read_input_tokens: cached,
+            cache_creation_input_tokens: None,
+            reasoning_tokens: reasoning,
+        },
+    })
+}
+
+/// 编码为 Gemini 响应
+pub fn encode_response(response: &CanonicalResponse) -> Value {
+    let usage = &response.usage;
+    let reasoning = usage.reasoning_tokens.unwrap_or(0);
+    let mut usage_value = json!({
+        "promptTokenCount": usage.prompt_tokens(),
+        "candidatesTokenCount": usage.output_tokens.saturating_sub(reasoning),
+        "totalTokenCount": usage.prompt_tokens() + usage.output_tokens,
+    });
+    if let Some(reasoning) = usage.reasoning_tokens {
+        usage_value["thoughtsTokenCount"] = json!(reasoning);
+    }
+    if let Some(cached) = usage.cache_read_input_tokens {
+        usage_value["cachedContentTokenCount"] = json!(cached);
+    }
+
+    json!({
+        "candidates": [{
+            "content": {
+                "role": "model",
+                "parts": encode_parts(&response.content, &HashMap::new()),
+            },
+            "finishReason": finish_reason(&response.stop_reason),
+            "index": 0,
+        }],
+        "usageMetadata": usage_value,
+        "modelVersion": response.model,
+        "responseId": response.id,
+    })
+}
+
+/// 编码为 Antigravity 响应
+pub fn encode_antigravity_response(response: &CanonicalResponse) -> Value {
+    json!({"response": encode_response(response)})
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_function_response_matched_by_name() {
+        let body = json!({
+            "systemInstruction": {"parts": [{"text": "sys"}]},
+            "contents": [
+                {"role": "user", "parts": [{"text": "list"}]},
+                {"role": "model", "parts": [
+                    {"functionCall": {"name": "ls", "args": {}}, "thoughtSignature": "sig"},
+                    {"functionCall": {"name": "ls", "args": {"path": "b"}}}
+                ]},
+                {"role": "user", "parts": [
+                    {"functionResponse": {"name": "ls", "response": {"result": "a.txt"}}},
+                    {"functionResponse": {"name": "ls", "response": {"error": "denied"}}}
+                ]}
+            ],
+            "tools": [
+                {"functionDeclarations": [{"name": "ls", "parameters": {"type": "object"}}]},
+                {"googleSearch": {}}
+            ],
+            "toolConfig": {"functionCallingConfig": {"mode": "ANY", "allowedFunctionNames": ["ls"]}},
+            "generationConfig": {"maxOutputTokens": 100, "responseMimeType": "text/plain",
+                                 "thinkingConfig": {"thinkingBudget": 0}},
+            "safetySettings": [{"category": "HARM_CATEGORY_HARASSMENT", "threshold": "OFF"}]
+        });
+        let request = decode_request(&body).unwrap();
+
+        let (first_id, second_id) = match request.messages[1].content.as_slice() {
+            [ContentBlock::ToolUse {
+                id: a,
+                signature: Some(_),
+                ..
+            }, ContentBlock::ToolUse { id: b, .. }] => (a.clone(), b.clone()),
+            other => panic!("unexpected content: {:?}", other),
+        };
+        assert!(matches!(
+            &request.messages[2].content[0],
+            ContentBlock::ToolResult { tool_use_id, is_error: false, .. } if *tool_use_id == first_id
+        ));
+        assert!(matches!(
+            &request.messages[2].content[1],
+            ContentBlock::ToolResult { tool_use_id, is_error: true, .. } if *tool_use_id == second_id
+        ));
+        assert_eq!(
+            request.tool_choice,
+            Some(ToolChoice::Tool {
+                name: "ls".to_string()
+            })
+        );
+        assert!(!request.thinking.as_ref().unwrap().enabled);
+
+        let encoded = encode_request(&request);
+        assert_eq!(encoded["tools"][1], json!({"googleSearch": {}}));
+        assert_eq!(
+            encoded["generationConfig"]["responseMimeType"],
+            "text/plain"
+        );
+        assert_eq!(encoded["safetySettings"][0]["threshold"], "OFF");
+        assert_eq!(
+            encoded["contents"][2]["parts"][1]["functionResponse"]["name"],
+            "ls"
+        );
+    }
+
+    #[test]
+    fn test_antigravity_envelope() {
+        let body = json!({
+            "model": "gemini-3-pro-high",
+            "project": "p",
+            "request": {
+                "contents": [{"role": "user", "parts": [{"text": "hi"}]}],
+                "generationConfig": {"thinkingConfig": {"includeThoughts": true, "thinkingLevel": "HIGH"}}
+            }
+        });
+        let request = decode_antigravity_request(&body).unwrap();
+        assert_eq!(request.model, "gemini-3-pro-high");
+        assert_eq!(
+            request.thinking.as_ref().unwrap().effort.as_deref(),
+            Some("high")
+        );
+
+        let encoded = encode_antigravity_request(&request);
+        assert_eq!(
+            encoded["request"]["generationConfig"]["thinkingConfig"]["thinkingLevel"],
+            "high"
+        );
+    }
+}
@@ -0,0 +1,150 @@
+//! 规范中间模型
+//!
+//! 协议之间的转换可以经过同一个中间表示：
+//!
+//! ```text
+//! OpenAI        ──┐                        ┌──> OpenAI
+//! Anthropic     ──┤                        ├──> Anthropic
+//! Gemini        ──┼──> CanonicalRequest ───┼──> Gemini
+//! Antigravity   ──┘    CanonicalResponse   └──> Antigravity（仅 Gemini 结构）
+//! ```
+//!
+//! 新增协议只需要实现一对编解码函数，即可与已有协议互转。
+//! 思维链签名、缓存标记、引用等只有部分协议支持的信息保存在中间模型中，
+//! 由编码器按目标协议的能力决定是否输出。
+//!
+//! ## 范围
+//!
+//! - OpenAI、Anthropic、Gemini 之间的请求 / 响应转换经过本模块，
+//!   包括 Anthropic → OpenAI 类型化请求（`anthropic_to_chat_completion`）和 Gemini 原生入口
+//! - Antigravity 可以解码；`encode_antigravity_request` 只输出 Gemini 请求结构，
+//!   Antigravity 后端请求（模型映射、安全设置、函数调用签名等）仍由
+//!   `converter::openai_to_antigravity` 生成
+//! - CodeWhisperer（Kiro）只解码响应（`codewhisperer::decode_response_bytes`），
+//!   请求由 `translator::kiro` 生成
+//!
+//! `ProtocolSelector::supports_direct_conversion` 按上述范围列出可直接转换的协议对，
+//! Kiro 和 Antigravity 后端的其余入口经一个中间协议转换。
+//!
+//! 流式响应不经过本模块的请求/响应结构：各协议的 SSE 解析器和生成器
+//! （见 `stream::parsers` / `stream::generators`）直接以 `StreamEvent` 为中间表示，
+//! `stream` 子模块负责 `StreamEvent` 与 `CanonicalResponse` 之间的互转。
+
+pub mod anthropic;
+pub mod codewhisperer;
+pub mod gemini;
+pub mod openai;
+pub mod stream;
+pub mod types;
+
+#[cfg(test)]
+mod tests;
+
+pub use stream::{response_to_events, ResponseAccumulator};
+pub use types::{
+    CanonicalMessage, CanonicalRequest, CanonicalResponse, CanonicalUsage, ContentBlock,
+    ImageSource, Passthrough, Role, ThinkingConfig, ToolChoice, ToolDefinition,
+};
+
+use serde_json::Value;
+
+use crate::converter::protocol_selector::Protocol;
+use crate::models::anthropic::AnthropicMessagesRequest;
+use crate::models::openai::ChatCompletionRequest;
+use crate::translator::traits::{TranslateError, TranslateErrorKind};
+
+/// 解码请求
+pub fn decode_request(
+    protocol: Protocol,
+    body: &Value,
+) -> Result<CanonicalRequest, TranslateError> {
+    match protocol {
+        Protocol::OpenAI => openai::decode_request(body),
+        Protocol::Anthropic => anthropic::decode_request(body),
+        Protocol::Gemini => gemini::decode_request(body),
+        Protocol::Antigravity => gemini::decode_antigravity_request(body),
+        Protocol::CodeWhisperer => Err(TranslateError::unsupported(
+            "CodeWhisperer 只能作为后端协议",
+        )),
+    }
+}
+
+/// 编码请求
+pub fn encode_request(
+    protocol: Protocol,
+    request: &CanonicalRequest,
+) -> Result<Value, TranslateError> {
+    match protocol {
+        Protocol::OpenAI => Ok(openai::encode_request(request)),
+        Protocol::Anthropic => Ok(anthropic::encode_request(request)),
+        Protocol::Gemini => Ok(gemini::encode_request(request)),
+        Protocol::Antigravity => Ok(gemini::encode_antigravity_request(request)),
+        Protocol::CodeWhisperer => Err(TranslateError::unsupported(
+            "CodeWhisperer 请求由 translator::kiro 生成，不经过规范模型",
+        )),
+    }
+}
+
+/// 解码非流式响应
+pub fn decode_response(
+    protocol: Protocol,
+    body: &Value,
+) -> Result<CanonicalResponse, TranslateError> {
+    match protocol {
+        Protocol::OpenAI => openai::decode_response(body),
+        Protocol::Anthropic => anthropic::decode_response(body),
+        Protocol::Gemini | Protocol::Antigravity => gemini::decode_response(body),
+        Protocol::CodeWhisperer => Err(TranslateError::unsupported(
+            "CodeWhisperer 响应是二进制流，请使用 codewhisperer::decode_response_bytes",
+        )),
+    }
+}
+
+/// 编码非流式响应
+pub fn encode_response(
+    protocol: Protocol,
+    response: &CanonicalResponse,
+) -> Result<Value, TranslateError> {
+    match protocol {
+        Protocol::OpenAI => Ok(openai::encode_response(response)),
+        Protocol::Anthropic => Ok(anthropic::encode_response(response)),
+        Protocol::Gemini => Ok(gemini::encode_response(response)),
+        Protocol::Antigravity => Ok(gemini::encode_antigravity_response(response)),
+        Protocol::CodeWhisperer => Err(TranslateError::unsupported(
+            "CodeWhisperer 只能作为后端协议",
+        )),
+    }
+}
+
+/// 在两种协议之间转换请求
+pub fn convert_request(
+    from: Protocol,
+    to: Protocol,
+    body: &Value,
+) -> Result<Value, TranslateError> {
+    encode_request(to, &decode_request(from, body)?)
+}
+
+/// 在两种协议之间转换非流式响应
+pub fn convert_response(
+    from: Protocol,
+    to: Protocol,
+    body: &Value,
+) -> Result<Value, TranslateError> {
+    encode_response(to, &decode_response(from, body)?)
+}
+
+/// 将 Anthropic 请求转换为 OpenAI `ChatCompletionRequest`
+///
+/// 供以 OpenAI 类型化请求调用后端的路径（Kiro、OpenAI 兼容、Antigravity、Vertex 等）使用，
+/// 工具结果、图片和思维链配置按规范模型转换。无法转换时返回错误，调用方应拒绝请求。
+pub fn anthropic_to_chat_completion(
+    request: &AnthropicMessagesRequest,
+) -> Result<ChatCompletionRequest, TranslateError> {
+    let serialization_error = |e: serde_json::Error| {
+        TranslateError::new(TranslateErrorKind::SerializationError, e.to_string())
+    };
+    let body = serde_json::to_value(request).map_err(serialization_error)?;
+    let converted = convert_request(Protocol::Anthropic, Protocol::OpenAI, &body)?;
+    serde_json::from_value(converted).map_err(serialization_error)
+}
@@ -0,0 +1,585 @@
+//! OpenAI Chat Completions 编解码
+//!
+//! - 思维链使用 `reasoning_content` 字段（DeepSeek 等兼容实现的约定）
+//! - 工具结果拆分为 `tool` 角色消息，放在同一轮用户内容之前
+//! - 工具结果中的图片移到紧随其后的用户消息中
+//! - 不支持签名、缓存标记和引用，编码时丢弃
+
+use serde_json::{json, Map, Value};
+
+use super::types::*;
+use crate::converter::protocol_selector::Protocol;
+use crate::stream::StopReason;
+use crate::translator::traits::TranslateError;
+
+/// 请求中已映射的字段
+const REQUEST_FIELDS: &[&str] = &[
+    "model",
+    "messages",
+    "tools",
+    "tool_choice",
+    "parallel_tool_calls",
+    "max_tokens",
+    "max_completion_tokens",
+    "temperature",
+    "top_p",
+    "stop",
+    "stream",
+    "reasoning_effort",
+    "user",
+];
+
+// ============================================================================
+// 请求
+// ============================================================================
+
+/// 解码 OpenAI 请求
+pub fn decode_request(body: &Value) -> Result<CanonicalRequest, TranslateError> {
+    let model = str_field(body, "model").ok_or_else(|| TranslateError::missing_field("model"))?;
+    let messages = body
+        .get("messages")
+        .and_then(|m| m.as_array())
+        .ok_or_else(|| TranslateError::missing_field("messages"))?;
+
+    let mut request = CanonicalRequest::new(model);
+
+    for message in messages {
+        match message.get("role").and_then(|r| r.as_str()).unwrap_or("") {
+            "system" | "developer" => {
+                request
+                    .system
+                    .extend(decode_text_content(message.get("content")));
+            }
+            "user" => {
+                let content = decode_user_content(message.get("content"));
+                request.push_message(Role::User, content);
+            }
+            "assistant" => {
+                let content = decode_assistant_message(message);
+                request.push_message(Role::Assistant, content);
+            }
+            "tool" => {
+                let result = ContentBlock::ToolResult {
+                    tool_use_id: str_field(message, "tool_call_id").unwrap_or_default(),
+                    content: decode_text_content(message.get("content")),
+                    is_error: false,
+                    cache_control: None,
+                };
+                request.push_message(Role::User, vec![result]);
+            }
+            _ => {}
+        }
+    }
+
+    if let Some(tools) = body.get("tools").and_then(|t| t.as_array()) {
+        request.tools = tools
+            .iter()
+            .filter(|t| t.get("type").and_then(|v| v.as_str()) == Some("function"))
+            .filter_map(|t| t.get("function"))
+            .map(|f| ToolDefinition {
+                name: str_field(f, "name").unwrap_or_default(),
+                description: str_field(f, "description"),
+                input_schema: f
+                    .get("parameters")
+                    .cloned()
+                    .unwrap_or_else(|| json!({"type": "object", "properties": {}})),
+                cache_control: None,
+            })
+            .collect();
+    }
+
+    request.tool_choice = body.get("tool_choice").and_then(|choice| match choice {
+        Value::String(s) => match s.as_str() {
+            "auto" => Some(ToolChoice::Auto),
+            "none" => Some(ToolChoice::None),
+            "required" => Some(ToolChoice::Any),
+            _ => None,
+        },
+        Value::Object(_) => choice
+            .pointer("/function/name")
+            .and_then(|n| n.as_str())
+            .map(|name| ToolChoice::Tool {
+                name: name.to_string(),
+            }),
+        _ => None,
+    });
+    request.parallel_tool_calls = body.get("parallel_tool_calls").and_then(|v| v.as_bool());
+    request.max_tokens =
+        u32_field(body, "max_tokens").or_else(|| u32_field(body, "max_completion_tokens"));
+    request.temperature = body.get("temperature").and_then(|v| v.as_f64());
+    request.top_p = body.get("top_p").and_then(|v| v.as_f64());
+    request.stop_sequences = match body.get("stop") {
+        Some(Value::String(s)) => vec![s.clone()],
+        Some(Value::Array(items)) => items
+            .iter()
+            .filter_map(|s| s.as_str().map(|s| s.to_string()))
+            .collect(),
+        _ => Vec::new(),
+    };
+    request.stream = body
+        .get("stream")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+    request.thinking = str_field(body, "reasoning_effort").map(|effort| {
+        if effort == "none" {
+            ThinkingConfig {
+                enabled: false,
+                budget_tokens: None,
+                effort: None,
+            }
+        } else {
+            ThinkingConfig {
+                enabled: true,
+                budget_tokens: None,
+                effort: Some(effort),
+            }
+        }
+    });
+    request.user = str_field(body, "user");
+    request.passthrough = collect_passthrough(Protocol::OpenAI, body, REQUEST_FIELDS);
+
+    Ok(request)
+}
+
+/// 文本内容（字符串或 text 分片数组）
+fn decode_text_content(content: Option<&Value>) -> Vec<ContentBlock> {
+    match content {
+        Some(Value::String(s)) if !s.is_empty() => vec![ContentBlock::text(s.clone())],
+        Some(Value::Array(parts)) => parts
+            .iter()
+            .filter_map(|p| {
+                p.get("text")
+                    .or_else(|| p.get("refusal"))
+                    .and_then(|t| t.as_str())
+            })
+            .map(ContentBlock::text)
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn decode_user_content(content: Option<&Value>) -> Vec<ContentBlock> {
+    let Some(Value::Array(parts)) = content else {
+        return decode_text_content(content);
+    };
+    parts
+        .iter()
+        .filter_map(|part| match part.get("type").and_then(|t| t.as_str()) {
+            Some("text") => str_field(part, "text").map(ContentBlock::text),
+            Some("image_url") => {
+                let image_url = part.get("image_url")?;
+                let url = match image_url {
+                    Value::String(url) => url.clone(),
+                    _ => str_field(image_url, "url")?,
+                };
+                let source = match parse_data_url(&url) {
+                    Some((media_type, data)) => ImageSource::Base64 { media_type, data },
+                    None => ImageSource::Url {
+                        url,
+                        media_type: None,
+                    },
+                };
+                Some(ContentBlock::Image {
+                    source,
+                    detail: str_field(image_url, "detail"),
+                    cache_control: None,
+                })
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+fn decode_assistant_message(message: &Value) -> Vec<ContentBlock> {
+    let mut content = Vec::new();
+    if let Some(reasoning) = str_field(message, "reasoning_content").filter(|s| !s.is_empty()) {
+        content.push(ContentBlock::Thinking {
+            thinking: reasoning,
+            signature: None,
+        });
+    }
+    content.extend(decode_text_content(message.get("content")));
+    if let Some(calls) = message.get("tool_calls").and_then(|c| c.as_array()) {
+        content.extend(calls.iter().map(decode_tool_call));
+    }
+    content
+}
+
+fn decode_tool_call(call: &Value) -> ContentBlock {
+    let function = call.get("function").cloned().unwrap_or_default();
+    let arguments = str_field(&function, "arguments").unwrap_or_default();
+    ContentBlock::ToolUse {
+        id: str_field(call, "id").unwrap_or_default(),
+        name: str_field(&function, "name").unwrap_or_default(),
+        input: parse_arguments(&arguments),
+        signature: None,
+        cache_control: None,
+    }
+}
+
+/// 解析工具参数，空字符串视为空对象，非法 JSON 保留为字符串
+pub(crate) fn parse_arguments(arguments: &str) -> Value {
+    if arguments.trim().is_empty() {
+        return json!({});
+    }
+    serde_json::from_str(arguments).unwrap_or_else(|_| Value::String(arguments.to_string()))
+}
+
+/// 序列化工具参数
+pub(crate) fn format_arguments(input: &Value) -> String {
+    match input {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// 编码为 OpenAI 请求
+pub fn encode_request(request: &CanonicalRequest) -> Value {
+    let mut messages = Vec::new();
+
+    if !request.system.is_empty() {
+        messages.push(json!({
+            "role": "system",
+            "content": encode_text_content(&request.system),
+        }));
+    }
+
+    for message in &request.messages {
+        match message.role {
+            Role::User => encode_user_message(&message.content, &mut messages),
+            Role::Assistant => messages.push(encode_assistant_message(&message.content)),
+        }
+    }
+
+    let mut body = Map::new();
+    body.insert("model".into(), json!(request.model));
+    body.insert("messages".into(), Value::Array(messages));
+    if !request.tools.is_empty() {
+        let tools: Vec<Value> = request
+            .tools
+            .iter()
+            .map(|tool| {
+                let mut function = json!({
+                    "name": tool.name,
+                    "parameters": tool.input_schema,
+                });
+                if let Some(description) = &tool.description {
+                    function["description"] = json!(description);
+                }
+                json!({"type": "function", "function": function})
+            })
+            .collect();
+        body.insert("tools".into(), Value::Array(tools));
+    }
+    if let Some(choice) = &request.tool_choice {
+        let choice = match choice {
+            ToolChoice::Auto => json!("auto"),
+            ToolChoice::None => json!("none"),
+            ToolChoice::Any => json!("required"),
+            ToolChoice::Tool { name } => json!({"type": "function", "function": {"name": name}}),
+        };
+        body.insert("tool_choice".into(), choice);
+    }
+    if let Some(parallel) = request.parallel_tool_calls {
+        body.insert("parallel_tool_calls".into(), json!(parallel));
+    }
+    if let Some(max_tokens) = request.max_tokens {
+        body.insert("max_tokens".into(), json!(max_tokens));
+    }
+    if let Some(temperature) = request.temperature {
+        body.insert("temperature".into(), json!(temperature));
+    }
+    if let Some(top_p) = request.top_p {
+        body.insert("top_p".into(), json!(top_p));
+    }
+    if !request.stop_sequences.is_empty() {
+        body.insert("stop".into(), json!(request.stop_sequences));
+    }
+    body.insert("stream".into(), json!(request.stream));
+    if let Some(thinking) = &request.thinking {
+        let effort = if thinking.enabled {
+            thinking.effort_or_default()
+        } else {
+            "none".to_string()
+        };
+        body.insert("reasoning_effort".into(), json!(effort));
+    }
+    if let Some(user) = &request.user {
+        body.insert("user".into(), json!(user));
+    }
+    if let Some(fields) = request.passthrough_for(Protocol::OpenAI) {
+        for (key, value) in fields {
+            body.entry(key.clone()).or_insert_with(|| value.clone());
+        }
+    }
+
+    Value::Object(body)
+}
+
+/// 单个文本编码为字符串，多个文本编码为 text 分片数组
+fn encode_text_content(blocks: &[ContentBlock]) -> Value {
+    let texts: Vec<&str> = blocks.iter().filter_map(|b| b.as_text()).collect();
+    match texts.as_slice() {
+        [] => json!(""),
+        [text] => json!(text),
+        texts => Value::Array(
+            texts
+                .iter()
+                .map(|t| json!({"type": "text", "text": t}))
+                .collect(),
+        ),
+    }
+}
+
+fn encode_image(source: &ImageSource, detail: &Option<String>) -> Value {
+    let url = match source {
+        ImageSource::Base64 { media_type, data } => format!("data:{};base64,{}", media_type, data),
+        ImageSource::Url { url, .. } => url.clone(),
+    };
+    let mut image_url = json!({"url": url});
+    if let Some(detail) = detail {
+        image_url["detail"] = json!(detail);
+    }
+    json!({"type": "image_url", "image_url": image_url})
+}
+
+fn encode_user_message(content: &[ContentBlock], messages: &mut Vec<Value>) {
+    let mut parts = Vec::new();
+    for block in content {
+        match block {
+            ContentBlock::ToolResult {
+                tool_use_id,
+                content,
+                ..
+            } => {
+                messages.push(json!({
+                    "role": "tool",
+                    "tool_call_id": tool_use_id,
+                    "content": encode_text_content(content),
+                }));
+                // tool 消息只支持文本，图片放到随后的用户消息中
+                for inner in content {
+                    if let ContentBlock::Image { source, detail, .. } = inner {
+                        parts.push(encode_image(source, detail));
+                    }
+                }
+            }
+            ContentBlock::Text { text, .. } => parts.push(json!({"type": "text", "text": text})),
+            ContentBlock::Image { source, detail, .. } => parts.push(encode_image(source, detail)),
+            _ => {}
+        }
+    }
+
+    if parts.is_empty() {
+        return;
+    }
+    let content = match parts.as_slice() {
+        [part] if part["type"] == "text" => part["text"].clone(),
+        _ => Value::Array(parts),
+    };
+    messages.push(json!({"role": "user", "content": content}));
+}
+
+fn encode_assistant_message(content: &[ContentBlock]) -> Value {
+    let texts: Vec<ContentBlock> = content
+        .iter()
+        .filter(|b| matches!(b, ContentBlock::Text { .. }))
+        .cloned()
+        .collect();
+    let reasoning: String = content
+        .iter()
+        .filter_map(|b| match b {
+            ContentBlock::Thinking { thinking, .. } => Some(thinking.as_str()),
+            _ => None,
+        })
+        .collect();
+    let tool_calls: Vec<Value> = content
+        .iter()
+        .filter_map(|b| match b {
+            ContentBlock::ToolUse {
+                id, name, input, ..
+            } => Some(json!({
+                "id": id,
+                "type": "function",
+                "function": {"name": name, "arguments": format_arguments(input)},
+            })),
+            _ => None,
+        })
+        .collect();
+
+    let mut message = json!({
+        "role": "assistant",
+        "content": if texts.is_empty() { Value::Null } else { encode_text_content(&texts) },
+    });
+    if !reasoning.is_empty() {
+        message["reasoning_content"] = json!(reasoning);
+    }
+    if !tool_calls.is_empty() {
+        message["tool_calls"] = Value::Array(tool_calls);
+    }
+    message
+}
+
+// ============================================================================
+// 响应
+// ============================================================================
+
+/// 解码 OpenAI 响应
+pub fn decode_response(body: &Value) -> Result<CanonicalResponse, TranslateError> {
+    let choice = body
+        .get("choices")
+        .and_then(|c| c.as_array())
+        .and_then(|c| c.first())
+        .ok_or_else(|| TranslateError::missing_field("choices"))?;
+    let message = choice.get("message").cloned().unwrap_or_default();
+
+    let usage = body.get("usage").cloned().unwrap_or_default();
+    let cached = usage
+        .pointer("/prompt_tokens_details/cached_tokens")
+        .and_then(|v| v.as_u64())
+        .map(|v| v as u32);
+    let prompt_tokens = u32_field(&usage, "prompt_tokens").unwrap_or(0);
+
+    Ok(CanonicalResponse {
+        id: str_field(body, "id").unwrap_or_default(),
+        model: str_field(body, "model").unwrap_or_default(),
+        content: decode_assistant_message(&message),
+        stop_reason: StopReason::from_str(
+            choice
+                .get("finish_reason")
+                .and_then(|v| v.as_str())
+                .unwrap_or("stop"),
+        ),
+        stop_sequence: None,
+        usage: CanonicalUsage {
+            input_tokens: prompt_tokens.saturating_sub(cached.unwrap_or(0)),
+            output_tokens: u32_field(&usage, "completion_tokens").unwrap_or(0),
+            cache_read_input_tokens: cached,
+            cache_creation_input_tokens: None,
+            reasoning_tokens: usage
+                .pointer("/completion_tokens_details/reasoning_tokens")
+                .and_then(|v| v.as_u64())
+                .map(|v| v as u32),
+        },
+    })
+}
+
+/// 编码为 OpenAI 响应
+pub fn encode_response(response: &CanonicalResponse) -> Value {
+    let mut message = encode_assistant_message(&response.content);
+    // 响应中的多个文本块合并为字符串
+    if message["content"].is_array() {
+        message["content"] = json!(response.text());
+    }
+
+    let usage = &response.usage;
+    let mut usage_value = json!({
+        "prompt_tokens": usage.prompt_tokens(),
+        "completion_tokens": usage.output_tokens,
+        "total_tokens": usage.prompt_tokens() + usage.output_tokens,
+    });
+    if let Some(cached) = usage.cache_read_input_tokens {
+        usage_value["prompt_tokens_details"] = json!({"cached_tokens": cached});
+    }
+    if let Some(reasoning) = usage.reasoning_tokens {
+        usage_value["completion_tokens_details"] = json!({"reasoning_tokens": reasoning});
+    }
+
+    json!({
+        "id": response.id,
+        "object": "chat.completion",
+        "created": chrono::Utc::now().timestamp(),
+        "model": response.model,
+        "choices": [{
+            "index": 0,
+            "message": message,
+            "finish_reason": response.stop_reason.to_openai_str(),
+        }],
+        "usage": usage_value,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_tool_round() {
+        let body = json!({
+            "model": "gpt-4o",
+            "messages": [
+                {"role": "system", "content": "be brief"},
+                {"role": "user", "content": [
+                    {"type": "text", "text": "look"},
+                    {"type": "image_url", "image_url": {"url": "data:image/png;base64,AAAA", "detail": "low"}}
+                ]},
+                {"role": "assistant", "content": null, "reasoning_content": "hmm",
+                 "tool_calls": [{"id": "call_1", "type": "function", "function": {"name": "ls", "arguments": "{\"path\":\".\"}"}}]},
+                {"role": "tool", "tool_call_id": "call_1", "content": "a.txt"},
+                {"role": "user", "content": "thanks"}
+            ],
+            "stop": "END",
+            "reasoning_effort": "high",
+            "seed": 7
+        });
+        let request = decode_request(&body).unwrap();
+
+        assert_eq!(request.system_text(), "be brief");
+        assert_eq!(request.messages.len(), 3);
+        assert!(matches!(
+            &request.messages[0].content[1],
+            ContentBlock::Image { source: ImageSource::Base64 { media_type, .. }, detail: Some(d), .. }
+                if media_type == "image/png" && d == "low"
+        ));
+        assert!(matches!(
+            &request.messages[1].content[1],
+            ContentBlock::ToolUse { input, .. } if input["path"] == "."
+        ));
+        // tool 消息与随后的用户消息合并为一条用户消息
+        assert_eq!(request.messages[2].content.len(), 2);
+        assert_eq!(request.stop_sequences, vec!["END"]);
+        assert_eq!(
+            request.thinking.as_ref().unwrap().effort.as_deref(),
+            Some("high")
+        );
+        assert_eq!(
+            request.passthrough_for(Protocol::OpenAI).unwrap()["seed"],
+            7
+        );
+
+        let encoded = encode_request(&request);
+        assert_eq!(encoded["messages"][3]["role"], "tool");
+        assert_eq!(encoded["messages"][4]["content"], "thanks");
+        assert_eq!(encoded["seed"], 7);
+    }
+
+    #[test]
+    fn test_tool_result_images_follow_tool_message() {
+        let mut request = CanonicalRequest::new("gpt-4o");
+        request.push_message(
+            Role::User,
+            vec![ContentBlock::ToolResult {
+                tool_use_id: "call_1".to_string(),
+                content: vec![
+                    ContentBlock::text("screenshot"),
+                    ContentBlock::Image {
+                        source: ImageSource::Url {
+                            url: "https://example.com/a.png".to_string(),
+                            media_type: None,
+                        },
+                        detail: None,
+                        cache_control: None,
+                    },
+                ],
+                is_error: false,
+                cache_control: None,
+            }],
+        );
+        let encoded = encode_request(&request);
+        assert_eq!(encoded["messages"][0]["content"], "screenshot");
+        assert_eq!(
+            encoded["messages"][1]["content"][0]["image_url"]["url"],
+            "https://example.com/a.png"
+        );
+    }
+}
@@ -0,0 +1,295 @@
+//! 规范响应与 `StreamEvent` 之间的转换
+//!
+//! - `ResponseAccumulator`：把任意后端解析出的事件流聚合为 `CanonicalResponse`，
+//!   用于前端请求非流式而后端只能流式返回的场景
+//! - `response_to_events`：把完整响应展开为事件序列，用于前端请求流式而后端返回完整响应的场景
+
+use std::collections::HashMap;
+
+use super::openai::parse_arguments;
+use super::types::*;
+use crate::stream::{ContentBlockType, StopReason, StreamEvent};
+use crate::translator::traits::{TranslateError, TranslateErrorKind};
+
+/// 流事件聚合器
+#[derive(Debug, Default)]
+pub struct ResponseAccumulator {
+    id: String,
+    model: String,
+    content: Vec<ContentBlock>,
+    /// 当前接收增量的内容块位置
+    current: Option<usize>,
+    /// 工具调用 ID -> (内容块位置, 累积的输入 JSON)
+    tool_inputs: HashMap<String, (usize, String)>,
+    usage: CanonicalUsage,
+    stop_reason: Option<StopReason>,
+    error: Option<(String, String)>,
+}
+
+impl ResponseAccumulator {
+    /// 创建新的聚合器
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 处理单个事件
+    pub fn push(&mut self, event: &StreamEvent) {
+        match event {
+            StreamEvent::MessageStart { id, model } => {
+                self.id = id.clone();
+                self.model = model.clone();
+            }
+            StreamEvent::ContentBlockStart { block_type, .. } => match block_type {
+                ContentBlockType::Text => self.open(ContentBlock::text("")),
+                ContentBlockType::Thinking => self.open(ContentBlock::Thinking {
+                    thinking: String::new(),
+                    signature: None,
+                }),
+                ContentBlockType::RedactedThinking { data } => {
+                    self.open(ContentBlock::RedactedThinking { data: data.clone() })
+                }
+                ContentBlockType::ToolUse { id, name } => self.open_tool(id, name),
+            },
+            StreamEvent::TextDelta { text } => {
+                if !matches!(self.current_block(), Some(ContentBlock::Text { .. })) {
+                    self.open(ContentBlock::text(""));
+                }
+                if let Some(ContentBlock::Text { text: buffer, .. }) = self.current_block() {
+                    buffer.push_str(text);
+                }
+            }
+            StreamEvent::ThinkingDelta { text } => {
+                if !matches!(self.current_block(), Some(ContentBlock::Thinking { .. })) {
+                    self.open(ContentBlock::Thinking {
+                        thinking: String::new(),
+                        signature: None,
+                    });
+                }
+                if let Some(ContentBlock::Thinking { thinking, .. }) = self.current_block() {
+                    thinking.push_str(text);
+                }
+            }
+            StreamEvent::SignatureDelta { signature } => match self.current_block() {
+                Some(ContentBlock::Thinking { signature: s, .. })
+                | Some(ContentBlock::ToolUse { signature: s, .. }) => {
+                    *s = Some(s.take().unwrap_or_default() + signature.as_str());
+                }
+                _ => {}
+            },
+            StreamEvent::CitationDelta { citation } => {
+                if let Some(ContentBlock::Text { citations, .. }) = self.current_block() {
+                    citations.push(citation.clone());
+                }
+            }
+            StreamEvent::ToolUseStart { id, name } => match self.tool_inputs.get(id) {
+                Some((position, _)) => self.current = Some(*position),
+                None => self.open_tool(id, name),
+            },
+            StreamEvent::ToolUseInputDelta { id, partial_json } => {
+                if let Some((_, buffer)) = self.tool_inputs.get_mut(id) {
+                    buffer.push_str(partial_json);
+                }
+            }
+            StreamEvent::ToolUseStop { id } => self.finish_tool(id),
+            StreamEvent::ContentBlockStop { .. } => {
+                if let Some(ContentBlock::ToolUse { id, .. }) = self.current_block() {
+                    let id = id.clone();
+                    self.finish_tool(&id);
+                }
+                self.current = None;
+            }
+            StreamEvent::Usage {
+                input_tokens,
+                output_tokens,
+                cache_read_input_tokens,
+                cache_creation_input_tokens,
+            } => {
+                self.usage.input_tokens = *input_tokens;
+                self.usage.output_tokens = *output_tokens;
+                self.usage.cache_read_input_tokens = cache_read_input_tokens.filter(|v| *v > 0);
+                self.usage.cache_creation_input_tokens =
+                    cache_creation_input_tokens.filter(|v| *v > 0);
+            }
+            StreamEvent::MessageStop { stop_reason } => {
+                self.stop_reason = Some(stop_reason.clone());
+            }
+            StreamEvent::Error {
+                error_type,
+                message,
+            } => {
+                self.error = Some((error_type.clone(), message.clone()));
+            }
+            StreamEvent::BackendUsage { .. } | StreamEvent::Ping => {}
+        }
+    }
+
+    /// 处理一组事件
+    pub fn extend<'a>(&mut self, events: impl IntoIterator<Item = &'a StreamEvent>) {
+        for event in events {
+            self.push(event);
+        }
+    }
+
+    /// 生成完整响应，流中出现错误事件时返回错误
+    pub fn finish(mut self) -> Result<CanonicalResponse, TranslateError> {
+        if let Some((error_type, message)) = self.error {
+            return Err(TranslateError::new(
+                TranslateErrorKind::Other,
+                format!("{}: {}", error_type, message),
+            ));
+        }
+        let ids: Vec<String> = self.tool_inputs.keys().cloned().collect();
+        for id in ids {
+            self.finish_tool(&id);
+        }
+
+        let has_tool_use = self
+            .content
+            .iter()
+            .any(|b| matches!(b, ContentBlock::ToolUse { .. }));
+        let stop_reason = self.stop_reason.unwrap_or(if has_tool_use {
+            StopReason::ToolUse
+        } else {
+            StopReason::EndTurn
+        });
+
+        Ok(CanonicalResponse {
+            id: self.id,
+            model: self.model,
+            content: self.content,
+            stop_reason,
+            stop_sequence: None,
+            usage: self.usage,
+        })
+    }
+
+    fn current_block(&mut self) -> Option<&mut ContentBlock> {
+        self.current
+            .and_then(|position| self.content.get_mut(position))
+    }
+
+    fn open(&mut self, block: ContentBlock) {
+        self.content.push(block);
+        self.current = Some(self.content.len() - 1);
+    }
+
+    fn open_tool(&mut self, id: &str, name: &str) {
+        self.open(ContentBlock::ToolUse {
+            id: id.to_string(),
+            name: name.to_string(),
+            input: serde_json::Value::Null,
+            signature: None,
+            cache_control: None,
+        });
+        self.tool_inputs
+            .insert(id.to_string(), (self.content.len() - 1, String::new()));
+    }
+
+    /// 解析累积的工具输入
+    fn finish_tool(&mut self, id: &str) {
+        let Some((position, buffer)) = self.tool_inputs.remove(id) else {
+            return;
+        };
+        if let Some(ContentBlock::ToolUse { input, .. }) = self.content.get_mut(position) {
+            *input = parse_arguments(&buffer);
+        }
+    }
+}
+
+/// 将完整响应展开为事件序列
+pub fn response_to_events(response: &CanonicalResponse) -> Vec<StreamEvent> {
+    let mut events = vec![StreamEvent::MessageStart {
+        id: response.id.clone(),
+        model: response.model.clone(),
+    }];
+
+    let mut index = 0;
+    for block in &response.content {
+        match block {
+            ContentBlock::Text {
+                text, citations, ..
+            } => {
+                events.push(StreamEvent::ContentBlockStart {
+                    index,
+                    block_type: ContentBlockType::Text,
+                });
+                if !text.is_empty() {
+                    events.push(StreamEvent::TextDelta { text: text.clone() });
+                }
+                events.extend(citations.iter().map(|citation| StreamEvent::CitationDelta {
+                    citation: citation.clone(),
+                }));
+            }
+            ContentBlock::Thinking {
+                thinking,
+                signature,
+            } => {
+                events.push(StreamEvent::ContentBlockStart {
+                    index,
+                    block_type: ContentBlockType::Thinking,
+                });
+                if !thinking.is_empty() {
+                    events.push(StreamEvent::ThinkingDelta {
+                        text: thinking.clone(),
+                    });
+                }
+                if let Some(signature) = signature {
+                    events.push(StreamEvent::SignatureDelta {
+                        signature: signature.clone(),
+                    });
+                }
+            }
+            ContentBlock::RedactedThinking { data } => {
+                events.push(StreamEvent::ContentBlockStart {
+                    index,
+                    block_type: ContentBlockType::RedactedThinking { data: data.clone() },
+                });
+            }
+            ContentBlock::ToolUse {
+                id,
+                name,
+                input,
+                signature,
+                ..
+            } => {
+                events.push(StreamEvent::ContentBlockStart {
+                    index,
+                    block_type: ContentBlockType::ToolUse {
+                        id: id.clone(),
+                        name: name.clone(),
+                    },
+                });
+                events.push(StreamEvent::ToolUseStart {
+                    id: id.clone(),
+                    name: name.clone(),
+                });
+                events.push(StreamEvent::ToolUseInputDelta {
+                    id: id.clone(),
+                    partial_json: input.to_string(),
+                });
+                if let Some(signature) = signature {
+                    events.push(StreamEvent::SignatureDelta {
+                        signature: signature.clone(),
+                    });
+                }
+                events.push(StreamEvent::ToolUseStop { id: id.clone() });
+            }
+            // 图片和工具结果不会出现在响应中
+            ContentBlock::Image { .. } | ContentBlock::ToolResult { .. } => continue,
+        }
+        events.push(StreamEvent::ContentBlockStop { index });
+        index += 1;
+    }
+
+    let usage = &response.usage;
+    events.push(StreamEvent::Usage {
+        input_tokens: usage.input_tokens,
+        output_tokens: usage.output_tokens,
+        cache_read_input_tokens: usage.cache_read_input_tokens,
+        cache_creation_input_tokens: usage.cache_creation_input_tokens,
+    });
+    events.push(StreamEvent::MessageStop {
+        stop_reason: response.stop_reason.clone(),
+    });
+    events
+}
@@ -0,0 +1,900 @@
+//! 规范模型属性测试
+//!
+//! 对每个协议生成该协议可以表达的规范请求/响应，验证：
+//! - 请求：canonical → 协议 JSON → canonical 保持不变
+//! - 响应：canonical → 协议 JSON → canonical 保持不变
+//! - 流式：canonical → StreamEvent → 协议 SSE → StreamEvent → canonical 保持不变
+
+use proptest::prelude::*;
+use serde_json::{json, Value};
+
+use super::*;
+use crate::converter::protocol_selector::Protocol;
+use crate::stream::generators::{AnthropicSseGenerator, GeminiSseGenerator, OpenAiSseGenerator};
+use crate::stream::parsers::{AnthropicSseParser, GeminiSseParser, OpenAiSseParser};
+use crate::stream::StopReason;
+
+// ============================================================================
+// 基础生成器
+// ============================================================================
+
+fn arb_text() -> impl Strategy<Value = String> {
+    prop::string::string_regex("[a-zA-Z0-9][a-zA-Z0-9 .,]{0,15}").unwrap()
+}
+
+fn arb_ident() -> impl Strategy<Value = String> {
+    prop::string::string_regex("[a-z][a-z0-9_]{2,10}").unwrap()
+}
+
+fn arb_cache_control() -> impl Strategy<Value = Option<Value>> {
+    prop::option::of(Just(json!({"type": "ephemeral"})))
+}
+
+/// 简单 JSON 对象（工具参数、引用等）
+fn arb_object() -> impl Strategy<Value = Value> {
+    prop::collection::btree_map(
+        arb_ident(),
+        prop_oneof![
+            any::<i32>().prop_map(|n| json!(n)),
+            arb_text().prop_map(|s| json!(s)),
+            any::<bool>().prop_map(|b| json!(b)),
+        ],
+        0..3,
+    )
+    .prop_map(|map| Value::Object(map.into_iter().collect()))
+}
+
+fn arb_fraction() -> impl Strategy<Value = f64> {
+    (0u32..20).prop_map(|n| n as f64 / 10.0)
+}
+
+fn arb_plain_text() -> impl Strategy<Value = ContentBlock> {
+    arb_text().prop_map(ContentBlock::text)
+}
+
+fn arb_base64_image() -> impl Strategy<Value = ImageSource> {
+    (
+        prop_oneof![Just("image/png"), Just("image/jpeg")],
+        prop::string::string_regex("[A-Za-z0-9+/]{4,16}").unwrap(),
+    )
+        .prop_map(|(media_type, data)| ImageSource::Base64 {
+            media_type: media_type.to_string(),
+            data,
+        })
+}
+
+fn arb_url(with_media_type: bool) -> impl Strategy<Value = ImageSource> {
+    arb_ident().prop_map(move |name| ImageSource::Url {
+        url: format!("https://example.com/{}.png", name),
+        media_type: with_media_type.then(|| "image/png".to_string()),
+    })
+}
+
+fn arb_tool_use(with_signature: bool) -> impl Strategy<Value = ContentBlock> {
+    (
+        arb_ident(),
+        arb_ident(),
+        arb_object(),
+        prop::option::of(arb_ident()),
+    )
+        .prop_map(move |(id, name, input, signature)| ContentBlock::ToolUse {
+            id: format!("call_{}", id),
+            name,
+            input,
+            signature: signature.filter(|_| with_signature),
+            cache_control: None,
+        })
+}
+
+fn arb_tool(with_cache: bool) -> impl Strategy<Value = ToolDefinition> {
+    (
+        arb_ident(),
+        prop::option::of(arb_text()),
+        arb_object(),
+        arb_cache_control(),
+    )
+        .prop_map(
+            move |(name, description, properties, cache_control)| ToolDefinition {
+                name,
+                description,
+                input_schema: json!({"type": "object", "properties": properties}),
+                cache_control: cache_control.filter(|_| with_cache),
+            },
+        )
+}
+
+fn arb_tool_choice() -> impl Strategy<Value = Option<ToolChoice>> {
+    prop::option::of(prop_oneof![
+        Just(ToolChoice::Auto),
+        Just(ToolChoice::None),
+        Just(ToolChoice::Any),
+        arb_ident().prop_map(|name| ToolChoice::Tool { name }),
+    ])
+}
+
+/// 把生成的消息内容组装为角色交替的消息列表
+fn alternate(turns: Vec<(Vec<ContentBlock>, Vec<ContentBlock>)>) -> Vec<CanonicalMessage> {
+    let mut messages = Vec::new();
+    for (user, assistant) in turns {
+        messages.push(CanonicalMessage {
+            role: Role::User,
+            content: user,
+        });
+        messages.push(CanonicalMessage {
+            role: Role::Assistant,
+            content: assistant,
+        });
+    }
+    messages
+}
+
+/// 请求的公共字段
+#[derive(Debug, Clone)]
+struct Sampling {
+    max_tokens: Option<u32>,
+    temperature: Option<f64>,
+    top_p: Option<f64>,
+    stop_sequences: Vec<String>,
+}
+
+fn arb_sampling() -> impl Strategy<Value = Sampling> {
+    (
+        prop::option::of(1u32..100_000),
+        prop::option::of(arb_fraction()),
+        prop::option::of(arb_fraction()),
+        prop::collection::vec(arb_ident(), 0..3),
+    )
+        .prop_map(
+            |(max_tokens, temperature, top_p, stop_sequences)| Sampling {
+                max_tokens,
+                temperature,
+                top_p,
+                stop_sequences,
+            },
+        )
+}
+
+fn apply_sampling(request: &mut CanonicalRequest, sampling: Sampling) {
+    request.max_tokens = sampling.max_tokens;
+    request.temperature = sampling.temperature;
+    request.top_p = sampling.top_p;
+    request.stop_sequences = sampling.stop_sequences;
+}
+
+// ============================================================================
+// OpenAI 可表达的子集
+// ============================================================================
+
+fn arb_openai_user() -> impl Strategy<Value = Vec<ContentBlock>> {
+    let tool_result =
+        (arb_ident(), prop::collection::vec(arb_plain_text(), 0..3)).prop_map(|(id, content)| {
+            ContentBlock::ToolResult {
+                tool_use_id: format!("call_{}", id),
+                content,
+                is_error: false,
+                cache_control: None,
+            }
+        });
+    let image = (
+        prop_oneof![arb_base64_image().boxed(), arb_url(false).boxed()],
+        prop::option::of(prop_oneof![Just("low"), Just("high")]),
+    )
+        .prop_map(|(source, detail)| ContentBlock::Image {
+            source,
+            detail: detail.map(|d| d.to_string()),
+            cache_control: None,
+        });
+    (
+        prop::collection::vec(tool_result, 0..3),
+        prop::collection::vec(prop_oneof![arb_plain_text().boxed(), image.boxed()], 0..3),
+    )
+        // tool 消息总是排在同一轮的用户内容之前
+        .prop_map(|(mut results, parts)| {
+            results.extend(parts);
+            results
+        })
+        .prop_filter("用户消息不能为空", |content| !content.is_empty())
+}
+
+fn arb_openai_assistant() -> impl Strategy<Value = Vec<ContentBlock>> {
+    (
+        prop::option::of(arb_text()),
+        prop::collection::vec(arb_plain_text(), 0..3),
+        prop::collection::vec(arb_tool_use(false), 0..3),
+    )
+        .prop_map(|(reasoning, texts, tools)| {
+            let mut content: Vec<ContentBlock> = reasoning
+                .map(|thinking| ContentBlock::Thinking {
+                    thinking,
+                    signature: None,
+                })
+                .into_iter()
+                .collect();
+            content.extend(texts);
+            content.extend(tools);
+            content
+        })
+        .prop_filter("助手消息不能为空", |content| !content.is_empty())
+}
+
+fn arb_openai_request() -> impl Strategy<Value = CanonicalRequest> {
+    (
+        (
+            arb_ident(),
+            prop::collection::vec(arb_plain_text(), 0..3),
+            prop::collection::vec((arb_openai_user(), arb_openai_assistant()), 1..3),
+            prop::collection::vec(arb_tool(false), 0..3),
+            arb_tool_choice(),
+            prop::option::of(any::<bool>()),
+        ),
+        (
+            arb_sampling(),
+            any::<bool>(),
+            prop::option::of(prop_oneof![Just("none"), Just("low"), Just("high")]),
+            prop::option::of(arb_ident()),
+            prop::option::of(0u32..100),
+        ),
+    )
+        .prop_map(
+            |(
+                (model, system, turns, tools, tool_choice, parallel),
+                (sampling, stream, effort, user, seed),
+            )| {
+                let mut request = CanonicalRequest::new(model);
+                request.system = system;
+                request.messages = alternate(turns);
+                request.tools = tools;
+                request.tool_choice = tool_choice;
+                request.parallel_tool_calls = parallel;
+                apply_sampling(&mut request, sampling);
+                request.stream = stream;
+                request.thinking = effort.map(|effort| ThinkingConfig {
+                    enabled: effort != "none",
+                    budget_tokens: None,
+                    effort: (effort != "none").then(|| effort.to_string()),
+                });
+                request.user = user;
+                request.passthrough = seed.map(|seed| Passthrough {
+                    protocol: Protocol::OpenAI,
+                    fields: [("seed".to_string(), json!(seed))].into_iter().collect(),
+                });
+                request
+            },
+        )
+}
+
+fn arb_openai_response() -> impl Strategy<Value = CanonicalResponse> {
+    (
+        arb_ident(),
+        arb_ident(),
+        prop::option::of(arb_text()),
+        prop::option::of(arb_text()),
+        prop::collection::vec(arb_tool_use(false), 0..3),
+        any::<bool>(),
+        (0u32..10_000, 0u32..10_000, prop::option::of(1u32..1000)),
+    )
+        .prop_map(
+            |(id, model, reasoning, text, tools, truncated, (input, output, cached))| {
+                let mut content = Vec::new();
+                if let Some(thinking) = reasoning {
+                    content.push(ContentBlock::Thinking {
+                        thinking,
+                        signature: None,
+                    });
+                }
+                if let Some(text) = text {
+                    content.push(ContentBlock::text(text));
+                }
+                let stop_reason = if truncated {
+                    StopReason::MaxTokens
+                } else if tools.is_empty() {
+                    StopReason::EndTurn
+                } else {
+                    StopReason::ToolUse
+                };
+                content.extend(tools);
+                CanonicalResponse {
+                    id,
+                    model,
+                    content,
+                    stop_reason,
+                    stop_sequence: None,
+                    usage: CanonicalUsage {
+                        input_tokens: input,
+                        output_tokens: output,
+                        cache_read_input_tokens: cached,
+                        cache_creation_input_tokens: None,
+                        reasoning_tokens: None,
+                    },
+                }
+            },
+        )
+}
+
+// ============================================================================
+// Anthropic 可表达的子集
+// ============================================================================
+
+fn arb_anthropic_text() -> impl Strategy<Value = ContentBlock> {
+    (
+        arb_text(),
+        arb_cache_control(),
+        prop::collection::vec(arb_object(), 0..2),
+    )
+        .prop_map(|(text, cache_control, citations)| ContentBlock::Text {
+            text,
+            cache_control,
+            citations,
+        })
+}
+
+fn arb_anthropic_image() -> impl Strategy<Value = ContentBlock> {
+    (
+        prop_oneof![arb_base64_image().boxed(), arb_url(false).boxed()],
+        arb_cache_control(),
+    )
+        .prop_map(|(source, cache_control)| ContentBlock::Image {
+            source,
+            detail: None,
+            cache_control,
+        })
+}
+
+fn arb_anthropic_user() -> impl Strategy<Value = Vec<ContentBlock>> {
+    let tool_result = (
+        arb_ident(),
+        prop::collection::vec(
+            prop_oneof![arb_anthropic_text().boxed(), arb_anthropic_image().boxed()],
+            0..3,
+        ),
+        any::<bool>(),
+        arb_cache_control(),
+    )
+        .prop_map(
+            |(id, content, is_error, cache_control)| ContentBlock::ToolResult {
+                tool_use_id: format!("toolu_{}", id),
+                content,
+                is_error,
+                cache_control,
+            },
+        );
+    prop::collection::vec(
+        prop_oneof![
+            arb_anthropic_text().boxed(),
+            arb_anthropic_image().boxed(),
+            tool_result.boxed(),
+        ],
+        1..4,
+    )
+}
+
+fn arb_anthropic_assistant_block() -> impl Strategy<Value = ContentBlock> {
+    prop_oneof![
+        (arb_text(), arb_ident()).prop_map(|(thinking, signature)| ContentBlock::Thinking {
+            thinking,
+            signature: Some(signature),
+        }),
+        arb_ident().prop_map(|data| ContentBlock::RedactedThinking { data }),
+        arb_anthropic_text(),
+        arb_tool_use(false),
+    ]
+}
+
+fn arb_anthropic_request() -> impl Strategy<Value = CanonicalRequest> {
+    (
+        (
+            arb_ident(),
+            prop::collection::vec(arb_anthropic_text(), 0..3),
+            prop::collection::vec(
+                (
+                    arb_anthropic_user(),
+                    prop::collection::vec(arb_anthropic_assistant_block(), 1..4),
+                ),
+                1..3,
+            ),
+            prop::collection::vec(arb_tool(true), 0..3),
+            arb_tool_choice(),
+            prop::option::of(any::<bool>()),
+        ),
+        (
+            arb_sampling(),
+            1u32..100_000,
+            prop::option::of(1u32..100),
+            any::<bool>(),
+            prop::option::of(prop::option::of(1024u32..32_000)),
+            prop::option::of(arb_ident()),
+        ),
+    )
+        .prop_map(
+            |(
+                (model, system, turns, tools, tool_choice, parallel),
+                (sampling, max_tokens, top_k, stream, thinking, user),
+            )| {
+                let mut request = CanonicalRequest::new(model);
+                request.system = system;
+                request.messages = alternate(turns);
+                request.tools = tools;
+                // disable_parallel_tool_use 只能随 tool_choice 出现
+                request.parallel_tool_calls = parallel.filter(|_| tool_choice.is_some());
+                request.tool_choice = tool_choice;
+                apply_sampling(&mut request, sampling);
+                request.max_tokens = Some(max_tokens);
+                request.top_k = top_k;
+                request.stream = stream;
+                request.thinking = thinking.map(|budget| ThinkingConfig {
+                    enabled: budget.is_some(),
+                    budget_tokens: budget,
+                    effort: None,
+                });
+                request.user = user;
+                request
+            },
+        )
+}
+
+fn arb_anthropic_response() -> impl Strategy<Value = CanonicalResponse> {
+    (
+        arb_ident(),
+        arb_ident(),
+        prop::collection::vec(arb_anthropic_assistant_block(), 0..4),
+        prop_oneof![
+            Just(StopReason::EndTurn),
+            Just(StopReason::MaxTokens),
+            Just(StopReason::ToolUse),
+            Just(StopReason::StopSequence),
+            Just(StopReason::Other("refusal".to_string())),
+        ],
+        prop::option::of(arb_ident()),
+        (
+            0u32..10_000,
+            0u32..10_000,
+            prop::option::of(1u32..1000),
+            prop::option::of(1u32..1000),
+        ),
+    )
+        .prop_map(
+            |(id, model, content, stop_reason, stop_sequence, (input, output, read, creation))| {
+                // 响应中的文本块不带缓存标记
+                let content = content
+                    .into_iter()
+                    .map(|block| match block {
+                        ContentBlock::Text {
+                            text, citations, ..
+                        } => ContentBlock::Text {
+                            text,
+                            cache_control: None,
+                            citations,
+                        },
+                        other => other,
+                    })
+                    .collect();
+                CanonicalResponse {
+                    id,
+                    model,
+                    content,
+                    stop_reason,
+                    stop_sequence,
+                    usage: CanonicalUsage {
+                        input_tokens: input,
+                        output_tokens: output,
+                        cache_read_input_tokens: read,
+                        cache_creation_input_tokens: creation,
+                        reasoning_tokens: None,
+                    },
+                }
+            },
+        )
+}
+
+// ============================================================================
+// Gemini 可表达的子集
+// ============================================================================
+
+fn arb_gemini_user() -> impl Strategy<Value = Vec<ContentBlock>> {
+    let image = prop_oneof![arb_base64_image().boxed(), arb_url(true).boxed()].prop_map(|source| {
+        ContentBlock::Image {
+            source,
+            detail: None,
+            cache_control: None,
+        }
+    });
+    let tool_result = (
+        arb_ident(),
+        prop::option::of(arb_plain_text()),
+        any::<bool>(),
+    )
+        .prop_map(|(id, text, is_error)| ContentBlock::ToolResult {
+            tool_use_id: format!("call_{}", id),
+            content: text.into_iter().collect(),
+            is_error,
+            cache_control: None,
+        });
+    prop::collection::vec(
+        prop_oneof![arb_plain_text().boxed(), image.boxed(), tool_result.boxed()],
+        1..4,
+    )
+}
+
+fn arb_gemini_assistant_block() -> impl Strategy<Value = ContentBlock> {
+    prop_oneof![
+        (arb_text(), prop::option::of(arb_ident())).prop_map(|(thinking, signature)| {
+            ContentBlock::Thinking {
+                thinking,
+                signature,
+            }
+        }),
+        arb_plain_text(),
+        arb_tool_use(true),
+    ]
+}
+
+fn arb_gemini_request() -> impl Strategy<Value = CanonicalRequest> {
+    (
+        (
+            arb_ident(),
+            prop::collection::vec(arb_plain_text(), 0..3),
+            prop::collection::vec(
+                (
+                    arb_gemini_user(),
+                    prop::collection::vec(arb_gemini_assistant_block(), 1..4),
+                ),
+                1..3,
+            ),
+            prop::collection::vec(arb_tool(false), 0..3),
+            arb_tool_choice(),
+        ),
+        (
+            arb_sampling(),
+            prop::option::of(1u32..100),
+            prop::option::of(prop::option::of(prop::option::of(1u32..32_000))),
+            any::<bool>(),
+        ),
+    )
+        .prop_map(
+            |((model, system, turns, tools, tool_choice), (sampling, top_k, thinking, safety))| {
+                // 不使用 gemini-3 前缀，保证按 thinkingBudget 编码
+                let mut request = CanonicalRequest::new(format!("gemini-2.5-{}", model));
+                request.system = system;
+                request.messages = alternate(turns);
+                request.tools = tools;
+                request.tool_choice = tool_choice;
+                apply_sampling(&mut request, sampling);
+                request.top_k = top_k;
+                // None: 未配置；Some(None): 关闭；Some(Some(None)): 动态预算
+                request.thinking = thinking.map(|thinking| ThinkingConfig {
+                    enabled: thinking.is_some(),
+                    budget_tokens: thinking.flatten(),
+                    effort: None,
+                });
+                if safety {
+                    request.passthrough = Some(Passthrough {
+                        protocol: Protocol::Gemini,
+                        fields: [(
+                            "safetySettings".to_string(),
+                            json!([{"category": "HARM_CATEGORY_HARASSMENT", "threshold": "OFF"}]),
+                        )]
+                        .into_iter()
+                        .collect(),
+                    });
+                }
+                request
+            },
+        )
+}
+
+fn gemini_stop_reason(content: &[ContentBlock], variant: u8) -> StopReason {
+    match variant % 3 {
+        0 if content
+            .iter()
+            .any(|b| matches!(b, ContentBlock::ToolUse { .. })) =>
+        {
+            StopReason::ToolUse
+        }
+        0 => StopReason::EndTurn,
+        1 => StopReason::MaxTokens,
+        _ => StopReason::Other("SAFETY".to_string()),
+    }
+}
+
+fn arb_gemini_response() -> impl Strategy<Value = CanonicalResponse> {
+    (
+        arb_ident(),
+        arb_ident(),
+        prop::collection::vec(arb_gemini_assistant_block(), 0..4),
+        any::<u8>(),
+        (
+            0u32..10_000,
+            0u32..10_000,
+            prop::option::of(0u32..1000),
+            prop::option::of(1u32..1000),
+        ),
+    )
+        .prop_map(
+            |(id, model, content, variant, (input, candidates, thoughts, cached))| {
+                CanonicalResponse {
+                    id,
+                    model,
+                    stop_reason: gemini_stop_reason(&content, variant),
+                    content,
+                    stop_sequence: None,
+                    usage: CanonicalUsage {
+                        input_tokens: input,
+                        output_tokens: candidates + thoughts.unwrap_or(0),
+                        cache_read_input_tokens: cached,
+                        cache_creation_input_tokens: None,
+                        reasoning_tokens: thoughts,
+                    },
+                }
+            },
+        )
+}
+
+// ============================================================================
+// 流式往返
+// ============================================================================
+
+fn stream_via_anthropic(response: &CanonicalResponse) -> CanonicalResponse {
+    let mut generator = AnthropicSseGenerator::new(response.model.clone());
+    let sse: String = response_to_events(response)
+        .iter()
+        .flat_map(|event| generator.generate(event))
+        .collect();
+    let mut parser = AnthropicSseParser::new();
+    let mut accumulator = ResponseAccumulator::new();
+    accumulator.extend(&parser.process(sse.as_bytes()));
+    accumulator.extend(&parser.finish());
+    accumulator.finish().unwrap()
+}
+
+fn stream_via_openai(response: &CanonicalResponse) -> CanonicalResponse {
+    let mut generator = OpenAiSseGenerator::new(response.model.clone());
+    let mut sse: String = response_to_events(response)
+        .iter()
+        .filter_map(|event| generator.generate(event))
+        .collect();
+    sse.push_str(&generator.generate_done());
+    let mut parser = OpenAiSseParser::new();
+    let mut accumulator = ResponseAccumulator::new();
+    accumulator.extend(&parser.process(sse.as_bytes()));
+    accumulator.extend(&parser.finish());
+    accumulator.finish().unwrap()
+}
+
+fn stream_via_gemini(response: &CanonicalResponse) -> CanonicalResponse {
+    let mut generator = GeminiSseGenerator::new(response.model.clone());
+    let sse: String = response_to_events(response)
+        .iter()
+        .filter_map(|event| generator.generate(event))
+        .collect();
+    let mut parser = GeminiSseParser::new();
+    let mut accumulator = ResponseAccumulator::new();
+    accumulator.extend(&parser.process(sse.as_bytes()));
+    accumulator.extend(&parser.finish());
+    accumulator.finish().unwrap()
+}
+
+/// Gemini 流中相邻的同类文本/思维链 part 会合并为一个块
+fn merge_adjacent_gemini_parts(response: &mut CanonicalResponse) {
+    let mut merged: Vec<ContentBlock> = Vec::new();
+    for block in response.content.drain(..) {
+        match (merged.last_mut(), block) {
+            (Some(ContentBlock::Text { text: last, .. }), ContentBlock::Text { text, .. }) => {
+                last.push_str(&text)
+            }
+            (
+                Some(ContentBlock::Thinking {
+                    thinking: last,
+                    signature: last_signature,
+                }),
+                ContentBlock::Thinking {
+                    thinking,
+                    signature,
+                },
+            ) => {
+                last.push_str(&thinking);
+                if let Some(signature) = signature {
+                    *last_signature =
+                        Some(last_signature.take().unwrap_or_default() + signature.as_str());
+                }
+            }
+            (_, block) => merged.push(block),
+        }
+    }
+    response.content = merged;
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(100))]
+
+    #[test]
+    fn prop_openai_request_roundtrip(request in arb_openai_request()) {
+        let decoded = decode_request(Protocol::OpenAI, &encode_request(Protocol::OpenAI, &request).unwrap()).unwrap();
+        prop_assert_eq!(decoded, request);
+    }
+
+    #[test]
+    fn prop_anthropic_request_roundtrip(request in arb_anthropic_request()) {
+        let decoded = decode_request(Protocol::Anthropic, &encode_request(Protocol::Anthropic, &request).unwrap()).unwrap();
+        prop_assert_eq!(decoded, request);
+    }
+
+    #[test]
+    fn prop_gemini_request_roundtrip(request in arb_gemini_request()) {
+        let mut decoded = decode_request(Protocol::Gemini, &encode_request(Protocol::Gemini, &request).unwrap()).unwrap();
+        // Gemini 请求体不包含模型名
+        decoded.model = request.model.clone();
+        prop_assert_eq!(decoded, request);
+    }
+
+    #[test]
+    fn prop_antigravity_request_roundtrip(request in arb_gemini_request()) {
+        let decoded = decode_request(Protocol::Antigravity, &encode_request(Protocol::Antigravity, &request).unwrap()).unwrap();
+        prop_assert_eq!(&decoded.messages, &request.messages);
+        prop_assert_eq!(&decoded.model, &request.model);
+        prop_assert_eq!(
+            decoded.passthrough.map(|p| p.fields),
+            request.passthrough.map(|p| p.fields)
+        );
+    }
+
+    #[test]
+    fn prop_openai_response_roundtrip(response in arb_openai_response()) {
+        let decoded = decode_response(Protocol::OpenAI, &encode_response(Protocol::OpenAI, &response).unwrap()).unwrap();
+        prop_assert_eq!(decoded, response);
+    }
+
+    #[test]
+    fn prop_anthropic_response_roundtrip(response in arb_anthropic_response()) {
+        let decoded = decode_response(Protocol::Anthropic, &encode_response(Protocol::Anthropic, &response).unwrap()).unwrap();
+        prop_assert_eq!(decoded, response);
+    }
+
+    #[test]
+    fn prop_gemini_response_roundtrip(response in arb_gemini_response()) {
+        let decoded = decode_response(Protocol::Gemini, &encode_response(Protocol::Gemini, &response).unwrap()).unwrap();
+        prop_assert_eq!(&decoded, &response);
+        let decoded = decode_response(Protocol::Antigravity, &encode_response(Protocol::Antigravity, &response).unwrap()).unwrap();
+        prop_assert_eq!(decoded, response);
+    }
+
+    #[test]
+    fn prop_anthropic_stream_roundtrip(response in arb_anthropic_response()) {
+        let mut expected = response.clone();
+        // 流式响应不携带 stop_sequence
+        expected.stop_sequence = None;
+        prop_assert_eq!(stream_via_anthropic(&response), expected);
+    }
+
+    #[test]
+    fn prop_openai_stream_roundtrip(response in arb_openai_response()) {
+        prop_assert_eq!(stream_via_openai(&response), response);
+    }
+
+    #[test]
+    fn prop_gemini_stream_roundtrip(response in arb_gemini_response()) {
+        let mut expected = response.clone();
+        // Usage 事件不区分思维链 token
+        expected.usage.reasoning_tokens = None;
+        merge_adjacent_gemini_parts(&mut expected);
+        prop_assert_eq!(stream_via_gemini(&response), expected);
+    }
+
+    /// Gemini 请求转为 Anthropic 后，带签名的思维链原样保留
+    #[test]
+    fn prop_thinking_signature_survives_gemini(request in arb_gemini_request()) {
+        let anthropic = encode_request(Protocol::Anthropic, &request).unwrap();
+        let via_anthropic = decode_request(Protocol::Anthropic, &anthropic).unwrap();
+        let signatures = |r: &CanonicalRequest| -> Vec<Option<String>> {
+            r.messages
+                .iter()
+                .flat_map(|m| &m.content)
+                .filter_map(|b| match b {
+                    ContentBlock::Thinking { signature, .. } => Some(signature.clone()),
+                    _ => None,
+                })
+                .collect()
+        };
+        // Anthropic 只保留有签名的思维链
+        let expected: Vec<Option<String>> = signatures(&request).into_iter().filter(|s| s.is_some()).collect();
+        prop_assert_eq!(signatures(&via_anthropic), expected);
+    }
+}
+
+#[test]
+fn test_convert_request_keeps_cache_control_between_anthropic_endpoints() {
+    let body = json!({
+        "model": "claude-sonnet-4-5",
+        "max_tokens": 1024,
+        "system": [{"type": "text", "text": "long prompt", "cache_control": {"type": "ephemeral"}}],
+        "messages": [{"role": "user", "content": [
+            {"type": "text", "text": "hi", "citations": [{"type": "char_location", "start_char_index": 0}]}
+        ]}]
+    });
+    let converted = convert_request(Protocol::Anthropic, Protocol::Anthropic, &body).unwrap();
+    assert_eq!(converted["system"][0]["cache_control"]["type"], "ephemeral");
+    assert_eq!(
+        converted["messages"][0]["content"][0]["citations"][0]["type"],
+        "char_location"
+    );
+}
+
+#[test]
+fn test_convert_response_anthropic_thinking_to_openai_reasoning() {
+    let body = json!({
+        "id": "msg_1",
+        "type": "message",
+        "role": "assistant",
+        "model": "claude-sonnet-4-5",
+        "content": [
+            {"type": "thinking", "thinking": "let me think", "signature": "sig"},
+            {"type": "text", "text": "42"}
+        ],
+        "stop_reason": "end_turn",
+        "usage": {"input_tokens": 10, "output_tokens": 5, "cache_read_input_tokens": 90}
+    });
+    let converted = convert_response(Protocol::Anthropic, Protocol::OpenAI, &body).unwrap();
+    assert_eq!(
+        converted["choices"][0]["message"]["reasoning_content"],
+        "let me think"
+    );
+    assert_eq!(converted["choices"][0]["message"]["content"], "42");
+    assert_eq!(converted["usage"]["prompt_tokens"], 100);
+    assert_eq!(
+        converted["usage"]["prompt_tokens_details"]["cached_tokens"],
+        90
+    );
+}
+
+#[test]
+fn test_codewhisperer_is_backend_only() {
+    let body = json!({"conversationState": {}});
+    assert!(decode_request(Protocol::CodeWhisperer, &body).is_err());
+
+    let mut request = CanonicalRequest::new("claude-sonnet-4-5");
+    request.push_message(Role::User, vec![ContentBlock::text("hi")]);
+    assert!(encode_request(Protocol::CodeWhisperer, &request).is_err());
+}
+
+#[test]
+fn test_anthropic_to_chat_completion_keeps_tool_results_and_images() {
+    let request: crate::models::anthropic::AnthropicMessagesRequest =
+        serde_json::from_value(json!({
+            "model": "claude-sonnet-4-5",
+            "max_tokens": 1024,
+            "system": "be brief",
+            "messages": [
+                {"role": "user", "content": [
+                    {"type": "text", "text": "what is this?"},
+                    {"type": "image", "source": {"type": "base64", "media_type": "image/png", "data": "AAAA"}}
+                ]},
+                {"role": "assistant", "content": [
+                    {"type": "tool_use", "id": "toolu_1", "name": "lookup", "input": {"q": "png"}}
+                ]},
+                {"role": "user", "content": [
+                    {"type": "tool_result", "tool_use_id": "toolu_1", "content": [
+                        {"type": "text", "text": "a picture"}
+                    ]}
+                ]}
+            ]
+        }))
+        .unwrap();
+
+    let converted = anthropic_to_chat_completion(&request).unwrap();
+    let roles: Vec<&str> = converted.messages.iter().map(|m| m.role.as_str()).collect();
+    assert_eq!(roles, ["system", "user", "assistant", "tool"]);
+    assert!(matches!(
+        &converted.messages[1].content,
+        Some(crate::models::openai::MessageContent::Parts(parts)) if parts.len() == 2
+    ));
+    assert_eq!(
+        converted.messages[2].tool_calls.as_ref().unwrap()[0]
+            .function
+            .name,
+        "lookup"
+    );
+    assert_eq!(
+        converted.messages[3].tool_call_id.as_deref(),
+        Some("toolu_1")
+    );
+    assert_eq!(converted.messages[3].get_content_text(), "a picture");
+}
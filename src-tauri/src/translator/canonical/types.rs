@@ -0,0 +1,359 @@
+//! 规范请求/响应模型
+//!
+//! 覆盖各协议能力并集的中间表示。解码器只负责把协议字段映射到这里，
+//! 编码器按目标协议的能力输出，目标协议不支持的内容（如 OpenAI 的 thinking 签名）
+//! 只在编码时丢弃，不会在中间表示中丢失。
+
+use serde_json::{Map, Value};
+
+use crate::converter::protocol_selector::Protocol;
+use crate::stream::StopReason;
+
+/// 消息角色
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    User,
+    Assistant,
+}
+
+impl Role {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::User => "user",
+            Role::Assistant => "assistant",
+        }
+    }
+}
+
+/// 图片来源
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ImageSource {
+    /// base64 内联数据
+    Base64 { media_type: String, data: String },
+    /// 远程 URL（Gemini 的 fileData.fileUri 也映射到这里）
+    Url {
+        url: String,
+        /// MIME 类型（Gemini fileData 需要）
+        media_type: Option<String>,
+    },
+}
+
+/// 内容块
+#[derive(Debug, Clone, PartialEq)]
+pub enum ContentBlock {
+    /// 文本
+    Text {
+        text: String,
+        /// Anthropic 缓存标记，如 `{"type":"ephemeral"}`
+        cache_control: Option<Value>,
+        /// Anthropic 引用，保持原始 JSON 结构
+        citations: Vec<Value>,
+    },
+    /// 图片
+    Image {
+        source: ImageSource,
+        /// OpenAI image_url.detail
+        detail: Option<String>,
+        cache_control: Option<Value>,
+    },
+    /// 思维链
+    Thinking {
+        thinking: String,
+        /// Anthropic 签名 / Gemini thoughtSignature
+        signature: Option<String>,
+    },
+    /// 已加密的思维链
+    RedactedThinking { data: String },
+    /// 工具调用
+    ToolUse {
+        id: String,
+        name: String,
+        input: Value,
+        /// Gemini functionCall 的 thoughtSignature
+        signature: Option<String>,
+        cache_control: Option<Value>,
+    },
+    /// 工具结果，内容可以包含多个文本和图片块
+    ToolResult {
+        tool_use_id: String,
+        content: Vec<ContentBlock>,
+        is_error: bool,
+        cache_control: Option<Value>,
+    },
+}
+
+impl ContentBlock {
+    /// 创建纯文本块
+    pub fn text(text: impl Into<String>) -> Self {
+        ContentBlock::Text {
+            text: text.into(),
+            cache_control: None,
+            citations: Vec::new(),
+        }
+    }
+
+    /// 文本内容（非文本块返回 None）
+    pub fn as_text(&self) -> Option<&str> {
+        match self {
+            ContentBlock::Text { text, .. } => Some(text),
+            _ => None,
+        }
+    }
+}
+
+/// 消息
+#[derive(Debug, Clone, PartialEq)]
+pub struct CanonicalMessage {
+    pub role: Role,
+    pub content: Vec<ContentBlock>,
+}
+
+/// 工具定义
+#[derive(Debug, Clone, PartialEq)]
+pub struct ToolDefinition {
+    pub name: String,
+    pub description: Option<String>,
+    /// JSON Schema
+    pub input_schema: Value,
+    pub cache_control: Option<Value>,
+}
+
+/// 工具选择
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ToolChoice {
+    /// 模型自行决定
+    Auto,
+    /// 禁止调用工具
+    None,
+    /// 必须调用某个工具
+    Any,
+    /// 必须调用指定工具
+    Tool { name: String },
+}
+
+/// 思维链配置
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ThinkingConfig {
+    /// 是否启用
+    pub enabled: bool,
+    /// token 预算（Anthropic budget_tokens / Gemini thinkingBudget）
+    pub budget_tokens: Option<u32>,
+    /// 强度（OpenAI reasoning_effort / Gemini thinkingLevel）
+    pub effort: Option<String>,
+}
+
+impl ThinkingConfig {
+    /// 取 token 预算，未指定时按强度估算
+    pub fn budget_or_default(&self) -> u32 {
+        let estimated = match self.effort.as_deref() {
+            Some("minimal") | Some("low") => 1024,
+            Some("high") => 24576,
+            _ => 8192,
+        };
+        self.budget_tokens.unwrap_or(estimated)
+    }
+
+    /// 取强度，未指定时按 token 预算估算
+    pub fn effort_or_default(&self) -> String {
+        if let Some(effort) = &self.effort {
+            return effort.clone();
+        }
+        match self.budget_tokens {
+            Some(budget) if budget <= 2048 => "low".to_string(),
+            Some(budget) if budget > 16384 => "high".to_string(),
+            _ => "medium".to_string(),
+        }
+    }
+}
+
+/// 协议特有、无法映射的字段
+///
+/// 只在编码为同一协议时原样输出，例如 Gemini 的 safetySettings、OpenAI 的 logprobs。
+#[derive(Debug, Clone, PartialEq)]
+pub struct Passthrough {
+    pub protocol: Protocol,
+    pub fields: Map<String, Value>,
+}
+
+/// 规范请求
+#[derive(Debug, Clone, PartialEq)]
+pub struct CanonicalRequest {
+    pub model: String,
+    /// 系统提示，每个块可以带缓存标记
+    pub system: Vec<ContentBlock>,
+    pub messages: Vec<CanonicalMessage>,
+    pub tools: Vec<ToolDefinition>,
+    pub tool_choice: Option<ToolChoice>,
+    /// 是否允许并行工具调用
+    pub parallel_tool_calls: Option<bool>,
+    pub max_tokens: Option<u32>,
+    pub temperature: Option<f64>,
+    pub top_p: Option<f64>,
+    pub top_k: Option<u32>,
+    pub stop_sequences: Vec<String>,
+    pub stream: bool,
+    pub thinking: Option<ThinkingConfig>,
+    /// 终端用户标识（Anthropic metadata.user_id / OpenAI user）
+    pub user: Option<String>,
+    pub passthrough: Option<Passthrough>,
+}
+
+impl CanonicalRequest {
+    /// 创建空请求
+    pub fn new(model: impl Into<String>) -> Self {
+        Self {
+            model: model.into(),
+            system: Vec::new(),
+            messages: Vec::new(),
+            tools: Vec::new(),
+            tool_choice: None,
+            parallel_tool_calls: None,
+            max_tokens: None,
+            temperature: None,
+            top_p: None,
+            top_k: None,
+            stop_sequences: Vec::new(),
+            stream: false,
+            thinking: None,
+            user: None,
+            passthrough: None,
+        }
+    }
+
+    /// 系统提示文本
+    pub fn system_text(&self) -> String {
+        self.system
+            .iter()
+            .filter_map(|b| b.as_text())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// 目标协议与来源相同时返回透传字段（Antigravity 与 Gemini 视为同一协议）
+    pub fn passthrough_for(&self, protocol: Protocol) -> Option<&Map<String, Value>> {
+        self.passthrough
+            .as_ref()
+            .filter(|p| protocol_family(p.protocol) == protocol_family(protocol))
+            .map(|p| &p.fields)
+    }
+
+    /// 追加消息，与上一条同角色时合并内容
+    pub fn push_message(&mut self, role: Role, content: Vec<ContentBlock>) {
+        push_merged(&mut self.messages, role, content);
+    }
+}
+
+/// 追加消息，与上一条同角色时合并内容
+pub(crate) fn push_merged(
+    messages: &mut Vec<CanonicalMessage>,
+    role: Role,
+    content: Vec<ContentBlock>,
+) {
+    if content.is_empty() {
+        return;
+    }
+    match messages.last_mut() {
+        Some(last) if last.role == role => last.content.extend(content),
+        _ => messages.push(CanonicalMessage { role, content }),
+    }
+}
+
+/// 使用量
+///
+/// `input_tokens` 不包含缓存命中和缓存写入的 token（Anthropic 语义），
+/// `output_tokens` 包含思维链 token（OpenAI 语义）。
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CanonicalUsage {
+    pub input_tokens: u32,
+    pub output_tokens: u32,
+    pub cache_read_input_tokens: Option<u32>,
+    pub cache_creation_input_tokens: Option<u32>,
+    /// 思维链 token（包含在 output_tokens 中）
+    pub reasoning_tokens: Option<u32>,
+}
+
+impl CanonicalUsage {
+    /// 包含缓存的输入 token 总数（OpenAI prompt_tokens / Gemini promptTokenCount）
+    pub fn prompt_tokens(&self) -> u32 {
+        self.input_tokens
+            + self.cache_read_input_tokens.unwrap_or(0)
+            + self.cache_creation_input_tokens.unwrap_or(0)
+    }
+}
+
+/// 规范响应
+#[derive(Debug, Clone, PartialEq)]
+pub struct CanonicalResponse {
+    pub id: String,
+    pub model: String,
+    /// Text / Thinking / RedactedThinking / ToolUse 块
+    pub content: Vec<ContentBlock>,
+    pub stop_reason: StopReason,
+    /// 命中的停止序列（Anthropic）
+    pub stop_sequence: Option<String>,
+    pub usage: CanonicalUsage,
+}
+
+impl CanonicalResponse {
+    /// 拼接的文本内容
+    pub fn text(&self) -> String {
+        self.content
+            .iter()
+            .filter_map(|b| b.as_text())
+            .collect::<Vec<_>>()
+            .join("")
+    }
+
+    /// 是否包含工具调用
+    pub fn has_tool_use(&self) -> bool {
+        self.content
+            .iter()
+            .any(|b| matches!(b, ContentBlock::ToolUse { .. }))
+    }
+}
+
+/// Antigravity 请求体是包装后的 Gemini 请求
+fn protocol_family(protocol: Protocol) -> Protocol {
+    match protocol {
+        Protocol::Antigravity => Protocol::Gemini,
+        other => other,
+    }
+}
+
+pub(crate) fn str_field(value: &Value, key: &str) -> Option<String> {
+    value
+        .get(key)
+        .and_then(|v| v.as_str())
+        .map(|s| s.to_string())
+}
+
+pub(crate) fn u32_field(value: &Value, key: &str) -> Option<u32> {
+    value.get(key).and_then(|v| v.as_u64()).map(|v| v as u32)
+}
+
+/// 收集未被映射的字段
+pub(crate) fn collect_passthrough(
+    protocol: Protocol,
+    body: &Value,
+    known: &[&str],
+) -> Option<Passthrough> {
+    let fields: Map<String, Value> = body
+        .as_object()?
+        .iter()
+        .filter(|(key, _)| !known.contains(&key.as_str()))
+        .map(|(key, value)| (key.clone(), value.clone()))
+        .collect();
+    if fields.is_empty() {
+        None
+    } else {
+        Some(Passthrough { protocol, fields })
+    }
+}
+
+/// 解析 `data:` URL，返回 (media_type, data)
+pub(crate) fn parse_data_url(url: &str) -> Option<(String, String)> {
+    let rest = url.strip_prefix("data:")?;
+    let (meta, data) = rest.split_once(',')?;
+    let media_type = meta.strip_suffix(";base64")?;
+    Some((media_type.to_string(), data.to_string()))
+}
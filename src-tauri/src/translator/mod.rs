@@ -8,6 +8,17 @@
 //! ```text
 //! translator/
 //! ├── traits.rs              # 转换器 trait 定义
+//! ├── canonical/              # 规范中间模型（所有协议互转）
+//! │   ├── types.rs            # CanonicalRequest / CanonicalResponse
+//! │   ├── openai.rs           # OpenAI 编解码
+//! │   ├── anthropic.rs        # Anthropic 编解码
+//! │   ├── gemini.rs           # Gemini / Antigravity 编解码
+//! │   ├── codewhisperer.rs    # CodeWhisperer 请求编码
+//! │   └── stream.rs           # StreamEvent ↔ CanonicalResponse
+//! ├── tool_emulation/         # 工具调用模拟（不支持原生函数调用的后端）
+//! │   ├── prompt.rs           # 工具定义 / 调用 / 结果 → 文本
+//! │   ├── parser.rs           # 文本 → 工具调用
+//! │   └── stream.rs           # 流式响应中的工具调用解析
 //! └── kiro/                   # Kiro/CodeWhisperer 后端
 //!     ├── openai/             # OpenAI 前端协议
 //!     │   ├── request.rs      # OpenAI → Kiro 请求
@@ -39,7 +50,9 @@
 //! }
 //! ```
 
+pub mod canonical;
 pub mod kiro;
+pub mod tool_emulation;
 pub mod traits;
 
 // 重新导出核心类型
@@ -0,0 +1,337 @@
+//! 工具调用模拟
+//!
+//! 用于不支持原生函数调用的后端（或后端上的部分模型）：
+//!
+//! - 请求：工具定义和 `tool_choice` 渲染进系统提示，历史中的工具调用改写为
+//!   模型输出格式的文本，工具结果改写为 `<tool_result>` 文本
+//! - 响应：从完整响应或流式文本中解析工具调用，还原为 `ToolUse` 内容块，
+//!   再由各协议编码器输出 OpenAI `tool_calls` / Anthropic `tool_use`
+//!
+//! 提示中的调用格式由 `PromptFormat` 决定，响应中两种格式（以及 Kiro 的
+//! `[Called xxx with args: {...}]`）都会被识别。
+//!
+//! ```text
+//! tool_emulation/
+//! ├── prompt.rs   # 工具定义 / 调用 / 结果 → 文本
+//! ├── parser.rs   # 文本 → 工具调用
+//! └── stream.rs   # 流式响应中的工具调用解析
+//! ```
+
+pub mod parser;
+pub mod prompt;
+pub mod stream;
+
+pub use parser::{
+    extract_bracket_tool_calls, extract_tool_calls, split_tool_calls, ParsedToolCall, Segment,
+};
+pub use stream::ToolCallStreamFilter;
+
+use std::collections::HashMap;
+
+use crate::agent::tools::prompt::PromptFormat;
+use crate::stream::StopReason;
+use crate::translator::canonical::{
+    CanonicalMessage, CanonicalRequest, CanonicalResponse, ContentBlock, ToolDefinition,
+};
+
+/// 提示模型在输出工具调用后停止，避免模型自己续写工具结果
+const TOOL_RESULT_STOP: &str = "<tool_result";
+
+/// 多数后端最多支持 4 个停止序列
+const MAX_STOP_SEQUENCES: usize = 4;
+
+/// 单个请求的工具调用模拟状态
+#[derive(Debug, Clone)]
+pub struct ToolEmulation {
+    tools: Vec<ToolDefinition>,
+}
+
+impl ToolEmulation {
+    /// 改写请求，移除原生工具字段
+    ///
+    /// 请求声明了工具时返回模拟状态，用于解析响应；只有历史中包含工具调用时
+    /// 仍会改写历史，但返回 None（本轮不会产生新的工具调用）。
+    pub fn prepare(request: &mut CanonicalRequest, format: PromptFormat) -> Option<Self> {
+        let tools = std::mem::take(&mut request.tools);
+        let tool_choice = request.tool_choice.take();
+        let parallel_tool_calls = request.parallel_tool_calls.take();
+
+        rewrite_history(&mut request.messages, format);
+        if tools.is_empty() {
+            return None;
+        }
+
+        let prompt =
+            prompt::render_tools_prompt(&tools, tool_choice.as_ref(), parallel_tool_calls, format);
+        match request.system.last_mut() {
+            Some(ContentBlock::Text {
+                text,
+                cache_control: None,
+                ..
+            }) => {
+                text.push_str("\n\n");
+                text.push_str(&prompt);
+            }
+            _ => request.system.push(ContentBlock::text(prompt)),
+        }
+
+        if request.stop_sequences.len() < MAX_STOP_SEQUENCES
+            && !request.stop_sequences.iter().any(|s| s == TOOL_RESULT_STOP)
+        {
+            request.stop_sequences.push(TOOL_RESULT_STOP.to_string());
+        }
+
+        Some(Self { tools })
+    }
+
+    /// 请求声明的工具
+    pub fn tools(&self) -> &[ToolDefinition] {
+        &self.tools
+    }
+
+    /// 将完整响应中的工具调用文本还原为 `ToolUse` 块，返回是否解析到调用
+    pub fn apply_to_response(&self, response: &mut CanonicalResponse) -> bool {
+        let mut found = false;
+        let mut content = Vec::with_capacity(response.content.len());
+        for block in std::mem::take(&mut response.content) {
+            let ContentBlock::Text { text, .. } = &block else {
+                content.push(block);
+                continue;
+            };
+            let segments = split_tool_calls(text, &self.tools);
+            if !segments.iter().any(|s| matches!(s, Segment::Call(_))) {
+                content.push(block);
+                continue;
+            }
+            found = true;
+            for segment in segments {
+                match segment {
+                    Segment::Text(text) if text.trim().is_empty() => {}
+                    Segment::Text(text) => content.push(ContentBlock::text(text.trim())),
+                    Segment::Call(call) => content.push(ContentBlock::ToolUse {
+                        id: new_tool_use_id(),
+                        name: call.name,
+                        input: call.arguments,
+                        signature: None,
+                        cache_control: None,
+                    }),
+                }
+            }
+        }
+        response.content = content;
+
+        if found {
+            if matches!(
+                response.stop_reason,
+                StopReason::EndTurn | StopReason::StopSequence
+            ) {
+                response.stop_reason = StopReason::ToolUse;
+            }
+            if response.stop_sequence.as_deref() == Some(TOOL_RESULT_STOP) {
+                response.stop_sequence = None;
+            }
+        }
+        found
+    }
+
+    /// 创建流式响应过滤器
+    pub fn stream_filter(&self) -> ToolCallStreamFilter {
+        ToolCallStreamFilter::new(self.tools.clone())
+    }
+}
+
+/// 生成工具调用 ID
+pub(crate) fn new_tool_use_id() -> String {
+    format!("call_{}", uuid::Uuid::new_v4().simple())
+}
+
+/// 将历史中的工具调用和工具结果改写为文本，相邻文本块合并
+fn rewrite_history(messages: &mut [CanonicalMessage], format: PromptFormat) {
+    let names: HashMap<String, String> = messages
+        .iter()
+        .flat_map(|m| &m.content)
+        .filter_map(|block| match block {
+            ContentBlock::ToolUse { id, name, .. } => Some((id.clone(), name.clone())),
+            _ => None,
+        })
+        .collect();
+
+    for message in messages.iter_mut() {
+        let has_tool_blocks = message.content.iter().any(|b| {
+            matches!(
+                b,
+                ContentBlock::ToolUse { .. } | ContentBlock::ToolResult { .. }
+            )
+        });
+        if !has_tool_blocks {
+            continue;
+        }
+
+        let mut content: Vec<ContentBlock> = Vec::new();
+        for block in std::mem::take(&mut message.content) {
+            match block {
+                ContentBlock::ToolUse { name, input, .. } => {
+                    push_text(
+                        &mut content,
+                        prompt::render_tool_call(&name, &input, format),
+                    );
+                }
+                ContentBlock::ToolResult {
+                    tool_use_id,
+                    content: result,
+                    is_error,
+                    ..
+                } => {
+                    let text: Vec<&str> = result.iter().filter_map(|b| b.as_text()).collect();
+                    push_text(
+                        &mut content,
+                        prompt::render_tool_result(
+                            &tool_use_id,
+                            names.get(&tool_use_id).map(String::as_str),
+                            &text.join("\n"),
+                            is_error,
+                        ),
+                    );
+                    // 工具结果中的图片保留为独立内容块
+                    content.extend(
+                        result
+                            .into_iter()
+                            .filter(|b| matches!(b, ContentBlock::Image { .. })),
+                    );
+                }
+                ContentBlock::Text {
+                    text,
+                    cache_control: None,
+                    citations,
+                } if citations.is_empty() => push_text(&mut content, text),
+                other => content.push(other),
+            }
+        }
+        message.content = content;
+    }
+}
+
+fn push_text(content: &mut Vec<ContentBlock>, text: String) {
+    if let Some(ContentBlock::Text {
+        text: last,
+        cache_control: None,
+        citations,
+    }) = content.last_mut()
+    {
+        if citations.is_empty() {
+            if !last.is_empty() && !text.is_empty() {
+                last.push_str("\n\n");
+            }
+            last.push_str(&text);
+            return;
+        }
+    }
+    content.push(ContentBlock::text(text));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::translator::canonical::{CanonicalUsage, Role, ToolChoice};
+    use serde_json::json;
+
+    fn weather_tool() -> ToolDefinition {
+        ToolDefinition {
+            name: "get_weather".to_string(),
+            description: Some("Get the weather".to_string()),
+            input_schema: json!({"type": "object", "properties": {"city": {"type": "string"}}}),
+            cache_control: None,
+        }
+    }
+
+    fn request_with_history() -> CanonicalRequest {
+        let mut request = CanonicalRequest::new("m");
+        request.system = vec![ContentBlock::text("Be brief.")];
+        request.tools = vec![weather_tool()];
+        request.tool_choice = Some(ToolChoice::Any);
+        request.push_message(Role::User, vec![ContentBlock::text("Weather in Paris?")]);
+        request.push_message(
+            Role::Assistant,
+            vec![ContentBlock::ToolUse {
+                id: "call_1".into(),
+                name: "get_weather".into(),
+                input: json!({"city": "Paris"}),
+                signature: None,
+                cache_control: None,
+            }],
+        );
+        request.push_message(
+            Role::User,
+            vec![ContentBlock::ToolResult {
+                tool_use_id: "call_1".into(),
+                content: vec![ContentBlock::text("Sunny")],
+                is_error: false,
+                cache_control: None,
+            }],
+        );
+        request
+    }
+
+    #[test]
+    fn test_prepare_moves_tools_into_text() {
+        let mut request = request_with_history();
+        let emulation = ToolEmulation::prepare(&mut request, PromptFormat::Xml).unwrap();
+
+        assert_eq!(emulation.tools().len(), 1);
+        assert!(request.tools.is_empty());
+        assert!(request.tool_choice.is_none());
+        assert_eq!(request.system.len(), 1);
+        let system = request.system_text();
+        assert!(system.starts_with("Be brief.\n\n# Tools"));
+        assert!(system.contains("You must call at least one tool"));
+        assert_eq!(request.stop_sequences, vec![TOOL_RESULT_STOP.to_string()]);
+
+        let assistant = request.messages[1].content[0].as_text().unwrap();
+        assert!(assistant.contains("<invoke name=\"get_weather\">"));
+        let user = request.messages[2].content[0].as_text().unwrap();
+        assert_eq!(
+            user,
+            "<tool_result tool_use_id=\"call_1\" name=\"get_weather\">\nSunny\n</tool_result>"
+        );
+    }
+
+    #[test]
+    fn test_prepare_without_tools_only_rewrites_history() {
+        let mut request = request_with_history();
+        request.tools.clear();
+        assert!(ToolEmulation::prepare(&mut request, PromptFormat::Json).is_none());
+        assert_eq!(request.system_text(), "Be brief.");
+        assert!(request.stop_sequences.is_empty());
+        assert!(request.messages[1].content[0]
+            .as_text()
+            .unwrap()
+            .starts_with("<tool_call>"));
+    }
+
+    #[test]
+    fn test_apply_to_response() {
+        let emulation = ToolEmulation {
+            tools: vec![weather_tool()],
+        };
+        let mut response = CanonicalResponse {
+            id: "msg_1".into(),
+            model: "m".into(),
+            content: vec![ContentBlock::text(
+                "Let me check.\n<tool_call>{\"name\": \"get_weather\", \"arguments\": {\"city\": \"Oslo\"}}</tool_call>\n",
+            )],
+            stop_reason: StopReason::StopSequence,
+            stop_sequence: Some(TOOL_RESULT_STOP.into()),
+            usage: CanonicalUsage::default(),
+        };
+        assert!(emulation.apply_to_response(&mut response));
+        assert_eq!(response.stop_reason, StopReason::ToolUse);
+        assert!(response.stop_sequence.is_none());
+        assert_eq!(response.content.len(), 2);
+        assert_eq!(response.content[0].as_text(), Some("Let me check."));
+        assert!(matches!(
+            &response.content[1],
+            ContentBlock::ToolUse { name, input, .. }
+                if name == "get_weather" && input == &json!({"city": "Oslo"})
+        ));
+    }
+}
@@ -0,0 +1,484 @@
+//! 工具调用解析
+//!
+//! 从模型输出的文本中识别以下格式的工具调用：
+//!
+//! - `<function_calls><invoke name="..."><parameter name="...">...</parameter></invoke></function_calls>`
+//! - 单独的 `<invoke name="...">...</invoke>`
+//! - `<tool_call>{"name": "...", "arguments": {...}}</tool_call>`
+//! - Kiro 的 `[Called xxx with args: {...}]`
+//!
+//! 无法解析或调用了未声明工具的片段保留为文本。
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde_json::{json, Map, Value};
+
+use crate::translator::canonical::openai::parse_arguments;
+use crate::translator::canonical::ToolDefinition;
+
+/// 工具调用起始标记
+pub(crate) const CALL_MARKERS: &[&str] = &["<function_calls", "<invoke", "<tool_call", "[Called "];
+
+static INVOKE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"(?s)<invoke\s+name\s*=\s*"([^"]+)"\s*>(.*?)</invoke>"#).unwrap());
+
+static PARAMETER: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(?s)<parameter\s+name\s*=\s*"([^"]+)"\s*>(.*?)</parameter>"#).unwrap()
+});
+
+static TOOL_CALL: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(?s)^<tool_call(?:\s+name\s*=\s*"([^"]+)")?\s*>(.*)</tool_call>$"#).unwrap()
+});
+
+static BRACKET_PREFIX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^\[Called\s+([\w.-]+)\s+with\s+args:\s*").unwrap());
+
+/// 解析出的工具调用
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParsedToolCall {
+    pub name: String,
+    pub arguments: Value,
+}
+
+/// 文本切分结果
+#[derive(Debug, Clone, PartialEq)]
+pub enum Segment {
+    Text(String),
+    Call(ParsedToolCall),
+}
+
+/// 单次扫描结果
+#[derive(Debug, PartialEq)]
+pub(crate) enum Scan {
+    /// 没有工具调用标记
+    None,
+    /// `start` 处有工具调用标记但尚未结束（流式响应需要等待更多文本）
+    Incomplete { start: usize },
+    /// `start..end` 是一个完整的调用片段，`calls` 为 None 表示无法解析
+    Complete {
+        start: usize,
+        end: usize,
+        calls: Option<Vec<ParsedToolCall>>,
+    },
+}
+
+/// 从 `from` 开始查找下一个工具调用片段
+///
+/// `tools` 为空时接受任意工具名。
+pub(crate) fn scan(text: &str, from: usize, tools: &[ToolDefinition]) -> Scan {
+    let mut pos = from;
+    loop {
+        let Some((start, marker)) = find_marker(text, pos) else {
+            return Scan::None;
+        };
+        let rest = &text[start..];
+        let end = match marker {
+            "<function_calls" => closing_end(rest, "</function_calls>"),
+            "<invoke" => closing_end(rest, "</invoke>"),
+            "<tool_call" => match rest[marker.len()..].chars().next() {
+                None => Some(None),
+                Some(c) if c == '>' || c.is_whitespace() => closing_end(rest, "</tool_call>"),
+                // `<tool_calls` 等其他标签
+                Some(_) => None,
+            },
+            _ => bracket_end(rest),
+        };
+        match end {
+            Some(Some(len)) => {
+                let segment = &rest[..len];
+                let calls = match marker {
+                    "<function_calls" | "<invoke" => parse_invokes(segment, tools),
+                    "<tool_call" => parse_tool_call(segment, tools).map(|call| vec![call]),
+                    _ => parse_bracket(segment, tools).map(|call| vec![call]),
+                };
+                return Scan::Complete {
+                    start,
+                    end: start + len,
+                    calls,
+                };
+            }
+            Some(None) => return Scan::Incomplete { start },
+            None => pos = start + marker.len(),
+        }
+    }
+}
+
+/// 将文本切分为普通文本和工具调用，未结束的调用片段保留为文本
+pub fn split_tool_calls(text: &str, tools: &[ToolDefinition]) -> Vec<Segment> {
+    let mut segments = Vec::new();
+    let mut pending = String::new();
+    let mut pos = 0;
+    loop {
+        match scan(text, pos, tools) {
+            Scan::Complete {
+                start,
+                end,
+                calls: Some(calls),
+            } => {
+                pending.push_str(&text[pos..start]);
+                if !pending.is_empty() {
+                    segments.push(Segment::Text(std::mem::take(&mut pending)));
+                }
+                segments.extend(calls.into_iter().map(Segment::Call));
+                pos = end;
+            }
+            Scan::Complete {
+                end, calls: None, ..
+            } => {
+                pending.push_str(&text[pos..end]);
+                pos = end;
+            }
+            Scan::None | Scan::Incomplete { .. } => {
+                pending.push_str(&text[pos..]);
+                break;
+            }
+        }
+    }
+    if !pending.is_empty() {
+        segments.push(Segment::Text(pending));
+    }
+    segments
+}
+
+/// 提取文本中的工具调用，返回去除调用后的文本和调用列表
+pub fn extract_tool_calls(text: &str, tools: &[ToolDefinition]) -> (String, Vec<ParsedToolCall>) {
+    let mut remaining = String::new();
+    let mut calls = Vec::new();
+    for segment in split_tool_calls(text, tools) {
+        match segment {
+            Segment::Text(t) => remaining.push_str(&t),
+            Segment::Call(call) => calls.push(call),
+        }
+    }
+    (remaining, calls)
+}
+
+/// 只提取 Kiro 的 `[Called xxx with args: {...}]` 格式，接受任意工具名
+pub fn extract_bracket_tool_calls(text: &str) -> (String, Vec<ParsedToolCall>) {
+    const MARKER: &str = "[Called ";
+    let mut remaining = String::new();
+    let mut calls = Vec::new();
+    let mut pos = 0;
+    while let Some(i) = text[pos..].find(MARKER) {
+        let start = pos + i;
+        let rest = &text[start..];
+        let call = match bracket_end(rest) {
+            Some(Some(len)) => parse_bracket(&rest[..len], &[]).map(|call| (call, len)),
+            _ => None,
+        };
+        match call {
+            Some((call, len)) => {
+                remaining.push_str(&text[pos..start]);
+                calls.push(call);
+                pos = start + len;
+            }
+            None => {
+                remaining.push_str(&text[pos..start + MARKER.len()]);
+                pos = start + MARKER.len();
+            }
+        }
+    }
+    remaining.push_str(&text[pos..]);
+    (remaining, calls)
+}
+
+/// 文本末尾可能是工具调用标记前缀的起始位置
+///
+/// 流式响应中这部分文本需要暂缓输出，等待后续内容确认。
+pub(crate) fn partial_marker_start(text: &str) -> Option<usize> {
+    let max = CALL_MARKERS.iter().map(|m| m.len()).max().unwrap_or(0);
+    let lower = text.len().saturating_sub(max - 1);
+    (lower..text.len())
+        .filter(|i| text.is_char_boundary(*i))
+        .find(|i| {
+            let suffix = &text[*i..];
+            CALL_MARKERS.iter().any(|m| m.starts_with(suffix))
+        })
+}
+
+fn find_marker(text: &str, from: usize) -> Option<(usize, &'static str)> {
+    CALL_MARKERS
+        .iter()
+        .filter_map(|marker| text[from..].find(marker).map(|i| (from + i, *marker)))
+        .min_by_key(|(start, _)| *start)
+}
+
+/// 查找闭合标签，返回 `Some(Some(len))` 表示完整，`Some(None)` 表示尚未结束
+fn closing_end(rest: &str, closing: &str) -> Option<Option<usize>> {
+    Some(rest.find(closing).map(|i| i + closing.len()))
+}
+
+/// `[Called xxx with args: {...}]`，参数按括号配对查找结束位置
+///
+/// 返回 None 表示不是该格式。
+fn bracket_end(rest: &str) -> Option<Option<usize>> {
+    let Some(prefix) = BRACKET_PREFIX.find(rest) else {
+        // 前缀还没收完整时继续等待
+        let incomplete = rest.len() < 48 && !rest.contains(['{', ']', '\n']);
+        return incomplete.then_some(None);
+    };
+    let body = &rest[prefix.end()..];
+    if body.is_empty() {
+        return Some(None);
+    }
+    if !body.starts_with('{') {
+        return None;
+    }
+    let Some(json_len) = balanced_json_len(body) else {
+        return Some(None);
+    };
+    let after = &body[json_len..];
+    let trimmed = after.trim_start();
+    match trimmed.chars().next() {
+        None => Some(None),
+        Some(']') => Some(Some(
+            prefix.end() + json_len + (after.len() - trimmed.len()) + 1,
+        )),
+        Some(_) => None,
+    }
+}
+
+/// 括号配对的 JSON 对象长度（忽略字符串中的括号）
+fn balanced_json_len(text: &str) -> Option<usize> {
+    let mut depth = 0usize;
+    let mut in_string = false;
+    let mut escaped = false;
+    for (i, c) in text.char_indices() {
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            '"' => in_string = true,
+            '{' => depth += 1,
+            '}' => {
+                depth = depth.saturating_sub(1);
+                if depth == 0 {
+                    return Some(i + 1);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+fn find_tool<'a>(tools: &'a [ToolDefinition], name: &str) -> Option<Option<&'a ToolDefinition>> {
+    if tools.is_empty() {
+        return Some(None);
+    }
+    tools.iter().find(|t| t.name == name).map(Some)
+}
+
+fn parse_invokes(segment: &str, tools: &[ToolDefinition]) -> Option<Vec<ParsedToolCall>> {
+    let mut calls = Vec::new();
+    for cap in INVOKE.captures_iter(segment) {
+        let name = cap[1].trim().to_string();
+        let tool = find_tool(tools, &name)?;
+        let body = &cap[2];
+
+        let mut arguments = Map::new();
+        for param in PARAMETER.captures_iter(body) {
+            let key = param[1].to_string();
+            let value = parameter_value(tool, &key, &param[2]);
+            arguments.insert(key, value);
+        }
+        let arguments = if arguments.is_empty() && !body.trim().is_empty() {
+            // `<invoke name="x">{...}</invoke>`
+            match serde_json::from_str::<Value>(strip_code_fence(body)) {
+                Ok(value @ Value::Object(_)) => value,
+                _ => return None,
+            }
+        } else {
+            Value::Object(arguments)
+        };
+        calls.push(ParsedToolCall { name, arguments });
+    }
+    (!calls.is_empty()).then_some(calls)
+}
+
+/// 参数值：schema 声明为字符串的原样保留，其他类型按 JSON 解析
+fn parameter_value(tool: Option<&ToolDefinition>, key: &str, raw: &str) -> Value {
+    let declared = tool.and_then(|t| t.input_schema["properties"][key]["type"].as_str());
+    if declared == Some("string") {
+        return json!(trim_newlines(raw));
+    }
+    serde_json::from_str(raw.trim()).unwrap_or_else(|_| json!(trim_newlines(raw)))
+}
+
+fn parse_tool_call(segment: &str, tools: &[ToolDefinition]) -> Option<ParsedToolCall> {
+    let cap = TOOL_CALL.captures(segment)?;
+    let body: Value = serde_json::from_str(strip_code_fence(&cap[2])).ok()?;
+
+    let (name, arguments) = match cap.get(1) {
+        Some(name) => (name.as_str().to_string(), body),
+        None => {
+            let name = body.get("name")?.as_str()?.to_string();
+            let arguments = ["arguments", "parameters", "input", "args"]
+                .iter()
+                .find_map(|key| body.get(*key))
+                .cloned()
+                .unwrap_or_else(|| json!({}));
+            (name, arguments)
+        }
+    };
+    let arguments = match arguments {
+        Value::String(s) => parse_arguments(&s),
+        other => other,
+    };
+    if !arguments.is_object() {
+        return None;
+    }
+    find_tool(tools, &name)?;
+    Some(ParsedToolCall { name, arguments })
+}
+
+fn parse_bracket(segment: &str, tools: &[ToolDefinition]) -> Option<ParsedToolCall> {
+    let cap = BRACKET_PREFIX.captures(segment)?;
+    let name = cap[1].to_string();
+    let body = segment[cap.get(0)?.end()..].trim_end();
+    let body = body.strip_suffix(']')?;
+    let arguments: Value = serde_json::from_str(body).ok()?;
+    find_tool(tools, &name)?;
+    Some(ParsedToolCall { name, arguments })
+}
+
+fn strip_code_fence(body: &str) -> &str {
+    let body = body.trim();
+    body.strip_prefix("```json")
+        .or_else(|| body.strip_prefix("```"))
+        .and_then(|b| b.strip_suffix("```"))
+        .map(str::trim)
+        .unwrap_or(body)
+}
+
+/// 去掉标签内容首尾的单个换行
+fn trim_newlines(raw: &str) -> &str {
+    let raw = raw
+        .strip_prefix("\r\n")
+        .or_else(|| raw.strip_prefix('\n'))
+        .unwrap_or(raw);
+    raw.strip_suffix("\r\n")
+        .or_else(|| raw.strip_suffix('\n'))
+        .unwrap_or(raw)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tool(name: &str, schema: Value) -> ToolDefinition {
+        ToolDefinition {
+            name: name.to_string(),
+            description: None,
+            input_schema: schema,
+            cache_control: None,
+        }
+    }
+
+    fn tools() -> Vec<ToolDefinition> {
+        vec![
+            tool(
+                "read_file",
+                json!({"type": "object", "properties": {
+                    "path": {"type": "string"},
+                    "limit": {"type": "integer"}
+                }}),
+            ),
+            tool("get_time", json!({"type": "object", "properties": {}})),
+        ]
+    }
+
+    #[test]
+    fn test_invoke_parameters_follow_schema_types() {
+        let text = "Let me check.\n<function_calls>\n<invoke name=\"read_file\">\n\
+            <parameter name=\"path\">123</parameter>\n\
+            <parameter name=\"limit\">20</parameter>\n\
+            </invoke>\n</function_calls>";
+        let (rest, calls) = extract_tool_calls(text, &tools());
+        assert_eq!(rest, "Let me check.\n");
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0].name, "read_file");
+        assert_eq!(calls[0].arguments, json!({"path": "123", "limit": 20}));
+    }
+
+    #[test]
+    fn test_multiple_invokes_in_one_block() {
+        let text = "<function_calls><invoke name=\"get_time\"></invoke>\
+            <invoke name=\"read_file\"><parameter name=\"path\">a.txt</parameter></invoke>\
+            </function_calls>";
+        let (_, calls) = extract_tool_calls(text, &tools());
+        let names: Vec<&str> = calls.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["get_time", "read_file"]);
+    }
+
+    #[test]
+    fn test_tool_call_json_body() {
+        let text = "<tool_call>\n{\"name\": \"read_file\", \"arguments\": \"{\\\"path\\\": \\\"a\\\"}\"}\n</tool_call> done";
+        let segments = split_tool_calls(text, &tools());
+        assert_eq!(
+            segments,
+            vec![
+                Segment::Call(ParsedToolCall {
+                    name: "read_file".into(),
+                    arguments: json!({"path": "a"}),
+                }),
+                Segment::Text(" done".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_bracket_format_with_nested_braces() {
+        let text = "[Called read_file with args: {\"path\": \"}{\", \"opts\": {\"a\": 1}}]";
+        let (rest, calls) = extract_tool_calls(text, &[]);
+        assert!(rest.is_empty());
+        assert_eq!(calls[0].arguments, json!({"path": "}{", "opts": {"a": 1}}));
+    }
+
+    #[test]
+    fn test_bracket_only_extraction_ignores_xml() {
+        let text = "<invoke name=\"x\"></invoke> [Called get_time with args: {}] [Called it off]";
+        let (rest, calls) = extract_bracket_tool_calls(text);
+        assert_eq!(rest, "<invoke name=\"x\"></invoke>  [Called it off]");
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0].name, "get_time");
+    }
+
+    #[test]
+    fn test_unknown_or_invalid_calls_stay_as_text() {
+        let text = "<invoke name=\"rm_rf\"></invoke> and <tool_call>not json</tool_call>";
+        let (rest, calls) = extract_tool_calls(text, &tools());
+        assert!(calls.is_empty());
+        assert_eq!(rest, text);
+    }
+
+    #[test]
+    fn test_scan_reports_incomplete_calls() {
+        let tools = tools();
+        assert_eq!(
+            scan("ok <invoke name=\"get_time\">", 0, &tools),
+            Scan::Incomplete { start: 3 }
+        );
+        assert_eq!(
+            scan("[Called get_time with args: {\"a\": ", 0, &tools),
+            Scan::Incomplete { start: 0 }
+        );
+        assert_eq!(scan("[Called it a day]", 0, &tools), Scan::None);
+        assert_eq!(scan("<tool_calls> is a tag", 0, &tools), Scan::None);
+    }
+
+    #[test]
+    fn test_partial_marker_start() {
+        assert_eq!(partial_marker_start("hello <inv"), Some(6));
+        assert_eq!(partial_marker_start("hello [Call"), Some(6));
+        assert_eq!(partial_marker_start("a < b"), None);
+        assert_eq!(partial_marker_start("a <"), Some(2));
+        assert_eq!(partial_marker_start("plain text"), None);
+    }
+}
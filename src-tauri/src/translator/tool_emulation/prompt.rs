@@ -0,0 +1,210 @@
+//! 工具定义、工具调用和工具结果的文本渲染
+//!
+//! 注入的提示使用英文：提示语言会影响模型回复所用的语言，英文对各模型的干扰最小。
+
+use serde_json::{json, Value};
+
+use crate::agent::tools::prompt::{escape_xml, PromptFormat};
+use crate::translator::canonical::{ToolChoice, ToolDefinition};
+
+/// 渲染注入系统提示的工具说明
+pub fn render_tools_prompt(
+    tools: &[ToolDefinition],
+    tool_choice: Option<&ToolChoice>,
+    parallel_tool_calls: Option<bool>,
+    format: PromptFormat,
+) -> String {
+    let mut prompt =
+        String::from("# Tools\n\nYou can call the following tools to help answer the user.\n\n");
+
+    match format {
+        PromptFormat::Xml => {
+            prompt.push_str("<tools>\n");
+            for tool in tools {
+                prompt.push_str(&format!("<tool name=\"{}\">\n", escape_xml(&tool.name)));
+                if let Some(description) = &tool.description {
+                    prompt.push_str(&format!(
+                        "<description>{}</description>\n",
+                        escape_xml(description)
+                    ));
+                }
+                prompt.push_str(&format!(
+                    "<parameters>{}</parameters>\n</tool>\n",
+                    tool.input_schema
+                ));
+            }
+            prompt.push_str("</tools>\n\n");
+            prompt.push_str(
+                "To call a tool, reply with a block in exactly this format:\n\n\
+                 <function_calls>\n\
+                 <invoke name=\"TOOL_NAME\">\n\
+                 <parameter name=\"PARAMETER_NAME\">VALUE</parameter>\n\
+                 </invoke>\n\
+                 </function_calls>\n\n\
+                 Write string values as-is and all other values (numbers, booleans, arrays, \
+                 objects) as JSON. To call several tools at once, put several <invoke> \
+                 elements in the same block.",
+            );
+        }
+        PromptFormat::Json => {
+            let definitions: Vec<Value> = tools
+                .iter()
+                .map(|tool| {
+                    json!({
+                        "name": tool.name,
+                        "description": tool.description,
+                        "parameters": tool.input_schema,
+                    })
+                })
+                .collect();
+            prompt.push_str("Available tools:\n```json\n");
+            prompt.push_str(
+                &serde_json::to_string_pretty(&definitions).unwrap_or_else(|_| "[]".into()),
+            );
+            prompt.push_str("\n```\n\n");
+            prompt.push_str(
+                "To call a tool, reply with a block in exactly this format:\n\n\
+                 <tool_call>\n\
+                 {\"name\": \"TOOL_NAME\", \"arguments\": {\"PARAMETER_NAME\": VALUE}}\n\
+                 </tool_call>\n\n\
+                 The arguments must be a JSON object. To call several tools at once, \
+                 write one <tool_call> block per call.",
+            );
+        }
+    }
+
+    prompt.push_str(
+        "\n\nAfter the tool calls, end your reply and wait. The results will be sent back \
+         in <tool_result> elements in the next message. Never write <tool_result> yourself. \
+         If no tool is needed, answer normally without any tool call block.",
+    );
+
+    match tool_choice {
+        Some(ToolChoice::Any) => {
+            prompt.push_str("\n\nYou must call at least one tool in this reply.")
+        }
+        Some(ToolChoice::Tool { name }) => prompt.push_str(&format!(
+            "\n\nYou must call the `{}` tool in this reply.",
+            name
+        )),
+        Some(ToolChoice::None) => {
+            prompt.push_str("\n\nDo not call any tool in this reply; answer with text only.")
+        }
+        Some(ToolChoice::Auto) | None => {}
+    }
+    if parallel_tool_calls == Some(false) {
+        prompt.push_str("\n\nCall at most one tool per reply.");
+    }
+
+    prompt
+}
+
+/// 将历史中的工具调用渲染为模型输出格式的文本
+pub fn render_tool_call(name: &str, input: &Value, format: PromptFormat) -> String {
+    match format {
+        PromptFormat::Xml => {
+            let mut text = format!("<function_calls>\n<invoke name=\"{}\">\n", escape_xml(name));
+            match input {
+                Value::Object(params) => {
+                    for (key, value) in params {
+                        let value = match value {
+                            Value::String(s) => s.clone(),
+                            other => other.to_string(),
+                        };
+                        text.push_str(&format!(
+                            "<parameter name=\"{}\">{}</parameter>\n",
+                            escape_xml(key),
+                            value
+                        ));
+                    }
+                }
+                Value::Null => {}
+                other => text.push_str(&format!("{}\n", other)),
+            }
+            text.push_str("</invoke>\n</function_calls>");
+            text
+        }
+        PromptFormat::Json => format!(
+            "<tool_call>\n{}\n</tool_call>",
+            json!({"name": name, "arguments": input})
+        ),
+    }
+}
+
+/// 将工具结果渲染为文本
+pub fn render_tool_result(
+    tool_use_id: &str,
+    name: Option<&str>,
+    content: &str,
+    is_error: bool,
+) -> String {
+    let mut attributes = format!("tool_use_id=\"{}\"", escape_xml(tool_use_id));
+    if let Some(name) = name {
+        attributes.push_str(&format!(" name=\"{}\"", escape_xml(name)));
+    }
+    if is_error {
+        attributes.push_str(" status=\"error\"");
+    }
+    format!("<tool_result {}>\n{}\n</tool_result>", attributes, content)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::translator::tool_emulation::parser::extract_tool_calls;
+
+    fn tools() -> Vec<ToolDefinition> {
+        vec![ToolDefinition {
+            name: "search".to_string(),
+            description: Some("Search <docs>".to_string()),
+            input_schema: json!({"type": "object", "properties": {
+                "query": {"type": "string"},
+                "top_k": {"type": "integer"},
+                "filters": {"type": "object"}
+            }}),
+            cache_control: None,
+        }]
+    }
+
+    #[test]
+    fn test_rendered_calls_parse_back() {
+        let input = json!({"query": "rust \"async\"", "top_k": 3, "filters": {"lang": "en"}});
+        for format in [PromptFormat::Xml, PromptFormat::Json] {
+            let text = render_tool_call("search", &input, format);
+            let (rest, calls) = extract_tool_calls(&text, &tools());
+            assert!(rest.is_empty(), "{:?}", format);
+            assert_eq!(calls.len(), 1);
+            assert_eq!(calls[0].arguments, input, "{:?}", format);
+        }
+    }
+
+    #[test]
+    fn test_tools_prompt_includes_definitions_and_choice() {
+        let prompt = render_tools_prompt(
+            &tools(),
+            Some(&ToolChoice::Tool {
+                name: "search".into(),
+            }),
+            Some(false),
+            PromptFormat::Xml,
+        );
+        assert!(prompt.contains("<tool name=\"search\">"));
+        assert!(prompt.contains("Search &lt;docs&gt;"));
+        assert!(prompt.contains("\"top_k\""));
+        assert!(prompt.contains("You must call the `search` tool"));
+        assert!(prompt.contains("at most one tool"));
+
+        let prompt = render_tools_prompt(&tools(), None, None, PromptFormat::Json);
+        assert!(prompt.contains("<tool_call>"));
+        assert!(prompt.contains("\"name\": \"search\""));
+    }
+
+    #[test]
+    fn test_tool_result_attributes() {
+        let text = render_tool_result("call_1", Some("search"), "boom", true);
+        assert_eq!(
+            text,
+            "<tool_result tool_use_id=\"call_1\" name=\"search\" status=\"error\">\nboom\n</tool_result>"
+        );
+    }
+}
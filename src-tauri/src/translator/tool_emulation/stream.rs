@@ -0,0 +1,382 @@
+//! 流式响应中的工具调用解析
+//!
+//! `ToolCallStreamFilter` 位于 SSE 解析器和生成器之间：文本增量先进入缓冲区，
+//! 可能属于工具调用的部分暂缓输出，调用完整后改为输出工具调用事件。
+//! 过滤器自行分配内容块索引，保证文本块和生成的工具调用块依次编号。
+
+use std::collections::HashMap;
+
+use super::new_tool_use_id;
+use super::parser::{partial_marker_start, scan, ParsedToolCall, Scan};
+use crate::stream::{ContentBlockType, StopReason, StreamEvent};
+use crate::translator::canonical::ToolDefinition;
+
+/// 流式工具调用过滤器
+#[derive(Debug)]
+pub struct ToolCallStreamFilter {
+    tools: Vec<ToolDefinition>,
+    /// 尚未输出的文本
+    buffer: String,
+    /// 当前打开的文本块索引
+    text_block: Option<u32>,
+    /// 上游其他内容块索引 -> 输出索引
+    block_map: HashMap<u32, u32>,
+    next_index: u32,
+    emitted_calls: usize,
+    finished: bool,
+}
+
+impl ToolCallStreamFilter {
+    /// 创建过滤器，只识别 `tools` 中声明的工具
+    pub fn new(tools: Vec<ToolDefinition>) -> Self {
+        Self {
+            tools,
+            buffer: String::new(),
+            text_block: None,
+            block_map: HashMap::new(),
+            next_index: 0,
+            emitted_calls: 0,
+            finished: false,
+        }
+    }
+
+    /// 重置状态，保留工具列表
+    pub fn reset(&mut self) {
+        *self = Self::new(std::mem::take(&mut self.tools));
+    }
+
+    /// 已输出的工具调用数量
+    pub fn emitted_calls(&self) -> usize {
+        self.emitted_calls
+    }
+
+    /// 处理单个事件
+    pub fn process(&mut self, event: StreamEvent) -> Vec<StreamEvent> {
+        let mut out = Vec::new();
+        match event {
+            // 上游文本块不单独开关，文本统一经缓冲区输出
+            StreamEvent::ContentBlockStart {
+                block_type: ContentBlockType::Text,
+                ..
+            } => {}
+            StreamEvent::ContentBlockStart { index, block_type } => {
+                self.flush(&mut out);
+                self.close_text(&mut out);
+                let mapped = self.allocate_index();
+                self.block_map.insert(index, mapped);
+                out.push(StreamEvent::ContentBlockStart {
+                    index: mapped,
+                    block_type,
+                });
+            }
+            StreamEvent::ContentBlockStop { index } => {
+                if let Some(mapped) = self.block_map.remove(&index) {
+                    out.push(StreamEvent::ContentBlockStop { index: mapped });
+                }
+            }
+            StreamEvent::TextDelta { text } => {
+                self.buffer.push_str(&text);
+                self.drain(false, &mut out);
+            }
+            event @ (StreamEvent::ThinkingDelta { .. } | StreamEvent::ToolUseStart { .. }) => {
+                self.flush(&mut out);
+                self.close_text(&mut out);
+                out.push(event);
+            }
+            StreamEvent::MessageStop { stop_reason } => {
+                self.finish_into(&mut out);
+                let stop_reason = match stop_reason {
+                    StopReason::EndTurn | StopReason::StopSequence if self.emitted_calls > 0 => {
+                        StopReason::ToolUse
+                    }
+                    other => other,
+                };
+                out.push(StreamEvent::MessageStop { stop_reason });
+            }
+            other => out.push(other),
+        }
+        out
+    }
+
+    /// 流结束时输出剩余内容（上游没有发送 MessageStop 时使用）
+    pub fn finish(&mut self) -> Vec<StreamEvent> {
+        let mut out = Vec::new();
+        self.finish_into(&mut out);
+        out
+    }
+
+    fn finish_into(&mut self, out: &mut Vec<StreamEvent>) {
+        if self.finished {
+            return;
+        }
+        self.finished = true;
+        self.drain(true, out);
+        self.close_text(out);
+        let mut open: Vec<u32> = self.block_map.drain().map(|(_, mapped)| mapped).collect();
+        open.sort_unstable();
+        out.extend(
+            open.into_iter()
+                .map(|index| StreamEvent::ContentBlockStop { index }),
+        );
+    }
+
+    /// 输出缓冲区中可以确定的部分；`finished` 为 true 时未结束的调用按文本输出
+    fn drain(&mut self, finished: bool, out: &mut Vec<StreamEvent>) {
+        loop {
+            match scan(&self.buffer, 0, &self.tools) {
+                Scan::Complete {
+                    start,
+                    end,
+                    calls: Some(calls),
+                } => {
+                    let before = self.buffer[..start].to_string();
+                    self.emit_text(&before, out);
+                    self.buffer.drain(..end);
+                    for call in calls {
+                        self.emit_call(call, out);
+                    }
+                }
+                Scan::Complete {
+                    end, calls: None, ..
+                } => {
+                    let text: String = self.buffer.drain(..end).collect();
+                    self.emit_text(&text, out);
+                }
+                Scan::Incomplete { start } if !finished => {
+                    let text: String = self.buffer.drain(..start).collect();
+                    self.emit_text(&text, out);
+                    return;
+                }
+                Scan::Incomplete { .. } => {
+                    self.flush(out);
+                    return;
+                }
+                Scan::None => {
+                    let keep = if finished {
+                        self.buffer.len()
+                    } else {
+                        partial_marker_start(&self.buffer).unwrap_or(self.buffer.len())
+                    };
+                    let text: String = self.buffer.drain(..keep).collect();
+                    self.emit_text(&text, out);
+                    return;
+                }
+            }
+        }
+    }
+
+    /// 缓冲区全部按文本输出
+    fn flush(&mut self, out: &mut Vec<StreamEvent>) {
+        let text = std::mem::take(&mut self.buffer);
+        self.emit_text(&text, out);
+    }
+
+    fn emit_text(&mut self, text: &str, out: &mut Vec<StreamEvent>) {
+        if text.is_empty() {
+            return;
+        }
+        if self.text_block.is_none() {
+            // 工具调用之间的空白不单独成块
+            if text.trim().is_empty() {
+                return;
+            }
+            let index = self.allocate_index();
+            self.text_block = Some(index);
+            out.push(StreamEvent::ContentBlockStart {
+                index,
+                block_type: ContentBlockType::Text,
+            });
+        }
+        out.push(StreamEvent::TextDelta {
+            text: text.to_string(),
+        });
+    }
+
+    fn emit_call(&mut self, call: ParsedToolCall, out: &mut Vec<StreamEvent>) {
+        self.close_text(out);
+        let index = self.allocate_index();
+        let id = new_tool_use_id();
+        out.push(StreamEvent::ContentBlockStart {
+            index,
+            block_type: ContentBlockType::ToolUse {
+                id: id.clone(),
+                name: call.name.clone(),
+            },
+        });
+        out.push(StreamEvent::ToolUseStart {
+            id: id.clone(),
+            name: call.name,
+        });
+        out.push(StreamEvent::ToolUseInputDelta {
+            id: id.clone(),
+            partial_json: call.arguments.to_string(),
+        });
+        out.push(StreamEvent::ToolUseStop { id });
+        out.push(StreamEvent::ContentBlockStop { index });
+        self.emitted_calls += 1;
+    }
+
+    fn close_text(&mut self, out: &mut Vec<StreamEvent>) {
+        if let Some(index) = self.text_block.take() {
+            out.push(StreamEvent::ContentBlockStop { index });
+        }
+    }
+
+    fn allocate_index(&mut self) -> u32 {
+        let index = self.next_index;
+        self.next_index += 1;
+        index
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::translator::canonical::ResponseAccumulator;
+    use crate::translator::canonical::{ContentBlock, ToolDefinition};
+    use serde_json::json;
+
+    fn filter() -> ToolCallStreamFilter {
+        ToolCallStreamFilter::new(vec![ToolDefinition {
+            name: "get_weather".to_string(),
+            description: None,
+            input_schema: json!({"type": "object", "properties": {"city": {"type": "string"}}}),
+            cache_control: None,
+        }])
+    }
+
+    fn run(filter: &mut ToolCallStreamFilter, chunks: &[&str]) -> Vec<StreamEvent> {
+        let mut events = filter.process(StreamEvent::MessageStart {
+            id: "msg_1".into(),
+            model: "m".into(),
+        });
+        for chunk in chunks {
+            events.extend(filter.process(StreamEvent::TextDelta {
+                text: chunk.to_string(),
+            }));
+        }
+        events.extend(filter.process(StreamEvent::MessageStop {
+            stop_reason: StopReason::EndTurn,
+        }));
+        events
+    }
+
+    #[test]
+    fn test_call_split_across_chunks() {
+        let mut filter = filter();
+        let events = run(
+            &mut filter,
+            &[
+                "Checking.\n<func",
+                "tion_calls>\n<invoke name=\"get_weather\">",
+                "<parameter name=\"city\">Paris</parameter></invoke>\n</function_calls>",
+            ],
+        );
+
+        // 标记前缀在确认前不会作为文本输出
+        let texts: String = events
+            .iter()
+            .filter_map(|e| match e {
+                StreamEvent::TextDelta { text } => Some(text.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(texts, "Checking.\n");
+
+        let mut accumulator = ResponseAccumulator::new();
+        accumulator.extend(&events);
+        let response = accumulator.finish().unwrap();
+        assert_eq!(response.stop_reason, StopReason::ToolUse);
+        assert_eq!(response.content.len(), 2);
+        match &response.content[1] {
+            ContentBlock::ToolUse { name, input, .. } => {
+                assert_eq!(name, "get_weather");
+                assert_eq!(input, &json!({"city": "Paris"}));
+            }
+            other => panic!("unexpected block: {:?}", other),
+        }
+        assert_eq!(filter.emitted_calls(), 1);
+    }
+
+    #[test]
+    fn test_block_indices_are_sequential() {
+        let mut filter = filter();
+        let events = run(
+            &mut filter,
+            &["A <tool_call>{\"name\":\"get_weather\",\"arguments\":{}}</tool_call> B"],
+        );
+        let indices: Vec<u32> = events
+            .iter()
+            .filter_map(|e| match e {
+                StreamEvent::ContentBlockStart { index, .. } => Some(*index),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(indices, vec![0, 1, 2]);
+        let stops = events
+            .iter()
+            .filter(|e| matches!(e, StreamEvent::ContentBlockStop { .. }))
+            .count();
+        assert_eq!(stops, 3);
+    }
+
+    #[test]
+    fn test_unfinished_call_is_flushed_as_text() {
+        let mut filter = filter();
+        let events = run(&mut filter, &["a < b and <invoke name=\"get_weather\">"]);
+        let texts: String = events
+            .iter()
+            .filter_map(|e| match e {
+                StreamEvent::TextDelta { text } => Some(text.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(texts, "a < b and <invoke name=\"get_weather\">");
+        assert!(matches!(
+            events.last(),
+            Some(StreamEvent::MessageStop {
+                stop_reason: StopReason::EndTurn
+            })
+        ));
+    }
+
+    #[test]
+    fn test_upstream_blocks_are_reindexed() {
+        let mut filter = filter();
+        let mut events = Vec::new();
+        for event in [
+            StreamEvent::ContentBlockStart {
+                index: 0,
+                block_type: ContentBlockType::Thinking,
+            },
+            StreamEvent::ThinkingDelta { text: "hmm".into() },
+            StreamEvent::ContentBlockStop { index: 0 },
+            StreamEvent::ContentBlockStart {
+                index: 1,
+                block_type: ContentBlockType::Text,
+            },
+            StreamEvent::TextDelta { text: "hi".into() },
+            StreamEvent::ContentBlockStop { index: 1 },
+        ] {
+            events.extend(filter.process(event));
+        }
+        events.extend(filter.finish());
+        assert_eq!(
+            events,
+            vec![
+                StreamEvent::ContentBlockStart {
+                    index: 0,
+                    block_type: ContentBlockType::Thinking,
+                },
+                StreamEvent::ThinkingDelta { text: "hmm".into() },
+                StreamEvent::ContentBlockStop { index: 0 },
+                StreamEvent::ContentBlockStart {
+                    index: 1,
+                    block_type: ContentBlockType::Text,
+                },
+                StreamEvent::TextDelta { text: "hi".into() },
+                StreamEvent::ContentBlockStop { index: 1 },
+            ]
+        );
+    }
+}
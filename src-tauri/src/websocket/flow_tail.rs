@@ -0,0 +1,715 @@
+//! Flow 事件实时订阅
+//!
+//! 为 WebSocket 客户端提供带序号、可续订的 Flow 事件流：
+//! - `FlowEventJournal`: 为 FlowMonitor 事件分配全局序号，并保留最近的事件用于回放
+//! - `FlowTailFilter`: 按订阅方的过滤表达式筛选事件
+//! - `run_flow_tail`: 单个订阅的事件泵，处理回放、落后补发和发送背压
+//!
+//! # 背压策略
+//!
+//! 每个订阅有一个有界发送队列。流式 chunk 事件在队列已满时直接丢弃，
+//! 并在下一条消息的 `dropped_chunks` 中报告丢弃数量；生命周期事件（开始、更新、
+//! 完成、失败）会等待队列空出。订阅方因此落后于广播通道时，从回放缓冲区补发，
+//! 超出缓冲区的部分以 `EventsLost` 事件告知。
+//!
+//! # 续订
+//!
+//! 序号只在同一事件日志内有效，日志标识（epoch）随服务端重启改变。续订时 epoch
+//! 不一致，或日志本身从 FlowMonitor 接收时落后，都会推送 `Resync` 事件。
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+
+use tokio::sync::{broadcast, mpsc};
+use tokio::task::JoinHandle;
+
+use super::{WsFlowEvent, WsFlowEventMessage, WsFlowSubscription, WsResyncReason};
+use crate::flow_monitor::filter_parser::{FilterExpr, FilterParseError, FilterParser};
+use crate::flow_monitor::models::{FlowState, LLMFlow};
+use crate::flow_monitor::monitor::{FlowEvent, FlowMonitor};
+
+/// 单个订阅最多跟踪的进行中 Flow 数量，超出时清空（防止未完成的 Flow 无限累积）
+const MAX_TRACKED_FLOWS: usize = 10_000;
+
+// ============================================================================
+// 事件日志
+// ============================================================================
+
+/// 事件日志中的事件
+#[derive(Debug, Clone)]
+pub enum JournalEvent {
+    /// FlowMonitor 事件
+    Flow(FlowEvent),
+    /// 日志从 FlowMonitor 接收时落后，丢失的事件数
+    Missed(u64),
+}
+
+/// 带序号的 Flow 事件
+#[derive(Debug, Clone)]
+pub struct SequencedFlowEvent {
+    /// 全局序号（从 1 开始）
+    pub seq: u64,
+    /// 原始事件
+    pub event: JournalEvent,
+    /// 事件发生时的 Flow 快照（仅开始、完成、失败事件携带，用于服务端过滤）
+    pub flow: Option<Arc<LLMFlow>>,
+}
+
+struct JournalInner {
+    next_seq: u64,
+    buffer: VecDeque<Arc<SequencedFlowEvent>>,
+}
+
+/// Flow 事件日志
+///
+/// 为事件分配单调递增的序号并保留最近 `capacity` 条，供订阅方续订和落后补发。
+pub struct FlowEventJournal {
+    /// 日志标识，每次创建日志（服务端启动）时生成
+    epoch: String,
+    inner: Mutex<JournalInner>,
+    capacity: usize,
+    sender: broadcast::Sender<Arc<SequencedFlowEvent>>,
+}
+
+impl FlowEventJournal {
+    /// 创建事件日志
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        let (sender, _) = broadcast::channel(capacity);
+        Self {
+            epoch: uuid::Uuid::new_v4().simple().to_string(),
+            inner: Mutex::new(JournalInner {
+                next_seq: 1,
+                buffer: VecDeque::with_capacity(capacity),
+            }),
+            capacity,
+            sender,
+        }
+    }
+
+    /// 日志标识
+    pub fn epoch(&self) -> &str {
+        &self.epoch
+    }
+
+    /// 追加事件，返回分配的序号
+    pub fn publish(&self, event: FlowEvent, flow: Option<Arc<LLMFlow>>) -> u64 {
+        self.push(JournalEvent::Flow(event), flow)
+    }
+
+    /// 记录接收落后丢失的事件数，订阅方收到后需要重新同步
+    pub fn publish_missed(&self, count: u64) -> u64 {
+        self.push(JournalEvent::Missed(count), None)
+    }
+
+    fn push(&self, event: JournalEvent, flow: Option<Arc<LLMFlow>>) -> u64 {
+        let mut inner = self.inner.lock().unwrap();
+        let seq = inner.next_seq;
+        inner.next_seq += 1;
+
+        let entry = Arc::new(SequencedFlowEvent { seq, event, flow });
+        if inner.buffer.len() >= self.capacity {
+            inner.buffer.pop_front();
+        }
+        inner.buffer.push_back(entry.clone());
+
+        // 在持有锁时发送，保证广播顺序与序号一致
+        let _ = self.sender.send(entry);
+        seq
+    }
+
+    /// 订阅新事件
+    pub fn subscribe(&self) -> broadcast::Receiver<Arc<SequencedFlowEvent>> {
+        self.sender.subscribe()
+    }
+
+    /// 最新已分配的序号（尚无事件时为 0）
+    pub fn latest_seq(&self) -> u64 {
+        self.inner.lock().unwrap().next_seq - 1
+    }
+
+    /// 获取序号大于 `after` 的缓冲事件
+    ///
+    /// 返回 `(丢失的序号区间, 可回放的事件)`；当 `after` 之后的部分事件已被淘汰时，
+    /// 丢失区间为 `Some((first, last))`。
+    pub fn replay_after(&self, after: u64) -> (Option<(u64, u64)>, Vec<Arc<SequencedFlowEvent>>) {
+        let inner = self.inner.lock().unwrap();
+        let oldest = inner
+            .buffer
+            .front()
+            .map(|e| e.seq)
+            .unwrap_or(inner.next_seq);
+
+        let lost = (after + 1 < oldest).then(|| (after + 1, oldest - 1));
+        let events = inner
+            .buffer
+            .iter()
+            .filter(|e| e.seq > after)
+            .cloned()
+            .collect();
+        (lost, events)
+    }
+
+    /// 启动后台任务，将 FlowMonitor 事件写入日志
+    ///
+    /// 开始、完成和失败事件会附带 Flow 快照，以便订阅方按完整 Flow 过滤。
+    pub fn spawn(self: Arc<Self>, monitor: Arc<FlowMonitor>) -> JoinHandle<()> {
+        let mut events = monitor.subscribe();
+        let mut chunks = monitor.subscribe_chunks();
+        tokio::spawn(async move {
+            loop {
+                // chunk 事件总是先于同一 Flow 的结束事件发送，优先处理以保持顺序
+                let (received, is_chunk) = tokio::select! {
+                    biased;
+                    received = chunks.recv() => (received, true),
+                    received = events.recv() => (received, false),
+                };
+                match received {
+                    Ok(event) => {
+                        let flow = match &event {
+                            FlowEvent::FlowStarted { flow } => {
+                                monitor.get_active_flow(&flow.id).await
+                            }
+                            FlowEvent::FlowCompleted { id, .. }
+                            | FlowEvent::FlowFailed { id, .. } => {
+                                let store = monitor.memory_store();
+                                let flow = store.read().await.get(id);
+                                flow.and_then(|f| f.read().ok().map(|f| f.clone()))
+                            }
+                            _ => None,
+                        };
+                        self.publish(event, flow.map(Arc::new));
+                    }
+                    Err(broadcast::error::RecvError::Lagged(n)) if is_chunk => {
+                        tracing::debug!("[WS] Flow 事件日志丢弃 {} 条 chunk 事件", n);
+                    }
+                    Err(broadcast::error::RecvError::Lagged(n)) => {
+                        tracing::warn!("[WS] Flow 事件日志落后 {} 条消息", n);
+                        self.publish_missed(n);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        })
+    }
+}
+
+// ============================================================================
+// 订阅过滤
+// ============================================================================
+
+/// 订阅方的事件过滤器
+///
+/// 过滤表达式在 Flow 开始时按请求快照求值，完成或失败时按最终 Flow 再次求值；
+/// 开始时已匹配的 Flow 会持续收到后续事件直到结束。依赖响应的条件（如 `~status`）
+/// 在开始时通常不匹配，这类 Flow 只会在完成时推送。
+pub struct FlowTailFilter {
+    filter: Option<FilterExpr>,
+    include_chunks: bool,
+    matched: HashMap<String, bool>,
+}
+
+impl FlowTailFilter {
+    /// 根据订阅参数创建过滤器
+    ///
+    /// 实时订阅无法访问已保存的过滤器，`~view` 引用会按视图不存在处理。
+    pub fn new(subscription: &WsFlowSubscription) -> Result<Self, FilterParseError> {
+        let filter = match subscription.filter.as_deref().map(str::trim) {
+            Some(expr) if !expr.is_empty() => {
+                let parsed = FilterParser::parse(expr)?;
+                Some(FilterParser::expand_views(&parsed, &|_| None)?)
+            }
+            _ => None,
+        };
+
+        Ok(Self {
+            filter,
+            include_chunks: subscription.include_chunks,
+            matched: HashMap::new(),
+        })
+    }
+
+    fn matches(&self, flow: Option<&Arc<LLMFlow>>) -> Option<bool> {
+        let filter = self.filter.as_ref()?;
+        flow.map(|f| FilterParser::evaluate(filter, f))
+    }
+
+    /// 判断事件是否推送给订阅方
+    pub fn accept(&mut self, event: &SequencedFlowEvent) -> Option<WsFlowEvent> {
+        let flow_event = match &event.event {
+            JournalEvent::Flow(flow_event) => flow_event,
+            // 丢失的事件可能属于任意 Flow，总是推送
+            JournalEvent::Missed(_) => {
+                return Some(WsFlowEvent::Resync {
+                    reason: WsResyncReason::JournalLagged,
+                })
+            }
+        };
+        if let FlowEvent::FlowStreamChunk { .. } = flow_event {
+            if !self.include_chunks {
+                return None;
+            }
+        }
+
+        if self.filter.is_none() {
+            return Some(flow_event.clone().into());
+        }
+
+        let forward = match flow_event {
+            FlowEvent::FlowStarted { flow } => {
+                let matched = self.matches(event.flow.as_ref()).unwrap_or(false);
+                if self.matched.len() >= MAX_TRACKED_FLOWS {
+                    self.matched.clear();
+                }
+                self.matched.insert(flow.id.clone(), matched);
+                matched
+            }
+            FlowEvent::FlowUpdated { id, update } => {
+                if matches!(update.state, Some(FlowState::Cancelled)) {
+                    self.matched.remove(id).unwrap_or(false)
+                } else {
+                    self.matched.get(id).copied().unwrap_or(false)
+                }
+            }
+            FlowEvent::FlowStreamChunk { id, .. } => self.matched.get(id).copied().unwrap_or(false),
+            FlowEvent::FlowCompleted { id, .. } | FlowEvent::FlowFailed { id, .. } => {
+                let was_matched = self.matched.remove(id).unwrap_or(false);
+                was_matched || self.matches(event.flow.as_ref()).unwrap_or(false)
+            }
+            // 阈值、通知和速率事件不属于单个 Flow 的生命周期，设置过滤时不推送
+            FlowEvent::ThresholdWarning { .. }
+            | FlowEvent::Notification { .. }
+            | FlowEvent::RequestRateUpdate { .. } => false,
+        };
+
+        forward.then(|| flow_event.clone().into())
+    }
+}
+
+// ============================================================================
+// 订阅事件泵
+// ============================================================================
+
+/// 运行单个订阅：回放、过滤并将事件写入有界队列
+///
+/// 返回时表示发送队列已关闭（连接断开或取消订阅）。
+pub async fn run_flow_tail(
+    journal: Arc<FlowEventJournal>,
+    subscription: WsFlowSubscription,
+    mut filter: FlowTailFilter,
+    queue: mpsc::Sender<WsFlowEventMessage>,
+) {
+    // 先订阅再回放，避免两者之间的事件丢失；重复事件按序号跳过
+    let mut receiver = journal.subscribe();
+    let latest_seq = journal.latest_seq();
+    // 续订的序号属于之前的日志（服务端已重启）时，从当前日志开头回放
+    let epoch_changed = subscription.resume_from.is_some_and(|seq| {
+        seq > latest_seq
+            || subscription
+                .resume_epoch
+                .as_deref()
+                .is_some_and(|epoch| epoch != journal.epoch())
+    });
+    let mut pump = TailPump {
+        epoch: journal.epoch().to_string(),
+        last_seq: match subscription.resume_from {
+            _ if epoch_changed => 0,
+            Some(seq) => seq,
+            None => latest_seq,
+        },
+        dropped_chunks: 0,
+        queue,
+    };
+
+    if epoch_changed && !pump.resync(WsResyncReason::EpochChanged).await {
+        return;
+    }
+    if subscription.resume_from.is_some() && !pump.catch_up(&journal, &mut filter).await {
+        return;
+    }
+
+    loop {
+        match receiver.recv().await {
+            Ok(event) => {
+                if !pump.deliver(&event, &mut filter).await {
+                    return;
+                }
+            }
+            Err(broadcast::error::RecvError::Lagged(_)) => {
+                if !pump.catch_up(&journal, &mut filter).await {
+                    return;
+                }
+            }
+            Err(broadcast::error::RecvError::Closed) => return,
+        }
+    }
+}
+
+struct TailPump {
+    epoch: String,
+    last_seq: u64,
+    dropped_chunks: u64,
+    queue: mpsc::Sender<WsFlowEventMessage>,
+}
+
+impl TailPump {
+    /// 推送重新同步通知，返回 false 表示队列已关闭
+    async fn resync(&mut self, reason: WsResyncReason) -> bool {
+        let message = WsFlowEventMessage {
+            epoch: self.epoch.clone(),
+            seq: self.last_seq,
+            dropped_chunks: std::mem::take(&mut self.dropped_chunks),
+            event: WsFlowEvent::Resync { reason },
+        };
+        self.queue.send(message).await.is_ok()
+    }
+
+    /// 从日志补发 `last_seq` 之后的事件，返回 false 表示队列已关闭
+    async fn catch_up(&mut self, journal: &FlowEventJournal, filter: &mut FlowTailFilter) -> bool {
+        let (lost, events) = journal.replay_after(self.last_seq);
+        if let Some((first_seq, last_seq)) = lost {
+            let message = WsFlowEventMessage {
+                epoch: self.epoch.clone(),
+                seq: last_seq,
+                dropped_chunks: std::mem::take(&mut self.dropped_chunks),
+                event: WsFlowEvent::EventsLost {
+                    first_seq,
+                    last_seq,
+                },
+            };
+            if self.queue.send(message).await.is_err() {
+                return false;
+            }
+            self.last_seq = last_seq;
+        }
+
+        for event in events {
+            if !self.deliver(&event, filter).await {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// 推送单个事件，返回 false 表示队列已关闭
+    async fn deliver(&mut self, event: &SequencedFlowEvent, filter: &mut FlowTailFilter) -> bool {
+        if event.seq <= self.last_seq {
+            return true;
+        }
+        self.last_seq = event.seq;
+
+        let Some(ws_event) = filter.accept(event) else {
+            return true;
+        };
+        let is_chunk = matches!(ws_event, WsFlowEvent::FlowStreamChunk { .. });
+        let message = WsFlowEventMessage {
+            epoch: self.epoch.clone(),
+            seq: event.seq,
+            dropped_chunks: self.dropped_chunks,
+            event: ws_event,
+        };
+
+        if is_chunk {
+            match self.queue.try_send(message) {
+                Ok(()) => self.dropped_chunks = 0,
+                Err(mpsc::error::TrySendError::Full(_)) => self.dropped_chunks += 1,
+                Err(mpsc::error::TrySendError::Closed(_)) => return false,
+            }
+            true
+        } else {
+            let sent = self.queue.send(message).await.is_ok();
+            self.dropped_chunks = 0;
+            sent
+        }
+    }
+}
+
+// ============================================================================
+// 测试模块
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flow_monitor::models::{FlowMetadata, FlowType, LLMRequest};
+    use crate::flow_monitor::monitor::{FlowSummary, FlowUpdate};
+
+    fn create_flow(id: &str, model: &str) -> Arc<LLMFlow> {
+        let request = LLMRequest {
+            model: model.to_string(),
+            ..Default::default()
+        };
+        Arc::new(LLMFlow::new(
+            id.to_string(),
+            FlowType::ChatCompletions,
+            request,
+            FlowMetadata::default(),
+        ))
+    }
+
+    fn started(journal: &FlowEventJournal, flow: &Arc<LLMFlow>) -> u64 {
+        journal.publish(
+            FlowEvent::FlowStarted {
+                flow: FlowSummary::from(flow.as_ref()),
+            },
+            Some(flow.clone()),
+        )
+    }
+
+    fn chunk(journal: &FlowEventJournal, id: &str, index: u32) -> u64 {
+        journal.publish(
+            FlowEvent::FlowStreamChunk {
+                id: id.to_string(),
+                index,
+                content_delta: Some("hi".to_string()),
+                content_length: 2 * (index as usize + 1),
+            },
+            None,
+        )
+    }
+
+    fn completed(journal: &FlowEventJournal, flow: &Arc<LLMFlow>) -> u64 {
+        journal.publish(
+            FlowEvent::FlowCompleted {
+                id: flow.id.clone(),
+                summary: FlowSummary::from(flow.as_ref()),
+            },
+            Some(flow.clone()),
+        )
+    }
+
+    fn subscription(filter: Option<&str>) -> WsFlowSubscription {
+        WsFlowSubscription {
+            filter: filter.map(String::from),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_journal_assigns_sequence_and_evicts() {
+        let journal = FlowEventJournal::new(3);
+        let flow = create_flow("f1", "gpt-4");
+        assert_eq!(journal.latest_seq(), 0);
+
+        started(&journal, &flow);
+        for i in 0..3 {
+            chunk(&journal, "f1", i);
+        }
+        assert_eq!(journal.latest_seq(), 4);
+
+        let (lost, events) = journal.replay_after(0);
+        assert_eq!(lost, Some((1, 1)));
+        assert_eq!(
+            events.iter().map(|e| e.seq).collect::<Vec<_>>(),
+            vec![2, 3, 4]
+        );
+
+        let (lost, events) = journal.replay_after(3);
+        assert_eq!(lost, None);
+        assert_eq!(events.len(), 1);
+    }
+
+    #[test]
+    fn test_filter_follows_flow_lifecycle() {
+        let journal = FlowEventJournal::new(16);
+        let mut filter = FlowTailFilter::new(&subscription(Some("~m claude"))).unwrap();
+        let claude = create_flow("c", "claude-sonnet");
+        let gpt = create_flow("g", "gpt-4");
+
+        started(&journal, &claude);
+        started(&journal, &gpt);
+        chunk(&journal, "c", 0);
+        chunk(&journal, "g", 0);
+        completed(&journal, &gpt);
+        completed(&journal, &claude);
+
+        let (_, events) = journal.replay_after(0);
+        let accepted: Vec<u64> = events
+            .iter()
+            .filter(|e| filter.accept(e).is_some())
+            .map(|e| e.seq)
+            .collect();
+        assert_eq!(accepted, vec![1, 3, 6]);
+        assert!(filter.matched.is_empty());
+    }
+
+    #[test]
+    fn test_filter_rejects_views_and_can_skip_chunks() {
+        assert!(FlowTailFilter::new(&subscription(Some("~view errors"))).is_err());
+        assert!(FlowTailFilter::new(&subscription(Some("~bogus"))).is_err());
+
+        let journal = FlowEventJournal::new(16);
+        let mut filter = FlowTailFilter::new(&WsFlowSubscription {
+            include_chunks: false,
+            ..Default::default()
+        })
+        .unwrap();
+        let flow = create_flow("f", "gpt-4");
+        started(&journal, &flow);
+        chunk(&journal, "f", 0);
+        journal.publish(
+            FlowEvent::FlowUpdated {
+                id: "f".to_string(),
+                update: FlowUpdate {
+                    state: Some(FlowState::Cancelled),
+                    content_delta: None,
+                    content_length: None,
+                    chunk_count: None,
+                },
+            },
+            None,
+        );
+
+        let (_, events) = journal.replay_after(0);
+        let accepted = events.iter().filter(|e| filter.accept(e).is_some()).count();
+        assert_eq!(accepted, 2);
+    }
+
+    #[tokio::test]
+    async fn test_tail_resumes_and_reports_lost_events() {
+        let journal = Arc::new(FlowEventJournal::new(2));
+        let flow = create_flow("f", "gpt-4");
+        started(&journal, &flow);
+        chunk(&journal, "f", 0);
+        chunk(&journal, "f", 1);
+
+        let sub = WsFlowSubscription {
+            resume_from: Some(0),
+            ..Default::default()
+        };
+        let filter = FlowTailFilter::new(&sub).unwrap();
+        let (tx, mut rx) = mpsc::channel(16);
+        let handle = tokio::spawn(run_flow_tail(journal.clone(), sub, filter, tx));
+
+        let lost = rx.recv().await.unwrap();
+        assert!(matches!(
+            lost.event,
+            WsFlowEvent::EventsLost {
+                first_seq: 1,
+                last_seq: 1
+            }
+        ));
+        assert_eq!(rx.recv().await.unwrap().seq, 2);
+        assert_eq!(rx.recv().await.unwrap().seq, 3);
+
+        completed(&journal, &flow);
+        let done = rx.recv().await.unwrap();
+        assert_eq!(done.seq, 4);
+        assert!(matches!(done.event, WsFlowEvent::FlowCompleted { .. }));
+
+        drop(rx);
+        completed(&journal, &flow);
+        handle.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_tail_resyncs_on_epoch_change_and_lag() {
+        let journal = Arc::new(FlowEventJournal::new(16));
+        let flow = create_flow("f", "gpt-4");
+        started(&journal, &flow);
+        chunk(&journal, "f", 0);
+
+        // 续订之前日志的序号：先推送 Resync，再从当前日志开头回放
+        let sub = WsFlowSubscription {
+            resume_from: Some(1),
+            resume_epoch: Some("previous".to_string()),
+            ..Default::default()
+        };
+        let filter = FlowTailFilter::new(&sub).unwrap();
+        let (tx, mut rx) = mpsc::channel(16);
+        let handle = tokio::spawn(run_flow_tail(journal.clone(), sub, filter, tx));
+
+        let resync = rx.recv().await.unwrap();
+        assert_eq!(resync.epoch, journal.epoch());
+        assert!(matches!(
+            resync.event,
+            WsFlowEvent::Resync {
+                reason: WsResyncReason::EpochChanged
+            }
+        ));
+        assert_eq!(rx.recv().await.unwrap().seq, 1);
+        assert_eq!(rx.recv().await.unwrap().seq, 2);
+
+        // 日志接收落后时，过滤条件之外的订阅方也会收到 Resync
+        journal.publish_missed(3);
+        let lagged = rx.recv().await.unwrap();
+        assert_eq!(lagged.seq, 3);
+        assert!(matches!(
+            lagged.event,
+            WsFlowEvent::Resync {
+                reason: WsResyncReason::JournalLagged
+            }
+        ));
+
+        drop(rx);
+        completed(&journal, &flow);
+        handle.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_tail_resyncs_when_resume_seq_is_ahead() {
+        let journal = Arc::new(FlowEventJournal::new(16));
+        let flow = create_flow("f", "gpt-4");
+        started(&journal, &flow);
+
+        // 未携带 epoch 的旧客户端：序号超过当前日志时同样视为重启
+        let sub = WsFlowSubscription {
+            resume_from: Some(100),
+            ..Default::default()
+        };
+        let filter = FlowTailFilter::new(&sub).unwrap();
+        let (tx, mut rx) = mpsc::channel(16);
+        let handle = tokio::spawn(run_flow_tail(journal.clone(), sub, filter, tx));
+
+        assert!(matches!(
+            rx.recv().await.unwrap().event,
+            WsFlowEvent::Resync {
+                reason: WsResyncReason::EpochChanged
+            }
+        ));
+        assert_eq!(rx.recv().await.unwrap().seq, 1);
+
+        drop(rx);
+        completed(&journal, &flow);
+        handle.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_tail_drops_chunks_when_queue_full() {
+        let journal = Arc::new(FlowEventJournal::new(64));
+        let flow = create_flow("f", "gpt-4");
+        started(&journal, &flow);
+        for i in 0..5 {
+            chunk(&journal, "f", i);
+        }
+        completed(&journal, &flow);
+
+        let sub = WsFlowSubscription {
+            resume_from: Some(0),
+            ..Default::default()
+        };
+        let filter = FlowTailFilter::new(&sub).unwrap();
+        let (tx, mut rx) = mpsc::channel(2);
+        let handle = tokio::spawn(run_flow_tail(journal.clone(), sub, filter, tx));
+
+        // 等待事件泵填满队列后再消费
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        let mut received = Vec::new();
+        while received.last().map(|m: &WsFlowEventMessage| m.seq) != Some(7) {
+            received.push(rx.recv().await.unwrap());
+        }
+
+        let completed = received.last().unwrap();
+        assert!(matches!(completed.event, WsFlowEvent::FlowCompleted { .. }));
+        let delivered_chunks = received
+            .iter()
+            .filter(|m| matches!(m.event, WsFlowEvent::FlowStreamChunk { .. }))
+            .count() as u64;
+        let dropped: u64 = received.iter().map(|m| m.dropped_chunks).sum();
+        assert_eq!(delivered_chunks + dropped, 5);
+        assert!(dropped > 0);
+
+        drop(rx);
+        handle.abort();
+    }
+}
@@ -246,7 +246,7 @@ async fn handle_message(
             // 忽略客户端发送的错误消息
             None
         }
-        WsMessage::SubscribeFlowEvents | WsMessage::UnsubscribeFlowEvents => {
+        WsMessage::SubscribeFlowEvents(_) | WsMessage::UnsubscribeFlowEvents => {
             // Flow 事件订阅在 server/handlers/websocket.rs 中处理
             // 这里的 handler 是旧的实现，暂时返回不支持的错误
             Some(WsMessage::Error(WsError::invalid_request(
@@ -5,13 +5,18 @@
 //! - 消息解析和处理
 //! - 流式响应转发
 //! - 心跳检测和连接生命周期管理
+//! - Flow 事件实时订阅（带序号续订和背压）
 
+mod flow_tail;
 mod handler;
 mod lifecycle;
 mod processor;
 mod stream;
 mod types;
 
+pub use flow_tail::{
+    run_flow_tail, FlowEventJournal, FlowTailFilter, JournalEvent, SequencedFlowEvent,
+};
 pub use handler::{parse_message, serialize_message, ws_handler, WsHandlerState};
 pub use lifecycle::{
     ConnectionLifecycle, GracefulShutdown, HeartbeatManager, LifecycleState, ResourceCleaner,
@@ -20,8 +25,8 @@ pub use processor::MessageProcessor;
 pub use stream::{BackpressureController, StreamForwarder};
 pub use types::{
     KiroTokenInfo, WsApiRequest, WsApiResponse, WsConfig, WsConnection, WsConnectionStatus,
-    WsEndpoint, WsError, WsErrorCode, WsFlowEvent, WsKiroEvent, WsMessage, WsStats,
-    WsStatsSnapshot, WsStreamChunk, WsStreamEnd,
+    WsEndpoint, WsError, WsErrorCode, WsFlowEvent, WsFlowEventMessage, WsFlowSubscription,
+    WsKiroEvent, WsMessage, WsResyncReason, WsStats, WsStatsSnapshot, WsStreamChunk, WsStreamEnd,
 };
 
 use dashmap::DashMap;
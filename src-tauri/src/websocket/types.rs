@@ -74,12 +74,12 @@ pub enum WsMessage {
     Ping { timestamp: i64 },
     /// 心跳响应
     Pong { timestamp: i64 },
-    /// 订阅 Flow 事件
-    SubscribeFlowEvents,
+    /// 订阅 Flow 事件（需要管理密钥）
+    SubscribeFlowEvents(WsFlowSubscription),
     /// 取消订阅 Flow 事件
     UnsubscribeFlowEvents,
     /// Flow 事件通知
-    FlowEvent(WsFlowEvent),
+    FlowEvent(WsFlowEventMessage),
     /// 订阅 Kiro 凭证状态事件
     SubscribeKiroEvents,
     /// 取消订阅 Kiro 凭证状态事件
@@ -234,6 +234,12 @@ pub struct WsConfig {
     /// 消息大小限制（字节）
     #[serde(default = "default_max_message_size")]
     pub max_message_size: usize,
+    /// Flow 事件回放缓冲区大小（条），用于断线续订
+    #[serde(default = "default_flow_event_history")]
+    pub flow_event_history: usize,
+    /// 每个订阅连接的 Flow 事件发送队列大小（条）
+    #[serde(default = "default_flow_event_queue_size")]
+    pub flow_event_queue_size: usize,
 }
 
 fn default_enabled() -> bool {
@@ -256,6 +262,14 @@ fn default_max_message_size() -> usize {
     16 * 1024 * 1024 // 16MB
 }
 
+fn default_flow_event_history() -> usize {
+    4096
+}
+
+fn default_flow_event_queue_size() -> usize {
+    256
+}
+
 impl Default for WsConfig {
     fn default() -> Self {
         Self {
@@ -264,6 +278,8 @@ impl Default for WsConfig {
             heartbeat_timeout_secs: default_heartbeat_timeout(),
             max_connections: default_max_connections(),
             max_message_size: default_max_message_size(),
+            flow_event_history: default_flow_event_history(),
+            flow_event_queue_size: default_flow_event_queue_size(),
         }
     }
 }
@@ -356,6 +372,27 @@ pub enum WsFlowEvent {
     Notification { notification: NotificationEvent },
     /// 请求速率更新
     RequestRateUpdate { rate: f64, count: usize },
+    /// 流式响应 chunk
+    FlowStreamChunk {
+        id: String,
+        index: u32,
+        content_delta: Option<String>,
+        content_length: usize,
+    },
+    /// 序号区间内的事件已超出回放缓冲区，无法补发
+    EventsLost { first_seq: u64, last_seq: u64 },
+    /// 事件流不连续，客户端应重新加载 Flow 列表后继续接收
+    Resync { reason: WsResyncReason },
+}
+
+/// 需要重新同步的原因
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum WsResyncReason {
+    /// 服务端已重启，`resume_from` 属于之前的事件日志
+    EpochChanged,
+    /// 服务端事件日志接收落后，部分事件没有记录
+    JournalLagged,
 }
 
 impl From<FlowEvent> for WsFlowEvent {
@@ -372,6 +409,70 @@ impl From<FlowEvent> for WsFlowEvent {
             FlowEvent::RequestRateUpdate { rate, count } => {
                 WsFlowEvent::RequestRateUpdate { rate, count }
             }
+            FlowEvent::FlowStreamChunk {
+                id,
+                index,
+                content_delta,
+                content_length,
+            } => WsFlowEvent::FlowStreamChunk {
+                id,
+                index,
+                content_delta,
+                content_length,
+            },
+        }
+    }
+}
+
+/// 带序号的 Flow 事件消息
+///
+/// `seq` 在同一事件日志（`epoch`）内单调递增，断线后可通过 `resume_epoch` + `resume_from` 续订。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WsFlowEventMessage {
+    /// 事件日志标识，服务端重启后改变
+    pub epoch: String,
+    /// 事件序号
+    pub seq: u64,
+    /// 自上一条消息以来因发送队列已满而丢弃的流式 chunk 数
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub dropped_chunks: u64,
+    /// 事件内容
+    #[serde(flatten)]
+    pub event: WsFlowEvent,
+}
+
+fn is_zero(value: &u64) -> bool {
+    *value == 0
+}
+
+/// Flow 事件订阅参数
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WsFlowSubscription {
+    /// 服务端过滤表达式（与 Flow Monitor 过滤语法相同）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub filter: Option<String>,
+    /// 从该序号之后开始续订（不含），为空时只接收新事件
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resume_from: Option<u64>,
+    /// `resume_from` 所属的事件日志标识，与当前不一致时先推送 `Resync` 再从头回放
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resume_epoch: Option<String>,
+    /// 是否接收流式 chunk 事件
+    #[serde(default = "default_include_chunks")]
+    pub include_chunks: bool,
+}
+
+fn default_include_chunks() -> bool {
+    true
+}
+
+impl Default for WsFlowSubscription {
+    fn default() -> Self {
+        Self {
+            filter: None,
+            resume_from: None,
+            resume_epoch: None,
+            include_chunks: default_include_chunks(),
         }
     }
 }
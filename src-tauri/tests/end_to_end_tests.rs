@@ -147,6 +147,7 @@ impl E2ETestContext {
             },
             state: FlowState::Completed,
             annotations: FlowAnnotations::default(),
+            redactions: Vec::new(),
         }
     }
 
@@ -20,6 +20,7 @@ import {
   Code2,
   Settings,
   Wrench,
+  Bot,
 } from "lucide-react";
 import { cn } from "@/lib/utils";
 import type { ToolCallState } from "@/lib/api/agent";
@@ -85,13 +86,20 @@ const getToolIcon = (toolName: string) => {
   ) {
     return Terminal;
   }
+  if (name === "task") {
+    return Bot;
+  }
   if (name.includes("read")) {
     return Eye;
   }
   if (name.includes("write") || name.includes("create")) {
     return FilePlus;
   }
-  if (name.includes("edit") || name.includes("replace")) {
+  if (
+    name.includes("edit") ||
+    name.includes("replace") ||
+    name.includes("patch")
+  ) {
     return Edit3;
   }
   if (name.includes("list") || name.includes("dir")) {
@@ -100,7 +108,8 @@ const getToolIcon = (toolName: string) => {
   if (
     name.includes("search") ||
     name.includes("find") ||
-    name.includes("grep")
+    name.includes("grep") ||
+    name.includes("glob")
   ) {
     return Search;
   }
@@ -139,6 +148,13 @@ const getToolDescription = (
     return "执行命令";
   }
 
+  if (name === "task") {
+    if (args.description) {
+      return `子任务: ${getStringValue(args.description)}`;
+    }
+    return "子任务";
+  }
+
   if (name.includes("read_file") || name === "read") {
     if (args.path || args.file_path) {
       return `读取 ${getStringValue(args.path || args.file_path)}`;
@@ -174,6 +190,17 @@ const getToolDescription = (
     return "搜索";
   }
 
+  if (name.includes("glob")) {
+    if (args.pattern) {
+      return `查找 ${getStringValue(args.pattern)}`;
+    }
+    return "查找文件";
+  }
+
+  if (name.includes("patch")) {
+    return "应用补丁";
+  }
+
   // 通用回退：工具名 + 参数键
   const entries = Object.entries(args);
   if (entries.length === 0) {
@@ -463,7 +490,18 @@ export const ToolCallDisplay: React.FC<ToolCallDisplayProps> = ({
 
   const hasArguments = Object.keys(parsedArgs).length > 0;
   const hasResult = toolCall.status !== "running" && toolCall.result;
-  const hasLogs = toolCall.logs && toolCall.logs.length > 0;
+  // 执行日志与实时输出合并显示
+  const logLines = useMemo(
+    () => [
+      ...(toolCall.logs || []),
+      ...(toolCall.output
+        ? toolCall.output.replace(/\n$/, "").split("\n")
+        : []),
+    ],
+    [toolCall.logs, toolCall.output],
+  );
+  const hasLogs = logLines.length > 0;
+  const hasChildren = toolCall.children && toolCall.children.length > 0;
   const isRunning = toolCall.status === "running";
 
   // 工具标签
@@ -507,13 +545,25 @@ export const ToolCallDisplay: React.FC<ToolCallDisplayProps> = ({
         {hasLogs && (
           <div className="border-t border-border">
             <ToolLogsView
-              logs={toolCall.logs!}
+              logs={logLines}
               working={isRunning}
               isStartExpanded={isRunning}
             />
           </div>
         )}
 
+        {/* 子代理进度 */}
+        {(hasChildren || toolCall.subagentText) && (
+          <div className="border-t border-border p-2 pl-4 flex flex-col gap-2">
+            {hasChildren && <ToolCallList toolCalls={toolCall.children!} />}
+            {isRunning && toolCall.subagentText && (
+              <div className="text-xs text-muted-foreground whitespace-pre-wrap line-clamp-4">
+                {toolCall.subagentText}
+              </div>
+            )}
+          </div>
+        )}
+
         {/* 执行结果 */}
         {hasResult && (
           <div className="border-t border-border">
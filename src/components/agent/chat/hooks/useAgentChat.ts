@@ -13,6 +13,7 @@ import {
   type AgentProcessStatus,
   type SessionInfo,
   type StreamEvent,
+  type ToolCallState,
 } from "@/lib/api/agent";
 import { Message, MessageImage, ContentPart, PROVIDER_CONFIG } from "../types";
 
@@ -73,6 +74,75 @@ const saveTransient = (key: string, value: unknown) => {
   }
 };
 
+/** 追加工具执行过程中的实时输出 */
+const appendToolOutput = (
+  toolCall: ToolCallState,
+  text: string,
+): ToolCallState => ({
+  ...toolCall,
+  output: (toolCall.output || "") + text,
+});
+
+/** 将子代理事件应用到对应的 task 工具调用上（子代理的工具调用显示为子节点） */
+const applySubAgentEvent = (
+  toolCall: ToolCallState,
+  event: StreamEvent,
+): ToolCallState => {
+  switch (event.type) {
+    case "text_delta":
+      return {
+        ...toolCall,
+        subagentText: (toolCall.subagentText || "") + event.text,
+      };
+    case "tool_start":
+      return {
+        ...toolCall,
+        children: [
+          ...(toolCall.children || []),
+          {
+            id: event.tool_id,
+            name: event.tool_name,
+            arguments: event.arguments,
+            status: "running" as const,
+            startTime: new Date(),
+          },
+        ],
+      };
+    case "tool_output":
+      return {
+        ...toolCall,
+        children: (toolCall.children || []).map((child) =>
+          child.id === event.tool_id
+            ? appendToolOutput(child, event.text)
+            : child,
+        ),
+      };
+    case "tool_end":
+      return {
+        ...toolCall,
+        children: (toolCall.children || []).map((child) =>
+          child.id === event.tool_id
+            ? {
+                ...child,
+                status: event.result.success
+                  ? ("completed" as const)
+                  : ("failed" as const),
+                result: event.result,
+                endTime: new Date(),
+              }
+            : child,
+        ),
+      };
+    case "error":
+      return {
+        ...toolCall,
+        logs: [...(toolCall.logs || []), `错误: ${event.message}`],
+      };
+    default:
+      return toolCall;
+  }
+};
+
 export function useAgentChat() {
   const [processStatus, setProcessStatus] = useState<AgentProcessStatus>({
     running: false,
@@ -404,6 +474,52 @@ export function useAgentChat() {
             break;
           }
 
+          case "subagent_event": {
+            // 子代理进度 - 更新对应 task 工具调用的子节点
+            const update = (tc: ToolCallState) =>
+              tc.id === data.parent_tool_id
+                ? applySubAgentEvent(tc, data.event)
+                : tc;
+            setMessages((prev) =>
+              prev.map((msg) =>
+                msg.id === assistantMsgId
+                  ? {
+                      ...msg,
+                      toolCalls: (msg.toolCalls || []).map(update),
+                      contentParts: (msg.contentParts || []).map((part) =>
+                        part.type === "tool_use"
+                          ? { ...part, toolCall: update(part.toolCall) }
+                          : part,
+                      ),
+                    }
+                  : msg,
+              ),
+            );
+            break;
+          }
+
+          case "tool_output": {
+            // 工具实时输出 - 追加到对应的工具调用
+            const update = (tc: ToolCallState) =>
+              tc.id === data.tool_id ? appendToolOutput(tc, data.text) : tc;
+            setMessages((prev) =>
+              prev.map((msg) =>
+                msg.id === assistantMsgId
+                  ? {
+                      ...msg,
+                      toolCalls: (msg.toolCalls || []).map(update),
+                      contentParts: (msg.contentParts || []).map((part) =>
+                        part.type === "tool_use"
+                          ? { ...part, toolCall: update(part.toolCall) }
+                          : part,
+                      ),
+                    }
+                  : msg,
+              ),
+            );
+            break;
+          }
+
           case "tool_end": {
             // 工具执行完成 - 更新工具调用状态和 contentParts
             console.log(`[Tool End] ${data.tool_id}`);
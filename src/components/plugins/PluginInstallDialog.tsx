@@ -110,6 +110,7 @@ export function PluginInstallDialog({
   const [activeTab, setActiveTab] = useState<"file" | "url">("file");
   const [filePath, setFilePath] = useState("");
   const [url, setUrl] = useState("");
+  const [allowUnsigned, setAllowUnsigned] = useState(false);
   const [installing, setInstalling] = useState(false);
   const [progress, setProgress] = useState<InstallProgress | null>(null);
   const [error, setError] = useState<string | null>(null);
@@ -165,7 +166,7 @@ export function PluginInstallDialog({
     try {
       const installResult = await invoke<InstallResult>(
         "install_plugin_from_url",
-        { url: currentUrl },
+        { url: currentUrl, allowUnsigned },
       );
 
       if (installResult.success && installResult.plugin) {
@@ -179,7 +180,7 @@ export function PluginInstallDialog({
     } finally {
       setInstalling(false);
     }
-  }, [url, onSuccess]);
+  }, [url, allowUnsigned, onSuccess]);
 
   // 自动开始安装（当有 initialUrl 时）
   useEffect(() => {
@@ -213,6 +214,7 @@ export function PluginInstallDialog({
   const resetState = () => {
     setFilePath("");
     setUrl("");
+    setAllowUnsigned(false);
     setInstalling(false);
     setProgress(null);
     setError(null);
@@ -258,7 +260,7 @@ export function PluginInstallDialog({
     try {
       const installResult = await invoke<InstallResult>(
         "install_plugin_from_file",
-        { filePath },
+        { filePath, allowUnsigned },
       );
 
       if (installResult.success && installResult.plugin) {
@@ -414,6 +416,24 @@ export function PluginInstallDialog({
               </TabsContent>
             </Tabs>
 
+            {/* 未签名插件包需要用户明确放行 */}
+            <div className="mt-4 flex items-center gap-2">
+              <input
+                type="checkbox"
+                id="allowUnsigned"
+                checked={allowUnsigned}
+                onChange={(e) => setAllowUnsigned(e.target.checked)}
+                disabled={installing}
+                className="h-4 w-4 rounded border-gray-300"
+              />
+              <label
+                htmlFor="allowUnsigned"
+                className="text-sm text-muted-foreground"
+              >
+                允许安装未签名的插件包
+              </label>
+            </div>
+
             {/* 进度显示 */}
             {installing && progress && (
               <div className="mt-4">{renderProgress()}</div>
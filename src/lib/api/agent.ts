@@ -42,7 +42,12 @@ export interface ToolExecutionResult {
 export type StreamEvent =
   | StreamEventTextDelta
   | StreamEventToolStart
+  | StreamEventToolOutput
   | StreamEventToolEnd
+  | StreamEventApprovalRequest
+  | StreamEventContextCompacted
+  | StreamEventCheckpointCreated
+  | StreamEventSubAgent
   | StreamEventDone
   | StreamEventFinalDone
   | StreamEventError;
@@ -70,6 +75,19 @@ export interface StreamEventToolStart {
   arguments?: string;
 }
 
+/**
+ * 工具输出增量事件（bash 命令执行过程中的 stdout / stderr）
+ */
+export interface StreamEventToolOutput {
+  type: "tool_output";
+  /** 工具调用 ID */
+  tool_id: string;
+  /** 输出流 */
+  stream: "stdout" | "stderr";
+  /** 输出文本 */
+  text: string;
+}
+
 /**
  * 工具调用结束事件
  * Requirements: 9.2 - WHEN a tool completes, THE Frontend SHALL display a collapsible section showing the tool result
@@ -82,6 +100,69 @@ export interface StreamEventToolEnd {
   result: ToolExecutionResult;
 }
 
+/**
+ * 工具调用审批请求事件
+ * 通过 resolveToolApproval 答复，超时未答复视为拒绝
+ */
+export interface StreamEventApprovalRequest {
+  type: "approval_request";
+  /** 审批请求 ID */
+  request_id: string;
+  /** 工具调用 ID */
+  tool_id: string;
+  /** 工具名称 */
+  tool_name: string;
+  /** 工具参数（JSON 字符串） */
+  arguments: string;
+  /** 匹配审批规则的调用对象（命令、路径等） */
+  subject?: string;
+  /** 审批超时时间（秒） */
+  timeout_secs: number;
+}
+
+/**
+ * 上下文压缩事件（较早的对话已被摘要代替）
+ */
+export interface StreamEventContextCompacted {
+  type: "context_compacted";
+  /** 压缩记录 ID */
+  compaction_id: string;
+  /** 本次摘要的消息数 */
+  compacted_messages: number;
+  /** 压缩前的上下文 token 数（估算） */
+  tokens_before: number;
+  /** 压缩后的上下文 token 数（估算） */
+  tokens_after: number;
+}
+
+/**
+ * 工作区检查点事件（工具执行前已快照涉及的文件）
+ */
+export interface StreamEventCheckpointCreated {
+  type: "checkpoint_created";
+  /** 检查点 ID */
+  checkpoint_id: string;
+  /** 创建时会话的消息数 */
+  message_index: number;
+  /** 快照的文件数 */
+  file_count: number;
+  /** 是否有无法追踪的改动 */
+  untracked: boolean;
+}
+
+/**
+ * 子代理事件（task 工具启动的子代理产生的事件）
+ */
+export interface StreamEventSubAgent {
+  type: "subagent_event";
+  /** 启动子代理的工具调用 ID */
+  parent_tool_id: string;
+  /** 子代理会话 ID */
+  session_id: string;
+  /** 子代理原始事件 */
+  event: StreamEvent;
+}
+
 /**
  * 完成事件（单次 API 响应完成，工具循环可能继续）
  * Requirements: 9.5 - THE Frontend SHALL display token usage statistics after each Agent response
@@ -131,6 +212,12 @@ export interface ToolCallState {
   endTime?: Date;
   /** 执行日志（实时更新） */
   logs?: string[];
+  /** 执行过程中的实时输出（bash 命令） */
+  output?: string;
+  /** 子代理的工具调用（task 工具） */
+  children?: ToolCallState[];
+  /** 子代理输出的文本（task 工具） */
+  subagentText?: string;
 }
 
 /**
@@ -157,12 +244,47 @@ export function parseStreamEvent(data: unknown): StreamEvent | null {
         tool_id: (event.tool_id as string) || "",
         arguments: event.arguments as string | undefined,
       };
+    case "tool_output":
+      return {
+        type: "tool_output",
+        tool_id: (event.tool_id as string) || "",
+        stream: event.stream === "stderr" ? "stderr" : "stdout",
+        text: (event.text as string) || "",
+      };
     case "tool_end":
       return {
         type: "tool_end",
         tool_id: (event.tool_id as string) || "",
         result: event.result as ToolExecutionResult,
       };
+    case "approval_request":
+      return {
+        type: "approval_request",
+        request_id: (event.request_id as string) || "",
+        tool_id: (event.tool_id as string) || "",
+        tool_name: (event.tool_name as string) || "",
+        arguments: (event.arguments as string) || "",
+        subject: event.subject as string | undefined,
+        timeout_secs: (event.timeout_secs as number) || 0,
+      };
+    case "context_compacted":
+      return {
+        type: "context_compacted",
+        compaction_id: (event.compaction_id as string) || "",
+        compacted_messages: (event.compacted_messages as number) || 0,
+        tokens_before: (event.tokens_before as number) || 0,
+        tokens_after: (event.tokens_after as number) || 0,
+      };
+    case "subagent_event": {
+      const inner = parseStreamEvent(event.event);
+      if (!inner) return null;
+      return {
+        type: "subagent_event",
+        parent_tool_id: (event.parent_tool_id as string) || "",
+        session_id: (event.session_id as string) || "",
+        event: inner,
+      };
+    }
     case "done":
       return {
         type: "done",
@@ -347,6 +469,210 @@ export async function deleteAgentSession(sessionId: string): Promise<void> {
   });
 }
 
+/**
+ * 工具调用审批答复
+ */
+export type ToolApprovalDecision =
+  | { action: "approve" }
+  | { action: "approve_for_session" }
+  | { action: "deny"; reason?: string }
+  | { action: "edit"; arguments: Record<string, unknown> };
+
+/**
+ * 等待审批的工具调用
+ */
+export interface PendingToolApproval {
+  request_id: string;
+  session_id?: string;
+  tool_id: string;
+  tool_name: string;
+  arguments: string;
+  subject?: string;
+  created_at: string;
+}
+
+/**
+ * 工具调用审批审计记录
+ */
+export interface ToolApprovalAuditEntry {
+  id: number;
+  session_id?: string;
+  tool_call_id: string;
+  tool_name: string;
+  arguments: string;
+  subject?: string;
+  decision: "allowed" | "edited" | "denied";
+  source: "rule" | "default" | "session" | "user" | "timeout" | "unattended";
+  reason?: string;
+  created_at: string;
+}
+
+/**
+ * 答复工具调用审批请求
+ */
+export async function resolveToolApproval(
+  requestId: string,
+  decision: ToolApprovalDecision,
+): Promise<void> {
+  return await invoke("native_agent_resolve_approval", {
+    requestId,
+    decision,
+  });
+}
+
+/**
+ * 获取等待审批的工具调用
+ */
+export async function getPendingToolApprovals(): Promise<
+  PendingToolApproval[]
+> {
+  return await invoke("native_agent_pending_approvals");
+}
+
+/**
+ * 获取工具调用审批审计日志
+ */
+export async function getToolApprovalAudit(
+  sessionId?: string,
+  limit?: number,
+): Promise<ToolApprovalAuditEntry[]> {
+  return await invoke("native_agent_approval_audit", {
+    sessionId,
+    limit,
+  });
+}
+
+/**
+ * 上下文压缩记录
+ */
+export interface ContextCompaction {
+  id: string;
+  /** 本次新摘要的第一条消息序号 */
+  compacted_from: number;
+  /** 被摘要代替的消息终点（不含） */
+  compacted_until: number;
+  summary: string;
+  /** 生成摘要的模型 */
+  model: string;
+  tokens_before: number;
+  tokens_after: number;
+  created_at: string;
+}
+
+/**
+ * 手动压缩会话上下文（保留最近几轮对话和固定消息）
+ */
+export async function compactAgentSession(
+  sessionId: string,
+): Promise<ContextCompaction> {
+  return await invoke("native_agent_compact_session", {
+    sessionId,
+  });
+}
+
+/**
+ * 固定 / 取消固定消息，固定的消息在上下文压缩时保留原文
+ */
+export async function pinAgentMessage(
+  sessionId: string,
+  messageIndex: number,
+  pinned: boolean,
+): Promise<void> {
+  return await invoke("native_agent_pin_message", {
+    sessionId,
+    messageIndex,
+    pinned,
+  });
+}
+
+/**
+ * 工作区检查点
+ */
+export interface AgentCheckpoint {
+  id: string;
+  session_id: string;
+  /** 创建时会话的消息数 */
+  message_index: number;
+  /** 触发检查点的工具调用 */
+  label: string;
+  file_count: number;
+  /** 整体快照的目录（bash 命令所在的 git 项目） */
+  scopes: string[];
+  /** 是否有无法追踪的改动 */
+  untracked: boolean;
+  created_at: string;
+}
+
+/**
+ * 检查点以来的文件变更
+ */
+export interface AgentFileChange {
+  path: string;
+  kind: "added" | "modified" | "deleted";
+  /** 统一 diff（二进制文件为空） */
+  diff: string;
+  binary: boolean;
+}
+
+/**
+ * 回退会话的结果
+ */
+export interface AgentRewindResult {
+  restored_files: string[];
+  removed_messages: number;
+  removed_checkpoints: number;
+}
+
+/**
+ * 列出会话的工作区检查点
+ */
+export async function listAgentCheckpoints(
+  sessionId: string,
+): Promise<AgentCheckpoint[]> {
+  return await invoke("native_agent_list_checkpoints", { sessionId });
+}
+
+/**
+ * 检查点以来 Agent 涉及文件的全部变更
+ */
+export async function getAgentCheckpointDiff(
+  sessionId: string,
+  checkpointId: string,
+): Promise<AgentFileChange[]> {
+  return await invoke("native_agent_checkpoint_diff", {
+    sessionId,
+    checkpointId,
+  });
+}
+
+/**
+ * 将文件恢复到检查点时的状态，不指定 paths 时恢复全部文件
+ */
+export async function restoreAgentCheckpointFiles(
+  sessionId: string,
+  checkpointId: string,
+  paths?: string[],
+): Promise<string[]> {
+  return await invoke("native_agent_restore_checkpoint_files", {
+    sessionId,
+    checkpointId,
+    paths,
+  });
+}
+
+/**
+ * 将会话和文件一起回退到指定用户消息之前
+ */
+export async function rewindAgentSession(
+  sessionId: string,
+  messageIndex: number,
+): Promise<AgentRewindResult> {
+  return await invoke("native_agent_rewind_session", {
+    sessionId,
+    messageIndex,
+  });
+}
+
 // ============================================================
 // Goose Agent API (基于 Goose 框架的完整 Agent 实现)
 // ============================================================
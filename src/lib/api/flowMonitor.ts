@@ -639,7 +639,14 @@ export type FlowEvent =
   | { type: "FlowUpdated"; id: string; update: FlowUpdate }
   | { type: "FlowCompleted"; id: string; summary: FlowSummary }
   | { type: "FlowFailed"; id: string; error: FlowError }
-  | { type: "ThresholdWarning"; id: string; result: ThresholdCheckResult };
+  | { type: "ThresholdWarning"; id: string; result: ThresholdCheckResult }
+  | {
+      type: "FlowStreamChunk";
+      id: string;
+      index: number;
+      content_delta?: string;
+      content_length: number;
+    };
 
 /**
  * 阈值检测结果（用于事件）